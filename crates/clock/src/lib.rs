@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Backward steps of the wall clock smaller than this are treated as jitter
+pub const BACKWARD_JUMP_TOLERANCE: Duration = Duration::from_millis(500);
+
+/// Time source abstraction
+///
+/// Use `now` only for timestamps that are shown to clients (published_at, issued_at, logs).
+/// Use `monotonic` for anything that measures elapsed time (expiry, timeouts, scheduling),
+/// so that NTP steps of the wall clock cannot shorten or lengthen durations.
+pub trait Clock: Send + Sync {
+    /// Current wall-clock time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Monotonic time elapsed since the clock was created
+    fn monotonic(&self) -> Duration;
+
+    /// Report of wall-clock jumps detected so far
+    fn status(&self) -> ClockStatus;
+}
+
+/// Wall-clock jump report exposed via the control API
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockStatus {
+    /// Number of detected backward wall-clock jumps
+    pub backward_jumps: u64,
+    /// Size of the most recent backward jump in milliseconds
+    pub last_backward_jump_millis: i64,
+}
+
+/// Detects backward wall-clock jumps by comparing wall and monotonic progress
+/// between consecutive observations
+#[derive(Default)]
+struct JumpDetector {
+    last: Mutex<Option<(DateTime<Utc>, Duration)>>,
+    backward_jumps: AtomicU64,
+    last_backward_jump_millis: AtomicI64,
+}
+
+impl JumpDetector {
+    fn observe(&self, wall: DateTime<Utc>, monotonic: Duration) {
+        let mut last = self
+            .last
+            .lock()
+            .expect("Failed to acquire lock on jump detector");
+
+        if let Some((last_wall, last_monotonic)) = *last {
+            // Where the wall clock should be if it advanced at the monotonic rate
+            let expected = last_wall
+                + chrono::Duration::from_std(monotonic.saturating_sub(last_monotonic))
+                    .unwrap_or_default();
+            let behind = expected - wall;
+            let tolerance = chrono::Duration::from_std(BACKWARD_JUMP_TOLERANCE).unwrap_or_default();
+            if behind > tolerance {
+                self.backward_jumps.fetch_add(1, Ordering::SeqCst);
+                self.last_backward_jump_millis
+                    .store(behind.num_milliseconds(), Ordering::SeqCst);
+            }
+        }
+
+        *last = Some((wall, monotonic));
+    }
+
+    fn status(&self) -> ClockStatus {
+        ClockStatus {
+            backward_jumps: self.backward_jumps.load(Ordering::SeqCst),
+            last_backward_jump_millis: self.last_backward_jump_millis.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Clock backed by the system wall clock and `std::time::Instant`
+pub struct SystemClock {
+    origin: Instant,
+    detector: JumpDetector,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            detector: JumpDetector::default(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        let wall = Utc::now();
+        self.detector.observe(wall, self.monotonic());
+        wall
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn status(&self) -> ClockStatus {
+        self.detector.status()
+    }
+}
+
+/// Manually driven clock for tests
+/// Wall and monotonic time only move when told to, and can be moved independently
+pub struct MockClock {
+    wall: RwLock<DateTime<Utc>>,
+    monotonic: RwLock<Duration>,
+    detector: JumpDetector,
+}
+
+impl MockClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        let clock = Self {
+            wall: RwLock::new(start),
+            monotonic: RwLock::new(Duration::ZERO),
+            detector: JumpDetector::default(),
+        };
+        clock.detector.observe(start, Duration::ZERO);
+        clock
+    }
+
+    /// Let time pass normally: both wall and monotonic time advance
+    pub fn advance(&self, duration: Duration) {
+        *self
+            .monotonic
+            .write()
+            .expect("Failed to acquire lock on mock clock") += duration;
+        *self
+            .wall
+            .write()
+            .expect("Failed to acquire lock on mock clock") +=
+            chrono::Duration::from_std(duration).expect("Duration should fit");
+    }
+
+    /// Step only the wall clock, as an NTP correction would
+    pub fn step_wall(&self, delta: chrono::Duration) {
+        *self
+            .wall
+            .write()
+            .expect("Failed to acquire lock on mock clock") += delta;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        let wall = *self
+            .wall
+            .read()
+            .expect("Failed to acquire lock on mock clock");
+        self.detector.observe(wall, self.monotonic());
+        wall
+    }
+
+    fn monotonic(&self) -> Duration {
+        *self
+            .monotonic
+            .read()
+            .expect("Failed to acquire lock on mock clock")
+    }
+
+    fn status(&self) -> ClockStatus {
+        self.detector.status()
+    }
+}
+
+static SYSTEM_CLOCK: LazyLock<Arc<SystemClock>> = LazyLock::new(|| Arc::new(SystemClock::new()));
+
+/// The process-wide system clock shared by all services
+pub fn system_clock() -> Arc<dyn Clock> {
+    SYSTEM_CLOCK.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+            .single()
+            .expect("Valid datetime")
+    }
+
+    #[test]
+    fn test_mock_clock_advance_moves_both_clocks() {
+        let clock = MockClock::new(start());
+        clock.advance(Duration::from_secs(10));
+
+        assert_eq!(clock.now(), start() + chrono::Duration::seconds(10));
+        assert_eq!(clock.monotonic(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_backward_wall_step_is_detected() {
+        let clock = MockClock::new(start());
+        clock.advance(Duration::from_secs(1));
+        clock.now();
+        assert_eq!(clock.status().backward_jumps, 0);
+
+        clock.step_wall(chrono::Duration::seconds(-5));
+        clock.now();
+
+        let status = clock.status();
+        assert_eq!(status.backward_jumps, 1);
+        assert_eq!(status.last_backward_jump_millis, 5000);
+        assert_eq!(clock.monotonic(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_backward_wall_step_does_not_delay_scheduled_messages() {
+        let clock = MockClock::new(start());
+        // A message scheduled 10s out, the way scenarios schedule them
+        let due = clock.monotonic() + Duration::from_secs(10);
+        let wall_due = clock.now() + chrono::Duration::seconds(10);
+
+        clock.advance(Duration::from_secs(5));
+        clock.step_wall(chrono::Duration::seconds(-5));
+        clock.advance(Duration::from_secs(5));
+
+        // Due on time by the monotonic clock, while a wall-clock deadline would still be 5s out
+        assert!(clock.monotonic() >= due);
+        assert_eq!(wall_due - clock.now(), chrono::Duration::seconds(5));
+        // Its published_at comes from the stepped wall clock, and the step is reported
+        assert_eq!(clock.now(), start() + chrono::Duration::seconds(5));
+        assert_eq!(clock.status().backward_jumps, 1);
+    }
+
+    #[test]
+    fn test_forward_wall_step_is_not_a_backward_jump() {
+        let clock = MockClock::new(start());
+        clock.step_wall(chrono::Duration::seconds(30));
+        clock.now();
+
+        assert_eq!(clock.status(), ClockStatus::default());
+    }
+
+    #[test]
+    fn test_small_backward_step_within_tolerance_is_ignored() {
+        let clock = MockClock::new(start());
+        clock.step_wall(chrono::Duration::milliseconds(-100));
+        clock.now();
+
+        assert_eq!(clock.status().backward_jumps, 0);
+    }
+}
@@ -0,0 +1,263 @@
+//! Field name casing of control API responses
+//!
+//! Control responses use camelCase field names, like the YouTube-shaped API. Videos used
+//! to be returned with the snake_case names of the datastore model; setting
+//! `CONTROL_LEGACY_FIELD_NAMES=true` keeps those names for one more release.
+
+use serde::{Serialize, Serializer};
+
+/// Whether videos keep their old snake_case field names
+fn legacy_field_names() -> bool {
+    std::env::var("CONTROL_LEGACY_FIELD_NAMES")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
+/// `camelCase` → `camel_case`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn legacy_video(video: &domain::Video) -> serde_json::Value {
+    match serde_json::to_value(video) {
+        Ok(serde_json::Value::Object(fields)) => fields
+            .into_iter()
+            .map(|(name, value)| (snake_case(&name), value))
+            .collect(),
+        other => other.unwrap_or_default(),
+    }
+}
+
+/// Serialize a video of a control response, honoring `CONTROL_LEGACY_FIELD_NAMES`
+pub(crate) fn video<S: Serializer>(
+    video: &domain::Video,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if legacy_field_names() {
+        legacy_video(video).serialize(serializer)
+    } else {
+        video.serialize(serializer)
+    }
+}
+
+/// Serialize the videos of a control response, honoring `CONTROL_LEGACY_FIELD_NAMES`
+pub(crate) fn videos<S: Serializer>(
+    videos: &[domain::Video],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if legacy_field_names() {
+        serializer.collect_seq(videos.iter().map(legacy_video))
+    } else {
+        videos.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::FaultsResponse;
+    use crate::live_chats::{CloseStreamsResponse, LiveChatResponse};
+    use crate::quota::QuotaResponse;
+    use crate::snapshot::{ChatSnapshot, StateResponse};
+    use crate::videos::VideoResponse;
+    use crate::warmup::{ChatStats, StatsResponse, WarmupResponse, WarmupStep};
+    use crate::{
+        CreateResponse, ErrorResponse, ReplayResponse, StatusResponse, UnknownEndpointResponse,
+    };
+    use chrono::Utc;
+
+    /// Paths of object keys that are not camelCase
+    /// Keys below `data_keyed` paths are data (chat IDs, close reasons) rather than field names
+    fn non_camel_case_keys(
+        value: &serde_json::Value,
+        path: &str,
+        data_keyed: &[&str],
+        found: &mut Vec<String>,
+    ) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (name, value) in fields {
+                    let child = format!("{path}.{name}");
+                    if data_keyed.contains(&path) {
+                        non_camel_case_keys(value, path, data_keyed, found);
+                        continue;
+                    }
+                    if name.contains('_') || name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        found.push(child.clone());
+                    }
+                    non_camel_case_keys(value, &child, data_keyed, found);
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    non_camel_case_keys(item, &format!("{path}[]"), data_keyed, found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn sample_video() -> domain::Video {
+        domain::Video {
+            id: "video".to_string(),
+            channel_id: "channel".to_string(),
+            title: "Title".to_string(),
+            description: String::new(),
+            channel_title: "Channel".to_string(),
+            published_at: Utc::now(),
+            live_chat_id: Some("chat".to_string()),
+            actual_start_time: Some(Utc::now()),
+            actual_end_time: Some(Utc::now()),
+            scheduled_start_time: Some(Utc::now()),
+            scheduled_end_time: Some(Utc::now()),
+            concurrent_viewers: Some(1),
+        }
+    }
+
+    #[test]
+    fn test_control_responses_are_camel_case() {
+        let registry = std::sync::Arc::new(domain::StreamRegistry::default());
+        registry.open("chat").close(domain::CloseReason::Error {
+            status: "internal".to_string(),
+        });
+        registry
+            .open("chat")
+            .close(domain::CloseReason::ClientDisconnect);
+        let quota = domain::QuotaLedger::default();
+        quota
+            .charge(domain::QuotaEndpoint::VideosList, "key")
+            .unwrap();
+
+        let samples = [
+            serde_json::to_value(CreateResponse {
+                success: true,
+                message: String::new(),
+            }),
+            serde_json::to_value(ErrorResponse {
+                success: false,
+                error: String::new(),
+            }),
+            serde_json::to_value(UnknownEndpointResponse {
+                success: false,
+                error: String::new(),
+                routes: vec![String::new()],
+            }),
+            serde_json::to_value(ReplayResponse {
+                success: true,
+                message: String::new(),
+                replayed: 1,
+                skipped: 1,
+                failed: 0,
+                redacted: 0,
+            }),
+            serde_json::to_value(StatusResponse {
+                now: Utc::now(),
+                clock: clock::ClockStatus::default(),
+            }),
+            serde_json::to_value(VideoResponse {
+                success: true,
+                video: sample_video(),
+                version: 1,
+            }),
+            serde_json::to_value(LiveChatResponse {
+                success: true,
+                live_chat: domain::LiveChat::scheduled("chat", Some(Utc::now())),
+                version: Some(1),
+            }),
+            serde_json::to_value(CloseStreamsResponse {
+                success: true,
+                closed: 1,
+            }),
+            serde_json::to_value(StateResponse {
+                captured_at: Utc::now(),
+                videos: vec![sample_video()],
+                chats: vec![ChatSnapshot {
+                    live_chat_id: "chat".to_string(),
+                    state: domain::LiveChatState::Active,
+                    message_count: 1,
+                    muted_message_count: 0,
+                    active_streams: 1,
+                    warm: true,
+                }],
+                active_streams: 1,
+                closed_streams: registry.closed_counts(),
+                stream_timeline: registry.timeline(),
+                tokens: oauth_service::token_store_summary(),
+            }),
+            serde_json::to_value(WarmupResponse {
+                success: true,
+                steps: vec![WarmupStep {
+                    step: "preload".to_string(),
+                    live_chat_id: "chat".to_string(),
+                    duration_micros: 1,
+                }],
+                total_duration_micros: 1,
+            }),
+            serde_json::to_value(StatsResponse {
+                chats: vec![ChatStats {
+                    live_chat_id: "chat".to_string(),
+                    state: "warm".to_string(),
+                    message_count: 1,
+                    warmed_at: Some(Utc::now()),
+                    active_warmup_streams: 1,
+                    preloaded_messages: Some(1),
+                }],
+                closed_streams: registry.closed_counts(),
+                slow_starts: 1,
+            }),
+            serde_json::to_value(quota.report(None)),
+            serde_json::to_value(QuotaResponse::of(&quota)),
+            serde_json::to_value(FaultsResponse {
+                success: true,
+                faults: vec![domain::Fault {
+                    target: domain::FaultTarget::VideosList,
+                    http_status: 403,
+                    reason: "quotaExceeded".to_string(),
+                    message: String::new(),
+                    grpc_code: "UNAVAILABLE".to_string(),
+                    probability: Some(0.5),
+                    remaining: Some(1),
+                    after_messages: Some(1),
+                }],
+            }),
+        ];
+
+        for sample in samples {
+            let sample = sample.expect("Serializable response");
+            let mut found = Vec::new();
+            non_camel_case_keys(
+                &sample,
+                "",
+                &[
+                    ".closedStreams",
+                    ".byEndpoint",
+                    ".byKey",
+                    ".costs",
+                    ".usage",
+                ],
+                &mut found,
+            );
+            assert!(found.is_empty(), "{found:?} in {sample}");
+        }
+    }
+
+    #[test]
+    fn test_legacy_video_field_names() {
+        let legacy = legacy_video(&sample_video());
+        assert_eq!(legacy["channel_id"], "channel");
+        assert_eq!(legacy["live_chat_id"], "chat");
+        assert_eq!(legacy["concurrent_viewers"], 1);
+        assert!(legacy.get("channelId").is_none());
+        assert_eq!(snake_case("scheduledStartTime"), "scheduled_start_time");
+    }
+}
@@ -0,0 +1,159 @@
+//! Fault injection: make the API services fail on demand
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use domain::{Fault, FaultConfig, FaultTarget};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{ControlJson, ErrorResponse};
+
+/// Request body for configuring a fault
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetFaultRequest {
+    /// `videos.list`, `oauth.token` or `liveChat.streamList`
+    pub target: String,
+    /// HTTP status of REST and OAuth errors
+    #[serde(default = "default_http_status")]
+    pub http_status: u16,
+    /// YouTube error reason of REST errors, the `error` code of OAuth errors
+    #[serde(default = "default_reason")]
+    pub reason: String,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// gRPC status code name of stream errors, e.g. `UNAVAILABLE`
+    #[serde(default = "default_grpc_code")]
+    pub grpc_code: String,
+    /// Chance of affecting each call, between 0 and 1
+    #[serde(default)]
+    pub probability: Option<f64>,
+    /// Number of calls to affect
+    #[serde(default)]
+    pub count: Option<u64>,
+    /// For `liveChat.streamList`, abort the stream after this many messages instead of
+    /// failing the call
+    #[serde(default)]
+    pub after_messages: Option<u64>,
+}
+
+fn default_http_status() -> u16 {
+    500
+}
+
+fn default_reason() -> String {
+    "backendError".to_string()
+}
+
+fn default_grpc_code() -> String {
+    "UNAVAILABLE".to_string()
+}
+
+/// Response listing the configured faults
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultsResponse {
+    pub success: bool,
+    pub faults: Vec<Fault>,
+}
+
+fn error_response(error: String) -> Response {
+    let response = ErrorResponse {
+        success: false,
+        error,
+    };
+    (StatusCode::BAD_REQUEST, Json(response)).into_response()
+}
+
+impl SetFaultRequest {
+    fn into_fault(self) -> Result<Fault, String> {
+        let target = FaultTarget::from_name(&self.target).ok_or_else(|| {
+            let targets: Vec<_> = FaultTarget::ALL.iter().map(FaultTarget::name).collect();
+            format!(
+                "Unknown fault target '{}'; expected one of {}",
+                self.target,
+                targets.join(", ")
+            )
+        })?;
+        if !(400..=599).contains(&self.http_status) {
+            return Err(format!(
+                "httpStatus must be an error status (400-599), got {}",
+                self.http_status
+            ));
+        }
+        if domain::faults::grpc_code(&self.grpc_code).is_none_or(|code| code == 0) {
+            return Err(format!(
+                "grpcCode must be a gRPC error code name such as UNAVAILABLE, got '{}'",
+                self.grpc_code
+            ));
+        }
+        match (self.probability, self.count) {
+            (Some(probability), None) if (0.0..=1.0).contains(&probability) => {}
+            (Some(_), None) => return Err("probability must be between 0 and 1".to_string()),
+            (None, Some(0)) => return Err("count must be at least 1".to_string()),
+            (None, Some(_)) => {}
+            _ => return Err("Exactly one of probability and count is required".to_string()),
+        }
+        if self.after_messages.is_some() && target != FaultTarget::LiveChatStreamList {
+            return Err(format!(
+                "afterMessages only applies to {}",
+                FaultTarget::LiveChatStreamList.name()
+            ));
+        }
+
+        Ok(Fault {
+            target,
+            http_status: self.http_status,
+            message: self
+                .message
+                .unwrap_or_else(|| format!("Injected fault: {}", self.reason)),
+            reason: self.reason,
+            grpc_code: self.grpc_code,
+            probability: self.probability,
+            remaining: self.count,
+            after_messages: self.after_messages,
+        })
+    }
+}
+
+// Handler for POST /control/faults
+// Replaces any fault configured earlier for the same target
+pub async fn set_fault(
+    State(faults): State<Arc<FaultConfig>>,
+    ControlJson(request): ControlJson<SetFaultRequest>,
+) -> Response {
+    let fault = match request.into_fault() {
+        Ok(fault) => fault,
+        Err(error) => return error_response(error),
+    };
+    faults.set(fault);
+
+    let response = FaultsResponse {
+        success: true,
+        faults: faults.list(),
+    };
+    (StatusCode::CREATED, Json(response)).into_response()
+}
+
+// Handler for GET /control/faults
+pub async fn list_faults(State(faults): State<Arc<FaultConfig>>) -> impl IntoResponse {
+    let response = FaultsResponse {
+        success: true,
+        faults: faults.list(),
+    };
+    (StatusCode::OK, Json(response))
+}
+
+// Handler for DELETE /control/faults
+pub async fn clear_faults(State(faults): State<Arc<FaultConfig>>) -> impl IntoResponse {
+    faults.clear();
+    let response = FaultsResponse {
+        success: true,
+        faults: Vec::new(),
+    };
+    (StatusCode::OK, Json(response))
+}
@@ -0,0 +1,421 @@
+//! Message generators: chat messages inserted at a steady rate
+//!
+//! A generator inserts a message into a chat every `intervalMs` until it has produced
+//! `count` messages, is stopped, or the server shuts down. It is meant for load and soak
+//! tests of a fetcher, where a scenario listing every message would be impractical.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{ControlJson, ControlState, ErrorResponse};
+
+/// Author of generated messages when the request does not name one
+const DEFAULT_GENERATOR_AUTHOR_CHANNEL_ID: &str = "generator-author";
+
+/// Text of generated messages when the request has no template
+const DEFAULT_TEXT_TEMPLATE: &str = "Generated message {seq}";
+
+/// Request body for starting a generator
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateGeneratorRequest {
+    pub live_chat_id: String,
+    pub interval_ms: u64,
+    /// Messages to produce, unbounded when omitted
+    #[serde(default)]
+    pub count: Option<u64>,
+    /// Message text with `{seq}` and `{timestamp}` placeholders
+    #[serde(default)]
+    pub text_template: Option<String>,
+    #[serde(default)]
+    pub author_channel_id: Option<String>,
+    /// Filled in from the author registry when empty or omitted
+    #[serde(default)]
+    pub author_display_name: String,
+}
+
+/// Progress of a generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GeneratorState {
+    Running,
+    Completed,
+    Stopped,
+    Failed,
+}
+
+/// Progress report of a generator
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratorResponse {
+    pub id: String,
+    pub live_chat_id: String,
+    pub state: GeneratorState,
+    pub started_at: DateTime<Utc>,
+    pub interval_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    /// Messages inserted so far
+    pub produced: u64,
+    /// Why the generator failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response body for listing generators
+#[derive(Debug, Serialize)]
+pub struct GeneratorListResponse {
+    pub generators: Vec<GeneratorResponse>,
+}
+
+/// Settings of a generator, fixed when it starts
+struct GeneratorConfig {
+    live_chat_id: String,
+    interval: Duration,
+    count: Option<u64>,
+    text_template: String,
+    author_channel_id: String,
+    author_display_name: String,
+}
+
+impl GeneratorConfig {
+    /// Validate a request; the template is checked against `max_text_len` for its first message
+    fn from_request(
+        request: CreateGeneratorRequest,
+        max_text_len: Option<usize>,
+    ) -> Result<Self, String> {
+        if request.live_chat_id.is_empty() {
+            return Err("liveChatId is required".to_string());
+        }
+        if request.interval_ms == 0 {
+            return Err("intervalMs must be at least 1".to_string());
+        }
+        if request.count == Some(0) {
+            return Err("count must be at least 1 when given".to_string());
+        }
+        let text_template = request
+            .text_template
+            .unwrap_or_else(|| DEFAULT_TEXT_TEMPLATE.to_string());
+        domain::validate_message_text_len(
+            &render_text(&text_template, 1, clock::system_clock().now()),
+            max_text_len,
+        )
+        .map_err(|e| format!("invalidValue: {e}"))?;
+        Ok(Self {
+            live_chat_id: request.live_chat_id,
+            interval: Duration::from_millis(request.interval_ms),
+            count: request.count,
+            text_template,
+            author_channel_id: request
+                .author_channel_id
+                .unwrap_or_else(|| DEFAULT_GENERATOR_AUTHOR_CHANNEL_ID.to_string()),
+            author_display_name: request.author_display_name,
+        })
+    }
+
+    /// The `seq`th message of generator `id`, counting from 1
+    fn message(&self, id: &str, seq: u64, published_at: DateTime<Utc>) -> domain::LiveChatMessage {
+        domain::LiveChatMessage {
+            id: message_id(id, seq),
+            live_chat_id: self.live_chat_id.clone(),
+            author_channel_id: self.author_channel_id.clone(),
+            author_display_name: self.author_display_name.clone(),
+            message_text: render_text(&self.text_template, seq, published_at),
+            published_at,
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        }
+    }
+}
+
+/// Fill in the `{seq}` and `{timestamp}` placeholders of a template
+fn render_text(template: &str, seq: u64, timestamp: DateTime<Utc>) -> String {
+    template
+        .replace("{seq}", &seq.to_string())
+        .replace("{timestamp}", &timestamp.to_rfc3339())
+}
+
+/// ID of the `seq`th message of generator `id`
+///
+/// The sequence number is zero-padded so the IDs of a generator sort in insertion order.
+fn message_id(id: &str, seq: u64) -> String {
+    format!("{id}-{seq:020}")
+}
+
+struct Generator {
+    config: GeneratorConfig,
+    started_at: DateTime<Utc>,
+    produced: AtomicU64,
+    status: Mutex<(GeneratorState, Option<String>)>,
+    task: Mutex<Option<tokio::task::AbortHandle>>,
+}
+
+impl Generator {
+    fn report(&self, id: &str) -> GeneratorResponse {
+        let (state, error) = self
+            .status
+            .lock()
+            .expect("Failed to acquire lock on generator")
+            .clone();
+        GeneratorResponse {
+            id: id.to_string(),
+            live_chat_id: self.config.live_chat_id.clone(),
+            state,
+            started_at: self.started_at,
+            interval_ms: self.config.interval.as_millis() as u64,
+            count: self.config.count,
+            produced: self.produced.load(Ordering::SeqCst),
+            error,
+        }
+    }
+
+    /// Move out of `Running`; a generator that already ended keeps its state
+    fn finish(&self, state: GeneratorState, error: Option<String>) -> bool {
+        let mut status = self
+            .status
+            .lock()
+            .expect("Failed to acquire lock on generator");
+        if status.0 != GeneratorState::Running {
+            return false;
+        }
+        *status = (state, error);
+        true
+    }
+
+    /// Stop the task, unless the generator already ended; returns whether it was running
+    fn stop(&self) -> bool {
+        if !self.finish(GeneratorState::Stopped, None) {
+            return false;
+        }
+        if let Some(task) = self
+            .task
+            .lock()
+            .expect("Failed to acquire lock on generator")
+            .take()
+        {
+            task.abort();
+        }
+        true
+    }
+}
+
+/// Generators started through the control API, until they are deleted
+#[derive(Default)]
+pub struct GeneratorRegistry {
+    /// Generators by ID; IDs sort in creation order
+    generators: Mutex<BTreeMap<String, Arc<Generator>>>,
+    next_id: AtomicU64,
+}
+
+impl GeneratorRegistry {
+    fn remove(&self, id: &str) -> Option<Arc<Generator>> {
+        self.generators
+            .lock()
+            .expect("Failed to acquire lock on generator registry")
+            .remove(id)
+    }
+
+    /// Stop and remove every generator; returns how many were running
+    pub(crate) fn clear(&self) -> usize {
+        let generators = std::mem::take(
+            &mut *self
+                .generators
+                .lock()
+                .expect("Failed to acquire lock on generator registry"),
+        );
+        generators
+            .values()
+            .filter(|generator| generator.stop())
+            .count()
+    }
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    let response = ErrorResponse {
+        success: false,
+        error,
+    };
+    (status, Json(response)).into_response()
+}
+
+/// Handler for starting a generator
+pub(crate) async fn create_generator(
+    State(state): State<ControlState>,
+    ControlJson(request): ControlJson<CreateGeneratorRequest>,
+) -> Response {
+    let config = match GeneratorConfig::from_request(request, state.max_text_len) {
+        Ok(config) => config,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let id = format!(
+        "generator-{:06}",
+        state.generators.next_id.fetch_add(1, Ordering::SeqCst) + 1
+    );
+    let generator = Arc::new(Generator {
+        config,
+        started_at: clock::system_clock().now(),
+        produced: AtomicU64::new(0),
+        status: Mutex::new((GeneratorState::Running, None)),
+        task: Mutex::new(None),
+    });
+    state
+        .generators
+        .generators
+        .lock()
+        .expect("Failed to acquire lock on generator registry")
+        .insert(id.clone(), Arc::clone(&generator));
+
+    let repo = Arc::clone(&state.repo);
+    let max_text_len = state.max_text_len;
+    let shutdown = state.shutdown.clone();
+    let task = tokio::spawn({
+        let generator = Arc::clone(&generator);
+        let id = id.clone();
+        async move {
+            let period = generator.config.interval;
+            let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            // A stalled tick delays the following ones instead of bursting to catch up
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            for seq in 1.. {
+                if generator.config.count.is_some_and(|count| seq > count) {
+                    generator.finish(GeneratorState::Completed, None);
+                    return;
+                }
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    _ = shutdown.cancelled() => {
+                        generator.finish(GeneratorState::Stopped, None);
+                        return;
+                    }
+                }
+                let message = generator
+                    .config
+                    .message(&id, seq, clock::system_clock().now());
+                let inserted =
+                    domain::validate_message_text_len(&message.message_text, max_text_len)
+                        .and_then(|()| repo.add_chat_message(message).map_err(|e| e.to_string()));
+                if let Err(e) = inserted {
+                    tracing::warn!("Generator {id} failed: {e}");
+                    generator.finish(GeneratorState::Failed, Some(e));
+                    return;
+                }
+                generator.produced.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    });
+    *generator
+        .task
+        .lock()
+        .expect("Failed to acquire lock on generator") = Some(task.abort_handle());
+
+    (StatusCode::CREATED, Json(generator.report(&id))).into_response()
+}
+
+/// Handler for listing generators with their progress
+pub(crate) async fn list_generators(State(state): State<ControlState>) -> Response {
+    let generators = state
+        .generators
+        .generators
+        .lock()
+        .expect("Failed to acquire lock on generator registry")
+        .iter()
+        .map(|(id, generator)| generator.report(id))
+        .collect();
+    (StatusCode::OK, Json(GeneratorListResponse { generators })).into_response()
+}
+
+/// Handler for stopping and removing a generator; its messages stay in the chat
+pub(crate) async fn delete_generator(
+    State(state): State<ControlState>,
+    Path(id): Path<String>,
+) -> Response {
+    let Some(generator) = state.generators.remove(&id) else {
+        return error_response(StatusCode::NOT_FOUND, format!("Generator '{id}' not found"));
+    };
+    generator.stop();
+    (StatusCode::OK, Json(generator.report(&id))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> CreateGeneratorRequest {
+        CreateGeneratorRequest {
+            live_chat_id: "chat".to_string(),
+            interval_ms: 100,
+            count: None,
+            text_template: None,
+            author_channel_id: None,
+            author_display_name: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_templates_fill_in_sequence_and_timestamp() {
+        let timestamp = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            render_text("#{seq} at {timestamp} ({seq})", 7, timestamp),
+            "#7 at 2024-01-01T00:00:00+00:00 (7)"
+        );
+
+        let config = GeneratorConfig::from_request(request(), None).unwrap();
+        let message = config.message("generator-000001", 3, timestamp);
+        assert_eq!(message.message_text, "Generated message 3");
+        assert_eq!(
+            message.author_channel_id,
+            DEFAULT_GENERATOR_AUTHOR_CHANNEL_ID
+        );
+        assert_eq!(message.published_at, timestamp);
+    }
+
+    #[test]
+    fn test_message_ids_sort_in_insertion_order() {
+        let ids: Vec<String> = [1, 9, 10, 100, 12345]
+            .into_iter()
+            .map(|seq| message_id("generator-000001", seq))
+            .collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+    }
+
+    #[test]
+    fn test_invalid_generators_are_rejected() {
+        let mut no_chat = request();
+        no_chat.live_chat_id = String::new();
+        assert!(GeneratorConfig::from_request(no_chat, None).is_err());
+
+        let mut no_interval = request();
+        no_interval.interval_ms = 0;
+        assert!(GeneratorConfig::from_request(no_interval, None).is_err());
+
+        let mut no_count = request();
+        no_count.count = Some(0);
+        assert!(GeneratorConfig::from_request(no_count, None).is_err());
+
+        assert!(
+            GeneratorConfig::from_request(request(), Some(5))
+                .err()
+                .unwrap()
+                .starts_with("invalidValue:")
+        );
+    }
+}
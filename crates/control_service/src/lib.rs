@@ -1,10 +1,149 @@
-use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::post};
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{FromRef, OriginalUri, State},
+    http::{Method, Request, StatusCode, header},
+    response::IntoResponse,
+    routing::{delete, get, patch, post},
+};
 use chrono::{DateTime, Utc};
 use fake::Fake;
 use fake::faker::internet::en::Username;
 use fake::faker::lorem::en::Sentence;
+use request_log::RequestLogEntry;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tower::ServiceExt;
+
+mod casing;
+mod faults;
+mod generators;
+mod live_chats;
+mod method_override;
+mod oauth;
+mod quota;
+mod read_only;
+mod reseed;
+mod reset;
+mod scenarios;
+mod snapshot;
+mod unknown_fields;
+mod videos;
+mod warmup;
+
+pub use generators::GeneratorRegistry;
+pub use method_override::METHOD_OVERRIDE_HEADER;
+pub use read_only::READ_ONLY_ERROR;
+pub use scenarios::ScenarioRegistry;
+pub use unknown_fields::ControlJson;
+pub use warmup::WarmupRegistry;
+
+/// Shared state for the control API handlers
+#[derive(Clone)]
+pub struct ControlState {
+    pub repo: Arc<dyn datastore::Repository>,
+    pub warmup: Arc<WarmupRegistry>,
+    /// Open gRPC streams, registered by the live chat service
+    pub streams: Arc<domain::StreamRegistry>,
+    /// Quota charged by the API services
+    pub quota: Arc<domain::QuotaLedger>,
+    /// Faults consulted by the API services
+    pub faults: Arc<domain::FaultConfig>,
+    /// Longest accepted message text in characters, unlimited when `None`
+    pub max_text_len: Option<usize>,
+    /// Scenarios inserting messages on a schedule
+    pub scenarios: Arc<ScenarioRegistry>,
+    /// Generators inserting messages at a steady rate
+    pub generators: Arc<GeneratorRegistry>,
+    /// Cancelled on server shutdown to stop the generators
+    pub shutdown: CancellationToken,
+    /// Give videos created without a live chat ID one in the format of the real API
+    pub realistic_chat_ids: bool,
+    /// Request log the server records to, which `POST /control/replay` replays by default
+    pub request_log_file: Option<PathBuf>,
+    /// Let `POST /control/replay` read any file named in the request, not just the request log
+    pub replay_any_path: bool,
+}
+
+impl ControlState {
+    pub fn new(
+        repo: Arc<dyn datastore::Repository>,
+        streams: Arc<domain::StreamRegistry>,
+        quota: Arc<domain::QuotaLedger>,
+        faults: Arc<domain::FaultConfig>,
+    ) -> Self {
+        Self {
+            repo,
+            warmup: Arc::new(WarmupRegistry::default()),
+            streams,
+            quota,
+            faults,
+            max_text_len: None,
+            scenarios: Arc::new(ScenarioRegistry::default()),
+            generators: Arc::new(GeneratorRegistry::default()),
+            shutdown: CancellationToken::new(),
+            realistic_chat_ids: false,
+            request_log_file: None,
+            replay_any_path: false,
+        }
+    }
+
+    /// Reject chat messages whose text is longer than `max_text_len` characters
+    pub fn with_max_text_len(mut self, max_text_len: Option<usize>) -> Self {
+        self.max_text_len = max_text_len;
+        self
+    }
+
+    /// Stop running generators once `shutdown` is cancelled
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Generate a realistic live chat ID for videos created without one
+    pub fn with_realistic_chat_ids(mut self, realistic_chat_ids: bool) -> Self {
+        self.realistic_chat_ids = realistic_chat_ids;
+        self
+    }
+
+    /// Replay the request log at `request_log_file` when a replay names no file
+    pub fn with_request_log_file(mut self, request_log_file: Option<PathBuf>) -> Self {
+        self.request_log_file = request_log_file;
+        self
+    }
+
+    /// Let replays read any file on the server instead of only the request log
+    pub fn with_replay_any_path(mut self, replay_any_path: bool) -> Self {
+        self.replay_any_path = replay_any_path;
+        self
+    }
+}
+
+impl FromRef<ControlState> for Arc<domain::FaultConfig> {
+    fn from_ref(state: &ControlState) -> Self {
+        Arc::clone(&state.faults)
+    }
+}
+
+impl FromRef<ControlState> for Arc<domain::QuotaLedger> {
+    fn from_ref(state: &ControlState) -> Self {
+        Arc::clone(&state.quota)
+    }
+}
+
+impl FromRef<ControlState> for Arc<domain::StreamRegistry> {
+    fn from_ref(state: &ControlState) -> Self {
+        Arc::clone(&state.streams)
+    }
+}
+
+impl FromRef<ControlState> for Arc<dyn datastore::Repository> {
+    fn from_ref(state: &ControlState) -> Self {
+        Arc::clone(&state.repo)
+    }
+}
 
 /// Request body for creating a new video
 #[derive(Debug, Deserialize)]
@@ -36,11 +175,165 @@ pub struct CreateChatMessageRequest {
     pub id: String,
     pub live_chat_id: String,
     pub author_channel_id: String,
+    /// Filled in from the author registry when empty or omitted
+    #[serde(default)]
     pub author_display_name: String,
     pub message_text: String,
     #[serde(default = "default_datetime")]
     pub published_at: DateTime<Utc>,
+    /// Whether the author is verified; most fixture authors are not, so this defaults to false
+    #[serde(default)]
     pub is_verified: bool,
+    /// Chat role badges of the author; all default to false
+    #[serde(default)]
+    pub is_chat_owner: bool,
+    #[serde(default)]
+    pub is_chat_moderator: bool,
+    #[serde(default)]
+    pub is_chat_sponsor: bool,
+    /// Avatar of the author; a placeholder derived from the channel ID when omitted
+    #[serde(default)]
+    pub profile_image_url: Option<String>,
+    /// Makes the message a super chat
+    #[serde(default)]
+    pub super_chat_details: Option<SuperChatRequest>,
+    /// Makes the message a super sticker
+    #[serde(default)]
+    pub super_sticker_details: Option<SuperStickerRequest>,
+    /// `textMessageEvent`, `superChatEvent` or `superStickerEvent`; derived from the details
+    /// when omitted, and checked against them when present
+    #[serde(default)]
+    pub event_type: Option<String>,
+}
+
+/// Super chat part of a chat message request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperChatRequest {
+    pub amount_micros: u64,
+    /// ISO 4217 currency code, e.g. "USD"
+    pub currency: String,
+    #[serde(default = "default_super_chat_tier")]
+    pub tier: u32,
+    /// Defaults to the message text
+    #[serde(default)]
+    pub user_comment: Option<String>,
+}
+
+fn default_super_chat_tier() -> u32 {
+    1
+}
+
+/// Super sticker part of a chat message request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperStickerRequest {
+    pub amount_micros: u64,
+    /// ISO 4217 currency code, e.g. "USD"
+    pub currency: String,
+    #[serde(default = "default_super_chat_tier")]
+    pub tier: u32,
+    #[serde(default = "default_sticker_id")]
+    pub sticker_id: String,
+    /// Defaults to the message text
+    #[serde(default)]
+    pub alt_text: Option<String>,
+}
+
+fn default_sticker_id() -> String {
+    "sticker-1".to_string()
+}
+
+/// Check the amount of a paid message, reporting errors under `field`
+fn validate_amount(field: &str, amount_micros: u64, currency: &str) -> Result<(), String> {
+    if amount_micros == 0 {
+        return Err(format!("{field}.amountMicros must be positive"));
+    }
+    if currency.len() != 3 || !currency.bytes().all(|b| b.is_ascii_uppercase()) {
+        return Err(format!(
+            "{field}.currency '{currency}' is not an ISO 4217 code such as 'USD'"
+        ));
+    }
+    Ok(())
+}
+
+impl SuperChatRequest {
+    /// Validate the request and build the super chat details of a message with `message_text`
+    fn into_details(self, message_text: &str) -> Result<domain::SuperChatDetails, String> {
+        validate_amount("superChatDetails", self.amount_micros, &self.currency)?;
+        Ok(domain::SuperChatDetails {
+            amount_micros: self.amount_micros,
+            currency: self.currency,
+            tier: self.tier,
+            user_comment: self
+                .user_comment
+                .unwrap_or_else(|| message_text.to_string()),
+        })
+    }
+}
+
+impl SuperStickerRequest {
+    /// Validate the request and build the super sticker details of a message with
+    /// `message_text`
+    fn into_details(self, message_text: &str) -> Result<domain::SuperStickerDetails, String> {
+        validate_amount("superStickerDetails", self.amount_micros, &self.currency)?;
+        Ok(domain::SuperStickerDetails {
+            amount_micros: self.amount_micros,
+            currency: self.currency,
+            tier: self.tier,
+            sticker_id: self.sticker_id,
+            alt_text: self.alt_text.unwrap_or_else(|| message_text.to_string()),
+        })
+    }
+}
+
+impl CreateChatMessageRequest {
+    /// Validate the paid message parts of the request against its `eventType`
+    fn paid_details(
+        &mut self,
+    ) -> Result<
+        (
+            Option<domain::SuperChatDetails>,
+            Option<domain::SuperStickerDetails>,
+        ),
+        String,
+    > {
+        let super_chat_details = self
+            .super_chat_details
+            .take()
+            .map(|details| details.into_details(&self.message_text))
+            .transpose()?;
+        let super_sticker_details = self
+            .super_sticker_details
+            .take()
+            .map(|details| details.into_details(&self.message_text))
+            .transpose()?;
+        let derived = match (&super_chat_details, &super_sticker_details) {
+            (Some(_), Some(_)) => {
+                return Err(
+                    "Only one of superChatDetails and superStickerDetails may be set".to_string(),
+                );
+            }
+            (Some(_), None) => "superChatEvent",
+            (None, Some(_)) => "superStickerEvent",
+            (None, None) => "textMessageEvent",
+        };
+        if let Some(event_type) = &self.event_type
+            && event_type != derived
+        {
+            return Err(match event_type.as_str() {
+                "textMessageEvent" | "superChatEvent" | "superStickerEvent" => format!(
+                    "eventType '{event_type}' does not match the message details, which make it \
+                     '{derived}'"
+                ),
+                _ => format!(
+                    "Unknown eventType '{event_type}'; expected textMessageEvent, superChatEvent \
+                     or superStickerEvent"
+                ),
+            });
+        }
+        Ok((super_chat_details, super_sticker_details))
+    }
 }
 
 /// Request body for generating a chat message with minimal fields
@@ -55,6 +348,24 @@ pub struct GenerateChatMessageRequest {
     pub author_display_name: Option<String>,
 }
 
+/// Request body for injecting the battery of tricky render-test messages
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InjectTrickyMessagesRequest {
+    pub live_chat_id: String,
+}
+
+/// Request body for replaying a recorded request log
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayRequest {
+    /// Path of the log file to replay
+    /// Defaults to the file configured via REQUEST_LOG_FILE, the only one allowed unless
+    /// CONTROL_REPLAY_ANY_PATH is set
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
 /// Response for successful creation
 #[derive(Debug, Serialize)]
 pub struct CreateResponse {
@@ -69,16 +380,89 @@ pub struct ErrorResponse {
     pub error: String,
 }
 
+/// Response for a path that matches no control endpoint
+#[derive(Debug, Serialize)]
+pub struct UnknownEndpointResponse {
+    pub success: bool,
+    pub error: String,
+    /// Every control endpoint, as `METHOD /control/path`
+    pub routes: Vec<String>,
+}
+
+/// Response for a request log replay
+#[derive(Debug, Serialize)]
+pub struct ReplayResponse {
+    pub success: bool,
+    pub message: String,
+    /// Number of control requests that were re-applied successfully
+    pub replayed: usize,
+    /// Number of entries that are not state-changing control requests
+    pub skipped: usize,
+    /// Number of control requests that were rejected on replay
+    pub failed: usize,
+    /// Number of control requests not replayed because redaction replaced part of their
+    /// body or query when they were recorded
+    pub redacted: usize,
+}
+
+/// Message texts that exercise displayMessage rendering
+/// Angle brackets, ampersands, quotes, script tags, pre-escaped entities and markdown-like text
+pub const TRICKY_MESSAGES: &[&str] = &[
+    "<b>bold</b> & <i>italic</i>",
+    "Tom & Jerry",
+    "<script>alert('xss')</script>",
+    "1 < 2 && 3 > 2",
+    "Quotes: \"double\" and 'single'",
+    "Already escaped: &amp; &lt;tag&gt; &#39;",
+    "**markdown** _emphasis_ `code` [link](https://example.com)",
+    "Unicode ñ and emoji 🎉",
+];
+
+/// Response for the status endpoint
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusResponse {
+    /// Current wall-clock time of the server
+    pub now: DateTime<Utc>,
+    /// Detected wall-clock jumps
+    pub clock: clock::ClockStatus,
+}
+
+/// Map a repository failure to a control API error response
+/// Backend failures become 500 rather than a panic
+pub(crate) fn repository_error_response(
+    error: &datastore::RepositoryError,
+) -> axum::response::Response {
+    let status = match error {
+        datastore::RepositoryError::NotFound => StatusCode::NOT_FOUND,
+        datastore::RepositoryError::Conflict => StatusCode::CONFLICT,
+        datastore::RepositoryError::Invalid(_) => StatusCode::BAD_REQUEST,
+        datastore::RepositoryError::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let response = ErrorResponse {
+        success: false,
+        error: error.to_string(),
+    };
+    (status, Json(response)).into_response()
+}
+
 /// Default to current datetime
 fn default_datetime() -> DateTime<Utc> {
-    Utc::now()
+    clock::system_clock().now()
 }
 
 /// Handler for creating a new video
 async fn create_video(
-    State(repo): State<Arc<dyn datastore::Repository>>,
-    Json(request): Json<CreateVideoRequest>,
+    State(state): State<ControlState>,
+    ControlJson(request): ControlJson<CreateVideoRequest>,
 ) -> impl IntoResponse {
+    let live_chat_id = match request.live_chat_id {
+        None if state.realistic_chat_ids => Some(domain::realistic_live_chat_id(
+            &request.id,
+            &request.channel_id,
+        )),
+        live_chat_id => live_chat_id,
+    };
     let video = domain::Video {
         id: request.id.clone(),
         channel_id: request.channel_id,
@@ -86,7 +470,7 @@ async fn create_video(
         description: request.description,
         channel_title: request.channel_title,
         published_at: request.published_at,
-        live_chat_id: request.live_chat_id,
+        live_chat_id: live_chat_id.clone(),
         actual_start_time: request.actual_start_time,
         actual_end_time: request.actual_end_time,
         scheduled_start_time: request.scheduled_start_time,
@@ -94,11 +478,28 @@ async fn create_video(
         concurrent_viewers: request.concurrent_viewers,
     };
 
-    repo.add_video(video);
+    if let Err(e) = video.validate_times() {
+        let error = ErrorResponse {
+            success: false,
+            error: e,
+        };
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
 
+    if let Err(e) = state.repo.add_video(video) {
+        return repository_error_response(&e);
+    }
+
+    let message = match live_chat_id {
+        Some(live_chat_id) if state.realistic_chat_ids => format!(
+            "Video '{}' created successfully with live chat '{live_chat_id}'",
+            request.id
+        ),
+        _ => format!("Video '{}' created successfully", request.id),
+    };
     let response = CreateResponse {
         success: true,
-        message: format!("Video '{}' created successfully", request.id),
+        message,
     };
 
     (StatusCode::CREATED, Json(response)).into_response()
@@ -106,20 +507,62 @@ async fn create_video(
 
 /// Handler for creating a new chat message
 async fn create_chat_message(
-    State(repo): State<Arc<dyn datastore::Repository>>,
-    Json(request): Json<CreateChatMessageRequest>,
+    State(state): State<ControlState>,
+    ControlJson(mut request): ControlJson<CreateChatMessageRequest>,
 ) -> impl IntoResponse {
+    let repo = state.repo;
+    if let Err(e) = domain::validate_message_text_len(&request.message_text, state.max_text_len) {
+        let response = ErrorResponse {
+            success: false,
+            error: format!("invalidValue: {e}"),
+        };
+        return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+    }
+    let (super_chat_details, super_sticker_details) = match request.paid_details() {
+        Ok(details) => details,
+        Err(error) => {
+            let response = ErrorResponse {
+                success: false,
+                error,
+            };
+            return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+        }
+    };
+    let live_chat_id = request.live_chat_id;
     let message = domain::LiveChatMessage {
         id: request.id.clone(),
-        live_chat_id: request.live_chat_id,
+        live_chat_id: live_chat_id.clone(),
         author_channel_id: request.author_channel_id,
         author_display_name: request.author_display_name,
         message_text: request.message_text,
         published_at: request.published_at,
         is_verified: request.is_verified,
+        super_chat_details,
+        super_sticker_details,
+        deleted_message_id: None,
+        author_profile: domain::AuthorProfile {
+            is_chat_owner: request.is_chat_owner,
+            is_chat_moderator: request.is_chat_moderator,
+            is_chat_sponsor: request.is_chat_sponsor,
+            profile_image_url: request.profile_image_url,
+        },
+        muted: false,
     };
 
-    repo.add_chat_message(message);
+    match repo.add_chat_message(message) {
+        Ok(()) => {}
+        Err(datastore::RepositoryError::Conflict) => {
+            let response = ErrorResponse {
+                success: false,
+                error: format!(
+                    "Chat message '{}' already exists in live chat '{live_chat_id}'",
+                    request.id
+                ),
+            };
+            return (StatusCode::CONFLICT, Json(response)).into_response();
+        }
+        Err(e) => return repository_error_response(&e),
+    }
 
     let response = CreateResponse {
         success: true,
@@ -129,10 +572,35 @@ async fn create_chat_message(
     (StatusCode::CREATED, Json(response)).into_response()
 }
 
+/// Handler for deleting a chat message
+/// Every message with the ID is deleted, in whichever chat it was posted
+async fn delete_chat_message(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    match repo.delete_chat_message(&id, clock::system_clock().now()) {
+        Ok(()) => {
+            let response = CreateResponse {
+                success: true,
+                message: format!("Chat message '{id}' deleted successfully"),
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(datastore::RepositoryError::NotFound) => {
+            let response = ErrorResponse {
+                success: false,
+                error: format!("Chat message '{id}' not found"),
+            };
+            (StatusCode::NOT_FOUND, Json(response)).into_response()
+        }
+        Err(e) => repository_error_response(&e),
+    }
+}
+
 /// Handler for generating a chat message with auto-generated fields
 async fn generate_chat_message(
     State(repo): State<Arc<dyn datastore::Repository>>,
-    Json(request): Json<GenerateChatMessageRequest>,
+    ControlJson(request): ControlJson<GenerateChatMessageRequest>,
 ) -> impl IntoResponse {
     // Generate a unique ID using UUID
     let id = format!("msg-{}", uuid::Uuid::new_v4());
@@ -151,11 +619,18 @@ async fn generate_chat_message(
         author_channel_id: format!("channel-{}", uuid::Uuid::new_v4()),
         author_display_name,
         message_text,
-        published_at: Utc::now(),
+        published_at: clock::system_clock().now(),
         is_verified: false,
+        super_chat_details: None,
+        super_sticker_details: None,
+        deleted_message_id: None,
+        author_profile: Default::default(),
+        muted: false,
     };
 
-    repo.add_chat_message(message);
+    if let Err(e) = repo.add_chat_message(message) {
+        return repository_error_response(&e);
+    }
 
     let response = CreateResponse {
         success: true,
@@ -165,11 +640,3165 @@ async fn generate_chat_message(
     (StatusCode::CREATED, Json(response)).into_response()
 }
 
+/// Handler for injecting the tricky render-test messages into a chat
+async fn inject_tricky_messages(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    ControlJson(request): ControlJson<InjectTrickyMessagesRequest>,
+) -> impl IntoResponse {
+    let published_at = clock::system_clock().now();
+
+    for (i, text) in TRICKY_MESSAGES.iter().enumerate() {
+        let message = domain::LiveChatMessage {
+            id: format!("tricky-{i}-{}", uuid::Uuid::new_v4()),
+            live_chat_id: request.live_chat_id.clone(),
+            author_channel_id: "tricky-author-channel".to_string(),
+            author_display_name: "Tricky <Author> & Co".to_string(),
+            message_text: text.to_string(),
+            published_at,
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        };
+        if let Err(e) = repo.add_chat_message(message) {
+            return repository_error_response(&e);
+        }
+    }
+
+    let response = CreateResponse {
+        success: true,
+        message: format!(
+            "{} tricky chat messages created successfully",
+            TRICKY_MESSAGES.len()
+        ),
+    };
+
+    (StatusCode::CREATED, Json(response)).into_response()
+}
+
+/// Control endpoints listed in the response for unknown paths
+pub const CONTROL_ROUTES: &[&str] = &[
+    "POST /control/videos",
+    "GET /control/videos/{id}",
+    "PATCH /control/videos/{id}",
+    "DELETE /control/videos/{id}",
+    "POST /control/videos/{id}/transition",
+    "POST /control/videos/{id}/start",
+    "POST /control/videos/{id}/end",
+    "POST /control/live_chats",
+    "PATCH /control/live_chats/{id}",
+    "POST /control/live_chats/{id}/transition",
+    "POST /control/live_chats/{id}/close_streams",
+    "GET /control/live_chats/{id}/delivery_audit",
+    "POST /control/live_chats/{id}/delivery_audit",
+    "DELETE /control/live_chats/{id}/delivery_audit",
+    "POST /control/live_chats/{id}/mutes",
+    "DELETE /control/live_chats/{id}/mutes/{channel_id}",
+    "POST /control/chat_messages",
+    "DELETE /control/chat_messages/{id}",
+    "POST /control/chat_messages/generate",
+    "POST /control/chat_messages/tricky",
+    "POST /control/scenarios",
+    "GET /control/scenarios/{id}",
+    "DELETE /control/scenarios/{id}",
+    "GET /control/generators",
+    "POST /control/generators",
+    "DELETE /control/generators/{id}",
+    "POST /control/replay",
+    "POST /control/reset",
+    "POST /control/reseed",
+    "POST /control/warmup",
+    "GET /control/stats",
+    "GET /control/status",
+    "GET /control/state",
+    "GET /control/quota",
+    "POST /control/quota",
+    "GET /control/quota/report",
+    "POST /control/quota/reset",
+    "POST /control/oauth/tokens/bulk",
+    "GET /control/faults",
+    "POST /control/faults",
+    "DELETE /control/faults",
+];
+
+/// Fallback for paths that match no control endpoint, e.g. a mistyped `/control/video`
+async fn unknown_endpoint(OriginalUri(uri): OriginalUri) -> impl IntoResponse {
+    let response = UnknownEndpointResponse {
+        success: false,
+        error: format!("unknown control endpoint: {}", uri.path()),
+        routes: CONTROL_ROUTES.iter().map(ToString::to_string).collect(),
+    };
+
+    (StatusCode::NOT_FOUND, Json(response)).into_response()
+}
+
+/// Handler for reporting server status, including detected wall-clock jumps
+async fn status() -> impl IntoResponse {
+    let clock = clock::system_clock();
+    let response = StatusResponse {
+        now: clock.now(),
+        clock: clock.status(),
+    };
+
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Handler for replaying a recorded request log to reconstruct state
+/// Only REST requests to the control endpoints are re-applied, in their original order
+async fn replay_request_log(
+    State(state): State<ControlState>,
+    ControlJson(request): ControlJson<ReplayRequest>,
+) -> impl IntoResponse {
+    // Other files on the server are only read when explicitly allowed
+    let path = match (request.path, &state.request_log_file) {
+        (Some(path), log_file)
+            if state.replay_any_path
+                || log_file
+                    .as_deref()
+                    .is_some_and(|log_file| same_file(log_file, Path::new(&path))) =>
+        {
+            PathBuf::from(path)
+        }
+        (Some(path), _) => {
+            let error = ErrorResponse {
+                success: false,
+                error: format!(
+                    "Only the REQUEST_LOG_FILE can be replayed, not '{path}', unless CONTROL_REPLAY_ANY_PATH is set"
+                ),
+            };
+            return (StatusCode::FORBIDDEN, Json(error)).into_response();
+        }
+        (None, Some(log_file)) => log_file.clone(),
+        (None, None) => {
+            let error = ErrorResponse {
+                success: false,
+                error: "No request log path given and REQUEST_LOG_FILE is not set".to_string(),
+            };
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+    let path_name = path.display().to_string();
+
+    let entries = match request_log::read_entries(&path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            let error = ErrorResponse {
+                success: false,
+                error: format!("Failed to read request log '{path_name}': {e}"),
+            };
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+
+    // Dispatch each recorded request through a fresh control router sharing the same state
+    let router = router_with_state(state);
+    let mut replayed = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+    let mut redacted = 0;
+
+    for entry in entries {
+        let RequestLogEntry::Rest {
+            method,
+            path,
+            query,
+            body,
+            ..
+        } = entry
+        else {
+            skipped += 1;
+            continue;
+        };
+
+        let Ok(method) = method.parse::<Method>() else {
+            failed += 1;
+            continue;
+        };
+
+        // Skip non-control requests, read-only requests, and earlier replays
+        let control_path = match path.strip_prefix("/control") {
+            Some(control_path)
+                if control_path != "/replay" && method != Method::GET && method != Method::HEAD =>
+            {
+                control_path
+            }
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        // A redacted value would be replayed as if it were the original, e.g. as a password
+        if request_log::redact::contains_marker(&body)
+            || query
+                .as_deref()
+                .is_some_and(request_log::redact::contains_marker)
+        {
+            tracing::warn!("Replay skipped {method} {path}: recorded with redacted values");
+            redacted += 1;
+            continue;
+        }
+
+        let uri = match query {
+            Some(query) => format!("{control_path}?{query}"),
+            None => control_path.to_string(),
+        };
+
+        let replay_request = match Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+        {
+            Ok(replay_request) => replay_request,
+            Err(_) => {
+                failed += 1;
+                continue;
+            }
+        };
+
+        match router.clone().oneshot(replay_request).await {
+            Ok(response) if response.status().is_success() => replayed += 1,
+            _ => failed += 1,
+        }
+    }
+
+    let response = ReplayResponse {
+        success: failed == 0 && redacted == 0,
+        message: format!("Replayed {replayed} control request(s) from '{path_name}'"),
+        replayed,
+        skipped,
+        failed,
+        redacted,
+    };
+
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Whether two paths name the same file, comparing them as given when either does not exist
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 /// Create the router for the control API
-pub fn create_router(repo: Arc<dyn datastore::Repository>) -> Router {
+pub fn create_router(
+    repo: Arc<dyn datastore::Repository>,
+    streams: Arc<domain::StreamRegistry>,
+    quota: Arc<domain::QuotaLedger>,
+    faults: Arc<domain::FaultConfig>,
+) -> Router {
+    router_with_state(ControlState::new(repo, streams, quota, faults))
+}
+
+/// Make a control router read-only: its mutating routes answer 403 while GET routes keep
+/// working. Unknown endpoints still answer 404.
+pub fn read_only(router: Router) -> Router {
+    router.route_layer(axum::middleware::from_fn(read_only::reject_writes))
+}
+
+/// Honor `X-HTTP-Method-Override` on a control router: a POST naming another method in the
+/// header is routed as that method, for clients behind proxies that only pass GET and POST.
+pub fn method_override(router: Router) -> Router {
+    // Wrapped as a fallback so the method is remapped before the inner router matches it
+    Router::new()
+        .fallback_service(router)
+        .layer(axum::middleware::from_fn(method_override::override_method))
+}
+
+/// Create the router for the control API with configured state
+pub fn router_with_state(state: ControlState) -> Router {
     Router::new()
         .route("/videos", post(create_video))
+        .route(
+            "/videos/{id}",
+            get(videos::get_video)
+                .patch(videos::patch_video)
+                .delete(videos::delete_video),
+        )
+        .route(
+            "/videos/{id}/transition",
+            post(live_chats::transition_broadcast),
+        )
+        .route("/videos/{id}/start", post(live_chats::start_video))
+        .route("/videos/{id}/end", post(live_chats::end_video))
+        .route("/live_chats", post(live_chats::create_live_chat))
+        .route("/live_chats/{id}", patch(live_chats::patch_live_chat))
+        .route(
+            "/live_chats/{id}/transition",
+            post(live_chats::transition_live_chat),
+        )
+        .route(
+            "/live_chats/{id}/close_streams",
+            post(live_chats::close_streams),
+        )
+        .route(
+            "/live_chats/{id}/delivery_audit",
+            get(live_chats::delivery_audit)
+                .post(live_chats::enable_delivery_audit)
+                .delete(live_chats::disable_delivery_audit),
+        )
+        .route("/live_chats/{id}/mutes", post(live_chats::mute_author))
+        .route(
+            "/live_chats/{id}/mutes/{channel_id}",
+            delete(live_chats::unmute_author),
+        )
         .route("/chat_messages", post(create_chat_message))
+        .route("/chat_messages/{id}", delete(delete_chat_message))
         .route("/chat_messages/generate", post(generate_chat_message))
-        .with_state(repo)
+        .route("/chat_messages/tricky", post(inject_tricky_messages))
+        .route("/scenarios", post(scenarios::create_scenario))
+        .route(
+            "/scenarios/{id}",
+            get(scenarios::get_scenario).delete(scenarios::cancel_scenario),
+        )
+        .route(
+            "/generators",
+            get(generators::list_generators).post(generators::create_generator),
+        )
+        .route("/generators/{id}", delete(generators::delete_generator))
+        .route("/replay", post(replay_request_log))
+        .route("/reset", post(reset::reset))
+        .route("/reseed", post(reseed::reseed))
+        .route("/warmup", post(warmup::warmup))
+        .route("/stats", get(warmup::stats))
+        .route("/status", get(status))
+        .route("/state", get(snapshot::state))
+        .route("/quota", get(quota::get_quota).post(quota::set_quota))
+        .route("/quota/report", get(quota::report))
+        .route("/quota/reset", post(quota::reset))
+        .route("/oauth/tokens/bulk", post(oauth::issue_bulk_tokens))
+        .route(
+            "/faults",
+            get(faults::list_faults)
+                .post(faults::set_fault)
+                .delete(faults::clear_faults),
+        )
+        .fallback(unknown_endpoint)
+        .layer(axum::middleware::from_fn(
+            unknown_fields::report_unknown_fields,
+        ))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    async fn post_json(router: &Router, uri: &str, body: serde_json::Value) -> serde_json::Value {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        assert!(response.status().is_success(), "{uri} should succeed");
+        read_json(response).await
+    }
+
+    async fn get_json(router: &Router, uri: &str) -> serde_json::Value {
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        read_json(response).await
+    }
+
+    async fn read_json(response: axum::response::Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        serde_json::from_slice(&bytes).expect("JSON body")
+    }
+
+    fn chat_state<'a>(stats: &'a serde_json::Value, live_chat_id: &str) -> &'a str {
+        stats["chats"]
+            .as_array()
+            .expect("chats array")
+            .iter()
+            .find(|chat| chat["liveChatId"] == live_chat_id)
+            .and_then(|chat| chat["state"].as_str())
+            .expect("chat should be listed")
+    }
+
+    #[tokio::test]
+    async fn test_warmup_reports_steps_and_marks_chat_warm() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            repo,
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let stats = get_json(&router, "/stats").await;
+        assert_eq!(chat_state(&stats, "live-chat-id-1"), "cold");
+
+        let response = post_json(
+            &router,
+            "/warmup",
+            serde_json::json!({
+                "liveChatIds": ["live-chat-id-1"],
+                "streams": 2,
+                "preloadHistory": true,
+                "holdSeconds": 1
+            }),
+        )
+        .await;
+        let steps: Vec<&str> = response["steps"]
+            .as_array()
+            .expect("steps array")
+            .iter()
+            .map(|step| step["step"].as_str().expect("step name"))
+            .collect();
+        assert_eq!(steps, vec!["dispatcher", "preloadHistory", "openStreams"]);
+
+        let stats = get_json(&router, "/stats").await;
+        assert_eq!(chat_state(&stats, "live-chat-id-1"), "warm");
+        let chat = stats["chats"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|chat| chat["liveChatId"] == "live-chat-id-1")
+            .unwrap();
+        assert_eq!(chat["activeWarmupStreams"], 2);
+        assert_eq!(chat["messageCount"], 5);
+        assert_eq!(chat["preloadedMessages"], 5);
+    }
+
+    #[tokio::test]
+    async fn test_first_stream_after_warmup_is_not_slower_than_cold() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::new(domain::StreamRegistry::default()),
+        );
+
+        async fn time_to_first_message(
+            service: &live_chat_service::LiveChatService,
+            live_chat_id: &str,
+        ) -> std::time::Duration {
+            let start = std::time::Instant::now();
+            let request = tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some(live_chat_id.to_string()),
+                ..Default::default()
+            });
+            let mut stream = service
+                .stream_list(request)
+                .await
+                .expect("Stream should open")
+                .into_inner();
+            stream
+                .next()
+                .await
+                .expect("Stream should yield")
+                .expect("First message should be Ok");
+            start.elapsed()
+        }
+
+        // The same chat is measured before and after its warm-up
+        let cold = time_to_first_message(&service, "live-chat-id-1").await;
+
+        post_json(
+            &router,
+            "/warmup",
+            serde_json::json!({
+                "liveChatIds": ["live-chat-id-1"],
+                "streams": 1,
+                "preloadHistory": true,
+                "holdSeconds": 1
+            }),
+        )
+        .await;
+        let warm = time_to_first_message(&service, "live-chat-id-1").await;
+
+        // Generous tolerance: only guard against warm-up making the first stream slower
+        let tolerance = std::time::Duration::from_millis(250);
+        assert!(
+            warm <= cold + tolerance,
+            "warm first message ({warm:?}) should not be slower than cold ({cold:?})"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backend_errors_return_500() {
+        let router = create_router(
+            Arc::new(datastore::FailingRepository),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/chat_messages/generate")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"liveChatId": "test-chat-id"}"#))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = read_json(response).await;
+        assert_eq!(body["success"], false);
+
+        let request = Request::builder()
+            .uri("/stats")
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_create_chat_message_defaults_is_verified_to_false() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let message = |id: &str| {
+            serde_json::json!({
+                "id": id,
+                "liveChatId": "verified-chat",
+                "authorChannelId": "channel-1",
+                "authorDisplayName": "Author",
+                "messageText": "hello",
+            })
+        };
+        post_json(&router, "/chat_messages", message("unverified")).await;
+
+        let mut explicit = message("verified");
+        explicit["isVerified"] = serde_json::json!(true);
+        post_json(&router, "/chat_messages", explicit).await;
+
+        let messages = repo.get_chat_messages("verified-chat").unwrap();
+        assert!(!messages[0].is_verified);
+        assert!(messages[1].is_verified);
+    }
+
+    #[tokio::test]
+    async fn test_create_chat_message_sets_author_roles_on_the_stream() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let message = |id: &str| {
+            serde_json::json!({
+                "id": id,
+                "liveChatId": "roles-chat",
+                "authorChannelId": "channel-1",
+                "authorDisplayName": "Author",
+                "messageText": "hello",
+            })
+        };
+        post_json(&router, "/chat_messages", message("viewer")).await;
+        let mut moderator = message("moderator");
+        moderator["isChatModerator"] = serde_json::json!(true);
+        moderator["isChatSponsor"] = serde_json::json!(true);
+        moderator["profileImageUrl"] = serde_json::json!("https://example.com/avatar.png");
+        post_json(&router, "/chat_messages", moderator).await;
+        let mut owner = message("owner");
+        owner["isChatOwner"] = serde_json::json!(true);
+        post_json(&router, "/chat_messages", owner).await;
+
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            streams,
+        );
+        let mut stream = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("roles-chat".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        let response = stream.next().await.unwrap().unwrap();
+        let authors: Vec<_> = response
+            .items
+            .into_iter()
+            .map(|item| item.author_details.unwrap())
+            .collect();
+        let roles = |author: &live_chat_service::proto::LiveChatMessageAuthorDetails| {
+            (
+                author.is_chat_owner.unwrap(),
+                author.is_chat_moderator.unwrap(),
+                author.is_chat_sponsor.unwrap(),
+            )
+        };
+        assert_eq!(roles(&authors[0]), (false, false, false));
+        assert_eq!(roles(&authors[1]), (false, true, true));
+        assert_eq!(roles(&authors[2]), (true, false, false));
+
+        // Without a URL the avatar is the channel's deterministic placeholder
+        assert_eq!(
+            authors[0].profile_image_url.as_deref(),
+            Some(domain::authors::profile_image_url("channel-1").as_str())
+        );
+        assert_eq!(
+            authors[1].profile_image_url.as_deref(),
+            Some("https://example.com/avatar.png")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_state_snapshot_aggregates_videos_chats_and_streams() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::clone(&streams),
+        );
+
+        let mut stream = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        stream.next().await.unwrap().unwrap();
+
+        let state = get_json(&router, "/state").await;
+        assert!(state["capturedAt"].is_string());
+        assert!(
+            state["videos"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|video| video["id"] == "test-video-1")
+        );
+        assert_eq!(state["activeStreams"], 1);
+        assert!(state["tokens"]["tracked"].is_number());
+
+        let chats = state["chats"].as_array().unwrap();
+        let chat = |id: &str| {
+            chats
+                .iter()
+                .find(|chat| chat["liveChatId"] == id)
+                .unwrap_or_else(|| panic!("{id} should be listed"))
+                .clone()
+        };
+        // Chats without a video are listed too
+        let streamed = chat("test-chat-id");
+        assert_eq!(streamed["messageCount"], 5);
+        assert_eq!(streamed["activeStreams"], 1);
+        assert_eq!(streamed["state"], "active");
+        assert_eq!(streamed["warm"], false);
+        assert_eq!(chat("live-chat-id-1")["activeStreams"], 0);
+
+        // Closing through the control API is recorded as the close reason
+        let closed = post_json(
+            &router,
+            "/live_chats/test-chat-id/close_streams",
+            serde_json::json!({}),
+        )
+        .await;
+        assert_eq!(closed["closed"], 1);
+        let ended = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+            .await
+            .expect("Killed stream should end");
+        assert!(ended.is_none());
+        drop(stream);
+
+        let stats = get_json(&router, "/stats").await;
+        assert_eq!(stats["closedStreams"]["killed_via_control"], 1);
+        let state = get_json(&router, "/state").await;
+        assert_eq!(state["activeStreams"], 0);
+        assert_eq!(state["closedStreams"]["killed_via_control"], 1);
+        let timeline = state["streamTimeline"].as_array().unwrap();
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0]["liveChatId"], "test-chat-id");
+        assert_eq!(timeline[0]["reason"], "killed_via_control");
+        assert!(timeline[0]["closedAt"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_audit_covers_reconnects_and_flags_duplicates() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let faults = Arc::new(domain::FaultConfig::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::clone(&faults),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::clone(&streams),
+        )
+        .with_faults(Arc::clone(&faults));
+        let open = |page_token: Option<String>| {
+            service.stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("audit-chat".to_string()),
+                page_token,
+                ..Default::default()
+            }))
+        };
+        // Deliveries are recorded once sent, so the client may read them a moment earlier
+        let audit_when = |done: fn(&serde_json::Value) -> bool| {
+            let router = router.clone();
+            async move {
+                for _ in 0..100 {
+                    let audit = get_json(&router, "/live_chats/audit-chat/delivery_audit").await;
+                    if done(&audit) {
+                        return audit;
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                }
+                panic!("Audit did not reach the expected state");
+            }
+        };
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/live_chats/audit-chat/delivery_audit")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        for i in 0..5 {
+            post_json(
+                &router,
+                "/chat_messages",
+                serde_json::json!({
+                    "id": format!("audit-{i}"),
+                    "liveChatId": "audit-chat",
+                    "authorChannelId": "channel",
+                    "messageText": format!("message {i}"),
+                }),
+            )
+            .await;
+        }
+        post_json(
+            &router,
+            "/live_chats/audit-chat/delivery_audit",
+            serde_json::json!({}),
+        )
+        .await;
+
+        // The first stream fails after two messages
+        post_json(
+            &router,
+            "/faults",
+            serde_json::json!({"target": "liveChat.streamList", "count": 1, "afterMessages": 2}),
+        )
+        .await;
+        let mut first = open(None).await.expect("Stream should open").into_inner();
+        let partial = first.next().await.unwrap().expect("First response");
+        assert_eq!(partial.items.len(), 2);
+        assert!(first.next().await.unwrap().is_err());
+        let resume_token = partial.next_page_token;
+
+        // The client reconnects from its last token and gets the rest
+        let mut second = open(resume_token.clone())
+            .await
+            .expect("Stream should reopen")
+            .into_inner();
+        let rest = second.next().await.unwrap().expect("Resumed response");
+        assert_eq!(rest.items.len(), 3);
+        drop(second);
+
+        let audit = audit_when(|audit| audit["summary"]["complete"] == true).await;
+        assert_eq!(audit["summary"]["messages"], 5);
+        assert_eq!(audit["summary"]["delivered"], 5);
+        assert!(
+            audit["summary"]["undeliveredIds"]
+                .as_array()
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            audit["summary"]["duplicates"]
+                .as_object()
+                .unwrap()
+                .is_empty()
+        );
+        let records = audit["records"].as_array().unwrap();
+        assert_eq!(records.len(), 5);
+        assert_ne!(records[0]["streamId"], records[4]["streamId"]);
+
+        // Resuming from a stale token delivers the same messages again
+        let mut stale = open(resume_token)
+            .await
+            .expect("Stream should reopen")
+            .into_inner();
+        assert_eq!(stale.next().await.unwrap().unwrap().items.len(), 3);
+        drop(stale);
+
+        let audit = audit_when(|audit| {
+            audit["summary"]["duplicates"]
+                .as_object()
+                .is_some_and(|duplicates| duplicates.len() == 3)
+        })
+        .await;
+        assert_eq!(audit["summary"]["duplicates"]["audit-2"], 2);
+        assert_eq!(audit["deliveries"]["audit-0"], 1);
+        assert_eq!(audit["deliveries"]["audit-4"], 2);
+
+        // Enabling again resets the audit, disabling discards it
+        post_json(
+            &router,
+            "/live_chats/audit-chat/delivery_audit",
+            serde_json::json!({}),
+        )
+        .await;
+        let audit = get_json(&router, "/live_chats/audit-chat/delivery_audit").await;
+        assert_eq!(audit["summary"]["delivered"], 0);
+        assert_eq!(
+            audit["summary"]["undeliveredIds"].as_array().unwrap().len(),
+            5
+        );
+
+        let request = Request::builder()
+            .method(Method::DELETE)
+            .uri("/live_chats/audit-chat/delivery_audit")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!streams.audits().any_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_scenarios_insert_on_schedule_interleave_and_cancel() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let scenario = |relative_to: &str, messages: serde_json::Value| {
+            serde_json::json!({
+                "liveChatId": "scenario-chat",
+                "relativeTo": relative_to,
+                "messages": messages,
+            })
+        };
+        let text = |delay_ms: u64, text: &str| serde_json::json!({"delayMs": delay_ms, "messageText": text});
+        let wait_until_done = |id: String| {
+            let router = router.clone();
+            async move {
+                for _ in 0..200 {
+                    let report = get_json(&router, &format!("/scenarios/{id}")).await;
+                    if report["state"] != "running" {
+                        return report;
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                }
+                panic!("Scenario {id} did not finish");
+            }
+        };
+
+        // Two scenarios on a chat that does not exist yet interleave by time
+        let first = post_json(
+            &router,
+            "/scenarios",
+            scenario(
+                "start",
+                serde_json::json!([text(0, "a0"), text(400, "a2"), text(200, "a1")]),
+            ),
+        )
+        .await;
+        assert_eq!(first["state"], "running");
+        assert_eq!(first["total"], 3);
+        let second = post_json(
+            &router,
+            "/scenarios",
+            scenario(
+                "previous",
+                serde_json::json!([text(100, "b0"), text(200, "b1")]),
+            ),
+        )
+        .await;
+
+        let first = wait_until_done(first["id"].as_str().unwrap().to_string()).await;
+        let second = wait_until_done(second["id"].as_str().unwrap().to_string()).await;
+        assert_eq!(first["state"], "completed");
+        assert_eq!(first["inserted"], 3);
+        assert!(first.get("nextMessageAt").is_none());
+        assert_eq!(second["state"], "completed");
+        let texts: Vec<_> = repo
+            .get_chat_messages("scenario-chat")
+            .unwrap()
+            .into_iter()
+            .map(|message| message.message_text)
+            .collect();
+        assert_eq!(texts, ["a0", "b0", "a1", "b1", "a2"]);
+
+        // Cancelling stops the messages not inserted yet
+        let slow = post_json(
+            &router,
+            "/scenarios",
+            scenario(
+                "start",
+                serde_json::json!([text(0, "now"), text(60_000, "never")]),
+            ),
+        )
+        .await;
+        let id = slow["id"].as_str().unwrap().to_string();
+        for _ in 0..200 {
+            if get_json(&router, &format!("/scenarios/{id}")).await["inserted"] == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let running = get_json(&router, &format!("/scenarios/{id}")).await;
+        assert_eq!(running["inserted"], 1);
+        assert!(running["nextMessageAt"].is_string());
+
+        let request = Request::builder()
+            .method(Method::DELETE)
+            .uri(format!("/scenarios/{id}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let cancelled = read_json(response).await;
+        assert_eq!(cancelled["state"], "cancelled");
+        assert_eq!(cancelled["inserted"], 1);
+        assert_eq!(repo.get_chat_messages("scenario-chat").unwrap().len(), 6);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/scenarios/scenario-unknown")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_generators_insert_until_count_stop_and_shutdown() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let shutdown = CancellationToken::new();
+        let router = router_with_state(
+            ControlState::new(
+                Arc::clone(&repo),
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            )
+            .with_shutdown(shutdown.clone()),
+        );
+        let generator_state = |id: String| {
+            let router = router.clone();
+            async move {
+                let list = get_json(&router, "/generators").await;
+                list["generators"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|generator| generator["id"] == id.as_str())
+                    .cloned()
+            }
+        };
+        // A bounded generator completes after `count` messages with ordered IDs
+        let bounded = post_json(
+            &router,
+            "/generators",
+            serde_json::json!({
+                "liveChatId": "generated-chat",
+                "intervalMs": 10,
+                "count": 3,
+                "textTemplate": "load {seq}",
+            }),
+        )
+        .await;
+        assert_eq!(bounded["state"], "running");
+        assert_eq!(bounded["produced"], 0);
+        let bounded_id = bounded["id"].as_str().unwrap().to_string();
+        let mut report = serde_json::Value::Null;
+        for _ in 0..300 {
+            report = generator_state(bounded_id.clone()).await.unwrap();
+            if report["state"] != "running" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(report["state"], "completed");
+        assert_eq!(report["produced"], 3);
+        let messages = repo.get_chat_messages("generated-chat").unwrap();
+        let texts: Vec<_> = messages.iter().map(|m| m.message_text.as_str()).collect();
+        assert_eq!(texts, ["load 1", "load 2", "load 3"]);
+        let ids: Vec<_> = messages.iter().map(|m| m.id.clone()).collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+
+        // Deleting an unbounded generator stops it
+        let unbounded = post_json(
+            &router,
+            "/generators",
+            serde_json::json!({"liveChatId": "generated-chat", "intervalMs": 10}),
+        )
+        .await;
+        let unbounded_id = unbounded["id"].as_str().unwrap().to_string();
+        for _ in 0..300 {
+            let report = generator_state(unbounded_id.clone()).await.unwrap();
+            if report["produced"].as_u64().unwrap() >= 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(Method::DELETE)
+                    .uri(format!("/generators/{unbounded_id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let stopped = read_json(response).await;
+        assert_eq!(stopped["state"], "stopped");
+        let produced = repo.get_chat_messages("generated-chat").unwrap().len();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(
+            repo.get_chat_messages("generated-chat").unwrap().len(),
+            produced
+        );
+        assert!(generator_state(unbounded_id.clone()).await.is_none());
+
+        // Shutting down stops the running generators
+        let slow = post_json(
+            &router,
+            "/generators",
+            serde_json::json!({"liveChatId": "generated-chat", "intervalMs": 60_000}),
+        )
+        .await;
+        let slow_id = slow["id"].as_str().unwrap().to_string();
+        shutdown.cancel();
+        for _ in 0..300 {
+            if generator_state(slow_id.clone()).await.unwrap()["state"] == "stopped" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(generator_state(slow_id).await.unwrap()["state"], "stopped");
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(Method::DELETE)
+                    .uri("/generators/generator-unknown")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/generators")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({"liveChatId": "generated-chat", "intervalMs": 0})
+                            .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_message_text_longer_than_the_limit_is_rejected() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = router_with_state(
+            ControlState::new(
+                Arc::clone(&repo),
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            )
+            .with_max_text_len(Some(5)),
+        );
+        let create = |id: &str, text: &str| {
+            let message = serde_json::json!({
+                "id": id,
+                "liveChatId": "limited-chat",
+                "authorChannelId": "channel",
+                "messageText": text,
+            });
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/chat_messages")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(message.to_string()))
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+
+        // Exactly at the limit, counted in characters rather than bytes
+        let response = create("at-limit", "héllo").await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = create("over-limit", "héllo!").await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = read_json(response).await;
+        assert_eq!(
+            body["error"],
+            "invalidValue: messageText is 6 characters long, the maximum is 5"
+        );
+
+        let messages = repo.get_chat_messages("limited-chat").unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "at-limit");
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_chat_message_id_conflicts_when_unique() {
+        let repo: Arc<dyn datastore::Repository> =
+            Arc::new(datastore::InMemoryRepository::new().with_unique_message_ids(true));
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let message = serde_json::json!({
+            "id": "fixture-msg",
+            "liveChatId": "fixture-chat",
+            "authorChannelId": "channel",
+            "authorDisplayName": "Author",
+            "messageText": "hello",
+        });
+
+        post_json(&router, "/chat_messages", message.clone()).await;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/chat_messages")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(message.to_string()))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = read_json(response).await;
+        assert_eq!(
+            body["error"],
+            "Chat message 'fixture-msg' already exists in live chat 'fixture-chat'"
+        );
+        assert_eq!(repo.get_chat_messages("fixture-chat").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ending_a_video_closes_its_chat_streams() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::clone(&streams),
+        );
+        let open = || {
+            service.stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("live-chat-id-1".to_string()),
+                ..Default::default()
+            }))
+        };
+
+        let mut stream = open().await.expect("Stream should open").into_inner();
+        let backlog = stream.next().await.unwrap().unwrap();
+        assert_eq!(backlog.items.len(), 5);
+
+        let ended = post_json(&router, "/videos/test-video-1/end", serde_json::json!({})).await;
+        assert_eq!(ended["liveChat"]["state"], "ended");
+        let offline_at: DateTime<Utc> =
+            serde_json::from_value(ended["liveChat"]["offlineAt"].clone()).unwrap();
+        let video = repo.get_video("test-video-1").unwrap().unwrap();
+        assert_eq!(video.actual_end_time, Some(offline_at));
+
+        // The open stream gets a terminal response and closes
+        let timeout = std::time::Duration::from_secs(5);
+        let terminal = tokio::time::timeout(timeout, stream.next())
+            .await
+            .expect("Terminal response should arrive")
+            .unwrap()
+            .unwrap();
+        assert!(terminal.items.is_empty());
+        assert_eq!(
+            terminal.offline_at,
+            Some(offline_at.to_rfc3339()),
+            "Terminal response should carry the end time"
+        );
+        assert!(terminal.next_page_token.is_none());
+        let end = tokio::time::timeout(timeout, stream.next()).await.unwrap();
+        assert!(
+            end.is_none(),
+            "Stream should close after the terminal response"
+        );
+        assert_eq!(streams.closed_counts()["chat_ended"], 1);
+
+        // New streams get the backlog and the terminal response
+        let responses: Vec<_> = open()
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .collect()
+            .await;
+        assert_eq!(responses.len(), 2);
+        assert!(responses[1].as_ref().unwrap().offline_at.is_some());
+
+        // Ending again keeps the original end time
+        let again = post_json(&router, "/videos/test-video-1/end", serde_json::json!({})).await;
+        assert_eq!(
+            serde_json::from_value::<DateTime<Utc>>(again["liveChat"]["offlineAt"].clone())
+                .unwrap(),
+            offline_at
+        );
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/videos/missing-video/end")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_reset_restores_initial_state_and_closes_streams() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let quota = Arc::new(domain::QuotaLedger::default());
+        let faults = Arc::new(domain::FaultConfig::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::clone(&quota),
+            Arc::clone(&faults),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::clone(&streams),
+        );
+
+        let mut stream = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("live-chat-id-1".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        let backlog = stream.next().await.unwrap().unwrap();
+        assert_eq!(backlog.items.len(), 5);
+
+        post_json(
+            &router,
+            "/chat_messages",
+            serde_json::json!({
+                "id": "injected",
+                "liveChatId": "live-chat-id-1",
+                "authorChannelId": "channel",
+                "authorDisplayName": "Author",
+                "messageText": "injected"
+            }),
+        )
+        .await;
+        post_json(
+            &router,
+            "/generators",
+            serde_json::json!({ "liveChatId": "generated-chat", "intervalMs": 60000 }),
+        )
+        .await;
+        post_json(
+            &router,
+            "/faults",
+            serde_json::json!({ "target": "videos.list", "count": 1 }),
+        )
+        .await;
+        quota
+            .charge(domain::QuotaEndpoint::VideosList, "key")
+            .unwrap();
+
+        let reset = post_json(&router, "/reset", serde_json::json!({})).await;
+        assert_eq!(reset["success"], true);
+        assert_eq!(reset["closedStreams"], 1);
+        assert_eq!(reset["stoppedGenerators"], 1);
+
+        // The open stream ends instead of paging on from its old position
+        let timeout = std::time::Duration::from_secs(5);
+        while let Some(next) = tokio::time::timeout(timeout, stream.next())
+            .await
+            .expect("Stream should close after the reset")
+        {
+            assert!(next.is_ok(), "Stream should end cleanly, got {next:?}");
+        }
+        assert_eq!(streams.closed_counts()["killed_via_control"], 1);
+
+        // The dummy data is back, everything added at runtime is gone
+        assert_eq!(repo.get_chat_messages("live-chat-id-1").unwrap().len(), 5);
+        assert!(repo.get_video("test-video-1").unwrap().is_some());
+        let generators = get_json(&router, "/generators").await;
+        assert!(generators["generators"].as_array().unwrap().is_empty());
+        assert!(faults.list().is_empty());
+        assert_eq!(quota.report(None).calls, 0);
+
+        let reset = post_json(&router, "/reset?empty=true", serde_json::json!({})).await;
+        assert_eq!(reset["closedStreams"], 0);
+        assert!(repo.get_videos().unwrap().is_empty());
+        assert!(repo.get_live_chat_ids().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reseed_keeps_streams_of_remaining_chats_open() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::clone(&streams),
+        );
+        let open = |live_chat_id: &str| {
+            service.stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some(live_chat_id.to_string()),
+                ..Default::default()
+            }))
+        };
+        let mut kept = open("live-chat-id-1").await.unwrap().into_inner();
+        let mut removed = open("test-chat-id").await.unwrap().into_inner();
+        assert_eq!(kept.next().await.unwrap().unwrap().items.len(), 5);
+        assert_eq!(removed.next().await.unwrap().unwrap().items.len(), 5);
+
+        let message = |id: &str| {
+            serde_json::json!({
+                "id": id,
+                "live_chat_id": "live-chat-id-1",
+                "author_channel_id": "channel",
+                "author_display_name": "Author",
+                "message_text": id,
+                "published_at": "2024-01-01T00:00:00Z",
+                "is_verified": false
+            })
+        };
+        let video = serde_json::json!({
+            "id": "test-video-1",
+            "channel_id": "channel-1",
+            "title": "Reseeded",
+            "description": "",
+            "channel_title": "Mock Channel",
+            "published_at": "2024-01-01T00:00:00Z",
+            "live_chat_id": "live-chat-id-1",
+            "actual_start_time": "2024-01-01T00:00:00Z"
+        });
+        let seed = serde_json::json!({
+            "videos": [video],
+            "chat_messages": [message("msg-id-4"), message("fresh")]
+        });
+        let reseeded = post_json(&router, "/reseed", seed).await;
+        assert_eq!(reseeded["success"], true);
+        assert_eq!(reseeded["chatMessages"], 2);
+        assert_eq!(reseeded["keptStreams"], 1);
+        assert_eq!(reseeded["endedStreams"], 1);
+        assert_eq!(
+            repo.get_video("test-video-1").unwrap().unwrap().title,
+            "Reseeded"
+        );
+
+        // The kept stream continues after msg-id-4, which the new data still has
+        let timeout = std::time::Duration::from_secs(5);
+        let next = tokio::time::timeout(timeout, kept.next())
+            .await
+            .expect("Kept stream should continue")
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.items.len(), 1);
+        assert_eq!(next.items[0].id(), "fresh");
+
+        // The stream of the removed chat ends with chat_ended
+        while tokio::time::timeout(timeout, removed.next())
+            .await
+            .expect("Removed stream should end")
+            .is_some()
+        {}
+        assert_eq!(streams.closed_counts()["chat_ended"], 1);
+
+        // Invalid seed data is rejected before anything is replaced
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/reseed")
+            .body(Body::from(r#"{"chat_messages": [{"id": "no-chat"}]}"#))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(repo.get_chat_messages("live-chat-id-1").unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_starting_an_upcoming_video_opens_its_chat() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let mut video = repo.get_video("test-video-1").unwrap().unwrap();
+        video.scheduled_start_time = Some(Utc::now() + chrono::Duration::hours(1));
+        video.actual_start_time = None;
+        repo.add_video(video).unwrap();
+        assert!(
+            repo.live_chat_not_started("live-chat-id-1", Utc::now())
+                .unwrap()
+        );
+
+        let started = post_json(&router, "/videos/test-video-1/start", serde_json::json!({})).await;
+        assert_eq!(started["liveChat"]["state"], "active");
+        let started_at: DateTime<Utc> =
+            serde_json::from_value(started["liveChat"]["actualStartTime"].clone()).unwrap();
+        let video = repo.get_video("test-video-1").unwrap().unwrap();
+        assert_eq!(video.actual_start_time, Some(started_at));
+        assert!(
+            !repo
+                .live_chat_not_started("live-chat-id-1", Utc::now())
+                .unwrap()
+        );
+
+        // Starting again keeps the original start time
+        let again = post_json(&router, "/videos/test-video-1/start", serde_json::json!({})).await;
+        assert_eq!(
+            again["liveChat"]["actualStartTime"],
+            started["liveChat"]["actualStartTime"]
+        );
+
+        // An ended broadcast cannot be started again
+        post_json(&router, "/videos/test-video-1/end", serde_json::json!({})).await;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/videos/test-video-1/start")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_injected_message_reaches_open_stream_without_polling_delay() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            streams,
+        );
+
+        let mut stream = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        // Backlog first, then let the stream go idle
+        stream.next().await.unwrap().unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        for i in 0..3 {
+            let injected_at = std::time::Instant::now();
+            post_json(
+                &router,
+                "/chat_messages",
+                serde_json::json!({
+                    "id": format!("latency-{i}"),
+                    "liveChatId": "test-chat-id",
+                    "authorChannelId": "latency-channel",
+                    "authorDisplayName": "Latency",
+                    "messageText": "ping",
+                }),
+            )
+            .await;
+            let response = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+                .await
+                .expect("Message should be delivered")
+                .unwrap()
+                .unwrap();
+            let latency = injected_at.elapsed();
+
+            assert_eq!(
+                response.items[0].id.as_deref(),
+                Some(format!("latency-{i}").as_str())
+            );
+            assert!(
+                latency < std::time::Duration::from_millis(200),
+                "Delivery took {latency:?}, expected well under the old 1s polling floor"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_video_rejects_inconsistent_times() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let video = |id: &str, scheduled: &str, start: &str, end: &str| {
+            serde_json::json!({
+                "id": id,
+                "channelId": "channel-1",
+                "title": "Times",
+                "description": "",
+                "channelTitle": "Channel",
+                "scheduledStartTime": scheduled,
+                "actualStartTime": start,
+                "actualEndTime": end,
+            })
+        };
+
+        // Equal times are consistent
+        let at = "2024-01-01T12:00:00Z";
+        post_json(&router, "/videos", video("same-instant", at, at, at)).await;
+
+        for (id, scheduled, start, end) in [
+            (
+                "late-schedule",
+                "2024-01-01T13:00:00Z",
+                at,
+                "2024-01-01T14:00:00Z",
+            ),
+            (
+                "early-end",
+                "2024-01-01T11:00:00Z",
+                at,
+                "2024-01-01T11:30:00Z",
+            ),
+        ] {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/videos")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(video(id, scheduled, start, end).to_string()))
+                .expect("Valid request");
+            let response = router.clone().oneshot(request).await.expect("Response");
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{id}");
+            let body = read_json(response).await;
+            assert_eq!(body["success"], false);
+            assert!(repo.get_video(id).unwrap().is_none(), "{id}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_realistic_chat_ids_are_generated_for_videos_without_one() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = router_with_state(
+            ControlState::new(
+                Arc::clone(&repo),
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            )
+            .with_realistic_chat_ids(true),
+        );
+        let video = |id: &str, live_chat_id: Option<&str>| {
+            let mut video = serde_json::json!({
+                "id": id,
+                "channelId": "UCrealistic",
+                "title": "Realistic",
+                "description": "",
+                "channelTitle": "Channel",
+            });
+            if let Some(live_chat_id) = live_chat_id {
+                video["liveChatId"] = live_chat_id.into();
+            }
+            video
+        };
+
+        let created = post_json(&router, "/videos", video("abcdefghijk", None)).await;
+        let live_chat_id = repo
+            .get_video("abcdefghijk")
+            .unwrap()
+            .unwrap()
+            .live_chat_id
+            .expect("A chat ID should be generated");
+        assert!(live_chat_id.starts_with("Cg0KC"), "{live_chat_id}");
+        assert!(created["message"].as_str().unwrap().contains(&live_chat_id));
+
+        // Recreating the video keeps its chat ID; explicit IDs are kept as given
+        post_json(&router, "/videos", video("abcdefghijk", None)).await;
+        let recreated = repo.get_video("abcdefghijk").unwrap().unwrap();
+        assert_eq!(recreated.live_chat_id, Some(live_chat_id));
+        post_json(&router, "/videos", video("explicit", Some("explicit-chat"))).await;
+        let explicit = repo.get_video("explicit").unwrap().unwrap();
+        assert_eq!(explicit.live_chat_id.as_deref(), Some("explicit-chat"));
+    }
+
+    #[tokio::test]
+    async fn test_live_chat_lifecycle_from_a_single_stream() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+        use std::time::Duration;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::new(domain::StreamRegistry::default()),
+        );
+
+        post_json(
+            &router,
+            "/videos",
+            serde_json::json!({
+                "id": "lifecycle-video",
+                "channelId": "channel-1",
+                "title": "Upcoming",
+                "description": "",
+                "channelTitle": "Channel",
+                "liveChatId": "lifecycle-chat",
+            }),
+        )
+        .await;
+        let scheduled_start = "2030-01-01T00:00:00Z";
+        let created = post_json(
+            &router,
+            "/live_chats",
+            serde_json::json!({"id": "lifecycle-chat", "scheduledStartTime": scheduled_start}),
+        )
+        .await;
+        assert_eq!(created["liveChat"]["state"], "scheduled");
+
+        // Messages injected before the start are not delivered while scheduled
+        post_json(
+            &router,
+            "/chat_messages/generate",
+            serde_json::json!({"liveChatId": "lifecycle-chat"}),
+        )
+        .await;
+
+        let mut stream = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("lifecycle-chat".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        async fn next<S: tokio_stream::Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+            tokio::time::timeout(Duration::from_secs(5), stream.next())
+                .await
+                .expect("Stream response in time")
+        }
+
+        let waiting = next(&mut stream).await.unwrap().unwrap();
+        assert!(waiting.items.is_empty());
+        assert!(waiting.offline_at.is_none());
+
+        let started = post_json(
+            &router,
+            "/videos/lifecycle-video/transition",
+            serde_json::json!({"broadcastStatus": "live"}),
+        )
+        .await;
+        assert_eq!(started["liveChat"]["state"], "active");
+        let live = next(&mut stream).await.unwrap().unwrap();
+        assert_eq!(live.items.len(), 1);
+
+        post_json(
+            &router,
+            "/live_chats/lifecycle-chat/transition",
+            serde_json::json!({"state": "ended"}),
+        )
+        .await;
+        let terminal = next(&mut stream).await.unwrap().unwrap();
+        assert!(terminal.items.is_empty());
+        assert!(terminal.offline_at.is_some());
+        assert!(terminal.next_page_token.is_none());
+        assert!(
+            next(&mut stream).await.is_none(),
+            "Stream should close after the chat ends"
+        );
+
+        let video = repo.get_video("lifecycle-video").unwrap().unwrap();
+        assert_eq!(
+            video.scheduled_start_time.map(|t| t.to_rfc3339()),
+            Some("2030-01-01T00:00:00+00:00".to_string())
+        );
+        assert!(video.actual_start_time.is_some());
+        assert!(video.actual_end_time.is_some());
+
+        // Ended chats cannot be restarted
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/live_chats/lifecycle-chat/transition")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"state": "active"}"#))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_quota_report_and_reset() {
+        let quota = Arc::new(domain::QuotaLedger::default());
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::clone(&quota),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        for (endpoint, key) in [
+            (domain::QuotaEndpoint::VideosList, "key-a"),
+            (domain::QuotaEndpoint::LiveChatMessagesList, "key-a"),
+            (domain::QuotaEndpoint::LiveChatMessagesStreamList, "oauth"),
+        ] {
+            quota.charge(endpoint, key).unwrap();
+        }
+
+        let report = get_json(&router, "/quota/report?windowSeconds=3600").await;
+        assert_eq!(report["calls"], 3);
+        assert_eq!(report["units"], 11);
+        assert_eq!(report["dailyLimit"], 10_000);
+        assert!(report["since"].is_string());
+        assert_eq!(report["byEndpoint"]["videos.list"]["units"], 1);
+        assert_eq!(report["byKey"]["key-a"]["units"], 6);
+        assert_eq!(report["byKey"]["key-a"]["remaining"], 9_994);
+        assert_eq!(
+            report["byKey"]["oauth"]["byEndpoint"]["liveChatMessages.streamList"]["calls"],
+            1
+        );
+
+        let reset = post_json(&router, "/quota/reset", serde_json::json!({})).await;
+        assert_eq!(reset["success"], true);
+        let report = get_json(&router, "/quota/report").await;
+        assert_eq!(report["units"], 0);
+        assert!(report["since"].is_null());
+        assert_eq!(report["byKey"], serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_quota_limit_and_costs_are_configurable() {
+        let quota = Arc::new(domain::QuotaLedger::default());
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::clone(&quota),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let current = get_json(&router, "/quota").await;
+        assert_eq!(current["dailyLimit"], 10_000);
+        assert_eq!(current["enforced"], false);
+        assert_eq!(current["costs"]["search.list"], 100);
+
+        let updated = post_json(
+            &router,
+            "/quota",
+            serde_json::json!({"dailyLimit": 50, "costs": {"videos.list": 20}}),
+        )
+        .await;
+        assert_eq!(updated["dailyLimit"], 50);
+        assert_eq!(updated["enforced"], true);
+        assert_eq!(updated["costs"]["videos.list"], 20);
+        assert_eq!(updated["costs"]["liveChatMessages.list"], 5);
+
+        for _ in 0..2 {
+            quota
+                .charge(domain::QuotaEndpoint::VideosList, "key-a")
+                .unwrap();
+        }
+        assert!(
+            quota
+                .charge(domain::QuotaEndpoint::VideosList, "key-a")
+                .is_err()
+        );
+        let current = get_json(&router, "/quota").await;
+        assert_eq!(
+            current["usage"]["key-a"],
+            serde_json::json!({"units": 40, "remaining": 10})
+        );
+
+        // Costs alone keep the limit; 0 stops enforcing it
+        let updated = post_json(&router, "/quota", serde_json::json!({"dailyLimit": 0})).await;
+        assert_eq!(updated["enforced"], false);
+        assert_eq!(updated["dailyLimit"], 10_000);
+        assert_eq!(updated["costs"]["videos.list"], 20);
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/quota")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"costs": {"videos.insert": 50}}"#))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(
+            read_json(response).await["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("Unknown method 'videos.insert' in costs")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fault_configuration() {
+        let faults = Arc::new(domain::FaultConfig::default());
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::clone(&faults),
+        );
+
+        let body = post_json(
+            &router,
+            "/faults",
+            serde_json::json!({
+                "target": "liveChat.streamList",
+                "grpcCode": "RESOURCE_EXHAUSTED",
+                "probability": 0.5,
+                "afterMessages": 3,
+            }),
+        )
+        .await;
+        assert_eq!(body["faults"][0]["target"], "liveChat.streamList");
+        assert_eq!(body["faults"][0]["httpStatus"], 500);
+        assert_eq!(body["faults"][0]["afterMessages"], 3);
+        assert_eq!(faults.list()[0].grpc_code, "RESOURCE_EXHAUSTED");
+
+        for invalid in [
+            serde_json::json!({"target": "videos.insert", "count": 1}),
+            serde_json::json!({"target": "videos.list", "httpStatus": 200, "count": 1}),
+            serde_json::json!({"target": "videos.list", "grpcCode": "OK", "count": 1}),
+            serde_json::json!({"target": "videos.list"}),
+            serde_json::json!({"target": "videos.list", "count": 1, "probability": 0.5}),
+            serde_json::json!({"target": "videos.list", "probability": 1.5}),
+            serde_json::json!({"target": "videos.list", "count": 0}),
+            serde_json::json!({"target": "videos.list", "count": 1, "afterMessages": 1}),
+        ] {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/faults")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(invalid.to_string()))
+                .expect("Valid request");
+            let response = router.clone().oneshot(request).await.expect("Response");
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{invalid}");
+            assert_eq!(read_json(response).await["success"], false);
+        }
+        assert_eq!(
+            get_json(&router, "/faults").await["faults"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let request = Request::builder()
+            .method(Method::DELETE)
+            .uri("/faults")
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(faults.list().is_empty());
+    }
+
+    async fn patch_json(
+        router: &Router,
+        uri: &str,
+        if_match: Option<&str>,
+        body: serde_json::Value,
+    ) -> (StatusCode, Option<String>, serde_json::Value) {
+        let mut request = Request::builder()
+            .method(Method::PATCH)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(if_match) = if_match {
+            request = request.header(header::IF_MATCH, if_match);
+        }
+        let request = request
+            .body(Body::from(body.to_string()))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        let status = response.status();
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .map(|value| value.to_str().unwrap().to_string());
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        (
+            status,
+            etag,
+            serde_json::from_slice(&bytes).expect("JSON body"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_video_patches_are_not_lost() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let original = repo.get_video("test-video-1").unwrap().unwrap();
+        let initial_version = repo.get_video_version("test-video-1").unwrap().unwrap();
+
+        let mut patches = tokio::task::JoinSet::new();
+        for i in 0..100u64 {
+            let router = router.clone();
+            let body = match i % 4 {
+                0 => serde_json::json!({"title": format!("title {i}")}),
+                1 => serde_json::json!({"description": format!("description {i}")}),
+                2 => serde_json::json!({"channelTitle": format!("channel {i}")}),
+                _ => serde_json::json!({"concurrentViewers": 1000 + i}),
+            };
+            patches.spawn(async move {
+                let (status, _, _) = patch_json(&router, "/videos/test-video-1", None, body).await;
+                assert_eq!(status, StatusCode::OK);
+            });
+        }
+        while let Some(result) = patches.join_next().await {
+            result.expect("Patch task");
+        }
+
+        // Every field kept a patched value and every patch was applied exactly once
+        let video = repo.get_video("test-video-1").unwrap().unwrap();
+        assert!(video.title.starts_with("title "), "{}", video.title);
+        assert!(video.description.starts_with("description "));
+        assert!(video.channel_title.starts_with("channel "));
+        assert!(video.concurrent_viewers.is_some_and(|n| n >= 1000));
+        assert_eq!(video.live_chat_id, original.live_chat_id);
+        assert_eq!(
+            repo.get_video_version("test-video-1").unwrap(),
+            Some(initial_version + 100)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_if_match_rejects_stale_versions() {
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let video = get_json(&router, "/videos/test-video-1").await;
+        let version = video["version"].as_u64().unwrap();
+        let current = format!("\"{version}\"");
+
+        let (status, etag, body) = patch_json(
+            &router,
+            "/videos/test-video-1",
+            Some(&current),
+            serde_json::json!({"title": "Versioned"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["video"]["title"], "Versioned");
+        assert_eq!(body["version"], version + 1);
+        assert_eq!(etag, Some(format!("\"{}\"", version + 1)));
+
+        // The version read before the first patch is stale now
+        let (status, _, body) = patch_json(
+            &router,
+            "/videos/test-video-1",
+            Some(&current),
+            serde_json::json!({"title": "Lost"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .contains(&format!("current version {}", version + 1)),
+            "{body}"
+        );
+        let video = get_json(&router, "/videos/test-video-1").await;
+        assert_eq!(video["video"]["title"], "Versioned");
+
+        let (status, _, _) = patch_json(
+            &router,
+            "/videos/test-video-1",
+            Some("not-a-version"),
+            serde_json::json!({}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, _) = patch_json(
+            &router,
+            "/videos/missing",
+            None,
+            serde_json::json!({"title": "Nope"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Live chats without a stored lifecycle are at version 0
+        let scheduled = serde_json::json!({"scheduledStartTime": "2030-01-01T00:00:00Z"});
+        let (status, etag, body) = patch_json(
+            &router,
+            "/live_chats/settings-chat",
+            Some("\"0\""),
+            scheduled.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(etag.as_deref(), Some("\"1\""));
+        assert_eq!(
+            body["liveChat"]["scheduledStartTime"],
+            "2030-01-01T00:00:00Z"
+        );
+        let (status, _, _) = patch_json(
+            &router,
+            "/live_chats/settings-chat",
+            Some("\"0\""),
+            scheduled,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_patch_live_chat_history_retention() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let (status, _, body) = patch_json(
+            &router,
+            "/live_chats/test-chat-id",
+            None,
+            serde_json::json!({"historyRetentionSeconds": 3600}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["liveChat"]["historyRetentionSeconds"], 3600);
+        // Withheld messages stay stored
+        assert_eq!(repo.count_chat_messages("test-chat-id").unwrap(), 5);
+
+        // 0 serves the whole history again
+        let (status, _, body) = patch_json(
+            &router,
+            "/live_chats/test-chat-id",
+            None,
+            serde_json::json!({"historyRetentionSeconds": 0}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["liveChat"].get("historyRetentionSeconds").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mute_and_unmute_author() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let add_message = |id: &str| {
+            post_json(
+                &router,
+                "/chat_messages",
+                serde_json::json!({
+                    "id": id,
+                    "liveChatId": "test-chat-id",
+                    "authorChannelId": "muted-channel",
+                    "messageText": "hi",
+                }),
+            )
+        };
+        let stored = |id: &str| {
+            repo.get_chat_messages("test-chat-id")
+                .unwrap()
+                .into_iter()
+                .find(|message| message.id == id)
+                .expect("Stored message")
+        };
+
+        add_message("before-mute").await;
+        let body = post_json(
+            &router,
+            "/live_chats/test-chat-id/mutes",
+            serde_json::json!({"channelId": "muted-channel"}),
+        )
+        .await;
+        assert_eq!(
+            body["liveChat"]["mutedAuthorChannelIds"],
+            serde_json::json!(["muted-channel"])
+        );
+        add_message("while-muted").await;
+        assert!(!stored("before-mute").muted);
+        assert!(stored("while-muted").muted);
+        let state = get_json(&router, "/state").await;
+        let chat = state["chats"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|chat| chat["liveChatId"] == "test-chat-id")
+            .expect("chat should be listed");
+        assert_eq!(chat["mutedMessageCount"], 1);
+
+        // Unmuting does not reveal what was posted while muted
+        let request = Request::builder()
+            .method(Method::DELETE)
+            .uri("/live_chats/test-chat-id/mutes/muted-channel")
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        add_message("after-unmute").await;
+        assert!(stored("while-muted").muted);
+        assert!(!stored("after-unmute").muted);
+
+        let request = Request::builder()
+            .method(Method::DELETE)
+            .uri("/live_chats/test-chat-id/mutes/muted-channel")
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_rejects_writes_and_serves_reads() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = read_only(create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        ));
+        let send = |method: Method, uri: &str, body: Body| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .expect("Valid request");
+            router.clone().oneshot(request)
+        };
+
+        let body = serde_json::json!({"liveChatId": "test-chat-id", "messageText": "hi"});
+        for (method, uri) in [
+            (Method::POST, "/chat_messages"),
+            (Method::DELETE, "/chat_messages/test-msg-id-0"),
+            (Method::PATCH, "/videos/test-video-1"),
+            (Method::POST, "/quota"),
+            (Method::DELETE, "/faults"),
+        ] {
+            let response = send(method.clone(), uri, Body::from(body.to_string()))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{method} {uri}");
+            let error = read_json(response).await;
+            assert_eq!(error["success"], false);
+            assert_eq!(error["error"], READ_ONLY_ERROR);
+        }
+        assert_eq!(repo.get_chat_messages("test-chat-id").unwrap().len(), 5);
+
+        for uri in [
+            "/status",
+            "/state",
+            "/videos/test-video-1",
+            "/quota",
+            "/faults",
+        ] {
+            let response = send(Method::GET, uri, Body::empty()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "GET {uri}");
+        }
+        let response = send(Method::POST, "/no-such-endpoint", Body::empty())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_method_override_remaps_posts() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let send = |router: Router, method: Method, uri: &str, method_override: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(METHOD_OVERRIDE_HEADER, method_override)
+                .body(Body::empty())
+                .expect("Valid request");
+            router.oneshot(request)
+        };
+
+        // Without the wrapper the header is ignored
+        let response = send(
+            router.clone(),
+            Method::POST,
+            "/videos/test-video-1",
+            "DELETE",
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        // Read-only mode wraps the control router first, as in the server, and sees the
+        // overridden method
+        let response = send(
+            method_override(read_only(router.clone())),
+            Method::POST,
+            "/faults",
+            "DELETE",
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let router = method_override(router);
+        let response = send(
+            router.clone(),
+            Method::POST,
+            "/videos/test-video-1",
+            "delete",
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(repo.get_video("test-video-1").unwrap().is_none());
+
+        // Only POSTs are remapped
+        let response = send(router.clone(), Method::GET, "/status", "DELETE")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(router.clone(), Method::POST, "/faults", "NOT A METHOD")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_delete_endpoints() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let delete = |uri: &'static str| {
+            let request = Request::builder()
+                .method(Method::DELETE)
+                .uri(uri)
+                .body(Body::empty())
+                .expect("Valid request");
+            router.clone().oneshot(request)
+        };
+
+        let response = delete("/videos/test-video-1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(repo.get_video("test-video-1").unwrap().is_none());
+        let response = delete("/videos/test-video-1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = read_json(response).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"], "Video 'test-video-1' not found");
+
+        let response = delete("/chat_messages/test-msg-id-0").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let ids: Vec<_> = repo
+            .get_chat_messages("test-chat-id")
+            .unwrap()
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert!(!ids.contains(&"test-msg-id-0".to_string()));
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids[4], "test-msg-id-0-deleted");
+        let response = delete("/chat_messages/test-msg-id-0").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            read_json(response).await["error"],
+            "Chat message 'test-msg-id-0' not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleting_delivered_messages_keeps_open_stream_in_place() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let service = live_chat_service::LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            streams,
+        );
+        let ids = |response: &live_chat_service::proto::LiveChatMessageListResponse| {
+            response
+                .items
+                .iter()
+                .filter_map(|item| item.id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // Deliver the first three of the five messages
+        let mut stream = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                max_results: Some(3),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            ids(&first),
+            ["test-msg-id-0", "test-msg-id-1", "test-msg-id-2"]
+        );
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!(ids(&second), ["test-msg-id-3", "test-msg-id-4"]);
+
+        // Deleting delivered messages neither re-sends nor skips anything; each deletion
+        // arrives once as its event
+        for id in ["test-msg-id-0", "test-msg-id-3"] {
+            let request = Request::builder()
+                .method(Method::DELETE)
+                .uri(format!("/chat_messages/{id}"))
+                .body(Body::empty())
+                .expect("Valid request");
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        post_json(
+            &router,
+            "/chat_messages",
+            serde_json::json!({
+                "id": "after-delete",
+                "liveChatId": "test-chat-id",
+                "authorChannelId": "channel",
+                "authorDisplayName": "Tester",
+                "messageText": "still here",
+            }),
+        )
+        .await;
+        let mut delivered = Vec::new();
+        while !delivered.iter().any(|id| id == "after-delete") {
+            let next = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+                .await
+                .expect("New message should be delivered")
+                .unwrap()
+                .unwrap();
+            delivered.extend(ids(&next));
+        }
+        assert_eq!(
+            delivered,
+            [
+                "test-msg-id-0-deleted",
+                "test-msg-id-3-deleted",
+                "after-delete"
+            ]
+        );
+
+        // Resuming from the first batch's token continues after the deleted gap
+        let mut resumed = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                page_token: first.next_page_token.clone(),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        let response = resumed.next().await.unwrap().unwrap();
+        assert_eq!(
+            ids(&response),
+            [
+                "test-msg-id-4",
+                "test-msg-id-0-deleted",
+                "test-msg-id-3-deleted",
+                "after-delete"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_fields_warn_by_default_and_fail_when_strict() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let body = serde_json::json!({
+            "liveChatId": "test-chat-id",
+            "messageText": "typo",
+            "authorDisplayname": "Misspelled",
+        });
+
+        let response = post_json(&router, "/chat_messages/generate", body.clone()).await;
+        assert_eq!(response["success"], true);
+        assert_eq!(
+            response["warnings"],
+            serde_json::json!(["Unknown field 'authorDisplayname' was ignored"])
+        );
+        let count = repo.get_chat_messages("test-chat-id").unwrap().len();
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/chat_messages/generate?strict=true")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let error = read_json(response).await;
+        assert_eq!(error["success"], false);
+        assert_eq!(
+            error["error"],
+            "Unknown fields in request body: authorDisplayname"
+        );
+        assert_eq!(repo.get_chat_messages("test-chat-id").unwrap().len(), count);
+
+        // Known fields only: no warnings
+        let response = post_json(
+            &router,
+            "/chat_messages/generate?strict=true",
+            serde_json::json!({"liveChatId": "test-chat-id"}),
+        )
+        .await;
+        assert!(response.get("warnings").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_super_chat_is_streamed_as_super_chat_event() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::live_chat_message_snippet::{
+            DisplayedContent, type_wrapper::Type,
+        };
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        post_json(
+            &router,
+            "/chat_messages",
+            serde_json::json!({
+                "id": "super-chat-1",
+                "liveChatId": "super-chat-id",
+                "authorChannelId": "channel",
+                "authorDisplayName": "Patron",
+                "messageText": "Keep it up!",
+                "superChatDetails": {"amountMicros": 5_000_000, "currency": "USD", "tier": 2},
+            }),
+        )
+        .await;
+
+        let service = live_chat_service::LiveChatService::new(
+            repo,
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            streams,
+        );
+        let response = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("super-chat-id".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        let snippet = response.items[0].snippet.clone().unwrap();
+        assert_eq!(snippet.r#type, Some(Type::SuperChatEvent as i32));
+        let Some(DisplayedContent::SuperChatDetails(details)) = snippet.displayed_content else {
+            panic!(
+                "Super chat details expected: {:?}",
+                snippet.displayed_content
+            );
+        };
+        assert_eq!(details.amount_micros, Some(5_000_000));
+        assert_eq!(details.currency.as_deref(), Some("USD"));
+        assert_eq!(details.amount_display_string.as_deref(), Some("$5.00"));
+        assert_eq!(details.tier, Some(2));
+        assert_eq!(details.user_comment.as_deref(), Some("Keep it up!"));
+    }
+
+    #[tokio::test]
+    async fn test_super_sticker_is_streamed_as_super_sticker_event() {
+        use live_chat_service::proto::LiveChatMessageListRequest;
+        use live_chat_service::proto::live_chat_message_snippet::{
+            DisplayedContent, type_wrapper::Type,
+        };
+        use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+        let router = create_router(
+            Arc::clone(&repo),
+            Arc::clone(&streams),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        post_json(
+            &router,
+            "/chat_messages",
+            serde_json::json!({
+                "id": "super-sticker-1",
+                "liveChatId": "super-sticker-chat",
+                "authorChannelId": "channel",
+                "authorDisplayName": "Patron",
+                "messageText": "Dancing duck",
+                "eventType": "superStickerEvent",
+                "superStickerDetails": {"amountMicros": 1_990_000, "currency": "EUR", "stickerId": "duck"},
+            }),
+        )
+        .await;
+
+        let service = live_chat_service::LiveChatService::new(
+            repo,
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            streams,
+        );
+        let response = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("super-sticker-chat".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        let snippet = response.items[0].snippet.clone().unwrap();
+        assert_eq!(snippet.r#type, Some(Type::SuperStickerEvent as i32));
+        let Some(DisplayedContent::SuperStickerDetails(details)) = snippet.displayed_content else {
+            panic!(
+                "Super sticker details expected: {:?}",
+                snippet.displayed_content
+            );
+        };
+        assert_eq!(details.amount_micros, Some(1_990_000));
+        assert_eq!(details.amount_display_string.as_deref(), Some("€1.99"));
+        assert_eq!(details.tier, Some(1));
+        let metadata = details.super_sticker_metadata.unwrap();
+        assert_eq!(metadata.sticker_id.as_deref(), Some("duck"));
+        assert_eq!(metadata.alt_text.as_deref(), Some("Dancing duck"));
+    }
+
+    #[tokio::test]
+    async fn test_event_type_must_match_message_details() {
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        for (body, error) in [
+            (
+                serde_json::json!({"eventType": "superChatEvent"}),
+                "eventType 'superChatEvent' does not match the message details, which make it \
+                 'textMessageEvent'",
+            ),
+            (
+                serde_json::json!({"eventType": "pollEvent"}),
+                "Unknown eventType 'pollEvent'; expected textMessageEvent, superChatEvent or \
+                 superStickerEvent",
+            ),
+            (
+                serde_json::json!({
+                    "superChatDetails": {"amountMicros": 1_000_000, "currency": "USD"},
+                    "superStickerDetails": {"amountMicros": 1_000_000, "currency": "USD"},
+                }),
+                "Only one of superChatDetails and superStickerDetails may be set",
+            ),
+        ] {
+            let mut request = serde_json::json!({
+                "id": "paid-1",
+                "liveChatId": "paid-chat",
+                "authorChannelId": "channel",
+                "messageText": "Hi",
+            });
+            request
+                .as_object_mut()
+                .unwrap()
+                .extend(body.as_object().unwrap().clone());
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/chat_messages")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(request.to_string()))
+                .expect("Valid request");
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(read_json(response).await["error"], error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_super_chat_is_rejected() {
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/chat_messages")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({
+                    "id": "super-chat-1",
+                    "liveChatId": "super-chat-id",
+                    "authorChannelId": "channel",
+                    "messageText": "Hi",
+                    "superChatDetails": {"amountMicros": 1_000_000, "currency": "dollars"},
+                })
+                .to_string(),
+            ))
+            .expect("Valid request");
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            read_json(response).await["error"],
+            "superChatDetails.currency 'dollars' is not an ISO 4217 code such as 'USD'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_endpoint_returns_json_404_with_routes() {
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let send = |method: &str, uri: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .expect("Valid request");
+            router.clone().oneshot(request)
+        };
+
+        let response = send("POST", "/video").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = read_json(response).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"], "unknown control endpoint: /video");
+        assert_eq!(
+            body["routes"].as_array().unwrap().len(),
+            CONTROL_ROUTES.len()
+        );
+        assert_eq!(body["routes"][0], "POST /control/videos");
+
+        // Every listed route is served; a known path with the wrong method is still a 405
+        for route in CONTROL_ROUTES {
+            let (method, path) = route.split_once(' ').unwrap();
+            let uri = path
+                .strip_prefix("/control")
+                .unwrap()
+                .replace("{id}", "test-video-1");
+            let response = send(method, &uri).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(
+                !String::from_utf8_lossy(&body).contains("unknown control endpoint"),
+                "{route}"
+            );
+        }
+        let response = send("GET", "/videos").await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    fn oauth_router() -> Router {
+        create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            Arc::new(domain::StreamRegistry::default()),
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_bulk_tokens_are_accepted_by_the_strict_validator() {
+        use oauth_service::{IssuedTokenValidator, TokenError, TokenValidator};
+
+        let router = oauth_router();
+        let started = std::time::Instant::now();
+        let response = post_json(
+            &router,
+            "/oauth/tokens/bulk",
+            serde_json::json!({"count": 10_000, "expiredFraction": 0.1, "prefix": "load_"}),
+        )
+        .await;
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(5),
+            "issuing 10k tokens took {:?}",
+            started.elapsed()
+        );
+        assert_eq!(response["count"], 10_000);
+        assert_eq!(response["expired"], 1_000);
+        let tokens = response["tokens"].as_array().expect("tokens array");
+        assert_eq!(tokens.len(), 10_000);
+        assert!(oauth_service::token_store_summary().tracked >= 10_000);
+
+        let strict = IssuedTokenValidator { strict: true };
+        for token in tokens.iter().step_by(97) {
+            let value = token["accessToken"].as_str().expect("token value");
+            assert!(value.starts_with("load_"));
+            let expected = if token["expiresIn"] == 0 {
+                Err(TokenError::Expired)
+            } else {
+                Ok(())
+            };
+            assert_eq!(strict.validate(value), expected, "{value}");
+        }
+        assert_eq!(tokens[0]["expiresIn"], 0);
+        assert_eq!(tokens[1_000]["expiresIn"], 3600);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_tokens_stream_as_ndjson() {
+        let router = oauth_router();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/oauth/tokens/bulk")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, "application/x-ndjson")
+            .body(Body::from(r#"{"count": 2500, "scope": "custom"}"#))
+            .expect("Valid request");
+        let response = router.oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        let body = String::from_utf8(bytes.to_vec()).expect("UTF-8 body");
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("JSON line"))
+            .collect();
+        assert_eq!(lines.len(), 2500);
+        assert!(lines.iter().all(|line| line["scope"] == "custom"));
+        assert!(
+            oauth_service::validate_token(lines[2499]["accessToken"].as_str().unwrap()).is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_tokens_reject_bad_counts_and_fractions() {
+        let router = oauth_router();
+        for body in [
+            serde_json::json!({"count": 0}),
+            serde_json::json!({"count": oauth::MAX_BULK_TOKENS + 1}),
+            serde_json::json!({"count": 5, "expiredFraction": 1.5}),
+        ] {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/oauth/tokens/bulk")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .expect("Valid request");
+            let response = router.clone().oneshot(request).await.expect("Response");
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{body}");
+            let error = read_json(response).await;
+            assert_eq!(error["success"], false);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_reads_only_the_request_log_unless_any_path_is_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = |path: &Path, video_id: &str| {
+            let log = request_log::RequestLog::open(path).unwrap();
+            log.record(&RequestLogEntry::Rest {
+                timestamp: Utc::now(),
+                method: "POST".to_string(),
+                path: "/control/videos".to_string(),
+                query: None,
+                body: serde_json::json!({
+                    "id": video_id,
+                    "channelId": "replay-channel",
+                    "title": "Replayed",
+                    "description": "Recorded",
+                    "channelTitle": "Replay Channel",
+                    "liveChatId": format!("{video_id}-chat"),
+                })
+                .to_string(),
+            });
+        };
+        let log_file = dir.path().join("requests.jsonl");
+        let other_file = dir.path().join("elsewhere.jsonl");
+        record(&log_file, "logged-video");
+        record(&other_file, "other-video");
+        let router_for = |replay_any_path: bool| {
+            let repo: Arc<dyn datastore::Repository> =
+                Arc::new(datastore::InMemoryRepository::new());
+            let state = ControlState::new(
+                Arc::clone(&repo),
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            )
+            .with_request_log_file(Some(log_file.clone()))
+            .with_replay_any_path(replay_any_path);
+            (repo, router_with_state(state))
+        };
+        let replay = |router: Router, path: Option<&Path>| {
+            let body = match path {
+                Some(path) => serde_json::json!({ "path": path }),
+                None => serde_json::json!({}),
+            };
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/replay")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .expect("Valid request");
+            router.oneshot(request)
+        };
+
+        // Other files are refused without reading them
+        let (repo, router) = router_for(false);
+        let response = replay(router.clone(), Some(&other_file)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(repo.get_video("other-video").unwrap().is_none());
+
+        // The request log is replayed by default or when named
+        let response = replay(router.clone(), None).await.unwrap();
+        assert_eq!(read_json(response).await["replayed"], 1);
+        assert!(repo.get_video("logged-video").unwrap().is_some());
+        let response = replay(router, Some(&log_file)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Opting in allows any file
+        let (repo, router) = router_for(true);
+        let response = replay(router, Some(&other_file)).await.unwrap();
+        assert_eq!(read_json(response).await["replayed"], 1);
+        assert!(repo.get_video("other-video").unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_replay_skips_and_reports_requests_with_redacted_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_file = dir.path().join("requests.jsonl");
+        let log = request_log::RequestLog::open(&log_file).unwrap();
+        let record = |path: &str, body: serde_json::Value| {
+            log.record(&RequestLogEntry::Rest {
+                timestamp: Utc::now(),
+                method: "POST".to_string(),
+                path: path.to_string(),
+                query: None,
+                body: body.to_string(),
+            });
+        };
+        record(
+            "/control/chat_messages",
+            serde_json::json!({
+                "id": "plain-msg",
+                "liveChatId": "test-chat-id",
+                "authorChannelId": "replay-author",
+                "authorDisplayName": "Replay Author",
+                "messageText": "Nothing secret",
+            }),
+        );
+        // Token-looking text is redacted by default, so the message cannot be replayed as sent
+        record(
+            "/control/chat_messages",
+            serde_json::json!({
+                "id": "token-msg",
+                "liveChatId": "test-chat-id",
+                "authorChannelId": "replay-author",
+                "authorDisplayName": "Replay Author",
+                "messageText": "my token is ya29.mock_0123456789abcdef",
+            }),
+        );
+        let recorded = std::fs::read_to_string(&log_file).unwrap();
+        assert!(request_log::redact::contains_marker(&recorded));
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = router_with_state(
+            ControlState::new(
+                Arc::clone(&repo),
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            )
+            .with_request_log_file(Some(log_file.clone())),
+        );
+        let report = post_json(&router, "/replay", serde_json::json!({})).await;
+        assert_eq!(report["replayed"], 1);
+        assert_eq!(report["redacted"], 1);
+        assert_eq!(report["success"], false);
+        let ids: Vec<_> = repo
+            .get_chat_messages("test-chat-id")
+            .unwrap()
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert!(ids.contains(&"plain-msg".to_string()));
+        assert!(!ids.contains(&"token-msg".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_file_repository_keeps_control_api_writes_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let router_for = |repo: Arc<dyn datastore::Repository>| {
+            create_router(
+                repo,
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            )
+        };
+
+        let router = router_for(Arc::new(datastore::FileRepository::open(&path)));
+        post_json(
+            &router,
+            "/videos",
+            serde_json::json!({
+                "id": "persisted-video",
+                "channelId": "persisted-channel",
+                "title": "Persisted",
+                "description": "Survives restarts",
+                "channelTitle": "Persisted Channel",
+                "liveChatId": "persisted-chat",
+                "scheduledStartTime": "2030-01-01T00:00:00Z",
+                "concurrentViewers": 1234,
+            }),
+        )
+        .await;
+        post_json(
+            &router,
+            "/chat_messages",
+            serde_json::json!({
+                "id": "persisted-msg",
+                "liveChatId": "persisted-chat",
+                "authorChannelId": "persisted-author",
+                "authorDisplayName": "Persisted Author",
+                "messageText": "Thanks!",
+                "isChatModerator": true,
+                "profileImageUrl": "https://example.com/avatar.png",
+                "superChatDetails": {"amountMicros": 5000000, "currency": "USD"},
+            }),
+        )
+        .await;
+        // Dropping the last handle writes what the debounce still holds
+        drop(router);
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::FileRepository::open(&path));
+        let video = repo.get_video("persisted-video").unwrap().expect("Video");
+        assert_eq!(video.live_chat_id.as_deref(), Some("persisted-chat"));
+        assert_eq!(
+            video.scheduled_start_time.map(|time| time.to_rfc3339()),
+            Some("2030-01-01T00:00:00+00:00".to_string())
+        );
+        assert_eq!(video.concurrent_viewers, Some(1234));
+        assert_eq!(video.actual_end_time, None);
+        let messages = repo.get_chat_messages("persisted-chat").unwrap();
+        assert_eq!(messages.len(), 1);
+        let message = &messages[0];
+        assert_eq!(message.author_display_name, "Persisted Author");
+        assert!(message.author_profile.is_chat_moderator);
+        assert!(!message.author_profile.is_chat_owner);
+        assert_eq!(
+            message.author_profile.profile_image_url.as_deref(),
+            Some("https://example.com/avatar.png")
+        );
+        let super_chat = message.super_chat_details.as_ref().expect("Super chat");
+        assert_eq!(super_chat.amount_micros, 5_000_000);
+        assert_eq!(super_chat.currency, "USD");
+
+        // The restored repository serves the control API like before the restart
+        let router = router_for(repo);
+        let stats = get_json(&router, "/stats").await;
+        let chat = stats["chats"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|chat| chat["liveChatId"] == "persisted-chat")
+            .expect("chat should be listed");
+        assert_eq!(chat["messageCount"], 1);
+    }
 }
@@ -0,0 +1,496 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use datastore::RepositoryError;
+use domain::{LiveChat, LiveChatState};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::videos::{etag, if_match_version, version_conflict};
+use crate::{ControlJson, CreateResponse, ErrorResponse, repository_error_response};
+
+/// Request body for creating a live chat lifecycle
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateLiveChatRequest {
+    pub id: String,
+    /// Initial state; defaults to scheduled
+    #[serde(default = "default_state")]
+    pub state: LiveChatState,
+    #[serde(default)]
+    pub scheduled_start_time: Option<DateTime<Utc>>,
+    /// Withhold messages older than this many seconds from clients
+    #[serde(default)]
+    pub history_retention_seconds: Option<u64>,
+}
+
+fn default_state() -> LiveChatState {
+    LiveChatState::Scheduled
+}
+
+/// Request body for transitioning a live chat
+#[derive(Debug, Deserialize)]
+pub struct TransitionLiveChatRequest {
+    pub state: LiveChatState,
+}
+
+/// Request body for transitioning a video's broadcast, mirroring liveBroadcasts.transition
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionBroadcastRequest {
+    /// "testing", "live" or "complete"
+    pub broadcast_status: String,
+}
+
+/// Request body for patching the settings of a live chat; absent fields are left unchanged
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchLiveChatRequest {
+    #[serde(default)]
+    pub scheduled_start_time: Option<DateTime<Utc>>,
+    /// Withhold messages older than this many seconds from clients; 0 serves the whole history
+    #[serde(default)]
+    pub history_retention_seconds: Option<u64>,
+}
+
+/// Request body for muting an author in a live chat
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MuteAuthorRequest {
+    pub channel_id: String,
+}
+
+/// Response carrying the resulting live chat lifecycle
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatResponse {
+    pub success: bool,
+    pub live_chat: LiveChat,
+    /// Version of the stored lifecycle, also sent as the `ETag`; absent when none is stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+/// Response for closing the open streams of a live chat
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseStreamsResponse {
+    pub success: bool,
+    /// Streams asked to close; each ends on its next recheck
+    pub closed: usize,
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    let response = ErrorResponse {
+        success: false,
+        error,
+    };
+    (status, Json(response)).into_response()
+}
+
+fn live_chat_response(live_chat: LiveChat, version: Option<u64>, status: StatusCode) -> Response {
+    let response = LiveChatResponse {
+        success: true,
+        live_chat,
+        version,
+    };
+    match version {
+        Some(version) => (status, [(header::ETAG, etag(version))], Json(response)).into_response(),
+        None => (status, Json(response)).into_response(),
+    }
+}
+
+/// Align the broadcast times of every video that uses the chat
+/// Each video is updated atomically so concurrent video patches are not lost
+fn align_videos(
+    repo: &Arc<dyn datastore::Repository>,
+    chat: &LiveChat,
+) -> datastore::RepositoryResult<()> {
+    let videos = repo.get_videos()?;
+
+    for video in videos
+        .into_iter()
+        .filter(|video| video.live_chat_id.as_deref() == Some(chat.id.as_str()))
+    {
+        repo.update_video_with(&video.id, None, &mut |video| {
+            video.scheduled_start_time = chat.scheduled_start_time.or(video.scheduled_start_time);
+            video.actual_start_time = chat.actual_start_time.or(video.actual_start_time);
+            video.actual_end_time = chat.offline_at.or(video.actual_end_time);
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// Store the chat and align the broadcast times of every video that uses it
+fn save_and_align(
+    repo: &Arc<dyn datastore::Repository>,
+    chat: LiveChat,
+    status: StatusCode,
+) -> Response {
+    if let Err(e) = align_videos(repo, &chat) {
+        return repository_error_response(&e);
+    }
+    let version = repo
+        .save_live_chat(chat.clone())
+        .and_then(|()| repo.get_live_chat_version(&chat.id));
+    match version {
+        Ok(version) => live_chat_response(chat, version, status),
+        Err(e) => repository_error_response(&e),
+    }
+}
+
+/// Atomically update a chat's lifecycle and align its videos
+/// `update` returns the message of a rejected change, answered with 409
+fn update_and_align(
+    repo: &Arc<dyn datastore::Repository>,
+    id: &str,
+    expected_version: Option<u64>,
+    update: &mut dyn FnMut(&mut LiveChat) -> Result<(), String>,
+) -> Response {
+    let mut rejection = None;
+    let result = repo.update_live_chat_with(id, expected_version, &mut |chat| {
+        update(chat).map_err(|e| {
+            rejection = Some(e);
+            RepositoryError::Conflict
+        })
+    });
+    let (chat, version) = match (result, rejection, expected_version) {
+        (Ok(updated), _, _) => updated,
+        (Err(_), Some(rejection), _) => return error_response(StatusCode::CONFLICT, rejection),
+        (Err(RepositoryError::Conflict), None, Some(expected)) => {
+            let current = repo.get_live_chat_version(id).ok().flatten();
+            return version_conflict("Live chat", id, expected, Some(current.unwrap_or(0)));
+        }
+        (Err(e), _, _) => return repository_error_response(&e),
+    };
+
+    if let Err(e) = align_videos(repo, &chat) {
+        return repository_error_response(&e);
+    }
+    live_chat_response(chat, Some(version), StatusCode::OK)
+}
+
+/// Move a chat to `state`, starting from the active state when it has no stored lifecycle
+fn transition(repo: &Arc<dyn datastore::Repository>, id: &str, state: LiveChatState) -> Response {
+    let now = clock::system_clock().now();
+    update_and_align(repo, id, None, &mut |chat| chat.transition(state, now))
+}
+
+/// Handler for creating (or replacing) a live chat lifecycle
+pub(crate) async fn create_live_chat(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    ControlJson(request): ControlJson<CreateLiveChatRequest>,
+) -> impl IntoResponse {
+    let mut chat = LiveChat::scheduled(&request.id, request.scheduled_start_time);
+    chat.history_retention_seconds = request.history_retention_seconds.filter(|&s| s > 0);
+    if let Err(e) = chat.transition(request.state, clock::system_clock().now()) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+
+    save_and_align(&repo, chat, StatusCode::CREATED)
+}
+
+/// Handler for transitioning a live chat between lifecycle states
+pub(crate) async fn transition_live_chat(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(id): Path<String>,
+    ControlJson(request): ControlJson<TransitionLiveChatRequest>,
+) -> impl IntoResponse {
+    transition(&repo, &id, request.state)
+}
+
+/// Handler for patching the settings of a live chat
+/// With `If-Match`, the patch only applies if the chat is still at that version
+pub(crate) async fn patch_live_chat(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    ControlJson(request): ControlJson<PatchLiveChatRequest>,
+) -> impl IntoResponse {
+    let expected_version = match if_match_version(&headers) {
+        Ok(version) => version,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    update_and_align(&repo, &id, expected_version, &mut |chat| {
+        if request.scheduled_start_time.is_some() {
+            chat.scheduled_start_time = request.scheduled_start_time;
+        }
+        if let Some(retention) = request.history_retention_seconds {
+            chat.history_retention_seconds = (retention > 0).then_some(retention);
+        }
+        Ok(())
+    })
+}
+
+/// Handler for muting an author in a live chat
+///
+/// Unlike a ban, muting is silent: no event is sent, and the author's later messages are
+/// stored as usual but only served to streams and lists viewing the chat as that author.
+pub(crate) async fn mute_author(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(id): Path<String>,
+    ControlJson(request): ControlJson<MuteAuthorRequest>,
+) -> impl IntoResponse {
+    let channel_id = request.channel_id.trim().to_string();
+    if channel_id.is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "channelId must not be empty".to_string(),
+        );
+    }
+    update_and_align(&repo, &id, None, &mut |chat| {
+        chat.muted_author_channel_ids.insert(channel_id.clone());
+        Ok(())
+    })
+}
+
+/// Handler for unmuting an author in a live chat
+/// Only later messages are served to everyone again; those posted while muted stay hidden
+pub(crate) async fn unmute_author(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path((id, channel_id)): Path<(String, String)>,
+) -> Response {
+    let muted = match repo.get_live_chat(&id) {
+        Ok(chat) => chat.is_some_and(|chat| chat.muted_author_channel_ids.contains(&channel_id)),
+        Err(e) => return repository_error_response(&e),
+    };
+    if !muted {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            format!("Author '{channel_id}' is not muted in live chat '{id}'"),
+        );
+    }
+    update_and_align(&repo, &id, None, &mut |chat| {
+        chat.muted_author_channel_ids.remove(&channel_id);
+        Ok(())
+    })
+}
+
+/// Handler for transitioning a video's broadcast, which transitions its live chat
+pub(crate) async fn transition_broadcast(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(video_id): Path<String>,
+    ControlJson(request): ControlJson<TransitionBroadcastRequest>,
+) -> impl IntoResponse {
+    let state = match request.broadcast_status.as_str() {
+        "testing" => LiveChatState::Scheduled,
+        "live" => LiveChatState::Active,
+        "complete" => LiveChatState::Ended,
+        other => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown broadcastStatus '{other}'. Use 'testing', 'live' or 'complete'"),
+            );
+        }
+    };
+
+    let video = match repo.get_video(&video_id) {
+        Ok(Some(video)) => video,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("Video '{video_id}' not found"),
+            );
+        }
+        Err(e) => return repository_error_response(&e),
+    };
+    let Some(live_chat_id) = video.live_chat_id else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Video '{video_id}' has no live chat"),
+        );
+    };
+
+    transition(&repo, &live_chat_id, state)
+}
+
+/// Handler for starting a video's broadcast
+///
+/// Moves the video's live chat to active and sets the `actual_start_time` of the chat and its
+/// videos, ending the pre-show wait of an upcoming broadcast. A broadcast started before its
+/// scheduled time is rescheduled to the start, keeping `scheduled_start_time <=
+/// actual_start_time`. Starting a started video keeps its original start time; an ended video
+/// cannot be started again.
+pub(crate) async fn start_video(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(video_id): Path<String>,
+) -> impl IntoResponse {
+    let video = match repo.get_video(&video_id) {
+        Ok(Some(video)) => video,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("Video '{video_id}' not found"),
+            );
+        }
+        Err(e) => return repository_error_response(&e),
+    };
+    let Some(live_chat_id) = video.live_chat_id else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Video '{video_id}' has no live chat"),
+        );
+    };
+
+    let started_at = video
+        .actual_start_time
+        .unwrap_or_else(|| clock::system_clock().now());
+    update_and_align(&repo, &live_chat_id, None, &mut |chat| {
+        chat.scheduled_start_time = chat
+            .scheduled_start_time
+            .or(video.scheduled_start_time)
+            .map(|scheduled| scheduled.min(started_at));
+        // Chats without a stored lifecycle are already active but may lack a start time
+        if chat.state == LiveChatState::Active {
+            chat.actual_start_time.get_or_insert(started_at);
+            return Ok(());
+        }
+        chat.transition(LiveChatState::Active, started_at)
+    })
+}
+
+/// Handler for ending a video's broadcast
+///
+/// Sets the video's `actual_end_time` and ends its live chat, which closes open streams after
+/// a terminal response. Ending an ended video is a no-op.
+pub(crate) async fn end_video(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(video_id): Path<String>,
+) -> impl IntoResponse {
+    let live_chat_id = match repo.get_video(&video_id) {
+        Ok(Some(video)) => video.live_chat_id,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("Video '{video_id}' not found"),
+            );
+        }
+        Err(e) => return repository_error_response(&e),
+    };
+    let Some(live_chat_id) = live_chat_id else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Video '{video_id}' has no live chat"),
+        );
+    };
+
+    let mut rejection = None;
+    let result = repo.update_video_with(&video_id, None, &mut |video| {
+        video
+            .actual_end_time
+            .get_or_insert_with(|| clock::system_clock().now());
+        video.validate_times().map_err(|e| {
+            rejection = Some(e);
+            RepositoryError::Conflict
+        })
+    });
+    let ended_at = match (result, rejection) {
+        (Ok((video, _)), _) => video
+            .actual_end_time
+            .expect("The end time was set by the update"),
+        (Err(_), Some(rejection)) => return error_response(StatusCode::CONFLICT, rejection),
+        (Err(e), None) => return repository_error_response(&e),
+    };
+
+    // A stored lifecycle ends along with the video
+    match repo.get_live_chat(&live_chat_id) {
+        Ok(Some(_)) => {
+            let result = repo.update_live_chat_with(&live_chat_id, None, &mut |chat| {
+                if chat.state == LiveChatState::Ended {
+                    return Ok(());
+                }
+                chat.transition(LiveChatState::Ended, ended_at)
+                    .map_err(RepositoryError::Invalid)
+            });
+            if let Err(e) = result {
+                return repository_error_response(&e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => return repository_error_response(&e),
+    }
+
+    let live_chat = repo
+        .get_effective_live_chat(&live_chat_id)
+        .and_then(|chat| Ok(chat.zip(Some(repo.get_live_chat_version(&live_chat_id)?))));
+    match live_chat {
+        Ok(Some((live_chat, version))) => live_chat_response(live_chat, version, StatusCode::OK),
+        Ok(None) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Live chat '{live_chat_id}' has no lifecycle after ending its video"),
+        ),
+        Err(e) => repository_error_response(&e),
+    }
+}
+
+/// Handler for closing every open stream of a live chat
+pub(crate) async fn close_streams(
+    State(streams): State<Arc<domain::StreamRegistry>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let response = CloseStreamsResponse {
+        success: true,
+        closed: streams.close_streams(&id),
+    };
+    (StatusCode::OK, Json(response))
+}
+
+/// Handler for enabling the delivery audit of a live chat, or resetting it when enabled
+pub(crate) async fn enable_delivery_audit(
+    State(streams): State<Arc<domain::StreamRegistry>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let message = if streams.audits().enable(&id) {
+        format!("Delivery audit of live chat '{id}' reset")
+    } else {
+        format!("Delivery audit of live chat '{id}' enabled")
+    };
+    let response = CreateResponse {
+        success: true,
+        message,
+    };
+    (StatusCode::OK, Json(response))
+}
+
+/// Handler for reporting the deliveries of an audited live chat
+pub(crate) async fn delivery_audit(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    State(streams): State<Arc<domain::StreamRegistry>>,
+    Path(id): Path<String>,
+) -> Response {
+    let message_ids: Vec<String> = match repo.get_chat_messages(&id) {
+        Ok(messages) => messages.into_iter().map(|message| message.id).collect(),
+        Err(e) => return repository_error_response(&e),
+    };
+    match streams.audits().report(&id, &message_ids) {
+        Some(report) => (StatusCode::OK, Json(report)).into_response(),
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            format!("Delivery audit of live chat '{id}' is not enabled"),
+        ),
+    }
+}
+
+/// Handler for disabling the delivery audit of a live chat and discarding its records
+pub(crate) async fn disable_delivery_audit(
+    State(streams): State<Arc<domain::StreamRegistry>>,
+    Path(id): Path<String>,
+) -> Response {
+    if !streams.audits().disable(&id) {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            format!("Delivery audit of live chat '{id}' is not enabled"),
+        );
+    }
+    let response = CreateResponse {
+        success: true,
+        message: format!("Delivery audit of live chat '{id}' disabled"),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
@@ -0,0 +1,46 @@
+//! HTTP method override of the control API
+//!
+//! Some proxies only let GET and POST through. With `CONTROL_METHOD_OVERRIDE=true` the
+//! server wraps the control router with [`crate::method_override`]: a POST carrying an
+//! `X-HTTP-Method-Override` header is routed as the method the header names, so
+//! `POST /control/faults` with `X-HTTP-Method-Override: DELETE` clears the faults.
+
+use crate::ErrorResponse;
+use axum::{
+    Json,
+    extract::Request,
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Header naming the method a POST request stands for
+pub const METHOD_OVERRIDE_HEADER: &str = "x-http-method-override";
+
+/// Remap a POST to the method of its override header
+/// Requests with other methods are routed as they are, header or not. The method name is
+/// case-insensitive.
+pub(crate) async fn override_method(mut request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(value) = request.headers_mut().remove(METHOD_OVERRIDE_HEADER) else {
+        return next.run(request).await;
+    };
+    match Method::from_bytes(&value.as_bytes().to_ascii_uppercase()) {
+        Ok(method) => {
+            *request.method_mut() = method;
+            next.run(request).await
+        }
+        Err(_) => {
+            let error = ErrorResponse {
+                success: false,
+                error: format!(
+                    "Invalid {METHOD_OVERRIDE_HEADER} header '{}'",
+                    String::from_utf8_lossy(value.as_bytes())
+                ),
+            };
+            (StatusCode::BAD_REQUEST, Json(error)).into_response()
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Bulk issuance of OAuth access tokens for load tests
+
+use axum::{
+    Json,
+    body::Body,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use oauth_service::BulkToken;
+use serde::{Deserialize, Serialize};
+
+use crate::{ControlJson, ErrorResponse};
+
+/// Most tokens one bulk request may issue
+pub const MAX_BULK_TOKENS: usize = 100_000;
+
+/// Tokens per chunk of an NDJSON response
+const NDJSON_CHUNK: usize = 1_000;
+
+/// Content type of the line-delimited response
+const NDJSON: &str = "application/x-ndjson";
+
+/// Request body for issuing access tokens in bulk
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkTokenRequest {
+    pub count: usize,
+    /// Scope of every token; resolved like that of the token endpoint when omitted
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Lifetime of the tokens in seconds
+    #[serde(default = "default_expires_in")]
+    pub expires_in: i64,
+    /// Prefix of the token values, `ya29.mock_` by default
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Share of the tokens, between 0 and 1, issued already expired
+    #[serde(default)]
+    pub expired_fraction: f64,
+}
+
+fn default_expires_in() -> i64 {
+    3600
+}
+
+/// Response for bulk token issuance
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkTokenResponse {
+    pub success: bool,
+    pub count: usize,
+    /// How many of the tokens were issued already expired; they come first
+    pub expired: usize,
+    pub tokens: Vec<BulkToken>,
+}
+
+/// Issue access tokens in bulk
+///
+/// Answers with JSON, or with one token per line when the client accepts
+/// `application/x-ndjson`, streamed in chunks so very large batches are not buffered twice.
+pub async fn issue_bulk_tokens(
+    headers: HeaderMap,
+    ControlJson(request): ControlJson<BulkTokenRequest>,
+) -> Response {
+    if request.count == 0 || request.count > MAX_BULK_TOKENS {
+        return bad_request(format!("count must be between 1 and {MAX_BULK_TOKENS}"));
+    }
+    if !(0.0..=1.0).contains(&request.expired_fraction) {
+        return bad_request("expiredFraction must be between 0 and 1".to_string());
+    }
+
+    let expired = (request.count as f64 * request.expired_fraction).round() as usize;
+    let prefix = request.prefix.as_deref().unwrap_or("ya29.mock_");
+    let tokens = oauth_service::issue_bulk_tokens(
+        request.count,
+        expired,
+        request.scope,
+        request.expires_in,
+        prefix,
+    );
+    tracing::info!(
+        "Issued {} access tokens in bulk ({expired} expired)",
+        tokens.len()
+    );
+
+    let ndjson = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains(NDJSON));
+    if !ndjson {
+        let response = BulkTokenResponse {
+            success: true,
+            count: tokens.len(),
+            expired,
+            tokens,
+        };
+        return (StatusCode::OK, Json(response)).into_response();
+    }
+
+    let chunks: Vec<Vec<BulkToken>> = tokens
+        .chunks(NDJSON_CHUNK)
+        .map(<[BulkToken]>::to_vec)
+        .collect();
+    let lines = futures::stream::iter(chunks.into_iter().map(|chunk| {
+        let mut lines = String::new();
+        for token in chunk {
+            lines.push_str(&serde_json::to_string(&token).expect("Token serializes to JSON"));
+            lines.push('\n');
+        }
+        Ok::<_, std::convert::Infallible>(lines)
+    }));
+    ([(header::CONTENT_TYPE, NDJSON)], Body::from_stream(lines)).into_response()
+}
+
+fn bad_request(error: String) -> Response {
+    let error = ErrorResponse {
+        success: false,
+        error,
+    };
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
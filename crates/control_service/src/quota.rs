@@ -0,0 +1,133 @@
+//! Quota consumption report built from the ledger the API services charge, and the daily
+//! limit and unit costs it charges with
+
+use crate::{ControlJson, CreateResponse, ErrorResponse};
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Request body for configuring the quota
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetQuotaRequest {
+    /// Reject calls once a key has used this many units; 0 stops enforcing a limit
+    #[serde(default)]
+    pub daily_limit: Option<u64>,
+    /// Units per call by method name, e.g. `{"search.list": 100}`
+    #[serde(default)]
+    pub costs: BTreeMap<String, u64>,
+}
+
+/// Units a key has used and has left
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyQuota {
+    pub units: u64,
+    pub remaining: u64,
+}
+
+/// Response with the quota settings and the usage of each key since the last reset
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaResponse {
+    pub success: bool,
+    #[serde(flatten)]
+    pub settings: domain::QuotaSettings,
+    pub usage: BTreeMap<String, KeyQuota>,
+}
+
+impl QuotaResponse {
+    pub(crate) fn of(quota: &domain::QuotaLedger) -> Self {
+        let usage = quota
+            .report(None)
+            .by_key
+            .into_iter()
+            .map(|(key, usage)| {
+                let usage = KeyQuota {
+                    units: usage.units,
+                    remaining: usage.remaining,
+                };
+                (key, usage)
+            })
+            .collect();
+        Self {
+            success: true,
+            settings: quota.settings(),
+            usage,
+        }
+    }
+}
+
+// Handler for GET /control/quota
+pub async fn get_quota(State(quota): State<Arc<domain::QuotaLedger>>) -> impl IntoResponse {
+    (StatusCode::OK, Json(QuotaResponse::of(&quota)))
+}
+
+// Handler for POST /control/quota
+// Fields left out of the body keep their current values
+pub async fn set_quota(
+    State(quota): State<Arc<domain::QuotaLedger>>,
+    ControlJson(request): ControlJson<SetQuotaRequest>,
+) -> Response {
+    let mut costs = Vec::with_capacity(request.costs.len());
+    for (name, cost) in request.costs {
+        let Some(endpoint) = domain::QuotaEndpoint::from_name(&name) else {
+            let methods: Vec<_> = domain::QuotaEndpoint::ALL
+                .iter()
+                .map(domain::QuotaEndpoint::name)
+                .collect();
+            let response = ErrorResponse {
+                success: false,
+                error: format!(
+                    "Unknown method '{name}' in costs; expected one of {}",
+                    methods.join(", ")
+                ),
+            };
+            return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+        };
+        costs.push((endpoint, cost));
+    }
+
+    if let Some(daily_limit) = request.daily_limit {
+        quota.set_daily_limit((daily_limit > 0).then_some(daily_limit));
+    }
+    for (endpoint, cost) in costs {
+        quota.set_cost(endpoint, cost);
+    }
+    (StatusCode::OK, Json(QuotaResponse::of(&quota))).into_response()
+}
+
+/// Query parameters of the quota report
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportParams {
+    /// Only count calls from the last `windowSeconds` seconds; everything since the last reset when absent
+    pub window_seconds: Option<i64>,
+}
+
+// Handler for GET /control/quota/report
+pub async fn report(
+    State(quota): State<Arc<domain::QuotaLedger>>,
+    Query(params): Query<ReportParams>,
+) -> impl IntoResponse {
+    let window = params
+        .window_seconds
+        .map(|seconds| chrono::Duration::seconds(seconds.max(0)));
+    (StatusCode::OK, Json(quota.report(window)))
+}
+
+// Handler for POST /control/quota/reset
+pub async fn reset(State(quota): State<Arc<domain::QuotaLedger>>) -> impl IntoResponse {
+    quota.reset();
+    let response = CreateResponse {
+        success: true,
+        message: "Quota usage reset".to_string(),
+    };
+    (StatusCode::OK, Json(response))
+}
@@ -0,0 +1,29 @@
+//! Read-only mode of the control API
+//!
+//! With `CONTROL_READONLY=true` the server wraps the control router with
+//! [`crate::read_only`]: every route that changes state answers 403, while the GET
+//! introspection routes keep working, e.g. for shared staging environments.
+
+use crate::ErrorResponse;
+use axum::{
+    Json,
+    extract::Request,
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Error returned by mutating routes in read-only mode
+pub const READ_ONLY_ERROR: &str = "control API is read-only";
+
+/// Reject requests other than GET and HEAD
+pub(crate) async fn reject_writes(request: Request, next: Next) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+    let error = ErrorResponse {
+        success: false,
+        error: READ_ONLY_ERROR.to_string(),
+    };
+    (StatusCode::FORBIDDEN, Json(error)).into_response()
+}
@@ -0,0 +1,69 @@
+//! Reseed: swap in a new dataset without closing open streams
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::{ControlState, ErrorResponse, repository_error_response};
+
+/// Response for a reseed
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReseedResponse {
+    pub success: bool,
+    pub message: String,
+    pub videos: usize,
+    pub chat_messages: usize,
+    /// Open streams whose chat is still there; they continue in the new data
+    pub kept_streams: usize,
+    /// Open streams whose chat is gone; they end with `chat_ended`
+    pub ended_streams: usize,
+}
+
+/// Handler for replacing all data with the seed data in the body
+///
+/// The body uses the format of `SEED_DATA_PATH` files in JSON. Unlike a reset, open streams
+/// stay open: each continues after its last delivered message, restarts with a gap notice
+/// when that message is gone, or ends when its chat is gone. Faults, generators, scenarios
+/// and quota usage are kept.
+pub(crate) async fn reseed(State(state): State<ControlState>, body: String) -> Response {
+    let seed = match datastore::SeedData::from_json(&body) {
+        Ok(seed) => seed,
+        Err(error) => {
+            let response = ErrorResponse {
+                success: false,
+                error,
+            };
+            return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+        }
+    };
+    let (videos, chat_messages) = (seed.videos.len(), seed.chat_messages.len());
+
+    if let Err(e) = state.repo.reseed(seed) {
+        return repository_error_response(&e);
+    }
+
+    // Counted after the swap, while the streams are still anchoring
+    let (mut kept_streams, mut ended_streams) = (0, 0);
+    for (live_chat_id, open) in state.streams.snapshot() {
+        match state.repo.live_chat_exists(&live_chat_id) {
+            Ok(true) => kept_streams += open,
+            Ok(false) => ended_streams += open,
+            Err(e) => return repository_error_response(&e),
+        }
+    }
+
+    let response = ReseedResponse {
+        success: true,
+        message: format!("Reseeded with {videos} videos and {chat_messages} chat messages"),
+        videos,
+        chat_messages,
+        kept_streams,
+        ended_streams,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
@@ -0,0 +1,70 @@
+//! Reset: restore the mock to its initial state between test cases
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{ControlState, repository_error_response};
+
+/// Query parameters of the reset endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct ResetParams {
+    /// Start without any data instead of the initial dummy or seed data
+    #[serde(default)]
+    pub empty: bool,
+}
+
+/// Response for a reset
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetResponse {
+    pub success: bool,
+    pub message: String,
+    /// Open gRPC streams that were asked to close
+    pub closed_streams: usize,
+    /// Generators that were still running
+    pub stopped_generators: usize,
+    /// Scenarios that were still running
+    pub cancelled_scenarios: usize,
+}
+
+/// Handler for resetting the mock
+///
+/// Open streams are closed before the data goes, so no stream resends old page tokens
+/// against the new data; clients reconnect from the start. Faults, generators,
+/// scenarios, quota usage, warm-ups and delivery audits are cleared.
+pub(crate) async fn reset(
+    State(state): State<ControlState>,
+    Query(params): Query<ResetParams>,
+) -> Response {
+    let closed_streams = state.streams.close_all_streams();
+    let stopped_generators = state.generators.clear();
+    let cancelled_scenarios = state.scenarios.clear();
+    state.faults.clear();
+    state.quota.reset();
+    state.warmup.clear();
+    state.streams.audits().clear();
+
+    if let Err(e) = state.repo.reset(params.empty) {
+        return repository_error_response(&e);
+    }
+
+    let message = if params.empty {
+        "Mock reset to an empty state"
+    } else {
+        "Mock reset to its initial state"
+    };
+    let response = ResetResponse {
+        success: true,
+        message: message.to_string(),
+        closed_streams,
+        stopped_generators,
+        cancelled_scenarios,
+    };
+
+    (StatusCode::OK, Json(response)).into_response()
+}
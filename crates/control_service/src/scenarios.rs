@@ -0,0 +1,423 @@
+//! Scenario playback: chat messages inserted on a schedule
+//!
+//! A scenario is a list of messages, each with a delay relative to the scenario start or to
+//! the previous message. A background task inserts them into the repository when they are
+//! due, so a test can reproduce the timing of a real chat without racing its own requests.
+//! Scenarios run independently: several on the same chat interleave by time.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{ControlJson, ControlState, ErrorResponse};
+
+/// Author of scenario messages that do not name one
+const DEFAULT_SCENARIO_AUTHOR_CHANNEL_ID: &str = "scenario-author";
+
+/// What the delays of a scenario's messages are measured from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DelayOrigin {
+    /// The scenario start
+    #[default]
+    Start,
+    /// The previous message of the scenario
+    Previous,
+}
+
+/// Request body for starting a scenario
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateScenarioRequest {
+    /// Chat of the messages that do not name their own
+    #[serde(default)]
+    pub live_chat_id: Option<String>,
+    #[serde(default)]
+    pub relative_to: DelayOrigin,
+    pub messages: Vec<ScenarioMessage>,
+}
+
+/// A message of a scenario
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioMessage {
+    pub delay_ms: u64,
+    /// Generated when omitted
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub live_chat_id: Option<String>,
+    #[serde(default)]
+    pub author_channel_id: Option<String>,
+    /// Filled in from the author registry when empty or omitted
+    #[serde(default)]
+    pub author_display_name: String,
+    pub message_text: String,
+}
+
+/// Progress of a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScenarioState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Progress report of a scenario
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioResponse {
+    pub id: String,
+    pub state: ScenarioState,
+    pub started_at: DateTime<Utc>,
+    pub total: usize,
+    pub inserted: usize,
+    /// When the next message is due, while the scenario runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_message_at: Option<DateTime<Utc>>,
+    /// Why the scenario failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A message with its offset from the scenario start
+struct ScheduledMessage {
+    offset: Duration,
+    message: domain::LiveChatMessage,
+}
+
+struct Scenario {
+    started_at: DateTime<Utc>,
+    /// Offsets of the messages from the start, in insertion order
+    offsets: Vec<Duration>,
+    inserted: AtomicUsize,
+    status: Mutex<(ScenarioState, Option<String>)>,
+    task: Mutex<Option<tokio::task::AbortHandle>>,
+}
+
+impl Scenario {
+    fn report(&self, id: &str) -> ScenarioResponse {
+        let (state, error) = self
+            .status
+            .lock()
+            .expect("Failed to acquire lock on scenario")
+            .clone();
+        let inserted = self.inserted.load(Ordering::SeqCst);
+        let next_message_at = (state == ScenarioState::Running)
+            .then(|| self.offsets.get(inserted))
+            .flatten()
+            .and_then(|offset| chrono::Duration::from_std(*offset).ok())
+            .map(|offset| self.started_at + offset);
+        ScenarioResponse {
+            id: id.to_string(),
+            state,
+            started_at: self.started_at,
+            total: self.offsets.len(),
+            inserted,
+            next_message_at,
+            error,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.status
+            .lock()
+            .expect("Failed to acquire lock on scenario")
+            .0
+            == ScenarioState::Running
+    }
+
+    /// Move out of `Running`; a scenario that already ended keeps its state
+    fn finish(&self, state: ScenarioState, error: Option<String>) -> bool {
+        let mut status = self
+            .status
+            .lock()
+            .expect("Failed to acquire lock on scenario");
+        if status.0 != ScenarioState::Running {
+            return false;
+        }
+        *status = (state, error);
+        true
+    }
+
+    /// Stop the task, unless the scenario already ended; returns whether it was running
+    fn cancel(&self) -> bool {
+        if !self.finish(ScenarioState::Cancelled, None) {
+            return false;
+        }
+        if let Some(task) = self
+            .task
+            .lock()
+            .expect("Failed to acquire lock on scenario")
+            .take()
+        {
+            task.abort();
+        }
+        true
+    }
+}
+
+/// Scenarios started through the control API, kept after they end so their outcome can be read
+#[derive(Default)]
+pub struct ScenarioRegistry {
+    scenarios: Mutex<HashMap<String, Arc<Scenario>>>,
+}
+
+impl ScenarioRegistry {
+    fn get(&self, id: &str) -> Option<Arc<Scenario>> {
+        self.scenarios
+            .lock()
+            .expect("Failed to acquire lock on scenario registry")
+            .get(id)
+            .cloned()
+    }
+
+    /// Cancel every running scenario and forget them all; returns how many were running
+    pub(crate) fn clear(&self) -> usize {
+        let scenarios = std::mem::take(
+            &mut *self
+                .scenarios
+                .lock()
+                .expect("Failed to acquire lock on scenario registry"),
+        );
+        scenarios
+            .values()
+            .filter(|scenario| scenario.cancel())
+            .count()
+    }
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    let response = ErrorResponse {
+        success: false,
+        error,
+    };
+    (status, Json(response)).into_response()
+}
+
+fn not_found(id: &str) -> Response {
+    error_response(StatusCode::NOT_FOUND, format!("Scenario '{id}' not found"))
+}
+
+/// Validate the messages and order them by when they are due
+fn schedule(
+    request: CreateScenarioRequest,
+    max_text_len: Option<usize>,
+) -> Result<Vec<ScheduledMessage>, String> {
+    if request.messages.is_empty() {
+        return Err("A scenario needs at least one message".to_string());
+    }
+    let mut elapsed = Duration::ZERO;
+    let mut scheduled = Vec::with_capacity(request.messages.len());
+    for (index, message) in request.messages.into_iter().enumerate() {
+        let Some(live_chat_id) = message
+            .live_chat_id
+            .or_else(|| request.live_chat_id.clone())
+            .filter(|id| !id.is_empty())
+        else {
+            return Err(format!("Message {index} has no liveChatId"));
+        };
+        domain::validate_message_text_len(&message.message_text, max_text_len)
+            .map_err(|e| format!("invalidValue: message {index}: {e}"))?;
+        let delay = Duration::from_millis(message.delay_ms);
+        let offset = match request.relative_to {
+            DelayOrigin::Start => delay,
+            DelayOrigin::Previous => elapsed + delay,
+        };
+        elapsed = offset;
+        scheduled.push(ScheduledMessage {
+            offset,
+            message: domain::LiveChatMessage {
+                id: message
+                    .id
+                    .unwrap_or_else(|| format!("msg-{}", uuid::Uuid::new_v4())),
+                live_chat_id,
+                author_channel_id: message
+                    .author_channel_id
+                    .unwrap_or_else(|| DEFAULT_SCENARIO_AUTHOR_CHANNEL_ID.to_string()),
+                author_display_name: message.author_display_name,
+                message_text: message.message_text,
+                // Set when the message is inserted
+                published_at: DateTime::<Utc>::MIN_UTC,
+                is_verified: false,
+                super_chat_details: None,
+                super_sticker_details: None,
+                deleted_message_id: None,
+                author_profile: Default::default(),
+                muted: false,
+            },
+        });
+    }
+    // Delays measured from the start may be listed out of order; ties keep their order
+    scheduled.sort_by_key(|scheduled| scheduled.offset);
+    Ok(scheduled)
+}
+
+/// Handler for starting a scenario
+pub(crate) async fn create_scenario(
+    State(state): State<ControlState>,
+    ControlJson(request): ControlJson<CreateScenarioRequest>,
+) -> Response {
+    let scheduled = match schedule(request, state.max_text_len) {
+        Ok(scheduled) => scheduled,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let id = format!("scenario-{}", uuid::Uuid::new_v4());
+    let scenario = Arc::new(Scenario {
+        started_at: clock::system_clock().now(),
+        offsets: scheduled.iter().map(|scheduled| scheduled.offset).collect(),
+        inserted: AtomicUsize::new(0),
+        status: Mutex::new((ScenarioState::Running, None)),
+        task: Mutex::new(None),
+    });
+    state
+        .scenarios
+        .scenarios
+        .lock()
+        .expect("Failed to acquire lock on scenario registry")
+        .insert(id.clone(), Arc::clone(&scenario));
+
+    let start = tokio::time::Instant::now();
+    let repo = Arc::clone(&state.repo);
+    let task = tokio::spawn({
+        let scenario = Arc::clone(&scenario);
+        let id = id.clone();
+        async move {
+            for ScheduledMessage {
+                offset,
+                mut message,
+            } in scheduled
+            {
+                tokio::time::sleep_until(start + offset).await;
+                if !scenario.is_running() {
+                    return;
+                }
+                message.published_at = clock::system_clock().now();
+                if let Err(e) = repo.add_chat_message(message) {
+                    tracing::warn!("Scenario {id} failed: {e}");
+                    scenario.finish(ScenarioState::Failed, Some(e.to_string()));
+                    return;
+                }
+                scenario.inserted.fetch_add(1, Ordering::SeqCst);
+            }
+            scenario.finish(ScenarioState::Completed, None);
+        }
+    });
+    *scenario
+        .task
+        .lock()
+        .expect("Failed to acquire lock on scenario") = Some(task.abort_handle());
+
+    (StatusCode::CREATED, Json(scenario.report(&id))).into_response()
+}
+
+/// Handler for reporting the progress of a scenario
+pub(crate) async fn get_scenario(
+    State(state): State<ControlState>,
+    Path(id): Path<String>,
+) -> Response {
+    match state.scenarios.get(&id) {
+        Some(scenario) => (StatusCode::OK, Json(scenario.report(&id))).into_response(),
+        None => not_found(&id),
+    }
+}
+
+/// Handler for cancelling a scenario; messages already inserted stay in their chats
+pub(crate) async fn cancel_scenario(
+    State(state): State<ControlState>,
+    Path(id): Path<String>,
+) -> Response {
+    let Some(scenario) = state.scenarios.get(&id) else {
+        return not_found(&id);
+    };
+    scenario.cancel();
+    (StatusCode::OK, Json(scenario.report(&id))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(delay_ms: u64, text: &str) -> ScenarioMessage {
+        ScenarioMessage {
+            delay_ms,
+            id: None,
+            live_chat_id: None,
+            author_channel_id: None,
+            author_display_name: String::new(),
+            message_text: text.to_string(),
+        }
+    }
+
+    fn request(relative_to: DelayOrigin, messages: Vec<ScenarioMessage>) -> CreateScenarioRequest {
+        CreateScenarioRequest {
+            live_chat_id: Some("chat".to_string()),
+            relative_to,
+            messages,
+        }
+    }
+
+    #[test]
+    fn test_delays_are_measured_from_start_or_previous_message() {
+        let messages = vec![message(300, "a"), message(100, "b"), message(100, "c")];
+
+        let from_start = schedule(request(DelayOrigin::Start, messages.clone()), None).unwrap();
+        let order: Vec<_> = from_start
+            .iter()
+            .map(|scheduled| {
+                (
+                    scheduled.offset.as_millis(),
+                    scheduled.message.message_text.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(order, [(100, "b"), (100, "c"), (300, "a")]);
+
+        let from_previous = schedule(request(DelayOrigin::Previous, messages), None).unwrap();
+        let offsets: Vec<_> = from_previous
+            .iter()
+            .map(|scheduled| scheduled.offset.as_millis())
+            .collect();
+        assert_eq!(offsets, [300, 400, 500]);
+        assert_eq!(from_previous[0].message.live_chat_id, "chat");
+        assert_eq!(
+            from_previous[0].message.author_channel_id,
+            DEFAULT_SCENARIO_AUTHOR_CHANNEL_ID
+        );
+    }
+
+    #[test]
+    fn test_invalid_scenarios_are_rejected() {
+        assert!(schedule(request(DelayOrigin::Start, vec![]), None).is_err());
+
+        let mut without_chat = request(DelayOrigin::Start, vec![message(0, "a")]);
+        without_chat.live_chat_id = None;
+        assert_eq!(
+            schedule(without_chat, None).err().unwrap(),
+            "Message 0 has no liveChatId"
+        );
+
+        let too_long = request(
+            DelayOrigin::Start,
+            vec![message(0, "ok"), message(0, "long")],
+        );
+        assert!(
+            schedule(too_long, Some(3))
+                .err()
+                .unwrap()
+                .starts_with("invalidValue: message 1:")
+        );
+    }
+}
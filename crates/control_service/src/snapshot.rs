@@ -0,0 +1,98 @@
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use domain::LiveChatState;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{ControlState, repository_error_response};
+
+/// State of a single chat in the snapshot
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSnapshot {
+    pub live_chat_id: String,
+    /// Lifecycle state; chats without a stored lifecycle are active until their video ends
+    pub state: LiveChatState,
+    pub message_count: usize,
+    /// Stored messages posted while their author was muted, which other viewers do not see
+    pub muted_message_count: usize,
+    /// Open gRPC streams for the chat
+    pub active_streams: usize,
+    /// Whether a warm-up has run for the chat
+    pub warm: bool,
+}
+
+/// Response for the state endpoint
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateResponse {
+    pub captured_at: DateTime<Utc>,
+    #[serde(serialize_with = "crate::casing::videos")]
+    pub videos: Vec<domain::Video>,
+    pub chats: Vec<ChatSnapshot>,
+    /// Open gRPC streams across all chats
+    pub active_streams: usize,
+    /// Closed gRPC streams per close reason
+    pub closed_streams: BTreeMap<String, u64>,
+    /// Most recently closed gRPC streams, oldest first
+    pub stream_timeline: Vec<domain::ClosedStream>,
+    pub tokens: oauth_service::TokenStoreSummary,
+}
+
+/// Handler returning a snapshot of the whole mock state, for attaching to bug reports
+pub(crate) async fn state(State(state): State<ControlState>) -> Response {
+    let videos = match state.repo.get_videos() {
+        Ok(videos) => videos,
+        Err(e) => return repository_error_response(&e),
+    };
+    let stream_counts = state.streams.snapshot();
+
+    // Chats known to the datastore, referenced by videos, streamed or warmed up
+    let mut chat_ids: BTreeSet<String> = match state.repo.get_live_chat_ids() {
+        Ok(ids) => ids.into_iter().collect(),
+        Err(e) => return repository_error_response(&e),
+    };
+    chat_ids.extend(videos.iter().filter_map(|video| video.live_chat_id.clone()));
+    chat_ids.extend(stream_counts.keys().cloned());
+    chat_ids.extend(state.warmup.warmed_chat_ids());
+
+    let mut chats = Vec::with_capacity(chat_ids.len());
+    for live_chat_id in chat_ids {
+        let (message_count, muted_message_count) = match state.repo.get_chat_messages(&live_chat_id)
+        {
+            Ok(messages) => (
+                messages.len(),
+                messages.iter().filter(|message| message.muted).count(),
+            ),
+            Err(e) => return repository_error_response(&e),
+        };
+        let lifecycle = match state.repo.get_effective_live_chat(&live_chat_id) {
+            Ok(chat) => chat.map_or(LiveChatState::Active, |chat| chat.state),
+            Err(e) => return repository_error_response(&e),
+        };
+        chats.push(ChatSnapshot {
+            state: lifecycle,
+            message_count,
+            muted_message_count,
+            active_streams: stream_counts.get(&live_chat_id).copied().unwrap_or(0),
+            warm: state.warmup.is_warm(&live_chat_id),
+            live_chat_id,
+        });
+    }
+
+    let response = StateResponse {
+        captured_at: clock::system_clock().now(),
+        videos,
+        chats,
+        active_streams: stream_counts.values().sum(),
+        closed_streams: state.streams.closed_counts(),
+        stream_timeline: state.streams.timeline(),
+        tokens: oauth_service::token_store_summary(),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
@@ -0,0 +1,222 @@
+//! Detection of unknown fields in control request bodies
+//!
+//! Bodies are read in two passes: first as a raw JSON value, then into the request type
+//! while recording every field the type ignored, including those of nested objects. By
+//! default the request is applied and the ignored fields are reported in a `warnings`
+//! array of the JSON response; with `?strict=true` the request is rejected with 422.
+
+use crate::ErrorResponse;
+use axum::{
+    Json,
+    body::Body,
+    extract::{FromRequest, Query, Request},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, Mutex};
+
+/// Unknown fields found while extracting the body of the current request
+#[derive(Debug, Clone, Default)]
+struct UnknownFields(Arc<Mutex<Vec<String>>>);
+
+#[derive(Debug, Default, Deserialize)]
+struct StrictParams {
+    #[serde(default)]
+    strict: bool,
+}
+
+/// JSON body extractor for control requests that reports unknown fields
+#[derive(Debug)]
+pub struct ControlJson<T>(pub T);
+
+/// Deserialize `value` into `T`, returning the paths of the fields `T` does not know
+pub(crate) fn deserialize_reporting_unknown<T: DeserializeOwned>(
+    value: serde_json::Value,
+) -> Result<(T, Vec<String>), serde_json::Error> {
+    let mut unknown = Vec::new();
+    let parsed = serde_ignored::deserialize(value, |path| unknown.push(path.to_string()))?;
+    Ok((parsed, unknown))
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    let error = ErrorResponse {
+        success: false,
+        error,
+    };
+    (status, Json(error)).into_response()
+}
+
+impl<T, S> FromRequest<S> for ControlJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let strict = match request.uri().query() {
+            Some(_) => match Query::<StrictParams>::try_from_uri(request.uri()) {
+                Ok(Query(params)) => params.strict,
+                Err(_) => {
+                    return Err(error_response(
+                        StatusCode::BAD_REQUEST,
+                        "Invalid 'strict' parameter: expected true or false".to_string(),
+                    ));
+                }
+            },
+            None => false,
+        };
+        let reported = request.extensions().get::<UnknownFields>().cloned();
+
+        let Json(value) = Json::<serde_json::Value>::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let (parsed, unknown) = deserialize_reporting_unknown(value).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Failed to deserialize the JSON body into the target type: {e}"),
+            )
+                .into_response()
+        })?;
+
+        if !unknown.is_empty() {
+            if strict {
+                return Err(error_response(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("Unknown fields in request body: {}", unknown.join(", ")),
+                ));
+            }
+            if let Some(UnknownFields(reported)) = reported {
+                reported
+                    .lock()
+                    .expect("Unknown fields lock poisoned")
+                    .extend(unknown);
+            }
+        }
+        Ok(ControlJson(parsed))
+    }
+}
+
+/// Middleware adding a `warnings` array to JSON object responses of requests whose
+/// body had unknown fields
+pub(crate) async fn report_unknown_fields(mut request: Request, next: Next) -> Response {
+    let unknown = UnknownFields::default();
+    request.extensions_mut().insert(unknown.clone());
+    let response = next.run(request).await;
+
+    let unknown = std::mem::take(&mut *unknown.0.lock().expect("Unknown fields lock poisoned"));
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value == "application/json");
+    if unknown.is_empty() || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read response body: {e}"),
+            );
+        }
+    };
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            let warnings = unknown
+                .iter()
+                .map(|path| format!("Unknown field '{path}' was ignored").into())
+                .collect();
+            object.insert("warnings".to_string(), serde_json::Value::Array(warnings));
+            serde_json::Value::Object(object).to_string().into()
+        }
+        _ => bytes,
+    };
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::post};
+    use tower::ServiceExt;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Outer {
+        name: String,
+        inner: Inner,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Inner {
+        display_name: String,
+    }
+
+    #[test]
+    fn test_nested_unknown_fields_are_reported_by_path() {
+        let (parsed, unknown) = deserialize_reporting_unknown::<Outer>(serde_json::json!({
+            "name": "outer",
+            "extra": 1,
+            "inner": {"displayName": "Inner", "displayname": "typo"},
+        }))
+        .unwrap();
+        assert_eq!(parsed.name, "outer");
+        assert_eq!(parsed.inner.display_name, "Inner");
+        assert_eq!(unknown, ["extra", "inner.displayname"]);
+    }
+
+    #[tokio::test]
+    async fn test_nested_unknown_fields_in_responses() {
+        let router = Router::new()
+            .route(
+                "/outer",
+                post(|ControlJson(outer): ControlJson<Outer>| async move {
+                    Json(serde_json::json!({ "success": true, "name": outer.name }))
+                }),
+            )
+            .layer(axum::middleware::from_fn(report_unknown_fields));
+        let send = |uri: &'static str| {
+            let request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"{"name":"outer","inner":{"displayName":"Inner","colour":"red"}}"#,
+                ))
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+        let read = |response: Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+
+        let response = send("/outer").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = read(response).await;
+        assert_eq!(body["name"], "outer");
+        assert_eq!(
+            body["warnings"],
+            serde_json::json!(["Unknown field 'inner.colour' was ignored"])
+        );
+
+        let response = send("/outer?strict=true").await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            read(response).await["error"],
+            "Unknown fields in request body: inner.colour"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Reading, patching and deleting individual videos, with optional optimistic concurrency
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use datastore::RepositoryError;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{ControlJson, CreateResponse, ErrorResponse, repository_error_response};
+
+/// Request body for patching a video; absent fields are left unchanged
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchVideoRequest {
+    pub channel_id: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub channel_title: Option<String>,
+    pub live_chat_id: Option<String>,
+    pub actual_start_time: Option<DateTime<Utc>>,
+    pub actual_end_time: Option<DateTime<Utc>>,
+    pub scheduled_start_time: Option<DateTime<Utc>>,
+    pub scheduled_end_time: Option<DateTime<Utc>>,
+    pub concurrent_viewers: Option<u64>,
+}
+
+impl PatchVideoRequest {
+    /// Merge the present fields into `video`
+    fn apply(&self, video: &mut domain::Video) {
+        fn merge<T: Clone>(field: &mut T, patch: &Option<T>) {
+            if let Some(value) = patch {
+                *field = value.clone();
+            }
+        }
+        fn merge_option<T: Clone>(field: &mut Option<T>, patch: &Option<T>) {
+            if patch.is_some() {
+                field.clone_from(patch);
+            }
+        }
+
+        merge(&mut video.channel_id, &self.channel_id);
+        merge(&mut video.title, &self.title);
+        merge(&mut video.description, &self.description);
+        merge(&mut video.channel_title, &self.channel_title);
+        merge_option(&mut video.live_chat_id, &self.live_chat_id);
+        merge_option(&mut video.actual_start_time, &self.actual_start_time);
+        merge_option(&mut video.actual_end_time, &self.actual_end_time);
+        merge_option(&mut video.scheduled_start_time, &self.scheduled_start_time);
+        merge_option(&mut video.scheduled_end_time, &self.scheduled_end_time);
+        merge_option(&mut video.concurrent_viewers, &self.concurrent_viewers);
+    }
+}
+
+/// Response carrying a video and its version, which is also sent as the `ETag`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoResponse {
+    pub success: bool,
+    #[serde(serialize_with = "crate::casing::video")]
+    pub video: domain::Video,
+    pub version: u64,
+}
+
+/// `ETag` header value of a version
+pub(crate) fn etag(version: u64) -> String {
+    format!("\"{version}\"")
+}
+
+/// Version required by an `If-Match` header, `None` when the header is absent or `*`
+pub(crate) fn if_match_version(headers: &HeaderMap) -> Result<Option<u64>, String> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse::<u64>()
+        .map(Some)
+        .map_err(|_| format!("Invalid If-Match header '{value}'. Use a version such as \"3\""))
+}
+
+/// 409 response for an `If-Match` version that is no longer current
+pub(crate) fn version_conflict(
+    kind: &str,
+    id: &str,
+    expected: u64,
+    current: Option<u64>,
+) -> Response {
+    let current = current.map_or_else(|| "unknown".to_string(), |version| version.to_string());
+    let response = ErrorResponse {
+        success: false,
+        error: format!(
+            "{kind} '{id}' was modified concurrently: If-Match version {expected} does not match current version {current}"
+        ),
+    };
+    (StatusCode::CONFLICT, Json(response)).into_response()
+}
+
+fn video_response(video: domain::Video, version: u64) -> Response {
+    let etag = etag(version);
+    let response = VideoResponse {
+        success: true,
+        video,
+        version,
+    };
+    (StatusCode::OK, [(header::ETAG, etag)], Json(response)).into_response()
+}
+
+/// Handler for reading a video with its version
+pub(crate) async fn get_video(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let video = repo
+        .get_video(&id)
+        .and_then(|video| Ok(video.zip(repo.get_video_version(&id)?)));
+    match video {
+        Ok(Some((video, version))) => video_response(video, version),
+        Ok(None) => repository_error_response(&RepositoryError::NotFound),
+        Err(e) => repository_error_response(&e),
+    }
+}
+
+/// Handler for patching a video
+///
+/// The present fields are merged into the stored video atomically, so concurrent patches to
+/// different fields never lose each other's changes. With `If-Match`, the patch only applies
+/// if the video is still at that version and fails with 409 otherwise.
+pub(crate) async fn patch_video(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    ControlJson(request): ControlJson<PatchVideoRequest>,
+) -> impl IntoResponse {
+    let expected_version = match if_match_version(&headers) {
+        Ok(version) => version,
+        Err(error) => {
+            let response = ErrorResponse {
+                success: false,
+                error,
+            };
+            return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+        }
+    };
+
+    let result = repo.update_video_with(&id, expected_version, &mut |video| {
+        request.apply(video);
+        video.validate_times().map_err(RepositoryError::Invalid)
+    });
+    match (result, expected_version) {
+        (Ok((video, version)), _) => video_response(video, version),
+        (Err(RepositoryError::Conflict), Some(expected)) => version_conflict(
+            "Video",
+            &id,
+            expected,
+            repo.get_video_version(&id).ok().flatten(),
+        ),
+        (Err(e), _) => repository_error_response(&e),
+    }
+}
+
+/// Handler for deleting a video
+/// The video's chat messages and lifecycle are kept, so a video re-created with the same
+/// chat picks them up again
+pub(crate) async fn delete_video(
+    State(repo): State<Arc<dyn datastore::Repository>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match repo.delete_video(&id) {
+        Ok(()) => {
+            let response = CreateResponse {
+                success: true,
+                message: format!("Video '{id}' deleted successfully"),
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(RepositoryError::NotFound) => {
+            let response = ErrorResponse {
+                success: false,
+                error: format!("Video '{id}' not found"),
+            };
+            (StatusCode::NOT_FOUND, Json(response)).into_response()
+        }
+        Err(e) => repository_error_response(&e),
+    }
+}
@@ -0,0 +1,322 @@
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::{ControlJson, ControlState, repository_error_response};
+
+/// Interval at which internal no-op subscribers recheck the chat without a change notification,
+/// matching the stream polling interval
+const WARMUP_POLLING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default duration for holding internal warm-up streams open
+const DEFAULT_HOLD_SECONDS: u64 = 30;
+
+/// Request body for warming up chats before a load test
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupRequest {
+    pub live_chat_ids: Vec<String>,
+    /// Number of internal no-op subscriber streams to hold open per chat
+    #[serde(default)]
+    pub streams: usize,
+    /// Whether to read each chat's full message history and start the subscribers after it
+    #[serde(default)]
+    pub preload_history: bool,
+    /// How long the internal subscriber streams are held open
+    #[serde(default = "default_hold_seconds")]
+    pub hold_seconds: u64,
+}
+
+fn default_hold_seconds() -> u64 {
+    DEFAULT_HOLD_SECONDS
+}
+
+/// Timing of a single warm-up step
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupStep {
+    pub step: String,
+    pub live_chat_id: String,
+    pub duration_micros: u128,
+}
+
+/// Response for a warm-up request
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupResponse {
+    pub success: bool,
+    pub steps: Vec<WarmupStep>,
+    pub total_duration_micros: u128,
+}
+
+/// Per-chat warm state as reported by the stats endpoint
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatStats {
+    pub live_chat_id: String,
+    /// "warm" once a warm-up has run for the chat, "cold" otherwise
+    pub state: String,
+    pub message_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warmed_at: Option<DateTime<Utc>>,
+    pub active_warmup_streams: usize,
+    /// Messages read by the last history preload of the chat
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preloaded_messages: Option<usize>,
+}
+
+/// Response for the stats endpoint
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsResponse {
+    pub chats: Vec<ChatStats>,
+    /// Closed gRPC streams per close reason, e.g. `timeout` or `error{internal}`
+    pub closed_streams: BTreeMap<String, u64>,
+    /// gRPC streams whose first response was an empty fallback sent because the history was
+    /// not read within `FIRST_RESPONSE_BUDGET_MS`
+    pub slow_starts: u64,
+}
+
+/// Warm state of a single chat
+struct ChatWarmState {
+    warmed_at: DateTime<Utc>,
+    active_streams: Arc<AtomicUsize>,
+    /// Subscription to the chat's change notifier, kept so the notifier stays in place
+    _changes: watch::Receiver<u64>,
+    preloaded_messages: Option<usize>,
+}
+
+/// Registry of chats that have been warmed up
+#[derive(Default)]
+pub struct WarmupRegistry {
+    chats: RwLock<HashMap<String, ChatWarmState>>,
+}
+
+impl WarmupRegistry {
+    /// Mark a chat as warm, holding `changes` on its first warm-up, and return its active
+    /// stream counter
+    fn mark_warm(&self, live_chat_id: &str, changes: watch::Receiver<u64>) -> Arc<AtomicUsize> {
+        let mut chats = self
+            .chats
+            .write()
+            .expect("Failed to acquire write lock on warm-up registry");
+        let state = chats
+            .entry(live_chat_id.to_string())
+            .or_insert_with(|| ChatWarmState {
+                warmed_at: clock::system_clock().now(),
+                active_streams: Arc::new(AtomicUsize::new(0)),
+                _changes: changes,
+                preloaded_messages: None,
+            });
+        state.warmed_at = clock::system_clock().now();
+        Arc::clone(&state.active_streams)
+    }
+
+    /// Record that the chat's history was preloaded up to `messages`
+    fn mark_preloaded(&self, live_chat_id: &str, messages: usize) {
+        if let Some(state) = self
+            .chats
+            .write()
+            .expect("Failed to acquire write lock on warm-up registry")
+            .get_mut(live_chat_id)
+        {
+            state.preloaded_messages = Some(messages);
+        }
+    }
+
+    /// Forget every warm-up
+    pub(crate) fn clear(&self) {
+        self.chats
+            .write()
+            .expect("Failed to acquire write lock on warm-up registry")
+            .clear();
+    }
+
+    /// Whether a warm-up has run for the chat
+    pub fn is_warm(&self, live_chat_id: &str) -> bool {
+        self.chats
+            .read()
+            .expect("Failed to acquire read lock on warm-up registry")
+            .contains_key(live_chat_id)
+    }
+
+    /// IDs of all warmed chats
+    pub(crate) fn warmed_chat_ids(&self) -> Vec<String> {
+        self.chats
+            .read()
+            .expect("Failed to acquire read lock on warm-up registry")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Warm-up timestamp, active stream count and preloaded messages of a chat
+    fn state_of(&self, live_chat_id: &str) -> Option<(DateTime<Utc>, usize, Option<usize>)> {
+        self.chats
+            .read()
+            .expect("Failed to acquire read lock on warm-up registry")
+            .get(live_chat_id)
+            .map(|state| {
+                (
+                    state.warmed_at,
+                    state.active_streams.load(Ordering::SeqCst),
+                    state.preloaded_messages,
+                )
+            })
+    }
+}
+
+/// Handler for warming up chats before a load test
+pub(crate) async fn warmup(
+    State(state): State<ControlState>,
+    ControlJson(request): ControlJson<WarmupRequest>,
+) -> impl IntoResponse {
+    let total_start = Instant::now();
+    let hold = Duration::from_secs(request.hold_seconds);
+    let mut steps = Vec::new();
+
+    for live_chat_id in &request.live_chat_ids {
+        // Create the chat's change notifier, which every stream of the chat subscribes to, and
+        // make the lookups a stream makes when it opens
+        let start = Instant::now();
+        let changes = state.repo.subscribe(live_chat_id);
+        let lookups = state
+            .repo
+            .live_chat_exists(live_chat_id)
+            .and_then(|_| state.repo.get_effective_live_chat(live_chat_id));
+        if let Err(e) = lookups {
+            return repository_error_response(&e);
+        }
+        let active_streams = state.warmup.mark_warm(live_chat_id, changes);
+        steps.push(WarmupStep {
+            step: "dispatcher".to_string(),
+            live_chat_id: live_chat_id.clone(),
+            duration_micros: start.elapsed().as_micros(),
+        });
+
+        // Subscribers start after the history when it is preloaded, at the end of the chat
+        // when it is not
+        let cursor = if request.preload_history {
+            let start = Instant::now();
+            let history = match state.repo.get_chat_messages_from(live_chat_id, 0) {
+                Ok(history) => history,
+                Err(e) => return repository_error_response(&e),
+            };
+            let cursor = history.last().map_or(0, |(position, _)| position + 1);
+            state.warmup.mark_preloaded(live_chat_id, history.len());
+            steps.push(WarmupStep {
+                step: "preloadHistory".to_string(),
+                live_chat_id: live_chat_id.clone(),
+                duration_micros: start.elapsed().as_micros(),
+            });
+            cursor
+        } else {
+            match state.repo.count_chat_messages(live_chat_id) {
+                Ok(count) => count,
+                Err(e) => return repository_error_response(&e),
+            }
+        };
+
+        if request.streams > 0 {
+            let start = Instant::now();
+            for _ in 0..request.streams {
+                spawn_noop_subscriber(
+                    Arc::clone(&state.repo),
+                    live_chat_id.clone(),
+                    cursor,
+                    Arc::clone(&active_streams),
+                    hold,
+                );
+            }
+            steps.push(WarmupStep {
+                step: "openStreams".to_string(),
+                live_chat_id: live_chat_id.clone(),
+                duration_micros: start.elapsed().as_micros(),
+            });
+        }
+    }
+
+    let response = WarmupResponse {
+        success: true,
+        steps,
+        total_duration_micros: total_start.elapsed().as_micros(),
+    };
+
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Spawn an internal subscriber that follows the chat from `cursor` like a stream would: it
+/// waits on the chat's change notifier and reads the new messages, without sending anything
+fn spawn_noop_subscriber(
+    repo: Arc<dyn datastore::Repository>,
+    live_chat_id: String,
+    mut cursor: usize,
+    active_streams: Arc<AtomicUsize>,
+    hold: Duration,
+) {
+    active_streams.fetch_add(1, Ordering::SeqCst);
+    tokio::spawn(async move {
+        let until = tokio::time::Instant::now() + hold;
+        let mut changes = repo.subscribe(&live_chat_id);
+        while tokio::time::Instant::now() < until {
+            if let Ok(new) = repo.get_chat_messages_from(&live_chat_id, cursor)
+                && let Some((position, _)) = new.last()
+            {
+                cursor = position + 1;
+            }
+            tokio::select! {
+                changed = changes.changed() => {
+                    if changed.is_err() {
+                        tokio::time::sleep(WARMUP_POLLING_INTERVAL).await;
+                    }
+                }
+                _ = tokio::time::sleep(WARMUP_POLLING_INTERVAL) => {}
+                _ = tokio::time::sleep_until(until) => {}
+            }
+        }
+        active_streams.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+/// Handler for reporting per-chat warm/cold state
+pub(crate) async fn stats(State(state): State<ControlState>) -> impl IntoResponse {
+    // Known chats are those referenced by videos plus any chat that was warmed up
+    let videos = match state.repo.get_videos() {
+        Ok(videos) => videos,
+        Err(e) => return repository_error_response(&e),
+    };
+    let mut chat_ids: BTreeSet<String> = videos
+        .into_iter()
+        .filter_map(|video| video.live_chat_id)
+        .collect();
+    chat_ids.extend(state.warmup.warmed_chat_ids());
+
+    let mut chats = Vec::with_capacity(chat_ids.len());
+    for live_chat_id in chat_ids {
+        let message_count = match state.repo.get_chat_messages(&live_chat_id) {
+            Ok(messages) => messages.len(),
+            Err(e) => return repository_error_response(&e),
+        };
+        let warm_state = state.warmup.state_of(&live_chat_id);
+        chats.push(ChatStats {
+            state: if warm_state.is_some() { "warm" } else { "cold" }.to_string(),
+            message_count,
+            warmed_at: warm_state.map(|(warmed_at, _, _)| warmed_at),
+            active_warmup_streams: warm_state.map(|(_, active, _)| active).unwrap_or(0),
+            preloaded_messages: warm_state.and_then(|(_, _, preloaded)| preloaded),
+            live_chat_id,
+        });
+    }
+
+    let response = StatsResponse {
+        chats,
+        closed_streams: state.streams.closed_counts(),
+        slow_starts: state.streams.slow_starts(),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
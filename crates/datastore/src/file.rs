@@ -0,0 +1,587 @@
+//! Repository persisted to a JSON file, so mock state survives restarts
+//!
+//! [`FileRepository`] keeps the data in an [`InMemoryRepository`] and writes a snapshot of
+//! its videos, channels, chat messages and chat lifecycles to the file after mutations,
+//! debounced so bursts of writes cost one snapshot. The snapshot is written to a temporary
+//! file and renamed into place, so a crash never leaves a half-written file behind. Each chat
+//! message is stored with its position, so deleted messages keep their positions empty and
+//! page tokens issued before a restart resume at the same message after it.
+
+use crate::{InMemoryRepository, Repository, RepositoryError, RepositoryResult, SeedData};
+use chrono::{DateTime, Utc};
+use domain::{Channel, LiveChat, LiveChatMessage, Video};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, mpsc};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// How long a mutation waits for further ones before the snapshot is written
+pub const PERSIST_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Contents of the file
+///
+/// Videos use the snake_case field names of seed files.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    #[serde(default)]
+    videos: Vec<serde_json::Value>,
+    #[serde(default)]
+    channels: Vec<Channel>,
+    #[serde(default)]
+    chat_messages: Vec<StoredMessage>,
+    #[serde(default)]
+    live_chats: Vec<LiveChat>,
+}
+
+/// A chat message and its position in its chat
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredMessage {
+    /// Missing in files written before positions were kept; such messages are appended
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<usize>,
+    #[serde(flatten)]
+    message: LiveChatMessage,
+}
+
+/// `camelCase` → `camel_case`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn video_record(video: &Video) -> RepositoryResult<serde_json::Value> {
+    match serde_json::to_value(video) {
+        Ok(serde_json::Value::Object(fields)) => Ok(fields
+            .into_iter()
+            .map(|(name, value)| (snake_case(&name), value))
+            .collect()),
+        Ok(_) => Err(RepositoryError::Backend(
+            "video did not serialize to an object".to_string(),
+        )),
+        Err(e) => Err(RepositoryError::Backend(e.to_string())),
+    }
+}
+
+/// Capture everything the file keeps
+fn capture(repo: &InMemoryRepository) -> RepositoryResult<Snapshot> {
+    let videos = repo
+        .get_videos()?
+        .iter()
+        .map(video_record)
+        .collect::<RepositoryResult<_>>()?;
+    let mut channels: Vec<Channel> = repo
+        .channels
+        .read()
+        .map_err(crate::poisoned)?
+        .values()
+        .cloned()
+        .collect();
+    channels.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut chat_messages = Vec::new();
+    let mut live_chats = Vec::new();
+    for id in repo.get_live_chat_ids()? {
+        chat_messages.extend(repo.get_chat_messages_from(&id, 0)?.into_iter().map(
+            |(position, message)| StoredMessage {
+                position: Some(position),
+                message,
+            },
+        ));
+        live_chats.extend(repo.get_live_chat(&id)?);
+    }
+    Ok(Snapshot {
+        videos,
+        channels,
+        chat_messages,
+        live_chats,
+    })
+}
+
+/// Restore a repository from the file's contents
+/// A reset restores these contents again, not what was written since.
+fn restore(json: &str) -> Result<InMemoryRepository, String> {
+    let snapshot: Snapshot = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let videos = snapshot
+        .videos
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<Video>, _>>()
+        .map_err(|e| format!("invalid video: {e}"))?;
+    let (chat_messages, channels, live_chats) = (
+        snapshot.chat_messages,
+        snapshot.channels,
+        snapshot.live_chats,
+    );
+    InMemoryRepository::populated(Arc::new(move |repo| {
+        for video in videos.iter().cloned() {
+            repo.add_video(video)?;
+        }
+        for stored in chat_messages.iter() {
+            let message = stored.message.clone();
+            match stored.position {
+                Some(position) => repo.add_chat_message_at(position, message)?,
+                None => repo.add_chat_message(message)?,
+            }
+        }
+        for channel in channels.iter().cloned() {
+            repo.add_channel(channel)?;
+        }
+        for chat in live_chats.iter().cloned() {
+            repo.save_live_chat(chat)?;
+        }
+        Ok(())
+    }))
+    .map_err(|e| e.to_string())
+}
+
+/// Serializes the writers of one file and remembers where it lives
+struct Persister {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl Persister {
+    /// Write a snapshot of `repo`, reporting failures without interrupting the caller
+    fn persist(&self, repo: &InMemoryRepository) {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let result = capture(repo).and_then(|snapshot| {
+            let json = serde_json::to_string_pretty(&snapshot)
+                .map_err(|e| RepositoryError::Backend(e.to_string()))?;
+            let tmp_path = self.path.with_extension("tmp");
+            std::fs::write(&tmp_path, json)
+                .and_then(|()| std::fs::rename(&tmp_path, &self.path))
+                .map_err(|e| RepositoryError::Backend(e.to_string()))
+        });
+        if let Err(e) = result {
+            tracing::error!("Failed to persist datastore to {:?}: {e}", self.path);
+        }
+    }
+}
+
+/// Repository persisted to a JSON file
+pub struct FileRepository {
+    inner: Arc<InMemoryRepository>,
+    persister: Arc<Persister>,
+    /// Wakes the background writer; started by the first mutation
+    writer: OnceLock<mpsc::Sender<()>>,
+}
+
+impl FileRepository {
+    /// Load the repository stored at `path`
+    ///
+    /// A missing or unreadable file is reported and the repository starts empty; the file
+    /// is (re)written after the first mutation. A corrupt file is first renamed to
+    /// `<path>.corrupt`, so its contents are not lost.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let inner = match std::fs::read_to_string(&path) {
+            Ok(json) => match restore(&json) {
+                Ok(repo) => {
+                    tracing::info!("Loaded datastore from {path:?}");
+                    repo
+                }
+                Err(e) => {
+                    // Kept aside so the first snapshot does not overwrite what was persisted
+                    let mut corrupt_path = path.clone().into_os_string();
+                    corrupt_path.push(".corrupt");
+                    let corrupt_path = PathBuf::from(corrupt_path);
+                    match std::fs::rename(&path, &corrupt_path) {
+                        Ok(()) => tracing::warn!(
+                            "Datastore file {path:?} is corrupt ({e}), moved it to \
+                             {corrupt_path:?} and starting empty"
+                        ),
+                        Err(rename_error) => tracing::error!(
+                            "Datastore file {path:?} is corrupt ({e}) and could not be moved \
+                             to {corrupt_path:?} ({rename_error}), starting empty"
+                        ),
+                    }
+                    InMemoryRepository::empty()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!("Datastore file {path:?} does not exist yet, starting empty");
+                InMemoryRepository::empty()
+            }
+            Err(e) => {
+                tracing::warn!("Failed to read datastore file {path:?} ({e}), starting empty");
+                InMemoryRepository::empty()
+            }
+        };
+        Self {
+            inner: Arc::new(inner),
+            persister: Arc::new(Persister {
+                path,
+                lock: Mutex::new(()),
+            }),
+            writer: OnceLock::new(),
+        }
+    }
+
+    /// Reject chat messages whose ID already exists in the same chat with `Conflict`
+    /// instead of appending a duplicate
+    pub fn with_unique_message_ids(mut self, unique_message_ids: bool) -> Self {
+        let inner = Arc::get_mut(&mut self.inner)
+            .map(|inner| std::mem::replace(inner, InMemoryRepository::empty()))
+            .expect("Configured before any mutation");
+        self.inner = Arc::new(inner.with_unique_message_ids(unique_message_ids));
+        self
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.persister.path
+    }
+
+    /// Write the snapshot now instead of after the debounce
+    pub fn flush(&self) {
+        self.persister.persist(&self.inner);
+    }
+
+    /// Schedule a snapshot after the debounce, on a background thread
+    fn changed(&self) {
+        let writer = self.writer.get_or_init(|| {
+            let (tx, rx) = mpsc::channel::<()>();
+            let inner = Arc::clone(&self.inner);
+            let persister = Arc::clone(&self.persister);
+            std::thread::spawn(move || {
+                while rx.recv().is_ok() {
+                    std::thread::sleep(PERSIST_DEBOUNCE);
+                    while rx.try_recv().is_ok() {}
+                    persister.persist(&inner);
+                }
+            });
+            tx
+        });
+        let _ = writer.send(());
+    }
+
+    /// Run a mutation, scheduling a snapshot when it succeeds
+    fn mutate<T>(&self, result: RepositoryResult<T>) -> RepositoryResult<T> {
+        if result.is_ok() {
+            self.changed();
+        }
+        result
+    }
+}
+
+/// Pending changes are written when the repository goes away
+impl Drop for FileRepository {
+    fn drop(&mut self) {
+        if self.writer.get().is_some() {
+            self.flush();
+        }
+    }
+}
+
+impl Repository for FileRepository {
+    fn get_video(&self, id: &str) -> RepositoryResult<Option<Video>> {
+        self.inner.get_video(id)
+    }
+
+    fn get_video_by_live_chat_id(&self, live_chat_id: &str) -> RepositoryResult<Option<Video>> {
+        self.inner.get_video_by_live_chat_id(live_chat_id)
+    }
+
+    fn get_videos(&self) -> RepositoryResult<Vec<Video>> {
+        self.inner.get_videos()
+    }
+
+    fn get_channel(&self, id: &str) -> RepositoryResult<Option<Channel>> {
+        self.inner.get_channel(id)
+    }
+
+    fn add_channel(&self, channel: Channel) -> RepositoryResult<()> {
+        self.mutate(self.inner.add_channel(channel))
+    }
+
+    fn get_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<Vec<LiveChatMessage>> {
+        self.inner.get_chat_messages(live_chat_id)
+    }
+
+    fn get_chat_messages_from(
+        &self,
+        live_chat_id: &str,
+        position: usize,
+    ) -> RepositoryResult<Vec<(usize, LiveChatMessage)>> {
+        self.inner.get_chat_messages_from(live_chat_id, position)
+    }
+
+    fn count_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<usize> {
+        self.inner.count_chat_messages(live_chat_id)
+    }
+
+    fn add_video(&self, video: Video) -> RepositoryResult<()> {
+        self.mutate(self.inner.add_video(video))
+    }
+
+    fn add_chat_message(&self, message: LiveChatMessage) -> RepositoryResult<()> {
+        self.mutate(self.inner.add_chat_message(message))
+    }
+
+    fn delete_video(&self, id: &str) -> RepositoryResult<()> {
+        self.mutate(self.inner.delete_video(id))
+    }
+
+    fn delete_chat_message(&self, id: &str, deleted_at: DateTime<Utc>) -> RepositoryResult<()> {
+        self.mutate(self.inner.delete_chat_message(id, deleted_at))
+    }
+
+    fn get_live_chat(&self, id: &str) -> RepositoryResult<Option<LiveChat>> {
+        self.inner.get_live_chat(id)
+    }
+
+    fn save_live_chat(&self, chat: LiveChat) -> RepositoryResult<()> {
+        self.mutate(self.inner.save_live_chat(chat))
+    }
+
+    fn update_video_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut Video) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(Video, u64)> {
+        self.mutate(self.inner.update_video_with(id, expected_version, update))
+    }
+
+    fn get_video_version(&self, id: &str) -> RepositoryResult<Option<u64>> {
+        self.inner.get_video_version(id)
+    }
+
+    fn update_live_chat_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut LiveChat) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(LiveChat, u64)> {
+        self.mutate(
+            self.inner
+                .update_live_chat_with(id, expected_version, update),
+        )
+    }
+
+    fn get_live_chat_version(&self, id: &str) -> RepositoryResult<Option<u64>> {
+        self.inner.get_live_chat_version(id)
+    }
+
+    fn get_live_chat_ids(&self) -> RepositoryResult<Vec<String>> {
+        self.inner.get_live_chat_ids()
+    }
+
+    fn subscribe(&self, live_chat_id: &str) -> watch::Receiver<u64> {
+        self.inner.subscribe(live_chat_id)
+    }
+
+    fn reset(&self, empty: bool) -> RepositoryResult<()> {
+        self.mutate(self.inner.reset(empty))
+    }
+
+    fn reseed(&self, seed: SeedData) -> RepositoryResult<()> {
+        self.mutate(self.inner.reseed(seed))
+    }
+
+    fn generation(&self) -> u64 {
+        self.inner.generation()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(id: &str, live_chat_id: Option<&str>) -> Video {
+        Video {
+            id: id.to_string(),
+            channel_id: "channel".to_string(),
+            title: "Title".to_string(),
+            description: "Description".to_string(),
+            channel_title: "Channel".to_string(),
+            published_at: Utc::now(),
+            live_chat_id: live_chat_id.map(str::to_string),
+            actual_start_time: Some(Utc::now()),
+            actual_end_time: None,
+            scheduled_start_time: None,
+            scheduled_end_time: None,
+            concurrent_viewers: Some(42),
+        }
+    }
+
+    #[test]
+    fn test_missing_file_starts_empty_and_is_created_on_mutation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let repo = FileRepository::open(&path);
+        assert!(repo.get_videos().unwrap().is_empty());
+        assert!(!path.exists());
+
+        repo.add_video(video("file-video", Some("file-chat")))
+            .unwrap();
+        // The debounced writer catches up on its own
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !path.exists() {
+            assert!(std::time::Instant::now() < deadline, "Snapshot not written");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        drop(repo);
+
+        let reopened = FileRepository::open(&path);
+        let restored = reopened.get_video("file-video").unwrap().expect("Restored");
+        assert_eq!(restored.concurrent_viewers, Some(42));
+        assert_eq!(restored.live_chat_id.as_deref(), Some("file-chat"));
+    }
+
+    #[test]
+    fn test_corrupt_file_starts_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        let repo = FileRepository::open(&path);
+        assert!(repo.get_videos().unwrap().is_empty());
+        assert!(repo.get_live_chat_ids().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_file_is_moved_aside_before_it_is_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "{ not json").unwrap();
+
+        let repo = FileRepository::open(&path);
+        let corrupt_path = dir.path().join("state.json.corrupt");
+        assert!(!path.exists());
+        repo.add_video(video("new-video", None)).unwrap();
+        repo.flush();
+        drop(repo);
+
+        assert_eq!(
+            std::fs::read_to_string(&corrupt_path).unwrap(),
+            "{ not json"
+        );
+        let reopened = FileRepository::open(&path);
+        assert!(reopened.get_video("new-video").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_deleted_messages_lifecycles_and_channels_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let seeded = InMemoryRepository::new();
+        let mut message = seeded.get_chat_messages("test-chat-id").unwrap()[0].clone();
+
+        let repo = FileRepository::open(&path);
+        for id in ["kept", "deleted"] {
+            message.id = id.to_string();
+            repo.add_chat_message(message.clone()).unwrap();
+        }
+        repo.delete_chat_message("deleted", Utc::now()).unwrap();
+        repo.save_live_chat(LiveChat::active("test-chat-id"))
+            .unwrap();
+        repo.add_channel(seeded.get_channel("channel-1").unwrap().unwrap())
+            .unwrap();
+        drop(repo);
+
+        let reopened = FileRepository::open(&path);
+        let ids: Vec<_> = reopened
+            .get_chat_messages("test-chat-id")
+            .unwrap()
+            .into_iter()
+            .map(|message| (message.id, message.deleted_message_id))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                ("kept".to_string(), None),
+                ("deleted-deleted".to_string(), Some("deleted".to_string()))
+            ]
+        );
+        assert!(reopened.get_live_chat("test-chat-id").unwrap().is_some());
+        assert!(reopened.get_channel("channel-1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_resume_across_a_restart_after_a_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let seeded = InMemoryRepository::new();
+        let mut message = seeded.get_chat_messages("test-chat-id").unwrap()[0].clone();
+
+        let repo = FileRepository::open(&path);
+        for id in ["first", "deleted", "last"] {
+            message.id = id.to_string();
+            repo.add_chat_message(message.clone()).unwrap();
+        }
+        repo.delete_chat_message("deleted", Utc::now()).unwrap();
+        let positions = |repo: &FileRepository, from| -> Vec<(usize, String)> {
+            repo.get_chat_messages_from("test-chat-id", from)
+                .unwrap()
+                .into_iter()
+                .map(|(position, message)| (position, message.id))
+                .collect()
+        };
+        let before = positions(&repo, 0);
+        drop(repo);
+
+        // A page token issued before the restart points after "first"
+        let reopened = FileRepository::open(&path);
+        assert_eq!(positions(&reopened, 0), before);
+        assert_eq!(
+            positions(&reopened, 1),
+            [(2, "last".to_string()), (3, "deleted-deleted".to_string())]
+        );
+        message.id = "after-restart".to_string();
+        reopened.add_chat_message(message).unwrap();
+        assert_eq!(positions(&reopened, 4), [(4, "after-restart".to_string())]);
+
+        // Files written before positions were kept still load, in order
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "chat_messages": [seeded.get_chat_messages("test-chat-id").unwrap()[0]]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            positions(&FileRepository::open(&path), 0),
+            [(0, "test-msg-id-0".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_reset_restores_the_loaded_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let repo = FileRepository::open(&path);
+        repo.add_video(video("loaded-video", Some("loaded-chat")))
+            .unwrap();
+        repo.save_live_chat(LiveChat::active("loaded-chat"))
+            .unwrap();
+        drop(repo);
+
+        let reopened = FileRepository::open(&path);
+        reopened
+            .add_video(video("added-video", Some("added-chat")))
+            .unwrap();
+        reopened.reset(false).unwrap();
+        let ids: Vec<_> = reopened
+            .get_videos()
+            .unwrap()
+            .into_iter()
+            .map(|video| video.id)
+            .collect();
+        assert_eq!(ids, ["loaded-video"]);
+        assert!(reopened.get_live_chat("loaded-chat").unwrap().is_some());
+
+        // The reset is written back to the file
+        reopened.reset(true).unwrap();
+        drop(reopened);
+        assert!(FileRepository::open(&path).get_videos().unwrap().is_empty());
+    }
+}
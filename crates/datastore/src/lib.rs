@@ -1,49 +1,389 @@
-use chrono::{TimeZone, Utc};
-use domain::{LiveChatMessage, Video};
+use chrono::{DateTime, TimeZone, Utc};
+use domain::{AuthorRegistry, Channel, LiveChat, LiveChatMessage, LiveChatState, Video};
 use fake::Fake;
 use fake::faker::internet::en::Username;
 use fake::faker::lorem::en::Sentence;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+
+pub mod file;
+pub mod seed;
+
+pub use file::FileRepository;
+pub use seed::SeedData;
+
+/// Errors returned by repository operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepositoryError {
+    /// The referenced entity does not exist
+    NotFound,
+    /// The operation conflicts with existing data
+    Conflict,
+    /// The storage backend failed (poisoned lock, I/O error, ...)
+    Backend(String),
+    /// The input was rejected by the backend
+    Invalid(String),
+}
+
+impl std::fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Not found"),
+            Self::Conflict => write!(f, "Conflict"),
+            Self::Backend(message) => write!(f, "Backend error: {message}"),
+            Self::Invalid(message) => write!(f, "Invalid input: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+/// Result type of repository operations
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
 
 /// Repository trait for data access abstraction
 /// This allows switching between different storage backends (in-memory, filesystem, database)
 pub trait Repository: Send + Sync {
-    /// Get a video by ID
-    fn get_video(&self, id: &str) -> Option<Video>;
+    /// Get a video by ID, `None` if it does not exist
+    fn get_video(&self, id: &str) -> RepositoryResult<Option<Video>>;
+
+    /// Get the video owning a live chat, `None` if no video uses it
+    ///
+    /// When several videos share the chat, the first in `get_videos` order is returned.
+    fn get_video_by_live_chat_id(&self, live_chat_id: &str) -> RepositoryResult<Option<Video>>;
+
+    /// Get all videos, ordered by `published_at` and then `id`
+    ///
+    /// Implementations must return a stable order so list responses are deterministic.
+    fn get_videos(&self) -> RepositoryResult<Vec<Video>>;
 
-    /// Get all videos
-    fn get_videos(&self) -> Vec<Video>;
+    /// Get a channel by ID, `None` if it does not exist
+    fn get_channel(&self, id: &str) -> RepositoryResult<Option<Channel>>;
+
+    /// Add a channel, replacing any channel with the same ID
+    fn add_channel(&self, channel: Channel) -> RepositoryResult<()>;
 
     /// Get live chat messages for a specific live chat ID
-    fn get_chat_messages(&self, live_chat_id: &str) -> Vec<LiveChatMessage>;
+    fn get_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<Vec<LiveChatMessage>>;
+
+    /// Get the messages of a chat at or after `position`, with their positions
+    ///
+    /// A message's position is its index among all messages ever added to the chat. Deleting
+    /// a message leaves a gap instead of shifting later positions, so page tokens built from
+    /// positions neither re-send delivered messages nor skip later ones.
+    fn get_chat_messages_from(
+        &self,
+        live_chat_id: &str,
+        position: usize,
+    ) -> RepositoryResult<Vec<(usize, LiveChatMessage)>>;
+
+    /// Number of messages stored for a chat
+    fn count_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<usize> {
+        Ok(self.get_chat_messages(live_chat_id)?.len())
+    }
 
     /// Add a video to the repository
-    fn add_video(&self, video: Video);
+    fn add_video(&self, video: Video) -> RepositoryResult<()>;
 
     /// Add a chat message to the repository
-    fn add_chat_message(&self, message: LiveChatMessage);
+    ///
+    /// Implementations may reject a message whose ID already exists in its chat with `Conflict`.
+    /// A message whose author is muted in the stored lifecycle of its chat is marked `muted`.
+    fn add_chat_message(&self, message: LiveChatMessage) -> RepositoryResult<()>;
+
+    /// Delete a video, `NotFound` if it does not exist
+    fn delete_video(&self, id: &str) -> RepositoryResult<()>;
+
+    /// Delete every chat message with this ID, `NotFound` if there is none
+    ///
+    /// Each chat that held the message records a tombstone: a deletion event published at
+    /// `deleted_at` and appended at the chat's next position, so streams deliver it once.
+    fn delete_chat_message(&self, id: &str, deleted_at: DateTime<Utc>) -> RepositoryResult<()>;
+
+    /// Get the lifecycle of a live chat, `None` if none was stored
+    fn get_live_chat(&self, id: &str) -> RepositoryResult<Option<LiveChat>>;
+
+    /// Store the lifecycle of a live chat, replacing any previous one
+    fn save_live_chat(&self, chat: LiveChat) -> RepositoryResult<()>;
+
+    /// Get the lifecycle of a live chat as clients see it
+    ///
+    /// The stored lifecycle if there is one; otherwise a chat whose owning video has an
+    /// `actual_end_time` has ended at that time.
+    fn get_effective_live_chat(&self, id: &str) -> RepositoryResult<Option<LiveChat>> {
+        if let Some(chat) = self.get_live_chat(id)? {
+            return Ok(Some(chat));
+        }
+        let ended_at = self
+            .get_video_by_live_chat_id(id)?
+            .and_then(|video| video.actual_end_time);
+        Ok(ended_at.map(|ended_at| {
+            let mut chat = LiveChat::active(id);
+            chat.state = LiveChatState::Ended;
+            chat.offline_at = Some(ended_at);
+            chat
+        }))
+    }
+
+    /// Whether a live chat exists
+    ///
+    /// A chat exists once a video references it, a lifecycle is stored for it or it has
+    /// messages, even if all of them were deleted.
+    fn live_chat_exists(&self, id: &str) -> RepositoryResult<bool> {
+        Ok(self.get_video_by_live_chat_id(id)?.is_some()
+            || self.get_live_chat(id)?.is_some()
+            || self.count_chat_messages(id)? > 0)
+    }
+
+    /// Whether a live chat has not started at `now`
+    ///
+    /// A chat has not started while its lifecycle is scheduled or, without a stored
+    /// lifecycle, while its owning video is upcoming.
+    fn live_chat_not_started(&self, id: &str, now: DateTime<Utc>) -> RepositoryResult<bool> {
+        if let Some(chat) = self.get_effective_live_chat(id)? {
+            return Ok(chat.state == LiveChatState::Scheduled);
+        }
+        Ok(self.get_video_by_live_chat_id(id)?.is_some_and(|video| {
+            video.live_broadcast_content(now) == domain::LiveBroadcastContent::Upcoming
+        }))
+    }
+
+    /// Atomically update a video
+    ///
+    /// `update` runs on the stored video while no other write can interleave, and its changes
+    /// are kept only if it returns `Ok`. With `expected_version`, the update fails with
+    /// `Conflict` unless the video is still at that version. Returns the updated video and its
+    /// new version.
+    fn update_video_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut Video) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(Video, u64)>;
+
+    /// Version of a video, bumped on every write, `None` if it does not exist
+    fn get_video_version(&self, id: &str) -> RepositoryResult<Option<u64>>;
+
+    /// Atomically update the lifecycle of a live chat, as `update_video_with` does for videos
+    ///
+    /// A chat without a stored lifecycle starts from its effective lifecycle, or an active
+    /// chat if it has none, at version 0.
+    fn update_live_chat_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut LiveChat) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(LiveChat, u64)>;
+
+    /// Version of a stored live chat lifecycle, bumped on every write, `None` if none is stored
+    fn get_live_chat_version(&self, id: &str) -> RepositoryResult<Option<u64>>;
+
+    /// IDs of all chats that have messages or a stored lifecycle, sorted
+    fn get_live_chat_ids(&self) -> RepositoryResult<Vec<String>>;
+
+    /// Subscribe to changes of a live chat
+    ///
+    /// The receiver is marked changed after every message, lifecycle or owning video write,
+    /// so streams can wait on it instead of polling.
+    fn subscribe(&self, live_chat_id: &str) -> watch::Receiver<u64>;
+
+    /// Remove all data and, unless `empty`, restore the data the repository started with
+    ///
+    /// Subscribers of every chat are notified. Readers running concurrently may observe
+    /// the repository half cleared or half restored.
+    fn reset(&self, empty: bool) -> RepositoryResult<()>;
+
+    /// Replace all data with `seed` in one step, bumping the generation
+    ///
+    /// The new data is built aside and swapped in, so every read sees either the old or the
+    /// new data, never a half-empty store. Subscribers of every chat are notified. What
+    /// `reset` restores is unchanged.
+    fn reseed(&self, seed: SeedData) -> RepositoryResult<()>;
+
+    /// Counter bumped whenever all data is replaced by `reset` or `reseed`
+    ///
+    /// Positions from before a replacement do not apply to the new data, so streams compare
+    /// the generation around their reads to notice one.
+    fn generation(&self) -> u64 {
+        0
+    }
+}
+
+fn poisoned<T>(_: std::sync::PoisonError<T>) -> RepositoryError {
+    RepositoryError::Backend("lock poisoned".to_string())
+}
+
+/// A stored entity with the version of its last write
+struct Versioned<T> {
+    value: T,
+    version: u64,
 }
 
+/// Store `value` under `id`, bumping its version
+fn put_versioned<T>(map: &mut HashMap<String, Versioned<T>>, id: String, value: T) {
+    let version = map.get(&id).map_or(0, |stored| stored.version) + 1;
+    map.insert(id, Versioned { value, version });
+}
+
+/// Apply `update` to a copy of the stored value and keep it if the update succeeds
+fn update_versioned<T: Clone>(
+    stored: &mut Versioned<T>,
+    expected_version: Option<u64>,
+    update: &mut dyn FnMut(&mut T) -> RepositoryResult<()>,
+) -> RepositoryResult<(T, u64)> {
+    if expected_version.is_some_and(|expected| expected != stored.version) {
+        return Err(RepositoryError::Conflict);
+    }
+    let mut value = stored.value.clone();
+    update(&mut value)?;
+    stored.value = value.clone();
+    stored.version += 1;
+    Ok((value, stored.version))
+}
+
+/// Random author index in `0..count`, where index `k` has weight `1 / (k + 1)`
+fn weighted_author_index(count: usize) -> usize {
+    // Integer weights proportional to 1 / (k + 1)
+    let weights: Vec<usize> = (1..=count).map(|rank| 1_000_000 / rank).collect();
+    let total: usize = weights.iter().sum();
+    let mut pick: usize = (0..total).fake();
+    for (index, weight) in weights.iter().enumerate() {
+        if pick < *weight {
+            return index;
+        }
+        pick -= weight;
+    }
+    count - 1
+}
+
+/// Fills a cleared repository with its initial data, on creation and on reset
+type Populate = Arc<dyn Fn(&InMemoryRepository) -> RepositoryResult<()> + Send + Sync>;
+
 /// In-memory implementation of the Repository trait
 pub struct InMemoryRepository {
-    videos: Arc<RwLock<HashMap<String, Video>>>,
-    chat_messages: Arc<RwLock<HashMap<String, Vec<LiveChatMessage>>>>,
+    videos: Arc<RwLock<HashMap<String, Versioned<Video>>>>,
+    channels: Arc<RwLock<HashMap<String, Channel>>>,
+    /// Messages per chat in the order they were added; deleted messages leave a `None`
+    chat_messages: Arc<RwLock<HashMap<String, Vec<Option<LiveChatMessage>>>>>,
+    live_chats: Arc<RwLock<HashMap<String, Versioned<LiveChat>>>>,
+    /// Change counter per subscribed chat
+    changes: RwLock<HashMap<String, watch::Sender<u64>>>,
+    /// Reject messages whose ID already exists in the same chat
+    unique_message_ids: bool,
+    /// Fills in the authors of messages added without a display name
+    authors: AuthorRegistry,
+    /// Restores the initial data on reset
+    populate: Populate,
+    /// Bumped whenever all data is replaced
+    generation: AtomicU64,
 }
 
 impl InMemoryRepository {
     /// Create a new in-memory repository with initial dummy data
     pub fn new() -> Self {
+        Self::with_dummy_author_pool(0)
+    }
+
+    /// Create a repository with dummy data whose generated chat messages come from a pool
+    /// of `author_count` authors, so the same authors post repeatedly
+    ///
+    /// Authors are picked at random with weights falling off like 1, 1/2, 1/3, ..., so a few
+    /// regulars post most messages. An `author_count` of 0 behaves like [`Self::new`].
+    pub fn with_dummy_author_pool(author_count: usize) -> Self {
+        let author_pool = (author_count > 0).then_some(author_count);
+        Self::populated(Arc::new(move |repo| {
+            repo.populate_dummy_data(author_pool);
+            Ok(())
+        }))
+        .expect("Dummy data should populate a fresh repository")
+    }
+
+    /// Create a repository holding only the seeded videos and chat messages
+    pub fn from_seed(seed: SeedData) -> RepositoryResult<Self> {
+        Self::populated(Arc::new(move |repo| {
+            for video in seed.videos.iter().cloned() {
+                repo.add_video(video)?;
+            }
+            for message in seed.chat_messages.iter().cloned() {
+                repo.add_chat_message(message)?;
+            }
+            Ok(())
+        }))
+    }
+
+    /// Create a repository filled by `populate`, which also restores the data on reset
+    pub(crate) fn populated(populate: Populate) -> RepositoryResult<Self> {
         let repo = Self {
+            populate: Arc::clone(&populate),
+            ..Self::empty()
+        };
+        populate(&repo)?;
+        Ok(repo)
+    }
+
+    fn empty() -> Self {
+        Self {
             videos: Arc::new(RwLock::new(HashMap::new())),
+            channels: Arc::new(RwLock::new(HashMap::new())),
             chat_messages: Arc::new(RwLock::new(HashMap::new())),
-        };
-        repo.populate_dummy_data();
-        repo
+            live_chats: Arc::new(RwLock::new(HashMap::new())),
+            changes: RwLock::new(HashMap::new()),
+            unique_message_ids: false,
+            authors: AuthorRegistry::default(),
+            populate: Arc::new(|_| Ok(())),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Reject chat messages whose ID already exists in the same chat with `Conflict`
+    /// instead of appending a duplicate
+    pub fn with_unique_message_ids(mut self, unique_message_ids: bool) -> Self {
+        self.unique_message_ids = unique_message_ids;
+        self
+    }
+
+    /// Put a chat message at `position` of its chat
+    ///
+    /// Positions before it that hold no message stay empty, like those of deleted messages.
+    pub(crate) fn add_chat_message_at(
+        &self,
+        position: usize,
+        mut message: LiveChatMessage,
+    ) -> RepositoryResult<()> {
+        let live_chat_id = message.live_chat_id.clone();
+        {
+            let mut chat_messages = self.chat_messages.write().map_err(poisoned)?;
+            let messages = chat_messages.entry(live_chat_id.clone()).or_default();
+            if messages.len() <= position {
+                messages.resize(position + 1, None);
+            }
+            self.authors.enrich(&mut message);
+            messages[position] = Some(message);
+        }
+        self.notify(&live_chat_id)
+    }
+
+    /// Wake the subscribers of every chat
+    fn notify_all(&self) -> RepositoryResult<()> {
+        for changes in self.changes.read().map_err(poisoned)?.values() {
+            changes.send_modify(|version| *version = version.wrapping_add(1));
+        }
+        Ok(())
+    }
+
+    /// Wake the subscribers of a chat
+    fn notify(&self, live_chat_id: &str) -> RepositoryResult<()> {
+        if let Some(changes) = self.changes.read().map_err(poisoned)?.get(live_chat_id) {
+            changes.send_modify(|version| *version = version.wrapping_add(1));
+        }
+        Ok(())
     }
 
     /// Populate the repository with initial dummy data
-    fn populate_dummy_data(&self) {
+    /// With `author_pool`, the generated messages are posted by that many recurring authors
+    fn populate_dummy_data(&self, author_pool: Option<usize>) {
         // Fixed point in time for consistent dummy data
         let fixed_time = Utc
             .with_ymd_and_hms(2023, 1, 1, 0, 0, 0)
@@ -66,20 +406,53 @@ impl InMemoryRepository {
             concurrent_viewers: Some(42),
         };
 
-        self.add_video(video1);
+        self.add_video(video1)
+            .expect("Fresh repository should accept dummy videos");
+
+        // Add the dummy video's channel
+        let channel1 = Channel {
+            id: "channel-1".to_string(),
+            title: "Mock Channel".to_string(),
+            description: "This is a mock channel for testing the YouTube Data API".to_string(),
+            custom_url: "@mockchannel".to_string(),
+            published_at: fixed_time,
+            subscriber_count: 1200,
+            video_count: 1,
+            view_count: 34_000,
+        };
+
+        self.add_channel(channel1)
+            .expect("Fresh repository should accept dummy channels");
 
         // Add dummy chat messages for live-chat-id-1 using fake library
+        let authors: Vec<(String, String)> = match author_pool {
+            Some(count) => (0..count)
+                .map(|k| (format!("channel-id-{k}"), Username().fake()))
+                .collect(),
+            None => Vec::new(),
+        };
         for i in 0..5 {
+            let (author_channel_id, author_display_name) = if authors.is_empty() {
+                (format!("channel-id-{i}"), Username().fake())
+            } else {
+                authors[weighted_author_index(authors.len())].clone()
+            };
             let message = LiveChatMessage {
                 id: format!("msg-id-{i}"),
                 live_chat_id: "live-chat-id-1".to_string(),
-                author_channel_id: format!("channel-id-{i}"),
-                author_display_name: Username().fake(),
+                author_channel_id,
+                author_display_name,
                 message_text: Sentence(3..8).fake(),
                 published_at: fixed_time,
                 is_verified: true,
+                super_chat_details: None,
+                super_sticker_details: None,
+                deleted_message_id: None,
+                author_profile: Default::default(),
+                muted: false,
             };
-            self.add_chat_message(message);
+            self.add_chat_message(message)
+                .expect("Fresh repository should accept dummy chat messages");
         }
 
         // Add dummy chat messages for test-chat-id (used in tests)
@@ -92,8 +465,14 @@ impl InMemoryRepository {
                 message_text: format!("Test message {i}"),
                 published_at: fixed_time,
                 is_verified: true,
+                super_chat_details: None,
+                super_sticker_details: None,
+                deleted_message_id: None,
+                author_profile: Default::default(),
+                muted: false,
             };
-            self.add_chat_message(message);
+            self.add_chat_message(message)
+                .expect("Fresh repository should accept dummy chat messages");
         }
     }
 }
@@ -105,46 +484,579 @@ impl Default for InMemoryRepository {
 }
 
 impl Repository for InMemoryRepository {
-    fn get_video(&self, id: &str) -> Option<Video> {
-        self.videos
+    fn get_video(&self, id: &str) -> RepositoryResult<Option<Video>> {
+        Ok(self
+            .videos
             .read()
-            .expect("Failed to acquire read lock on videos")
+            .map_err(poisoned)?
             .get(id)
-            .cloned()
+            .map(|stored| stored.value.clone()))
+    }
+
+    fn get_video_by_live_chat_id(&self, live_chat_id: &str) -> RepositoryResult<Option<Video>> {
+        Ok(self
+            .get_videos()?
+            .into_iter()
+            .find(|video| video.live_chat_id.as_deref() == Some(live_chat_id)))
     }
 
-    fn get_videos(&self) -> Vec<Video> {
-        self.videos
+    fn get_videos(&self) -> RepositoryResult<Vec<Video>> {
+        let mut videos: Vec<Video> = self
+            .videos
             .read()
-            .expect("Failed to acquire read lock on videos")
+            .map_err(poisoned)?
             .values()
-            .cloned()
-            .collect()
+            .map(|stored| stored.value.clone())
+            .collect();
+        // HashMap iteration order is random; sort so listings are deterministic
+        videos.sort_by(|a, b| {
+            a.published_at
+                .cmp(&b.published_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(videos)
+    }
+
+    fn get_channel(&self, id: &str) -> RepositoryResult<Option<Channel>> {
+        Ok(self.channels.read().map_err(poisoned)?.get(id).cloned())
+    }
+
+    fn add_channel(&self, channel: Channel) -> RepositoryResult<()> {
+        self.channels
+            .write()
+            .map_err(poisoned)?
+            .insert(channel.id.clone(), channel);
+        Ok(())
     }
 
-    fn get_chat_messages(&self, live_chat_id: &str) -> Vec<LiveChatMessage> {
-        self.chat_messages
+    fn get_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<Vec<LiveChatMessage>> {
+        Ok(self
+            .chat_messages
             .read()
-            .expect("Failed to acquire read lock on chat_messages")
+            .map_err(poisoned)?
             .get(live_chat_id)
-            .cloned()
-            .unwrap_or_default()
+            .map(|messages| messages.iter().flatten().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn get_chat_messages_from(
+        &self,
+        live_chat_id: &str,
+        position: usize,
+    ) -> RepositoryResult<Vec<(usize, LiveChatMessage)>> {
+        let chat_messages = self.chat_messages.read().map_err(poisoned)?;
+        let Some(messages) = chat_messages.get(live_chat_id) else {
+            return Ok(Vec::new());
+        };
+        Ok(messages
+            .iter()
+            .enumerate()
+            .skip(position)
+            .filter_map(|(position, message)| Some((position, message.clone()?)))
+            .collect())
     }
 
-    fn add_video(&self, video: Video) {
-        self.videos
+    fn count_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<usize> {
+        let chat_messages = self.chat_messages.read().map_err(poisoned)?;
+        Ok(chat_messages
+            .get(live_chat_id)
+            .map_or(0, |messages| messages.iter().flatten().count()))
+    }
+
+    fn add_video(&self, video: Video) -> RepositoryResult<()> {
+        let live_chat_id = video.live_chat_id.clone();
+        put_versioned(
+            &mut *self.videos.write().map_err(poisoned)?,
+            video.id.clone(),
+            video,
+        );
+        // The video's broadcast times can end its chat
+        match live_chat_id {
+            Some(live_chat_id) => self.notify(&live_chat_id),
+            None => Ok(()),
+        }
+    }
+
+    fn add_chat_message(&self, mut message: LiveChatMessage) -> RepositoryResult<()> {
+        let live_chat_id = message.live_chat_id.clone();
+        // Muting only affects messages posted while it lasts
+        message.muted |= self
+            .live_chats
+            .read()
+            .map_err(poisoned)?
+            .get(&live_chat_id)
+            .is_some_and(|chat| {
+                chat.value
+                    .muted_author_channel_ids
+                    .contains(&message.author_channel_id)
+            });
+        {
+            let mut chat_messages = self.chat_messages.write().map_err(poisoned)?;
+            let messages = chat_messages.entry(live_chat_id.clone()).or_default();
+            if self.unique_message_ids && messages.iter().flatten().any(|m| m.id == message.id) {
+                return Err(RepositoryError::Conflict);
+            }
+            self.authors.enrich(&mut message);
+            messages.push(Some(message));
+        }
+        self.notify(&live_chat_id)
+    }
+
+    fn delete_video(&self, id: &str) -> RepositoryResult<()> {
+        let removed = self
+            .videos
             .write()
-            .expect("Failed to acquire write lock on videos")
-            .insert(video.id.clone(), video);
+            .map_err(poisoned)?
+            .remove(id)
+            .ok_or(RepositoryError::NotFound)?;
+        // The chat may no longer count as ended by its video
+        match removed.value.live_chat_id {
+            Some(live_chat_id) => self.notify(&live_chat_id),
+            None => Ok(()),
+        }
+    }
+
+    fn delete_chat_message(&self, id: &str, deleted_at: DateTime<Utc>) -> RepositoryResult<()> {
+        let mut changed_chats = Vec::new();
+        {
+            let mut chat_messages = self.chat_messages.write().map_err(poisoned)?;
+            for (live_chat_id, messages) in chat_messages.iter_mut() {
+                let mut deleted = None;
+                // Tombstones themselves cannot be deleted
+                for slot in messages.iter_mut() {
+                    if slot.as_ref().is_some_and(|message| {
+                        message.id == id && message.deleted_message_id.is_none()
+                    }) {
+                        deleted = slot.take();
+                    }
+                }
+                if let Some(deleted) = deleted {
+                    messages.push(Some(LiveChatMessage::deletion_event(&deleted, deleted_at)));
+                    changed_chats.push(live_chat_id.clone());
+                }
+            }
+        }
+        if changed_chats.is_empty() {
+            return Err(RepositoryError::NotFound);
+        }
+        for live_chat_id in changed_chats {
+            self.notify(&live_chat_id)?;
+        }
+        Ok(())
+    }
+
+    fn get_live_chat(&self, id: &str) -> RepositoryResult<Option<LiveChat>> {
+        Ok(self
+            .live_chats
+            .read()
+            .map_err(poisoned)?
+            .get(id)
+            .map(|stored| stored.value.clone()))
+    }
+
+    fn save_live_chat(&self, chat: LiveChat) -> RepositoryResult<()> {
+        let live_chat_id = chat.id.clone();
+        put_versioned(
+            &mut *self.live_chats.write().map_err(poisoned)?,
+            live_chat_id.clone(),
+            chat,
+        );
+        self.notify(&live_chat_id)
+    }
+
+    fn update_video_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut Video) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(Video, u64)> {
+        let (video, version) = {
+            let mut videos = self.videos.write().map_err(poisoned)?;
+            let stored = videos.get_mut(id).ok_or(RepositoryError::NotFound)?;
+            update_versioned(stored, expected_version, update)?
+        };
+        if let Some(live_chat_id) = &video.live_chat_id {
+            self.notify(live_chat_id)?;
+        }
+        Ok((video, version))
+    }
+
+    fn get_video_version(&self, id: &str) -> RepositoryResult<Option<u64>> {
+        Ok(self
+            .videos
+            .read()
+            .map_err(poisoned)?
+            .get(id)
+            .map(|stored| stored.version))
     }
 
-    fn add_chat_message(&self, message: LiveChatMessage) {
-        self.chat_messages
+    fn update_live_chat_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut LiveChat) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(LiveChat, u64)> {
+        // Resolved before taking the write lock, which the effective lifecycle needs to read
+        let initial = self
+            .get_effective_live_chat(id)?
+            .unwrap_or_else(|| LiveChat::active(id));
+        let result = {
+            let mut live_chats = self.live_chats.write().map_err(poisoned)?;
+            let stored = live_chats.entry(id.to_string()).or_insert(Versioned {
+                value: initial,
+                version: 0,
+            });
+            let result = update_versioned(stored, expected_version, update);
+            // A failed first update leaves no lifecycle behind
+            if stored.version == 0 {
+                live_chats.remove(id);
+            }
+            result?
+        };
+        self.notify(id)?;
+        Ok(result)
+    }
+
+    fn get_live_chat_version(&self, id: &str) -> RepositoryResult<Option<u64>> {
+        Ok(self
+            .live_chats
+            .read()
+            .map_err(poisoned)?
+            .get(id)
+            .map(|stored| stored.version))
+    }
+
+    fn get_live_chat_ids(&self) -> RepositoryResult<Vec<String>> {
+        let mut ids: BTreeSet<String> = self
+            .chat_messages
+            .read()
+            .map_err(poisoned)?
+            .keys()
+            .cloned()
+            .collect();
+        ids.extend(self.live_chats.read().map_err(poisoned)?.keys().cloned());
+        Ok(ids.into_iter().collect())
+    }
+
+    fn subscribe(&self, live_chat_id: &str) -> watch::Receiver<u64> {
+        self.changes
             .write()
-            .expect("Failed to acquire write lock on chat_messages")
-            .entry(message.live_chat_id.clone())
-            .or_default()
-            .push(message);
+            .expect("Failed to acquire lock on change notifiers")
+            .entry(live_chat_id.to_string())
+            .or_insert_with(|| watch::channel(0).0)
+            .subscribe()
+    }
+
+    fn reset(&self, empty: bool) -> RepositoryResult<()> {
+        self.videos.write().map_err(poisoned)?.clear();
+        self.channels.write().map_err(poisoned)?.clear();
+        self.chat_messages.write().map_err(poisoned)?.clear();
+        self.live_chats.write().map_err(poisoned)?.clear();
+        self.authors.clear();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if !empty {
+            (self.populate)(self)?;
+        }
+        // Chats that are gone now were not notified by the restore
+        self.notify_all()
+    }
+
+    fn reseed(&self, seed: SeedData) -> RepositoryResult<()> {
+        let staged = Self::from_seed(seed)?;
+        {
+            // Every map is locked before any is swapped, so no read sees a mix
+            let mut videos = self.videos.write().map_err(poisoned)?;
+            let mut channels = self.channels.write().map_err(poisoned)?;
+            let mut chat_messages = self.chat_messages.write().map_err(poisoned)?;
+            let mut live_chats = self.live_chats.write().map_err(poisoned)?;
+            std::mem::swap(&mut *videos, &mut *staged.videos.write().map_err(poisoned)?);
+            std::mem::swap(
+                &mut *channels,
+                &mut *staged.channels.write().map_err(poisoned)?,
+            );
+            std::mem::swap(
+                &mut *chat_messages,
+                &mut *staged.chat_messages.write().map_err(poisoned)?,
+            );
+            std::mem::swap(
+                &mut *live_chats,
+                &mut *staged.live_chats.write().map_err(poisoned)?,
+            );
+            self.authors.swap(&staged.authors);
+            // Bumped before the locks are released, so a read of the new data is always
+            // followed by the new generation
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        self.notify_all()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+}
+
+/// Repository whose every operation fails with a backend error
+/// Used by service tests to check that backend failures are surfaced instead of panicking
+#[cfg(feature = "test-util")]
+pub struct FailingRepository;
+
+#[cfg(feature = "test-util")]
+impl FailingRepository {
+    fn error() -> RepositoryError {
+        RepositoryError::Backend("simulated backend failure".to_string())
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Repository for FailingRepository {
+    fn get_video(&self, _id: &str) -> RepositoryResult<Option<Video>> {
+        Err(Self::error())
+    }
+
+    fn get_video_by_live_chat_id(&self, _live_chat_id: &str) -> RepositoryResult<Option<Video>> {
+        Err(Self::error())
+    }
+
+    fn get_videos(&self) -> RepositoryResult<Vec<Video>> {
+        Err(Self::error())
+    }
+
+    fn get_channel(&self, _id: &str) -> RepositoryResult<Option<Channel>> {
+        Err(Self::error())
+    }
+
+    fn add_channel(&self, _channel: Channel) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+
+    fn get_chat_messages(&self, _live_chat_id: &str) -> RepositoryResult<Vec<LiveChatMessage>> {
+        Err(Self::error())
+    }
+
+    fn get_chat_messages_from(
+        &self,
+        _live_chat_id: &str,
+        _position: usize,
+    ) -> RepositoryResult<Vec<(usize, LiveChatMessage)>> {
+        Err(Self::error())
+    }
+
+    fn delete_video(&self, _id: &str) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+
+    fn delete_chat_message(&self, _id: &str, _deleted_at: DateTime<Utc>) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+
+    fn add_video(&self, _video: Video) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+
+    fn add_chat_message(&self, _message: LiveChatMessage) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+
+    fn get_live_chat(&self, _id: &str) -> RepositoryResult<Option<LiveChat>> {
+        Err(Self::error())
+    }
+
+    fn save_live_chat(&self, _chat: LiveChat) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+
+    fn update_video_with(
+        &self,
+        _id: &str,
+        _expected_version: Option<u64>,
+        _update: &mut dyn FnMut(&mut Video) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(Video, u64)> {
+        Err(Self::error())
+    }
+
+    fn get_video_version(&self, _id: &str) -> RepositoryResult<Option<u64>> {
+        Err(Self::error())
+    }
+
+    fn update_live_chat_with(
+        &self,
+        _id: &str,
+        _expected_version: Option<u64>,
+        _update: &mut dyn FnMut(&mut LiveChat) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(LiveChat, u64)> {
+        Err(Self::error())
+    }
+
+    fn get_live_chat_version(&self, _id: &str) -> RepositoryResult<Option<u64>> {
+        Err(Self::error())
+    }
+
+    fn get_live_chat_ids(&self) -> RepositoryResult<Vec<String>> {
+        Err(Self::error())
+    }
+
+    /// Never notifies: the sender is dropped right away
+    fn subscribe(&self, _live_chat_id: &str) -> watch::Receiver<u64> {
+        watch::channel(0).1
+    }
+
+    fn reset(&self, _empty: bool) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+
+    fn reseed(&self, _seed: SeedData) -> RepositoryResult<()> {
+        Err(Self::error())
+    }
+}
+
+/// Repository that blocks for `delay` before every operation of the wrapped repository
+/// Used by tests to simulate a wedged or contended backend
+#[cfg(feature = "test-util")]
+pub struct SlowRepository {
+    pub inner: Arc<dyn Repository>,
+    pub delay: std::time::Duration,
+}
+
+#[cfg(feature = "test-util")]
+impl SlowRepository {
+    fn wait(&self) {
+        std::thread::sleep(self.delay);
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Repository for SlowRepository {
+    fn get_video(&self, id: &str) -> RepositoryResult<Option<Video>> {
+        self.wait();
+        self.inner.get_video(id)
+    }
+
+    fn get_video_by_live_chat_id(&self, live_chat_id: &str) -> RepositoryResult<Option<Video>> {
+        self.wait();
+        self.inner.get_video_by_live_chat_id(live_chat_id)
+    }
+
+    fn get_videos(&self) -> RepositoryResult<Vec<Video>> {
+        self.wait();
+        self.inner.get_videos()
+    }
+
+    fn get_channel(&self, id: &str) -> RepositoryResult<Option<Channel>> {
+        self.wait();
+        self.inner.get_channel(id)
+    }
+
+    fn add_channel(&self, channel: Channel) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.add_channel(channel)
+    }
+
+    fn get_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<Vec<LiveChatMessage>> {
+        self.wait();
+        self.inner.get_chat_messages(live_chat_id)
+    }
+
+    fn get_chat_messages_from(
+        &self,
+        live_chat_id: &str,
+        position: usize,
+    ) -> RepositoryResult<Vec<(usize, LiveChatMessage)>> {
+        self.wait();
+        self.inner.get_chat_messages_from(live_chat_id, position)
+    }
+
+    fn count_chat_messages(&self, live_chat_id: &str) -> RepositoryResult<usize> {
+        self.wait();
+        self.inner.count_chat_messages(live_chat_id)
+    }
+
+    fn add_video(&self, video: Video) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.add_video(video)
+    }
+
+    fn add_chat_message(&self, message: LiveChatMessage) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.add_chat_message(message)
+    }
+
+    fn delete_video(&self, id: &str) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.delete_video(id)
+    }
+
+    fn delete_chat_message(&self, id: &str, deleted_at: DateTime<Utc>) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.delete_chat_message(id, deleted_at)
+    }
+
+    fn get_live_chat(&self, id: &str) -> RepositoryResult<Option<LiveChat>> {
+        self.wait();
+        self.inner.get_live_chat(id)
+    }
+
+    fn save_live_chat(&self, chat: LiveChat) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.save_live_chat(chat)
+    }
+
+    fn get_effective_live_chat(&self, id: &str) -> RepositoryResult<Option<LiveChat>> {
+        self.wait();
+        self.inner.get_effective_live_chat(id)
+    }
+
+    fn update_video_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut Video) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(Video, u64)> {
+        self.wait();
+        self.inner.update_video_with(id, expected_version, update)
+    }
+
+    fn get_video_version(&self, id: &str) -> RepositoryResult<Option<u64>> {
+        self.wait();
+        self.inner.get_video_version(id)
+    }
+
+    fn update_live_chat_with(
+        &self,
+        id: &str,
+        expected_version: Option<u64>,
+        update: &mut dyn FnMut(&mut LiveChat) -> RepositoryResult<()>,
+    ) -> RepositoryResult<(LiveChat, u64)> {
+        self.wait();
+        self.inner
+            .update_live_chat_with(id, expected_version, update)
+    }
+
+    fn get_live_chat_version(&self, id: &str) -> RepositoryResult<Option<u64>> {
+        self.wait();
+        self.inner.get_live_chat_version(id)
+    }
+
+    fn get_live_chat_ids(&self) -> RepositoryResult<Vec<String>> {
+        self.wait();
+        self.inner.get_live_chat_ids()
+    }
+
+    fn subscribe(&self, live_chat_id: &str) -> watch::Receiver<u64> {
+        self.inner.subscribe(live_chat_id)
+    }
+
+    fn reset(&self, empty: bool) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.reset(empty)
+    }
+
+    fn reseed(&self, seed: SeedData) -> RepositoryResult<()> {
+        self.wait();
+        self.inner.reseed(seed)
+    }
+
+    fn generation(&self) -> u64 {
+        self.inner.generation()
     }
 }
 
@@ -153,16 +1065,57 @@ mod tests {
     use super::*;
     use chrono::{TimeZone, Utc};
 
+    #[test]
+    fn test_dummy_author_pool_repeats_authors() {
+        let repo = InMemoryRepository::with_dummy_author_pool(2);
+        let messages = repo.get_chat_messages("live-chat-id-1").unwrap();
+        assert_eq!(messages.len(), 5);
+
+        let mut names = HashMap::new();
+        for message in &messages {
+            assert!(
+                ["channel-id-0", "channel-id-1"].contains(&message.author_channel_id.as_str()),
+                "{}",
+                message.author_channel_id
+            );
+            // Each pooled author keeps one display name
+            let name = names
+                .entry(message.author_channel_id.clone())
+                .or_insert_with(|| message.author_display_name.clone());
+            assert_eq!(*name, message.author_display_name);
+        }
+        assert!(
+            names.len() < messages.len(),
+            "Some author should post twice"
+        );
+
+        // Without a pool every message has its own author
+        let repo = InMemoryRepository::with_dummy_author_pool(0);
+        let messages = repo.get_chat_messages("live-chat-id-1").unwrap();
+        let channels: BTreeSet<_> = messages.iter().map(|m| &m.author_channel_id).collect();
+        assert_eq!(channels.len(), messages.len());
+    }
+
+    #[test]
+    fn test_weighted_author_index_favors_first_authors() {
+        let mut counts = [0; 5];
+        for _ in 0..10_000 {
+            counts[weighted_author_index(5)] += 1;
+        }
+        assert!(counts[0] > counts[1] && counts[1] > counts[4], "{counts:?}");
+        assert!(counts[4] > 0, "{counts:?}");
+    }
+
     #[test]
     fn test_new_repository_creates_with_dummy_data() {
         let repo = InMemoryRepository::new();
 
         // Should have at least one video from dummy data
-        let videos = repo.get_videos();
+        let videos = repo.get_videos().unwrap();
         assert!(!videos.is_empty(), "Repository should contain dummy videos");
 
         // Should have the test-video-1
-        let video = repo.get_video("test-video-1");
+        let video = repo.get_video("test-video-1").unwrap();
         assert!(video.is_some(), "Repository should contain test-video-1");
     }
 
@@ -171,7 +1124,7 @@ mod tests {
         let repo = InMemoryRepository::default();
 
         // Default should behave the same as new()
-        let videos = repo.get_videos();
+        let videos = repo.get_videos().unwrap();
         assert!(
             !videos.is_empty(),
             "Default repository should contain dummy videos"
@@ -182,7 +1135,7 @@ mod tests {
     fn test_get_video_existing() {
         let repo = InMemoryRepository::new();
 
-        let video = repo.get_video("test-video-1");
+        let video = repo.get_video("test-video-1").unwrap();
         assert!(video.is_some(), "Should find test-video-1");
 
         let video = video.unwrap();
@@ -194,11 +1147,29 @@ mod tests {
         assert_eq!(video.concurrent_viewers, Some(42));
     }
 
+    #[test]
+    fn test_get_channel() {
+        let repo = InMemoryRepository::new();
+
+        let channel = repo.get_channel("channel-1").unwrap().unwrap();
+        assert_eq!(channel.title, "Mock Channel");
+        assert_eq!(channel.custom_url, "@mockchannel");
+        assert!(repo.get_channel("non-existent-id").unwrap().is_none());
+
+        let mut renamed = channel.clone();
+        renamed.title = "Renamed Channel".to_string();
+        repo.add_channel(renamed).unwrap();
+        assert_eq!(
+            repo.get_channel("channel-1").unwrap().unwrap().title,
+            "Renamed Channel"
+        );
+    }
+
     #[test]
     fn test_get_video_non_existing() {
         let repo = InMemoryRepository::new();
 
-        let video = repo.get_video("non-existent-id");
+        let video = repo.get_video("non-existent-id").unwrap();
         assert!(video.is_none(), "Should not find non-existent video");
     }
 
@@ -226,9 +1197,9 @@ mod tests {
             concurrent_viewers: Some(100),
         };
 
-        repo.add_video(new_video.clone());
+        repo.add_video(new_video.clone()).unwrap();
 
-        let retrieved = repo.get_video("new-video-id");
+        let retrieved = repo.get_video("new-video-id").unwrap();
         assert!(retrieved.is_some(), "Should find newly added video");
 
         let retrieved = retrieved.unwrap();
@@ -262,9 +1233,9 @@ mod tests {
             concurrent_viewers: Some(999),
         };
 
-        repo.add_video(updated_video);
+        repo.add_video(updated_video).unwrap();
 
-        let retrieved = repo.get_video("test-video-1");
+        let retrieved = repo.get_video("test-video-1").unwrap();
         assert!(retrieved.is_some());
 
         let retrieved = retrieved.unwrap();
@@ -277,7 +1248,7 @@ mod tests {
     fn test_get_videos() {
         let repo = InMemoryRepository::new();
 
-        let videos = repo.get_videos();
+        let videos = repo.get_videos().unwrap();
 
         // Should have at least the dummy video
         assert!(!videos.is_empty(), "Should have videos");
@@ -304,9 +1275,9 @@ mod tests {
         };
 
         let initial_count = videos.len();
-        repo.add_video(video2);
+        repo.add_video(video2).unwrap();
 
-        let videos = repo.get_videos();
+        let videos = repo.get_videos().unwrap();
         assert_eq!(
             videos.len(),
             initial_count + 1,
@@ -322,7 +1293,7 @@ mod tests {
     fn test_get_chat_messages_existing() {
         let repo = InMemoryRepository::new();
 
-        let messages = repo.get_chat_messages("live-chat-id-1");
+        let messages = repo.get_chat_messages("live-chat-id-1").unwrap();
         assert!(
             !messages.is_empty(),
             "Should have messages for live-chat-id-1"
@@ -349,7 +1320,7 @@ mod tests {
     fn test_get_chat_messages_test_chat_id() {
         let repo = InMemoryRepository::new();
 
-        let messages = repo.get_chat_messages("test-chat-id");
+        let messages = repo.get_chat_messages("test-chat-id").unwrap();
         assert!(
             !messages.is_empty(),
             "Should have messages for test-chat-id"
@@ -369,7 +1340,7 @@ mod tests {
     fn test_get_chat_messages_non_existing() {
         let repo = InMemoryRepository::new();
 
-        let messages = repo.get_chat_messages("non-existent-chat-id");
+        let messages = repo.get_chat_messages("non-existent-chat-id").unwrap();
         assert!(
             messages.is_empty(),
             "Should return empty vec for non-existent chat ID"
@@ -393,11 +1364,16 @@ mod tests {
             message_text: "Hello from new chat!".to_string(),
             published_at: fixed_time,
             is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
         };
 
-        repo.add_chat_message(new_message.clone());
+        repo.add_chat_message(new_message.clone()).unwrap();
 
-        let messages = repo.get_chat_messages("new-chat-id");
+        let messages = repo.get_chat_messages("new-chat-id").unwrap();
         assert_eq!(messages.len(), 1, "Should have one message in new chat");
 
         let retrieved = &messages[0];
@@ -427,11 +1403,16 @@ mod tests {
                 message_text: format!("Message number {i}"),
                 published_at: fixed_time,
                 is_verified: i % 2 == 0,
+                super_chat_details: None,
+                super_sticker_details: None,
+                deleted_message_id: None,
+                author_profile: Default::default(),
+                muted: false,
             };
-            repo.add_chat_message(message);
+            repo.add_chat_message(message).unwrap();
         }
 
-        let messages = repo.get_chat_messages(chat_id);
+        let messages = repo.get_chat_messages(chat_id).unwrap();
         assert_eq!(messages.len(), 10, "Should have 10 messages");
 
         // Verify order is preserved
@@ -447,16 +1428,312 @@ mod tests {
         let repo: Box<dyn Repository> = Box::new(InMemoryRepository::new());
 
         // Should be able to call trait methods through trait object
-        let videos = repo.get_videos();
+        let videos = repo.get_videos().unwrap();
         assert!(!videos.is_empty());
 
-        let video = repo.get_video("test-video-1");
+        let video = repo.get_video("test-video-1").unwrap();
         assert!(video.is_some());
 
-        let messages = repo.get_chat_messages("live-chat-id-1");
+        let messages = repo.get_chat_messages("live-chat-id-1").unwrap();
         assert!(!messages.is_empty());
     }
 
+    #[test]
+    fn test_get_videos_is_sorted_by_published_at_then_id() {
+        use chrono::TimeZone;
+
+        let repo = InMemoryRepository::new();
+        let base = repo.get_videos().unwrap()[0].clone();
+        let at = |hour| {
+            chrono::Utc
+                .with_ymd_and_hms(2000, 1, 1, hour, 0, 0)
+                .unwrap()
+        };
+        for (id, hour) in [("sort-c", 1), ("sort-b", 2), ("sort-a", 2), ("sort-d", 0)] {
+            repo.add_video(Video {
+                id: id.to_string(),
+                published_at: at(hour),
+                ..base.clone()
+            })
+            .unwrap();
+        }
+
+        let ids: Vec<String> = repo
+            .get_videos()
+            .unwrap()
+            .into_iter()
+            .map(|video| video.id)
+            .filter(|id| id.starts_with("sort-"))
+            .collect();
+        assert_eq!(ids, ["sort-d", "sort-c", "sort-a", "sort-b"]);
+    }
+
+    #[test]
+    fn test_get_live_chat_ids_covers_messages_and_lifecycles() {
+        let repo = InMemoryRepository::new();
+        repo.save_live_chat(domain::LiveChat::scheduled("lifecycle-only", None))
+            .unwrap();
+        repo.save_live_chat(domain::LiveChat::active("test-chat-id"))
+            .unwrap();
+
+        let ids = repo.get_live_chat_ids().unwrap();
+        assert_eq!(
+            ids,
+            ["lifecycle-only", "live-chat-id-1", "test-chat-id"],
+            "IDs should be deduplicated and sorted"
+        );
+    }
+
+    #[test]
+    fn test_live_chat_lifecycle_round_trip() {
+        let repo = InMemoryRepository::new();
+        assert!(repo.get_live_chat("lifecycle-chat").unwrap().is_none());
+
+        let mut chat = domain::LiveChat::scheduled("lifecycle-chat", None);
+        repo.save_live_chat(chat.clone()).unwrap();
+        assert_eq!(
+            repo.get_live_chat("lifecycle-chat").unwrap().unwrap().state,
+            domain::LiveChatState::Scheduled
+        );
+
+        // Saving again replaces the stored lifecycle
+        chat.transition(domain::LiveChatState::Active, chrono::Utc::now())
+            .unwrap();
+        repo.save_live_chat(chat).unwrap();
+        let stored = repo.get_live_chat("lifecycle-chat").unwrap().unwrap();
+        assert_eq!(stored.state, domain::LiveChatState::Active);
+        assert!(stored.actual_start_time.is_some());
+    }
+
+    #[test]
+    fn test_duplicate_message_ids() {
+        let message = |id: &str, live_chat_id: &str| LiveChatMessage {
+            id: id.to_string(),
+            live_chat_id: live_chat_id.to_string(),
+            author_channel_id: "channel".to_string(),
+            author_display_name: "Author".to_string(),
+            message_text: "hello".to_string(),
+            published_at: Utc::now(),
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        };
+
+        // Lenient by default: duplicates are appended
+        let repo = InMemoryRepository::new();
+        repo.add_chat_message(message("dup", "dup-chat")).unwrap();
+        repo.add_chat_message(message("dup", "dup-chat")).unwrap();
+        assert_eq!(repo.get_chat_messages("dup-chat").unwrap().len(), 2);
+
+        let repo = InMemoryRepository::new().with_unique_message_ids(true);
+        repo.add_chat_message(message("dup", "dup-chat")).unwrap();
+        assert_eq!(
+            repo.add_chat_message(message("dup", "dup-chat")),
+            Err(RepositoryError::Conflict)
+        );
+        // The same ID in another chat is fine
+        repo.add_chat_message(message("dup", "other-chat")).unwrap();
+        assert_eq!(repo.get_chat_messages("dup-chat").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_chat_of_an_ended_video_is_ended() {
+        let repo = InMemoryRepository::new();
+        let mut video = repo
+            .get_video_by_live_chat_id("live-chat-id-1")
+            .unwrap()
+            .expect("Dummy video owns live-chat-id-1");
+        assert_eq!(video.id, "test-video-1");
+        assert!(
+            repo.get_video_by_live_chat_id("unused-chat")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            repo.get_effective_live_chat("live-chat-id-1")
+                .unwrap()
+                .is_none()
+        );
+
+        let mut changes = repo.subscribe("live-chat-id-1");
+        let ended_at = Utc::now();
+        video.actual_end_time = Some(ended_at);
+        repo.add_video(video).unwrap();
+        assert!(changes.has_changed().unwrap());
+        changes.mark_unchanged();
+
+        let chat = repo
+            .get_effective_live_chat("live-chat-id-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(chat.state, LiveChatState::Ended);
+        assert_eq!(chat.offline_at, Some(ended_at));
+
+        // A stored lifecycle takes precedence over the video
+        repo.save_live_chat(domain::LiveChat::active("live-chat-id-1"))
+            .unwrap();
+        let chat = repo
+            .get_effective_live_chat("live-chat-id-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(chat.state, LiveChatState::Active);
+    }
+
+    #[test]
+    fn test_chat_of_an_upcoming_video_has_not_started() {
+        let repo = InMemoryRepository::new();
+        let now = Utc::now();
+        let mut video = repo
+            .get_video_by_live_chat_id("live-chat-id-1")
+            .unwrap()
+            .unwrap();
+        assert!(!repo.live_chat_not_started("live-chat-id-1", now).unwrap());
+        assert!(!repo.live_chat_not_started("unused-chat", now).unwrap());
+
+        video.scheduled_start_time = Some(now + chrono::Duration::hours(1));
+        video.actual_start_time = None;
+        repo.add_video(video).unwrap();
+        assert!(repo.live_chat_not_started("live-chat-id-1", now).unwrap());
+
+        // A stored lifecycle takes precedence over the video
+        repo.save_live_chat(domain::LiveChat::active("live-chat-id-1"))
+            .unwrap();
+        assert!(!repo.live_chat_not_started("live-chat-id-1", now).unwrap());
+        repo.save_live_chat(domain::LiveChat::scheduled("test-chat-id", None))
+            .unwrap();
+        assert!(repo.live_chat_not_started("test-chat-id", now).unwrap());
+    }
+
+    #[test]
+    fn test_subscribers_are_notified_of_chat_writes() {
+        let repo = InMemoryRepository::new();
+        let mut changes = repo.subscribe("notified-chat");
+        let other = repo.subscribe("other-chat");
+        assert!(!changes.has_changed().unwrap());
+
+        repo.add_chat_message(LiveChatMessage {
+            id: "notified-msg".to_string(),
+            live_chat_id: "notified-chat".to_string(),
+            author_channel_id: "channel".to_string(),
+            author_display_name: "Author".to_string(),
+            message_text: "hello".to_string(),
+            published_at: Utc::now(),
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        })
+        .unwrap();
+        assert!(changes.has_changed().unwrap());
+        changes.mark_unchanged();
+
+        repo.save_live_chat(domain::LiveChat::active("notified-chat"))
+            .unwrap();
+        assert!(changes.has_changed().unwrap());
+        assert!(!other.has_changed().unwrap());
+    }
+
+    #[test]
+    fn test_reset_restores_initial_data_or_empties() {
+        let message = |id: &str| LiveChatMessage {
+            id: id.to_string(),
+            live_chat_id: "seeded-chat".to_string(),
+            author_channel_id: "channel".to_string(),
+            author_display_name: "Author".to_string(),
+            message_text: "hello".to_string(),
+            published_at: Utc::now(),
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        };
+        let repo = InMemoryRepository::from_seed(SeedData {
+            videos: vec![],
+            chat_messages: vec![message("seeded-msg")],
+        })
+        .unwrap();
+        repo.add_chat_message(message("added-msg")).unwrap();
+        repo.save_live_chat(domain::LiveChat::active("added-chat"))
+            .unwrap();
+        let changes = repo.subscribe("added-chat");
+
+        repo.reset(false).unwrap();
+        let ids: Vec<_> = repo
+            .get_chat_messages("seeded-chat")
+            .unwrap()
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(ids, ["seeded-msg"]);
+        assert_eq!(repo.get_live_chat_ids().unwrap(), ["seeded-chat"]);
+        // Streams of removed chats are woken too
+        assert!(changes.has_changed().unwrap());
+
+        repo.reset(true).unwrap();
+        assert!(repo.get_live_chat_ids().unwrap().is_empty());
+
+        let repo = InMemoryRepository::new();
+        repo.delete_video("test-video-1").unwrap();
+        repo.reset(false).unwrap();
+        assert!(repo.get_video("test-video-1").unwrap().is_some());
+        assert!(repo.get_channel("channel-1").unwrap().is_some());
+        assert_eq!(repo.get_chat_messages("test-chat-id").unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_reseed_swaps_data_and_bumps_generation() {
+        let repo = InMemoryRepository::new();
+        let changes = repo.subscribe("test-chat-id");
+        let seed = SeedData::from_json(
+            r#"{"chat_messages": [{
+                "id": "reseeded-msg",
+                "live_chat_id": "live-chat-id-1",
+                "author_channel_id": "channel",
+                "author_display_name": "Author",
+                "message_text": "hello",
+                "published_at": "2024-01-01T00:00:00Z",
+                "is_verified": false
+            }], "videos": [{
+                "id": "reseeded-video",
+                "channel_id": "channel",
+                "title": "Title",
+                "description": "",
+                "channel_title": "Channel",
+                "published_at": "2024-01-01T00:00:00Z",
+                "live_chat_id": "live-chat-id-1"
+            }]}"#,
+        )
+        .unwrap();
+        assert_eq!(repo.generation(), 0);
+
+        repo.reseed(seed).unwrap();
+        assert_eq!(repo.generation(), 1);
+        assert_eq!(repo.get_live_chat_ids().unwrap(), ["live-chat-id-1"]);
+        let ids: Vec<_> = repo
+            .get_chat_messages("live-chat-id-1")
+            .unwrap()
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(ids, ["reseeded-msg"]);
+        assert!(repo.get_video("test-video-1").unwrap().is_none());
+        // Streams of removed chats are woken too
+        assert!(changes.has_changed().unwrap());
+
+        // A reset still restores the data the repository started with
+        repo.reset(false).unwrap();
+        assert_eq!(repo.generation(), 2);
+        assert!(repo.get_video("test-video-1").unwrap().is_some());
+        assert!(repo.get_video("reseeded-video").unwrap().is_none());
+    }
+
     #[test]
     fn test_concurrent_video_operations() {
         use std::thread;
@@ -488,7 +1765,7 @@ mod tests {
                     concurrent_viewers: Some(i as u64),
                 };
 
-                repo_clone.add_video(video);
+                repo_clone.add_video(video).unwrap();
             });
             handles.push(handle);
         }
@@ -499,9 +1776,9 @@ mod tests {
         }
 
         // Verify all videos were added
-        let videos = repo.get_videos();
+        let videos = repo.get_videos().unwrap();
         for i in 0..10 {
-            let video = repo.get_video(&format!("concurrent-video-{i}"));
+            let video = repo.get_video(&format!("concurrent-video-{i}")).unwrap();
             assert!(video.is_some(), "Video {i} should exist");
         }
 
@@ -513,6 +1790,99 @@ mod tests {
         assert_eq!(concurrent_count, 10, "Should have all 10 concurrent videos");
     }
 
+    #[test]
+    fn test_concurrent_updates_to_different_fields_are_not_lost() {
+        use std::thread;
+
+        let repo = Arc::new(InMemoryRepository::new());
+        let initial_version = repo.get_video_version("test-video-1").unwrap().unwrap();
+
+        let handles: Vec<_> = (0..100)
+            .map(|i| {
+                let repo = Arc::clone(&repo);
+                thread::spawn(move || {
+                    repo.update_video_with("test-video-1", None, &mut |video| {
+                        if i % 2 == 0 {
+                            video.concurrent_viewers = video.concurrent_viewers.map(|n| n + 1);
+                        } else {
+                            video.description.push_str(&format!(" [{i}]"));
+                        }
+                        Ok(())
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().expect("Thread should complete successfully");
+        }
+
+        let video = repo.get_video("test-video-1").unwrap().unwrap();
+        assert_eq!(video.concurrent_viewers, Some(42 + 50));
+        for i in (1..100).step_by(2) {
+            assert!(video.description.contains(&format!(" [{i}]")), "{i}");
+        }
+        assert_eq!(
+            repo.get_video_version("test-video-1").unwrap(),
+            Some(initial_version + 100)
+        );
+    }
+
+    #[test]
+    fn test_versioned_updates_reject_stale_versions() {
+        let repo = InMemoryRepository::new();
+        let version = repo.get_video_version("test-video-1").unwrap().unwrap();
+
+        let (video, new_version) = repo
+            .update_video_with("test-video-1", Some(version), &mut |video| {
+                video.title = "First".to_string();
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(video.title, "First");
+        assert_eq!(new_version, version + 1);
+
+        // The stale version loses; the update is not applied
+        let result = repo.update_video_with("test-video-1", Some(version), &mut |video| {
+            video.title = "Second".to_string();
+            Ok(())
+        });
+        assert_eq!(result.unwrap_err(), RepositoryError::Conflict);
+        // A failing update is discarded without bumping the version
+        let result = repo.update_video_with("test-video-1", None, &mut |video| {
+            video.title = "Third".to_string();
+            Err(RepositoryError::Invalid("rejected".to_string()))
+        });
+        assert!(matches!(result, Err(RepositoryError::Invalid(_))));
+        assert_eq!(
+            repo.get_video("test-video-1").unwrap().unwrap().title,
+            "First"
+        );
+        assert_eq!(
+            repo.get_video_version("test-video-1").unwrap(),
+            Some(new_version)
+        );
+        assert_eq!(
+            repo.update_video_with("missing", None, &mut |_| Ok(()))
+                .unwrap_err(),
+            RepositoryError::NotFound
+        );
+
+        // Chats without a stored lifecycle start at version 0
+        assert_eq!(repo.get_live_chat_version("new-chat").unwrap(), None);
+        let result = repo.update_live_chat_with("new-chat", Some(1), &mut |_| Ok(()));
+        assert_eq!(result.unwrap_err(), RepositoryError::Conflict);
+        assert_eq!(repo.get_live_chat("new-chat").unwrap(), None);
+        let (chat, version) = repo
+            .update_live_chat_with("new-chat", Some(0), &mut |chat| {
+                chat.state = LiveChatState::Ended;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!((chat.state, version), (LiveChatState::Ended, 1));
+        assert_eq!(repo.get_live_chat_version("new-chat").unwrap(), Some(1));
+    }
+
     #[test]
     fn test_concurrent_chat_message_operations() {
         use std::thread;
@@ -540,9 +1910,14 @@ mod tests {
                     message_text: format!("Concurrent message {i}"),
                     published_at: fixed_time,
                     is_verified: true,
+                    super_chat_details: None,
+                    super_sticker_details: None,
+                    deleted_message_id: None,
+                    author_profile: Default::default(),
+                    muted: false,
                 };
 
-                repo_clone.add_chat_message(message);
+                repo_clone.add_chat_message(message).unwrap();
             });
             handles.push(handle);
         }
@@ -553,7 +1928,7 @@ mod tests {
         }
 
         // Verify all messages were added
-        let messages = repo.get_chat_messages(chat_id);
+        let messages = repo.get_chat_messages(chat_id).unwrap();
         assert_eq!(messages.len(), 10, "Should have all 10 concurrent messages");
 
         // Verify all message IDs are present (order may vary due to concurrency)
@@ -577,8 +1952,8 @@ mod tests {
             let repo_clone = Arc::clone(&repo);
             let handle = thread::spawn(move || {
                 for _ in 0..100 {
-                    let _ = repo_clone.get_videos();
-                    let _ = repo_clone.get_video("test-video-1");
+                    let _ = repo_clone.get_videos().unwrap();
+                    let _ = repo_clone.get_video("test-video-1").unwrap();
                 }
             });
             handles.push(handle);
@@ -608,7 +1983,7 @@ mod tests {
                         scheduled_end_time: None,
                         concurrent_viewers: None,
                     };
-                    repo_clone.add_video(video);
+                    repo_clone.add_video(video).unwrap();
                 }
             });
             handles.push(handle);
@@ -620,11 +1995,111 @@ mod tests {
         }
 
         // Verify data integrity - should have at least the added videos
-        let videos = repo.get_videos();
+        let videos = repo.get_videos().unwrap();
         let rw_count = videos
             .iter()
             .filter(|v| v.id.starts_with("rw-video-"))
             .count();
         assert_eq!(rw_count, 50, "Should have all 50 read-write test videos");
     }
+
+    #[test]
+    fn test_deleted_messages_leave_stable_positions() {
+        let repo = InMemoryRepository::new();
+        let positions = |from| {
+            repo.get_chat_messages_from("test-chat-id", from)
+                .unwrap()
+                .into_iter()
+                .map(|(position, message)| (position, message.id))
+                .collect::<Vec<_>>()
+        };
+
+        repo.delete_chat_message("test-msg-id-1", Utc::now())
+            .unwrap();
+        assert_eq!(
+            positions(1),
+            [
+                (2, "test-msg-id-2".to_string()),
+                (3, "test-msg-id-3".to_string()),
+                (4, "test-msg-id-4".to_string()),
+                (5, "test-msg-id-1-deleted".to_string()),
+            ]
+        );
+        let tombstone = repo
+            .get_chat_messages("test-chat-id")
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(
+            tombstone.deleted_message_id.as_deref(),
+            Some("test-msg-id-1")
+        );
+        assert_eq!(tombstone.message_type(), "messageDeletedEvent");
+        assert_eq!(repo.get_chat_messages("test-chat-id").unwrap().len(), 5);
+        assert_eq!(repo.count_chat_messages("test-chat-id").unwrap(), 5);
+        assert_eq!(repo.count_chat_messages("no-such-chat").unwrap(), 0);
+        assert_eq!(
+            repo.delete_chat_message("test-msg-id-1", Utc::now()),
+            Err(RepositoryError::NotFound)
+        );
+        assert_eq!(
+            repo.delete_chat_message("test-msg-id-1-deleted", Utc::now()),
+            Err(RepositoryError::NotFound)
+        );
+
+        // New messages take the next position, after the gap and the tombstone
+        let mut message = repo.get_chat_messages("test-chat-id").unwrap()[0].clone();
+        message.id = "new-msg".to_string();
+        repo.add_chat_message(message).unwrap();
+        assert_eq!(positions(6), [(6, "new-msg".to_string())]);
+    }
+
+    #[test]
+    fn test_delete_video() {
+        let repo = InMemoryRepository::new();
+        let changes = repo.subscribe("live-chat-id-1");
+        repo.delete_video("test-video-1").unwrap();
+        assert!(repo.get_video("test-video-1").unwrap().is_none());
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(
+            repo.delete_video("test-video-1"),
+            Err(RepositoryError::NotFound)
+        );
+        // The chat's messages are kept
+        assert_eq!(repo.get_chat_messages("live-chat-id-1").unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_messages_without_author_name_are_filled_on_insert() {
+        let repo = InMemoryRepository::new();
+        let mut message = repo.get_chat_messages("test-chat-id").unwrap()[0].clone();
+        message.id = "anonymous-known".to_string();
+        message.author_display_name = String::new();
+        message.is_verified = false;
+        repo.add_chat_message(message.clone()).unwrap();
+
+        message.id = "anonymous-unknown".to_string();
+        message.author_channel_id = "channel-never-seen".to_string();
+        repo.add_chat_message(message).unwrap();
+
+        let names: HashMap<_, _> = repo
+            .get_chat_messages("test-chat-id")
+            .unwrap()
+            .into_iter()
+            .map(|message| {
+                (
+                    message.id,
+                    (message.author_display_name, message.is_verified),
+                )
+            })
+            .collect();
+        assert_eq!(names["anonymous-known"], ("Test User 0".to_string(), true));
+        assert_eq!(
+            names["anonymous-unknown"],
+            (
+                domain::authors::synthesized_display_name("channel-never-seen"),
+                false
+            )
+        );
+    }
 }
@@ -0,0 +1,302 @@
+//! Seed data loaded from a JSON or YAML file at startup
+//!
+//! The file holds `videos` and `chat_messages` arrays whose entries use the field names
+//! of [`Video`] and [`LiveChatMessage`]. Every entry is validated before anything is
+//! stored, and errors name the offending entry. Files ending in `.yaml` or `.yml` are
+//! read as YAML, which needs the `yaml` feature; their errors also give the line.
+
+use domain::{LiveChatMessage, Video};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Videos and chat messages to load into an empty repository
+#[derive(Debug, Clone, Default)]
+pub struct SeedData {
+    pub videos: Vec<Video>,
+    pub chat_messages: Vec<LiveChatMessage>,
+}
+
+#[cfg(feature = "yaml")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct YamlSeedFile {
+    #[serde(default)]
+    videos: Vec<Video>,
+    #[serde(default)]
+    chat_messages: Vec<LiveChatMessage>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeedFile {
+    #[serde(default)]
+    videos: Vec<serde_json::Value>,
+    #[serde(default)]
+    chat_messages: Vec<serde_json::Value>,
+}
+
+/// Label of an entry for error messages, e.g. `videos[2] (id 'my-video')`
+fn entry_label(list: &str, index: usize, id: Option<&str>) -> String {
+    match id {
+        Some(id) => format!("{list}[{index}] (id '{id}')"),
+        None => format!("{list}[{index}]"),
+    }
+}
+
+fn parse_entries<T: DeserializeOwned>(
+    list: &str,
+    entries: Vec<serde_json::Value>,
+) -> Result<Vec<T>, String> {
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let label = entry_label(list, index, entry.get("id").and_then(|id| id.as_str()));
+            serde_json::from_value(entry).map_err(|e| format!("{label}: {e}"))
+        })
+        .collect()
+}
+
+impl SeedData {
+    /// Parse and validate seed data from JSON
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: SeedFile =
+            serde_json::from_str(json).map_err(|e| format!("Invalid seed data: {e}"))?;
+        let seed = Self {
+            videos: parse_entries("videos", file.videos)?,
+            chat_messages: parse_entries("chat_messages", file.chat_messages)?,
+        };
+        seed.validate()?;
+        Ok(seed)
+    }
+
+    /// Parse and validate seed data from YAML
+    /// Parse errors carry the path of the offending field and its line and column
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Self, String> {
+        let file: YamlSeedFile =
+            serde_norway::from_str(yaml).map_err(|e| format!("Invalid seed data: {e}"))?;
+        let seed = Self {
+            videos: file.videos,
+            chat_messages: file.chat_messages,
+        };
+        seed.validate()?;
+        Ok(seed)
+    }
+
+    /// Read, parse and validate a seed file, as YAML when it ends in `.yaml` or `.yml`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read seed file {path:?}: {e}"))?;
+        let is_yaml = path
+            .extension()
+            .is_some_and(|extension| extension == "yaml" || extension == "yml");
+        let seed = if is_yaml {
+            Self::parse_yaml(&contents)
+        } else {
+            Self::from_json(&contents)
+        };
+        seed.map_err(|e| format!("Seed file {path:?}: {e}"))
+    }
+
+    #[cfg(feature = "yaml")]
+    fn parse_yaml(yaml: &str) -> Result<Self, String> {
+        Self::from_yaml(yaml)
+    }
+
+    #[cfg(not(feature = "yaml"))]
+    fn parse_yaml(_yaml: &str) -> Result<Self, String> {
+        Err("YAML seed files need the server to be built with the `yaml` feature".to_string())
+    }
+
+    /// Reject duplicate IDs, out-of-order broadcast times and messages for chats
+    /// that no seeded video uses
+    pub fn validate(&self) -> Result<(), String> {
+        let mut video_ids = HashSet::new();
+        let mut live_chat_ids = HashSet::new();
+        for (index, video) in self.videos.iter().enumerate() {
+            let label = entry_label("videos", index, Some(&video.id));
+            if !video_ids.insert(video.id.as_str()) {
+                return Err(format!("{label}: duplicate video id"));
+            }
+            video
+                .validate_times()
+                .map_err(|e| format!("{label}: {e}"))?;
+            if let Some(live_chat_id) = &video.live_chat_id
+                && !live_chat_ids.insert(live_chat_id.as_str())
+            {
+                return Err(format!(
+                    "{label}: live chat '{live_chat_id}' is already used by another video"
+                ));
+            }
+        }
+
+        let mut message_ids: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for (index, message) in self.chat_messages.iter().enumerate() {
+            let label = entry_label("chat_messages", index, Some(&message.id));
+            if !live_chat_ids.contains(message.live_chat_id.as_str()) {
+                return Err(format!(
+                    "{label}: live chat '{}' is not used by any seeded video",
+                    message.live_chat_id
+                ));
+            }
+            if !message_ids
+                .entry(&message.live_chat_id)
+                .or_default()
+                .insert(&message.id)
+            {
+                return Err(format!(
+                    "{label}: duplicate message id in live chat '{}'",
+                    message.live_chat_id
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryRepository, Repository};
+
+    const SAMPLE: &str = include_str!("../../../examples/seed.json");
+    #[cfg(feature = "yaml")]
+    const YAML_SAMPLE: &str = include_str!("../../../examples/seed.yaml");
+
+    fn video(id: &str, live_chat_id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "channel_id": "channel",
+            "title": "Title",
+            "description": "",
+            "channel_title": "Channel",
+            "published_at": "2024-01-01T00:00:00Z",
+            "live_chat_id": live_chat_id,
+        })
+    }
+
+    fn message(id: &str, live_chat_id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "live_chat_id": live_chat_id,
+            "author_channel_id": "author",
+            "author_display_name": "Author",
+            "message_text": "Hello",
+            "published_at": "2024-01-01T00:00:00Z",
+            "is_verified": false,
+        })
+    }
+
+    fn seed_error(seed: serde_json::Value) -> String {
+        SeedData::from_json(&seed.to_string()).expect_err("Seed should be rejected")
+    }
+
+    #[test]
+    fn test_sample_seed_loads_into_repository() {
+        let seed = SeedData::from_json(SAMPLE).expect("Sample seed should be valid");
+        let expected_messages = seed.chat_messages.len();
+        let repo = InMemoryRepository::from_seed(seed).unwrap();
+
+        let video = repo.get_video("seed-video-1").unwrap().unwrap();
+        let live_chat_id = video.live_chat_id.expect("Seeded video has a chat");
+        assert_eq!(
+            repo.get_chat_messages(&live_chat_id).unwrap().len(),
+            expected_messages
+        );
+        // The dummy data is not loaded alongside the seed
+        assert!(repo.get_video("test-video-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_bad_timestamp_names_the_entry() {
+        let mut bad = video("video-b", "chat-b");
+        bad["published_at"] = "yesterday".into();
+        let error = seed_error(serde_json::json!({
+            "videos": [video("video-a", "chat-a"), bad],
+        }));
+        assert!(error.starts_with("videos[1] (id 'video-b'): "), "{error}");
+    }
+
+    #[test]
+    fn test_duplicate_ids_are_rejected() {
+        let error = seed_error(serde_json::json!({
+            "videos": [video("video-a", "chat-a"), video("video-a", "chat-b")],
+        }));
+        assert_eq!(error, "videos[1] (id 'video-a'): duplicate video id");
+
+        let error = seed_error(serde_json::json!({
+            "videos": [video("video-a", "chat-a")],
+            "chat_messages": [message("msg-1", "chat-a"), message("msg-1", "chat-a")],
+        }));
+        assert_eq!(
+            error,
+            "chat_messages[1] (id 'msg-1'): duplicate message id in live chat 'chat-a'"
+        );
+    }
+
+    #[test]
+    fn test_messages_for_unknown_chats_are_rejected() {
+        let error = seed_error(serde_json::json!({
+            "videos": [video("video-a", "chat-a")],
+            "chat_messages": [message("msg-1", "chat-b")],
+        }));
+        assert_eq!(
+            error,
+            "chat_messages[0] (id 'msg-1'): live chat 'chat-b' is not used by any seeded video"
+        );
+    }
+
+    #[test]
+    fn test_out_of_order_times_are_rejected() {
+        let mut bad = video("video-a", "chat-a");
+        bad["actual_start_time"] = "2024-01-02T00:00:00Z".into();
+        bad["actual_end_time"] = "2024-01-01T00:00:00Z".into();
+        let error = seed_error(serde_json::json!({ "videos": [bad] }));
+        assert!(
+            error.starts_with("videos[0] (id 'video-a'): actualStartTime"),
+            "{error}"
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_sample_matches_json_sample() {
+        let json = SeedData::from_json(SAMPLE).unwrap();
+        let yaml = SeedData::from_yaml(YAML_SAMPLE).expect("YAML sample should be valid");
+        assert_eq!(format!("{:?}", yaml.videos), format!("{:?}", json.videos));
+        assert_eq!(
+            format!("{:?}", yaml.chat_messages),
+            format!("{:?}", json.chat_messages)
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_errors_give_field_and_line() {
+        let yaml = "videos:\n  - id: video-a\n    channel_id: channel\n    title: Title\n    description: ''\n    channel_title: Channel\n    published_at: yesterday\n";
+        let error = SeedData::from_yaml(yaml).expect_err("Seed should be rejected");
+        assert!(error.contains("videos[0].published_at"), "{error}");
+        assert!(error.contains("line 7"), "{error}");
+
+        // Validation errors name the entry as for JSON
+        let yaml = serde_norway::to_string(&serde_json::json!({
+            "videos": [video("video-a", "chat-a"), video("video-a", "chat-b")],
+        }))
+        .unwrap();
+        let error = SeedData::from_yaml(&yaml).expect_err("Seed should be rejected");
+        assert_eq!(error, "videos[1] (id 'video-a'): duplicate video id");
+    }
+
+    #[cfg(not(feature = "yaml"))]
+    #[test]
+    fn test_yaml_needs_the_feature() {
+        let path = std::env::temp_dir().join(format!("seed-{}.yaml", std::process::id()));
+        std::fs::write(&path, "videos: []\n").unwrap();
+        let error = SeedData::load(&path).expect_err("YAML should need the feature");
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains("`yaml` feature"), "{error}");
+    }
+}
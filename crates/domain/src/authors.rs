@@ -0,0 +1,210 @@
+//! Registry of known chat authors
+//!
+//! Messages that give only an `author_channel_id` are filled in from the persona the
+//! registry knows for that channel. Channels it does not know get deterministic
+//! synthesized values instead of blank fields. The datastore applies this on insert, so
+//! every path that adds messages benefits.
+
+use crate::LiveChatMessage;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Author fields remembered for a channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorPersona {
+    pub display_name: String,
+    pub is_verified: bool,
+}
+
+/// Known author personas by channel ID
+/// Authors of messages that carry a display name are learned as they are added
+#[derive(Debug, Default)]
+pub struct AuthorRegistry {
+    personas: RwLock<HashMap<String, AuthorPersona>>,
+}
+
+impl AuthorRegistry {
+    /// Remember `persona` for a channel, replacing any earlier one
+    pub fn register(&self, channel_id: &str, persona: AuthorPersona) {
+        self.personas
+            .write()
+            .expect("Failed to acquire lock on author registry")
+            .insert(channel_id.to_string(), persona);
+    }
+
+    /// Forget every persona
+    pub fn clear(&self) {
+        self.personas
+            .write()
+            .expect("Failed to acquire lock on author registry")
+            .clear();
+    }
+
+    /// Exchange every persona with those of `other`
+    pub fn swap(&self, other: &AuthorRegistry) {
+        let mut personas = self
+            .personas
+            .write()
+            .expect("Failed to acquire lock on author registry");
+        let mut others = other
+            .personas
+            .write()
+            .expect("Failed to acquire lock on author registry");
+        std::mem::swap(&mut *personas, &mut *others);
+    }
+
+    /// The persona known for a channel
+    pub fn get(&self, channel_id: &str) -> Option<AuthorPersona> {
+        self.personas
+            .read()
+            .expect("Failed to acquire lock on author registry")
+            .get(channel_id)
+            .cloned()
+    }
+
+    /// Fill in the author of a message that has no display name
+    ///
+    /// Known channels get their persona's name and verified flag; unknown channels get a
+    /// synthesized name. A message that names its author is left as is, and teaches the
+    /// registry that author if the channel was unknown.
+    pub fn enrich(&self, message: &mut LiveChatMessage) {
+        if !message.author_display_name.trim().is_empty() {
+            self.personas
+                .write()
+                .expect("Failed to acquire lock on author registry")
+                .entry(message.author_channel_id.clone())
+                .or_insert_with(|| AuthorPersona {
+                    display_name: message.author_display_name.clone(),
+                    is_verified: message.is_verified,
+                });
+            return;
+        }
+
+        match self.get(&message.author_channel_id) {
+            Some(persona) => {
+                message.author_display_name = persona.display_name;
+                message.is_verified |= persona.is_verified;
+            }
+            None => {
+                message.author_display_name = synthesized_display_name(&message.author_channel_id);
+            }
+        }
+    }
+}
+
+/// FNV-1a, stable across builds unlike the std hasher
+fn channel_hash(channel_id: &str) -> u64 {
+    channel_id
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Display name for a channel the registry does not know, e.g. "Viewer 4821"
+pub fn synthesized_display_name(channel_id: &str) -> String {
+    format!("Viewer {}", 1000 + channel_hash(channel_id) % 9000)
+}
+
+/// Generated avatar URL for a channel, the same for every message of the channel
+pub fn profile_image_url(channel_id: &str) -> String {
+    format!(
+        "https://yt3.ggpht.com/mock-avatar/{:016x}=s64-c-k-c0x00ffffff-no-rj",
+        channel_hash(channel_id)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(channel_id: &str, display_name: &str) -> LiveChatMessage {
+        LiveChatMessage {
+            id: "msg".to_string(),
+            live_chat_id: "chat".to_string(),
+            author_channel_id: channel_id.to_string(),
+            author_display_name: display_name.to_string(),
+            message_text: "Hello".to_string(),
+            published_at: chrono::Utc::now(),
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        }
+    }
+
+    #[test]
+    fn test_known_author_is_filled_from_persona() {
+        let registry = AuthorRegistry::default();
+        registry.register(
+            "channel-known",
+            AuthorPersona {
+                display_name: "Known Author".to_string(),
+                is_verified: true,
+            },
+        );
+
+        let mut msg = message("channel-known", "");
+        registry.enrich(&mut msg);
+        assert_eq!(msg.author_display_name, "Known Author");
+        assert!(msg.is_verified);
+
+        // Authors of earlier messages are learned
+        registry.enrich(&mut message("channel-learned", "Learned Author"));
+        let mut msg = message("channel-learned", " ");
+        registry.enrich(&mut msg);
+        assert_eq!(msg.author_display_name, "Learned Author");
+    }
+
+    #[test]
+    fn test_unknown_author_gets_deterministic_values() {
+        let registry = AuthorRegistry::default();
+        let mut first = message("channel-unknown", "");
+        let mut second = message("channel-unknown", "");
+        registry.enrich(&mut first);
+        AuthorRegistry::default().enrich(&mut second);
+
+        assert_eq!(first.author_display_name, second.author_display_name);
+        assert!(
+            first.author_display_name.starts_with("Viewer "),
+            "{}",
+            first.author_display_name
+        );
+        assert_ne!(
+            first.author_display_name,
+            synthesized_display_name("channel-other")
+        );
+        assert_eq!(
+            profile_image_url("channel-unknown"),
+            profile_image_url("channel-unknown")
+        );
+        assert_ne!(
+            profile_image_url("channel-unknown"),
+            profile_image_url("channel-other")
+        );
+    }
+
+    #[test]
+    fn test_explicit_values_win_over_persona() {
+        let registry = AuthorRegistry::default();
+        registry.register(
+            "channel-known",
+            AuthorPersona {
+                display_name: "Known Author".to_string(),
+                is_verified: true,
+            },
+        );
+
+        let mut msg = message("channel-known", "Renamed Author");
+        registry.enrich(&mut msg);
+        assert_eq!(msg.author_display_name, "Renamed Author");
+        assert!(!msg.is_verified);
+        // The registered persona is kept
+        assert_eq!(
+            registry.get("channel-known").unwrap().display_name,
+            "Known Author"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Delivery audits of live chat streams
+//!
+//! While an audit is enabled for a chat, every message its streams send is recorded with
+//! the stream and the time it went out, so a test can check afterwards that each message
+//! reached the client and which ones it received more than once. Streams check a single
+//! counter before anything else, so chats without an audit cost nothing.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of delivery records kept per chat; older ones are dropped but still counted
+pub const DELIVERY_AUDIT_CAPACITY: usize = 100_000;
+
+/// A message sent by a stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delivery {
+    pub message_id: String,
+    pub stream_id: u64,
+    pub delivered_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct ChatAudit {
+    enabled_at: DateTime<Utc>,
+    /// Deliveries per message, kept in full even when records are dropped
+    counts: HashMap<String, u64>,
+    records: VecDeque<Delivery>,
+    dropped_records: u64,
+}
+
+impl ChatAudit {
+    fn new() -> Self {
+        Self {
+            enabled_at: clock::system_clock().now(),
+            counts: HashMap::new(),
+            records: VecDeque::new(),
+            dropped_records: 0,
+        }
+    }
+}
+
+/// Delivery audits of the chats they are enabled for
+#[derive(Debug, Default)]
+pub struct DeliveryAudits {
+    /// Number of audited chats, read by streams before taking the lock
+    enabled: AtomicUsize,
+    chats: Mutex<HashMap<String, ChatAudit>>,
+}
+
+impl DeliveryAudits {
+    /// Start auditing a chat, discarding what an earlier audit recorded
+    /// Returns whether the chat was already audited.
+    pub fn enable(&self, live_chat_id: &str) -> bool {
+        let mut chats = self
+            .chats
+            .lock()
+            .expect("Failed to acquire lock on delivery audits");
+        let reset = chats
+            .insert(live_chat_id.to_string(), ChatAudit::new())
+            .is_some();
+        if !reset {
+            self.enabled.fetch_add(1, Ordering::Relaxed);
+        }
+        reset
+    }
+
+    /// Stop auditing a chat and discard its records; returns whether it was audited
+    pub fn disable(&self, live_chat_id: &str) -> bool {
+        let removed = self
+            .chats
+            .lock()
+            .expect("Failed to acquire lock on delivery audits")
+            .remove(live_chat_id)
+            .is_some();
+        if removed {
+            self.enabled.fetch_sub(1, Ordering::Relaxed);
+        }
+        removed
+    }
+
+    /// Stop auditing every chat and discard the records
+    pub fn clear(&self) {
+        let mut chats = self
+            .chats
+            .lock()
+            .expect("Failed to acquire lock on delivery audits");
+        chats.clear();
+        self.enabled.store(0, Ordering::Relaxed);
+    }
+
+    /// Whether any chat is audited
+    pub fn any_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed) > 0
+    }
+
+    /// Record that `stream_id` sent the messages of `live_chat_id`
+    pub fn record<'a>(
+        &self,
+        live_chat_id: &str,
+        stream_id: u64,
+        message_ids: impl IntoIterator<Item = &'a str>,
+    ) {
+        if !self.any_enabled() {
+            return;
+        }
+        let mut chats = self
+            .chats
+            .lock()
+            .expect("Failed to acquire lock on delivery audits");
+        let Some(audit) = chats.get_mut(live_chat_id) else {
+            return;
+        };
+        let delivered_at = clock::system_clock().now();
+        for message_id in message_ids {
+            *audit.counts.entry(message_id.to_string()).or_default() += 1;
+            if audit.records.len() == DELIVERY_AUDIT_CAPACITY {
+                audit.records.pop_front();
+                audit.dropped_records += 1;
+            }
+            audit.records.push_back(Delivery {
+                message_id: message_id.to_string(),
+                stream_id,
+                delivered_at,
+            });
+        }
+    }
+
+    /// Audit of a chat against the messages it holds, `None` when the chat is not audited
+    pub fn report(
+        &self,
+        live_chat_id: &str,
+        message_ids: &[String],
+    ) -> Option<DeliveryAuditReport> {
+        let chats = self
+            .chats
+            .lock()
+            .expect("Failed to acquire lock on delivery audits");
+        let audit = chats.get(live_chat_id)?;
+
+        let stored: HashSet<&str> = message_ids.iter().map(String::as_str).collect();
+        let mut deliveries: BTreeMap<String, u64> = message_ids
+            .iter()
+            .map(|id| (id.clone(), audit.counts.get(id).copied().unwrap_or(0)))
+            .collect();
+        // Messages deleted since they were sent are still reported
+        for (id, count) in &audit.counts {
+            if !stored.contains(id.as_str()) {
+                deliveries.insert(id.clone(), *count);
+            }
+        }
+        let undelivered_ids: Vec<String> = message_ids
+            .iter()
+            .filter(|id| !audit.counts.contains_key(*id))
+            .cloned()
+            .collect();
+        let duplicates: BTreeMap<String, u64> = deliveries
+            .iter()
+            .filter(|(_, count)| **count > 1)
+            .map(|(id, count)| (id.clone(), *count))
+            .collect();
+
+        Some(DeliveryAuditReport {
+            live_chat_id: live_chat_id.to_string(),
+            enabled_at: audit.enabled_at,
+            summary: DeliveryAuditSummary {
+                messages: message_ids.len(),
+                delivered: message_ids.len() - undelivered_ids.len(),
+                complete: undelivered_ids.is_empty(),
+                undelivered_ids,
+                duplicates,
+                dropped_records: audit.dropped_records,
+            },
+            deliveries,
+            records: audit.records.iter().cloned().collect(),
+        })
+    }
+}
+
+/// Delivery counts of an audited chat
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryAuditReport {
+    pub live_chat_id: String,
+    pub enabled_at: DateTime<Utc>,
+    pub summary: DeliveryAuditSummary,
+    /// Number of times each message was sent, by message ID
+    pub deliveries: BTreeMap<String, u64>,
+    /// Retained delivery records, oldest first
+    pub records: Vec<Delivery>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryAuditSummary {
+    /// Messages stored in the chat
+    pub messages: usize,
+    /// Stored messages sent at least once
+    pub delivered: usize,
+    /// Whether every stored message was sent
+    pub complete: bool,
+    /// Stored messages never sent, in chat order
+    pub undelivered_ids: Vec<String>,
+    /// Messages sent more than once, with their delivery counts
+    pub duplicates: BTreeMap<String, u64>,
+    /// Records dropped to keep the log within its capacity
+    pub dropped_records: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_deliveries_are_only_recorded_for_audited_chats() {
+        let audits = DeliveryAudits::default();
+        audits.record("chat-1", 0, ["msg-1"]);
+        assert!(!audits.any_enabled());
+        assert!(audits.report("chat-1", &ids(&["msg-1"])).is_none());
+
+        assert!(!audits.enable("chat-1"));
+        audits.record("chat-1", 0, ["msg-1", "msg-2"]);
+        audits.record("chat-1", 1, ["msg-2"]);
+        audits.record("chat-2", 1, ["other"]);
+
+        let report = audits
+            .report("chat-1", &ids(&["msg-1", "msg-2", "msg-3"]))
+            .unwrap();
+        assert_eq!(report.deliveries["msg-1"], 1);
+        assert_eq!(report.deliveries["msg-2"], 2);
+        assert_eq!(report.deliveries["msg-3"], 0);
+        assert_eq!(report.summary.delivered, 2);
+        assert!(!report.summary.complete);
+        assert_eq!(report.summary.undelivered_ids, ids(&["msg-3"]));
+        assert_eq!(report.summary.duplicates.len(), 1);
+        assert_eq!(report.summary.duplicates["msg-2"], 2);
+        assert_eq!(report.records.len(), 3);
+        assert_eq!(report.records[2].stream_id, 1);
+
+        // Enabling again resets the audit
+        assert!(audits.enable("chat-1"));
+        let report = audits.report("chat-1", &ids(&["msg-1"])).unwrap();
+        assert_eq!(report.summary.undelivered_ids, ids(&["msg-1"]));
+        assert!(report.records.is_empty());
+
+        assert!(audits.disable("chat-1"));
+        assert!(!audits.disable("chat-1"));
+        assert!(!audits.any_enabled());
+    }
+
+    #[test]
+    fn test_records_are_bounded_but_counts_stay_exact() {
+        let audits = DeliveryAudits::default();
+        audits.enable("chat-1");
+        audits.record("chat-1", 0, ["first"]);
+        let repeated: Vec<&str> = vec!["repeated"; DELIVERY_AUDIT_CAPACITY];
+        audits.record("chat-1", 0, repeated);
+
+        let report = audits.report("chat-1", &ids(&["first"])).unwrap();
+        assert_eq!(report.records.len(), DELIVERY_AUDIT_CAPACITY);
+        assert_eq!(report.summary.dropped_records, 1);
+        assert_eq!(report.deliveries["first"], 1);
+        assert!(report.summary.complete);
+        // A delivered message that is no longer stored is still reported
+        assert_eq!(
+            report.summary.duplicates["repeated"],
+            DELIVERY_AUDIT_CAPACITY as u64
+        );
+    }
+}
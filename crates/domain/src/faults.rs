@@ -0,0 +1,223 @@
+//! Fault injection for resilience testing
+//!
+//! The control API configures faults on a shared [`FaultConfig`]; the REST, OAuth and
+//! gRPC handlers consult it and answer with the configured error instead of serving the
+//! call. A fault affects either a number of calls or each call with some probability.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::Mutex;
+
+/// Canonical gRPC status code names, indexed by code
+pub const GRPC_CODE_NAMES: [&str; 17] = [
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+/// Numeric gRPC status code of a canonical name such as `UNAVAILABLE`
+pub fn grpc_code(name: &str) -> Option<i32> {
+    GRPC_CODE_NAMES
+        .iter()
+        .position(|code| *code == name)
+        .and_then(|code| i32::try_from(code).ok())
+}
+
+/// Call a fault applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FaultTarget {
+    VideosList,
+    OauthToken,
+    LiveChatStreamList,
+}
+
+impl FaultTarget {
+    pub const ALL: [Self; 3] = [Self::VideosList, Self::OauthToken, Self::LiveChatStreamList];
+
+    /// Name used by the control API
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VideosList => "videos.list",
+            Self::OauthToken => "oauth.token",
+            Self::LiveChatStreamList => "liveChat.streamList",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|target| target.name() == name)
+    }
+}
+
+impl Serialize for FaultTarget {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// A configured fault and the error it returns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fault {
+    pub target: FaultTarget,
+    /// HTTP status of REST and OAuth errors
+    pub http_status: u16,
+    /// YouTube error reason of REST errors, the `error` code of OAuth errors
+    pub reason: String,
+    pub message: String,
+    /// gRPC status code name of stream errors
+    pub grpc_code: String,
+    /// Chance of affecting each call; `None` when the fault affects a number of calls
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f64>,
+    /// Calls still to be affected; `None` when the fault is probabilistic
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<u64>,
+    /// For streams, deliver this many messages before aborting instead of failing the call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_messages: Option<u64>,
+}
+
+/// Faults by target, shared by the API services and the control API
+#[derive(Debug, Default)]
+pub struct FaultConfig {
+    faults: Mutex<BTreeMap<FaultTarget, Fault>>,
+}
+
+impl FaultConfig {
+    /// Configure `fault`, replacing any earlier fault of its target
+    pub fn set(&self, fault: Fault) {
+        self.faults
+            .lock()
+            .expect("Failed to acquire lock on faults")
+            .insert(fault.target, fault);
+    }
+
+    /// Remove every fault
+    pub fn clear(&self) {
+        self.faults
+            .lock()
+            .expect("Failed to acquire lock on faults")
+            .clear();
+    }
+
+    /// The configured faults, ordered by target
+    pub fn list(&self) -> Vec<Fault> {
+        self.faults
+            .lock()
+            .expect("Failed to acquire lock on faults")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// The fault affecting this call to `target`, if any
+    /// Counted faults use up one call and are removed once exhausted
+    pub fn hit(&self, target: FaultTarget) -> Option<Fault> {
+        self.hit_with_roll(target, random_roll())
+    }
+
+    fn hit_with_roll(&self, target: FaultTarget, roll: f64) -> Option<Fault> {
+        let mut faults = self
+            .faults
+            .lock()
+            .expect("Failed to acquire lock on faults");
+        let fault = faults.get_mut(&target)?;
+        match (fault.remaining.as_mut(), fault.probability) {
+            (Some(remaining), _) => {
+                *remaining = remaining.saturating_sub(1);
+                let exhausted = *remaining == 0;
+                let hit = fault.clone();
+                if exhausted {
+                    faults.remove(&target);
+                }
+                Some(hit)
+            }
+            (None, Some(probability)) => (roll < probability).then(|| fault.clone()),
+            (None, None) => Some(fault.clone()),
+        }
+    }
+}
+
+/// Uniform value in `[0, 1)` from the randomly keyed std hasher
+fn random_roll() -> f64 {
+    (RandomState::new().hash_one(0u8) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fault(remaining: Option<u64>, probability: Option<f64>) -> Fault {
+        Fault {
+            target: FaultTarget::VideosList,
+            http_status: 403,
+            reason: "quotaExceeded".to_string(),
+            message: "Injected fault".to_string(),
+            grpc_code: "UNAVAILABLE".to_string(),
+            probability,
+            remaining,
+            after_messages: None,
+        }
+    }
+
+    #[test]
+    fn test_counted_fault_is_used_up() {
+        let faults = FaultConfig::default();
+        faults.set(fault(Some(2), None));
+
+        assert!(faults.hit(FaultTarget::OauthToken).is_none());
+        assert_eq!(
+            faults.hit(FaultTarget::VideosList).unwrap().remaining,
+            Some(1)
+        );
+        assert_eq!(
+            faults.hit(FaultTarget::VideosList).unwrap().remaining,
+            Some(0)
+        );
+        assert!(faults.hit(FaultTarget::VideosList).is_none());
+        assert!(faults.list().is_empty());
+    }
+
+    #[test]
+    fn test_probabilistic_fault_follows_roll() {
+        let faults = FaultConfig::default();
+        faults.set(fault(None, Some(0.25)));
+
+        assert!(faults.hit_with_roll(FaultTarget::VideosList, 0.1).is_some());
+        assert!(faults.hit_with_roll(FaultTarget::VideosList, 0.5).is_none());
+        assert_eq!(faults.list().len(), 1);
+
+        faults.clear();
+        assert!(faults.hit_with_roll(FaultTarget::VideosList, 0.1).is_none());
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for target in FaultTarget::ALL {
+            assert_eq!(FaultTarget::from_name(target.name()), Some(target));
+        }
+        assert_eq!(grpc_code("UNAVAILABLE"), Some(14));
+        assert_eq!(grpc_code("unavailable"), None);
+        assert!(
+            (0..1000)
+                .map(|_| random_roll())
+                .all(|roll| (0.0..1.0).contains(&roll))
+        );
+    }
+}
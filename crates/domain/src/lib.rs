@@ -1,8 +1,27 @@
+use base64::{
+    Engine as _,
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD},
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+pub mod authors;
+pub mod delivery_audit;
+pub mod faults;
+pub mod quota;
+pub mod streams;
+
+pub use authors::{AuthorPersona, AuthorRegistry};
+pub use delivery_audit::{DeliveryAuditReport, DeliveryAudits};
+pub use faults::{Fault, FaultConfig, FaultTarget};
+pub use quota::{QuotaEndpoint, QuotaErrorStatus, QuotaExceeded, QuotaLedger, QuotaSettings};
+pub use streams::{CloseReason, ClosedStream, StreamGuard, StreamRegistry};
 
 /// Represents a video resource
+/// Serialized with camelCase field names; seed files keep the snake_case names
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
 pub struct Video {
     pub id: String,
     pub channel_id: String,
@@ -18,6 +37,74 @@ pub struct Video {
     pub concurrent_viewers: Option<u64>,
 }
 
+/// Broadcast state of a video, as reported in `snippet.liveBroadcastContent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveBroadcastContent {
+    /// Not a live broadcast, or the broadcast has ended
+    None,
+    /// Scheduled but not started yet
+    Upcoming,
+    /// Currently live
+    Live,
+}
+
+impl Video {
+    /// Check that `scheduled_start_time <= actual_start_time <= actual_end_time` for the times that are set
+    pub fn validate_times(&self) -> Result<(), String> {
+        let ordered = [
+            ("scheduledStartTime", self.scheduled_start_time),
+            ("actualStartTime", self.actual_start_time),
+            ("actualEndTime", self.actual_end_time),
+        ];
+        let set: Vec<_> = ordered
+            .iter()
+            .filter_map(|(name, time)| time.map(|time| (*name, time)))
+            .collect();
+        for pair in set.windows(2) {
+            let ((earlier_name, earlier), (later_name, later)) = (pair[0], pair[1]);
+            if earlier > later {
+                return Err(format!(
+                    "{earlier_name} ({}) must not be after {later_name} ({})",
+                    earlier.to_rfc3339(),
+                    later.to_rfc3339()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Broadcast state at `now`
+    ///
+    /// Start times are inclusive and end times exclusive: exactly at `actual_start_time`
+    /// the video is live, exactly at `actual_end_time` it is no longer live.
+    pub fn live_broadcast_content(&self, now: DateTime<Utc>) -> LiveBroadcastContent {
+        if self.actual_end_time.is_some_and(|end| end <= now) {
+            LiveBroadcastContent::None
+        } else if self.actual_start_time.is_some_and(|start| start <= now) {
+            LiveBroadcastContent::Live
+        } else if self.scheduled_start_time.is_some() || self.actual_start_time.is_some() {
+            LiveBroadcastContent::Upcoming
+        } else {
+            LiveBroadcastContent::None
+        }
+    }
+}
+
+/// Represents a channel resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Channel {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Handle such as `@mockchannel`
+    pub custom_url: String,
+    pub published_at: DateTime<Utc>,
+    pub subscriber_count: u64,
+    pub video_count: u64,
+    pub view_count: u64,
+}
+
 /// Represents a live chat message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiveChatMessage {
@@ -28,4 +115,650 @@ pub struct LiveChatMessage {
     pub message_text: String,
     pub published_at: DateTime<Utc>,
     pub is_verified: bool,
+    /// Set for super chats; plain text messages have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub super_chat_details: Option<SuperChatDetails>,
+    /// Set for super stickers; plain text messages have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub super_sticker_details: Option<SuperStickerDetails>,
+    /// Set for deletion events, to the ID of the message that was deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_message_id: Option<String>,
+    /// Chat roles and avatar of the author
+    #[serde(flatten)]
+    pub author_profile: AuthorProfile,
+    /// Posted while its author was muted in the chat: stored, but only served to the author
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub muted: bool,
+}
+
+/// Chat roles and avatar of a message's author, shown as badges by chat clients
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthorProfile {
+    pub is_chat_owner: bool,
+    pub is_chat_moderator: bool,
+    pub is_chat_sponsor: bool,
+    /// Avatar of the author; a placeholder derived from the channel ID when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_image_url: Option<String>,
+}
+
+/// Paid message details of a super chat
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperChatDetails {
+    /// Amount paid in micros of `currency`, e.g. 5000000 for 5.00
+    pub amount_micros: u64,
+    /// ISO 4217 currency code
+    pub currency: String,
+    /// Tier of the super chat, which decides its color in the real client
+    pub tier: u32,
+    pub user_comment: String,
+}
+
+impl SuperChatDetails {
+    /// Amount as the real API displays it, e.g. "$5.00" or "¥500"
+    pub fn amount_display_string(&self) -> String {
+        amount_display_string(self.amount_micros, &self.currency)
+    }
+}
+
+/// Paid sticker details of a super sticker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperStickerDetails {
+    /// Amount paid in micros of `currency`, e.g. 5000000 for 5.00
+    pub amount_micros: u64,
+    /// ISO 4217 currency code
+    pub currency: String,
+    /// Tier of the super sticker, which decides its color in the real client
+    pub tier: u32,
+    pub sticker_id: String,
+    /// Accessible description of the sticker
+    pub alt_text: String,
+}
+
+impl SuperStickerDetails {
+    /// Amount as the real API displays it, e.g. "$5.00" or "¥500"
+    pub fn amount_display_string(&self) -> String {
+        amount_display_string(self.amount_micros, &self.currency)
+    }
+}
+
+fn amount_display_string(amount_micros: u64, currency: &str) -> String {
+    let amount = amount_micros as f64 / 1_000_000.0;
+    let (symbol, decimals) = match currency {
+        "USD" => ("$", 2),
+        "EUR" => ("€", 2),
+        "GBP" => ("£", 2),
+        "JPY" => ("¥", 0),
+        _ => return format!("{currency} {amount:.2}"),
+    };
+    format!("{symbol}{amount:.decimals$}")
+}
+
+/// Error reason for joining a chat that has not started
+pub const LIVE_CHAT_NOT_STARTED: &str = "liveChatNotStarted";
+
+/// Whether chats that have not started are closed to clients, set by
+/// `SCHEDULED_CHAT_NOT_STARTED`
+/// Streams of such chats then fail with `liveChatNotStarted` instead of waiting for the start,
+/// and videos.list omits their `activeLiveChatId`. Read on each call, like `REQUIRE_AUTH`.
+pub fn scheduled_chat_not_started() -> bool {
+    std::env::var("SCHEDULED_CHAT_NOT_STARTED")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
+/// Lifecycle state of a live chat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveChatState {
+    /// The broadcast has not started; the chat exists but has no messages yet
+    Scheduled,
+    /// The broadcast is live and messages are delivered
+    Active,
+    /// The broadcast is over; no further messages will be delivered
+    Ended,
+}
+
+impl std::str::FromStr for LiveChatState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "scheduled" => Ok(Self::Scheduled),
+            "active" => Ok(Self::Active),
+            "ended" => Ok(Self::Ended),
+            _ => Err(format!(
+                "Unknown live chat state '{s}'. Use 'scheduled', 'active' or 'ended'"
+            )),
+        }
+    }
+}
+
+/// Lifecycle of a live chat
+/// Chats without a stored lifecycle are treated as active
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChat {
+    pub id: String,
+    pub state: LiveChatState,
+    pub scheduled_start_time: Option<DateTime<Utc>>,
+    pub actual_start_time: Option<DateTime<Utc>>,
+    /// When the chat ended
+    pub offline_at: Option<DateTime<Utc>>,
+    /// Messages published longer ago than this many seconds are withheld from clients but
+    /// stay stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_retention_seconds: Option<u64>,
+    /// Channels whose messages are stored but only served back to their author
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub muted_author_channel_ids: BTreeSet<String>,
+}
+
+impl LiveChat {
+    /// A chat for a broadcast that has not started yet
+    pub fn scheduled(id: &str, scheduled_start_time: Option<DateTime<Utc>>) -> Self {
+        Self {
+            id: id.to_string(),
+            state: LiveChatState::Scheduled,
+            scheduled_start_time,
+            actual_start_time: None,
+            offline_at: None,
+            history_retention_seconds: None,
+            muted_author_channel_ids: BTreeSet::new(),
+        }
+    }
+
+    /// A chat that is live, as chats without a stored lifecycle are
+    pub fn active(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            state: LiveChatState::Active,
+            scheduled_start_time: None,
+            actual_start_time: None,
+            offline_at: None,
+            history_retention_seconds: None,
+            muted_author_channel_ids: BTreeSet::new(),
+        }
+    }
+
+    /// Oldest `published_at` served to clients at `now`; `None` serves the whole history
+    pub fn history_cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let retention = chrono::Duration::try_seconds(
+            i64::try_from(self.history_retention_seconds?).unwrap_or(i64::MAX),
+        )?;
+        now.checked_sub_signed(retention)
+    }
+
+    /// Whether a message is still served to clients at `now` under the history retention
+    pub fn serves(&self, message: &LiveChatMessage, now: DateTime<Utc>) -> bool {
+        self.history_cutoff(now)
+            .is_none_or(|cutoff| message.published_at >= cutoff)
+    }
+
+    /// Move the chat forward to `to` at time `at`
+    /// Transitions only go forward (scheduled → active → ended, or scheduled → ended);
+    /// transitioning to the current state is a no-op
+    pub fn transition(&mut self, to: LiveChatState, at: DateTime<Utc>) -> Result<(), String> {
+        use LiveChatState::*;
+        match (self.state, to) {
+            (from, to) if from == to => return Ok(()),
+            (Scheduled, Active) => self.actual_start_time = Some(at),
+            (Scheduled | Active, Ended) => self.offline_at = Some(at),
+            (from, to) => {
+                return Err(format!(
+                    "Cannot transition live chat '{}' from {from:?} to {to:?}",
+                    self.id
+                ));
+            }
+        }
+        self.state = to;
+        Ok(())
+    }
+}
+
+/// Author details of a live chat message
+/// Shared by the REST and gRPC serializers so both transports expose identical flags
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorDetails {
+    pub channel_id: String,
+    pub channel_url: String,
+    pub display_name: String,
+    pub profile_image_url: String,
+    pub is_verified: bool,
+    pub is_chat_owner: bool,
+    pub is_chat_sponsor: bool,
+    pub is_chat_moderator: bool,
+}
+
+/// Check that a message text is at most `max_len` characters long, counted as Unicode
+/// scalar values; any length passes when `max_len` is `None`
+pub fn validate_message_text_len(text: &str, max_len: Option<usize>) -> Result<(), String> {
+    match max_len {
+        Some(max_len) if text.chars().count() > max_len => Err(format!(
+            "messageText is {} characters long, the maximum is {max_len}",
+            text.chars().count()
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl LiveChatMessage {
+    /// Event announcing that `deleted` was removed from its chat at `deleted_at`
+    ///
+    /// The event is attributed to the author of the deleted message and has no text.
+    pub fn deletion_event(deleted: &LiveChatMessage, deleted_at: DateTime<Utc>) -> Self {
+        Self {
+            id: format!("{}-deleted", deleted.id),
+            live_chat_id: deleted.live_chat_id.clone(),
+            author_channel_id: deleted.author_channel_id.clone(),
+            author_display_name: deleted.author_display_name.clone(),
+            message_text: String::new(),
+            published_at: deleted_at,
+            is_verified: deleted.is_verified,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: Some(deleted.id.clone()),
+            author_profile: deleted.author_profile.clone(),
+            muted: deleted.muted,
+        }
+    }
+
+    /// Whether the message is served to a viewer, `None` when the viewer is not identified
+    /// Messages posted while their author was muted are only served back to that author
+    pub fn visible_to(&self, viewer_channel_id: Option<&str>) -> bool {
+        !self.muted || viewer_channel_id == Some(self.author_channel_id.as_str())
+    }
+
+    /// Value of `snippet.type` in the JSON API
+    pub fn message_type(&self) -> &'static str {
+        if self.deleted_message_id.is_some() {
+            "messageDeletedEvent"
+        } else if self.super_chat_details.is_some() {
+            "superChatEvent"
+        } else if self.super_sticker_details.is_some() {
+            "superStickerEvent"
+        } else {
+            "textMessageEvent"
+        }
+    }
+
+    /// Build the author details exposed for this message
+    pub fn author_details(&self) -> AuthorDetails {
+        AuthorDetails {
+            channel_id: self.author_channel_id.clone(),
+            channel_url: format!("http://www.youtube.com/channel/{}", self.author_channel_id),
+            display_name: self.author_display_name.clone(),
+            profile_image_url: self
+                .author_profile
+                .profile_image_url
+                .clone()
+                .unwrap_or_else(|| authors::profile_image_url(&self.author_channel_id)),
+            is_verified: self.is_verified,
+            is_chat_owner: self.author_profile.is_chat_owner,
+            is_chat_sponsor: self.author_profile.is_chat_sponsor,
+            is_chat_moderator: self.author_profile.is_chat_moderator,
+        }
+    }
+}
+
+/// Append a length-delimited protobuf field
+fn put_proto_field(out: &mut Vec<u8>, field: u8, value: &[u8]) {
+    out.push(field << 3 | 2);
+    let mut len = value.len();
+    while len >= 0x80 {
+        out.push((len as u8 & 0x7f) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+    out.extend_from_slice(value);
+}
+
+/// Live chat ID in the format of the real API, derived from the video and its channel
+///
+/// Real IDs are unpadded base64 of a protobuf message wrapping the video and channel IDs,
+/// starting with `Cg0KC` for 11-character video IDs. The same video always gets the same ID.
+pub fn realistic_live_chat_id(video_id: &str, channel_id: &str) -> String {
+    let mut video = Vec::new();
+    put_proto_field(&mut video, 1, video_id.as_bytes());
+    let mut channel = Vec::new();
+    put_proto_field(&mut channel, 1, channel_id.as_bytes());
+
+    let mut id = Vec::new();
+    put_proto_field(&mut id, 1, &video);
+    put_proto_field(&mut id, 5, &channel);
+    put_proto_field(&mut id, 2, video_id.as_bytes());
+    URL_SAFE_NO_PAD.encode(id)
+}
+
+/// Default number of chat messages per list response, as in the real API
+pub const DEFAULT_MAX_RESULTS: usize = 500;
+
+/// Largest `maxResults` accepted for chat message lists
+pub const MAX_MAX_RESULTS: usize = 2000;
+
+/// Encode a chat message index as a page token (base64 of the decimal index)
+/// Shared by the gRPC stream and the REST list endpoint so tokens work on both
+pub fn encode_page_token(index: usize) -> String {
+    BASE64.encode(index.to_string().as_bytes())
+}
+
+/// Reasons a page token cannot be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageTokenError {
+    NotBase64,
+    NotUtf8,
+    NotIndex,
+}
+
+impl std::fmt::Display for PageTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotBase64 => write!(f, "Invalid page_token: not valid base64"),
+            Self::NotUtf8 => write!(f, "Invalid page_token: not valid UTF-8"),
+            Self::NotIndex => write!(f, "Invalid page_token: not a message index"),
+        }
+    }
+}
+
+impl std::error::Error for PageTokenError {}
+
+/// Decode a page token into the chat message index to resume from
+pub fn decode_page_token(token: &str) -> Result<usize, PageTokenError> {
+    let decoded = BASE64
+        .decode(token)
+        .map_err(|_| PageTokenError::NotBase64)?;
+    let decoded_str = String::from_utf8(decoded).map_err(|_| PageTokenError::NotUtf8)?;
+    decoded_str
+        .parse::<usize>()
+        .map_err(|_| PageTokenError::NotIndex)
+}
+
+/// Policy for rendering a chat message's `displayMessage`
+/// The stored message text is always raw; the policy is applied exactly once at serialization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayMessagePolicy {
+    /// Pass the message text through unchanged
+    Raw,
+    /// HTML-escape `&`, `<`, `>`, `"` and `'`
+    Escaped,
+}
+
+/// Default rendering policy, matching the raw text returned by the streamList endpoint
+pub const DEFAULT_DISPLAY_MESSAGE_POLICY: DisplayMessagePolicy = DisplayMessagePolicy::Raw;
+
+impl Default for DisplayMessagePolicy {
+    fn default() -> Self {
+        DEFAULT_DISPLAY_MESSAGE_POLICY
+    }
+}
+
+impl std::str::FromStr for DisplayMessagePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "raw" => Ok(Self::Raw),
+            "escaped" => Ok(Self::Escaped),
+            _ => Err(format!(
+                "Unknown display message policy '{s}'. Use 'raw' or 'escaped'"
+            )),
+        }
+    }
+}
+
+impl DisplayMessagePolicy {
+    /// Render the raw message text according to this policy
+    pub fn render(&self, text: &str) -> String {
+        match self {
+            Self::Raw => text.to_string(),
+            Self::Escaped => {
+                let mut escaped = String::with_capacity(text.len());
+                for c in text.chars() {
+                    match c {
+                        '&' => escaped.push_str("&amp;"),
+                        '<' => escaped.push_str("&lt;"),
+                        '>' => escaped.push_str("&gt;"),
+                        '"' => escaped.push_str("&quot;"),
+                        '\'' => escaped.push_str("&#39;"),
+                        _ => escaped.push(c),
+                    }
+                }
+                escaped
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_text_len_is_counted_in_characters() {
+        assert_eq!(validate_message_text_len(&"x".repeat(500), None), Ok(()));
+        assert_eq!(validate_message_text_len("héllo", Some(5)), Ok(()));
+        assert_eq!(validate_message_text_len("", Some(0)), Ok(()));
+        assert_eq!(
+            validate_message_text_len("héllo!", Some(5)),
+            Err("messageText is 6 characters long, the maximum is 5".to_string())
+        );
+    }
+
+    const TRICKY: &str = r#"<script>alert('x')</script> Tom & "Jerry" &amp; **bold**"#;
+
+    fn broadcast(
+        scheduled: Option<DateTime<Utc>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Video {
+        Video {
+            id: "video".to_string(),
+            channel_id: "channel".to_string(),
+            title: String::new(),
+            description: String::new(),
+            channel_title: String::new(),
+            published_at: Utc::now(),
+            live_chat_id: None,
+            actual_start_time: start,
+            actual_end_time: end,
+            scheduled_start_time: scheduled,
+            scheduled_end_time: None,
+            concurrent_viewers: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_times_requires_scheduled_start_end_order() {
+        use chrono::Duration;
+
+        let t = Utc::now();
+        let hour = Duration::hours(1);
+        assert!(broadcast(None, None, None).validate_times().is_ok());
+        assert!(
+            broadcast(Some(t), Some(t), Some(t))
+                .validate_times()
+                .is_ok()
+        );
+        assert!(
+            broadcast(Some(t), None, Some(t + hour))
+                .validate_times()
+                .is_ok()
+        );
+
+        assert!(
+            broadcast(Some(t + hour), Some(t), None)
+                .validate_times()
+                .is_err()
+        );
+        assert!(
+            broadcast(None, Some(t + hour), Some(t))
+                .validate_times()
+                .is_err()
+        );
+        // Pairs are checked even when the time between them is missing
+        let error = broadcast(Some(t + hour), None, Some(t))
+            .validate_times()
+            .unwrap_err();
+        assert!(error.starts_with("scheduledStartTime"), "{error}");
+    }
+
+    #[test]
+    fn test_live_broadcast_content_at_boundaries() {
+        use chrono::Duration;
+
+        let start = Utc::now();
+        let end = start + Duration::hours(1);
+        let before = start - Duration::nanoseconds(1);
+        let video = broadcast(Some(start), Some(start), Some(end));
+
+        assert_eq!(
+            video.live_broadcast_content(before),
+            LiveBroadcastContent::Upcoming
+        );
+        assert_eq!(
+            video.live_broadcast_content(start),
+            LiveBroadcastContent::Live
+        );
+        assert_eq!(
+            video.live_broadcast_content(end - Duration::nanoseconds(1)),
+            LiveBroadcastContent::Live
+        );
+        assert_eq!(
+            video.live_broadcast_content(end),
+            LiveBroadcastContent::None
+        );
+
+        let scheduled = broadcast(Some(start), None, None);
+        assert_eq!(
+            scheduled.live_broadcast_content(start),
+            LiveBroadcastContent::Upcoming
+        );
+        assert_eq!(
+            broadcast(None, None, None).live_broadcast_content(start),
+            LiveBroadcastContent::None
+        );
+    }
+
+    #[test]
+    fn test_live_chat_transitions_forward_only() {
+        let at = chrono::Utc::now();
+        let mut chat = LiveChat::scheduled("chat", None);
+
+        chat.transition(LiveChatState::Active, at).unwrap();
+        assert_eq!(chat.state, LiveChatState::Active);
+        assert_eq!(chat.actual_start_time, Some(at));
+        chat.transition(LiveChatState::Active, at).unwrap();
+
+        chat.transition(LiveChatState::Ended, at).unwrap();
+        assert_eq!(chat.offline_at, Some(at));
+        assert!(chat.transition(LiveChatState::Active, at).is_err());
+        assert!(chat.transition(LiveChatState::Scheduled, at).is_err());
+    }
+
+    #[test]
+    fn test_realistic_live_chat_ids_are_stable_and_opaque() {
+        // An 11-character video ID gives the `Cg0KC` prefix of real chat IDs
+        let id = realistic_live_chat_id("dQw4w9WgXcQ", "UCuAXFkgsw1L7xaCfnd5JJOw");
+        assert!(id.starts_with("Cg0KC"), "{id}");
+        assert_eq!(
+            id,
+            realistic_live_chat_id("dQw4w9WgXcQ", "UCuAXFkgsw1L7xaCfnd5JJOw")
+        );
+        assert_ne!(
+            id,
+            realistic_live_chat_id("other-video", "UCuAXFkgsw1L7xaCfnd5JJOw")
+        );
+        assert!(
+            id.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "{id}"
+        );
+
+        // Lengths past 127 bytes take two varint bytes
+        let long = "v".repeat(200);
+        let decoded = URL_SAFE_NO_PAD
+            .decode(realistic_live_chat_id(&long, "channel"))
+            .unwrap();
+        assert_eq!(&decoded[..4], [0x0a, 0xcb, 0x01, 0x0a]);
+    }
+
+    #[test]
+    fn test_page_token_round_trip() {
+        for index in [0, 1, 42, 10_000] {
+            assert_eq!(decode_page_token(&encode_page_token(index)), Ok(index));
+        }
+    }
+
+    #[test]
+    fn test_page_token_errors() {
+        assert_eq!(
+            decode_page_token("not base64!"),
+            Err(PageTokenError::NotBase64)
+        );
+        assert_eq!(
+            decode_page_token(&BASE64.encode([0xff, 0xfe])),
+            Err(PageTokenError::NotUtf8)
+        );
+        assert_eq!(
+            decode_page_token(&BASE64.encode("-1")),
+            Err(PageTokenError::NotIndex)
+        );
+    }
+
+    #[test]
+    fn test_default_policy_is_raw() {
+        assert_eq!(DisplayMessagePolicy::default(), DisplayMessagePolicy::Raw);
+    }
+
+    #[test]
+    fn test_raw_policy_passes_text_through() {
+        let rendered = DisplayMessagePolicy::Raw.render(TRICKY);
+        assert_eq!(rendered.as_bytes(), TRICKY.as_bytes());
+    }
+
+    #[test]
+    fn test_escaped_policy_escapes_exactly_once() {
+        let rendered = DisplayMessagePolicy::Escaped.render(TRICKY);
+        assert_eq!(
+            rendered.as_bytes(),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; Tom &amp; &quot;Jerry&quot; &amp;amp; **bold**"
+                .as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_escaped_policy_preserves_unicode() {
+        let rendered = DisplayMessagePolicy::Escaped.render("ñ 🎉 <3");
+        assert_eq!(rendered.as_bytes(), "ñ 🎉 &lt;3".as_bytes());
+    }
+
+    #[test]
+    fn test_policy_from_str() {
+        assert_eq!("raw".parse(), Ok(DisplayMessagePolicy::Raw));
+        assert_eq!("Escaped".parse(), Ok(DisplayMessagePolicy::Escaped));
+        assert!("html".parse::<DisplayMessagePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_super_chat_amount_display_string() {
+        let details = |amount_micros, currency: &str| SuperChatDetails {
+            amount_micros,
+            currency: currency.to_string(),
+            tier: 1,
+            user_comment: String::new(),
+        };
+        assert_eq!(details(5_000_000, "USD").amount_display_string(), "$5.00");
+        assert_eq!(details(1_990_000, "EUR").amount_display_string(), "€1.99");
+        assert_eq!(details(500_000_000, "JPY").amount_display_string(), "¥500");
+        assert_eq!(
+            details(2_500_000, "CAD").amount_display_string(),
+            "CAD 2.50"
+        );
+    }
 }
@@ -0,0 +1,573 @@
+//! Quota accounting modeled on the YouTube Data API's unit costs
+//!
+//! Each API call is charged its documented cost against the caller's key. The REST and
+//! gRPC services charge calls to a shared [`QuotaLedger`]; the control API reports and
+//! resets the consumption, and can enforce a daily limit so callers run out of quota like
+//! they would against the real API.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Default daily quota of a Google Cloud project
+pub const DEFAULT_DAILY_QUOTA: u64 = 10_000;
+
+/// Units charged for `videos.list`
+pub const VIDEOS_LIST_COST: u64 = 1;
+
+/// Units charged for `channels.list`
+pub const CHANNELS_LIST_COST: u64 = 1;
+
+/// Units charged for `liveChatMessages.list`
+pub const LIVE_CHAT_MESSAGES_LIST_COST: u64 = 5;
+
+/// Units charged for `liveChatMessages.insert`
+pub const LIVE_CHAT_MESSAGES_INSERT_COST: u64 = 50;
+
+/// Units charged for `search.list`
+pub const SEARCH_LIST_COST: u64 = 100;
+
+/// Units charged for opening a `liveChatMessages.streamList` stream
+/// The stream is charged like a `liveChatMessages.list` call
+pub const LIVE_CHAT_MESSAGES_STREAM_LIST_COST: u64 = 5;
+
+/// Response header carrying the units charged for the call
+pub const QUOTA_COST_HEADER: &str = "x-mock-quota-cost";
+
+/// Response header carrying the caller's units left for the day
+pub const QUOTA_REMAINING_HEADER: &str = "x-mock-quota-remaining";
+
+/// Caller key for OAuth requests without an API key
+pub const OAUTH_CALLER: &str = "oauth";
+
+/// Caller key for requests without credentials
+pub const ANONYMOUS_CALLER: &str = "anonymous";
+
+/// Error reason of calls rejected for exceeding the daily quota
+pub const QUOTA_EXCEEDED: &str = "quotaExceeded";
+
+/// Message of calls rejected for exceeding the daily quota, as the real API words it
+pub const QUOTA_EXCEEDED_MESSAGE: &str = "The request cannot be completed because you have exceeded your <a href=\"/youtube/v3/getting-started#quota\">quota</a>.";
+
+/// HTTP status of REST calls rejected for exceeding the daily quota
+///
+/// The real API answers most of them with 403, and some with 429; clients that span API
+/// versions have to handle both shapes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuotaErrorStatus {
+    #[default]
+    Forbidden,
+    TooManyRequests,
+}
+
+impl QuotaErrorStatus {
+    /// Numeric HTTP status code
+    pub fn code(self) -> u16 {
+        match self {
+            Self::Forbidden => 403,
+            Self::TooManyRequests => 429,
+        }
+    }
+
+    /// Canonical status name reported in the error body, e.g. `RESOURCE_EXHAUSTED`
+    pub fn canonical_name(self) -> &'static str {
+        match self {
+            Self::Forbidden => "PERMISSION_DENIED",
+            Self::TooManyRequests => "RESOURCE_EXHAUSTED",
+        }
+    }
+}
+
+impl std::str::FromStr for QuotaErrorStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "403" => Ok(Self::Forbidden),
+            "429" => Ok(Self::TooManyRequests),
+            _ => Err(format!("Unknown quota error status '{s}'. Use 403 or 429")),
+        }
+    }
+}
+
+/// API method a call is charged for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuotaEndpoint {
+    VideosList,
+    SearchList,
+    ChannelsList,
+    LiveChatMessagesList,
+    LiveChatMessagesStreamList,
+    LiveChatMessagesInsert,
+}
+
+impl QuotaEndpoint {
+    pub const ALL: [Self; 6] = [
+        Self::VideosList,
+        Self::SearchList,
+        Self::ChannelsList,
+        Self::LiveChatMessagesList,
+        Self::LiveChatMessagesStreamList,
+        Self::LiveChatMessagesInsert,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|endpoint| endpoint.name() == name)
+    }
+
+    /// Method name as listed in the real API's quota cost table
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VideosList => "videos.list",
+            Self::SearchList => "search.list",
+            Self::ChannelsList => "channels.list",
+            Self::LiveChatMessagesList => "liveChatMessages.list",
+            Self::LiveChatMessagesStreamList => "liveChatMessages.streamList",
+            Self::LiveChatMessagesInsert => "liveChatMessages.insert",
+        }
+    }
+
+    /// Units charged per call unless the ledger overrides it
+    pub fn cost(&self) -> u64 {
+        match self {
+            Self::VideosList => VIDEOS_LIST_COST,
+            Self::SearchList => SEARCH_LIST_COST,
+            Self::ChannelsList => CHANNELS_LIST_COST,
+            Self::LiveChatMessagesList => LIVE_CHAT_MESSAGES_LIST_COST,
+            Self::LiveChatMessagesStreamList => LIVE_CHAT_MESSAGES_STREAM_LIST_COST,
+            Self::LiveChatMessagesInsert => LIVE_CHAT_MESSAGES_INSERT_COST,
+        }
+    }
+}
+
+/// Key a call is charged against: the API key, else [`OAUTH_CALLER`] for bearer
+/// credentials, else [`ANONYMOUS_CALLER`]
+pub fn caller_key(api_key: Option<&str>, has_authorization: bool) -> String {
+    match api_key.filter(|key| !key.is_empty()) {
+        Some(key) => key.to_string(),
+        None if has_authorization => OAUTH_CALLER.to_string(),
+        None => ANONYMOUS_CALLER.to_string(),
+    }
+}
+
+/// Result of charging a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaCharge {
+    pub cost: u64,
+    /// Units the caller has left of the daily quota
+    pub remaining: u64,
+}
+
+/// A call rejected because its key has used up the enforced daily limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    /// Units the call would have cost
+    pub cost: u64,
+    /// Units the caller has left, too few for the call
+    pub remaining: u64,
+    /// Enforced daily limit the key ran into
+    pub daily_limit: u64,
+}
+
+/// Daily limit and unit costs the ledger charges with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaSettings {
+    pub daily_limit: u64,
+    /// Whether calls beyond the daily limit are rejected; otherwise the limit only
+    /// determines the reported remaining units
+    pub enforced: bool,
+    /// Units charged per call, by method name
+    pub costs: BTreeMap<String, u64>,
+    /// HTTP status of REST calls rejected for exceeding the limit
+    pub error_status: u16,
+}
+
+/// Calls and units per endpoint in a report
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointUsage {
+    pub calls: u64,
+    pub units: u64,
+}
+
+/// Calls and units per caller key in a report
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyUsage {
+    pub calls: u64,
+    pub units: u64,
+    /// Units left of the daily quota, counting every charge since the last reset
+    pub remaining: u64,
+    pub by_endpoint: BTreeMap<String, EndpointUsage>,
+}
+
+/// Consumption within a time window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaReport {
+    /// Start of the window, `None` when the report covers everything since the last reset
+    pub since: Option<DateTime<Utc>>,
+    pub until: DateTime<Utc>,
+    pub daily_limit: u64,
+    pub calls: u64,
+    pub units: u64,
+    pub by_endpoint: BTreeMap<String, EndpointUsage>,
+    pub by_key: BTreeMap<String, KeyUsage>,
+}
+
+#[derive(Debug)]
+struct ChargeRecord {
+    at: DateTime<Utc>,
+    endpoint: QuotaEndpoint,
+    key: String,
+    cost: u64,
+}
+
+#[derive(Debug)]
+struct Limits {
+    /// Enforced daily limit, `None` when calls are never rejected
+    enforced_limit: Option<u64>,
+    /// Costs overriding the defaults of the quota table
+    costs: BTreeMap<QuotaEndpoint, u64>,
+}
+
+impl Limits {
+    fn daily_limit(&self) -> u64 {
+        self.enforced_limit.unwrap_or(DEFAULT_DAILY_QUOTA)
+    }
+
+    fn cost(&self, endpoint: QuotaEndpoint) -> u64 {
+        self.costs
+            .get(&endpoint)
+            .copied()
+            .unwrap_or_else(|| endpoint.cost())
+    }
+}
+
+/// Ledger of quota charges shared by the API services and the control API
+#[derive(Debug)]
+pub struct QuotaLedger {
+    cost_headers: bool,
+    error_status: QuotaErrorStatus,
+    limits: Mutex<Limits>,
+    charges: Mutex<Vec<ChargeRecord>>,
+}
+
+impl Default for QuotaLedger {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl QuotaLedger {
+    /// Ledger with the default daily quota
+    /// `cost_headers` enables the cost and remaining headers/metadata on responses
+    pub fn new(cost_headers: bool) -> Self {
+        Self {
+            cost_headers,
+            error_status: QuotaErrorStatus::default(),
+            limits: Mutex::new(Limits {
+                enforced_limit: None,
+                costs: BTreeMap::new(),
+            }),
+            charges: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Reject REST calls beyond the daily limit with `error_status` instead of 403
+    pub fn with_error_status(mut self, error_status: QuotaErrorStatus) -> Self {
+        self.error_status = error_status;
+        self
+    }
+
+    /// HTTP status of REST calls rejected for exceeding the daily limit
+    pub fn error_status(&self) -> QuotaErrorStatus {
+        self.error_status
+    }
+
+    /// Reject calls once their key has used `daily_limit` units, or stop rejecting with `None`
+    pub fn set_daily_limit(&self, daily_limit: Option<u64>) {
+        self.limits.lock().unwrap().enforced_limit = daily_limit;
+    }
+
+    /// Charge `cost` units per call to `endpoint` instead of its default
+    pub fn set_cost(&self, endpoint: QuotaEndpoint, cost: u64) {
+        self.limits.lock().unwrap().costs.insert(endpoint, cost);
+    }
+
+    /// Current daily limit and unit costs
+    pub fn settings(&self) -> QuotaSettings {
+        let limits = self.limits.lock().unwrap();
+        QuotaSettings {
+            daily_limit: limits.daily_limit(),
+            enforced: limits.enforced_limit.is_some(),
+            costs: QuotaEndpoint::ALL
+                .into_iter()
+                .map(|endpoint| (endpoint.name().to_string(), limits.cost(endpoint)))
+                .collect(),
+            error_status: self.error_status.code(),
+        }
+    }
+
+    /// Whether responses should carry the cost and remaining headers
+    pub fn cost_headers(&self) -> bool {
+        self.cost_headers
+    }
+
+    /// Charge a call to `endpoint` against `key`
+    /// With an enforced limit, a call the key cannot afford is rejected and not charged.
+    pub fn charge(&self, endpoint: QuotaEndpoint, key: &str) -> Result<QuotaCharge, QuotaExceeded> {
+        self.charge_at(endpoint, key, clock::system_clock().now())
+    }
+
+    fn charge_at(
+        &self,
+        endpoint: QuotaEndpoint,
+        key: &str,
+        at: DateTime<Utc>,
+    ) -> Result<QuotaCharge, QuotaExceeded> {
+        let limits = self.limits.lock().unwrap();
+        let mut charges = self.charges.lock().unwrap();
+        let cost = limits.cost(endpoint);
+        let used: u64 = charges
+            .iter()
+            .filter(|charge| charge.key == key)
+            .map(|charge| charge.cost)
+            .sum();
+        let remaining = limits.daily_limit().saturating_sub(used);
+        if limits.enforced_limit.is_some() && cost > remaining {
+            return Err(QuotaExceeded {
+                cost,
+                remaining,
+                daily_limit: limits.daily_limit(),
+            });
+        }
+
+        charges.push(ChargeRecord {
+            at,
+            endpoint,
+            key: key.to_string(),
+            cost,
+        });
+        Ok(QuotaCharge {
+            cost,
+            remaining: remaining.saturating_sub(cost),
+        })
+    }
+
+    /// Consumption over the last `window`, or since the last reset when `None`
+    pub fn report(&self, window: Option<Duration>) -> QuotaReport {
+        self.report_at(window, clock::system_clock().now())
+    }
+
+    fn report_at(&self, window: Option<Duration>, now: DateTime<Utc>) -> QuotaReport {
+        let daily_limit = self.limits.lock().unwrap().daily_limit();
+        let charges = self.charges.lock().unwrap();
+        let since = window.map(|window| now - window);
+
+        // Remaining quota is per day, not per window
+        let mut used: BTreeMap<&str, u64> = BTreeMap::new();
+        for charge in charges.iter() {
+            *used.entry(&charge.key).or_default() += charge.cost;
+        }
+
+        let mut report = QuotaReport {
+            since,
+            until: now,
+            daily_limit,
+            calls: 0,
+            units: 0,
+            by_endpoint: BTreeMap::new(),
+            by_key: BTreeMap::new(),
+        };
+        for charge in charges
+            .iter()
+            .filter(|charge| since.is_none_or(|since| charge.at >= since) && charge.at <= now)
+        {
+            let name = charge.endpoint.name().to_string();
+            report.calls += 1;
+            report.units += charge.cost;
+
+            let endpoint = report.by_endpoint.entry(name.clone()).or_default();
+            endpoint.calls += 1;
+            endpoint.units += charge.cost;
+
+            let key = report
+                .by_key
+                .entry(charge.key.clone())
+                .or_insert_with(|| KeyUsage {
+                    remaining: daily_limit.saturating_sub(used[charge.key.as_str()]),
+                    ..Default::default()
+                });
+            key.calls += 1;
+            key.units += charge.cost;
+            let key_endpoint = key.by_endpoint.entry(name).or_default();
+            key_endpoint.calls += 1;
+            key_endpoint.units += charge.cost;
+        }
+        report
+    }
+
+    /// Forget every charge, restoring each caller's full daily quota
+    pub fn reset(&self) {
+        self.charges.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_costs_match_the_quota_table() {
+        assert_eq!(QuotaEndpoint::VideosList.cost(), 1);
+        assert_eq!(QuotaEndpoint::ChannelsList.cost(), 1);
+        assert_eq!(QuotaEndpoint::LiveChatMessagesList.cost(), 5);
+        assert_eq!(QuotaEndpoint::LiveChatMessagesStreamList.cost(), 5);
+    }
+
+    #[test]
+    fn test_caller_key() {
+        assert_eq!(caller_key(Some("key-1"), true), "key-1");
+        assert_eq!(caller_key(Some(""), true), OAUTH_CALLER);
+        assert_eq!(caller_key(None, false), ANONYMOUS_CALLER);
+    }
+
+    #[test]
+    fn test_mixed_workload_report() {
+        let ledger = QuotaLedger::default();
+        let now = Utc::now();
+        let earlier = now - Duration::hours(2);
+
+        ledger
+            .charge_at(QuotaEndpoint::VideosList, "key-a", earlier)
+            .unwrap();
+        for _ in 0..3 {
+            ledger
+                .charge_at(QuotaEndpoint::VideosList, "key-a", now)
+                .unwrap();
+        }
+        ledger
+            .charge_at(QuotaEndpoint::LiveChatMessagesList, "key-a", now)
+            .unwrap();
+        let charge = ledger
+            .charge_at(QuotaEndpoint::LiveChatMessagesStreamList, "key-b", now)
+            .unwrap();
+        assert_eq!(
+            charge,
+            QuotaCharge {
+                cost: 5,
+                remaining: DEFAULT_DAILY_QUOTA - 5
+            }
+        );
+        let charge = ledger
+            .charge_at(QuotaEndpoint::LiveChatMessagesList, "key-a", now)
+            .unwrap();
+        assert_eq!(charge.remaining, DEFAULT_DAILY_QUOTA - 14);
+
+        let all = ledger.report_at(None, now);
+        assert_eq!(all.since, None);
+        assert_eq!((all.calls, all.units), (7, 19));
+        assert_eq!(
+            all.by_endpoint["videos.list"],
+            EndpointUsage { calls: 4, units: 4 }
+        );
+        assert_eq!(all.by_key["key-a"].units, 14);
+
+        let hour = ledger.report_at(Some(Duration::hours(1)), now);
+        assert_eq!((hour.calls, hour.units), (6, 18));
+        assert_eq!(
+            hour.by_endpoint["videos.list"],
+            EndpointUsage { calls: 3, units: 3 }
+        );
+        let key_a = &hour.by_key["key-a"];
+        assert_eq!((key_a.calls, key_a.units), (5, 13));
+        // Remaining counts the whole day, not only the window
+        assert_eq!(key_a.remaining, DEFAULT_DAILY_QUOTA - 14);
+        assert_eq!(
+            key_a.by_endpoint["liveChatMessages.list"],
+            EndpointUsage {
+                calls: 2,
+                units: 10
+            }
+        );
+        assert_eq!(hour.by_key["key-b"].units, 5);
+
+        ledger.reset();
+        let report = ledger.report_at(None, now);
+        assert_eq!((report.calls, report.units), (0, 0));
+        let charge = ledger
+            .charge_at(QuotaEndpoint::VideosList, "key-a", now)
+            .unwrap();
+        assert_eq!(charge.remaining, DEFAULT_DAILY_QUOTA - 1);
+    }
+
+    #[test]
+    fn test_enforced_limit_rejects_unaffordable_calls() {
+        let ledger = QuotaLedger::default();
+        let now = Utc::now();
+        ledger.set_daily_limit(Some(12));
+        ledger.set_cost(QuotaEndpoint::VideosList, 2);
+
+        for remaining in [10, 8, 6, 4, 2, 0] {
+            let charge = ledger.charge_at(QuotaEndpoint::VideosList, "key-a", now);
+            assert_eq!(charge, Ok(QuotaCharge { cost: 2, remaining }));
+        }
+        assert_eq!(
+            ledger.charge_at(QuotaEndpoint::VideosList, "key-a", now),
+            Err(QuotaExceeded {
+                cost: 2,
+                remaining: 0,
+                daily_limit: 12,
+            })
+        );
+        // A call costing more than the rest is rejected even with units left
+        for remaining in [7, 2] {
+            let charge = ledger.charge_at(QuotaEndpoint::LiveChatMessagesList, "key-b", now);
+            assert_eq!(charge.unwrap().remaining, remaining);
+        }
+        assert_eq!(
+            ledger.charge_at(QuotaEndpoint::LiveChatMessagesList, "key-b", now),
+            Err(QuotaExceeded {
+                cost: 5,
+                remaining: 2,
+                daily_limit: 12,
+            })
+        );
+        // Rejected calls are not charged
+        let report = ledger.report_at(None, now);
+        assert_eq!(report.daily_limit, 12);
+        assert_eq!(report.by_key["key-a"].units, 12);
+        assert_eq!(report.by_key["key-b"].calls, 2);
+
+        let settings = ledger.settings();
+        assert!(settings.enforced);
+        assert_eq!(settings.costs["videos.list"], 2);
+        assert_eq!(settings.costs["search.list"], 100);
+
+        // Lifting the limit lets the key through again
+        ledger.set_daily_limit(None);
+        assert!(
+            ledger
+                .charge_at(QuotaEndpoint::VideosList, "key-a", now)
+                .is_ok()
+        );
+        assert!(!ledger.settings().enforced);
+    }
+
+    #[test]
+    fn test_quota_error_status_parses_403_and_429() {
+        assert_eq!("403".parse(), Ok(QuotaErrorStatus::Forbidden));
+        assert_eq!("429".parse(), Ok(QuotaErrorStatus::TooManyRequests));
+        assert!("500".parse::<QuotaErrorStatus>().is_err());
+
+        let ledger = QuotaLedger::default();
+        assert_eq!(ledger.settings().error_status, 403);
+        let ledger = ledger.with_error_status(QuotaErrorStatus::TooManyRequests);
+        assert_eq!(ledger.settings().error_status, 429);
+    }
+}
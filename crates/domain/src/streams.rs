@@ -0,0 +1,366 @@
+//! Registry of open live chat streams
+//!
+//! Shared by the gRPC service, which registers each stream for as long as it runs,
+//! and the control API, which reports the counts, close reasons and closes streams.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::DeliveryAudits;
+
+/// Number of closed streams kept in the timeline
+const CLOSE_TIMELINE_CAPACITY: usize = 100;
+
+/// Why a stream ended, determined by the exit path of the streaming task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum CloseReason {
+    /// The client went away
+    ClientDisconnect,
+    /// The configured stream timeout elapsed
+    Timeout,
+    /// The client's deadline (`grpc-timeout`) passed
+    DeadlineExceeded,
+    /// Closed through the control API
+    KilledViaControl,
+    /// The chat ended and the terminal response was sent
+    ChatEnded,
+    /// The server shut down and ended the stream cleanly
+    ServerShutdown,
+    /// The stream failed with a gRPC status, e.g. `internal` or `unauthenticated`
+    Error { status: String },
+}
+
+impl CloseReason {
+    /// Label used for the closed stream counters, e.g. `timeout` or `error{internal}`
+    pub fn label(&self) -> String {
+        match self {
+            CloseReason::ClientDisconnect => "client_disconnect".to_string(),
+            CloseReason::Timeout => "timeout".to_string(),
+            CloseReason::DeadlineExceeded => "deadline_exceeded".to_string(),
+            CloseReason::KilledViaControl => "killed_via_control".to_string(),
+            CloseReason::ChatEnded => "chat_ended".to_string(),
+            CloseReason::ServerShutdown => "server_shutdown".to_string(),
+            CloseReason::Error { status } => format!("error{{{status}}}"),
+        }
+    }
+}
+
+/// Entry of the closed stream timeline
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosedStream {
+    pub live_chat_id: String,
+    #[serde(flatten)]
+    pub reason: CloseReason,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: DateTime<Utc>,
+    /// Time from opening the stream to its first response, if one was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_response_millis: Option<u64>,
+    /// Whether the first response was an empty fallback sent because the history was not
+    /// ready within the first-response budget
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub slow_start: bool,
+}
+
+#[derive(Debug)]
+struct OpenStream {
+    live_chat_id: String,
+    killed: Arc<AtomicBool>,
+}
+
+/// Open streams per live chat, plus how closed streams ended
+#[derive(Debug, Default)]
+pub struct StreamRegistry {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<u64, OpenStream>>,
+    closed: Mutex<BTreeMap<String, u64>>,
+    timeline: Mutex<VecDeque<ClosedStream>>,
+    slow_starts: AtomicU64,
+    audits: DeliveryAudits,
+}
+
+impl StreamRegistry {
+    /// Register a stream for `live_chat_id`; it counts as active until the guard is dropped
+    pub fn open(self: &Arc<Self>, live_chat_id: &str) -> StreamGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let killed = Arc::new(AtomicBool::new(false));
+        self.open
+            .lock()
+            .expect("Failed to acquire lock on stream registry")
+            .insert(
+                id,
+                OpenStream {
+                    live_chat_id: live_chat_id.to_string(),
+                    killed: Arc::clone(&killed),
+                },
+            );
+        StreamGuard {
+            registry: Arc::clone(self),
+            id,
+            live_chat_id: live_chat_id.to_string(),
+            opened_at: clock::system_clock().now(),
+            killed,
+            reason: None,
+            first_response: None,
+            slow_start: false,
+        }
+    }
+
+    /// Number of active streams for a chat
+    pub fn active_streams(&self, live_chat_id: &str) -> usize {
+        self.open
+            .lock()
+            .expect("Failed to acquire lock on stream registry")
+            .values()
+            .filter(|stream| stream.live_chat_id == live_chat_id)
+            .count()
+    }
+
+    /// Active stream counts of every chat with at least one open stream
+    pub fn snapshot(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for stream in self
+            .open
+            .lock()
+            .expect("Failed to acquire lock on stream registry")
+            .values()
+        {
+            *counts.entry(stream.live_chat_id.clone()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Ask every open stream of a chat to close; returns how many were asked
+    ///
+    /// Streams notice the request on their next recheck and end with `killed_via_control`.
+    pub fn close_streams(&self, live_chat_id: &str) -> usize {
+        let open = self
+            .open
+            .lock()
+            .expect("Failed to acquire lock on stream registry");
+        let mut asked = 0;
+        for stream in open.values() {
+            if stream.live_chat_id == live_chat_id {
+                stream.killed.store(true, Ordering::Relaxed);
+                asked += 1;
+            }
+        }
+        asked
+    }
+
+    /// Ask every open stream to close, like [`Self::close_streams`] for all chats
+    pub fn close_all_streams(&self) -> usize {
+        let open = self
+            .open
+            .lock()
+            .expect("Failed to acquire lock on stream registry");
+        for stream in open.values() {
+            stream.killed.store(true, Ordering::Relaxed);
+        }
+        open.len()
+    }
+
+    /// Number of closed streams per close reason label
+    pub fn closed_counts(&self) -> BTreeMap<String, u64> {
+        self.closed
+            .lock()
+            .expect("Failed to acquire lock on stream registry")
+            .clone()
+    }
+
+    /// Most recently closed streams, oldest first
+    pub fn timeline(&self) -> Vec<ClosedStream> {
+        self.timeline
+            .lock()
+            .expect("Failed to acquire lock on stream registry")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Delivery audits, recorded by the streams of audited chats
+    pub fn audits(&self) -> &DeliveryAudits {
+        &self.audits
+    }
+
+    /// Number of streams, open or closed, whose first response was a slow-start fallback
+    pub fn slow_starts(&self) -> u64 {
+        self.slow_starts.load(Ordering::Relaxed)
+    }
+
+    fn record_close(&self, closed: ClosedStream) {
+        *self
+            .closed
+            .lock()
+            .expect("Failed to acquire lock on stream registry")
+            .entry(closed.reason.label())
+            .or_default() += 1;
+
+        let mut timeline = self
+            .timeline
+            .lock()
+            .expect("Failed to acquire lock on stream registry");
+        if timeline.len() == CLOSE_TIMELINE_CAPACITY {
+            timeline.pop_front();
+        }
+        timeline.push_back(closed);
+    }
+}
+
+/// Keeps a stream registered while alive
+///
+/// The close reason is set with [`StreamGuard::close`]; a guard dropped without one
+/// was cancelled along with its client and counts as `client_disconnect`.
+#[derive(Debug)]
+pub struct StreamGuard {
+    registry: Arc<StreamRegistry>,
+    id: u64,
+    live_chat_id: String,
+    opened_at: DateTime<Utc>,
+    killed: Arc<AtomicBool>,
+    reason: Option<CloseReason>,
+    first_response: Option<Duration>,
+    slow_start: bool,
+}
+
+impl StreamGuard {
+    /// Record that a response was sent `latency` after the stream opened
+    /// Only the first call counts; later responses do not change the first-response latency.
+    pub fn record_response(&mut self, latency: Duration) {
+        self.first_response.get_or_insert(latency);
+    }
+
+    /// Whether any response was sent on the stream
+    pub fn has_responded(&self) -> bool {
+        self.first_response.is_some()
+    }
+
+    /// Record that the first response was an empty fallback sent `latency` after the stream
+    /// opened, because the history was not ready in time
+    pub fn record_slow_start(&mut self, latency: Duration) {
+        if self.first_response.is_none() {
+            self.first_response = Some(latency);
+            self.slow_start = true;
+            self.registry.slow_starts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record that the stream sent the messages, when its chat is audited
+    pub fn record_delivery<'a>(&self, message_ids: impl IntoIterator<Item = &'a str>) {
+        self.registry
+            .audits
+            .record(&self.live_chat_id, self.id, message_ids);
+    }
+
+    /// Whether the control API asked this stream to close
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Relaxed)
+    }
+
+    /// Unregister the stream, recording why it ended
+    pub fn close(mut self, reason: CloseReason) {
+        self.reason = Some(reason);
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.registry
+            .open
+            .lock()
+            .expect("Failed to acquire lock on stream registry")
+            .remove(&self.id);
+        self.registry.record_close(ClosedStream {
+            live_chat_id: self.live_chat_id.clone(),
+            reason: self.reason.take().unwrap_or(CloseReason::ClientDisconnect),
+            opened_at: self.opened_at,
+            closed_at: clock::system_clock().now(),
+            first_response_millis: self
+                .first_response
+                .map(|latency| u64::try_from(latency.as_millis()).unwrap_or(u64::MAX)),
+            slow_start: self.slow_start,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guards_track_active_streams() {
+        let registry = Arc::new(StreamRegistry::default());
+        let first = registry.open("chat-1");
+        let second = registry.open("chat-1");
+        let other = registry.open("chat-2");
+        assert_eq!(registry.active_streams("chat-1"), 2);
+
+        drop(first);
+        assert_eq!(registry.active_streams("chat-1"), 1);
+
+        drop(second);
+        drop(other);
+        assert_eq!(registry.active_streams("chat-1"), 0);
+        assert!(registry.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_close_reasons_are_counted_and_kept_in_timeline() {
+        let registry = Arc::new(StreamRegistry::default());
+        registry.open("chat-1").close(CloseReason::Timeout);
+        registry.open("chat-1").close(CloseReason::Error {
+            status: "internal".to_string(),
+        });
+        drop(registry.open("chat-2"));
+
+        let killed = registry.open("chat-3");
+        assert_eq!(registry.close_streams("chat-3"), 1);
+        assert!(killed.is_killed());
+        killed.close(CloseReason::KilledViaControl);
+
+        let counts = registry.closed_counts();
+        assert_eq!(counts["timeout"], 1);
+        assert_eq!(counts["error{internal}"], 1);
+        assert_eq!(counts["client_disconnect"], 1);
+        assert_eq!(counts["killed_via_control"], 1);
+
+        let timeline = registry.timeline();
+        assert_eq!(timeline.len(), 4);
+        assert_eq!(timeline[0].live_chat_id, "chat-1");
+        assert_eq!(timeline[2].reason, CloseReason::ClientDisconnect);
+        assert!(registry.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_first_response_latency_is_recorded_once() {
+        let registry = Arc::new(StreamRegistry::default());
+        let mut fast = registry.open("chat-1");
+        assert!(!fast.has_responded());
+        fast.record_response(Duration::from_millis(5));
+        fast.record_response(Duration::from_millis(900));
+        fast.record_slow_start(Duration::from_millis(950));
+        assert!(fast.has_responded());
+        drop(fast);
+
+        let mut slow = registry.open("chat-1");
+        slow.record_slow_start(Duration::from_millis(250));
+        slow.record_response(Duration::from_millis(1200));
+        assert_eq!(registry.slow_starts(), 1);
+        drop(slow);
+        drop(registry.open("chat-1"));
+
+        let timeline = registry.timeline();
+        assert_eq!(timeline[0].first_response_millis, Some(5));
+        assert!(!timeline[0].slow_start);
+        assert_eq!(timeline[1].first_response_millis, Some(250));
+        assert!(timeline[1].slow_start);
+        assert_eq!(timeline[2].first_response_millis, None);
+        assert_eq!(registry.slow_starts(), 1);
+    }
+}
@@ -0,0 +1,24 @@
+use std::path::{Path, PathBuf};
+
+#[path = "../live_chat_service/build_support/proto_check.rs"]
+mod proto_check;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let root = proto_check::proto_root(Path::new(env!("CARGO_MANIFEST_DIR")))?;
+
+    let descriptor_path =
+        PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("e2e_descriptor.bin");
+
+    // The suite talks to the server as an ordinary client
+    tonic_prost_build::configure()
+        .build_server(false)
+        .build_client(true)
+        .file_descriptor_set_path(&descriptor_path)
+        .compile_protos(
+            &[root.join("stream_list.proto")],
+            std::slice::from_ref(&root),
+        )?;
+
+    proto_check::verify(&descriptor_path, &root)?;
+    Ok(())
+}
@@ -0,0 +1,301 @@
+//! Black-box harness for the server binary
+//!
+//! Builds the real `server` binary, starts it on ephemeral ports with an isolated
+//! environment and talks to it only over the network, like a client would.
+
+use nix::sys::signal::{Signal, kill};
+use nix::unistd::Pid;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+pub mod proto {
+    tonic::include_proto!("youtube.api.v3");
+}
+
+/// How long to wait for the server to report its ports and become ready
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for the server to exit after SIGTERM
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Host name the generated TLS certificate is issued for
+pub const TLS_HOST: &str = "localhost";
+
+/// Build the server binary once per test process and return its path
+fn server_binary() -> &'static Path {
+    static BINARY: OnceLock<PathBuf> = OnceLock::new();
+    BINARY.get_or_init(|| {
+        let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../Cargo.toml");
+        escargot::CargoBuild::new()
+            .manifest_path(manifest)
+            .package("server")
+            .bin("server")
+            .current_release()
+            .current_target()
+            .run()
+            .expect("Failed to build the server binary")
+            .path()
+            .to_path_buf()
+    })
+}
+
+/// Options for starting a server
+#[derive(Debug, Default, Clone)]
+pub struct ServerOptions {
+    /// Generate a self-signed certificate and serve gRPC and REST over TLS
+    pub tls: bool,
+    /// Additional environment variables passed to the server
+    pub env: Vec<(String, String)>,
+}
+
+impl ServerOptions {
+    pub fn with_tls(mut self) -> Self {
+        self.tls = true;
+        self
+    }
+
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Ports reported by the server through `PORT_FILE`
+#[derive(Debug, Clone, Copy)]
+pub struct Ports {
+    pub grpc: u16,
+    pub rest: u16,
+    pub health: u16,
+}
+
+/// A running server process, killed on drop if not shut down explicitly
+pub struct TestServer {
+    child: Child,
+    ports: Ports,
+    ca_pem: Option<String>,
+    dir: TempDir,
+}
+
+impl TestServer {
+    /// Start a server and wait until its readiness endpoint reports OK
+    pub async fn start(options: ServerOptions) -> Self {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let port_file = dir.path().join("ports.json");
+        let log = std::fs::File::create(dir.path().join("server.log"))
+            .expect("Failed to create server log");
+
+        let mut command = Command::new(server_binary());
+        command
+            .env_clear()
+            .env("GRPC_BIND_ADDRESS", "127.0.0.1:0")
+            .env("REST_BIND_ADDRESS", "127.0.0.1:0")
+            .env("HEALTH_BIND_ADDRESS", "127.0.0.1:0")
+            .env("PORT_FILE", &port_file)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone().expect("Failed to clone log handle"))
+            .stderr(log);
+
+        let ca_pem = if options.tls {
+            let certified = rcgen::generate_simple_self_signed(vec![TLS_HOST.to_string()])
+                .expect("Failed to generate TLS certificate");
+            let cert_pem = certified.cert.pem();
+            let cert_path = dir.path().join("server.crt");
+            let key_path = dir.path().join("server.key");
+            std::fs::write(&cert_path, &cert_pem).expect("Failed to write certificate");
+            std::fs::write(&key_path, certified.key_pair.serialize_pem())
+                .expect("Failed to write key");
+            command
+                .env("TLS_CERT_PATH", &cert_path)
+                .env("TLS_KEY_PATH", &key_path);
+            Some(cert_pem)
+        } else {
+            None
+        };
+
+        for (key, value) in &options.env {
+            command.env(key, value);
+        }
+
+        let child = command.spawn().expect("Failed to spawn server");
+        let mut server = Self {
+            child,
+            ports: Ports {
+                grpc: 0,
+                rest: 0,
+                health: 0,
+            },
+            ca_pem,
+            dir,
+        };
+        server.ports = server.wait_for_port_file(&port_file).await;
+        server.wait_until_ready().await;
+        server
+    }
+
+    async fn wait_for_port_file(&mut self, port_file: &Path) -> Ports {
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            if let Ok(contents) = std::fs::read_to_string(port_file) {
+                let ports: serde_json::Value =
+                    serde_json::from_str(&contents).expect("PORT_FILE should contain JSON");
+                let port = |name: &str| {
+                    ports[name]
+                        .as_u64()
+                        .and_then(|port| u16::try_from(port).ok())
+                        .unwrap_or_else(|| panic!("PORT_FILE is missing '{name}': {contents}"))
+                };
+                return Ports {
+                    grpc: port("grpc"),
+                    rest: port("rest"),
+                    health: port("health"),
+                };
+            }
+            if let Some(status) = self.child.try_wait().expect("Failed to poll server") {
+                panic!(
+                    "Server exited with {status} before binding:\n{}",
+                    self.log()
+                );
+            }
+            assert!(
+                Instant::now() < deadline,
+                "Server did not write PORT_FILE in time:\n{}",
+                self.log()
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn wait_until_ready(&mut self) {
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        let client = reqwest::Client::new();
+        loop {
+            if let Ok(response) = client.get(self.health_url("/readyz")).send().await
+                && response.status().is_success()
+            {
+                return;
+            }
+            if let Some(status) = self.child.try_wait().expect("Failed to poll server") {
+                panic!("Server exited with {status} before ready:\n{}", self.log());
+            }
+            assert!(
+                Instant::now() < deadline,
+                "Server did not become ready in time:\n{}",
+                self.log()
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    pub fn ports(&self) -> Ports {
+        self.ports
+    }
+
+    /// Everything the server has written to stdout and stderr so far
+    pub fn log(&self) -> String {
+        std::fs::read_to_string(self.dir.path().join("server.log")).unwrap_or_default()
+    }
+
+    fn scheme(&self) -> &'static str {
+        if self.ca_pem.is_some() {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    fn host(&self) -> &'static str {
+        if self.ca_pem.is_some() {
+            TLS_HOST
+        } else {
+            "127.0.0.1"
+        }
+    }
+
+    /// URL of a REST path, using https when TLS is enabled
+    pub fn rest_url(&self, path: &str) -> String {
+        format!(
+            "{}://{}:{}{path}",
+            self.scheme(),
+            self.host(),
+            self.ports.rest
+        )
+    }
+
+    /// URL of a health check path (always plain http)
+    pub fn health_url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{path}", self.ports.health)
+    }
+
+    /// HTTP client that trusts the server's generated certificate
+    pub fn http_client(&self) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(ca_pem) = &self.ca_pem {
+            let cert = reqwest::Certificate::from_pem(ca_pem.as_bytes())
+                .expect("Generated certificate should parse");
+            builder = builder
+                .add_root_certificate(cert)
+                .resolve(TLS_HOST, ([127, 0, 0, 1], self.ports.rest).into());
+        }
+        builder.build().expect("Failed to build HTTP client")
+    }
+
+    /// gRPC live chat client, using TLS when enabled
+    pub async fn live_chat_client(
+        &self,
+    ) -> proto::v3_data_live_chat_message_service_client::V3DataLiveChatMessageServiceClient<
+        tonic::transport::Channel,
+    > {
+        let mut endpoint = tonic::transport::Endpoint::from_shared(format!(
+            "{}://127.0.0.1:{}",
+            self.scheme(),
+            self.ports.grpc
+        ))
+        .expect("gRPC endpoint should be valid");
+        if let Some(ca_pem) = &self.ca_pem {
+            let tls = tonic::transport::ClientTlsConfig::new()
+                .ca_certificate(tonic::transport::Certificate::from_pem(ca_pem))
+                .domain_name(TLS_HOST);
+            endpoint = endpoint
+                .tls_config(tls)
+                .expect("Failed to configure gRPC TLS");
+        }
+        let channel = endpoint
+            .connect()
+            .await
+            .expect("Failed to connect to gRPC server");
+        proto::v3_data_live_chat_message_service_client::V3DataLiveChatMessageServiceClient::new(
+            channel,
+        )
+    }
+
+    /// Send SIGTERM and wait for the process to exit
+    pub async fn shutdown(mut self) -> ExitStatus {
+        let pid = Pid::from_raw(self.child.id() as i32);
+        kill(pid, Signal::SIGTERM).expect("Failed to send SIGTERM");
+
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        loop {
+            if let Some(status) = self.child.try_wait().expect("Failed to poll server") {
+                return status;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "Server did not exit after SIGTERM:\n{}",
+                self.log()
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -0,0 +1,1859 @@
+use e2e::proto::LiveChatMessageListRequest;
+use e2e::{ServerOptions, TestServer};
+use serde_json::{Value, json};
+use tokio_stream::StreamExt;
+
+async fn get_json(client: &reqwest::Client, url: &str) -> (reqwest::StatusCode, Value) {
+    let response = client.get(url).send().await.expect("GET should succeed");
+    let status = response.status();
+    (status, response.json().await.expect("Body should be JSON"))
+}
+
+async fn assert_clean_shutdown(server: TestServer) {
+    let status = server.shutdown().await;
+    assert_eq!(
+        status.code(),
+        Some(0),
+        "Server should exit cleanly on SIGTERM"
+    );
+}
+
+#[tokio::test]
+async fn test_health_and_readiness() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let client = server.http_client();
+
+    let (status, body) = get_json(&client, &server.health_url("/healthz")).await;
+    assert_eq!(status, reqwest::StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["components"].as_array().unwrap().len(), 4);
+
+    let (status, body) = get_json(&client, &server.health_url("/healthz?component=grpc")).await;
+    assert_eq!(status, reqwest::StatusCode::OK, "{body}");
+    assert_eq!(body["component"], "grpc");
+
+    let response = client
+        .get(server.health_url("/readyz"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "OK");
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_videos_list_and_control_round_trip() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let client = server.http_client();
+
+    let (status, body) = get_json(
+        &client,
+        &server.rest_url("/youtube/v3/videos?part=liveStreamingDetails&id=test-video-1"),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(
+        body["items"][0]["liveStreamingDetails"]["activeLiveChatId"],
+        "live-chat-id-1"
+    );
+
+    // Clients resolve the channel before its videos
+    let (status, body) = get_json(
+        &client,
+        &server.rest_url("/youtube/v3/channels?part=snippet,statistics&id=channel-1"),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(body["items"][0]["snippet"]["title"], "Mock Channel");
+    assert_eq!(body["items"][0]["statistics"]["videoCount"], "1");
+
+    let response = client
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "e2e-video",
+            "channelId": "e2e-channel",
+            "title": "E2E",
+            "description": "Created by the e2e suite",
+            "channelTitle": "E2E Channel",
+            "liveChatId": "e2e-chat",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+    let (status, body) = get_json(
+        &client,
+        &server.rest_url("/youtube/v3/videos?part=liveStreamingDetails&id=e2e-video"),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(
+        body["items"][0]["liveStreamingDetails"]["activeLiveChatId"],
+        "e2e-chat"
+    );
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_injected_faults_fail_videos_list_then_recover() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let client = server.http_client();
+    let videos_url = server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1");
+
+    let response = client
+        .post(server.rest_url("/control/faults"))
+        .json(&json!({
+            "target": "videos.list",
+            "httpStatus": 403,
+            "reason": "quotaExceeded",
+            "count": 3,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+    for _ in 0..3 {
+        let (status, body) = get_json(&client, &videos_url).await;
+        assert_eq!(status, reqwest::StatusCode::FORBIDDEN);
+        assert_eq!(body["error"]["errors"][0]["reason"], "quotaExceeded");
+    }
+    let (status, body) = get_json(&client, &videos_url).await;
+    assert_eq!(status, reqwest::StatusCode::OK, "{body}");
+    assert_eq!(body["items"][0]["id"], "test-video-1");
+
+    // Clearing removes faults that have not been used up
+    client
+        .post(server.rest_url("/control/faults"))
+        .json(&json!({"target": "videos.list", "probability": 1.0}))
+        .send()
+        .await
+        .unwrap();
+    let response = client
+        .delete(server.rest_url("/control/faults"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let (status, _) = get_json(&client, &videos_url).await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_health_probe_marker_cannot_be_sent_over_the_wire() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_file = dir.path().join("requests.jsonl");
+    let server = TestServer::start(
+        ServerOptions::default().with_env("REQUEST_LOG_FILE", log_file.to_str().unwrap()),
+    )
+    .await;
+    let client = server.http_client();
+    let response = client
+        .post(server.rest_url("/control/faults"))
+        .json(&json!({"target": "liveChat.streamList", "count": 1, "message": "Injected"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let request = || {
+        let mut request = tonic::Request::new(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        });
+        request
+            .metadata_mut()
+            .insert("x-mock-health-probe", "true".parse().unwrap());
+        request
+    };
+    let mut grpc = server.live_chat_client().await;
+
+    // A client claiming to be the health probe is faulted, charged and recorded all the same
+    let status = grpc
+        .stream_list(request())
+        .await
+        .expect_err("Fault should fail the call");
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+    assert_eq!(status.message(), "Injected");
+    let stream = grpc
+        .stream_list(request())
+        .await
+        .expect("Stream should open");
+    drop(stream);
+
+    let (_, report) = get_json(&client, &server.rest_url("/control/quota/report")).await;
+    assert_eq!(
+        report["byEndpoint"]["liveChatMessages.streamList"]["calls"], 1,
+        "{report}"
+    );
+    let recorded = std::fs::read_to_string(&log_file)
+        .unwrap()
+        .lines()
+        .filter(|line| line.contains("x-mock-health-probe"))
+        .count();
+    assert_eq!(recorded, 2);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_upcoming_broadcast_chat_opens_when_started() {
+    let server =
+        TestServer::start(ServerOptions::default().with_env("SCHEDULED_CHAT_NOT_STARTED", "true"))
+            .await;
+    let client = server.http_client();
+    let response = client
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "upcoming-video",
+            "channelId": "e2e-channel",
+            "title": "Pre-show",
+            "description": "Starts later",
+            "channelTitle": "E2E Channel",
+            "liveChatId": "upcoming-chat",
+            "scheduledStartTime": "2099-01-01T00:00:00Z",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let videos_url =
+        server.rest_url("/youtube/v3/videos?part=snippet,liveStreamingDetails&id=upcoming-video");
+    let request = || LiveChatMessageListRequest {
+        live_chat_id: Some("upcoming-chat".to_string()),
+        ..Default::default()
+    };
+
+    let (_, body) = get_json(&client, &videos_url).await;
+    assert_eq!(
+        body["items"][0]["snippet"]["liveBroadcastContent"],
+        "upcoming"
+    );
+    assert!(body["items"][0]["liveStreamingDetails"]["scheduledStartTime"].is_string());
+    assert!(
+        body["items"][0]["liveStreamingDetails"]
+            .get("activeLiveChatId")
+            .is_none()
+    );
+    let status = server
+        .live_chat_client()
+        .await
+        .stream_list(request())
+        .await
+        .expect_err("Stream should be rejected before the start");
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert_eq!(status.message(), "liveChatNotStarted");
+
+    let response = client
+        .post(server.rest_url("/control/videos/upcoming-video/start"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let (_, body) = get_json(&client, &videos_url).await;
+    assert_eq!(body["items"][0]["snippet"]["liveBroadcastContent"], "live");
+    assert_eq!(
+        body["items"][0]["liveStreamingDetails"]["activeLiveChatId"],
+        "upcoming-chat"
+    );
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(request())
+        .await
+        .expect("Stream should open after the start")
+        .into_inner();
+    let first = stream
+        .next()
+        .await
+        .expect("Response")
+        .expect("Stream response");
+    assert!(first.items.is_empty());
+    drop(stream);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_multi_chat_stream_rejects_chats_not_started() {
+    let server =
+        TestServer::start(ServerOptions::default().with_env("SCHEDULED_CHAT_NOT_STARTED", "true"))
+            .await;
+    let response = server
+        .http_client()
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "upcoming-video",
+            "channelId": "e2e-channel",
+            "title": "Pre-show",
+            "description": "Starts later",
+            "channelTitle": "E2E Channel",
+            "liveChatId": "upcoming-chat",
+            "scheduledStartTime": "2099-01-01T00:00:00Z",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+    // One chat that has not started rejects the whole stream
+    let mut request = tonic::Request::new(LiveChatMessageListRequest {
+        live_chat_id: Some("test-chat-id,upcoming-chat".to_string()),
+        ..Default::default()
+    });
+    request
+        .metadata_mut()
+        .insert("x-mock-multi-chat", "true".parse().unwrap());
+    let status = server
+        .live_chat_client()
+        .await
+        .stream_list(request)
+        .await
+        .expect_err("Stream should be rejected before the start");
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert_eq!(status.message(), "liveChatNotStarted");
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_oauth_flow_with_auth_required() {
+    let server = TestServer::start(ServerOptions::default().with_env("REQUIRE_AUTH", "true")).await;
+    let client = server.http_client();
+    let videos_url = server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1");
+
+    let response = client.get(&videos_url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let token_url = server.rest_url("/oauth2/token");
+    let tokens: Value = client
+        .post(&token_url)
+        .form(&[("grant_type", "authorization_code"), ("code", "e2e-code")])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let access_token = tokens["access_token"].as_str().expect("access_token");
+    let refresh_token = tokens["refresh_token"].as_str().expect("refresh_token");
+
+    let response = client
+        .get(&videos_url)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let refreshed: Value = client
+        .post(&token_url)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let refreshed_token = refreshed["access_token"].as_str().expect("access_token");
+    assert_ne!(refreshed_token, access_token);
+
+    let response = client
+        .get(&videos_url)
+        .bearer_auth(refreshed_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_oauth_token_exchange_at_root_over_tls() {
+    let server = TestServer::start(
+        ServerOptions::default()
+            .with_tls()
+            .with_env("OAUTH_PATH_PREFIX", "/"),
+    )
+    .await;
+    let client = server.http_client();
+
+    let (status, authorized) = get_json(&client, &server.rest_url("/authorize")).await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    let code = authorized["code"].as_str().expect("code");
+
+    let token_url = server.rest_url("/token");
+    let response = client
+        .post(&token_url)
+        .form(&[("grant_type", "authorization_code"), ("code", code)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let tokens: Value = response.json().await.unwrap();
+    let access_token = tokens["access_token"].as_str().expect("access_token");
+    assert!(access_token.starts_with("ya29.mock_"));
+
+    // Issued codes can only be redeemed once
+    let response = client
+        .post(&token_url)
+        .form(&[("grant_type", "authorization_code"), ("code", code)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let refreshed: Value = client
+        .post(&token_url)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", tokens["refresh_token"].as_str().unwrap()),
+        ])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let refreshed_token = refreshed["access_token"].as_str().expect("access_token");
+    assert!(refreshed_token.starts_with("ya29.mock_"));
+    assert_ne!(refreshed_token, access_token);
+
+    // The default prefix is no longer mounted
+    let response = client
+        .post(server.rest_url("/oauth2/token"))
+        .form(&[("grant_type", "refresh_token"), ("refresh_token", "x")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_concurrency_limit_queues_excess_requests() {
+    let server = TestServer::start(
+        ServerOptions::default()
+            .with_env("LISTEN_BACKLOG", "4")
+            .with_env("MAX_CONCURRENT_REQUESTS", "1"),
+    )
+    .await;
+    let client = server.http_client();
+
+    // Requests beyond the limit wait for a slot rather than being refused
+    let url = server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1");
+    let mut requests = tokio::task::JoinSet::new();
+    for _ in 0..16 {
+        requests.spawn(client.get(&url).send());
+    }
+    while let Some(response) = requests.join_next().await {
+        assert_eq!(response.unwrap().unwrap().status(), reqwest::StatusCode::OK);
+    }
+
+    // Established streams do not hold a slot
+    let mut grpc = server.live_chat_client().await;
+    let mut streams = Vec::new();
+    for _ in 0..2 {
+        let stream = grpc
+            .stream_list(LiveChatMessageListRequest {
+                live_chat_id: Some("live-chat-id-1".to_string()),
+                ..Default::default()
+            })
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        streams.push(stream);
+    }
+    for stream in &mut streams {
+        stream.next().await.unwrap().expect("First response");
+    }
+    drop(streams);
+    drop(grpc);
+
+    assert!(
+        server
+            .log()
+            .contains("Concurrent request limit: 1 per listener (backlog 4)")
+    );
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_gateway_parity_error_pages() {
+    let server =
+        TestServer::start(ServerOptions::default().with_env("GATEWAY_PARITY", "true")).await;
+    let client = server.http_client();
+    let content_type = |response: &reqwest::Response| {
+        response.headers()[reqwest::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string()
+    };
+
+    let response = client
+        .get(server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(content_type(&response), "application/json; charset=UTF-8");
+
+    let response = client
+        .get(server.rest_url("/youtube/v3/unknown"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    assert_eq!(content_type(&response), "text/html; charset=UTF-8");
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<code>/youtube/v3/unknown</code>"), "{body}");
+
+    let response = client
+        .get(server.rest_url("/youtube/v3/videos?part=%zz&id=test-video-1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(content_type(&response), "text/html; charset=UTF-8");
+
+    let response = client
+        .post(server.rest_url("/oauth2/token"))
+        .header(reqwest::header::CONTENT_TYPE, "text/plain")
+        .body("grant_type=authorization_code")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.status(),
+        reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+
+    // Control endpoints keep their own behavior
+    let response = client
+        .get(server.rest_url("/control/state"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_videos_list_auth_errors_with_auth_required() {
+    let server = TestServer::start(ServerOptions::default().with_env("REQUIRE_AUTH", "true")).await;
+    let client = server.http_client();
+    let videos_url = server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1");
+    let reason = |body: &Value| body["error"]["errors"][0]["reason"].clone();
+
+    // No credential at all mirrors Google's "Login Required"
+    let response = client.get(&videos_url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], 401);
+    assert_eq!(reason(&body), "required");
+    assert_eq!(body["error"]["errors"][0]["message"], "Login Required");
+
+    // Expired tokens and non-Bearer credentials are auth errors
+    let tokens: Value = client
+        .post(server.rest_url("/oauth2/token"))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", "e2e-code"),
+            ("expires_in", "-60"),
+        ])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let expired = tokens["access_token"].as_str().unwrap();
+    for request in [
+        client.get(&videos_url).bearer_auth(expired),
+        client.get(&videos_url).basic_auth("user", Some("password")),
+    ] {
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(reason(&body), "authError");
+    }
+
+    // API keys are accepted as a query parameter or header
+    let response = client
+        .get(format!("{videos_url}&key=e2e-key"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let response = client
+        .get(&videos_url)
+        .header("x-goog-api-key", "e2e-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_stream_list_enforces_required_scope() {
+    const READONLY: &str = "https://www.googleapis.com/auth/youtube.readonly";
+    let server = TestServer::start(
+        ServerOptions::default()
+            .with_env("REQUIRE_AUTH", "true")
+            .with_env("REQUIRED_SCOPE", READONLY),
+    )
+    .await;
+    let client = server.http_client();
+    let issue = |scope: &'static str| {
+        let request = client.post(server.rest_url("/oauth2/token")).form(&[
+            ("grant_type", "authorization_code"),
+            ("code", "e2e-code"),
+            ("scope", scope),
+        ]);
+        async move {
+            let tokens: Value = request.send().await.unwrap().json().await.unwrap();
+            tokens["access_token"].as_str().unwrap().to_string()
+        }
+    };
+    let request = |token: &str| {
+        let mut request = tonic::Request::new(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        });
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {token}").parse().unwrap());
+        request
+    };
+    let mut grpc = server.live_chat_client().await;
+
+    // Scopes that merely contain the required one as a prefix do not count
+    for scope in [
+        "mock.scope.read",
+        "https://www.googleapis.com/auth/youtube.readonly.extra",
+    ] {
+        let status = grpc
+            .stream_list(request(&issue(scope).await))
+            .await
+            .expect_err("Token without the scope should be rejected");
+        assert_eq!(status.code(), tonic::Code::PermissionDenied, "{scope}");
+        assert_eq!(status.message(), "insufficient scope");
+    }
+
+    let token = issue("openid https://www.googleapis.com/auth/youtube.readonly").await;
+    let mut stream = grpc
+        .stream_list(request(&token))
+        .await
+        .expect("Token with the scope should open the stream")
+        .into_inner();
+    assert!(stream.message().await.unwrap().is_some());
+    drop(stream);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_stream_list_enforces_token_expiry() {
+    let server = TestServer::start(ServerOptions::default().with_env("REQUIRE_AUTH", "true")).await;
+    let client = server.http_client();
+    let issue = |expires_in: &'static str| {
+        let request = client.post(server.rest_url("/oauth2/token")).form(&[
+            ("grant_type", "authorization_code"),
+            ("code", "e2e-code"),
+            ("expires_in", expires_in),
+        ]);
+        async move {
+            let tokens: Value = request.send().await.unwrap().json().await.unwrap();
+            tokens["access_token"].as_str().unwrap().to_string()
+        }
+    };
+    let request = |token: &str, api_key: bool| {
+        let mut request = tonic::Request::new(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        });
+        let metadata = request.metadata_mut();
+        metadata.insert("authorization", format!("Bearer {token}").parse().unwrap());
+        if api_key {
+            metadata.insert("x-goog-api-key", "e2e-key".parse().unwrap());
+        }
+        request
+    };
+
+    // Already expired tokens are rejected up front, unless an API key is sent too
+    let expired = issue("-1").await;
+    let mut grpc = server.live_chat_client().await;
+    let status = grpc
+        .stream_list(request(&expired, false))
+        .await
+        .expect_err("Expired token should be rejected");
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    assert!(
+        status
+            .message()
+            .starts_with("Request had invalid authentication credentials"),
+        "{}",
+        status.message()
+    );
+    grpc.stream_list(request(&expired, true))
+        .await
+        .expect("API key should bypass token expiry");
+
+    // A token expiring mid-session ends the stream
+    let short_lived = issue("2").await;
+    let mut stream = grpc
+        .stream_list(request(&short_lived, false))
+        .await
+        .expect("Valid token should open the stream")
+        .into_inner();
+    stream
+        .next()
+        .await
+        .expect("Stream should yield the backlog")
+        .expect("Stream response");
+    let status = tokio::time::timeout(std::time::Duration::from_secs(10), stream.next())
+        .await
+        .expect("Stream should end once the token expires")
+        .expect("Stream should yield the error")
+        .expect_err("Expired token should end the stream");
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    assert!(
+        status
+            .message()
+            .starts_with("Request had invalid authentication credentials"),
+        "{}",
+        status.message()
+    );
+
+    drop(grpc);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_multi_chat_stream_enforces_token_expiry() {
+    let server = TestServer::start(ServerOptions::default().with_env("REQUIRE_AUTH", "true")).await;
+    let tokens: Value = server
+        .http_client()
+        .post(server.rest_url("/oauth2/token"))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", "e2e-code"),
+            ("expires_in", "2"),
+        ])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token = tokens["access_token"].as_str().unwrap();
+    let mut request = tonic::Request::new(LiveChatMessageListRequest {
+        live_chat_id: Some("test-chat-id,live-chat-id-1".to_string()),
+        ..Default::default()
+    });
+    let metadata = request.metadata_mut();
+    metadata.insert("authorization", format!("Bearer {token}").parse().unwrap());
+    metadata.insert("x-mock-multi-chat", "true".parse().unwrap());
+
+    // A token expiring mid-session ends the stream over both chats
+    let mut grpc = server.live_chat_client().await;
+    let mut stream = grpc
+        .stream_list(request)
+        .await
+        .expect("Valid token should open the stream")
+        .into_inner();
+    stream
+        .next()
+        .await
+        .expect("Stream should yield the backlog")
+        .expect("Stream response");
+    let status = tokio::time::timeout(std::time::Duration::from_secs(10), stream.next())
+        .await
+        .expect("Stream should end once the token expires")
+        .expect("Stream should yield the error")
+        .expect_err("Expired token should end the stream");
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    drop(grpc);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_stream_list_strict_token_validation() {
+    let request = |token: &str| {
+        let mut request = tonic::Request::new(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        });
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {token}").parse().unwrap());
+        request
+    };
+
+    // Tokens the server never issued are accepted by default
+    let server = TestServer::start(ServerOptions::default().with_env("REQUIRE_AUTH", "true")).await;
+    let mut grpc = server.live_chat_client().await;
+    grpc.stream_list(request("hand-written-token"))
+        .await
+        .expect("Unknown tokens should be accepted outside strict mode");
+    drop(grpc);
+    assert_clean_shutdown(server).await;
+
+    let server = TestServer::start(
+        ServerOptions::default()
+            .with_env("REQUIRE_AUTH", "true")
+            .with_env("STRICT_TOKEN_VALIDATION", "true"),
+    )
+    .await;
+    let mut grpc = server.live_chat_client().await;
+    let status = grpc
+        .stream_list(request("hand-written-token"))
+        .await
+        .expect_err("Unknown tokens should be rejected in strict mode");
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    let tokens: Value = server
+        .http_client()
+        .post(server.rest_url("/oauth2/token"))
+        .form(&[("grant_type", "authorization_code"), ("code", "e2e-code")])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    grpc.stream_list(request(tokens["access_token"].as_str().unwrap()))
+        .await
+        .expect("Issued tokens should be accepted in strict mode");
+
+    drop(grpc);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_stream_list_resumes_after_reconnect() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let request = |page_token: Option<String>, max_results| LiveChatMessageListRequest {
+        live_chat_id: Some("test-chat-id".to_string()),
+        page_token,
+        max_results,
+        ..Default::default()
+    };
+
+    // The whole backlog fits in the first response by default
+    let mut client = server.live_chat_client().await;
+    let backlog = client
+        .stream_list(request(None, None))
+        .await
+        .expect("Stream should open")
+        .into_inner()
+        .next()
+        .await
+        .expect("Stream should yield a response")
+        .expect("Stream response");
+    assert!(backlog.items.len() > 2);
+    let expected_id = backlog.items[2].id.clone();
+
+    // Read a batch of two messages, then drop the stream
+    let first = client
+        .stream_list(request(None, Some(2)))
+        .await
+        .expect("Stream should open")
+        .into_inner()
+        .next()
+        .await
+        .expect("Stream should yield a response")
+        .expect("Stream response");
+    assert_eq!(first.items.len(), 2);
+    let resume_token = first.next_page_token.clone();
+
+    // Reconnect on a fresh channel with the token of that batch
+    let mut client = server.live_chat_client().await;
+    let resumed = client
+        .stream_list(request(resume_token, Some(2)))
+        .await
+        .expect("Stream should reopen")
+        .into_inner()
+        .next()
+        .await
+        .expect("Resumed stream should yield a response")
+        .expect("Stream response");
+    assert_eq!(resumed.items[0].id, expected_id);
+
+    drop(client);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_stream_list_resumes_messages_added_after_timeout() {
+    let server =
+        TestServer::start(ServerOptions::default().with_env("CHAT_STREAM_TIMEOUT", "1")).await;
+    // Every response of a stream opened with `page_token`, until the stream times out
+    async fn read_until_timeout(
+        server: &TestServer,
+        page_token: Option<String>,
+    ) -> Vec<e2e::proto::LiveChatMessageListResponse> {
+        server
+            .live_chat_client()
+            .await
+            .stream_list(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                page_token,
+                ..Default::default()
+            })
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .map(|response| response.expect("Stream response"))
+            .collect()
+            .await
+    }
+
+    // Read the whole backlog until the stream times out
+    let responses = read_until_timeout(&server, None).await;
+    let backlog: Vec<_> = responses.iter().flat_map(|r| r.items.clone()).collect();
+    assert_eq!(backlog.len(), 5);
+    let token = responses.last().unwrap().next_page_token.clone();
+    assert_eq!(token.as_deref(), Some("NQ=="));
+
+    // With nothing new, the empty response hands back the token it resumed from
+    let responses = read_until_timeout(&server, token.clone()).await;
+    assert_eq!(responses.len(), 1);
+    assert!(responses[0].items.is_empty());
+    assert_eq!(responses[0].next_page_token, token);
+
+    let http = server.http_client();
+    for id in ["late-msg-1", "late-msg-2"] {
+        let response = http
+            .post(server.rest_url("/control/chat_messages"))
+            .json(&json!({
+                "id": id,
+                "liveChatId": "test-chat-id",
+                "authorChannelId": "late-channel",
+                "authorDisplayName": "Late Author",
+                "messageText": format!("Message {id}"),
+                "publishedAt": "2024-01-01T00:00:00Z",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    }
+
+    // Reconnecting with the stored token delivers both new messages in order
+    let responses = read_until_timeout(&server, token).await;
+    let ids: Vec<_> = responses
+        .iter()
+        .flat_map(|r| r.items.iter().filter_map(|item| item.id.clone()))
+        .collect();
+    assert_eq!(ids, ["late-msg-1", "late-msg-2"]);
+    assert_eq!(
+        responses.last().unwrap().next_page_token.as_deref(),
+        Some("Nw==")
+    );
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_client_deadline_ends_the_server_side_stream() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let mut request = tonic::Request::new(LiveChatMessageListRequest {
+        live_chat_id: Some("live-chat-id-1".to_string()),
+        ..Default::default()
+    });
+    request.set_timeout(std::time::Duration::from_millis(500));
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(request)
+        .await
+        .expect("Stream should open")
+        .into_inner();
+    stream
+        .next()
+        .await
+        .expect("Response")
+        .expect("Stream response");
+
+    // The stream is held open, but the server ends it once the deadline passes
+    let client = server.http_client();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let (_, stats) = get_json(&client, &server.rest_url("/control/stats")).await;
+        if stats["closedStreams"]["deadline_exceeded"] == 1 {
+            break;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "Stream was not closed by its deadline: {stats}"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    drop(stream);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_shutdown_ends_open_streams_and_exits_promptly() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("live-chat-id-1".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Stream should open")
+        .into_inner();
+    stream
+        .next()
+        .await
+        .expect("Response")
+        .expect("Stream response");
+
+    // Read the stream's end while the server shuts down
+    let end = tokio::spawn(async move { stream.next().await });
+    let started = std::time::Instant::now();
+    assert_clean_shutdown(server).await;
+    assert!(
+        started.elapsed() < std::time::Duration::from_secs(3),
+        "Server took {:?} to exit",
+        started.elapsed()
+    );
+
+    // The stream ends with trailers, not a reset connection
+    let end = tokio::time::timeout(std::time::Duration::from_secs(5), end)
+        .await
+        .expect("Stream should end")
+        .unwrap();
+    assert!(end.is_none(), "Stream should end cleanly, got {end:?}");
+}
+
+#[tokio::test]
+async fn test_rest_write_reaches_open_stream_and_rest_list() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let http = server.http_client();
+
+    // Streams only open for chats that exist, here one without messages yet
+    let response = http
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "consistency-video",
+            "channelId": "consistency-channel",
+            "title": "Consistency",
+            "description": "Owns an empty chat",
+            "channelTitle": "Consistency Channel",
+            "liveChatId": "consistency-chat",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("consistency-chat".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Stream should open")
+        .into_inner();
+    let first = stream
+        .next()
+        .await
+        .expect("Response")
+        .expect("Stream response");
+    assert!(first.items.is_empty());
+
+    // A write over REST reaches the stream that is already open
+    let response = http
+        .post(server.rest_url("/control/chat_messages"))
+        .json(&json!({
+            "id": "consistency-msg",
+            "liveChatId": "consistency-chat",
+            "authorChannelId": "consistency-channel",
+            "authorDisplayName": "Consistent Author",
+            "messageText": "Seen everywhere",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let delivered = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+        .await
+        .expect("The open stream should deliver the write")
+        .expect("Response")
+        .expect("Stream response");
+    assert_eq!(delivered.items.len(), 1);
+    let item = &delivered.items[0];
+    assert_eq!(item.id.as_deref(), Some("consistency-msg"));
+
+    // The REST list serves the same message at the same position
+    let (status, body) = get_json(
+        &http,
+        &server.rest_url("/youtube/v3/liveChat/messages?liveChatId=consistency-chat&part=snippet"),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+    assert_eq!(body["items"][0]["id"], "consistency-msg");
+    assert_eq!(body["items"][0]["etag"], item.etag.as_deref().unwrap());
+    assert_eq!(
+        body["items"][0]["snippet"]["displayMessage"],
+        item.snippet
+            .as_ref()
+            .unwrap()
+            .display_message
+            .as_deref()
+            .unwrap()
+    );
+    assert_eq!(
+        body["nextPageToken"],
+        delivered.next_page_token.as_deref().unwrap()
+    );
+    drop(stream);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_inserted_message_reaches_open_stream() {
+    let server =
+        TestServer::start(ServerOptions::default().with_env("CHAT_MAX_TEXT_LEN", "200")).await;
+    let http = server.http_client();
+
+    let response = http
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "insert-video",
+            "channelId": "insert-channel",
+            "title": "Insert",
+            "description": "Chat written by the client",
+            "channelTitle": "Insert Channel",
+            "liveChatId": "insert-chat",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("insert-chat".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Stream should open")
+        .into_inner();
+    let first = stream
+        .next()
+        .await
+        .expect("Response")
+        .expect("Stream response");
+    assert!(first.items.is_empty());
+
+    let insert = |text: String| {
+        http.post(server.rest_url("/youtube/v3/liveChat/messages?part=snippet"))
+            .json(&json!({
+                "snippet": {
+                    "liveChatId": "insert-chat",
+                    "type": "textMessageEvent",
+                    "textMessageDetails": {"messageText": text},
+                }
+            }))
+            .send()
+    };
+    let response = insert("x".repeat(201)).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["errors"][0]["reason"], "invalidValue");
+
+    let response = insert("Posted by the client".to_string()).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let created: Value = response.json().await.unwrap();
+
+    let delivered = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+        .await
+        .expect("The open stream should deliver the inserted message")
+        .expect("Response")
+        .expect("Stream response");
+    assert_eq!(delivered.items.len(), 1);
+    let item = &delivered.items[0];
+    assert_eq!(item.id.as_deref(), created["id"].as_str());
+    assert_eq!(
+        item.snippet.as_ref().unwrap().display_message.as_deref(),
+        Some("Posted by the client")
+    );
+    drop(stream);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_author_details_match_across_transports() {
+    let server = TestServer::start(ServerOptions::default()).await;
+    let http = server.http_client();
+
+    let response = http
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "authors-video",
+            "channelId": "authors-channel",
+            "title": "Authors",
+            "description": "Chat with badged authors",
+            "channelTitle": "Authors Channel",
+            "liveChatId": "authors-chat",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    // One author with every badge and an avatar, one with the placeholder avatar
+    for message in [
+        json!({
+            "id": "badged-msg",
+            "liveChatId": "authors-chat",
+            "authorChannelId": "badged-author",
+            "authorDisplayName": "Badged Author",
+            "messageText": "With badges",
+            "isVerified": true,
+            "isChatOwner": true,
+            "isChatModerator": true,
+            "isChatSponsor": true,
+            "profileImageUrl": "https://example.com/badged.png",
+        }),
+        json!({
+            "id": "plain-msg",
+            "liveChatId": "authors-chat",
+            "authorChannelId": "plain-author",
+            "authorDisplayName": "Plain Author",
+            "messageText": "Without badges",
+        }),
+    ] {
+        let response = http
+            .post(server.rest_url("/control/chat_messages"))
+            .json(&message)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    }
+
+    let (status, body) = get_json(
+        &http,
+        &server.rest_url(
+            "/youtube/v3/liveChat/messages?liveChatId=authors-chat&part=snippet,authorDetails",
+        ),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    let rest = body["items"].as_array().unwrap();
+
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("authors-chat".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Stream should open")
+        .into_inner();
+    let grpc = stream
+        .next()
+        .await
+        .expect("Response")
+        .expect("Stream response")
+        .items;
+    drop(stream);
+
+    assert_eq!(rest.len(), 2);
+    assert_eq!(grpc.len(), 2);
+    for (rest, grpc) in rest.iter().zip(&grpc) {
+        assert_eq!(rest["id"], grpc.id.as_deref().unwrap());
+        let (rest, grpc) = (
+            &rest["authorDetails"],
+            grpc.author_details.as_ref().unwrap(),
+        );
+        assert_eq!(rest["channelId"], grpc.channel_id.as_deref().unwrap());
+        assert_eq!(rest["channelUrl"], grpc.channel_url.as_deref().unwrap());
+        assert_eq!(rest["displayName"], grpc.display_name.as_deref().unwrap());
+        assert_eq!(
+            rest["profileImageUrl"],
+            grpc.profile_image_url.as_deref().unwrap()
+        );
+        assert_eq!(rest["isVerified"], grpc.is_verified.unwrap());
+        assert_eq!(rest["isChatOwner"], grpc.is_chat_owner.unwrap());
+        assert_eq!(rest["isChatSponsor"], grpc.is_chat_sponsor.unwrap());
+        assert_eq!(rest["isChatModerator"], grpc.is_chat_moderator.unwrap());
+    }
+    assert_eq!(
+        rest[0]["authorDetails"]["profileImageUrl"],
+        "https://example.com/badged.png"
+    );
+    assert_eq!(rest[0]["authorDetails"]["isChatOwner"], true);
+    assert_ne!(
+        rest[1]["authorDetails"]["profileImageUrl"],
+        rest[0]["authorDetails"]["profileImageUrl"]
+    );
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_tls_serves_rest_and_grpc() {
+    let server = TestServer::start(ServerOptions::default().with_tls()).await;
+    let client = server.http_client();
+
+    let (status, body) = get_json(
+        &client,
+        &server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1"),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(body["items"][0]["id"], "test-video-1");
+
+    let mut grpc = server.live_chat_client().await;
+    let response = grpc
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("TLS stream should open")
+        .into_inner()
+        .next()
+        .await
+        .expect("TLS stream should yield a response")
+        .expect("Stream response");
+    assert!(!response.items.is_empty());
+
+    drop(grpc);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_seed_data_is_served_over_rest_and_grpc() {
+    let seed_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/seed.json");
+    let server =
+        TestServer::start(ServerOptions::default().with_env("SEED_DATA_PATH", seed_path)).await;
+    let client = server.http_client();
+
+    let (status, body) = get_json(
+        &client,
+        &server
+            .rest_url("/youtube/v3/videos?part=liveStreamingDetails&id=seed-video-1,test-video-1"),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    // Only the seeded video exists; the dummy data is not loaded
+    assert_eq!(body["items"].as_array().map(Vec::len), Some(1));
+    assert_eq!(
+        body["items"][0]["liveStreamingDetails"]["activeLiveChatId"],
+        "seed-chat-1"
+    );
+
+    let mut grpc = server.live_chat_client().await;
+    let response = grpc
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("seed-chat-1".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Seeded chat stream should open")
+        .into_inner()
+        .next()
+        .await
+        .expect("Seeded chat stream should yield a response")
+        .expect("Stream response");
+    let ids: Vec<_> = response
+        .items
+        .iter()
+        .filter_map(|item| item.id.as_deref())
+        .collect();
+    assert_eq!(ids, ["seed-msg-1", "seed-msg-2", "seed-msg-3"]);
+
+    drop(grpc);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_reset_restores_seed_data_and_ends_open_streams() {
+    let seed_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/seed.json");
+    let server =
+        TestServer::start(ServerOptions::default().with_env("SEED_DATA_PATH", seed_path)).await;
+    let client = server.http_client();
+
+    let response = client
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "reset-video",
+            "channelId": "reset-channel",
+            "title": "Reset",
+            "description": "Gone after the reset",
+            "channelTitle": "Reset Channel",
+            "liveChatId": "reset-chat",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("seed-chat-1".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Seeded chat stream should open")
+        .into_inner();
+    let backlog = stream.next().await.expect("Response").expect("Backlog");
+    assert_eq!(backlog.items.len(), 3);
+
+    let response = client
+        .post(server.rest_url("/control/reset"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["closedStreams"], 1);
+
+    // The open stream ends cleanly instead of paging on into the restored chat
+    let timeout = std::time::Duration::from_secs(5);
+    while let Some(next) = tokio::time::timeout(timeout, stream.next())
+        .await
+        .expect("Stream should close after the reset")
+    {
+        assert!(next.is_ok(), "Stream should end cleanly, got {next:?}");
+    }
+
+    let videos_url = server.rest_url("/youtube/v3/videos?part=snippet&id=seed-video-1,reset-video");
+    let (status, body) = get_json(&client, &videos_url).await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    let ids: Vec<_> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| item["id"].as_str())
+        .collect();
+    assert_eq!(ids, ["seed-video-1"]);
+
+    let response = client
+        .post(server.rest_url("/control/reset?empty=true"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let (_, body) = get_json(&client, &videos_url).await;
+    assert_eq!(body["items"].as_array().map(Vec::len), Some(0));
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_goaway_cycles_connections_without_losing_messages() {
+    let server = TestServer::start(
+        ServerOptions::default().with_env("GRPC_MAX_STREAMS_PER_CONNECTION", "2"),
+    )
+    .await;
+    let request = |page_token: Option<String>| LiveChatMessageListRequest {
+        live_chat_id: Some("test-chat-id".to_string()),
+        page_token,
+        max_results: Some(1),
+        ..Default::default()
+    };
+
+    // One channel for every call, like a long-lived client. A call racing the GOAWAY fails
+    // without reaching the server; the channel reconnects and the call is retried
+    let mut client = server.live_chat_client().await;
+    let mut ids = Vec::new();
+    let mut page_token = None;
+    for _ in 0..5 {
+        let opened = match client.stream_list(request(page_token.clone())).await {
+            Ok(opened) => opened,
+            Err(status) => {
+                assert!(
+                    matches!(
+                        status.code(),
+                        tonic::Code::Cancelled | tonic::Code::Unavailable
+                    ),
+                    "{status:?}"
+                );
+                client
+                    .stream_list(request(page_token.clone()))
+                    .await
+                    .expect("Stream should open on a fresh connection after GOAWAY")
+            }
+        };
+        let response = opened
+            .into_inner()
+            .next()
+            .await
+            .expect("Stream should yield a response")
+            .expect("Stream response");
+        ids.extend(response.items.iter().filter_map(|item| item.id.clone()));
+        page_token = response.next_page_token;
+    }
+    let expected: Vec<_> = (0..5).map(|i| format!("test-msg-id-{i}")).collect();
+    assert_eq!(ids, expected);
+    // The GOAWAY is logged by the connection task, possibly after the response arrived
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while server.log().matches("Sending GOAWAY").count() < 2 {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "Expected two GOAWAYs:\n{}",
+            server.log()
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    drop(client);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_goaway_grace_closes_open_streams() {
+    let server = TestServer::start(
+        ServerOptions::default()
+            .with_env("GRPC_MAX_STREAMS_PER_CONNECTION", "1")
+            .with_env("GRPC_GOAWAY_GRACE", "1"),
+    )
+    .await;
+    let mut client = server.live_chat_client().await;
+    let mut stream = client
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Stream should open")
+        .into_inner();
+    let backlog = stream
+        .next()
+        .await
+        .expect("Stream should yield a response")
+        .expect("Stream response");
+    assert!(!backlog.items.is_empty());
+
+    // The stream outlives the grace period only until the connection is closed
+    let end = tokio::time::timeout(std::time::Duration::from_secs(10), stream.next())
+        .await
+        .expect("Connection should close after the grace period");
+    assert!(!matches!(end, Some(Ok(_))), "{end:?}");
+    assert!(server.log().contains("GOAWAY grace period elapsed"));
+
+    // New calls reconnect
+    let mut client = server.live_chat_client().await;
+    client
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Stream should open on a new connection");
+
+    drop(client);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_json_logs_carry_peer_grpc_method_and_stream_span() {
+    let server = TestServer::start(ServerOptions::default().with_env("LOG_FORMAT", "json")).await;
+    let (status, _) = get_json(
+        &server.http_client(),
+        &server.rest_url("/youtube/v3/videos?part=snippet&id=test-video"),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+
+    let mut stream = server
+        .live_chat_client()
+        .await
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some("live-chat-id-1".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("Stream should open")
+        .into_inner();
+    stream
+        .next()
+        .await
+        .expect("Response")
+        .expect("Stream response");
+    drop(stream);
+
+    // The close is logged by the stream task once it notices the client left
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let events = loop {
+        let events: Vec<Value> = server
+            .log()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Every log line should be JSON"))
+            .collect();
+        if events
+            .iter()
+            .any(|event| event["fields"]["reason"] == "client_disconnect")
+        {
+            break events;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "Expected the stream to close:\n{}",
+            server.log()
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    };
+
+    let rest = events
+        .iter()
+        .find(|event| event["fields"]["uri"] == "/youtube/v3/videos?part=snippet&id=test-video")
+        .expect("REST request should be logged");
+    assert!(
+        rest["fields"]["peer"]
+            .as_str()
+            .is_some_and(|peer| peer.starts_with("127.0.0.1:"))
+    );
+    let grpc = events
+        .iter()
+        .find(|event| {
+            event["fields"]["grpc.method"]
+                == "youtube.api.v3.V3DataLiveChatMessageService/StreamList"
+        })
+        .expect("gRPC call should be logged with its full method name");
+    assert!(
+        grpc["fields"]["peer"]
+            .as_str()
+            .is_some_and(|peer| peer.starts_with("127.0.0.1:"))
+    );
+    let closed = events
+        .iter()
+        .find(|event| event["fields"]["reason"] == "client_disconnect")
+        .unwrap();
+    assert_eq!(closed["span"]["live_chat_id"], "live-chat-id-1");
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_tokens_near_expiry_are_rotated_via_response_header() {
+    let server = TestServer::start(
+        ServerOptions::default()
+            .with_env("REQUIRE_AUTH", "true")
+            .with_env("OAUTH_ROTATION_THRESHOLD_SECS", "60"),
+    )
+    .await;
+    let client = server.http_client();
+    let videos_url = server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1");
+    let issue = |expires_in: &'static str| {
+        let client = client.clone();
+        let token_url = server.rest_url("/oauth2/token");
+        async move {
+            let tokens: Value = client
+                .post(&token_url)
+                .form(&[
+                    ("grant_type", "authorization_code"),
+                    ("code", "e2e-code"),
+                    ("expires_in", expires_in),
+                ])
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            tokens["access_token"].as_str().unwrap().to_string()
+        }
+    };
+    let rotated_header = |response: &reqwest::Response| {
+        response
+            .headers()
+            .get("x-mock-rotated-token")
+            .map(|value| value.to_str().unwrap().to_string())
+    };
+
+    // A token far from expiry is used as is
+    let fresh = issue("3600").await;
+    let response = client
+        .get(&videos_url)
+        .bearer_auth(&fresh)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(rotated_header(&response), None);
+
+    // A token about to expire still works and comes back with its successor
+    let expiring = issue("30").await;
+    let response = client
+        .get(&videos_url)
+        .bearer_auth(&expiring)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let successor = rotated_header(&response).expect("Token should be rotated");
+    assert_ne!(successor, expiring);
+    let response = client
+        .get(&videos_url)
+        .bearer_auth(&successor)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_recorded_control_requests_replay_into_a_fresh_server() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_file = dir.path().join("requests.jsonl");
+    let log_file = log_file.to_str().unwrap();
+    let options = ServerOptions::default().with_env("REQUEST_LOG_FILE", log_file);
+    let messages_path = "/youtube/v3/liveChat/messages?liveChatId=replay-chat&part=snippet";
+
+    // The recording server logs its REST requests, control and API reads alike
+    let server = TestServer::start(options.clone()).await;
+    let client = server.http_client();
+    let response = client
+        .post(server.rest_url("/control/videos"))
+        .json(&json!({
+            "id": "replay-video",
+            "channelId": "replay-channel",
+            "title": "Recorded",
+            "description": "Replayed later",
+            "channelTitle": "Replay Channel",
+            "liveChatId": "replay-chat",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let response = client
+        .post(server.rest_url("/control/chat_messages"))
+        .json(&json!({
+            "id": "replay-msg",
+            "liveChatId": "replay-chat",
+            "authorChannelId": "replay-author",
+            "authorDisplayName": "Replay Author",
+            "messageText": "Recorded message",
+            "publishedAt": "2024-01-01T00:00:00Z",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let (_, recorded) = get_json(&client, &server.rest_url(messages_path)).await;
+    assert_clean_shutdown(server).await;
+
+    // A fresh server rebuilds the state from its request log
+    let server = TestServer::start(options).await;
+    let client = server.http_client();
+    let (_, before) = get_json(&client, &server.rest_url(messages_path)).await;
+    assert_eq!(before["items"], json!([]), "{before}");
+    let response = client
+        .post(server.rest_url("/control/replay"))
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let report: Value = response.json().await.unwrap();
+    assert_eq!(report["replayed"], 2, "{report}");
+    assert_eq!(report["failed"], 0, "{report}");
+    let (_, replayed) = get_json(&client, &server.rest_url(messages_path)).await;
+    assert_eq!(replayed["items"], recorded["items"]);
+    assert_eq!(
+        replayed["items"][0]["snippet"]["displayMessage"],
+        "Recorded message"
+    );
+
+    // Files other than the request log are not read
+    let response = client
+        .post(server.rest_url("/control/replay"))
+        .json(&json!({"path": "/etc/passwd"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+    assert_clean_shutdown(server).await;
+}
+
+#[tokio::test]
+async fn test_record_header_saves_the_response_body() {
+    let dir = tempfile::tempdir().expect("Temp dir");
+    let fixtures = dir.path().join("fixtures");
+    let server = TestServer::start(
+        ServerOptions::default().with_env("RECORD_RESPONSES_DIR", fixtures.to_str().unwrap()),
+    )
+    .await;
+    let client = server.http_client();
+    let videos_url = server.rest_url("/youtube/v3/videos?part=snippet&id=test-video-1");
+
+    let response = client
+        .get(&videos_url)
+        .header("x-mock-record", "videos-list")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let body = response.bytes().await.unwrap();
+    let recorded = std::fs::read(fixtures.join("videos-list.json")).expect("Recorded fixture");
+    assert_eq!(recorded, body);
+
+    // Chat responses are recorded the same way
+    let chat_url =
+        server.rest_url("/youtube/v3/liveChat/messages?liveChatId=live-chat-id-1&part=snippet");
+    let response = client
+        .get(&chat_url)
+        .header("x-mock-record", "chat-messages")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let body = response.bytes().await.unwrap();
+    assert_eq!(
+        std::fs::read(fixtures.join("chat-messages.json")).unwrap(),
+        body
+    );
+
+    // A name that would leave the directory is rejected and nothing is written
+    let response = client
+        .get(&videos_url)
+        .header("x-mock-record", "../escape")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert!(!dir.path().join("escape.json").exists());
+
+    // Requests without the header are not recorded
+    client.get(&videos_url).send().await.unwrap();
+    assert_eq!(std::fs::read_dir(&fixtures).unwrap().count(), 2);
+    assert_clean_shutdown(server).await;
+}
@@ -1,15 +1,21 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+#[path = "build_support/proto_check.rs"]
+mod proto_check;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let proto_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../proto");
-    let root = proto_path.canonicalize().map_err(|e| {
-        format!(
-            "Failed to find proto directory at {proto_path:?}. \
-             Make sure to initialize git submodules with: \
-             git submodule update --init --recursive\nError: {e}",
-        )
-    })?;
-    let proto_file = root.join("stream_list.proto");
+    let root = proto_check::proto_root(Path::new(env!("CARGO_MANIFEST_DIR")))?;
+
+    // The current youtube.api.v3 package is always compiled
+    let mut proto_files = vec![root.join("stream_list.proto")];
+    let mut include_dirs = vec![root.clone()];
+
+    // The experimental package lives in this crate and is feature-gated
+    if std::env::var_os("CARGO_FEATURE_VNEXT").is_some() {
+        let vnext_root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("proto/vnext");
+        proto_files.push(vnext_root.join("stream_list_vnext.proto"));
+        include_dirs.push(vnext_root);
+    }
 
     let descriptor_path =
         PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("live_chat_service_descriptor.bin");
@@ -18,6 +24,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build_server(true)
         .build_client(false)
         .file_descriptor_set_path(&descriptor_path)
-        .compile_protos(&[proto_file], &[root])?;
+        .compile_protos(&proto_files, &include_dirs)?;
+
+    // An outdated submodule compiles too; name what it lacks before the crate fails on it
+    proto_check::verify(&descriptor_path, &root)?;
     Ok(())
 }
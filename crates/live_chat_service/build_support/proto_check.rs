@@ -0,0 +1,392 @@
+//! Build-time check of the proto submodule checkout
+//!
+//! Shared by the build scripts that compile `proto/stream_list.proto`. An outdated
+//! submodule still compiles, and the code reading a field it lacks then fails deep in the
+//! generated code. Checking the descriptor set right after compilation turns that into one
+//! error naming the missing fields and the submodule revision this tree expects.
+
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorSet};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Messages the crates use, by full name, with the fields they read or set
+pub const REQUIRED_FIELDS: &[(&str, &[&str])] = &[
+    (
+        "youtube.api.v3.LiveChatMessageListRequest",
+        &[
+            "live_chat_id",
+            "hl",
+            "profile_image_size",
+            "max_results",
+            "page_token",
+            "part",
+        ],
+    ),
+    (
+        "youtube.api.v3.LiveChatMessageListResponse",
+        &[
+            "kind",
+            "etag",
+            "offline_at",
+            "page_info",
+            "next_page_token",
+            "items",
+        ],
+    ),
+    (
+        "youtube.api.v3.PageInfo",
+        &["total_results", "results_per_page"],
+    ),
+    (
+        "youtube.api.v3.LiveChatMessage",
+        &["kind", "etag", "id", "snippet", "author_details"],
+    ),
+    (
+        "youtube.api.v3.LiveChatMessageAuthorDetails",
+        &[
+            "channel_id",
+            "channel_url",
+            "display_name",
+            "profile_image_url",
+            "is_verified",
+            "is_chat_owner",
+            "is_chat_sponsor",
+            "is_chat_moderator",
+        ],
+    ),
+    (
+        "youtube.api.v3.LiveChatMessageSnippet",
+        &[
+            "type",
+            "live_chat_id",
+            "author_channel_id",
+            "published_at",
+            "display_message",
+            "text_message_details",
+            "message_deleted_details",
+            "super_chat_details",
+            "super_sticker_details",
+        ],
+    ),
+    (
+        "youtube.api.v3.LiveChatTextMessageDetails",
+        &["message_text"],
+    ),
+    (
+        "youtube.api.v3.LiveChatMessageDeletedDetails",
+        &["deleted_message_id"],
+    ),
+    (
+        "youtube.api.v3.LiveChatSuperChatDetails",
+        &[
+            "amount_micros",
+            "currency",
+            "amount_display_string",
+            "user_comment",
+            "tier",
+        ],
+    ),
+    (
+        "youtube.api.v3.LiveChatSuperStickerDetails",
+        &[
+            "amount_micros",
+            "currency",
+            "amount_display_string",
+            "tier",
+            "super_sticker_metadata",
+        ],
+    ),
+    (
+        "youtube.api.v3.SuperStickerMetadata",
+        &["sticker_id", "alt_text", "alt_text_language"],
+    ),
+];
+
+/// A missing or outdated proto checkout
+///
+/// `Debug` prints the message as is, since the build script returns it from `main`.
+pub struct CheckoutError(pub String);
+
+impl fmt::Debug for CheckoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Display for CheckoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CheckoutError {}
+
+/// The workspace's proto checkout, from the manifest directory of a crate in `crates/`
+///
+/// Fails with a hint to initialize the submodule when the directory or
+/// `stream_list.proto` is missing, as an uninitialized submodule is an empty directory.
+pub fn proto_root(manifest_dir: &Path) -> Result<PathBuf, CheckoutError> {
+    let proto_path = manifest_dir.join("../../proto");
+    let root = proto_path.canonicalize().map_err(|e| {
+        CheckoutError(format!(
+            "Failed to find proto directory at {proto_path:?}. \
+             Make sure to initialize git submodules with: \
+             git submodule update --init --recursive\nError: {e}",
+        ))
+    })?;
+    if !root.join("stream_list.proto").is_file() {
+        return Err(CheckoutError(format!(
+            "The proto directory at {root:?} has no stream_list.proto. \
+             Make sure to initialize git submodules with: \
+             git submodule update --init --recursive",
+        )));
+    }
+    Ok(root)
+}
+
+/// Required messages and fields the descriptor set does not define, as `Message.field`
+///
+/// A missing message is reported once, by its name alone.
+pub fn missing_fields(set: &FileDescriptorSet, required: &[(&str, &[&str])]) -> Vec<String> {
+    let mut defined = HashSet::new();
+    for file in &set.file {
+        let prefix = match file.package() {
+            "" => String::new(),
+            package => format!("{package}."),
+        };
+        for message in &file.message_type {
+            collect_fields(&prefix, message, &mut defined);
+        }
+    }
+
+    let mut missing = Vec::new();
+    for (message, fields) in required {
+        if !defined.contains(*message) {
+            missing.push(message.to_string());
+            continue;
+        }
+        for field in *fields {
+            let name = format!("{message}.{field}");
+            if !defined.contains(&name) {
+                missing.push(name);
+            }
+        }
+    }
+    missing
+}
+
+/// Add the full names of a message, its fields and its nested messages to `defined`
+fn collect_fields(prefix: &str, message: &DescriptorProto, defined: &mut HashSet<String>) {
+    let name = format!("{prefix}{}", message.name());
+    for field in &message.field {
+        defined.insert(format!("{name}.{}", field.name()));
+    }
+    let nested_prefix = format!("{name}.");
+    for nested in &message.nested_type {
+        collect_fields(&nested_prefix, nested, defined);
+    }
+    defined.insert(name);
+}
+
+/// Build error for a checkout that lacks `missing`
+///
+/// `expected` is the submodule revision recorded in this tree and `checked_out` the one in
+/// the working copy, when they could be read.
+pub fn drift_error(
+    proto_root: &Path,
+    missing: &[String],
+    expected: Option<&str>,
+    checked_out: Option<&str>,
+) -> String {
+    let mut error =
+        format!("The proto checkout at {proto_root:?} is out of date; it does not define:\n");
+    for name in missing {
+        error.push_str(&format!("  - {name}\n"));
+    }
+    match expected {
+        Some(expected) => error.push_str(&format!(
+            "This tree expects the proto submodule at {expected}"
+        )),
+        None => error.push_str("This tree expects the proto submodule at the revision it records"),
+    }
+    match checked_out {
+        Some(checked_out) => error.push_str(&format!(" (checked out: {checked_out}).\n")),
+        None => error.push_str(".\n"),
+    }
+    error.push_str("Update it with: git submodule update --init --recursive");
+    error
+}
+
+/// Check the descriptor set written by `tonic_prost_build` against [`REQUIRED_FIELDS`]
+pub fn verify(descriptor_path: &Path, proto_root: &Path) -> Result<(), CheckoutError> {
+    let bytes = std::fs::read(descriptor_path).map_err(|e| {
+        CheckoutError(format!(
+            "Failed to read descriptor set {descriptor_path:?}: {e}"
+        ))
+    })?;
+    let set = FileDescriptorSet::decode(bytes.as_slice()).map_err(|e| {
+        CheckoutError(format!(
+            "Failed to decode descriptor set {descriptor_path:?}: {e}"
+        ))
+    })?;
+    let missing = missing_fields(&set, REQUIRED_FIELDS);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let expected = expected_revision(proto_root);
+    let checked_out =
+        git_output(proto_root, &["rev-parse", "HEAD"]).filter(|_| proto_root.join(".git").exists());
+    Err(CheckoutError(drift_error(
+        proto_root,
+        &missing,
+        expected.as_deref(),
+        checked_out.as_deref(),
+    )))
+}
+
+/// Submodule commit recorded in the superproject's `HEAD`
+fn expected_revision(proto_root: &Path) -> Option<String> {
+    let parent = proto_root.parent()?;
+    let entry = git_output(parent, &["ls-tree", "HEAD", "proto"])?;
+    // <mode> commit <sha>\tproto
+    let mut parts = entry.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("160000"), Some("commit"), Some(sha)) => Some(sha.to_string()),
+        _ => None,
+    }
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::{FieldDescriptorProto, FileDescriptorProto};
+
+    fn field(name: &str) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn message(name: &str, fields: &[&str], nested: Vec<DescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field: fields.iter().map(|name| field(name)).collect(),
+            nested_type: nested,
+            ..Default::default()
+        }
+    }
+
+    /// Descriptor set of a single file in `youtube.api.v3`
+    fn fixture(messages: Vec<DescriptorProto>) -> FileDescriptorSet {
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("stream_list.proto".to_string()),
+                package: Some("youtube.api.v3".to_string()),
+                message_type: messages,
+                ..Default::default()
+            }],
+        }
+    }
+
+    const REQUIRED: &[(&str, &[&str])] = &[
+        (
+            "youtube.api.v3.LiveChatMessage",
+            &["id", "snippet", "author_details"],
+        ),
+        (
+            "youtube.api.v3.LiveChatMessageSnippet.TypeWrapper",
+            &["type"],
+        ),
+        ("youtube.api.v3.PageInfo", &["total_results"]),
+    ];
+
+    #[test]
+    fn test_complete_checkout_has_nothing_missing() {
+        let set = fixture(vec![
+            message(
+                "LiveChatMessage",
+                &["id", "snippet", "author_details"],
+                vec![],
+            ),
+            message(
+                "LiveChatMessageSnippet",
+                &[],
+                vec![message("TypeWrapper", &["type"], vec![])],
+            ),
+            message("PageInfo", &["total_results", "results_per_page"], vec![]),
+        ]);
+        assert!(missing_fields(&set, REQUIRED).is_empty());
+    }
+
+    #[test]
+    fn test_missing_fields_and_messages_are_named() {
+        let set = fixture(vec![
+            message("LiveChatMessage", &["id", "snippet"], vec![]),
+            message("LiveChatMessageSnippet", &[], vec![]),
+        ]);
+        assert_eq!(
+            missing_fields(&set, REQUIRED),
+            vec![
+                "youtube.api.v3.LiveChatMessage.author_details",
+                "youtube.api.v3.LiveChatMessageSnippet.TypeWrapper",
+                "youtube.api.v3.PageInfo",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_messages_are_matched_by_package() {
+        let mut set = fixture(vec![]);
+        set.file.push(FileDescriptorProto {
+            name: Some("other.proto".to_string()),
+            package: Some("youtube.api.vnext".to_string()),
+            message_type: vec![message("PageInfo", &["total_results"], vec![])],
+            ..Default::default()
+        });
+        assert_eq!(
+            missing_fields(&set, &[("youtube.api.v3.PageInfo", &["total_results"])]),
+            vec!["youtube.api.v3.PageInfo"]
+        );
+    }
+
+    #[test]
+    fn test_descriptor_written_by_the_build_has_every_required_field() {
+        let bytes = include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/live_chat_service_descriptor.bin"
+        ));
+        let set = FileDescriptorSet::decode(bytes.as_slice()).unwrap();
+        assert!(missing_fields(&set, REQUIRED_FIELDS).is_empty());
+    }
+
+    #[test]
+    fn test_drift_error_names_fields_and_revisions() {
+        let missing = vec!["youtube.api.v3.LiveChatMessage.author_details".to_string()];
+        let error = drift_error(Path::new("proto"), &missing, Some("abc123"), Some("def456"));
+        assert!(error.contains("  - youtube.api.v3.LiveChatMessage.author_details\n"));
+        assert!(error.contains("at abc123 (checked out: def456)"));
+        assert!(error.ends_with("git submodule update --init --recursive"));
+
+        let error = drift_error(Path::new("proto"), &missing, None, None);
+        assert!(error.contains("at the revision it records.\n"));
+    }
+}
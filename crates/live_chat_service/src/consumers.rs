@@ -0,0 +1,65 @@
+//! Single-consumer streams
+//!
+//! When enabled, at most one stream may be open for a given page token of a chat, like a
+//! server that prevents duplicate consumers on one cursor. The token a stream was opened
+//! with stays claimed until that stream ends.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Page tokens claimed by open streams, per chat
+#[derive(Debug, Default)]
+pub struct ActiveCursors {
+    claimed: Mutex<HashSet<(String, String)>>,
+}
+
+impl ActiveCursors {
+    /// Claim `page_token` of a chat for a new stream, `None` if another stream holds it
+    pub fn claim(self: &Arc<Self>, live_chat_id: &str, page_token: &str) -> Option<CursorClaim> {
+        let key = (live_chat_id.to_string(), page_token.to_string());
+        let inserted = self
+            .claimed
+            .lock()
+            .expect("Failed to acquire lock on active cursors")
+            .insert(key.clone());
+        inserted.then(|| CursorClaim {
+            cursors: Arc::clone(self),
+            key,
+        })
+    }
+}
+
+/// A claimed page token, released when dropped
+#[derive(Debug)]
+pub struct CursorClaim {
+    cursors: Arc<ActiveCursors>,
+    key: (String, String),
+}
+
+impl Drop for CursorClaim {
+    fn drop(&mut self) {
+        self.cursors
+            .claimed
+            .lock()
+            .expect("Failed to acquire lock on active cursors")
+            .remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_claimed_once_until_released() {
+        let cursors = Arc::new(ActiveCursors::default());
+        let claim = cursors.claim("chat-1", "token").expect("First claim");
+        assert!(cursors.claim("chat-1", "token").is_none());
+        // Other tokens and other chats are independent
+        assert!(cursors.claim("chat-1", "other-token").is_some());
+        assert!(cursors.claim("chat-2", "token").is_some());
+
+        drop(claim);
+        assert!(cursors.claim("chat-1", "token").is_some());
+    }
+}
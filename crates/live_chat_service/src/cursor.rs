@@ -0,0 +1,165 @@
+//! Server-tracked stream cursors
+//!
+//! When enabled, page tokens are opaque IDs mapped to server-side state instead of
+//! encoded message indexes, so they can expire and force clients to restart the stream.
+
+use clock::Clock;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tonic::Status;
+
+/// Server-side state behind a cursor token
+#[derive(Debug, Clone)]
+struct Cursor {
+    /// Index of the next message to send
+    index: usize,
+    /// Chat the cursor was issued for; a token cannot be reused for another chat
+    live_chat_id: String,
+    /// Monotonic time at which the cursor was issued
+    issued_at: Duration,
+}
+
+/// Store of issued cursor tokens with a fixed time-to-live
+pub struct CursorStore {
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    cursors: RwLock<HashMap<String, Cursor>>,
+}
+
+impl CursorStore {
+    pub fn new(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            ttl,
+            clock,
+            cursors: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Time-to-live of issued cursors
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Issue a cursor for resuming `live_chat_id` at `index`
+    pub fn issue(&self, live_chat_id: &str, index: usize) -> String {
+        let now = self.clock.monotonic();
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let mut cursors = self
+            .cursors
+            .write()
+            .expect("Failed to acquire write lock on cursor store");
+
+        // Drop cursors expired for a whole TTL so the store stays bounded; more recently
+        // expired ones are kept so they are still reported as expired rather than unknown
+        cursors.retain(|_, cursor| now.saturating_sub(cursor.issued_at) < self.ttl * 2);
+        cursors.insert(
+            token.clone(),
+            Cursor {
+                index,
+                live_chat_id: live_chat_id.to_string(),
+                issued_at: now,
+            },
+        );
+        token
+    }
+
+    /// Resolve a cursor token into the message index to resume from
+    ///
+    /// - unknown token: `invalid_argument`
+    /// - token issued for another chat: `invalid_argument`
+    /// - token older than the TTL: `invalid_argument("page token expired")`
+    pub fn resolve(&self, token: &str, live_chat_id: Option<&str>) -> Result<usize, Status> {
+        let cursor = self
+            .cursors
+            .read()
+            .expect("Failed to acquire read lock on cursor store")
+            .get(token)
+            .cloned()
+            .ok_or_else(|| Status::invalid_argument("Invalid page_token: unknown cursor"))?;
+
+        if self.clock.monotonic().saturating_sub(cursor.issued_at) >= self.ttl {
+            self.cursors
+                .write()
+                .expect("Failed to acquire write lock on cursor store")
+                .remove(token);
+            return Err(Status::invalid_argument("page token expired"));
+        }
+
+        if live_chat_id.is_some_and(|id| id != cursor.live_chat_id) {
+            return Err(Status::invalid_argument(
+                "Invalid page_token: issued for a different live_chat_id",
+            ));
+        }
+
+        Ok(cursor.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn store(ttl_secs: u64) -> (Arc<clock::MockClock>, CursorStore) {
+        let clock = Arc::new(clock::MockClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+        ));
+        let store = CursorStore::new(Duration::from_secs(ttl_secs), clock.clone());
+        (clock, store)
+    }
+
+    #[test]
+    fn test_cursor_resolves_to_issued_index() {
+        let (_, store) = store(60);
+        let token = store.issue("chat-1", 7);
+        assert_eq!(store.resolve(&token, Some("chat-1")).unwrap(), 7);
+        // Resolving does not consume the cursor
+        assert_eq!(store.resolve(&token, None).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_cursor_expires_after_ttl() {
+        let (clock, store) = store(60);
+        let token = store.issue("chat-1", 3);
+
+        clock.advance(Duration::from_secs(59));
+        assert!(store.resolve(&token, Some("chat-1")).is_ok());
+
+        clock.advance(Duration::from_secs(1));
+        let status = store.resolve(&token, Some("chat-1")).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(status.message(), "page token expired");
+    }
+
+    #[test]
+    fn test_cursor_expired_before_another_is_issued_is_still_reported_expired() {
+        let (clock, store) = store(60);
+        let token = store.issue("chat-1", 3);
+
+        clock.advance(Duration::from_secs(60));
+        store.issue("chat-1", 4);
+        let status = store.resolve(&token, Some("chat-1")).unwrap_err();
+        assert_eq!(status.message(), "page token expired");
+
+        // Forgotten once expired for a whole TTL
+        let token = store.issue("chat-1", 5);
+        clock.advance(Duration::from_secs(120));
+        store.issue("chat-1", 6);
+        assert_eq!(store.cursors.read().unwrap().len(), 1);
+        let status = store.resolve(&token, Some("chat-1")).unwrap_err();
+        assert_eq!(status.message(), "Invalid page_token: unknown cursor");
+    }
+
+    #[test]
+    fn test_cursor_rejects_unknown_token_and_other_chat() {
+        let (_, store) = store(60);
+        let token = store.issue("chat-1", 0);
+
+        let status = store.resolve("unknown", Some("chat-1")).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let status = store.resolve(&token, Some("chat-2")).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
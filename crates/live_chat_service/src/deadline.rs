@@ -0,0 +1,77 @@
+//! Client deadlines
+//!
+//! gRPC clients send their deadline as a `grpc-timeout` header. tonic only applies it to
+//! the handler, which returns as soon as a stream is opened, so streaming tasks read it
+//! themselves and end once it passes.
+
+use std::time::Duration;
+use tokio::time::Instant;
+use tonic::metadata::MetadataMap;
+
+/// Header carrying the client's deadline, e.g. `2S` or `200m`
+pub const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Timeout of a request, from its `grpc-timeout` header
+///
+/// The value is at most 8 digits followed by a unit: `H`ours, `M`inutes, `S`econds,
+/// `m`illiseconds, `u`microseconds or `n`anoseconds. Malformed values are ignored, as if
+/// the client had set no deadline.
+pub fn grpc_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let value = metadata.get(GRPC_TIMEOUT_HEADER)?.to_str().ok()?;
+    let (digits, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 60 * 60)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// Wait until `deadline`, forever when there is none
+pub async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Whether `deadline` has passed
+pub fn passed(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeout_of(value: &str) -> Option<Duration> {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(GRPC_TIMEOUT_HEADER, value.parse().unwrap());
+        grpc_timeout(&metadata)
+    }
+
+    #[test]
+    fn test_grpc_timeout_units() {
+        assert_eq!(timeout_of("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(timeout_of("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(timeout_of("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(timeout_of("200m"), Some(Duration::from_millis(200)));
+        assert_eq!(timeout_of("5u"), Some(Duration::from_micros(5)));
+        assert_eq!(timeout_of("7n"), Some(Duration::from_nanos(7)));
+        assert_eq!(grpc_timeout(&MetadataMap::new()), None);
+    }
+
+    #[test]
+    fn test_malformed_grpc_timeout_is_ignored() {
+        for value in ["", "S", "12", "12x", "-1S", "123456789S", "1.5S"] {
+            assert_eq!(timeout_of(value), None, "{value:?}");
+        }
+    }
+}
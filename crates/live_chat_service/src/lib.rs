@@ -1,10 +1,42 @@
 pub mod proto {
     tonic::include_proto!("youtube.api.v3");
+    /// Descriptor set covering every compiled package, for reflection
     pub const FILE_DESCRIPTOR_SET: &[u8] =
         tonic::include_file_descriptor_set!("live_chat_service_descriptor");
+    /// Proto package of the live chat service
+    pub const PACKAGE: &str = "youtube.api.v3";
+
+    #[cfg(feature = "vnext")]
+    pub mod vnext {
+        tonic::include_proto!("youtube.api.vnext");
+        /// Proto package of the experimental live chat service
+        pub const PACKAGE: &str = "youtube.api.vnext";
+    }
 }
 
-use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+pub mod consumers;
+pub mod cursor;
+pub mod deadline;
+pub mod multi_chat;
+pub mod reseed;
+#[cfg(feature = "vnext")]
+pub mod vnext;
+
+// The build scripts' submodule check, compiled here for its tests
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../build_support/proto_check.rs"]
+mod proto_check;
+
+pub use consumers::ActiveCursors;
+pub use cursor::CursorStore;
+pub use oauth_service::{IssuedTokenValidator, TokenValidator};
+pub use reseed::RESEED_GAP_ETAG;
+
+use domain::{
+    CloseReason, DEFAULT_MAX_RESULTS, DisplayMessagePolicy, LiveChatState, MAX_MAX_RESULTS,
+    StreamRegistry,
+};
 use proto::v3_data_live_chat_message_service_server::{
     V3DataLiveChatMessageService, V3DataLiveChatMessageServiceServer,
 };
@@ -13,23 +45,349 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
+
+// Longest wait for a change notification before rechecking the timeout, control kills,
+// token expiry and scheduled keepalives
+const IDLE_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Interval between empty responses while a chat is scheduled but not started
+const SCHEDULED_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Polling interval advertised to clients unless configured otherwise
+pub const DEFAULT_POLLING_INTERVAL_MILLIS: u64 = 1000;
+
+/// Request metadata naming the viewer's channel (mock extension)
+/// Messages of muted authors are only served to streams whose viewer is that author
+pub const VIEWER_CHANNEL_ID_HEADER: &str = "x-mock-viewer-channel-id";
+
+/// Request extension marking the calls of the server's own health probe
+/// They are not recorded, charged or failed by injected faults, so probing leaves no trace.
+/// Extensions cannot be sent over the wire, so only in-process callers can set it.
+#[derive(Debug, Clone, Copy)]
+pub struct HealthProbe;
 
-// Polling interval for checking new messages
-const POLLING_INTERVAL_SECS: u64 = 1;
+/// Response metadata carrying the polling interval
+/// The v3 stream response has no `pollingIntervalMillis` field, so the interval travels
+/// as initial metadata like the quota headers
+pub const POLLING_INTERVAL_HEADER: &str = "x-mock-polling-interval-millis";
 
+// Status message the real API uses for rejected credentials
+const INVALID_CREDENTIALS: &str = "Request had invalid authentication credentials. Expected OAuth 2 access token, login cookie or other valid authentication credential. See https://developers.google.com/identity/sign-in/web/devconsole-project.";
+
+/// Whether the space-separated `scopes` of a token contain `required` exactly
+/// Tokens this server did not issue have no known scope
+fn has_scope(scopes: Option<&str>, required: &str) -> bool {
+    scopes.is_some_and(|scopes| scopes.split(' ').any(|scope| scope == required))
+}
+
+/// pageInfo for a response carrying `results_per_page` of the chat's `total_results` messages
+fn page_info(total_results: usize, results_per_page: usize) -> Option<proto::PageInfo> {
+    Some(proto::PageInfo {
+        total_results: Some(i32::try_from(total_results).unwrap_or(i32::MAX)),
+        results_per_page: Some(i32::try_from(results_per_page).unwrap_or(i32::MAX)),
+    })
+}
+
+/// Encode a message index as a page token (base64 of the decimal index)
+pub fn encode_page_token(index: usize) -> String {
+    domain::encode_page_token(index)
+}
+
+/// Decode a page token into the message index to resume from
+///
+/// - missing or empty token: start from 0
+/// - not valid base64: `invalid_argument`
+/// - valid base64 that is not UTF-8 or not a non-negative integer: `invalid_argument`
+pub fn parse_page_token(token: Option<&str>) -> Result<usize, Status> {
+    let token = match token {
+        Some(token) if !token.is_empty() => token,
+        _ => return Ok(0),
+    };
+
+    domain::decode_page_token(token).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// Map a repository failure to a gRPC status
+/// Backend failures become `INTERNAL` rather than a panic
+pub fn status_from_repository_error(error: &datastore::RepositoryError) -> Status {
+    match error {
+        datastore::RepositoryError::NotFound => Status::not_found(error.to_string()),
+        datastore::RepositoryError::Conflict => Status::already_exists(error.to_string()),
+        datastore::RepositoryError::Invalid(_) => Status::invalid_argument(error.to_string()),
+        datastore::RepositoryError::Backend(_) => Status::internal(error.to_string()),
+    }
+}
+
+/// Convert the shared author details into the gRPC message
+/// The REST serializers use the same `domain::AuthorDetails`, keeping both transports identical
+pub fn author_details_to_proto(
+    details: &domain::AuthorDetails,
+) -> proto::LiveChatMessageAuthorDetails {
+    proto::LiveChatMessageAuthorDetails {
+        channel_id: Some(details.channel_id.clone()),
+        channel_url: Some(details.channel_url.clone()),
+        display_name: Some(details.display_name.clone()),
+        profile_image_url: Some(details.profile_image_url.clone()),
+        is_verified: Some(details.is_verified),
+        is_chat_owner: Some(details.is_chat_owner),
+        is_chat_sponsor: Some(details.is_chat_sponsor),
+        is_chat_moderator: Some(details.is_chat_moderator),
+    }
+}
+
+/// Snippet type and details of a message: deletion events name the deleted message, super
+/// chats and super stickers carry their payment details, everything else is a text message
+fn displayed_content(
+    msg: &domain::LiveChatMessage,
+) -> (
+    proto::live_chat_message_snippet::type_wrapper::Type,
+    proto::live_chat_message_snippet::DisplayedContent,
+) {
+    use proto::live_chat_message_snippet::{DisplayedContent, type_wrapper::Type};
+
+    if let Some(deleted_message_id) = &msg.deleted_message_id {
+        return (
+            Type::MessageDeletedEvent,
+            DisplayedContent::MessageDeletedDetails(proto::LiveChatMessageDeletedDetails {
+                deleted_message_id: Some(deleted_message_id.clone()),
+            }),
+        );
+    }
+    if let Some(details) = &msg.super_sticker_details {
+        return (
+            Type::SuperStickerEvent,
+            DisplayedContent::SuperStickerDetails(proto::LiveChatSuperStickerDetails {
+                amount_micros: Some(details.amount_micros),
+                currency: Some(details.currency.clone()),
+                amount_display_string: Some(details.amount_display_string()),
+                tier: Some(details.tier),
+                super_sticker_metadata: Some(proto::SuperStickerMetadata {
+                    sticker_id: Some(details.sticker_id.clone()),
+                    alt_text: Some(details.alt_text.clone()),
+                    alt_text_language: Some("en".to_string()),
+                }),
+            }),
+        );
+    }
+    match &msg.super_chat_details {
+        Some(details) => (
+            Type::SuperChatEvent,
+            DisplayedContent::SuperChatDetails(proto::LiveChatSuperChatDetails {
+                amount_micros: Some(details.amount_micros),
+                currency: Some(details.currency.clone()),
+                amount_display_string: Some(details.amount_display_string()),
+                user_comment: Some(details.user_comment.clone()),
+                tier: Some(details.tier),
+            }),
+        ),
+        None => (
+            Type::TextMessageEvent,
+            DisplayedContent::TextMessageDetails(proto::LiveChatTextMessageDetails {
+                message_text: Some(msg.message_text.clone()),
+            }),
+        ),
+    }
+}
+
+#[derive(Clone)]
 pub struct LiveChatService {
     repo: Arc<dyn datastore::Repository>,
     stream_timeout: Option<Duration>,
+    request_log: Option<Arc<request_log::RequestLog>>,
+    display_message_policy: DisplayMessagePolicy,
+    cursors: Option<Arc<CursorStore>>,
+    token_validator: Arc<dyn oauth_service::TokenValidator>,
+    streams: Arc<StreamRegistry>,
+    quota: Arc<domain::QuotaLedger>,
+    faults: Arc<domain::FaultConfig>,
+    polling_interval_millis: u64,
+    /// Time within which a stream sends its first response, empty if the history is not ready
+    first_response_budget: Option<Duration>,
+    /// Page tokens held by open streams, when single-consumer mode is on
+    active_cursors: Option<Arc<ActiveCursors>>,
+    /// Cancelled when the server shuts down, ending every open stream
+    shutdown: CancellationToken,
+    /// Messages after which a stream sends an empty keep-alive response, even with more pending
+    keepalive_every_n_messages: Option<usize>,
 }
 
 impl LiveChatService {
-    pub fn new(repo: Arc<dyn datastore::Repository>, stream_timeout: Option<Duration>) -> Self {
+    pub fn new(
+        repo: Arc<dyn datastore::Repository>,
+        stream_timeout: Option<Duration>,
+        request_log: Option<Arc<request_log::RequestLog>>,
+        display_message_policy: DisplayMessagePolicy,
+        cursors: Option<Arc<CursorStore>>,
+        token_validator: Arc<dyn oauth_service::TokenValidator>,
+        streams: Arc<StreamRegistry>,
+    ) -> Self {
         Self {
             repo,
             stream_timeout,
+            request_log,
+            display_message_policy,
+            cursors,
+            token_validator,
+            streams,
+            quota: Arc::new(domain::QuotaLedger::default()),
+            faults: Arc::new(domain::FaultConfig::default()),
+            polling_interval_millis: DEFAULT_POLLING_INTERVAL_MILLIS,
+            first_response_budget: None,
+            active_cursors: None,
+            shutdown: CancellationToken::new(),
+            keepalive_every_n_messages: None,
         }
     }
+
+    /// Send the first response of each stream within `budget`: when reading the history takes
+    /// longer, an empty response is sent first and the history follows once read
+    pub fn with_first_response_budget(mut self, budget: Option<Duration>) -> Self {
+        self.first_response_budget = budget;
+        self
+    }
+
+    /// End open streams cleanly, without an error status, once `shutdown` is cancelled
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Follow every `n` delivered messages with an empty keep-alive response carrying the
+    /// current page token, so busy chats interleave empty and data responses
+    pub fn with_keepalive_every_n_messages(mut self, n: Option<usize>) -> Self {
+        self.keepalive_every_n_messages = n.filter(|&n| n > 0);
+        self
+    }
+
+    /// Reject a stream opened with a page token another open stream of the chat presented
+    pub fn with_single_consumer(mut self, single_consumer: bool) -> Self {
+        self.active_cursors = single_consumer.then(Default::default);
+        self
+    }
+
+    /// Charge stream openings to `quota` instead of a private ledger
+    pub fn with_quota(mut self, quota: Arc<domain::QuotaLedger>) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Advertise `polling_interval_millis` as the interval clients should wait between polls
+    pub fn with_polling_interval_millis(mut self, polling_interval_millis: u64) -> Self {
+        self.polling_interval_millis = polling_interval_millis;
+        self
+    }
+
+    /// Interval clients should wait between polls
+    pub fn polling_interval_millis(&self) -> u64 {
+        self.polling_interval_millis
+    }
+
+    /// Consult `faults` for errors injected via the control API
+    pub fn with_faults(mut self, faults: Arc<domain::FaultConfig>) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Resolve a page token into the message index to resume from
+    /// Uses the cursor store when server-tracked cursors are enabled, index tokens otherwise
+    pub fn resolve_page_token(
+        &self,
+        token: Option<&str>,
+        live_chat_id: Option<&str>,
+    ) -> Result<usize, Status> {
+        match (&self.cursors, token) {
+            (Some(cursors), Some(token)) if !token.is_empty() => {
+                cursors.resolve(token, live_chat_id)
+            }
+            _ => parse_page_token(token),
+        }
+    }
+
+    // Streams of chats that do not exist fail like the real API instead of staying empty.
+    // The lookup runs on a blocking thread; a repository too slow to answer within the
+    // first-response budget from `stream_start` opens the stream unchecked so the budget holds
+    async fn ensure_live_chat_exists(
+        &self,
+        live_chat_id: &str,
+        stream_start: tokio::time::Instant,
+    ) -> Result<(), Status> {
+        let lookup = tokio::task::spawn_blocking({
+            let repo = Arc::clone(&self.repo);
+            let live_chat_id = live_chat_id.to_string();
+            move || repo.live_chat_exists(&live_chat_id)
+        });
+        let exists = match self.first_response_budget {
+            Some(budget) => match tokio::time::timeout_at(stream_start + budget, lookup).await {
+                Ok(exists) => exists,
+                Err(_) => return Ok(()),
+            },
+            None => lookup.await,
+        };
+        let exists = exists
+            .unwrap_or_else(|e| Err(datastore::RepositoryError::Backend(e.to_string())))
+            .map_err(|e| status_from_repository_error(&e))?;
+        if exists {
+            Ok(())
+        } else {
+            Err(Status::not_found("liveChatId not found"))
+        }
+    }
+
+    /// Claim `page_token` for a new stream of `live_chat_id` in single-consumer mode
+    /// Fails while another open stream holds it; streams without a token never conflict
+    fn claim_cursor(
+        &self,
+        live_chat_id: &str,
+        page_token: Option<&str>,
+    ) -> Result<Option<consumers::CursorClaim>, Status> {
+        match (&self.active_cursors, page_token) {
+            (Some(active_cursors), Some(token)) if !token.is_empty() => active_cursors
+                .claim(live_chat_id, token)
+                .map(Some)
+                .ok_or_else(|| Status::already_exists("stream already active for cursor")),
+            _ => Ok(None),
+        }
+    }
+
+    // Record a stream_list invocation (metadata and arguments) to the request log
+    fn record_stream_list(&self, request: &Request<LiveChatMessageListRequest>) {
+        let Some(log) = &self.request_log else {
+            return;
+        };
+
+        let metadata = request
+            .metadata()
+            .clone()
+            .into_headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    value.to_str().unwrap_or("<binary>").to_string(),
+                )
+            })
+            .collect();
+
+        let args = request.get_ref();
+        let arguments = serde_json::json!({
+            "live_chat_id": args.live_chat_id,
+            "hl": args.hl,
+            "profile_image_size": args.profile_image_size,
+            "max_results": args.max_results,
+            "page_token": args.page_token,
+            "part": args.part,
+        });
+
+        log.record(&request_log::RequestLogEntry::Grpc {
+            timestamp: clock::system_clock().now(),
+            method: "stream_list".to_string(),
+            metadata,
+            arguments,
+        });
+    }
 }
 
 #[tonic::async_trait]
@@ -40,12 +398,40 @@ impl V3DataLiveChatMessageService for LiveChatService {
         &self,
         request: Request<LiveChatMessageListRequest>,
     ) -> Result<Response<Self::StreamListStream>, Status> {
+        let health_probe = request.extensions().get::<HealthProbe>().is_some();
+        if !health_probe {
+            self.record_stream_list(&request);
+        }
+
+        // An injected fault fails the call, or aborts the stream after some messages
+        let fault = (!health_probe)
+            .then(|| self.faults.hit(domain::FaultTarget::LiveChatStreamList))
+            .flatten();
+        let abort_after = match fault {
+            Some(fault) => {
+                let code = domain::faults::grpc_code(&fault.grpc_code)
+                    .map_or(tonic::Code::Unavailable, tonic::Code::from_i32);
+                match fault.after_messages {
+                    None => return Err(Status::new(code, fault.message)),
+                    Some(limit) => Some((
+                        usize::try_from(limit).unwrap_or(usize::MAX),
+                        code,
+                        fault.message,
+                    )),
+                }
+            }
+            None => None,
+        };
+
         // Check if auth check is enabled via environment variable
         let require_auth = std::env::var("REQUIRE_AUTH")
             .unwrap_or_else(|_| "false".to_string())
             .parse::<bool>()
             .unwrap_or(false);
 
+        // Bearer token whose expiry is enforced for the lifetime of the stream
+        let mut bearer_token = None;
+
         if require_auth {
             // Check for authentication in metadata
             // Look for either:
@@ -62,171 +448,1657 @@ impl V3DataLiveChatMessageService for LiveChatService {
                 ));
             }
 
-            // Validate OAuth token expiry if authorization metadata is present
+            // API keys do not expire, so only OAuth-only requests are checked
             #[allow(clippy::collapsible_if)]
-            if let Some(auth_value) = auth_metadata {
+            if let Some(auth_value) = auth_metadata.filter(|_| !has_api_key) {
                 if let Ok(auth_str) = auth_value.to_str() {
                     // Extract token from "Bearer <token>" format
-                    if let Some(token) = auth_str
+                    bearer_token = auth_str
                         .strip_prefix("Bearer ")
                         .or_else(|| auth_str.strip_prefix("bearer "))
-                    {
-                        // Validate token expiry
-                        if let Err(err_msg) = oauth_service::validate_token(token) {
-                            return Err(Status::unauthenticated(format!(
-                                "Invalid credentials: {err_msg}"
-                            )));
-                        }
-                    }
+                        .map(str::to_string);
+                }
+            }
+
+            // Validate the token before opening the stream
+            if let Some(token) = &bearer_token {
+                if self.token_validator.validate(token).is_err() {
+                    return Err(Status::unauthenticated(INVALID_CREDENTIALS));
                 }
             }
+
+            // Bearer tokens must also carry REQUIRED_SCOPE when it is set
+            if let Some(token) = &bearer_token
+                && let Ok(required_scope) = std::env::var("REQUIRED_SCOPE")
+                && !required_scope.is_empty()
+                && !has_scope(
+                    oauth_service::get_token_scope(token).as_deref(),
+                    &required_scope,
+                )
+            {
+                return Err(Status::permission_denied("insufficient scope"));
+            }
         }
 
         let (tx, rx) = mpsc::channel(4);
 
+        // Opening the stream is charged to the caller once authenticated, and is refused
+        // once the caller has used up an enforced daily limit
+        let metadata = request.metadata();
+        let quota_key = domain::quota::caller_key(
+            metadata
+                .get("x-goog-api-key")
+                .and_then(|value| value.to_str().ok()),
+            metadata.contains_key("authorization"),
+        );
+        let charge = if health_probe {
+            domain::quota::QuotaCharge {
+                cost: 0,
+                remaining: 0,
+            }
+        } else {
+            self.quota
+                .charge(
+                    domain::QuotaEndpoint::LiveChatMessagesStreamList,
+                    &quota_key,
+                )
+                .map_err(|_| Status::resource_exhausted(domain::quota::QUOTA_EXCEEDED_MESSAGE))?
+        };
+
+        // Mock extension: one stream over several comma-separated chats
+        let multi_chat = multi_chat::requested(metadata);
+
+        // Mock extension: the author a stream is watched as, who sees their muted messages
+        let viewer_channel_id = metadata
+            .get(VIEWER_CHANNEL_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+
+        // The client's deadline bounds the stream's lifetime alongside the stream timeout
+        let deadline =
+            deadline::grpc_timeout(metadata).map(|timeout| tokio::time::Instant::now() + timeout);
+
         // Extract request parameters
         let request_inner = request.into_inner();
         let live_chat_id = request_inner
             .live_chat_id
             .ok_or_else(|| Status::invalid_argument("live_chat_id is required"))?;
 
-        // Parse page_token to determine starting index
-        let start_index = match request_inner.page_token {
-            Some(token) if !token.is_empty() => {
-                // Decode the page token (simple base64 encoding of the index)
-                match BASE64.decode(&token) {
-                    Ok(decoded) => {
-                        let decoded_str = String::from_utf8(decoded)
-                            .map_err(|_| Status::invalid_argument("Invalid page_token"))?;
-
-                        // Parse directly to usize
-                        decoded_str
-                            .parse::<usize>()
-                            .map_err(|_| Status::invalid_argument("Invalid page_token"))?
-                    }
-                    Err(_) => return Err(Status::invalid_argument("Invalid page_token")),
-                }
+        // Messages per response: unset or 0 uses the default, larger values are capped
+        let max_results = match request_inner.max_results {
+            None | Some(0) => DEFAULT_MAX_RESULTS,
+            Some(max) => (max as usize).min(MAX_MAX_RESULTS),
+        };
+
+        // Counted from before the existence check, which the first-response budget covers
+        let stream_start = tokio::time::Instant::now();
+        let chat_ids = if multi_chat {
+            // Server-tracked cursors are issued per chat; multi-chat tokens carry every cursor
+            if self.cursors.is_some() {
+                return Err(Status::failed_precondition(
+                    "Multi-chat streams do not support server-tracked cursors",
+                ));
             }
-            _ => 0, // Start from the beginning if no page_token
+            multi_chat::parse_chat_ids(&live_chat_id)?
+        } else {
+            vec![live_chat_id.clone()]
         };
+        for chat_id in &chat_ids {
+            self.ensure_live_chat_exists(chat_id, stream_start).await?;
+        }
+
+        // Clients cannot join a chat before it starts when not-started chats are closed
+        if domain::scheduled_chat_not_started() {
+            let now = clock::system_clock().now();
+            for chat_id in &chat_ids {
+                let not_started = self
+                    .repo
+                    .live_chat_not_started(chat_id, now)
+                    .map_err(|e| status_from_repository_error(&e))?;
+                if not_started {
+                    return Err(Status::failed_precondition(domain::LIVE_CHAT_NOT_STARTED));
+                }
+            }
+        }
+
+        if multi_chat {
+            let cursors =
+                multi_chat::decode_cursors(request_inner.page_token.as_deref(), &chat_ids)?;
+            let cursor_claim =
+                self.claim_cursor(&live_chat_id, request_inner.page_token.as_deref())?;
+            let stream = multi_chat::MultiChatStream {
+                repo: Arc::clone(&self.repo),
+                chat_ids,
+                cursors,
+                max_results,
+                display_message_policy: self.display_message_policy,
+                stream_timeout: self.stream_timeout,
+                deadline,
+                shutdown: self.shutdown.clone(),
+                viewer_channel_id,
+                bearer_token,
+                token_validator: Arc::clone(&self.token_validator),
+                abort_after,
+                first_response_budget: self.first_response_budget,
+                keepalive_every_n_messages: self.keepalive_every_n_messages,
+                stream_start,
+                cursor_claim,
+            };
+            let span = tracing::info_span!("stream_list", live_chat_id = %live_chat_id);
+            span.in_scope(|| tracing::info!("Multi-chat stream opened"));
+            tokio::spawn(stream.run(Arc::clone(&self.streams), tx).instrument(span));
+            return Ok(self.stream_response(rx, &charge));
+        }
+
+        // Parse page_token to determine starting index
+        let start_index =
+            self.resolve_page_token(request_inner.page_token.as_deref(), Some(&live_chat_id))?;
+
+        // Held until the streaming task ends
+        let cursor_claim = self.claim_cursor(&live_chat_id, request_inner.page_token.as_deref())?;
 
         // Clone necessary data for the spawned task
         let repo = Arc::clone(&self.repo);
         let stream_timeout = self.stream_timeout;
+        let display_message_policy = self.display_message_policy;
+        let cursors = self.cursors.clone();
+        let token_validator = Arc::clone(&self.token_validator);
+        let first_response_budget = self.first_response_budget;
+        let shutdown = self.shutdown.clone();
+        let keepalive_every_n_messages = self.keepalive_every_n_messages;
+        // Counted as active until the streaming task ends
+        let mut stream_guard = self.streams.open(&live_chat_id);
+        // Correlates everything logged over the stream's lifetime
+        let span = tracing::info_span!("stream_list", live_chat_id = %live_chat_id);
+        span.in_scope(|| tracing::info!("Stream opened"));
 
-        tokio::spawn(async move {
-            let mut current_index = start_index;
-            let stream_start = tokio::time::Instant::now();
-            let mut sent_any_response = false;
-
-            loop {
-                // Get chat messages from the datastore filtered by live_chat_id
-                let messages = repo.get_chat_messages(&live_chat_id);
-
-                // Track if we sent any messages in this iteration
-                let mut sent_in_iteration = false;
-
-                // Send messages starting from current_index
-                for (i, msg) in messages.iter().enumerate().skip(current_index) {
-                    let snippet = proto::LiveChatMessageSnippet {
-                        r#type: Some(
-                            proto::live_chat_message_snippet::type_wrapper::Type::TextMessageEvent
-                                as i32,
-                        ),
-                        live_chat_id: Some(msg.live_chat_id.clone()),
-                        author_channel_id: Some(msg.author_channel_id.clone()),
-                        published_at: Some(msg.published_at.to_rfc3339()),
-                        display_message: Some(msg.message_text.clone()),
-                        displayed_content: Some(
-                            proto::live_chat_message_snippet::DisplayedContent::TextMessageDetails(
-                                proto::LiveChatTextMessageDetails {
-                                    message_text: Some(msg.message_text.clone()),
-                                },
-                            ),
-                        ),
+        tokio::spawn(
+            async move {
+                let _cursor_claim = cursor_claim;
+                // Position of the next unread message. Every response's token encodes it, so the
+                // token of an empty response is the one the next message would resume from
+                let mut current_index = start_index;
+                let mut last_scheduled_response: Option<tokio::time::Instant> = None;
+                let next_page_token = |index: usize| match &cursors {
+                    Some(cursors) => cursors.issue(&live_chat_id, index),
+                    None => encode_page_token(index),
+                };
+                // Response without items whose token resumes at `index`
+                let empty_response =
+                    |index: usize, total_results: usize| LiveChatMessageListResponse {
+                        kind: Some("youtube#liveChatMessageListResponse".to_string()),
+                        etag: Some(format!("etag-{index}")),
+                        page_info: page_info(total_results, 0),
+                        items: vec![],
+                        next_page_token: Some(next_page_token(index)),
                         ..Default::default()
                     };
-
-                    let author_details = proto::LiveChatMessageAuthorDetails {
-                        display_name: Some(msg.author_display_name.clone()),
-                        channel_id: Some(msg.author_channel_id.clone()),
-                        is_verified: Some(msg.is_verified),
-                        ..Default::default()
+                // Terminal response: everything after the delivered messages is offline
+                let ended_response =
+                    |index: usize, offline_at: Option<String>, total_results: usize| {
+                        LiveChatMessageListResponse {
+                            kind: Some("youtube#liveChatMessageListResponse".to_string()),
+                            etag: Some(format!("etag-{index}")),
+                            offline_at,
+                            page_info: page_info(total_results, 0),
+                            items: vec![],
+                            ..Default::default()
+                        }
                     };
 
-                    let item = proto::LiveChatMessage {
-                        kind: Some("youtube#liveChatMessage".to_string()),
-                        etag: Some(format!("etag-{i}")),
-                        id: Some(msg.id.clone()),
-                        snippet: Some(snippet),
-                        author_details: Some(author_details),
-                    };
+                // Subscribed before the first read so no write is missed
+                let mut changes = repo.subscribe(&live_chat_id);
+                // A different generation means all data was replaced since the last read
+                let mut generation = repo.generation();
+                // ID of the last message sent, to find the stream's place after a reseed
+                let mut last_delivered: Option<String> = None;
+                let mut messages_changed = true;
+                // Stored messages in the chat, reported as pageInfo.totalResults
+                let mut total_results = 0;
+                // Messages sent so far, counted towards an injected abort
+                let mut delivered = 0;
+                // Messages sent since the last forced keep-alive
+                let mut since_keepalive = 0;
 
-                    // Always generate next_page_token to allow resuming the stream later
-                    // even if no more messages exist currently (they may be added later)
-                    let next_index = (i + 1).to_string();
-                    let next_page_token = Some(BASE64.encode(next_index.as_bytes()));
+                let reason = 'stream: loop {
+                    if tx.is_closed() {
+                        break 'stream CloseReason::ClientDisconnect;
+                    }
 
-                    let response = LiveChatMessageListResponse {
-                        kind: Some("youtube#liveChatMessageListResponse".to_string()),
-                        etag: Some(format!("etag-{i}")),
-                        items: vec![item],
-                        next_page_token,
-                        ..Default::default()
-                    };
+                    if stream_guard.is_killed() {
+                        break 'stream CloseReason::KilledViaControl;
+                    }
 
-                    if (tx.send(Ok(response)).await).is_err() {
-                        return; // Client disconnected
+                    if shutdown.is_cancelled() {
+                        break 'stream CloseReason::ServerShutdown;
                     }
 
-                    current_index = i + 1;
-                    sent_in_iteration = true;
-                    sent_any_response = true;
-                    // Yield to the scheduler to allow other tasks to run
-                    tokio::task::yield_now().await;
-                }
+                    // The client gave up waiting: nobody reads the stream anymore
+                    if deadline::passed(deadline) {
+                        break 'stream CloseReason::DeadlineExceeded;
+                    }
+
+                    // A token expiring mid-session ends the stream so the client refreshes it
+                    if bearer_token
+                        .as_deref()
+                        .is_some_and(|token| token_validator.validate(token).is_err())
+                    {
+                        let status = Status::unauthenticated(INVALID_CREDENTIALS);
+                        let reason = close_reason_for(&status);
+                        let _ = tx.send(Err(status)).await;
+                        break 'stream reason;
+                    }
 
-                // If no messages were sent in this iteration and we haven't sent any response yet,
-                // send an empty response to indicate the stream is active but has no items
-                if !sent_in_iteration && !sent_any_response {
-                    let next_page_token = Some(BASE64.encode(current_index.to_string().as_bytes()));
+                    // The data was replaced: continue after the last delivered message if it is
+                    // still there, restart with a gap notice if not, end if the chat is gone
+                    let current_generation = repo.generation();
+                    if current_generation != generation {
+                        generation = current_generation;
+                        messages_changed = true;
+                        let anchor = reseed::reanchor(
+                            repo.as_ref(),
+                            &live_chat_id,
+                            current_index,
+                            last_delivered.as_deref(),
+                        );
+                        match anchor {
+                            Ok(reseed::Anchor::Resume(index)) => current_index = index,
+                            Ok(reseed::Anchor::Restart) => {
+                                tracing::info!("Stream restarted: data reseeded");
+                                current_index = 0;
+                                last_delivered = None;
+                                let response = LiveChatMessageListResponse {
+                                    etag: Some(RESEED_GAP_ETAG.to_string()),
+                                    ..empty_response(0, total_results)
+                                };
+                                if (tx.send(Ok(response)).await).is_err() {
+                                    break 'stream CloseReason::ClientDisconnect;
+                                }
+                                stream_guard.record_response(stream_start.elapsed());
+                            }
+                            Ok(reseed::Anchor::Removed) => {
+                                let offline_at = Some(clock::system_clock().now().to_rfc3339());
+                                let response = ended_response(current_index, offline_at, 0);
+                                if (tx.send(Ok(response)).await).is_ok() {
+                                    stream_guard.record_response(stream_start.elapsed());
+                                }
+                                break 'stream CloseReason::ChatEnded;
+                            }
+                            Err(e) => {
+                                let status = status_from_repository_error(&e);
+                                let reason = close_reason_for(&status);
+                                let _ = tx.send(Err(status)).await;
+                                break 'stream reason;
+                            }
+                        }
+                    }
 
-                    let response = LiveChatMessageListResponse {
-                        kind: Some("youtube#liveChatMessageListResponse".to_string()),
-                        etag: Some(format!("etag-{current_index}")),
-                        items: vec![],
-                        next_page_token,
-                        ..Default::default()
+                    // The history is read before anything else waits. Until the first response,
+                    // the read races the first-response budget on a blocking thread: when the
+                    // repository is too slow, an empty response goes out first so clients with a
+                    // deadline on the first response keep the stream
+                    let read = match first_response_budget.filter(|_| !stream_guard.has_responded())
+                    {
+                        Some(budget) => {
+                            let mut read = tokio::task::spawn_blocking({
+                                let repo = Arc::clone(&repo);
+                                let live_chat_id = live_chat_id.clone();
+                                move || {
+                                    read_chat(
+                                        repo.as_ref(),
+                                        &live_chat_id,
+                                        current_index,
+                                        messages_changed,
+                                    )
+                                }
+                            });
+                            let read = tokio::select! {
+                                read = &mut read => read,
+                                _ = tokio::time::sleep_until(stream_start + budget) => {
+                                    let response = empty_response(current_index, total_results);
+                                    if (tx.send(Ok(response)).await).is_err() {
+                                        break 'stream CloseReason::ClientDisconnect;
+                                    }
+                                    stream_guard.record_slow_start(stream_start.elapsed());
+                                    tracing::warn!(
+                                        budget_ms = budget.as_millis() as u64,
+                                        "Stream started slowly: history not read within {}ms",
+                                        budget.as_millis()
+                                    );
+                                    read.await
+                                }
+                            };
+                            read.unwrap_or_else(|e| {
+                                Err(datastore::RepositoryError::Backend(e.to_string()))
+                            })
+                        }
+                        None => read_chat(
+                            repo.as_ref(),
+                            &live_chat_id,
+                            current_index,
+                            messages_changed,
+                        ),
                     };
+                    let ChatRead {
+                        chat,
+                        total_results: counted,
+                        mut pending,
+                    } = match read {
+                        Ok(read) => read,
+                        Err(e) => {
+                            let status = status_from_repository_error(&e);
+                            let reason = close_reason_for(&status);
+                            let _ = tx.send(Err(status)).await;
+                            break 'stream reason;
+                        }
+                    };
+                    // Read while the data was replaced: positions may not match, so anchor first
+                    if repo.generation() != generation {
+                        continue 'stream;
+                    }
+                    if let Some(count) = counted {
+                        total_results = count;
+                    }
+                    let state = chat
+                        .as_ref()
+                        .map_or(LiveChatState::Active, |chat| chat.state);
+
+                    if state == LiveChatState::Scheduled {
+                        // Not started yet: keep the stream open with periodic empty responses
+                        if last_scheduled_response
+                            .is_none_or(|sent_at| sent_at.elapsed() >= SCHEDULED_KEEPALIVE_INTERVAL)
+                        {
+                            let response = empty_response(current_index, total_results);
+                            if (tx.send(Ok(response)).await).is_err() {
+                                break 'stream CloseReason::ClientDisconnect;
+                            }
+                            last_scheduled_response = Some(tokio::time::Instant::now());
+                            stream_guard.record_response(stream_start.elapsed());
+                        }
+                    } else {
+                        messages_changed = false;
+                        // Messages past the chat's history retention are skipped like deleted ones
+                        if let Some(chat) = &chat {
+                            let now = clock::system_clock().now();
+                            pending.retain(|(_, msg)| chat.serves(msg, now));
+                        }
+                        // So are messages of muted authors, unless the viewer is the author
+                        pending.retain(|(_, msg)| msg.visible_to(viewer_channel_id.as_deref()));
+
+                        // An injected abort cuts the backlog at its message count
+                        if let Some((limit, _, _)) = &abort_after {
+                            pending.truncate(limit.saturating_sub(delivered));
+                        }
+
+                        // Track if we sent any messages in this iteration
+                        let mut sent_in_iteration = false;
+
+                        // Send messages starting from current_index, batched up to max_results per
+                        // response. Batches end where a forced keep-alive is due
+                        let mut remaining = pending.as_slice();
+                        while !remaining.is_empty() {
+                            let batch_len = keepalive_every_n_messages
+                                .map_or(max_results, |n| max_results.min(n - since_keepalive))
+                                .min(remaining.len());
+                            let (batch, rest) = remaining.split_at(batch_len);
+                            remaining = rest;
+                            let items = batch
+                                .iter()
+                                .map(|(position, msg)| {
+                                    message_to_proto(*position, msg, display_message_policy)
+                                })
+                                .collect();
+                            let next_index = batch
+                                .last()
+                                .map_or(current_index, |(position, _)| position + 1);
 
-                    if (tx.send(Ok(response)).await).is_err() {
-                        return; // Client disconnected
+                            // Always generate next_page_token to allow resuming the stream later
+                            // even if no more messages exist currently (they may be added later)
+                            let response = LiveChatMessageListResponse {
+                                kind: Some("youtube#liveChatMessageListResponse".to_string()),
+                                etag: Some(format!("etag-{}", next_index - 1)),
+                                page_info: page_info(total_results, batch.len()),
+                                items,
+                                next_page_token: Some(next_page_token(next_index)),
+                                ..Default::default()
+                            };
+
+                            if (tx.send(Ok(response)).await).is_err() {
+                                break 'stream CloseReason::ClientDisconnect;
+                            }
+
+                            stream_guard
+                                .record_delivery(batch.iter().map(|(_, msg)| msg.id.as_str()));
+                            last_delivered = batch.last().map(|(_, msg)| msg.id.clone());
+                            current_index = next_index;
+                            delivered += batch.len();
+                            sent_in_iteration = true;
+                            stream_guard.record_response(stream_start.elapsed());
+
+                            if let Some(n) = keepalive_every_n_messages {
+                                since_keepalive += batch.len();
+                                if since_keepalive >= n {
+                                    let response = empty_response(current_index, total_results);
+                                    if (tx.send(Ok(response)).await).is_err() {
+                                        break 'stream CloseReason::ClientDisconnect;
+                                    }
+                                    since_keepalive = 0;
+                                    stream_guard.record_response(stream_start.elapsed());
+                                }
+                            }
+                            // Yield to the scheduler to allow other tasks to run
+                            tokio::task::yield_now().await;
+                        }
+
+                        if let Some((limit, code, message)) = &abort_after
+                            && delivered >= *limit
+                        {
+                            let status = Status::new(*code, message.clone());
+                            let reason = close_reason_for(&status);
+                            let _ = tx.send(Err(status)).await;
+                            break 'stream reason;
+                        }
+
+                        // If no messages were sent in this iteration and we haven't sent any response yet,
+                        // send an empty response to indicate the stream is active but has no items
+                        if !sent_in_iteration
+                            && !stream_guard.has_responded()
+                            && state == LiveChatState::Active
+                        {
+                            let response = empty_response(current_index, total_results);
+                            if (tx.send(Ok(response)).await).is_err() {
+                                break 'stream CloseReason::ClientDisconnect;
+                            }
+                            stream_guard.record_response(stream_start.elapsed());
+                        }
+
+                        if state == LiveChatState::Ended {
+                            let offline_at = chat
+                                .and_then(|chat| chat.offline_at)
+                                .map(|offline_at| offline_at.to_rfc3339());
+                            let response = ended_response(current_index, offline_at, total_results);
+                            if (tx.send(Ok(response)).await).is_ok() {
+                                stream_guard.record_response(stream_start.elapsed());
+                            }
+                            break 'stream CloseReason::ChatEnded;
+                        }
                     }
-                    sent_any_response = true;
-                }
 
-                // Check if timeout has been reached
-                #[allow(clippy::collapsible_if)]
-                if let Some(timeout) = stream_timeout {
-                    if stream_start.elapsed() >= timeout {
-                        break; // Timeout reached, close the stream
+                    // Check if timeout has been reached
+                    #[allow(clippy::collapsible_if)]
+                    if let Some(timeout) = stream_timeout {
+                        if stream_start.elapsed() >= timeout {
+                            break 'stream CloseReason::Timeout;
+                        }
                     }
-                }
 
-                // If no timeout is configured or timeout not reached yet, keep polling for new messages
-                // Wait before polling again to avoid busy loop
-                tokio::time::sleep(tokio::time::Duration::from_secs(POLLING_INTERVAL_SECS)).await;
+                    // Wait for the next write to the chat, rechecking the stream at least every interval
+                    tokio::select! {
+                        changed = changes.changed() => {
+                            if changed.is_err() {
+                                // The repository does not notify: fall back to polling
+                                tokio::time::sleep(IDLE_RECHECK_INTERVAL).await;
+                            }
+                            messages_changed = true;
+                        }
+                        _ = tokio::time::sleep(IDLE_RECHECK_INTERVAL) => {}
+                        _ = shutdown.cancelled() => {}
+                        _ = deadline::sleep_until(deadline) => {}
+                    }
+                };
+
+                tracing::info!(reason = reason.label(), "Stream closed: {}", reason.label());
+                stream_guard.close(reason);
             }
+            .instrument(span),
+        );
+
+        Ok(self.stream_response(rx, &charge))
+    }
+}
+
+impl LiveChatService {
+    /// Response for an opened stream, with the polling interval and quota metadata
+    fn stream_response(
+        &self,
+        rx: mpsc::Receiver<Result<LiveChatMessageListResponse, Status>>,
+        charge: &domain::quota::QuotaCharge,
+    ) -> Response<ReceiverStream<Result<LiveChatMessageListResponse, Status>>> {
+        let mut response = Response::new(ReceiverStream::new(rx));
+        response
+            .metadata_mut()
+            .insert(POLLING_INTERVAL_HEADER, self.polling_interval_millis.into());
+        if self.quota.cost_headers() {
+            let metadata = response.metadata_mut();
+            metadata.insert(domain::quota::QUOTA_COST_HEADER, charge.cost.into());
+            metadata.insert(
+                domain::quota::QUOTA_REMAINING_HEADER,
+                charge.remaining.into(),
+            );
+        }
+        response
+    }
+}
+
+/// Convert the stored message at `position` of its chat into the gRPC message
+/// displayMessage is rendered per `policy`; messageText is always raw
+fn message_to_proto(
+    position: usize,
+    msg: &domain::LiveChatMessage,
+    policy: DisplayMessagePolicy,
+) -> proto::LiveChatMessage {
+    let (message_type, displayed_content) = displayed_content(msg);
+    let snippet = proto::LiveChatMessageSnippet {
+        r#type: Some(message_type as i32),
+        live_chat_id: Some(msg.live_chat_id.clone()),
+        author_channel_id: Some(msg.author_channel_id.clone()),
+        published_at: Some(msg.published_at.to_rfc3339()),
+        display_message: Some(policy.render(&msg.message_text)),
+        displayed_content: Some(displayed_content),
+        ..Default::default()
+    };
+
+    proto::LiveChatMessage {
+        kind: Some("youtube#liveChatMessage".to_string()),
+        etag: Some(message_etag(position)),
+        id: Some(msg.id.clone()),
+        snippet: Some(snippet),
+        author_details: Some(author_details_to_proto(&msg.author_details())),
+    }
+}
+
+/// Chat state and undelivered messages read at the start of a stream iteration
+struct ChatRead {
+    /// Effective lifecycle; chats without a stored lifecycle are active until their video ends
+    chat: Option<domain::LiveChat>,
+    /// Stored messages in the chat, `None` when they were not read
+    total_results: Option<usize>,
+    /// Messages at or after the stream's position, with their positions
+    pending: Vec<(usize, domain::LiveChatMessage)>,
+}
+
+/// Read what a stream iteration needs from the repository
+///
+/// Started chats read their messages from `position` only after a change notification
+/// (`messages_changed`). Positions are stable, so deleting an earlier message does not move
+/// the stream onto an already delivered or past an undelivered message. Scheduled chats only
+/// count their messages for the keepalive responses.
+fn read_chat(
+    repo: &dyn datastore::Repository,
+    live_chat_id: &str,
+    position: usize,
+    messages_changed: bool,
+) -> datastore::RepositoryResult<ChatRead> {
+    let chat = repo.get_effective_live_chat(live_chat_id)?;
+    let scheduled = chat
+        .as_ref()
+        .is_some_and(|chat| chat.state == LiveChatState::Scheduled);
+    if scheduled {
+        return Ok(ChatRead {
+            chat,
+            total_results: Some(repo.count_chat_messages(live_chat_id)?),
+            pending: Vec::new(),
         });
+    }
+    if !messages_changed {
+        return Ok(ChatRead {
+            chat,
+            total_results: None,
+            pending: Vec::new(),
+        });
+    }
+    let pending = repo.get_chat_messages_from(live_chat_id, position)?;
+    Ok(ChatRead {
+        chat,
+        total_results: Some(repo.count_chat_messages(live_chat_id)?),
+        pending,
+    })
+}
+
+/// Etag of the message at `position` in its chat
+pub(crate) fn message_etag(position: usize) -> String {
+    format!("etag-{position}")
+}
 
-        Ok(Response::new(ReceiverStream::new(rx)))
+/// Position of a message in its chat, from its etag
+#[cfg(feature = "vnext")]
+pub(crate) fn message_position(etag: &str) -> Option<usize> {
+    etag.strip_prefix("etag-")?.parse().ok()
+}
+
+/// Close reason for a stream that ended with `status`, labelled by its snake_case code
+fn close_reason_for(status: &Status) -> CloseReason {
+    let mut label = String::new();
+    for (i, c) in format!("{:?}", status.code()).chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            label.push('_');
+        }
+        label.push(c.to_ascii_lowercase());
     }
+    CloseReason::Error { status: label }
 }
 
 // Public function to create the server
 pub fn create_service(
     repo: Arc<dyn datastore::Repository>,
     stream_timeout: Option<Duration>,
+    request_log: Option<Arc<request_log::RequestLog>>,
+    display_message_policy: DisplayMessagePolicy,
+    cursors: Option<Arc<CursorStore>>,
+    token_validator: Arc<dyn oauth_service::TokenValidator>,
+    streams: Arc<StreamRegistry>,
 ) -> V3DataLiveChatMessageServiceServer<LiveChatService> {
-    V3DataLiveChatMessageServiceServer::new(LiveChatService::new(repo, stream_timeout))
+    V3DataLiveChatMessageServiceServer::new(LiveChatService::new(
+        repo,
+        stream_timeout,
+        request_log,
+        display_message_policy,
+        cursors,
+        token_validator,
+        streams,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+    #[test]
+    fn test_has_scope_matches_whole_scopes() {
+        let scopes = Some("https://www.googleapis.com/auth/youtube.readonly openid");
+        assert!(has_scope(scopes, "openid"));
+        assert!(has_scope(
+            scopes,
+            "https://www.googleapis.com/auth/youtube.readonly"
+        ));
+        assert!(!has_scope(
+            scopes,
+            "https://www.googleapis.com/auth/youtube"
+        ));
+        assert!(!has_scope(scopes, "open"));
+        assert!(!has_scope(None, "openid"));
+    }
+
+    fn assert_invalid_argument(result: Result<usize, Status>) {
+        let status = result.expect_err("Token should be rejected");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_parse_page_token_missing_starts_from_zero() {
+        assert_eq!(parse_page_token(None).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_parse_page_token_empty_starts_from_zero() {
+        assert_eq!(parse_page_token(Some("")).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_parse_page_token_valid_index() {
+        assert_eq!(parse_page_token(Some("NQ==")).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_parse_page_token_round_trip() {
+        for index in [0, 1, 42, usize::MAX] {
+            let token = encode_page_token(index);
+            assert_eq!(parse_page_token(Some(&token)).unwrap(), index);
+        }
+    }
+
+    #[test]
+    fn test_parse_page_token_non_base64() {
+        assert_invalid_argument(parse_page_token(Some("not base64!")));
+    }
+
+    #[test]
+    fn test_parse_page_token_non_utf8() {
+        let token = BASE64.encode([0xff, 0xfe, 0xfd]);
+        assert_invalid_argument(parse_page_token(Some(&token)));
+    }
+
+    #[test]
+    fn test_parse_page_token_non_numeric() {
+        let token = BASE64.encode("abc");
+        assert_invalid_argument(parse_page_token(Some(&token)));
+    }
+
+    #[test]
+    fn test_parse_page_token_negative_number() {
+        let token = BASE64.encode("-1");
+        assert_invalid_argument(parse_page_token(Some(&token)));
+    }
+
+    #[test]
+    fn test_author_details_match_across_transports() {
+        let repo = datastore::InMemoryRepository::new();
+
+        for msg in datastore::Repository::get_chat_messages(&repo, "test-chat-id").unwrap() {
+            let details = msg.author_details();
+            let grpc = author_details_to_proto(&details);
+            let rest = serde_json::to_value(&details).expect("Serializable author details");
+
+            assert_eq!(rest["channelId"], grpc.channel_id.unwrap());
+            assert_eq!(rest["channelUrl"], grpc.channel_url.unwrap());
+            assert_eq!(rest["displayName"], grpc.display_name.unwrap());
+            assert_eq!(rest["profileImageUrl"], grpc.profile_image_url.unwrap());
+            assert_eq!(rest["isVerified"], grpc.is_verified.unwrap());
+            assert_eq!(rest["isChatOwner"], grpc.is_chat_owner.unwrap());
+            assert_eq!(rest["isChatSponsor"], grpc.is_chat_sponsor.unwrap());
+            assert_eq!(rest["isChatModerator"], grpc.is_chat_moderator.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_page_token_overflow() {
+        let token = BASE64.encode("99999999999999999999999999");
+        assert_invalid_argument(parse_page_token(Some(&token)));
+    }
+
+    #[tokio::test]
+    async fn test_stream_resumes_from_cursor_until_it_expires() {
+        use chrono::TimeZone;
+        use tokio_stream::StreamExt;
+
+        let clock = Arc::new(clock::MockClock::new(
+            chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+        ));
+        let cursors = Arc::new(CursorStore::new(Duration::from_secs(30), clock.clone()));
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            Some(cursors),
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let request = |page_token: Option<String>| {
+            Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                page_token,
+                max_results: Some(1),
+                ..Default::default()
+            })
+        };
+
+        let first: Vec<_> = service
+            .stream_list(request(None))
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .take(2)
+            .map(|response| response.expect("Stream response"))
+            .collect()
+            .await;
+        let cursor = first[0].next_page_token.clone();
+        assert!(
+            parse_page_token(cursor.as_deref()).is_err(),
+            "Cursor should be opaque"
+        );
+
+        let resumed = service
+            .stream_list(request(cursor.clone()))
+            .await
+            .expect("Stream should resume")
+            .into_inner()
+            .next()
+            .await
+            .expect("Resumed stream should yield a response")
+            .expect("Stream response");
+        assert_eq!(resumed.items[0].id, first[1].items[0].id);
+
+        clock.advance(Duration::from_secs(30));
+        let status = service
+            .stream_list(request(cursor))
+            .await
+            .expect_err("Expired cursor should be rejected");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(status.message(), "page token expired");
+    }
+
+    #[tokio::test]
+    async fn test_backend_error_is_surfaced_as_internal() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::FailingRepository),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let status = service
+            .stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect_err("Backend failure should fail the call");
+        assert_eq!(status.code(), tonic::Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_chat_is_not_found_and_empty_chat_keeps_streaming() {
+        use tokio_stream::StreamExt;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let service = LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let open = |live_chat_id: &str| {
+            service.stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some(live_chat_id.to_string()),
+                ..Default::default()
+            }))
+        };
+
+        let status = open("no-such-chat")
+            .await
+            .expect_err("Unknown chat should fail the call");
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert_eq!(status.message(), "liveChatId not found");
+
+        // A chat with a stored lifecycle but no messages is waiting for its first one
+        repo.save_live_chat(domain::LiveChat::active("empty-chat"))
+            .unwrap();
+        let mut stream = open("empty-chat")
+            .await
+            .expect("Known chat should open")
+            .into_inner();
+        let first = stream.next().await.expect("Response").unwrap();
+        assert!(first.items.is_empty());
+    }
+
+    /// Collect the item IDs of the first `responses` responses of a stream
+    async fn stream_batches(
+        service: &LiveChatService,
+        max_results: u32,
+        page_token: Option<String>,
+        responses: usize,
+    ) -> (Vec<Vec<String>>, Option<String>) {
+        use tokio_stream::StreamExt;
+
+        let responses: Vec<_> = service
+            .stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                page_token,
+                max_results: Some(max_results),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .take(responses)
+            .map(|response| response.expect("Stream response"))
+            .collect()
+            .await;
+        let token = responses.last().and_then(|r| r.next_page_token.clone());
+        let batches = responses
+            .into_iter()
+            .map(|response| {
+                response
+                    .items
+                    .into_iter()
+                    .map(|item| item.id.unwrap())
+                    .collect()
+            })
+            .collect();
+        (batches, token)
+    }
+
+    fn ids(range: std::ops::Range<usize>) -> Vec<String> {
+        range.map(|i| format!("test-msg-id-{i}")).collect()
+    }
+
+    #[tokio::test]
+    async fn test_stream_open_reports_quota_in_metadata() {
+        let quota = Arc::new(domain::QuotaLedger::new(true));
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        )
+        .with_quota(Arc::clone(&quota));
+
+        for remaining in ["9995", "9990"] {
+            let mut request = Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            });
+            request
+                .metadata_mut()
+                .insert("x-goog-api-key", "key-a".parse().unwrap());
+            let response = service.stream_list(request).await.expect("Stream");
+            let metadata = response.metadata();
+            assert_eq!(metadata.get(domain::quota::QUOTA_COST_HEADER).unwrap(), "5");
+            assert_eq!(
+                metadata.get(domain::quota::QUOTA_REMAINING_HEADER).unwrap(),
+                remaining
+            );
+        }
+        let report = quota.report(None);
+        assert_eq!(report.by_endpoint["liveChatMessages.streamList"].calls, 2);
+        assert_eq!(report.by_key["key-a"].units, 10);
+    }
+
+    #[tokio::test]
+    async fn test_stream_open_beyond_enforced_quota_is_resource_exhausted() {
+        let quota = Arc::new(domain::QuotaLedger::default());
+        quota.set_daily_limit(Some(10));
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        )
+        .with_quota(Arc::clone(&quota));
+        let request = || {
+            let mut request = Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            });
+            request
+                .metadata_mut()
+                .insert("x-goog-api-key", "key-a".parse().unwrap());
+            request
+        };
+
+        for _ in 0..2 {
+            service.stream_list(request()).await.expect("Stream");
+        }
+        let status = service
+            .stream_list(request())
+            .await
+            .expect_err("Quota should be used up");
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(status.message(), domain::quota::QUOTA_EXCEEDED_MESSAGE);
+        assert_eq!(quota.report(None).by_key["key-a"].calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_open_reports_polling_interval_in_metadata() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let request = || {
+            Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            })
+        };
+
+        let response = service.stream_list(request()).await.expect("Stream");
+        assert_eq!(
+            response.metadata().get(POLLING_INTERVAL_HEADER).unwrap(),
+            "1000"
+        );
+        let service = service.with_polling_interval_millis(250);
+        let response = service.stream_list(request()).await.expect("Stream");
+        assert_eq!(
+            response.metadata().get(POLLING_INTERVAL_HEADER).unwrap(),
+            "250"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_max_results_one_sends_single_messages() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let (batches, token) = stream_batches(&service, 1, None, 5).await;
+        assert_eq!(batches, (0..5).map(|i| ids(i..i + 1)).collect::<Vec<_>>());
+        assert_eq!(parse_page_token(token.as_deref()).unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_follows_every_n_messages() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        )
+        .with_keepalive_every_n_messages(Some(2));
+        // Batches of up to 3 end after every second message for the keep-alive
+        let (batches, token) = stream_batches(&service, 3, None, 5).await;
+        assert_eq!(batches, [ids(0..2), vec![], ids(2..4), vec![], ids(4..5)]);
+        assert_eq!(parse_page_token(token.as_deref()).unwrap(), 5);
+
+        // The keep-alive carries the token of the message after the last one sent
+        use tokio_stream::StreamExt;
+        let responses: Vec<_> = service
+            .stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                max_results: Some(3),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .take(2)
+            .map(|response| response.expect("Stream response"))
+            .collect()
+            .await;
+        assert_eq!(responses[1].next_page_token, responses[0].next_page_token);
+        assert_eq!(
+            parse_page_token(responses[1].next_page_token.as_deref()).unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_injected_faults_fail_or_abort_streams() {
+        use tokio_stream::StreamExt;
+
+        let faults = Arc::new(domain::FaultConfig::default());
+        let fault = |after_messages| domain::Fault {
+            target: domain::FaultTarget::LiveChatStreamList,
+            http_status: 500,
+            reason: "backendError".to_string(),
+            message: "Injected".to_string(),
+            grpc_code: "UNAVAILABLE".to_string(),
+            probability: None,
+            remaining: Some(1),
+            after_messages,
+        };
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        )
+        .with_faults(Arc::clone(&faults));
+        let open = || {
+            service.stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                max_results: Some(2),
+                ..Default::default()
+            }))
+        };
+
+        faults.set(fault(None));
+        let status = open().await.expect_err("Call should fail");
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(status.message(), "Injected");
+
+        // Aborting after three messages cuts the second batch short
+        faults.set(fault(Some(3)));
+        let responses: Vec<_> = open()
+            .await
+            .expect("Stream should open")
+            .into_inner()
+            .take(3)
+            .collect()
+            .await;
+        let items: Vec<_> = responses[..2]
+            .iter()
+            .map(|response| response.as_ref().expect("Stream response").items.len())
+            .collect();
+        assert_eq!(items, vec![2, 1]);
+        let status = responses[2].as_ref().expect_err("Stream should abort");
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        // The fault was used up
+        let (batches, _) = stream_batches(&service, 5, None, 1).await;
+        assert_eq!(batches, vec![ids(0..5)]);
+    }
+
+    #[tokio::test]
+    async fn test_health_probe_calls_leave_no_trace() {
+        let faults = Arc::new(domain::FaultConfig::default());
+        let quota = Arc::new(domain::QuotaLedger::default());
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        )
+        .with_faults(Arc::clone(&faults))
+        .with_quota(Arc::clone(&quota));
+        faults.set(domain::Fault {
+            target: domain::FaultTarget::LiveChatStreamList,
+            http_status: 500,
+            reason: "backendError".to_string(),
+            message: "Injected".to_string(),
+            grpc_code: "UNAVAILABLE".to_string(),
+            probability: None,
+            remaining: Some(1),
+            after_messages: None,
+        });
+
+        let mut request = Request::new(LiveChatMessageListRequest {
+            live_chat_id: Some("healthz-probe".to_string()),
+            ..Default::default()
+        });
+        request.extensions_mut().insert(HealthProbe);
+        let status = service
+            .stream_list(request)
+            .await
+            .expect_err("Sentinel chat should not exist");
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        // Nothing was charged, and the fault is left for the next client call
+        assert_eq!(faults.list()[0].remaining, Some(1));
+        assert_eq!(quota.report(None).calls, 0);
+        let status = service
+            .stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect_err("Fault should fail the call");
+        assert_eq!(status.message(), "Injected");
+    }
+
+    #[tokio::test]
+    async fn test_stream_skips_messages_past_history_retention() {
+        use datastore::Repository;
+
+        // The dummy messages of test-chat-id were published in 2023
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        let mut chat = domain::LiveChat::active("test-chat-id");
+        chat.history_retention_seconds = Some(86_400);
+        repo.save_live_chat(chat).unwrap();
+        repo.add_chat_message(domain::LiveChatMessage {
+            id: "test-msg-id-5".to_string(),
+            live_chat_id: "test-chat-id".to_string(),
+            author_channel_id: "test-channel-id-5".to_string(),
+            author_display_name: "Test User 5".to_string(),
+            message_text: "Test message 5".to_string(),
+            published_at: chrono::Utc::now(),
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        })
+        .unwrap();
+        let service = LiveChatService::new(
+            repo.clone(),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+
+        // A token pointing at a withheld message resumes from the oldest served one
+        let (batches, token) =
+            stream_batches(&service, 5, Some(domain::encode_page_token(0)), 1).await;
+        assert_eq!(batches, vec![ids(5..6)]);
+        assert_eq!(parse_page_token(token.as_deref()).unwrap(), 6);
+        assert_eq!(repo.count_chat_messages("test-chat-id").unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_stream_batches_backlog_and_resumes_without_gaps() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let (batches, _) = stream_batches(&service, 2, None, 3).await;
+        assert_eq!(batches, vec![ids(0..2), ids(2..4), ids(4..5)]);
+
+        // Resuming with the token of the first batch continues right after it
+        let (first, token) = stream_batches(&service, 2, None, 1).await;
+        assert_eq!(first, vec![ids(0..2)]);
+        let (resumed, _) = stream_batches(&service, 2, token, 2).await;
+        assert_eq!(resumed, vec![ids(2..4), ids(4..5)]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_max_results_larger_than_backlog_sends_one_response() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let (batches, token) = stream_batches(&service, 100, None, 1).await;
+        assert_eq!(batches, vec![ids(0..5)]);
+        assert_eq!(parse_page_token(token.as_deref()).unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_single_consumer_rejects_second_stream_on_same_token() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        )
+        .with_single_consumer(true);
+        let open = |page_token: Option<String>| {
+            service.stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                page_token,
+                ..Default::default()
+            }))
+        };
+        let token = Some(encode_page_token(2));
+
+        let first = open(token.clone()).await.expect("First consumer");
+        let status = open(token.clone())
+            .await
+            .expect_err("Second consumer should be rejected");
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+        assert_eq!(status.message(), "stream already active for cursor");
+
+        // Other tokens and streams without a token are not affected
+        let _other = open(Some(encode_page_token(3))).await.expect("Other token");
+        let _fresh = open(None).await.expect("No token");
+        let _fresh_too = open(None).await.expect("No token");
+
+        // The token is released once the first stream ends
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while open(token.clone()).await.is_err() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("Token should be released");
+    }
+
+    #[tokio::test]
+    async fn test_stream_responses_carry_page_info() {
+        use tokio_stream::StreamExt;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let service = LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let page_info = |response: &LiveChatMessageListResponse| {
+            let page_info = response.page_info.expect("pageInfo should be set");
+            (
+                page_info.total_results.unwrap(),
+                page_info.results_per_page.unwrap(),
+            )
+        };
+
+        let mut stream = service
+            .stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                max_results: Some(2),
+                ..Default::default()
+            }))
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        for per_page in [2, 2, 1] {
+            let response = stream.next().await.unwrap().unwrap();
+            assert_eq!(page_info(&response), (5, per_page));
+        }
+
+        // The total follows the stored messages as they change; the deletion leaves a
+        // tombstone that counts like a message
+        let mut message = repo.get_chat_messages("test-chat-id").unwrap()[0].clone();
+        repo.delete_chat_message("test-msg-id-0", chrono::Utc::now())
+            .unwrap();
+        message.id = "page-info-msg".to_string();
+        repo.add_chat_message(message).unwrap();
+        let response = stream.next().await.unwrap().unwrap();
+        assert_eq!(response.items.len(), 2);
+        assert_eq!(page_info(&response), (6, 2));
+    }
+
+    #[tokio::test]
+    async fn test_deleting_a_message_mid_stream_emits_one_deletion_event() {
+        use proto::live_chat_message_snippet::{DisplayedContent, type_wrapper::Type};
+        use tokio_stream::StreamExt;
+
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let service = LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let open = |page_token: Option<String>| {
+            let service = service.clone();
+            async move {
+                service
+                    .stream_list(Request::new(LiveChatMessageListRequest {
+                        live_chat_id: Some("test-chat-id".to_string()),
+                        page_token,
+                        ..Default::default()
+                    }))
+                    .await
+                    .expect("Stream should open")
+                    .into_inner()
+            }
+        };
+        let deletion_of = |item: &proto::LiveChatMessage| {
+            let snippet = item.snippet.as_ref().unwrap();
+            match &snippet.displayed_content {
+                Some(DisplayedContent::MessageDeletedDetails(details)) => {
+                    assert_eq!(snippet.r#type, Some(Type::MessageDeletedEvent as i32));
+                    details.deleted_message_id.clone()
+                }
+                _ => None,
+            }
+        };
+
+        let mut stream = open(None).await;
+        let backlog = stream.next().await.unwrap().unwrap();
+        assert_eq!(backlog.items.len(), 5);
+
+        // Delete a delivered message, then add one after it
+        repo.delete_chat_message("test-msg-id-2", chrono::Utc::now())
+            .unwrap();
+        let mut message = repo.get_chat_messages("test-chat-id").unwrap()[0].clone();
+        message.id = "after-delete".to_string();
+        repo.add_chat_message(message).unwrap();
+        let mut items = Vec::new();
+        let mut last_token = None;
+        while !items
+            .iter()
+            .any(|item: &proto::LiveChatMessage| item.id.as_deref() == Some("after-delete"))
+        {
+            let response = stream.next().await.unwrap().unwrap();
+            last_token = response.next_page_token.clone();
+            items.extend(response.items);
+        }
+        let deletions: Vec<_> = items.iter().filter_map(deletion_of).collect();
+        assert_eq!(deletions, ["test-msg-id-2"]);
+        assert_eq!(items[0].id.as_deref(), Some("test-msg-id-2-deleted"));
+        assert_eq!(last_token, Some(encode_page_token(7)));
+        drop(stream);
+
+        // Resuming after the event does not repeat it
+        let mut resumed = open(last_token).await;
+        let response = resumed.next().await.unwrap().unwrap();
+        assert!(response.items.is_empty());
+        drop(resumed);
+
+        // A new stream from the start sees the event once and never the deleted message
+        let mut fresh = open(None).await;
+        let response = fresh.next().await.unwrap().unwrap();
+        let ids: Vec<_> = response
+            .items
+            .iter()
+            .filter_map(|item| item.id.as_deref())
+            .collect();
+        assert!(!ids.contains(&"test-msg-id-2"));
+        assert_eq!(response.items.iter().filter_map(deletion_of).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_history_read_sends_empty_first_response_within_budget() {
+        use tokio_stream::StreamExt;
+
+        let registry = Arc::new(StreamRegistry::default());
+        let inner: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let service = |repo: Arc<dyn datastore::Repository>| {
+            LiveChatService::new(
+                repo,
+                None,
+                None,
+                DisplayMessagePolicy::Raw,
+                None,
+                Arc::new(IssuedTokenValidator::default()),
+                Arc::clone(&registry),
+            )
+            .with_first_response_budget(Some(Duration::from_millis(50)))
+        };
+        let open = |service: LiveChatService| async move {
+            service
+                .stream_list(Request::new(LiveChatMessageListRequest {
+                    live_chat_id: Some("test-chat-id".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .expect("Stream should open")
+                .into_inner()
+        };
+
+        // Each repository call blocks for 200ms, far beyond the budget
+        let slow = Arc::new(datastore::SlowRepository {
+            inner: Arc::clone(&inner),
+            delay: Duration::from_millis(200),
+        });
+        let opened_at = tokio::time::Instant::now();
+        let mut stream = open(service(slow)).await;
+        let first = stream.next().await.expect("First response").unwrap();
+        assert!(
+            opened_at.elapsed() < Duration::from_millis(200),
+            "First response took {:?}",
+            opened_at.elapsed()
+        );
+        assert!(first.items.is_empty());
+        assert_eq!(first.next_page_token, Some(encode_page_token(0)));
+        // The history follows from where the empty response left off
+        let history = stream.next().await.expect("History").unwrap();
+        assert_eq!(history.items[0].id.as_deref(), Some("test-msg-id-0"));
+        drop(stream);
+        nth_close_reason(&registry, 1).await;
+        let closed = &registry.timeline()[0];
+        assert!(closed.slow_start);
+        assert!(
+            closed
+                .first_response_millis
+                .is_some_and(|millis| millis < 200)
+        );
+        assert_eq!(registry.slow_starts(), 1);
+
+        // A fast repository answers with the history itself
+        let mut stream = open(service(inner)).await;
+        let first = stream.next().await.expect("First response").unwrap();
+        assert!(!first.items.is_empty());
+        drop(stream);
+        nth_close_reason(&registry, 2).await;
+        assert!(!registry.timeline()[1].slow_start);
+        assert_eq!(registry.slow_starts(), 1);
+    }
+
+    /// Wait until the registry has recorded `count` closed streams and return the last reason
+    async fn nth_close_reason(registry: &StreamRegistry, count: usize) -> CloseReason {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(closed) = registry.timeline().get(count - 1) {
+                    return closed.reason.clone();
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("Stream should close")
+    }
+
+    #[tokio::test]
+    async fn test_stream_records_close_reason_of_each_exit_path() {
+        use tokio_stream::StreamExt;
+
+        let registry = Arc::new(StreamRegistry::default());
+        let service = |repo: Arc<dyn datastore::Repository>, stream_timeout| {
+            LiveChatService::new(
+                repo,
+                stream_timeout,
+                None,
+                DisplayMessagePolicy::Raw,
+                None,
+                Arc::new(IssuedTokenValidator::default()),
+                Arc::clone(&registry),
+            )
+        };
+        let open = |service: LiveChatService| async move {
+            service
+                .stream_list(Request::new(LiveChatMessageListRequest {
+                    live_chat_id: Some("test-chat-id".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .expect("Stream should open")
+                .into_inner()
+        };
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+
+        // Client reads one response and goes away
+        let mut stream = open(service(Arc::clone(&repo), None)).await;
+        stream.next().await.expect("First response").unwrap();
+        drop(stream);
+        assert_eq!(
+            nth_close_reason(&registry, 1).await,
+            CloseReason::ClientDisconnect
+        );
+
+        // Timeout elapses after the backlog was sent
+        let stream = open(service(Arc::clone(&repo), Some(Duration::ZERO))).await;
+        let _: Vec<_> = stream.collect().await;
+        assert_eq!(nth_close_reason(&registry, 2).await, CloseReason::Timeout);
+
+        // Control API closes the open stream
+        let mut stream = open(service(Arc::clone(&repo), None)).await;
+        stream.next().await.expect("First response").unwrap();
+        assert_eq!(registry.close_streams("test-chat-id"), 1);
+        assert!(stream.next().await.is_none(), "Killed stream should end");
+        assert_eq!(
+            nth_close_reason(&registry, 3).await,
+            CloseReason::KilledViaControl
+        );
+
+        // An error status ends the stream
+        let faults = Arc::new(domain::FaultConfig::default());
+        faults.set(domain::Fault {
+            target: domain::FaultTarget::LiveChatStreamList,
+            http_status: 500,
+            reason: "backendError".to_string(),
+            message: "Injected".to_string(),
+            grpc_code: "INTERNAL".to_string(),
+            probability: None,
+            remaining: Some(1),
+            after_messages: Some(0),
+        });
+        let stream = open(service(Arc::clone(&repo), None).with_faults(faults)).await;
+        let _: Vec<_> = stream.collect().await;
+        assert_eq!(
+            nth_close_reason(&registry, 4).await,
+            CloseReason::Error {
+                status: "internal".to_string()
+            }
+        );
+
+        // Chat ends and the terminal response is sent
+        let mut chat = domain::LiveChat::active("test-chat-id");
+        chat.transition(LiveChatState::Ended, chrono::Utc::now())
+            .unwrap();
+        repo.save_live_chat(chat).unwrap();
+        let stream = open(service(Arc::clone(&repo), None)).await;
+        let _: Vec<_> = stream.collect().await;
+        assert_eq!(nth_close_reason(&registry, 5).await, CloseReason::ChatEnded);
+
+        // The client's deadline passes while it still holds the stream
+        let live_repo: Arc<dyn datastore::Repository> =
+            Arc::new(datastore::InMemoryRepository::new());
+        let mut request = Request::new(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id".to_string()),
+            ..Default::default()
+        });
+        request
+            .metadata_mut()
+            .insert(deadline::GRPC_TIMEOUT_HEADER, "200m".parse().unwrap());
+        let mut stream = service(live_repo, None)
+            .stream_list(request)
+            .await
+            .expect("Stream should open")
+            .into_inner();
+        stream.next().await.expect("First response").unwrap();
+        assert_eq!(registry.active_streams("test-chat-id"), 1);
+        assert_eq!(
+            nth_close_reason(&registry, 6).await,
+            CloseReason::DeadlineExceeded
+        );
+        assert_eq!(registry.active_streams("test-chat-id"), 0);
+        assert!(stream.next().await.is_none());
+
+        // Server shutdown ends the open stream without an error status
+        let shutdown = CancellationToken::new();
+        let live_repo: Arc<dyn datastore::Repository> =
+            Arc::new(datastore::InMemoryRepository::new());
+        let mut stream = open(service(live_repo, None).with_shutdown(shutdown.clone())).await;
+        stream.next().await.expect("First response").unwrap();
+        shutdown.cancel();
+        let end = tokio::time::timeout(Duration::from_millis(500), stream.next())
+            .await
+            .expect("Shutdown should end the stream before the idle recheck");
+        assert!(end.is_none(), "Stream should end without a status");
+        assert_eq!(
+            nth_close_reason(&registry, 7).await,
+            CloseReason::ServerShutdown
+        );
+
+        let counts = registry.closed_counts();
+        for label in [
+            "client_disconnect",
+            "timeout",
+            "killed_via_control",
+            "error{internal}",
+            "chat_ended",
+            "deadline_exceeded",
+            "server_shutdown",
+        ] {
+            assert_eq!(counts[label], 1, "{label}");
+        }
+        assert!(registry.snapshot().is_empty());
+    }
 }
@@ -0,0 +1,805 @@
+//! Multi-chat streams (mock extension)
+//!
+//! With the `x-mock-multi-chat: true` metadata, `live_chat_id` may list several chats
+//! separated by commas, and one stream interleaves the messages of all of them by publish
+//! time. Each item's `snippet.liveChatId` names its chat, and page tokens carry a cursor per
+//! chat. The real API serves exactly one chat per stream: without the metadata the list is
+//! treated as a single (unknown) chat ID, so fidelity-sensitive clients never see this.
+
+use crate::consumers::CursorClaim;
+use crate::proto::{self, LiveChatMessageListResponse};
+use crate::reseed::{self, RESEED_GAP_ETAG};
+use crate::{IDLE_RECHECK_INTERVAL, message_to_proto, page_info, status_from_repository_error};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use domain::{CloseReason, DisplayMessagePolicy, StreamGuard, StreamRegistry};
+use oauth_service::TokenValidator;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tonic::Status;
+use tonic::metadata::MetadataMap;
+
+/// Request metadata opting a stream into multi-chat subscriptions
+pub const MULTI_CHAT_HEADER: &str = "x-mock-multi-chat";
+
+/// Most chats one stream may subscribe to
+pub const MAX_MULTI_CHATS: usize = 50;
+
+/// Position of the next unread message of each chat
+pub type ChatCursors = BTreeMap<String, usize>;
+
+/// Whether the request opted into multi-chat subscriptions
+pub fn requested(metadata: &MetadataMap) -> bool {
+    metadata
+        .get(MULTI_CHAT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Split a comma-separated `live_chat_id` into distinct chat IDs, in request order
+pub fn parse_chat_ids(live_chat_id: &str) -> Result<Vec<String>, Status> {
+    let mut chat_ids: Vec<String> = Vec::new();
+    for id in live_chat_id.split(',').map(str::trim) {
+        if id.is_empty() {
+            return Err(Status::invalid_argument(
+                "live_chat_id must not contain empty chat IDs",
+            ));
+        }
+        if !chat_ids.iter().any(|known| known == id) {
+            chat_ids.push(id.to_string());
+        }
+    }
+    if chat_ids.len() > MAX_MULTI_CHATS {
+        return Err(Status::invalid_argument(format!(
+            "live_chat_id lists more than {MAX_MULTI_CHATS} chats"
+        )));
+    }
+    Ok(chat_ids)
+}
+
+/// Encode per-chat cursors as a page token (base64 of a JSON object of chat ID to index)
+pub fn encode_cursors(cursors: &ChatCursors) -> String {
+    BASE64.encode(serde_json::to_vec(cursors).expect("Cursors serialize to JSON"))
+}
+
+/// Cursors of `chat_ids` to start from, taken from a multi-chat page token
+///
+/// A missing or empty token starts every chat from 0, as do chats the token has no cursor
+/// for. Cursors of chats that are not subscribed are dropped.
+pub fn decode_cursors(token: Option<&str>, chat_ids: &[String]) -> Result<ChatCursors, Status> {
+    let mut decoded = match token {
+        Some(token) if !token.is_empty() => BASE64
+            .decode(token)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<ChatCursors>(&bytes).ok())
+            .ok_or_else(|| {
+                Status::invalid_argument("Invalid page_token: not a multi-chat cursor")
+            })?,
+        _ => ChatCursors::new(),
+    };
+    Ok(chat_ids
+        .iter()
+        .map(|id| (id.clone(), decoded.remove(id).unwrap_or(0)))
+        .collect())
+}
+
+/// A stream over several chats, run by [`MultiChatStream::run`]
+pub(crate) struct MultiChatStream {
+    pub repo: Arc<dyn datastore::Repository>,
+    pub chat_ids: Vec<String>,
+    pub cursors: ChatCursors,
+    pub max_results: usize,
+    pub display_message_policy: DisplayMessagePolicy,
+    pub stream_timeout: Option<Duration>,
+    /// The client's deadline, if it set one
+    pub deadline: Option<tokio::time::Instant>,
+    pub shutdown: CancellationToken,
+    /// Channel the stream is watched as, which sees its own muted messages
+    pub viewer_channel_id: Option<String>,
+    /// Bearer token whose expiry ends the stream
+    pub bearer_token: Option<String>,
+    pub token_validator: Arc<dyn TokenValidator>,
+    /// Injected abort: messages to deliver, then the status to end the stream with
+    pub abort_after: Option<(usize, tonic::Code, String)>,
+    /// Time within which the first response goes out, empty if the chats are not read yet
+    pub first_response_budget: Option<Duration>,
+    /// Messages after which an empty keep-alive response follows
+    pub keepalive_every_n_messages: Option<usize>,
+    /// When the stream was requested, which the first-response budget counts from
+    pub stream_start: tokio::time::Instant,
+    /// Page token held in single-consumer mode, released when the stream ends
+    pub cursor_claim: Option<CursorClaim>,
+}
+
+impl MultiChatStream {
+    /// Send the chats' messages to `tx` until the stream ends
+    ///
+    /// The stream is registered once per chat, so the control API counts and closes it with
+    /// each of them. Chat lifecycles are not reported: an ended chat sends no terminal
+    /// response and the stream stays open for the other chats. After a reseed, each chat
+    /// continues after its last delivered message; chats where that message or the chat
+    /// itself is gone restart from 0 after one gap notice. Token expiry, injected aborts, the
+    /// first-response budget and forced keep-alives apply as they do to single-chat streams,
+    /// counting the messages of all the chats.
+    pub async fn run(
+        mut self,
+        streams: Arc<StreamRegistry>,
+        tx: mpsc::Sender<Result<LiveChatMessageListResponse, Status>>,
+    ) {
+        let _cursor_claim = self.cursor_claim.take();
+        let mut guards: Vec<StreamGuard> =
+            self.chat_ids.iter().map(|id| streams.open(id)).collect();
+        let stream_start = self.stream_start;
+        // Subscribed before the first read so no write is missed
+        let mut changes: Vec<_> = self
+            .chat_ids
+            .iter()
+            .map(|id| self.repo.subscribe(id))
+            .collect();
+        let mut responded = false;
+        // A different generation means all data was replaced since the last read
+        let mut generation = self.repo.generation();
+        // ID of the last message sent per chat, to find each chat's place after a reseed
+        let mut last_delivered: BTreeMap<String, String> = BTreeMap::new();
+        // Messages sent so far, counted towards an injected abort
+        let mut delivered = 0;
+        // Messages sent since the last forced keep-alive
+        let mut since_keepalive = 0;
+
+        let reason = 'stream: loop {
+            if tx.is_closed() {
+                break 'stream CloseReason::ClientDisconnect;
+            }
+            if guards.iter().any(StreamGuard::is_killed) {
+                break 'stream CloseReason::KilledViaControl;
+            }
+            if self.shutdown.is_cancelled() {
+                break 'stream CloseReason::ServerShutdown;
+            }
+            if crate::deadline::passed(self.deadline) {
+                break 'stream CloseReason::DeadlineExceeded;
+            }
+
+            // A token expiring mid-session ends the stream so the client refreshes it
+            if self
+                .bearer_token
+                .as_deref()
+                .is_some_and(|token| self.token_validator.validate(token).is_err())
+            {
+                let status = Status::unauthenticated(crate::INVALID_CREDENTIALS);
+                let reason = crate::close_reason_for(&status);
+                let _ = tx.send(Err(status)).await;
+                break 'stream reason;
+            }
+
+            let current_generation = self.repo.generation();
+            if current_generation != generation {
+                generation = current_generation;
+                match self.reanchor(&mut last_delivered) {
+                    Ok(false) => {}
+                    Ok(true) => {
+                        let response = LiveChatMessageListResponse {
+                            kind: Some("youtube#liveChatMessageListResponse".to_string()),
+                            etag: Some(RESEED_GAP_ETAG.to_string()),
+                            page_info: page_info(0, 0),
+                            next_page_token: Some(encode_cursors(&self.cursors)),
+                            ..Default::default()
+                        };
+                        if tx.send(Ok(response)).await.is_err() {
+                            break 'stream CloseReason::ClientDisconnect;
+                        }
+                        record_response(&mut guards, stream_start);
+                    }
+                    Err(e) => {
+                        let status = status_from_repository_error(&e);
+                        let reason = crate::close_reason_for(&status);
+                        let _ = tx.send(Err(status)).await;
+                        break 'stream reason;
+                    }
+                }
+            }
+
+            // Until the first response, the chats are read on a blocking thread racing the
+            // first-response budget, as for single-chat streams
+            let read = match self.first_response_budget.filter(|_| !responded) {
+                Some(budget) => {
+                    let mut read = tokio::task::spawn_blocking({
+                        let repo = Arc::clone(&self.repo);
+                        let chat_ids = self.chat_ids.clone();
+                        let cursors = self.cursors.clone();
+                        let viewer_channel_id = self.viewer_channel_id.clone();
+                        move || {
+                            read_pending(
+                                repo.as_ref(),
+                                &chat_ids,
+                                &cursors,
+                                viewer_channel_id.as_deref(),
+                            )
+                        }
+                    });
+                    let read = tokio::select! {
+                        read = &mut read => read,
+                        _ = tokio::time::sleep_until(stream_start + budget) => {
+                            if !self.send(&tx, Vec::new(), 0).await {
+                                break 'stream CloseReason::ClientDisconnect;
+                            }
+                            for guard in &mut guards {
+                                guard.record_slow_start(stream_start.elapsed());
+                            }
+                            responded = true;
+                            tracing::warn!(
+                                budget_ms = budget.as_millis() as u64,
+                                "Stream started slowly: chats not read within {}ms",
+                                budget.as_millis()
+                            );
+                            read.await
+                        }
+                    };
+                    read.unwrap_or_else(|e| Err(datastore::RepositoryError::Backend(e.to_string())))
+                }
+                None => read_pending(
+                    self.repo.as_ref(),
+                    &self.chat_ids,
+                    &self.cursors,
+                    self.viewer_channel_id.as_deref(),
+                ),
+            };
+            let (mut pending, total_results) = match read {
+                Ok(read) => read,
+                Err(e) => {
+                    let status = status_from_repository_error(&e);
+                    let reason = crate::close_reason_for(&status);
+                    let _ = tx.send(Err(status)).await;
+                    break 'stream reason;
+                }
+            };
+            // Read while the data was replaced: positions may not match, so anchor first
+            if self.repo.generation() != generation {
+                continue 'stream;
+            }
+
+            // An injected abort cuts the backlog at its message count
+            let limit = self
+                .abort_after
+                .as_ref()
+                .map_or(usize::MAX, |(limit, _, _)| *limit);
+            let mut items = Vec::new();
+            // Chat and ID of each item, only collected while a chat is audited
+            let auditing = streams.audits().any_enabled();
+            let mut audited = Vec::new();
+            while delivered < limit
+                && let Some((position, msg)) = next_by_publish_time(&mut pending)
+            {
+                self.cursors.insert(msg.live_chat_id.clone(), position + 1);
+                last_delivered.insert(msg.live_chat_id.clone(), msg.id.clone());
+                items.push(message_to_proto(
+                    position,
+                    &msg,
+                    self.display_message_policy,
+                ));
+                delivered += 1;
+                if auditing {
+                    audited.push((msg.live_chat_id.clone(), msg.id.clone()));
+                }
+                // Batches end where a forced keep-alive is due
+                let keepalive_due = self
+                    .keepalive_every_n_messages
+                    .is_some_and(|n| since_keepalive + items.len() >= n);
+                if items.len() == self.max_results || keepalive_due {
+                    since_keepalive += items.len();
+                    if !self
+                        .send(&tx, std::mem::take(&mut items), total_results)
+                        .await
+                    {
+                        break 'stream CloseReason::ClientDisconnect;
+                    }
+                    record_deliveries(&guards, &self.chat_ids, std::mem::take(&mut audited));
+                    record_response(&mut guards, stream_start);
+                    responded = true;
+                    if keepalive_due {
+                        if !self.send(&tx, Vec::new(), total_results).await {
+                            break 'stream CloseReason::ClientDisconnect;
+                        }
+                        since_keepalive = 0;
+                    }
+                }
+            }
+            // The rest of the messages
+            if !items.is_empty() {
+                since_keepalive += items.len();
+                if !self.send(&tx, items, total_results).await {
+                    break 'stream CloseReason::ClientDisconnect;
+                }
+                record_deliveries(&guards, &self.chat_ids, audited);
+                record_response(&mut guards, stream_start);
+                responded = true;
+            }
+
+            if let Some((limit, code, message)) = &self.abort_after
+                && delivered >= *limit
+            {
+                let status = Status::new(*code, message.clone());
+                let reason = crate::close_reason_for(&status);
+                let _ = tx.send(Err(status)).await;
+                break 'stream reason;
+            }
+
+            // An empty first response shows the stream is open
+            if !responded {
+                if !self.send(&tx, Vec::new(), total_results).await {
+                    break 'stream CloseReason::ClientDisconnect;
+                }
+                record_response(&mut guards, stream_start);
+                responded = true;
+            }
+
+            if self
+                .stream_timeout
+                .is_some_and(|timeout| stream_start.elapsed() >= timeout)
+            {
+                break 'stream CloseReason::Timeout;
+            }
+
+            // Wait for the next write to any of the chats
+            let changed = futures::future::select_all(
+                changes
+                    .iter_mut()
+                    .map(|changes| Box::pin(changes.changed())),
+            );
+            tokio::select! {
+                (changed, _, _) = changed => {
+                    if changed.is_err() {
+                        // The repository does not notify: fall back to polling
+                        tokio::time::sleep(IDLE_RECHECK_INTERVAL).await;
+                    }
+                }
+                _ = tokio::time::sleep(IDLE_RECHECK_INTERVAL) => {}
+                _ = self.shutdown.cancelled() => {}
+                _ = crate::deadline::sleep_until(self.deadline) => {}
+            }
+        };
+
+        tracing::info!(
+            reason = reason.label(),
+            "Multi-chat stream closed: {}",
+            reason.label()
+        );
+        for guard in guards {
+            guard.close(reason.clone());
+        }
+    }
+
+    /// Move every cursor into the replaced data, true if any chat restarts from 0
+    fn reanchor(
+        &mut self,
+        last_delivered: &mut BTreeMap<String, String>,
+    ) -> datastore::RepositoryResult<bool> {
+        let mut restarted = false;
+        for id in &self.chat_ids {
+            let last = last_delivered.get(id).map(String::as_str);
+            let index = match reseed::reanchor(self.repo.as_ref(), id, self.cursors[id], last)? {
+                reseed::Anchor::Resume(index) => index,
+                reseed::Anchor::Restart => {
+                    last_delivered.remove(id);
+                    restarted = true;
+                    0
+                }
+                // Multi-chat streams do not end with a chat; it starts over if it comes back
+                reseed::Anchor::Removed => {
+                    last_delivered.remove(id);
+                    restarted |= self.cursors[id] > 0;
+                    0
+                }
+            };
+            self.cursors.insert(id.clone(), index);
+        }
+        Ok(restarted)
+    }
+
+    /// Send a response with `items`, whose token resumes every chat after them
+    async fn send(
+        &self,
+        tx: &mpsc::Sender<Result<LiveChatMessageListResponse, Status>>,
+        items: Vec<proto::LiveChatMessage>,
+        total_results: usize,
+    ) -> bool {
+        let delivered: usize = self.cursors.values().sum();
+        let response = LiveChatMessageListResponse {
+            kind: Some("youtube#liveChatMessageListResponse".to_string()),
+            etag: Some(format!("etag-multi-{delivered}")),
+            page_info: page_info(total_results, items.len()),
+            items,
+            next_page_token: Some(encode_cursors(&self.cursors)),
+            ..Default::default()
+        };
+        tx.send(Ok(response)).await.is_ok()
+    }
+}
+
+/// Unread messages of each chat from `cursors`, and the number of stored messages across the
+/// chats
+#[allow(clippy::type_complexity)]
+fn read_pending(
+    repo: &dyn datastore::Repository,
+    chat_ids: &[String],
+    cursors: &ChatCursors,
+    viewer_channel_id: Option<&str>,
+) -> datastore::RepositoryResult<(Vec<VecDeque<(usize, domain::LiveChatMessage)>>, usize)> {
+    let now = clock::system_clock().now();
+    let mut pending = Vec::with_capacity(chat_ids.len());
+    let mut total_results = 0;
+    for id in chat_ids {
+        let chat = repo.get_effective_live_chat(id)?;
+        let mut messages = repo.get_chat_messages_from(id, cursors[id])?;
+        // Messages past the chat's history retention are skipped like deleted ones
+        if let Some(chat) = &chat {
+            messages.retain(|(_, msg)| chat.serves(msg, now));
+        }
+        // So are messages of muted authors, unless the viewer is the author
+        messages.retain(|(_, msg)| msg.visible_to(viewer_channel_id));
+        pending.push(messages.into());
+        total_results += repo.count_chat_messages(id)?;
+    }
+    Ok((pending, total_results))
+}
+
+/// Record a response with every chat's guard
+fn record_response(guards: &mut [StreamGuard], stream_start: tokio::time::Instant) {
+    for guard in guards {
+        guard.record_response(stream_start.elapsed());
+    }
+}
+
+/// Take the earliest published message at the front of the chats' queues
+/// Ties go to the chat listed first; each chat's own order is kept
+fn next_by_publish_time(
+    pending: &mut [VecDeque<(usize, domain::LiveChatMessage)>],
+) -> Option<(usize, domain::LiveChatMessage)> {
+    pending
+        .iter_mut()
+        .filter(|queue| !queue.is_empty())
+        .min_by_key(|queue| queue[0].1.published_at)?
+        .pop_front()
+}
+
+/// Record sent messages with the guard of their chat; `guards` are in the order of `chat_ids`
+fn record_deliveries(guards: &[StreamGuard], chat_ids: &[String], sent: Vec<(String, String)>) {
+    for (chat_id, message_id) in &sent {
+        if let Some(guard) = chat_ids
+            .iter()
+            .position(|id| id == chat_id)
+            .map(|index| &guards[index])
+        {
+            guard.record_delivery([message_id.as_str()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LiveChatService;
+    use crate::proto::LiveChatMessageListRequest;
+    use crate::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn test_requested_needs_the_metadata_flag() {
+        let mut metadata = MetadataMap::new();
+        assert!(!requested(&metadata));
+        metadata.insert(MULTI_CHAT_HEADER, "false".parse().unwrap());
+        assert!(!requested(&metadata));
+        metadata.insert(MULTI_CHAT_HEADER, "TRUE".parse().unwrap());
+        assert!(requested(&metadata));
+    }
+
+    #[test]
+    fn test_parse_chat_ids() {
+        assert_eq!(parse_chat_ids("a, b,a").unwrap(), ["a", "b"]);
+        assert_eq!(parse_chat_ids("a").unwrap(), ["a"]);
+        assert!(parse_chat_ids("a,,b").is_err());
+        let too_many = (0..=MAX_MULTI_CHATS)
+            .map(|i| format!("chat-{i}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert!(parse_chat_ids(&too_many).is_err());
+    }
+
+    #[test]
+    fn test_cursors_round_trip_through_page_tokens() {
+        let chat_ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let cursors = ChatCursors::from([
+            ("a".to_string(), 3),
+            ("b".to_string(), 0),
+            ("gone".to_string(), 7),
+        ]);
+        let token = encode_cursors(&cursors);
+        let decoded = decode_cursors(Some(&token), &chat_ids).unwrap();
+        assert_eq!(
+            decoded,
+            ChatCursors::from([
+                ("a".to_string(), 3),
+                ("b".to_string(), 0),
+                ("c".to_string(), 0),
+            ])
+        );
+        assert_eq!(decode_cursors(None, &chat_ids).unwrap()["a"], 0);
+        // Single-chat tokens are not multi-chat cursors
+        let single = crate::encode_page_token(3);
+        assert_eq!(
+            decode_cursors(Some(&single), &chat_ids).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_interleaves_chats_and_resumes_every_cursor() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let template = repo.get_chat_messages("test-chat-id").unwrap()[0].clone();
+        let started = chrono::Utc::now();
+        let mut published = 0;
+        let mut add = |id: &str, chat: &str| {
+            let mut message = template.clone();
+            message.id = id.to_string();
+            message.live_chat_id = chat.to_string();
+            message.published_at = started + chrono::Duration::seconds(published);
+            published += 1;
+            repo.add_chat_message(message).unwrap();
+        };
+        for round in 1..=2 {
+            for chat in ["multi-a", "multi-b", "multi-c"] {
+                add(&format!("{chat}-{round}"), chat);
+            }
+        }
+        let service = LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(crate::IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let open = |page_token: Option<String>, multi_chat: bool| {
+            let service = service.clone();
+            async move {
+                let mut request = tonic::Request::new(LiveChatMessageListRequest {
+                    live_chat_id: Some("multi-a,multi-b,multi-c".to_string()),
+                    max_results: Some(4),
+                    page_token,
+                    ..Default::default()
+                });
+                if multi_chat {
+                    request
+                        .metadata_mut()
+                        .insert(MULTI_CHAT_HEADER, "true".parse().unwrap());
+                }
+                service
+                    .stream_list(request)
+                    .await
+                    .expect("Stream should open")
+                    .into_inner()
+            }
+        };
+        let items = |response: &LiveChatMessageListResponse| {
+            response
+                .items
+                .iter()
+                .map(|item| {
+                    let snippet = item.snippet.as_ref().unwrap();
+                    assert!(
+                        item.id
+                            .as_deref()
+                            .unwrap()
+                            .starts_with(snippet.live_chat_id.as_deref().unwrap())
+                    );
+                    item.id.clone().unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Without the flag the list is a single unknown chat, as in the real API
+        let status = service
+            .stream_list(tonic::Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some("multi-a,multi-b,multi-c".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect_err("Unknown chat should fail the call");
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let mut stream = open(None, true).await;
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            items(&first),
+            ["multi-a-1", "multi-b-1", "multi-c-1", "multi-a-2"]
+        );
+        assert_eq!(first.page_info.unwrap().total_results, Some(6));
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!(items(&second), ["multi-b-2", "multi-c-2"]);
+
+        // New messages of any chat reach the open stream in publish order
+        add("multi-c-3", "multi-c");
+        add("multi-a-3", "multi-a");
+        let third = stream.next().await.unwrap().unwrap();
+        assert_eq!(items(&third), ["multi-c-3", "multi-a-3"]);
+        drop(stream);
+
+        // Resuming from the first token continues every chat from its own cursor
+        let mut resumed = open(first.next_page_token.clone(), true).await;
+        let response = resumed.next().await.unwrap().unwrap();
+        assert_eq!(
+            items(&response),
+            ["multi-b-2", "multi-c-2", "multi-c-3", "multi-a-3"]
+        );
+
+        // Resuming from the last token waits for new messages
+        let mut caught_up = open(third.next_page_token.clone(), true).await;
+        assert!(caught_up.next().await.unwrap().unwrap().items.is_empty());
+    }
+
+    /// Service over the default data, whose two test chats have five messages each
+    fn service() -> LiveChatService {
+        LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(crate::IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        )
+    }
+
+    /// Open a multi-chat stream of both test chats
+    async fn open(
+        service: &LiveChatService,
+        max_results: u32,
+        page_token: Option<String>,
+    ) -> Result<<LiveChatService as V3DataLiveChatMessageService>::StreamListStream, Status> {
+        let mut request = tonic::Request::new(LiveChatMessageListRequest {
+            live_chat_id: Some("test-chat-id,live-chat-id-1".to_string()),
+            max_results: Some(max_results),
+            page_token,
+            ..Default::default()
+        });
+        request
+            .metadata_mut()
+            .insert(MULTI_CHAT_HEADER, "true".parse().unwrap());
+        service
+            .stream_list(request)
+            .await
+            .map(tonic::Response::into_inner)
+    }
+
+    #[tokio::test]
+    async fn test_injected_abort_counts_messages_of_all_chats() {
+        let faults = Arc::new(domain::FaultConfig::default());
+        faults.set(domain::Fault {
+            target: domain::FaultTarget::LiveChatStreamList,
+            http_status: 500,
+            reason: "backendError".to_string(),
+            message: "Injected".to_string(),
+            grpc_code: "UNAVAILABLE".to_string(),
+            probability: None,
+            remaining: Some(1),
+            after_messages: Some(3),
+        });
+        let service = service().with_faults(faults);
+
+        // Aborting after three messages cuts the second batch short
+        let responses: Vec<_> = open(&service, 2, None)
+            .await
+            .expect("Stream should open")
+            .take(3)
+            .collect()
+            .await;
+        let items: Vec<_> = responses[..2]
+            .iter()
+            .map(|response| response.as_ref().expect("Stream response").items.len())
+            .collect();
+        assert_eq!(items, [2, 1]);
+        let status = responses[2].as_ref().expect_err("Stream should abort");
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(status.message(), "Injected");
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_follows_every_n_messages_of_all_chats() {
+        let service = service().with_keepalive_every_n_messages(Some(3));
+        let responses: Vec<_> = open(&service, 4, None)
+            .await
+            .expect("Stream should open")
+            .take(7)
+            .map(|response| response.expect("Stream response"))
+            .collect()
+            .await;
+        let items: Vec<_> = responses
+            .iter()
+            .map(|response| response.items.len())
+            .collect();
+        assert_eq!(items, [3, 0, 3, 0, 3, 0, 1]);
+        // The keep-alive carries the token of the data response before it
+        assert_eq!(responses[1].next_page_token, responses[0].next_page_token);
+    }
+
+    #[tokio::test]
+    async fn test_slow_read_sends_empty_first_response_within_budget() {
+        let registry = Arc::new(StreamRegistry::default());
+        let slow = Arc::new(datastore::SlowRepository {
+            inner: Arc::new(datastore::InMemoryRepository::new()),
+            delay: Duration::from_millis(200),
+        });
+        let service = LiveChatService::new(
+            slow,
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(crate::IssuedTokenValidator::default()),
+            Arc::clone(&registry),
+        )
+        .with_first_response_budget(Some(Duration::from_millis(50)));
+
+        // The existence checks and the first read share the budget
+        let opened_at = tokio::time::Instant::now();
+        let mut stream = open(&service, 500, None).await.expect("Stream should open");
+        let first = stream.next().await.expect("First response").unwrap();
+        assert!(
+            opened_at.elapsed() < Duration::from_millis(200),
+            "First response took {:?}",
+            opened_at.elapsed()
+        );
+        assert!(first.items.is_empty());
+        // The messages of both chats follow once read
+        let history = stream.next().await.expect("History").unwrap();
+        assert_eq!(history.items.len(), 10);
+        drop(stream);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while registry.timeline().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("Stream should close");
+        assert!(registry.timeline().iter().all(|closed| closed.slow_start));
+    }
+
+    #[tokio::test]
+    async fn test_single_consumer_rejects_second_stream_on_same_token() {
+        let service = service().with_single_consumer(true);
+        let mut first = open(&service, 500, None).await.expect("Stream should open");
+        let token = first.next().await.unwrap().unwrap().next_page_token;
+        drop(first);
+
+        let _resumed = open(&service, 500, token.clone())
+            .await
+            .expect("First consumer");
+        let status = open(&service, 500, token)
+            .await
+            .expect_err("Second consumer should be rejected");
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn test_server_tracked_cursors_are_not_supported() {
+        let service = LiveChatService::new(
+            Arc::new(datastore::InMemoryRepository::new()),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            Some(Arc::new(crate::CursorStore::new(
+                Duration::from_secs(30),
+                clock::system_clock(),
+            ))),
+            Arc::new(crate::IssuedTokenValidator::default()),
+            Arc::new(StreamRegistry::default()),
+        );
+        let status = open(&service, 500, None)
+            .await
+            .expect_err("Stream should be rejected");
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+}
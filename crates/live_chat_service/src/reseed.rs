@@ -0,0 +1,192 @@
+//! Open streams across a reseed
+//!
+//! Positions from before a reseed do not apply to the new data. A stream looks up the last
+//! message it delivered in the new data and continues after it. When that message is gone,
+//! the stream restarts from the start of the chat after a gap notice, and when the chat is
+//! gone, the stream ends like an ended chat.
+
+use datastore::{Repository, RepositoryResult};
+
+/// Etag of the empty response telling the client that its stream restarted from the start
+/// of the chat after a reseed, so messages may be repeated or missing
+pub const RESEED_GAP_ETAG: &str = "etag-reseeded";
+
+/// Where a stream continues in the new data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    /// Continue at this position
+    Resume(usize),
+    /// Restart at position 0 after a gap notice
+    Restart,
+    /// The chat no longer exists
+    Removed,
+}
+
+/// Anchor a stream of `live_chat_id` that was at `position` and last delivered
+/// `last_delivered` before the data was replaced
+pub(crate) fn reanchor(
+    repo: &dyn Repository,
+    live_chat_id: &str,
+    position: usize,
+    last_delivered: Option<&str>,
+) -> RepositoryResult<Anchor> {
+    if !repo.live_chat_exists(live_chat_id)? {
+        return Ok(Anchor::Removed);
+    }
+    let Some(last_delivered) = last_delivered else {
+        // A stream that delivered nothing from the start of the chat cannot miss anything
+        return Ok(if position == 0 {
+            Anchor::Resume(0)
+        } else {
+            Anchor::Restart
+        });
+    };
+    Ok(repo
+        .get_chat_messages_from(live_chat_id, 0)?
+        .iter()
+        .rev()
+        .find(|(_, message)| message.id == last_delivered)
+        .map_or(Anchor::Restart, |(position, _)| {
+            Anchor::Resume(position + 1)
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datastore::{InMemoryRepository, SeedData};
+
+    /// Seed with the messages, as (chat, message ID), and a live video for each chat
+    fn seed(messages: &[(&str, &str)]) -> SeedData {
+        let mut chats: Vec<&str> = messages.iter().map(|(chat, _)| *chat).collect();
+        chats.dedup();
+        let json = serde_json::json!({
+            "videos": chats
+                .iter()
+                .map(|chat| serde_json::json!({
+                    "id": format!("video-{chat}"),
+                    "channel_id": "channel",
+                    "title": "Live",
+                    "description": "",
+                    "channel_title": "Channel",
+                    "published_at": "2024-01-01T00:00:00Z",
+                    "live_chat_id": chat,
+                    "actual_start_time": "2024-01-01T00:00:00Z",
+                }))
+                .collect::<Vec<_>>(),
+            "chat_messages": messages
+                .iter()
+                .map(|(chat, id)| serde_json::json!({
+                    "id": id,
+                    "live_chat_id": chat,
+                    "author_channel_id": "channel",
+                    "author_display_name": "Author",
+                    "message_text": "Hello",
+                    "published_at": "2024-01-01T00:00:00Z",
+                    "is_verified": false,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        SeedData::from_json(&json.to_string()).expect("Valid seed")
+    }
+
+    #[test]
+    fn test_reanchor_after_reseed() {
+        let repo = InMemoryRepository::from_seed(seed(&[
+            ("kept", "new-0"),
+            ("kept", "shared"),
+            ("kept", "new-2"),
+        ]))
+        .unwrap();
+
+        let reanchor = |chat, position, last| reanchor(&repo, chat, position, last).unwrap();
+        assert_eq!(reanchor("kept", 5, Some("shared")), Anchor::Resume(2));
+        assert_eq!(reanchor("kept", 5, Some("gone")), Anchor::Restart);
+        assert_eq!(reanchor("kept", 0, None), Anchor::Resume(0));
+        assert_eq!(reanchor("kept", 3, None), Anchor::Restart);
+        assert_eq!(reanchor("removed", 3, Some("shared")), Anchor::Removed);
+    }
+
+    #[tokio::test]
+    async fn test_open_streams_across_a_reseed() {
+        use crate::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+        use crate::proto::{LiveChatMessageListRequest, LiveChatMessageListResponse};
+        use crate::{IssuedTokenValidator, LiveChatService};
+        use domain::{CloseReason, DisplayMessagePolicy, StreamRegistry};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use tokio_stream::StreamExt;
+        use tokio_stream::wrappers::ReceiverStream;
+        use tonic::{Request, Status};
+
+        let repo: Arc<dyn Repository> = Arc::new(
+            InMemoryRepository::from_seed(seed(&[
+                ("kept", "kept-0"),
+                ("kept", "kept-1"),
+                ("restarted", "restarted-0"),
+                ("removed", "removed-0"),
+            ]))
+            .unwrap(),
+        );
+        let registry = Arc::new(StreamRegistry::default());
+        let service = LiveChatService::new(
+            Arc::clone(&repo),
+            None,
+            None,
+            DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(IssuedTokenValidator::default()),
+            Arc::clone(&registry),
+        );
+        let open = |live_chat_id: &str| {
+            service.stream_list(Request::new(LiveChatMessageListRequest {
+                live_chat_id: Some(live_chat_id.to_string()),
+                ..Default::default()
+            }))
+        };
+        let mut kept = open("kept").await.unwrap().into_inner();
+        let mut restarted = open("restarted").await.unwrap().into_inner();
+        let mut removed = open("removed").await.unwrap().into_inner();
+        for stream in [&mut kept, &mut restarted, &mut removed] {
+            let response = stream.next().await.unwrap().unwrap();
+            assert!(!response.items.is_empty());
+        }
+
+        repo.reseed(seed(&[
+            ("kept", "new-0"),
+            ("kept", "kept-1"),
+            ("kept", "new-2"),
+            ("restarted", "new-restarted-0"),
+        ]))
+        .unwrap();
+
+        async fn next(
+            stream: &mut ReceiverStream<Result<LiveChatMessageListResponse, Status>>,
+        ) -> Option<Result<LiveChatMessageListResponse, Status>> {
+            tokio::time::timeout(Duration::from_secs(5), stream.next())
+                .await
+                .expect("Response after the reseed")
+        }
+
+        // The kept chat continues after its last delivered message, in the new data
+        let response = next(&mut kept).await.unwrap().unwrap();
+        let ids: Vec<_> = response.items.iter().map(|item| item.id()).collect();
+        assert_eq!(ids, ["new-2"]);
+
+        // The last delivered message is gone: a gap notice, then the chat from its start
+        let response = next(&mut restarted).await.unwrap().unwrap();
+        assert_eq!(response.etag(), RESEED_GAP_ETAG);
+        assert!(response.items.is_empty());
+        let response = next(&mut restarted).await.unwrap().unwrap();
+        let ids: Vec<_> = response.items.iter().map(|item| item.id()).collect();
+        assert_eq!(ids, ["new-restarted-0"]);
+
+        // The removed chat ends like an ended chat
+        let response = next(&mut removed).await.unwrap().unwrap();
+        assert!(response.offline_at.is_some());
+        assert!(next(&mut removed).await.is_none());
+        let closed = registry.timeline();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].reason, CloseReason::ChatEnded);
+    }
+}
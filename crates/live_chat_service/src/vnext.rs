@@ -0,0 +1,296 @@
+//! Experimental `youtube.api.vnext` live chat service
+//!
+//! A thin adapter over the v3 streaming core: requests are translated to v3,
+//! streamed by [`LiveChatService`], and each response is mapped to the vNext types.
+
+use crate::LiveChatService;
+use crate::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageService;
+use crate::proto::vnext::live_chat_message_service_server::{
+    LiveChatMessageService, LiveChatMessageServiceServer,
+};
+use crate::proto::{self, vnext};
+use std::pin::Pin;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+pub struct VNextLiveChatService {
+    core: LiveChatService,
+}
+
+impl VNextLiveChatService {
+    pub fn new(core: LiveChatService) -> Self {
+        Self { core }
+    }
+}
+
+fn request_to_v3(request: vnext::LiveChatMessageListRequest) -> proto::LiveChatMessageListRequest {
+    proto::LiveChatMessageListRequest {
+        live_chat_id: request.live_chat_id,
+        page_token: request.page_token,
+        part: request.part,
+        max_results: request.max_results,
+        ..Default::default()
+    }
+}
+
+fn response_from_v3(
+    response: proto::LiveChatMessageListResponse,
+    polling_interval_millis: u64,
+) -> vnext::LiveChatMessageListResponse {
+    let items = response
+        .items
+        .into_iter()
+        .map(|item| {
+            let snippet = item.snippet.unwrap_or_default();
+            let mut deleted_message_id = None;
+            let message_text = match snippet.displayed_content {
+                Some(proto::live_chat_message_snippet::DisplayedContent::TextMessageDetails(
+                    details,
+                )) => details.message_text,
+                // A messageDeletedEvent tombstone names the removed message instead of a text
+                Some(
+                    proto::live_chat_message_snippet::DisplayedContent::MessageDeletedDetails(
+                        details,
+                    ),
+                ) => {
+                    deleted_message_id = details.deleted_message_id;
+                    None
+                }
+                // vNext has no paid message fields yet; the comment or sticker text stands in
+                Some(proto::live_chat_message_snippet::DisplayedContent::SuperChatDetails(
+                    details,
+                )) => details.user_comment,
+                Some(proto::live_chat_message_snippet::DisplayedContent::SuperStickerDetails(
+                    details,
+                )) => details
+                    .super_sticker_metadata
+                    .and_then(|metadata| metadata.alt_text),
+                _ => None,
+            };
+            let author = item.author_details.map(|author| vnext::Author {
+                channel_id: author.channel_id,
+                display_name: author.display_name,
+                is_verified: author.is_verified,
+                is_chat_owner: author.is_chat_owner,
+                is_chat_sponsor: author.is_chat_sponsor,
+                is_chat_moderator: author.is_chat_moderator,
+            });
+
+            vnext::LiveChatMessage {
+                id: item.id,
+                live_chat_id: snippet.live_chat_id,
+                published_at: snippet.published_at,
+                message_text,
+                display_message: snippet.display_message,
+                author,
+                // v3 items carry their position in the etag, gaps from deletions included
+                sequence_number: item
+                    .etag
+                    .as_deref()
+                    .and_then(crate::message_position)
+                    .map(|position| position as u64),
+                deleted_message_id,
+            }
+        })
+        .collect();
+
+    vnext::LiveChatMessageListResponse {
+        kind: response.kind,
+        etag: response.etag,
+        next_page_token: response.next_page_token,
+        items,
+        proto_version: Some(vnext::PACKAGE.to_string()),
+        polling_interval_millis: Some(polling_interval_millis),
+        offline_at: response.offline_at,
+        page_info: response.page_info.map(|page_info| vnext::PageInfo {
+            total_results: page_info.total_results,
+            results_per_page: page_info.results_per_page,
+        }),
+    }
+}
+
+#[tonic::async_trait]
+impl LiveChatMessageService for VNextLiveChatService {
+    type StreamListStream =
+        Pin<Box<dyn Stream<Item = Result<vnext::LiveChatMessageListResponse, Status>> + Send>>;
+
+    async fn stream_list(
+        &self,
+        request: Request<vnext::LiveChatMessageListRequest>,
+    ) -> Result<Response<Self::StreamListStream>, Status> {
+        let (metadata, extensions, message) = request.into_parts();
+        let v3_request = Request::from_parts(metadata, extensions, request_to_v3(message));
+
+        let v3_stream = self.core.stream_list(v3_request).await?.into_inner();
+        let polling_interval_millis = self.core.polling_interval_millis();
+        let stream = v3_stream.map(move |response| {
+            response.map(|response| response_from_v3(response, polling_interval_millis))
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+// Public function to create the experimental server
+pub fn create_service(core: LiveChatService) -> LiveChatMessageServiceServer<VNextLiveChatService> {
+    LiveChatMessageServiceServer::new(VNextLiveChatService::new(core))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn core() -> LiveChatService {
+        core_with(Arc::new(datastore::InMemoryRepository::new()))
+    }
+
+    fn core_with(repo: Arc<dyn datastore::Repository>) -> LiveChatService {
+        LiveChatService::new(
+            repo,
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(crate::IssuedTokenValidator::default()),
+            Arc::new(domain::StreamRegistry::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_vnext_stream_matches_v3_semantics() {
+        let count = 5;
+
+        let v3_service = core();
+        let v3_items: Vec<proto::LiveChatMessage> = v3_service
+            .stream_list(Request::new(proto::LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                max_results: Some(1),
+                ..Default::default()
+            }))
+            .await
+            .expect("v3 stream should open")
+            .into_inner()
+            .take(count)
+            .map(|response| response.expect("v3 response").items)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let vnext_service = VNextLiveChatService::new(core().with_polling_interval_millis(2500));
+        let vnext_responses: Vec<vnext::LiveChatMessageListResponse> = vnext_service
+            .stream_list(Request::new(vnext::LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                max_results: Some(1),
+                ..Default::default()
+            }))
+            .await
+            .expect("vnext stream should open")
+            .into_inner()
+            .take(count)
+            .map(|response| response.expect("vnext response"))
+            .collect()
+            .await;
+
+        assert_eq!(v3_items.len(), count);
+        for (i, (v3_item, response)) in v3_items.iter().zip(&vnext_responses).enumerate() {
+            assert_eq!(response.proto_version.as_deref(), Some(vnext::PACKAGE));
+            assert_eq!(response.polling_interval_millis, Some(2500));
+            let item = &response.items[0];
+            let snippet = v3_item.snippet.as_ref().unwrap();
+            let author = v3_item.author_details.as_ref().unwrap();
+
+            assert_eq!(item.id, v3_item.id);
+            assert_eq!(item.sequence_number, Some(i as u64));
+            assert_eq!(item.live_chat_id, snippet.live_chat_id);
+            assert_eq!(item.display_message, snippet.display_message);
+            assert_eq!(item.published_at, snippet.published_at);
+            let vnext_author = item.author.as_ref().unwrap();
+            assert_eq!(vnext_author.display_name, author.display_name);
+            assert_eq!(vnext_author.is_verified, author.is_verified);
+        }
+    }
+
+    async fn open(
+        service: &VNextLiveChatService,
+    ) -> <VNextLiveChatService as LiveChatMessageService>::StreamListStream {
+        service
+            .stream_list(Request::new(vnext::LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .expect("vnext stream should open")
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_vnext_reports_the_end_of_the_chat() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let mut chat = domain::LiveChat::active("test-chat-id");
+        chat.transition(domain::LiveChatState::Ended, chrono::Utc::now())
+            .unwrap();
+        repo.save_live_chat(chat).unwrap();
+
+        let responses: Vec<_> = open(&VNextLiveChatService::new(core_with(repo)))
+            .await
+            .map(|response| response.expect("vnext response"))
+            .collect()
+            .await;
+        let backlog = responses.first().expect("backlog response");
+        assert_eq!(backlog.items.len(), 5);
+        let page_info = backlog.page_info.as_ref().expect("page info");
+        assert_eq!(page_info.total_results, Some(5));
+
+        let terminal = responses.last().unwrap();
+        assert!(terminal.items.is_empty());
+        assert!(terminal.offline_at.is_some());
+        assert_eq!(terminal.next_page_token, None);
+    }
+
+    #[tokio::test]
+    async fn test_vnext_marks_deleted_message_events() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        repo.delete_chat_message("test-msg-id-2", chrono::Utc::now())
+            .unwrap();
+
+        let mut stream = open(&VNextLiveChatService::new(core_with(repo))).await;
+        let response = stream.next().await.unwrap().expect("vnext response");
+        let tombstones: Vec<_> = response
+            .items
+            .iter()
+            .filter(|item| item.deleted_message_id.is_some())
+            .collect();
+        assert_eq!(tombstones.len(), 1);
+        let tombstone = tombstones[0];
+        assert_eq!(tombstone.id.as_deref(), Some("test-msg-id-2-deleted"));
+        assert_eq!(
+            tombstone.deleted_message_id.as_deref(),
+            Some("test-msg-id-2")
+        );
+        assert_eq!(tombstone.message_text, None);
+        assert!(
+            response
+                .items
+                .iter()
+                .all(|item| item.id.as_deref() != Some("test-msg-id-2"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vnext_rejects_invalid_page_token_like_v3() {
+        let service = VNextLiveChatService::new(core());
+        let result = service
+            .stream_list(Request::new(vnext::LiveChatMessageListRequest {
+                live_chat_id: Some("test-chat-id".to_string()),
+                page_token: Some("not base64!".to_string()),
+                ..Default::default()
+            }))
+            .await;
+
+        let status = result.err().expect("Invalid token should be rejected");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
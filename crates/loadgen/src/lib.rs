@@ -0,0 +1,466 @@
+//! Load generator for the mock
+//!
+//! A run creates live chats, feeds each of them from a control API generator and follows
+//! them with gRPC streams that reconnect at random. Once the run is over the chats end, so
+//! every stream drains to the terminal response, and the run reports what was delivered.
+
+use clap::Parser;
+use mock_client::proto::LiveChatMessageListRequest;
+use mock_client::proto::v3_data_live_chat_message_service_client::V3DataLiveChatMessageServiceClient;
+use mock_client::{ClientError, Control};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::time::{Instant, timeout_at};
+use tokio_stream::StreamExt;
+
+pub mod mock;
+mod report;
+
+pub use report::{Percentiles, Report};
+
+/// Pause before reopening a stream that failed
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Errors kept in the report
+const MAX_ERROR_SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    name = "yt-api-mock-loadgen",
+    about = "Load test a YouTube API mock and report as JSON"
+)]
+pub struct Profile {
+    /// Base URL of the mock's REST server; a mock is started in-process when omitted
+    #[arg(long, env = "MOCK_BASE_URL", requires = "grpc_url")]
+    pub base_url: Option<String>,
+
+    /// URL of the mock's gRPC server
+    #[arg(long, env = "MOCK_GRPC_URL", requires = "base_url")]
+    pub grpc_url: Option<String>,
+
+    /// Live chats to create
+    #[arg(long, default_value_t = 1)]
+    pub chats: usize,
+
+    /// Messages per second generated in each chat
+    #[arg(long, default_value_t = 10.0)]
+    pub rate: f64,
+
+    /// gRPC streams, spread evenly over the chats
+    #[arg(long, default_value_t = 10)]
+    pub streams: usize,
+
+    /// Chance that a stream reconnects after a response carrying messages
+    #[arg(long, default_value_t = 0.0)]
+    pub reconnect_probability: f64,
+
+    /// Seconds to generate messages for
+    #[arg(long, default_value_t = 10)]
+    pub duration: u64,
+
+    /// Seconds the streams get to catch up once the chats end
+    #[arg(long, default_value_t = 10)]
+    pub drain_timeout: u64,
+
+    /// Seed of the reconnect decisions, so runs can be repeated
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+impl Profile {
+    fn validate(&self) -> Result<(), LoadError> {
+        let invalid = |msg: &str| Err(LoadError::InvalidProfile(msg.to_string()));
+        if self.base_url.is_some() != self.grpc_url.is_some() {
+            return invalid("base URL and gRPC URL must be given together");
+        }
+        if self.chats == 0 {
+            return invalid("chats must be at least 1");
+        }
+        if !(self.rate > 0.0 && self.rate <= 1000.0) {
+            return invalid("rate must be above 0 and at most 1000 messages per second");
+        }
+        if !(0.0..=1.0).contains(&self.reconnect_probability) {
+            return invalid("reconnect probability must be between 0 and 1");
+        }
+        Ok(())
+    }
+
+    /// Interval of the generators producing `rate` messages per second
+    fn interval_ms(&self) -> u64 {
+        ((1000.0 / self.rate).round() as u64).max(1)
+    }
+}
+
+/// Errors that stop a run
+#[derive(Debug)]
+pub enum LoadError {
+    InvalidProfile(String),
+    /// The in-process mock could not start
+    Io(std::io::Error),
+    /// The control API failed while setting up or winding down the run
+    Control(ClientError),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::InvalidProfile(msg) => write!(f, "invalid profile: {msg}"),
+            LoadError::Io(e) => write!(f, "failed to start the mock: {e}"),
+            LoadError::Control(e) => write!(f, "control API: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl From<ClientError> for LoadError {
+    fn from(e: ClientError) -> Self {
+        LoadError::Control(e)
+    }
+}
+
+/// SplitMix64, a small generator that gives the same sequence on every platform
+struct Rng(u64);
+
+impl Rng {
+    /// Generator of the `index`th stream of a run
+    fn new(seed: u64, index: u64) -> Self {
+        Self(seed ^ index.wrapping_mul(0xD1B5_4A32_D192_ED03))
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// What one stream saw over the run
+#[derive(Default)]
+struct StreamOutcome {
+    delivered: HashSet<String>,
+    duplicates: u64,
+    reconnects: u64,
+    errors: Vec<String>,
+    first_byte: Vec<Duration>,
+    inter_message: Vec<Duration>,
+    /// Whether the stream received the terminal response of its ended chat
+    completed: bool,
+}
+
+/// Follow a chat until its terminal response or `deadline`
+///
+/// After each response carrying messages the stream is dropped with probability
+/// `reconnect_probability` and reopened from the last page token, like a fetcher would.
+async fn follow(
+    grpc_url: String,
+    live_chat_id: String,
+    reconnect_probability: f64,
+    mut rng: Rng,
+    deadline: Instant,
+) -> StreamOutcome {
+    let mut outcome = StreamOutcome::default();
+    let mut client = match timeout_at(
+        deadline,
+        V3DataLiveChatMessageServiceClient::connect(grpc_url),
+    )
+    .await
+    {
+        Ok(Ok(client)) => client,
+        Ok(Err(e)) => {
+            outcome.errors.push(ClientError::from(e).to_string());
+            return outcome;
+        }
+        Err(_) => return outcome,
+    };
+
+    let mut page_token = None;
+    while !outcome.completed && Instant::now() < deadline {
+        let opened = Instant::now();
+        let request = LiveChatMessageListRequest {
+            live_chat_id: Some(live_chat_id.clone()),
+            page_token: page_token.clone(),
+            ..Default::default()
+        };
+        let mut stream = match timeout_at(deadline, client.stream_list(request)).await {
+            Ok(Ok(response)) => response.into_inner(),
+            Ok(Err(status)) => {
+                outcome.errors.push(ClientError::from(status).to_string());
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+            Err(_) => break,
+        };
+
+        let mut answered = false;
+        let mut last_messages_at = None;
+        loop {
+            let response = match timeout_at(deadline, stream.next()).await {
+                Ok(Some(Ok(response))) => response,
+                Ok(Some(Err(status))) => {
+                    outcome.errors.push(ClientError::from(status).to_string());
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    break;
+                }
+                Ok(None) => {
+                    outcome.errors.push(format!(
+                        "stream of {live_chat_id} closed before the chat ended"
+                    ));
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    break;
+                }
+                Err(_) => return outcome,
+            };
+
+            let now = Instant::now();
+            if !answered {
+                answered = true;
+                outcome.first_byte.push(now - opened);
+            }
+            if !response.items.is_empty() {
+                if let Some(last) = last_messages_at {
+                    outcome.inter_message.push(now - last);
+                }
+                last_messages_at = Some(now);
+                for id in response.items.iter().filter_map(|item| item.id.clone()) {
+                    if !outcome.delivered.insert(id) {
+                        outcome.duplicates += 1;
+                    }
+                }
+            }
+
+            match response.next_page_token {
+                Some(token) => page_token = Some(token),
+                None if response.offline_at.is_some() => {
+                    outcome.completed = true;
+                    break;
+                }
+                None => {}
+            }
+            if !response.items.is_empty() && rng.next_f64() < reconnect_probability {
+                outcome.reconnects += 1;
+                break;
+            }
+        }
+    }
+    outcome
+}
+
+/// Create the chats, generate messages for the run's duration and end the chats
+///
+/// Returns the number of messages in each chat once it ended.
+async fn publish(
+    control: &Control,
+    profile: &Profile,
+    live_chat_ids: &[String],
+) -> Result<HashMap<String, u64>, ClientError> {
+    let mut generator_ids = Vec::new();
+    for live_chat_id in live_chat_ids {
+        let generator = control
+            .post(
+                "/generators",
+                &json!({"liveChatId": live_chat_id, "intervalMs": profile.interval_ms()}),
+            )
+            .await?;
+        generator_ids.push(generator["id"].as_str().unwrap_or_default().to_string());
+    }
+
+    tokio::time::sleep(Duration::from_secs(profile.duration)).await;
+
+    for id in &generator_ids {
+        control.delete(&format!("/generators/{id}")).await?;
+    }
+    for live_chat_id in live_chat_ids {
+        control
+            .post(
+                &format!("/live_chats/{live_chat_id}/transition"),
+                &json!({"state": "ended"}),
+            )
+            .await?;
+    }
+
+    let state = control.get("/state").await?;
+    let counts = state["chats"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|chat| {
+            let live_chat_id = chat["liveChatId"].as_str()?;
+            live_chat_ids.iter().any(|id| id == live_chat_id).then(|| {
+                (
+                    live_chat_id.to_string(),
+                    chat["messageCount"].as_u64().unwrap_or(0),
+                )
+            })
+        })
+        .collect();
+    Ok(counts)
+}
+
+/// Run a load profile against its target mock, or a mock started in-process
+pub async fn run(profile: &Profile) -> Result<Report, LoadError> {
+    profile.validate()?;
+    let in_process = match &profile.base_url {
+        Some(_) => None,
+        None => Some(mock::InProcessMock::start().await?),
+    };
+    let (base_url, grpc_url) = match (&in_process, &profile.base_url, &profile.grpc_url) {
+        (Some(mock), _, _) => (mock.base_url.clone(), mock.grpc_url.clone()),
+        (None, base_url, grpc_url) => (
+            base_url.clone().unwrap_or_default(),
+            grpc_url.clone().unwrap_or_default(),
+        ),
+    };
+    let control = Control::new(&base_url);
+
+    // Fresh chats, so a run against a shared mock does not count other traffic
+    let run_id = uuid::Uuid::new_v4().simple().to_string();
+    let live_chat_ids: Vec<String> = (1..=profile.chats)
+        .map(|i| format!("loadgen-{}-chat-{i}", &run_id[..8]))
+        .collect();
+    for live_chat_id in &live_chat_ids {
+        control
+            .post(
+                "/live_chats",
+                &json!({"id": live_chat_id, "state": "active"}),
+            )
+            .await?;
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(profile.duration + profile.drain_timeout);
+    let streams: Vec<_> = (0..profile.streams)
+        .map(|index| {
+            tokio::spawn(follow(
+                grpc_url.clone(),
+                live_chat_ids[index % live_chat_ids.len()].clone(),
+                profile.reconnect_probability,
+                Rng::new(profile.seed, index as u64),
+                deadline,
+            ))
+        })
+        .collect();
+
+    let published = match publish(&control, profile, &live_chat_ids).await {
+        Ok(published) => published,
+        Err(e) => {
+            for stream in &streams {
+                stream.abort();
+            }
+            return Err(e.into());
+        }
+    };
+
+    let mut report = Report {
+        chats: profile.chats,
+        streams: profile.streams,
+        rate_per_chat: profile.rate,
+        reconnect_probability: profile.reconnect_probability,
+        duration_secs: profile.duration,
+        seed: profile.seed,
+        published: published.values().sum(),
+        expected: 0,
+        delivered: 0,
+        duplicates: 0,
+        missing: 0,
+        complete_delivery: true,
+        reconnects: 0,
+        errors: 0,
+        error_samples: Vec::new(),
+        first_byte_ms: Percentiles::default(),
+        inter_message_ms: Percentiles::default(),
+    };
+    let mut first_byte = Vec::new();
+    let mut inter_message = Vec::new();
+    for (index, stream) in streams.into_iter().enumerate() {
+        let outcome = stream.await.unwrap_or_else(|e| StreamOutcome {
+            errors: vec![format!("stream task failed: {e}")],
+            ..Default::default()
+        });
+        let expected = published
+            .get(&live_chat_ids[index % live_chat_ids.len()])
+            .copied()
+            .unwrap_or(0);
+        let delivered = outcome.delivered.len() as u64;
+
+        report.expected += expected;
+        report.delivered += delivered;
+        report.duplicates += outcome.duplicates;
+        report.missing += expected.saturating_sub(delivered);
+        report.complete_delivery &= outcome.completed && delivered >= expected;
+        report.reconnects += outcome.reconnects;
+        report.errors += outcome.errors.len() as u64;
+        let room = MAX_ERROR_SAMPLES.saturating_sub(report.error_samples.len());
+        report
+            .error_samples
+            .extend(outcome.errors.into_iter().take(room));
+        first_byte.extend(outcome.first_byte);
+        inter_message.extend(outcome.inter_message);
+    }
+    report.first_byte_ms = Percentiles::of(&mut first_byte);
+    report.inter_message_ms = Percentiles::of(&mut inter_message);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> Profile {
+        Profile::try_parse_from(["yt-api-mock-loadgen"]).expect("Valid arguments")
+    }
+
+    #[test]
+    fn test_rng_is_seeded_per_stream() {
+        let draws = |seed, index| {
+            let mut rng = Rng::new(seed, index);
+            (0..100).map(|_| rng.next_f64()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(draws(7, 3), draws(7, 3));
+        assert_ne!(draws(7, 3), draws(7, 4));
+        assert_ne!(draws(7, 3), draws(8, 3));
+        assert!(draws(7, 3).iter().all(|x| (0.0..1.0).contains(x)));
+    }
+
+    #[test]
+    fn test_validate_rejects_unusable_profiles() {
+        assert!(profile().validate().is_ok());
+        assert_eq!(profile().interval_ms(), 100);
+
+        let cases = [
+            Profile {
+                base_url: Some("http://localhost:8080".to_string()),
+                ..profile()
+            },
+            Profile {
+                chats: 0,
+                ..profile()
+            },
+            Profile {
+                rate: 0.0,
+                ..profile()
+            },
+            Profile {
+                reconnect_probability: 1.5,
+                ..profile()
+            },
+        ];
+        for case in cases {
+            assert!(
+                matches!(case.validate(), Err(LoadError::InvalidProfile(_))),
+                "{case:?}"
+            );
+        }
+    }
+}
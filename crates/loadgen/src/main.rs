@@ -0,0 +1,17 @@
+use clap::Parser;
+use loadgen::Profile;
+
+#[tokio::main]
+async fn main() {
+    let profile = Profile::parse();
+    match loadgen::run(&profile).await {
+        Ok(report) => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Report should serialize")
+        ),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
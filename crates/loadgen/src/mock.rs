@@ -0,0 +1,64 @@
+//! Mock served in-process, for runs that do not target a running server
+
+use std::sync::Arc;
+use tonic::transport::server::TcpIncoming;
+
+/// Control API and live chat service on ephemeral loopback ports
+///
+/// Both share an in-memory datastore. The servers stop when the mock is dropped.
+pub struct InProcessMock {
+    pub base_url: String,
+    pub grpc_url: String,
+    tasks: Vec<tokio::task::AbortHandle>,
+}
+
+impl InProcessMock {
+    pub async fn start() -> std::io::Result<Self> {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let streams = Arc::new(domain::StreamRegistry::default());
+
+        let rest_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", rest_listener.local_addr()?);
+        let app = axum::Router::new().nest(
+            "/control",
+            control_service::create_router(
+                Arc::clone(&repo),
+                Arc::clone(&streams),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            ),
+        );
+        let rest = tokio::spawn(async move { axum::serve(rest_listener, app).await });
+
+        let grpc_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let grpc_url = format!("http://{}", grpc_listener.local_addr()?);
+        let service = live_chat_service::create_service(
+            repo,
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            streams,
+        );
+        let grpc = tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpIncoming::from(grpc_listener)),
+        );
+
+        Ok(Self {
+            base_url,
+            grpc_url,
+            tasks: vec![rest.abort_handle(), grpc.abort_handle()],
+        })
+    }
+}
+
+impl Drop for InProcessMock {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Report of a load run, printed as JSON
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Latency percentiles in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p99: f64,
+}
+
+impl Percentiles {
+    /// Nearest-rank percentiles of `samples`; zero without samples
+    pub fn of(samples: &mut [Duration]) -> Self {
+        samples.sort_unstable();
+        let rank = |percent: usize| {
+            let Some(last) = samples.len().checked_sub(1) else {
+                return 0.0;
+            };
+            let index = (samples.len() * percent).div_ceil(100).saturating_sub(1);
+            samples[index.min(last)].as_secs_f64() * 1000.0
+        };
+        Self {
+            p50: rank(50),
+            p99: rank(99),
+        }
+    }
+}
+
+/// Outcome of a load run
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub chats: usize,
+    pub streams: usize,
+    pub rate_per_chat: f64,
+    pub reconnect_probability: f64,
+    pub duration_secs: u64,
+    pub seed: u64,
+    /// Messages in the chats when they ended
+    pub published: u64,
+    /// Messages the streams should have received: each stream gets every message of its chat
+    pub expected: u64,
+    /// Distinct messages the streams received
+    pub delivered: u64,
+    /// Messages a stream received again, e.g. after reconnecting
+    pub duplicates: u64,
+    pub missing: u64,
+    /// Whether every stream received every message of its chat and the end of the chat
+    pub complete_delivery: bool,
+    /// Streams dropped on purpose and reopened from their last page token
+    pub reconnects: u64,
+    pub errors: u64,
+    /// The first few errors
+    pub error_samples: Vec<String>,
+    /// From opening a stream to its first response
+    pub first_byte_ms: Percentiles,
+    /// Between consecutive responses carrying messages on the same stream
+    pub inter_message_ms: Percentiles,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_use_nearest_rank() {
+        let mut samples: Vec<Duration> = (1..=200).rev().map(Duration::from_millis).collect();
+        assert_eq!(
+            Percentiles::of(&mut samples),
+            Percentiles {
+                p50: 100.0,
+                p99: 198.0
+            }
+        );
+
+        let mut samples = vec![Duration::from_millis(7)];
+        assert_eq!(
+            Percentiles::of(&mut samples),
+            Percentiles { p50: 7.0, p99: 7.0 }
+        );
+        assert_eq!(Percentiles::of(&mut []), Percentiles::default());
+    }
+}
@@ -0,0 +1,31 @@
+use loadgen::Profile;
+
+/// The CI profile: a short run against an in-process mock
+fn smoke_profile() -> Profile {
+    Profile {
+        base_url: None,
+        grpc_url: None,
+        chats: 2,
+        rate: 20.0,
+        streams: 5,
+        reconnect_probability: 0.2,
+        duration: 5,
+        drain_timeout: 10,
+        seed: 42,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_smoke_profile_delivers_every_message() {
+    let report = loadgen::run(&smoke_profile())
+        .await
+        .expect("Run should finish");
+
+    assert_eq!(report.errors, 0, "{:?}", report.error_samples);
+    assert!(report.complete_delivery, "{report:?}");
+    assert!(report.published > 0, "{report:?}");
+    assert_eq!(report.delivered, report.expected);
+    assert_eq!(report.missing, 0);
+    assert!(report.reconnects > 0, "{report:?}");
+    assert!(report.first_byte_ms.p50 > 0.0);
+}
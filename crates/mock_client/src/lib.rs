@@ -0,0 +1,341 @@
+//! Command-line client for driving a running mock
+//!
+//! Control verbs speak to the REST control API; `tail` follows the gRPC live chat stream.
+
+use clap::{Parser, Subcommand};
+use serde_json::{Value, json};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio_stream::StreamExt;
+
+pub mod proto {
+    tonic::include_proto!("youtube.api.v3");
+}
+
+use proto::LiveChatMessageListRequest;
+use proto::v3_data_live_chat_message_service_client::V3DataLiveChatMessageServiceClient;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "yt-api-mock-client",
+    about = "Drive a running YouTube API mock"
+)]
+pub struct Cli {
+    /// Base URL of the mock's REST server
+    #[arg(long, env = "MOCK_BASE_URL", default_value = "http://[::1]:8080")]
+    pub base_url: String,
+
+    /// URL of the mock's gRPC server
+    #[arg(long, env = "MOCK_GRPC_URL", default_value = "http://[::1]:50051")]
+    pub grpc_url: String,
+
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create the videos and chat messages listed in a JSON file
+    ///
+    /// The file holds `videos` and/or `chatMessages` arrays of control request bodies.
+    Seed { file: PathBuf },
+    /// Add a chat message to a live chat
+    AddMessage {
+        #[arg(long)]
+        chat: String,
+        #[arg(long)]
+        text: String,
+        #[arg(long, default_value = "CLI User")]
+        author: String,
+        #[arg(long, default_value = "cli-channel")]
+        author_channel_id: String,
+    },
+    /// Restore the mock to its initial state
+    Reset,
+    /// Follow a live chat stream and print messages as they arrive
+    Tail {
+        #[arg(long)]
+        chat: String,
+        /// Stop after this many messages
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Show per-chat statistics
+    Stats,
+}
+
+/// Errors reported by the client
+#[derive(Debug)]
+pub enum ClientError {
+    Io(std::io::Error),
+    Http(reqwest::Error),
+    Connect(tonic::transport::Error),
+    Grpc(tonic::Status),
+    InvalidSeed(String),
+    /// The mock answered with a non-success status
+    Server {
+        status: u16,
+        body: String,
+    },
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "{e}"),
+            ClientError::Http(e) => write!(f, "request failed: {e}"),
+            ClientError::Connect(e) => write!(f, "failed to connect to gRPC server: {e}"),
+            ClientError::Grpc(status) => write!(f, "stream failed: {}", status.message()),
+            ClientError::InvalidSeed(msg) => write!(f, "invalid seed file: {msg}"),
+            ClientError::Server { status, body } => write!(f, "server returned {status}: {body}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
+
+impl From<tonic::transport::Error> for ClientError {
+    fn from(e: tonic::transport::Error) -> Self {
+        ClientError::Connect(e)
+    }
+}
+
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        ClientError::Grpc(status)
+    }
+}
+
+/// Run a parsed command, writing its output to `out`
+pub async fn run<W: Write>(cli: Cli, out: &mut W) -> Result<(), ClientError> {
+    let control = Control::new(&cli.base_url);
+    match cli.command {
+        Command::Seed { file } => seed(&control, &file, cli.json, out).await,
+        Command::AddMessage {
+            chat,
+            text,
+            author,
+            author_channel_id,
+        } => {
+            let id = format!("cli-{}", uuid::Uuid::new_v4().simple());
+            control
+                .post(
+                    "/chat_messages",
+                    &json!({
+                        "id": id,
+                        "liveChatId": chat,
+                        "authorChannelId": author_channel_id,
+                        "authorDisplayName": author,
+                        "messageText": text,
+                    }),
+                )
+                .await?;
+            if cli.json {
+                writeln!(out, "{}", json!({"id": id, "liveChatId": chat}))?;
+            } else {
+                writeln!(out, "Added message {id} to {chat}")?;
+            }
+            Ok(())
+        }
+        Command::Reset => {
+            let response = control
+                .post("/reset", &json!({}))
+                .await
+                .map_err(|e| match e {
+                    ClientError::Server { status: 404, .. } => ClientError::Server {
+                        status: 404,
+                        body: "the target mock does not support reset".to_string(),
+                    },
+                    e => e,
+                })?;
+            if cli.json {
+                writeln!(out, "{response}")?;
+            } else {
+                writeln!(out, "Mock reset")?;
+            }
+            Ok(())
+        }
+        Command::Tail { chat, limit } => tail(&cli.grpc_url, &chat, limit, cli.json, out).await,
+        Command::Stats => {
+            let stats = control.get("/stats").await?;
+            if cli.json {
+                writeln!(out, "{stats}")?;
+                return Ok(());
+            }
+            let chats = stats["chats"].as_array().cloned().unwrap_or_default();
+            if chats.is_empty() {
+                writeln!(out, "No chats")?;
+            }
+            for chat in chats {
+                writeln!(
+                    out,
+                    "{}\t{}\t{} messages",
+                    chat["liveChatId"].as_str().unwrap_or_default(),
+                    chat["state"].as_str().unwrap_or_default(),
+                    chat["messageCount"]
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Client for the REST control API
+pub struct Control {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Control {
+    /// Client for the control API of the mock whose REST server is at `base_url`
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/control{path}", self.base_url)
+    }
+
+    /// POST a JSON body to a control path such as `/chat_messages`
+    pub async fn post(&self, path: &str, body: &Value) -> Result<Value, ClientError> {
+        send(self.http.post(self.url(path)).json(body)).await
+    }
+
+    pub async fn get(&self, path: &str) -> Result<Value, ClientError> {
+        send(self.http.get(self.url(path))).await
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<Value, ClientError> {
+        send(self.http.delete(self.url(path))).await
+    }
+}
+
+/// Send a request and parse the JSON body, failing on non-success statuses
+async fn send(request: reqwest::RequestBuilder) -> Result<Value, ClientError> {
+    let response = request.send().await?;
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(ClientError::Server {
+            status: status.as_u16(),
+            body,
+        });
+    }
+    Ok(serde_json::from_str(&body).unwrap_or(Value::String(body)))
+}
+
+async fn seed<W: Write>(
+    control: &Control,
+    file: &Path,
+    json_output: bool,
+    out: &mut W,
+) -> Result<(), ClientError> {
+    let contents = std::fs::read_to_string(file)?;
+    let seed: Value =
+        serde_json::from_str(&contents).map_err(|e| ClientError::InvalidSeed(e.to_string()))?;
+    let entries = |key: &str| seed.get(key).and_then(Value::as_array).cloned();
+    let (videos, messages) = match (entries("videos"), entries("chatMessages")) {
+        (None, None) => {
+            return Err(ClientError::InvalidSeed(
+                "expected \"videos\" and/or \"chatMessages\" arrays".to_string(),
+            ));
+        }
+        (videos, messages) => (videos.unwrap_or_default(), messages.unwrap_or_default()),
+    };
+
+    // Videos first so chat messages can refer to their chats
+    for video in &videos {
+        control.post("/videos", video).await?;
+    }
+    for message in &messages {
+        control.post("/chat_messages", message).await?;
+    }
+
+    if json_output {
+        writeln!(
+            out,
+            "{}",
+            json!({"videos": videos.len(), "chatMessages": messages.len()})
+        )?;
+    } else {
+        writeln!(
+            out,
+            "Seeded {} videos and {} chat messages",
+            videos.len(),
+            messages.len()
+        )?;
+    }
+    Ok(())
+}
+
+async fn tail<W: Write>(
+    grpc_url: &str,
+    chat: &str,
+    limit: Option<usize>,
+    json_output: bool,
+    out: &mut W,
+) -> Result<(), ClientError> {
+    if limit == Some(0) {
+        return Ok(());
+    }
+
+    let mut client = V3DataLiveChatMessageServiceClient::connect(grpc_url.to_string()).await?;
+    let mut stream = client
+        .stream_list(LiveChatMessageListRequest {
+            live_chat_id: Some(chat.to_string()),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+
+    let mut printed = 0;
+    while let Some(response) = stream.next().await {
+        for item in response?.items {
+            let snippet = item.snippet.unwrap_or_default();
+            let author = item
+                .author_details
+                .and_then(|author| author.display_name)
+                .unwrap_or_default();
+            let published_at = snippet.published_at.unwrap_or_default();
+            let text = snippet.display_message.unwrap_or_default();
+
+            if json_output {
+                let line = json!({
+                    "id": item.id,
+                    "publishedAt": published_at,
+                    "author": author,
+                    "text": text,
+                });
+                writeln!(out, "{line}")?;
+            } else {
+                writeln!(out, "[{published_at}] {author}: {text}")?;
+            }
+            out.flush()?;
+
+            printed += 1;
+            if limit.is_some_and(|limit| printed >= limit) {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use clap::Parser;
+use mock_client::Cli;
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = mock_client::run(cli, &mut std::io::stdout()).await {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
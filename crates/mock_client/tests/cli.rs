@@ -0,0 +1,156 @@
+use clap::Parser;
+use mock_client::{Cli, ClientError};
+use std::sync::Arc;
+use tonic::transport::server::TcpIncoming;
+
+/// Control and live chat services served in-process on ephemeral ports
+struct InProcessMock {
+    repo: Arc<dyn datastore::Repository>,
+    base_url: String,
+    grpc_url: String,
+}
+
+impl InProcessMock {
+    async fn start() -> Self {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+
+        let rest_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", rest_listener.local_addr().unwrap());
+        let app = axum::Router::new().nest(
+            "/control",
+            control_service::create_router(
+                Arc::clone(&repo),
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            ),
+        );
+        tokio::spawn(async move { axum::serve(rest_listener, app).await });
+
+        let grpc_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let grpc_url = format!("http://{}", grpc_listener.local_addr().unwrap());
+        let service = live_chat_service::create_service(
+            Arc::clone(&repo),
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::new(domain::StreamRegistry::default()),
+        );
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpIncoming::from(grpc_listener)),
+        );
+
+        Self {
+            repo,
+            base_url,
+            grpc_url,
+        }
+    }
+
+    /// Run the client with `args` against this mock and return its output
+    async fn run(&self, args: &[&str]) -> Result<String, ClientError> {
+        let mut argv = vec![
+            "yt-api-mock-client",
+            "--base-url",
+            &self.base_url,
+            "--grpc-url",
+            &self.grpc_url,
+        ];
+        argv.extend_from_slice(args);
+        let cli = Cli::try_parse_from(argv).expect("Valid arguments");
+
+        let mut out = Vec::new();
+        mock_client::run(cli, &mut out).await?;
+        Ok(String::from_utf8(out).expect("UTF-8 output"))
+    }
+}
+
+#[tokio::test]
+async fn test_seed_add_message_and_tail() {
+    let mock = InProcessMock::start().await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let seed_path = dir.path().join("seed.json");
+    let seed = serde_json::json!({
+        "videos": [{
+            "id": "cli-video",
+            "channelId": "cli-channel",
+            "title": "CLI",
+            "description": "Seeded by the CLI",
+            "channelTitle": "CLI Channel",
+            "liveChatId": "cli-chat",
+        }],
+        "chatMessages": [{
+            "id": "cli-seeded",
+            "liveChatId": "cli-chat",
+            "authorChannelId": "cli-author",
+            "authorDisplayName": "Seeder",
+            "messageText": "seeded",
+        }],
+    });
+    std::fs::write(&seed_path, seed.to_string()).unwrap();
+
+    let output = mock
+        .run(&["seed", seed_path.to_str().unwrap()])
+        .await
+        .unwrap();
+    assert_eq!(output, "Seeded 1 videos and 1 chat messages\n");
+    assert!(mock.repo.get_video("cli-video").unwrap().is_some());
+
+    let output = mock
+        .run(&["add-message", "--chat", "cli-chat", "--text", "hello"])
+        .await
+        .unwrap();
+    assert!(output.starts_with("Added message cli-"), "{output}");
+
+    let output = mock
+        .run(&["tail", "--chat", "cli-chat", "--limit", "2"])
+        .await
+        .unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 2, "{output}");
+    assert!(lines[0].ends_with("Seeder: seeded"), "{output}");
+    assert!(lines[1].ends_with("CLI User: hello"), "{output}");
+
+    let output = mock
+        .run(&["--json", "tail", "--chat", "cli-chat", "--limit", "1"])
+        .await
+        .unwrap();
+    let line: serde_json::Value = serde_json::from_str(output.trim()).expect("JSON line");
+    assert_eq!(line["id"], "cli-seeded");
+    assert_eq!(line["text"], "seeded");
+}
+
+#[tokio::test]
+async fn test_stats_json_and_reset() {
+    let mock = InProcessMock::start().await;
+
+    let output = mock.run(&["stats", "--json"]).await.unwrap();
+    let stats: serde_json::Value = serde_json::from_str(output.trim()).expect("JSON output");
+    assert!(stats["chats"].is_array());
+
+    let output = mock.run(&["reset"]).await.unwrap();
+    assert_eq!(output.trim(), "Mock reset");
+    let output = mock.run(&["--json", "reset"]).await.unwrap();
+    let reset: serde_json::Value = serde_json::from_str(output.trim()).expect("JSON output");
+    assert_eq!(reset["success"], true);
+}
+
+#[tokio::test]
+async fn test_seed_rejects_files_without_entries() {
+    let mock = InProcessMock::start().await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let seed_path = dir.path().join("seed.json");
+    std::fs::write(&seed_path, r#"{"items": []}"#).unwrap();
+
+    let error = mock
+        .run(&["seed", seed_path.to_str().unwrap()])
+        .await
+        .expect_err("Seed file without entries should be rejected");
+    assert!(matches!(error, ClientError::InvalidSeed(_)), "{error}");
+}
@@ -0,0 +1,174 @@
+//! OpenID Connect ID tokens for clients that request the `openid` scope
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+use serde::{Deserialize, Serialize};
+
+/// Issuer of ID tokens, as Google's
+pub const ID_TOKEN_ISSUER: &str = "https://accounts.google.com";
+
+/// Subject of ID tokens issued by the mock
+pub const MOCK_USER_ID: &str = "100000000000000000001";
+
+/// Email of the mock user
+pub const MOCK_USER_EMAIL: &str = "mock-user@example.com";
+
+/// Key ID in the header of signed ID tokens
+pub const ID_TOKEN_KEY_ID: &str = "mock-hs256";
+
+/// Random HS256 key, for servers started without a configured key
+pub fn generate_key() -> String {
+    use ring::rand::SecureRandom as _;
+
+    let mut key = [0u8; 32];
+    ring::rand::SystemRandom::new()
+        .fill(&mut key)
+        .expect("System random source should be available");
+    BASE64URL.encode(key)
+}
+
+/// Public key set served at `/.well-known/jwks.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+/// Symmetric JSON Web Key (RFC 7517) of the ID token signing key
+/// Symmetric keys verify and sign alike, so anyone with the key set can forge ID tokens.
+/// That is fine for a mock, but the key must never be a real secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(rename = "use")]
+    pub use_: String,
+    pub alg: String,
+    pub kid: String,
+    /// The key bytes, base64url-encoded
+    pub k: String,
+}
+
+impl Jwk {
+    /// JWK of the HS256 `signing_key`
+    pub fn hs256(signing_key: &str) -> Self {
+        Self {
+            kty: "oct".to_string(),
+            use_: "sig".to_string(),
+            alg: "HS256".to_string(),
+            kid: ID_TOKEN_KEY_ID.to_string(),
+            k: BASE64URL.encode(signing_key.as_bytes()),
+        }
+    }
+}
+
+/// Whether `scope` requests an ID token
+pub fn requests_id_token(scope: &str) -> bool {
+    scope.split_whitespace().any(|scope| scope == "openid")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    alg: String,
+    typ: String,
+    kid: String,
+}
+
+/// Claims of an ID token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub email: String,
+}
+
+impl IdTokenClaims {
+    /// Claims for the mock user, issued at `iat` (Unix seconds) and valid for `expires_in` seconds
+    pub fn new(aud: &str, iat: i64, expires_in: i64) -> Self {
+        Self {
+            iss: ID_TOKEN_ISSUER.to_string(),
+            sub: MOCK_USER_ID.to_string(),
+            aud: aud.to_string(),
+            iat,
+            exp: iat + expires_in,
+            email: MOCK_USER_EMAIL.to_string(),
+        }
+    }
+}
+
+fn encode_part<T: Serialize>(value: &T) -> String {
+    BASE64URL.encode(serde_json::to_vec(value).expect("JWT parts serialize"))
+}
+
+/// Encode `claims` as a JWT signed with HS256
+pub fn encode(claims: &IdTokenClaims, signing_key: &str) -> String {
+    let header = Header {
+        alg: "HS256".to_string(),
+        typ: "JWT".to_string(),
+        kid: ID_TOKEN_KEY_ID.to_string(),
+    };
+    let signing_input = format!("{}.{}", encode_part(&header), encode_part(claims));
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, signing_key.as_bytes());
+    let signature = BASE64URL.encode(ring::hmac::sign(&key, signing_input.as_bytes()).as_ref());
+    format!("{signing_input}.{signature}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_part<T: for<'de> Deserialize<'de>>(part: &str) -> T {
+        serde_json::from_slice(&BASE64URL.decode(part).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_openid_scope_detection() {
+        assert!(requests_id_token("openid email"));
+        assert!(requests_id_token("email  openid"));
+        assert!(!requests_id_token("openid.read email"));
+        assert!(!requests_id_token(""));
+    }
+
+    #[test]
+    fn test_signed_token_verifies_with_the_key() {
+        let claims = IdTokenClaims::new("client", 1_700_000_000, 3600);
+        let token = encode(&claims, "secret");
+        let parts: Vec<_> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+
+        let header: Header = decode_part(parts[0]);
+        assert_eq!(header.alg, "HS256");
+        assert_eq!(header.kid, ID_TOKEN_KEY_ID);
+        let decoded: IdTokenClaims = decode_part(parts[1]);
+        assert_eq!(decoded, claims);
+        assert_eq!(decoded.exp, 1_700_003_600);
+
+        let signing_input = format!("{}.{}", parts[0], parts[1]);
+        let signature = BASE64URL.decode(parts[2]).unwrap();
+        let verify = |key: &str| {
+            let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key.as_bytes());
+            ring::hmac::verify(&key, signing_input.as_bytes(), &signature)
+        };
+        assert!(verify("secret").is_ok());
+        assert!(verify("other").is_err());
+    }
+
+    #[test]
+    fn test_jwk_holds_the_signing_key() {
+        let key = generate_key();
+        assert_ne!(key, generate_key());
+
+        let jwk = Jwk::hs256(&key);
+        assert_eq!(
+            (jwk.kty.as_str(), jwk.kid.as_str()),
+            ("oct", ID_TOKEN_KEY_ID)
+        );
+        // A verifier decodes `k` to the exact bytes the token was signed with
+        let token = encode(&IdTokenClaims::new("client", 0, 60), &key);
+        let (signing_input, signature) = token.rsplit_once('.').unwrap();
+        let verifier =
+            ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &BASE64URL.decode(&jwk.k).unwrap());
+        let signature = BASE64URL.decode(signature).unwrap();
+        assert!(ring::hmac::verify(&verifier, signing_input.as_bytes(), &signature).is_ok());
+    }
+}
@@ -1,14 +1,28 @@
-use axum::{Json, Router, extract::Form, http::StatusCode, response::IntoResponse, routing::post};
-use chrono::{DateTime, Utc};
+use axum::{
+    Json, Router,
+    extract::{Form, OriginalUri, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use clock::Clock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+pub mod id_token;
+pub mod persist;
+pub mod pkce;
+
+pub use persist::RefreshTokenFile;
+pub use pkce::{CodeChallenge, CodeChallengeMethod};
+
 /// Request body for token generation
-/// Supports both authorization_code and refresh_token grant types
+/// Supports the authorization_code, refresh_token and client_credentials grant types
 #[derive(Debug, Deserialize)]
 pub struct TokenRequest {
-    /// Grant type: "authorization_code" for initial token, "refresh_token" for refresh
+    /// Grant type: "authorization_code" for initial token, "refresh_token" for refresh,
+    /// "client_credentials" for service-to-service tokens
     pub grant_type: String,
 
     /// Authorization code (used with grant_type=authorization_code)
@@ -31,6 +45,11 @@ pub struct TokenRequest {
     #[serde(default)]
     pub redirect_uri: Option<String>,
 
+    /// PKCE verifier (used with grant_type=authorization_code)
+    /// Checked when the code was issued with a challenge, ignored otherwise
+    #[serde(default)]
+    pub code_verifier: Option<String>,
+
     /// Custom expiry in seconds from now (for testing)
     /// Can be negative to create expired tokens
     #[serde(default)]
@@ -49,7 +68,8 @@ pub struct TokenResponse {
     /// The access token
     pub access_token: String,
 
-    /// The refresh token (only included for grant_type=authorization_code)
+    /// The refresh token (included for grant_type=authorization_code, and for
+    /// grant_type=refresh_token when refresh tokens are rotated)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh_token: Option<String>,
 
@@ -62,6 +82,10 @@ pub struct TokenResponse {
     /// Scope (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scope: Option<String>,
+
+    /// OpenID Connect ID token (only included when the scope contains `openid`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
 }
 
 /// Error response for OAuth errors
@@ -79,43 +103,343 @@ pub struct ErrorResponse {
 /// Token metadata for tracking expiry and scope
 #[derive(Debug, Clone)]
 struct TokenMetadata {
-    /// When the token was issued
-    issued_at: DateTime<Utc>,
+    /// When the token was issued, on the monotonic clock
+    /// Wall-clock steps (e.g. NTP corrections) therefore cannot expire a token early
+    issued_at: std::time::Duration,
     /// Expiry duration in seconds (can be negative for expired tokens)
     expires_in: i64,
     /// The scope associated with this token
     scope: String,
+    /// Token suggested to replace this one as it neared expiry
+    rotated_to: Option<String>,
 }
 
 impl TokenMetadata {
+    fn new(clock: &dyn Clock, expires_in: i64, scope: String) -> Self {
+        Self {
+            issued_at: clock.monotonic(),
+            expires_in,
+            scope,
+            rotated_to: None,
+        }
+    }
+
     /// Check if the token is expired
-    fn is_expired(&self) -> bool {
-        let now = Utc::now();
-        let expiry_time = self.issued_at + chrono::Duration::seconds(self.expires_in);
-        now >= expiry_time
+    fn is_expired(&self, clock: &dyn Clock) -> bool {
+        let elapsed = clock.monotonic().saturating_sub(self.issued_at);
+        elapsed.as_millis() as i128 >= i128::from(self.expires_in) * 1000
+    }
+
+    /// Whether the token expired at least `retention` ago
+    fn expired_longer_than(&self, clock: &dyn Clock, retention: std::time::Duration) -> bool {
+        let elapsed = clock.monotonic().saturating_sub(self.issued_at);
+        elapsed.as_millis() as i128
+            >= i128::from(self.expires_in) * 1000 + retention.as_millis() as i128
+    }
+
+    /// Seconds left until the token expires (`issued_at + expires_in - now`)
+    fn remaining_secs(&self, clock: &dyn Clock) -> i64 {
+        let elapsed = clock.monotonic().saturating_sub(self.issued_at);
+        self.expires_in - elapsed.as_secs() as i64
     }
 }
 
-// Global token store for tracking token expiry
+/// Refresh token metadata
+/// Refresh tokens do not expire; they stay valid until rotated away
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RefreshTokenMetadata {
+    /// The scope access tokens refreshed with this token default to
+    scope: String,
+}
+
+// Global token store for tracking access token expiry
 lazy_static::lazy_static! {
     static ref TOKEN_STORE: Arc<RwLock<HashMap<String, TokenMetadata>>> =
         Arc::new(RwLock::new(HashMap::new()));
 }
 
-/// Validate if an access token is expired
-pub fn validate_token(token: &str) -> Result<(), String> {
-    let store = TOKEN_STORE.read().unwrap();
+// Global store of issued refresh tokens, kept apart so they never pass access token validation
+lazy_static::lazy_static! {
+    static ref REFRESH_TOKEN_STORE: Arc<RwLock<HashMap<String, RefreshTokenMetadata>>> =
+        Arc::new(RwLock::new(HashMap::new()));
+}
+
+/// Issue a refresh token whose refreshed access tokens default to `scope`
+fn issue_refresh_token(scope: String) -> String {
+    let refresh_token = format!("1//mock_{}", uuid::Uuid::new_v4());
+    let metadata = RefreshTokenMetadata { scope };
+    REFRESH_TOKEN_STORE
+        .write()
+        .unwrap()
+        .insert(refresh_token.clone(), metadata);
+    refresh_token
+}
+
+/// Options of the OAuth endpoints
+#[derive(Debug, Clone, Default)]
+pub struct OAuthConfig {
+    /// Return a new refresh token on each refresh and invalidate the presented one
+    pub rotate_refresh_tokens: bool,
+    /// HS256 key for ID tokens; [`create_router`] generates one when unset
+    pub id_token_signing_key: Option<String>,
+    /// The endpoints are served over TLS, so discovery advertises `https` URLs
+    pub tls: bool,
+    /// Faults injected into the token endpoint via the control API
+    pub faults: Arc<domain::FaultConfig>,
+    /// File keeping refresh tokens across restarts; they are only kept in memory when unset
+    pub refresh_token_file: Option<Arc<RefreshTokenFile>>,
+    /// Which authorization codes the token endpoint accepts
+    pub auth_code_validation: AuthCodeValidation,
+}
 
-    if let Some(metadata) = store.get(token) {
-        if metadata.is_expired() {
-            return Err("Token has expired".to_string());
+impl OAuthConfig {
+    /// Persist the refresh tokens after they changed, when a file is configured
+    fn refresh_tokens_changed(&self) {
+        if let Some(file) = &self.refresh_token_file {
+            file.save();
         }
-        Ok(())
-    } else {
-        // If token not found, it might be from before tracking was implemented
-        // or it's an invalid token. For mock purposes, we'll allow it.
-        Ok(())
     }
+
+    /// ID token for a token response, `None` unless `scope` contains `openid`
+    /// The audience is the requesting client, or the mock client ID when none is sent
+    fn id_token(&self, scope: &str, client_id: Option<&str>, expires_in: i64) -> Option<String> {
+        if !id_token::requests_id_token(scope) {
+            return None;
+        }
+        let signing_key = self.id_token_signing_key.as_deref()?;
+        let audience = client_id
+            .filter(|id| !id.is_empty())
+            .unwrap_or(MOCK_CLIENT_ID);
+        let claims = id_token::IdTokenClaims::new(
+            audience,
+            clock::system_clock().now().timestamp(),
+            expires_in,
+        );
+        Some(id_token::encode(&claims, signing_key))
+    }
+}
+
+/// Default lifetime of issued authorization codes in seconds
+pub const DEFAULT_AUTH_CODE_EXPIRES_IN: i64 = 600;
+
+/// Which authorization codes the token endpoint accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuthCodeValidation {
+    /// Any non-empty code until the first code is issued, then only issued codes, once each
+    #[default]
+    Auto,
+    /// Only issued codes, once each, even before any code is issued
+    Strict,
+    /// Any non-empty code, any number of times; issued codes still carry their scope
+    Permissive,
+}
+
+impl std::str::FromStr for AuthCodeValidation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "strict" => Ok(Self::Strict),
+            "permissive" => Ok(Self::Permissive),
+            _ => Err(format!(
+                "Unknown auth code validation '{s}'. Use 'auto', 'strict' or 'permissive'"
+            )),
+        }
+    }
+}
+
+/// Metadata of an issued authorization code
+#[derive(Debug, Clone)]
+struct AuthCodeMetadata {
+    /// Expiry and scope, tracked like token metadata
+    token: TokenMetadata,
+    /// Whether the code was already exchanged for tokens
+    redeemed: bool,
+    /// PKCE challenge the verifier must match at exchange
+    code_challenge: Option<CodeChallenge>,
+}
+
+/// Reasons an authorization code cannot be redeemed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthCodeError {
+    Unknown,
+    Redeemed,
+    Expired,
+    /// The code was issued with a PKCE challenge, but no verifier was given
+    MissingVerifier,
+    /// The verifier does not match the code's PKCE challenge
+    VerifierMismatch,
+}
+
+impl std::fmt::Display for AuthCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown => write!(f, "Malformed auth code."),
+            Self::Redeemed => write!(f, "Authorization code has already been redeemed."),
+            Self::Expired => write!(f, "Authorization code has expired."),
+            Self::MissingVerifier => write!(f, "Missing code verifier."),
+            Self::VerifierMismatch => write!(f, "Invalid code verifier."),
+        }
+    }
+}
+
+/// Registry of issued authorization codes
+///
+/// With [`AuthCodeValidation::Auto`], while no code has been issued the registry accepts
+/// any non-empty code, so clients that never call `/authorize` keep working. Once a code
+/// is issued, only issued codes are accepted, each of them once.
+#[derive(Debug, Default)]
+pub struct AuthCodeRegistry {
+    codes: RwLock<HashMap<String, AuthCodeMetadata>>,
+}
+
+impl AuthCodeRegistry {
+    /// Issue a single-use code valid for `expires_in` seconds
+    /// With a `code_challenge`, redeeming the code takes the matching PKCE verifier
+    pub fn issue(
+        &self,
+        clock: &dyn Clock,
+        scope: String,
+        expires_in: i64,
+        code_challenge: Option<CodeChallenge>,
+    ) -> String {
+        let code = format!("4/mock_{}", uuid::Uuid::new_v4());
+        self.codes.write().unwrap().insert(
+            code.clone(),
+            AuthCodeMetadata {
+                token: TokenMetadata::new(clock, expires_in, scope),
+                redeemed: false,
+                code_challenge,
+            },
+        );
+        code
+    }
+
+    /// Redeem a code, returning the scope it was issued with
+    /// Returns `Ok(None)` when the code is accepted without having been issued
+    ///
+    /// A code issued with a PKCE challenge needs the matching `code_verifier`, whatever the
+    /// validation; without a challenge the verifier is ignored, like Google does.
+    pub fn redeem(
+        &self,
+        clock: &dyn Clock,
+        code: &str,
+        code_verifier: Option<&str>,
+        validation: AuthCodeValidation,
+    ) -> Result<Option<String>, AuthCodeError> {
+        let mut codes = self.codes.write().unwrap();
+        if validation == AuthCodeValidation::Auto && codes.is_empty() {
+            return Ok(None);
+        }
+
+        let result = match codes.get_mut(code) {
+            None => Err(AuthCodeError::Unknown),
+            Some(metadata) if metadata.redeemed => Err(AuthCodeError::Redeemed),
+            Some(metadata) => {
+                // Redeemed codes are kept so a replay is rejected rather than accepted
+                metadata.redeemed = true;
+                if metadata.token.is_expired(clock) {
+                    Err(AuthCodeError::Expired)
+                } else {
+                    match (&metadata.code_challenge, code_verifier) {
+                        (None, _) => Ok(Some(metadata.token.scope.clone())),
+                        (Some(_), None) => Err(AuthCodeError::MissingVerifier),
+                        (Some(challenge), Some(verifier)) if !challenge.verify(verifier) => {
+                            Err(AuthCodeError::VerifierMismatch)
+                        }
+                        (Some(_), Some(_)) => Ok(Some(metadata.token.scope.clone())),
+                    }
+                }
+            }
+        };
+        match result {
+            Err(AuthCodeError::Unknown | AuthCodeError::Redeemed | AuthCodeError::Expired)
+                if validation == AuthCodeValidation::Permissive =>
+            {
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+}
+
+// Global authorization code store, mirroring TOKEN_STORE
+lazy_static::lazy_static! {
+    static ref AUTH_CODE_STORE: AuthCodeRegistry = AuthCodeRegistry::default();
+}
+
+/// Issue an authorization code that `grant_type=authorization_code` will accept once
+/// Uses the default mock scope when `scope` is not given
+pub fn issue_auth_code(scope: Option<String>, expires_in: i64) -> String {
+    AUTH_CODE_STORE.issue(
+        &*clock::system_clock(),
+        resolve_scope(scope, None),
+        expires_in,
+        None,
+    )
+}
+
+/// Pick the scope from the request, then the fallback, then OAUTH_MOCK_SCOPE, then the default
+fn resolve_scope(requested: Option<String>, fallback: Option<String>) -> String {
+    requested
+        .or(fallback)
+        .or_else(|| std::env::var("OAUTH_MOCK_SCOPE").ok())
+        .unwrap_or_else(|| "mock.scope.read mock.scope.write".to_string())
+}
+
+/// Reason a bearer token was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    /// The token was issued by this mock and has expired
+    Expired,
+    /// The token was not issued by this mock (only reported in strict mode)
+    Unknown,
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::Expired => write!(f, "Token has expired"),
+            TokenError::Unknown => write!(f, "Token was not issued by this server"),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+/// Bearer token validation shared by the services that accept OAuth credentials
+pub trait TokenValidator: Send + Sync {
+    fn validate(&self, token: &str) -> Result<(), TokenError>;
+}
+
+/// Validates tokens against those issued by this mock
+/// Unknown tokens are accepted unless `strict` is set
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IssuedTokenValidator {
+    pub strict: bool,
+}
+
+impl TokenValidator for IssuedTokenValidator {
+    fn validate(&self, token: &str) -> Result<(), TokenError> {
+        let store = TOKEN_STORE.read().unwrap();
+        match store.get(token) {
+            Some(metadata) if metadata.is_expired(&*clock::system_clock()) => {
+                Err(TokenError::Expired)
+            }
+            Some(_) => Ok(()),
+            // Tokens from elsewhere (e.g. hand-written in tests) pass unless strict
+            None if self.strict => Err(TokenError::Unknown),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Validate if an access token is expired
+pub fn validate_token(token: &str) -> Result<(), String> {
+    IssuedTokenValidator::default()
+        .validate(token)
+        .map_err(|e| e.to_string())
 }
 
 /// Retrieve the scope associated with a token
@@ -124,16 +448,166 @@ pub fn get_token_scope(token: &str) -> Option<String> {
     store.get(token).map(|metadata| metadata.scope.clone())
 }
 
+/// Fresh token a client should adopt in place of `token`, modeling proactive rotation
+///
+/// Only a tracked, still valid token with at most `threshold_secs` left is rotated. The
+/// successor gets the same scope and lifetime; later calls with the same token return the
+/// same successor instead of issuing another. The old token stays valid until it expires.
+pub fn rotate_if_expiring(token: &str, threshold_secs: i64) -> Option<String> {
+    let clock = clock::system_clock();
+    let mut store = TOKEN_STORE.write().unwrap();
+    let metadata = store.get_mut(token)?;
+    if metadata.is_expired(&*clock) || metadata.remaining_secs(&*clock) > threshold_secs {
+        return None;
+    }
+    if let Some(successor) = &metadata.rotated_to {
+        return Some(successor.clone());
+    }
+    let successor = format!("ya29.mock_{}", uuid::Uuid::new_v4());
+    metadata.rotated_to = Some(successor.clone());
+    let fresh = TokenMetadata::new(&*clock, metadata.expires_in, metadata.scope.clone());
+    store.insert(successor.clone(), fresh);
+    Some(successor)
+}
+
+/// An access token issued in bulk
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkToken {
+    pub access_token: String,
+    /// Lifetime in seconds; 0 for tokens issued already expired
+    pub expires_in: i64,
+    pub scope: String,
+}
+
+/// How long expired tokens are still reported as expired before bulk issues forget them
+pub const EXPIRED_TOKEN_RETENTION: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Most access tokens tracked after a bulk issue; the oldest beyond it are forgotten
+pub const MAX_TRACKED_TOKENS: usize = 500_000;
+
+/// Issue `count` access tokens named `{prefix}{uuid}`, stored under a single lock
+///
+/// The first `expired` of them are issued already expired, for negative-path load tests.
+/// The scope is resolved like that of the token endpoint. Under the same lock, tokens that
+/// expired over [`EXPIRED_TOKEN_RETENTION`] ago are forgotten, and the oldest tokens make room
+/// when the store would grow past [`MAX_TRACKED_TOKENS`], so repeated load runs stay bounded.
+pub fn issue_bulk_tokens(
+    count: usize,
+    expired: usize,
+    scope: Option<String>,
+    expires_in: i64,
+    prefix: &str,
+) -> Vec<BulkToken> {
+    let clock = clock::system_clock();
+    let scope = resolve_scope(scope, None);
+    let tokens: Vec<BulkToken> = (0..count)
+        .map(|i| BulkToken {
+            access_token: format!("{prefix}{}", uuid::Uuid::new_v4()),
+            expires_in: if i < expired { 0 } else { expires_in },
+            scope: scope.clone(),
+        })
+        .collect();
+
+    store_bulk_tokens(
+        &mut TOKEN_STORE.write().unwrap(),
+        &*clock,
+        &tokens,
+        MAX_TRACKED_TOKENS,
+    );
+    tokens
+}
+
+/// Store bulk-issued tokens, first evicting stale ones so at most `capacity` remain
+fn store_bulk_tokens(
+    store: &mut HashMap<String, TokenMetadata>,
+    clock: &dyn Clock,
+    tokens: &[BulkToken],
+    capacity: usize,
+) {
+    // Recently expired tokens stay, so clients using them are still told they expired
+    store.retain(|_, metadata| !metadata.expired_longer_than(clock, EXPIRED_TOKEN_RETENTION));
+    let excess = (store.len() + tokens.len()).saturating_sub(capacity);
+    if excess > 0 {
+        let mut by_age: Vec<_> = store
+            .iter()
+            .map(|(token, metadata)| (metadata.issued_at, token.clone()))
+            .collect();
+        by_age.sort_unstable();
+        for (_, token) in by_age.into_iter().take(excess) {
+            store.remove(&token);
+        }
+    }
+
+    store.reserve(tokens.len());
+    for token in tokens {
+        let metadata = TokenMetadata::new(clock, token.expires_in, token.scope.clone());
+        store.insert(token.access_token.clone(), metadata);
+    }
+}
+
+/// Counts of tokens tracked by this server, for debugging snapshots
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenStoreSummary {
+    /// Access tokens issued by this server
+    pub tracked: usize,
+    /// Tracked tokens that have expired
+    pub expired: usize,
+    /// Refresh tokens that are still valid
+    pub refresh_tokens: usize,
+}
+
+/// Summarize the token store
+pub fn token_store_summary() -> TokenStoreSummary {
+    let clock = clock::system_clock();
+    let store = TOKEN_STORE.read().unwrap();
+    TokenStoreSummary {
+        tracked: store.len(),
+        expired: store
+            .values()
+            .filter(|metadata| metadata.is_expired(&*clock))
+            .count(),
+        refresh_tokens: REFRESH_TOKEN_STORE.read().unwrap().len(),
+    }
+}
+
+/// Remaining lifetime in seconds of a tracked token, `None` if it is unknown or expired
+fn remaining_lifetime(token: &str) -> Option<i64> {
+    let clock = clock::system_clock();
+    let store = TOKEN_STORE.read().unwrap();
+    store
+        .get(token)
+        .filter(|metadata| !metadata.is_expired(&*clock))
+        .map(|metadata| metadata.remaining_secs(&*clock))
+}
+
 /// Handler for token generation and refresh
-async fn token_handler(Form(request): Form<TokenRequest>) -> impl IntoResponse {
+async fn token_handler(
+    State(config): State<OAuthConfig>,
+    Form(request): Form<TokenRequest>,
+) -> impl IntoResponse {
+    if let Some(fault) = config.faults.hit(domain::FaultTarget::OauthToken) {
+        let status =
+            StatusCode::from_u16(fault.http_status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let error = ErrorResponse {
+            error: fault.reason,
+            error_description: Some(fault.message),
+        };
+        return (status, Json(error)).into_response();
+    }
+
     match request.grant_type.as_str() {
-        "authorization_code" => handle_authorization_code(request).await.into_response(),
-        "refresh_token" => handle_refresh_token(request).await.into_response(),
+        "authorization_code" => handle_authorization_code(config, request)
+            .await
+            .into_response(),
+        "refresh_token" => handle_refresh_token(config, request).await.into_response(),
+        "client_credentials" => handle_client_credentials(request).await.into_response(),
         _ => {
             let error = ErrorResponse {
                 error: "unsupported_grant_type".to_string(),
                 error_description: Some(format!(
-                    "Grant type '{}' is not supported. Use 'authorization_code' or 'refresh_token'",
+                    "Grant type '{}' is not supported. Use 'authorization_code', 'refresh_token' or 'client_credentials'",
                     request.grant_type
                 )),
             };
@@ -143,7 +617,10 @@ async fn token_handler(Form(request): Form<TokenRequest>) -> impl IntoResponse {
 }
 
 /// Handle authorization_code grant type (initial token generation)
-async fn handle_authorization_code(request: TokenRequest) -> impl IntoResponse {
+async fn handle_authorization_code(
+    config: OAuthConfig,
+    request: TokenRequest,
+) -> impl IntoResponse {
     // In a real implementation, we would validate the authorization code
     // For mock purposes, we just check if it's present
     if request.code.is_none() || request.code.as_ref().unwrap().is_empty() {
@@ -156,32 +633,44 @@ async fn handle_authorization_code(request: TokenRequest) -> impl IntoResponse {
         return (StatusCode::BAD_REQUEST, Json(error)).into_response();
     }
 
-    // Generate tokens
+    // Codes are checked against the issued-code registry, as configured
+    let code = request.code.as_deref().unwrap_or_default();
+    let code_scope = match AUTH_CODE_STORE.redeem(
+        &*clock::system_clock(),
+        code,
+        request.code_verifier.as_deref(),
+        config.auth_code_validation,
+    ) {
+        Ok(scope) => scope,
+        Err(e) => {
+            let error = ErrorResponse {
+                error: "invalid_grant".to_string(),
+                error_description: Some(e.to_string()),
+            };
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+
+    // Generate the access token
     let access_token = format!("ya29.mock_{}", uuid::Uuid::new_v4());
-    let refresh_token = format!("1//mock_{}", uuid::Uuid::new_v4());
 
     // Use custom expiry if provided, otherwise default to 3600 seconds (1 hour)
     let expires_in = request.expires_in.unwrap_or(3600);
 
-    // Use custom scope if provided in request, then check environment variable, then use default
-    let scope = request
-        .scope
-        .or_else(|| std::env::var("OAUTH_MOCK_SCOPE").ok())
-        .or_else(|| Some("mock.scope.read mock.scope.write".to_string()))
-        .unwrap();
+    // Use custom scope if provided in request, then the scope the code was issued with,
+    // then check environment variable, then use default
+    let scope = resolve_scope(request.scope, code_scope);
 
     // Store token metadata for expiry validation and scope tracking
-    let metadata = TokenMetadata {
-        issued_at: Utc::now(),
-        expires_in,
-        scope: scope.clone(),
-    };
+    let metadata = TokenMetadata::new(&*clock::system_clock(), expires_in, scope.clone());
     {
         let mut store = TOKEN_STORE.write().unwrap();
-        store.insert(access_token.clone(), metadata.clone());
-        // Also store refresh token with the same scope so it can be retrieved later
-        store.insert(refresh_token.clone(), metadata.clone());
+        store.insert(access_token.clone(), metadata);
     }
+    // The refresh token keeps the scope so refreshed tokens can inherit it
+    let refresh_token = issue_refresh_token(scope.clone());
+    config.refresh_tokens_changed();
+    let id_token = config.id_token(&scope, request.client_id.as_deref(), expires_in);
 
     let response = TokenResponse {
         access_token,
@@ -189,15 +678,16 @@ async fn handle_authorization_code(request: TokenRequest) -> impl IntoResponse {
         token_type: "Bearer".to_string(),
         expires_in,
         scope: Some(scope),
+        id_token,
     };
 
     (StatusCode::OK, Json(response)).into_response()
 }
 
 /// Handle refresh_token grant type (token refresh)
-async fn handle_refresh_token(request: TokenRequest) -> impl IntoResponse {
-    // In a real implementation, we would validate the refresh token
-    // For mock purposes, we just check if it's present
+/// The refresh token must have been issued by this server; with rotation enabled it is
+/// exchanged for a new one, like Google does
+async fn handle_refresh_token(config: OAuthConfig, request: TokenRequest) -> impl IntoResponse {
     if request.refresh_token.is_none() || request.refresh_token.as_ref().unwrap().is_empty() {
         let error = ErrorResponse {
             error: "invalid_request".to_string(),
@@ -211,10 +701,24 @@ async fn handle_refresh_token(request: TokenRequest) -> impl IntoResponse {
 
     let refresh_token = request.refresh_token.as_ref().unwrap();
 
-    // Try to get the original scope from the refresh token
-    // In a real implementation, refresh tokens would be tracked separately
-    // For this mock, we'll try to look it up from TOKEN_STORE
-    let original_scope = get_token_scope(refresh_token);
+    // Unknown and rotated-away refresh tokens are rejected; a rotated token is
+    // removed in the same step so it can only be redeemed once
+    let presented = {
+        let mut store = REFRESH_TOKEN_STORE.write().unwrap();
+        if config.rotate_refresh_tokens {
+            store.remove(refresh_token)
+        } else {
+            store.get(refresh_token).cloned()
+        }
+    };
+    let Some(presented) = presented else {
+        let error = ErrorResponse {
+            error: "invalid_grant".to_string(),
+            error_description: Some("Token has been expired or revoked.".to_string()),
+        };
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    };
+    let original_scope = Some(presented.scope.clone());
 
     // Generate a new access token
     let access_token = format!("ya29.mock_{}", uuid::Uuid::new_v4());
@@ -224,36 +728,1335 @@ async fn handle_refresh_token(request: TokenRequest) -> impl IntoResponse {
 
     // Use custom scope if provided in request, then use original scope from refresh token,
     // then check environment variable, then use default
-    let scope = request
-        .scope
-        .or(original_scope)
-        .or_else(|| std::env::var("OAUTH_MOCK_SCOPE").ok())
-        .or_else(|| Some("mock.scope.read mock.scope.write".to_string()))
-        .unwrap();
+    let scope = resolve_scope(request.scope, original_scope);
 
     // Store token metadata for expiry validation and scope tracking
-    let metadata = TokenMetadata {
-        issued_at: Utc::now(),
+    let metadata = TokenMetadata::new(&*clock::system_clock(), expires_in, scope.clone());
+    {
+        let mut store = TOKEN_STORE.write().unwrap();
+        store.insert(access_token.clone(), metadata);
+    }
+
+    // The rotated token keeps the scope of the one it replaces
+    let new_refresh_token = config
+        .rotate_refresh_tokens
+        .then(|| issue_refresh_token(presented.scope));
+    if new_refresh_token.is_some() {
+        config.refresh_tokens_changed();
+    }
+    let id_token = config.id_token(&scope, request.client_id.as_deref(), expires_in);
+
+    let response = TokenResponse {
+        access_token,
+        refresh_token: new_refresh_token,
+        token_type: "Bearer".to_string(),
         expires_in,
-        scope: scope.clone(),
+        scope: Some(scope),
+        id_token,
     };
+
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Handle client_credentials grant type (service-to-service tokens)
+/// `client_id` and `client_secret` are neither required nor validated, as with the other
+/// grant types; no refresh token is issued since the client can request a new token directly,
+/// and no ID token since there is no signed-in user
+async fn handle_client_credentials(request: TokenRequest) -> impl IntoResponse {
+    let access_token = format!("ya29.mock_{}", uuid::Uuid::new_v4());
+
+    // Use custom expiry if provided, otherwise default to 3600 seconds (1 hour)
+    let expires_in = request.expires_in.unwrap_or(3600);
+
+    // Use custom scope if provided in request, then check environment variable, then use default
+    let scope = resolve_scope(request.scope, None);
+
+    // Store token metadata for expiry validation and scope tracking
+    let metadata = TokenMetadata::new(&*clock::system_clock(), expires_in, scope.clone());
     {
         let mut store = TOKEN_STORE.write().unwrap();
-        store.insert(access_token.clone(), metadata.clone());
+        store.insert(access_token.clone(), metadata);
     }
 
     let response = TokenResponse {
         access_token,
-        refresh_token: None, // Refresh tokens are not returned when refreshing
+        refresh_token: None,
         token_type: "Bearer".to_string(),
         expires_in,
         scope: Some(scope),
+        id_token: None,
     };
 
     (StatusCode::OK, Json(response)).into_response()
 }
 
+/// Query parameters for the authorization endpoint
+/// Follows `https://accounts.google.com/o/oauth2/v2/auth`; there is no consent screen
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeRequest {
+    /// Client ID (optional, not validated in mock)
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// Must be `code` when given; the implicit flow is not supported
+    #[serde(default)]
+    pub response_type: Option<String>,
+
+    /// Where to redirect with the issued code (optional)
+    /// Without it the code is returned as JSON
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+
+    /// Opaque value echoed back on redirect
+    #[serde(default)]
+    pub state: Option<String>,
+
+    /// Scope the resulting tokens are issued with (optional)
+    #[serde(default)]
+    pub scope: Option<String>,
+
+    /// PKCE challenge the verifier must match when the code is exchanged (optional)
+    #[serde(default)]
+    pub code_challenge: Option<String>,
+
+    /// `S256` or `plain` (the default)
+    #[serde(default)]
+    pub code_challenge_method: Option<String>,
+
+    /// Custom code lifetime in seconds (for testing)
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+}
+
+/// Response for the authorization endpoint when no redirect URI is given
+#[derive(Debug, Serialize)]
+pub struct AuthorizeResponse {
+    pub code: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+}
+
+/// `redirect_uri` with `params` and the echoed `state` appended to its query
+/// Answered with 302 Found like Google's endpoint
+fn redirect_with(redirect_uri: &str, params: &[(&str, &str)], state: Option<&str>) -> Response {
+    let mut location = redirect_uri.to_string();
+    let state = state.map(|state| ("state", state));
+    for (name, value) in params.iter().copied().chain(state) {
+        location.push(if location.contains('?') { '&' } else { '?' });
+        location.push_str(&format!("{name}={}", encode(value)));
+    }
+    (StatusCode::FOUND, [(header::LOCATION, location)]).into_response()
+}
+
+/// Authorization error, redirected to the client when it gave a redirect URI
+fn authorize_error(request: &AuthorizeRequest, error: &str, description: String) -> Response {
+    match &request.redirect_uri {
+        Some(redirect_uri) => redirect_with(
+            redirect_uri,
+            &[("error", error), ("error_description", &description)],
+            request.state.as_deref(),
+        ),
+        None => {
+            let error = ErrorResponse {
+                error: error.to_string(),
+                error_description: Some(description),
+            };
+            (StatusCode::BAD_REQUEST, Json(error)).into_response()
+        }
+    }
+}
+
+/// Handler for issuing authorization codes
+/// The user consents right away: the code is issued without any page in between
+async fn authorize_handler(Query(request): Query<AuthorizeRequest>) -> impl IntoResponse {
+    if let Some(response_type) = request.response_type.as_deref().filter(|t| *t != "code") {
+        let description = format!("Unsupported response_type '{response_type}'. Use 'code'");
+        return authorize_error(&request, "unsupported_response_type", description);
+    }
+    let code_challenge = match CodeChallenge::from_params(
+        request.code_challenge.as_deref(),
+        request.code_challenge_method.as_deref(),
+    ) {
+        Ok(code_challenge) => code_challenge,
+        Err(description) => return authorize_error(&request, "invalid_request", description),
+    };
+
+    let expires_in = request.expires_in.unwrap_or(DEFAULT_AUTH_CODE_EXPIRES_IN);
+    let code = AUTH_CODE_STORE.issue(
+        &*clock::system_clock(),
+        resolve_scope(request.scope, None),
+        expires_in,
+        code_challenge,
+    );
+
+    match request.redirect_uri {
+        Some(redirect_uri) => {
+            redirect_with(&redirect_uri, &[("code", &code)], request.state.as_deref())
+        }
+        None => {
+            let response = AuthorizeResponse {
+                code,
+                expires_in,
+                state: request.state,
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+    }
+}
+
+/// Client ID reported as `aud`/`azp` for introspected tokens (not validated in mock)
+pub const MOCK_CLIENT_ID: &str = "mock-client-id.apps.googleusercontent.com";
+
+/// Query parameters for the tokeninfo endpoint
+#[derive(Debug, Deserialize)]
+pub struct TokenInfoRequest {
+    #[serde(default)]
+    pub access_token: Option<String>,
+}
+
+/// Response for the tokeninfo endpoint
+/// Follows Google's tokeninfo response format
+#[derive(Debug, Serialize)]
+pub struct TokenInfoResponse {
+    /// Authorized party (placeholder)
+    pub azp: String,
+    /// Audience (placeholder)
+    pub aud: String,
+    pub scope: String,
+    /// Seconds left until the token expires
+    pub expires_in: i64,
+}
+
+/// Form parameters for the introspection endpoint (RFC 7662)
+#[derive(Debug, Deserialize)]
+pub struct IntrospectRequest {
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Response for the introspection endpoint (RFC 7662)
+/// Inactive tokens only carry `active: false`
+#[derive(Debug, Serialize)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    /// Expiry as a Unix timestamp in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+}
+
+/// Handler for checking an access token, like `https://oauth2.googleapis.com/tokeninfo`
+async fn tokeninfo_handler(Query(request): Query<TokenInfoRequest>) -> impl IntoResponse {
+    let token = request.access_token.unwrap_or_default();
+    let info = remaining_lifetime(&token).zip(get_token_scope(&token));
+
+    match info {
+        Some((expires_in, scope)) => {
+            let response = TokenInfoResponse {
+                azp: MOCK_CLIENT_ID.to_string(),
+                aud: MOCK_CLIENT_ID.to_string(),
+                scope,
+                expires_in,
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        None => {
+            let error = ErrorResponse {
+                error: "invalid_token".to_string(),
+                error_description: Some("Invalid Value".to_string()),
+            };
+            (StatusCode::BAD_REQUEST, Json(error)).into_response()
+        }
+    }
+}
+
+/// Handler for RFC 7662 token introspection
+async fn introspect_handler(Form(request): Form<IntrospectRequest>) -> impl IntoResponse {
+    let token = request.token.unwrap_or_default();
+    let info = remaining_lifetime(&token).zip(get_token_scope(&token));
+
+    let response = match info {
+        Some((expires_in, scope)) => IntrospectResponse {
+            active: true,
+            scope: Some(scope),
+            client_id: Some(MOCK_CLIENT_ID.to_string()),
+            token_type: Some("Bearer".to_string()),
+            exp: Some(clock::system_clock().now().timestamp() + expires_in),
+        },
+        None => IntrospectResponse {
+            active: false,
+            scope: None,
+            client_id: None,
+            token_type: None,
+            exp: None,
+        },
+    };
+    (StatusCode::OK, Json(response))
+}
+
+/// Request of the revocation endpoint
+#[derive(Debug, Deserialize)]
+pub struct RevokeRequest {
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Handler for revoking an access or refresh token, like `https://oauth2.googleapis.com/revoke`
+async fn revoke_handler(
+    State(config): State<OAuthConfig>,
+    Form(request): Form<RevokeRequest>,
+) -> impl IntoResponse {
+    let token = request.token.unwrap_or_default();
+    let revoked_refresh_token = || {
+        let revoked = REFRESH_TOKEN_STORE
+            .write()
+            .unwrap()
+            .remove(&token)
+            .is_some();
+        if revoked {
+            config.refresh_tokens_changed();
+        }
+        revoked
+    };
+    let revoked = TOKEN_STORE.write().unwrap().remove(&token).is_some() || revoked_refresh_token();
+    if revoked {
+        StatusCode::OK.into_response()
+    } else {
+        let error = ErrorResponse {
+            error: "invalid_token".to_string(),
+            error_description: Some("Token expired or revoked".to_string()),
+        };
+        (StatusCode::BAD_REQUEST, Json(error)).into_response()
+    }
+}
+
+/// Handler for the key set that verifies issued ID tokens
+async fn jwks_handler(State(config): State<OAuthConfig>) -> Json<id_token::JwkSet> {
+    let keys = config
+        .id_token_signing_key
+        .as_deref()
+        .map(id_token::Jwk::hs256)
+        .into_iter()
+        .collect();
+    Json(id_token::JwkSet { keys })
+}
+
+/// OpenID Connect discovery document
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub revocation_endpoint: String,
+    pub introspection_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub scopes_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+/// Path of the discovery document below the OAuth endpoints
+const OPENID_CONFIGURATION_PATH: &str = "/.well-known/openid-configuration";
+
+/// Handler for OpenID Connect discovery
+/// Endpoint URLs are built from the request's `Host` and the path the OAuth router is served under
+async fn openid_configuration_handler(
+    State(config): State<OAuthConfig>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Json<OpenIdConfiguration> {
+    let scheme = if config.tls { "https" } else { "http" };
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| uri.authority().map(|authority| authority.as_str()))
+        .unwrap_or("localhost");
+    let prefix = uri
+        .path()
+        .strip_suffix(OPENID_CONFIGURATION_PATH)
+        .unwrap_or_default();
+    let url = |path: &str| format!("{scheme}://{host}{prefix}{path}");
+    let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+
+    Json(OpenIdConfiguration {
+        issuer: id_token::ID_TOKEN_ISSUER.to_string(),
+        authorization_endpoint: url("/authorize"),
+        token_endpoint: url("/token"),
+        revocation_endpoint: url("/revoke"),
+        introspection_endpoint: url("/introspect"),
+        jwks_uri: url("/.well-known/jwks.json"),
+        response_types_supported: strings(&["code"]),
+        subject_types_supported: strings(&["public"]),
+        id_token_signing_alg_values_supported: strings(&["HS256"]),
+        scopes_supported: strings(&["openid", "email"]),
+        grant_types_supported: strings(&[
+            "authorization_code",
+            "refresh_token",
+            "client_credentials",
+        ]),
+        claims_supported: strings(&["iss", "sub", "aud", "iat", "exp", "email"]),
+        code_challenge_methods_supported: strings(&["S256", "plain"]),
+    })
+}
+
+/// Percent-encode a query parameter value
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
 /// Create the router for the OAuth service
-pub fn create_router() -> Router {
-    Router::new().route("/token", post(token_handler))
+/// Without a configured ID token key, a random one is generated for the router's lifetime
+pub fn create_router(mut config: OAuthConfig) -> Router {
+    config
+        .id_token_signing_key
+        .get_or_insert_with(id_token::generate_key);
+    Router::new()
+        .route("/authorize", get(authorize_handler))
+        // Path of Google's endpoint, accounts.google.com/o/oauth2/v2/auth
+        .route("/auth", get(authorize_handler))
+        .route("/token", post(token_handler))
+        .route("/tokeninfo", get(tokeninfo_handler))
+        .route("/introspect", post(introspect_handler))
+        .route("/revoke", post(revoke_handler))
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .route(OPENID_CONFIGURATION_PATH, get(openid_configuration_handler))
+        .with_state(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use clock::MockClock;
+    use std::time::Duration;
+
+    fn mock_clock() -> MockClock {
+        MockClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+                .single()
+                .expect("Valid datetime"),
+        )
+    }
+
+    #[test]
+    fn test_bulk_tokens_evict_stale_tokens_to_stay_bounded() {
+        let clock = mock_clock();
+        let mut store = HashMap::new();
+        let batch = |prefix: &str, expires_in| -> Vec<BulkToken> {
+            (0..10)
+                .map(|i| BulkToken {
+                    access_token: format!("{prefix}-{i}"),
+                    expires_in: if i < 3 { 0 } else { expires_in },
+                    scope: "scope".to_string(),
+                })
+                .collect()
+        };
+
+        // Repeated runs never grow the store past its capacity, keeping the newest tokens
+        for run in 0..5 {
+            clock.advance(Duration::from_secs(1));
+            store_bulk_tokens(&mut store, &clock, &batch(&format!("run{run}"), 60), 25);
+            assert!(store.len() <= 25, "{} tokens after run {run}", store.len());
+        }
+        assert_eq!(store.len(), 25);
+        assert!((0..10).all(|i| store.contains_key(&format!("run4-{i}"))));
+        assert!(!store.contains_key("run0-9"));
+        // Tokens issued already expired are kept and still fail validation as expired
+        assert!(store["run4-0"].is_expired(&clock));
+
+        // Once every earlier token expired over the retention ago, only the new batch is left
+        clock.advance(Duration::from_secs(60) + EXPIRED_TOKEN_RETENTION);
+        store_bulk_tokens(&mut store, &clock, &batch("late", 60), 25);
+        assert_eq!(store.len(), 10);
+        assert!(store.keys().all(|token| token.starts_with("late-")));
+    }
+
+    #[test]
+    fn test_token_expires_after_expires_in() {
+        let clock = mock_clock();
+        let token = TokenMetadata::new(&clock, 10, "scope".to_string());
+
+        clock.advance(Duration::from_secs(9));
+        assert!(!token.is_expired(&clock));
+
+        clock.advance(Duration::from_secs(1));
+        assert!(token.is_expired(&clock));
+    }
+
+    #[test]
+    fn test_negative_expires_in_is_already_expired() {
+        let clock = mock_clock();
+        let token = TokenMetadata::new(&clock, -60, "scope".to_string());
+
+        assert!(token.is_expired(&clock));
+    }
+
+    #[test]
+    fn test_backward_wall_clock_step_does_not_expire_token_early() {
+        let clock = mock_clock();
+        let token = TokenMetadata::new(&clock, 10, "scope".to_string());
+
+        clock.advance(Duration::from_secs(5));
+        clock.step_wall(chrono::Duration::seconds(-30));
+        clock.now();
+
+        assert_eq!(clock.status().backward_jumps, 1);
+        assert!(!token.is_expired(&clock));
+
+        clock.advance(Duration::from_secs(5));
+        assert!(token.is_expired(&clock));
+    }
+
+    #[test]
+    fn test_forward_wall_clock_step_does_not_expire_token_early() {
+        let clock = mock_clock();
+        let token = TokenMetadata::new(&clock, 10, "scope".to_string());
+
+        clock.step_wall(chrono::Duration::hours(1));
+        assert!(!token.is_expired(&clock));
+    }
+
+    #[test]
+    fn test_empty_registry_accepts_any_code() {
+        let clock = mock_clock();
+        let registry = AuthCodeRegistry::default();
+
+        assert_eq!(
+            registry.redeem(&clock, "anything", None, AuthCodeValidation::Auto),
+            Ok(None)
+        );
+        assert_eq!(
+            registry.redeem(&clock, "anything", None, AuthCodeValidation::Auto),
+            Ok(None)
+        );
+        assert_eq!(
+            registry.redeem(&clock, "anything", None, AuthCodeValidation::Strict),
+            Err(AuthCodeError::Unknown)
+        );
+    }
+
+    #[test]
+    fn test_permissive_validation_accepts_unknown_and_replayed_codes() {
+        let clock = mock_clock();
+        let registry = AuthCodeRegistry::default();
+        let code = registry.issue(&clock, "scope.a".to_string(), 60, None);
+        let permissive = AuthCodeValidation::Permissive;
+
+        assert_eq!(
+            registry.redeem(&clock, "unknown", None, permissive),
+            Ok(None)
+        );
+        assert_eq!(
+            registry.redeem(&clock, &code, None, permissive),
+            Ok(Some("scope.a".to_string()))
+        );
+        assert_eq!(registry.redeem(&clock, &code, None, permissive), Ok(None));
+        assert_eq!("PERMISSIVE".parse(), Ok(permissive));
+        assert!("lenient".parse::<AuthCodeValidation>().is_err());
+    }
+
+    #[test]
+    fn test_issued_code_is_single_use() {
+        let clock = mock_clock();
+        let registry = AuthCodeRegistry::default();
+        let code = registry.issue(&clock, "scope.a".to_string(), 60, None);
+
+        assert_eq!(
+            registry.redeem(&clock, "unknown", None, AuthCodeValidation::Auto),
+            Err(AuthCodeError::Unknown)
+        );
+        assert_eq!(
+            registry.redeem(&clock, &code, None, AuthCodeValidation::Auto),
+            Ok(Some("scope.a".to_string()))
+        );
+        assert_eq!(
+            registry.redeem(&clock, &code, None, AuthCodeValidation::Auto),
+            Err(AuthCodeError::Redeemed)
+        );
+    }
+
+    #[test]
+    fn test_issued_code_expires() {
+        let clock = mock_clock();
+        let registry = AuthCodeRegistry::default();
+        let code = registry.issue(&clock, "scope".to_string(), 60, None);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(
+            registry.redeem(&clock, &code, None, AuthCodeValidation::Auto),
+            Err(AuthCodeError::Expired)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_endpoint_rejects_unknown_and_replayed_codes() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let router = create_router(OAuthConfig::default());
+        let exchange = |code: String| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/token")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from(format!(
+                        "grant_type=authorization_code&code={}",
+                        encode(&code)
+                    )))
+                    .expect("Valid request");
+                let response = router.oneshot(request).await.expect("Response");
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+                (status, body)
+            }
+        };
+
+        let code = issue_auth_code(Some("seeded.scope".to_string()), 60);
+
+        let (status, body) = exchange(code.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "seeded.scope");
+
+        let (status, body) = exchange(code).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+
+        let (status, body) = exchange("never-issued".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+    }
+
+    #[tokio::test]
+    async fn test_auth_code_flow_from_authorization_to_refresh() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let router = create_router(OAuthConfig {
+            auth_code_validation: AuthCodeValidation::Strict,
+            ..Default::default()
+        });
+        let get = |uri: &str| {
+            let router = router.clone();
+            let request = Request::builder()
+                .uri(uri)
+                .body(Body::empty())
+                .expect("Valid request");
+            async move { router.oneshot(request).await.expect("Response") }
+        };
+        let token = |body: String| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/token")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from(body))
+                    .expect("Valid request");
+                let response = router.oneshot(request).await.expect("Response");
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+                (status, body)
+            }
+        };
+
+        let response = get(
+            "/auth?client_id=mock-client&redirect_uri=http%3A%2F%2Flocalhost%2Fcb\
+             &response_type=code&scope=flow.scope&state=xyz%20123",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        let location = response.headers()[header::LOCATION].to_str().unwrap();
+        assert!(location.starts_with("http://localhost/cb?code="));
+        let Query(params) =
+            Query::<HashMap<String, String>>::try_from_uri(&location.parse().unwrap())
+                .expect("Query string");
+        assert_eq!(params["state"], "xyz 123");
+        let code = &params["code"];
+
+        let exchange = format!("grant_type=authorization_code&code={}", encode(code));
+        let (status, body) = token(exchange.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "flow.scope");
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+        let (status, body) = token(exchange).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+
+        let (status, body) = token(format!(
+            "grant_type=refresh_token&refresh_token={}",
+            encode(&refresh_token)
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "flow.scope");
+
+        // Strict validation rejects codes that were never issued
+        let (status, body) = token("grant_type=authorization_code&code=made-up".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+
+        // Only the authorization code flow is supported
+        let response =
+            get("/auth?redirect_uri=http%3A%2F%2Flocalhost%2Fcb&response_type=token&state=s").await;
+        let location = response.headers()[header::LOCATION].to_str().unwrap();
+        assert!(location.contains("error=unsupported_response_type"));
+        assert!(location.ends_with("&state=s"));
+        let response = get("/auth?response_type=token").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_pkce_codes_need_the_matching_verifier() {
+        let clock = mock_clock();
+        let registry = AuthCodeRegistry::default();
+        let challenge = CodeChallenge::from_params(Some("plain-verifier"), Some("plain")).unwrap();
+        let issue = || registry.issue(&clock, "scope".to_string(), 60, challenge.clone());
+
+        let code = issue();
+        assert_eq!(
+            registry.redeem(&clock, &code, None, AuthCodeValidation::Auto),
+            Err(AuthCodeError::MissingVerifier)
+        );
+        // A failed exchange uses the code up, like an expired one
+        assert_eq!(
+            registry.redeem(
+                &clock,
+                &code,
+                Some("plain-verifier"),
+                AuthCodeValidation::Auto
+            ),
+            Err(AuthCodeError::Redeemed)
+        );
+        let code = issue();
+        assert_eq!(
+            registry.redeem(&clock, &code, Some("other"), AuthCodeValidation::Permissive),
+            Err(AuthCodeError::VerifierMismatch)
+        );
+        let code = issue();
+        assert_eq!(
+            registry.redeem(
+                &clock,
+                &code,
+                Some("plain-verifier"),
+                AuthCodeValidation::Auto
+            ),
+            Ok(Some("scope".to_string()))
+        );
+
+        // Without a challenge, a verifier is ignored
+        let code = registry.issue(&clock, "scope".to_string(), 60, None);
+        assert_eq!(
+            registry.redeem(&clock, &code, Some("anything"), AuthCodeValidation::Auto),
+            Ok(Some("scope".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pkce_flow_through_the_endpoints() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        // Example from RFC 7636, appendix B
+        const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        const S256_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+        let router = create_router(OAuthConfig::default());
+        let send = |request: Request<Body>| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.expect("Response");
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+                (status, body)
+            }
+        };
+        let authorize = |query: String| {
+            let request = Request::builder()
+                .uri(format!("/authorize?{query}"))
+                .body(Body::empty())
+                .expect("Valid request");
+            send(request)
+        };
+        let exchange = |code: &str, verifier: Option<&str>| {
+            let mut body = format!("grant_type=authorization_code&code={}", encode(code));
+            if let Some(verifier) = verifier {
+                body.push_str(&format!("&code_verifier={}", encode(verifier)));
+            }
+            let request = Request::builder()
+                .method("POST")
+                .uri("/token")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .expect("Valid request");
+            send(request)
+        };
+        let s256_code = || async {
+            let (status, body) = authorize(format!(
+                "code_challenge={S256_CHALLENGE}&code_challenge_method=S256"
+            ))
+            .await;
+            assert_eq!(status, StatusCode::OK);
+            body["code"].as_str().unwrap().to_string()
+        };
+
+        let (status, body) = exchange(&s256_code().await, Some(VERIFIER)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["access_token"].is_string());
+
+        let (status, body) = exchange(&s256_code().await, Some("wrong-verifier")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+        assert_eq!(body["error_description"], "Invalid code verifier.");
+
+        let (status, body) = exchange(&s256_code().await, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+        assert_eq!(body["error_description"], "Missing code verifier.");
+
+        // The plain method compares the verifier itself
+        let (_, body) =
+            authorize("code_challenge=plain-verifier&code_challenge_method=plain".into()).await;
+        let (status, _) = exchange(body["code"].as_str().unwrap(), Some("plain-verifier")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = authorize("code_challenge=x&code_challenge_method=S512".into()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_request");
+    }
+
+    #[test]
+    fn test_remaining_secs_counts_down() {
+        let clock = mock_clock();
+        let metadata = TokenMetadata::new(&clock, 3600, "scope".to_string());
+        assert_eq!(metadata.remaining_secs(&clock), 3600);
+
+        clock.advance(Duration::from_secs(600));
+        assert_eq!(metadata.remaining_secs(&clock), 3000);
+    }
+
+    #[tokio::test]
+    async fn test_tokeninfo_and_introspect() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let router = create_router(OAuthConfig::default());
+        let send = |request: Request<Body>| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.expect("Response");
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+                (status, body)
+            }
+        };
+        let form = |uri: &str, body: String| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .expect("Valid request")
+        };
+        let tokeninfo = |token: &str| {
+            Request::builder()
+                .uri(format!("/tokeninfo?access_token={}", encode(token)))
+                .body(Body::empty())
+                .expect("Valid request")
+        };
+
+        let refresh_token = issue_refresh_token("info.scope".to_string());
+        let (_, tokens) = send(form(
+            "/token",
+            format!(
+                "grant_type=refresh_token&refresh_token={}&expires_in=120",
+                encode(&refresh_token)
+            ),
+        ))
+        .await;
+        let token = tokens["access_token"].as_str().unwrap().to_string();
+
+        let (status, body) = send(tokeninfo(&token)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "info.scope");
+        assert_eq!(body["aud"], MOCK_CLIENT_ID);
+        assert_eq!(body["azp"], MOCK_CLIENT_ID);
+        let expires_in = body["expires_in"].as_i64().unwrap();
+        assert!((119..=120).contains(&expires_in), "{expires_in}");
+
+        let (status, body) = send(form("/introspect", format!("token={}", encode(&token)))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["active"], true);
+        assert_eq!(body["scope"], "info.scope");
+
+        let (_, expired) = send(form(
+            "/token",
+            format!(
+                "grant_type=refresh_token&refresh_token={}&expires_in=-1",
+                encode(&refresh_token)
+            ),
+        ))
+        .await;
+        let expired = expired["access_token"].as_str().unwrap().to_string();
+        // Refresh tokens are not access tokens
+        for token in [expired.as_str(), "never-issued", refresh_token.as_str()] {
+            let (status, body) = send(tokeninfo(token)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_token");
+
+            let (status, body) =
+                send(form("/introspect", format!("token={}", encode(token)))).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body, serde_json::json!({"active": false}));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_grant() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let router = create_router(OAuthConfig::default());
+        let token = |body: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/token")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from(body))
+                    .expect("Valid request");
+                let response = router.oneshot(request).await.expect("Response");
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                serde_json::from_slice::<serde_json::Value>(&bytes).expect("JSON body")
+            }
+        };
+
+        // No client credentials required
+        let body = token("grant_type=client_credentials&scope=service.scope&expires_in=120").await;
+        assert!(body.get("refresh_token").is_none());
+        assert_eq!(body["token_type"], "Bearer");
+        assert_eq!(body["expires_in"], 120);
+        assert_eq!(body["scope"], "service.scope");
+        let access_token = body["access_token"].as_str().unwrap();
+        assert!(validate_token(access_token).is_ok());
+        assert_eq!(
+            get_token_scope(access_token).as_deref(),
+            Some("service.scope")
+        );
+
+        let body =
+            token("grant_type=client_credentials&client_id=svc&client_secret=secret&expires_in=-1")
+                .await;
+        assert!(validate_token(body["access_token"].as_str().unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_endpoint_returns_injected_fault() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let config = OAuthConfig::default();
+        config.faults.set(domain::Fault {
+            target: domain::FaultTarget::OauthToken,
+            http_status: 503,
+            reason: "temporarily_unavailable".to_string(),
+            message: "Try again later".to_string(),
+            grpc_code: "UNAVAILABLE".to_string(),
+            probability: None,
+            remaining: Some(1),
+            after_messages: None,
+        });
+        let router = create_router(config);
+        let token = || {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/token")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from("grant_type=client_credentials"))
+                .expect("Valid request");
+            router.clone().oneshot(request)
+        };
+
+        let response = token().await.expect("Response");
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+        assert_eq!(body["error"], "temporarily_unavailable");
+        assert_eq!(body["error_description"], "Try again later");
+
+        assert_eq!(token().await.expect("Response").status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_issued_token_validator_modes() {
+        let clock = clock::system_clock();
+        let store_token = |token: &str, expires_in: i64| {
+            TOKEN_STORE.write().unwrap().insert(
+                token.to_string(),
+                TokenMetadata::new(&*clock, expires_in, "scope".to_string()),
+            );
+        };
+        store_token("validator-live", 3600);
+        // Negative expires_in is rejected immediately
+        store_token("validator-expired", -1);
+
+        let lenient = IssuedTokenValidator::default();
+        let strict = IssuedTokenValidator { strict: true };
+        for validator in [lenient, strict] {
+            assert_eq!(validator.validate("validator-live"), Ok(()));
+            assert_eq!(
+                validator.validate("validator-expired"),
+                Err(TokenError::Expired)
+            );
+        }
+        assert_eq!(lenient.validate("validator-unknown"), Ok(()));
+        assert_eq!(
+            strict.validate("validator-unknown"),
+            Err(TokenError::Unknown)
+        );
+    }
+
+    #[test]
+    fn test_tokens_near_expiry_are_rotated_once() {
+        let clock = clock::system_clock();
+        let store_token = |token: &str, expires_in: i64| {
+            TOKEN_STORE.write().unwrap().insert(
+                token.to_string(),
+                TokenMetadata::new(&*clock, expires_in, "rotation.scope".to_string()),
+            );
+        };
+        store_token("rotation-fresh", 3600);
+        store_token("rotation-expiring", 30);
+        store_token("rotation-expired", -1);
+
+        assert_eq!(rotate_if_expiring("rotation-fresh", 60), None);
+        assert_eq!(rotate_if_expiring("rotation-expired", 60), None);
+        assert_eq!(rotate_if_expiring("rotation-unknown", 60), None);
+
+        let successor = rotate_if_expiring("rotation-expiring", 60).expect("Should rotate");
+        assert!(successor.starts_with("ya29.mock_"));
+        assert_eq!(validate_token(&successor), Ok(()));
+        assert_eq!(
+            get_token_scope(&successor).as_deref(),
+            Some("rotation.scope")
+        );
+        // Asking again suggests the same successor, and the old token keeps working
+        assert_eq!(
+            rotate_if_expiring("rotation-expiring", 60).as_deref(),
+            Some(successor.as_str())
+        );
+        assert_eq!(validate_token("rotation-expiring"), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_tokens_are_validated_and_rotated() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let refresh = |config: OAuthConfig, refresh_token: String| async move {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/token")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "grant_type=refresh_token&refresh_token={}",
+                    encode(&refresh_token)
+                )))
+                .expect("Valid request");
+            let response = create_router(config)
+                .oneshot(request)
+                .await
+                .expect("Response");
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("Readable body");
+            let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+            (status, body)
+        };
+        let plain = OAuthConfig::default();
+        let rotating = OAuthConfig {
+            rotate_refresh_tokens: true,
+            ..Default::default()
+        };
+
+        let (status, body) = refresh(plain.clone(), "1//never-issued".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+
+        // Without rotation the same refresh token keeps working and none is returned
+        let original = issue_refresh_token("rotate.scope".to_string());
+        for _ in 0..2 {
+            let (status, body) = refresh(plain.clone(), original.clone()).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(body.get("refresh_token").is_none());
+        }
+
+        // With rotation each refresh returns a new token and invalidates the presented one
+        let (status, body) = refresh(rotating.clone(), original.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "rotate.scope");
+        let rotated = body["refresh_token"].as_str().unwrap().to_string();
+        assert!(rotated.starts_with("1//mock_"));
+        assert_ne!(rotated, original);
+
+        let (status, body) = refresh(rotating.clone(), original).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+
+        let (status, body) = refresh(rotating.clone(), rotated).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "rotate.scope");
+
+        // Refresh tokens never pass access token validation
+        let strict = IssuedTokenValidator { strict: true };
+        let refresh_token = issue_refresh_token("scope".to_string());
+        assert_eq!(strict.validate(&refresh_token), Err(TokenError::Unknown));
+    }
+
+    #[tokio::test]
+    async fn test_id_token_only_for_openid_scope() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+        use tower::ServiceExt;
+
+        let router = create_router(OAuthConfig {
+            id_token_signing_key: Some("id-token-secret".to_string()),
+            ..Default::default()
+        });
+        let token = |body: String| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/token")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from(body))
+                    .expect("Valid request");
+                let response = router.oneshot(request).await.expect("Response");
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                serde_json::from_slice::<serde_json::Value>(&bytes).expect("JSON body")
+            }
+        };
+        let claims = |id_token: &str| {
+            let payload = id_token.split('.').nth(1).unwrap();
+            serde_json::from_slice::<id_token::IdTokenClaims>(&BASE64URL.decode(payload).unwrap())
+                .unwrap()
+        };
+
+        let code = issue_auth_code(Some("openid email".to_string()), 600);
+        let body = token(format!(
+            "grant_type=authorization_code&code={}&client_id=my-client&expires_in=120",
+            encode(&code)
+        ))
+        .await;
+        let id_token = body["id_token"].as_str().expect("id_token");
+        assert!(
+            id_token
+                .split('.')
+                .nth(2)
+                .is_some_and(|sig| !sig.is_empty())
+        );
+        let issued = claims(id_token);
+        assert_eq!(issued.iss, id_token::ID_TOKEN_ISSUER);
+        assert_eq!(issued.aud, "my-client");
+        assert_eq!(issued.email, id_token::MOCK_USER_EMAIL);
+        assert_eq!(issued.exp - issued.iat, 120);
+
+        // Refreshing an openid-scoped token issues a new ID token for the mock client
+        let refresh_token = body["refresh_token"].as_str().unwrap();
+        let body = token(format!(
+            "grant_type=refresh_token&refresh_token={}",
+            encode(refresh_token)
+        ))
+        .await;
+        assert_eq!(
+            claims(body["id_token"].as_str().unwrap()).aud,
+            MOCK_CLIENT_ID
+        );
+
+        let code = issue_auth_code(Some("email".to_string()), 600);
+        let body = token(format!(
+            "grant_type=authorization_code&code={}",
+            encode(&code)
+        ))
+        .await;
+        assert!(body.get("id_token").is_none());
+        let body = token("grant_type=client_credentials&scope=openid".to_string()).await;
+        assert!(body.get("id_token").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_id_tokens_verify_with_the_discovered_key_set() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+        use tower::ServiceExt;
+
+        // No configured key: one is generated when the router is created
+        let router = Router::new().nest("/oauth2", create_router(OAuthConfig::default()));
+        let send = |request: Request<Body>| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.expect("Response");
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                serde_json::from_slice::<serde_json::Value>(&bytes).expect("JSON body")
+            }
+        };
+        let get = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .header(header::HOST, "localhost:8080")
+                .body(Body::empty())
+                .expect("Valid request")
+        };
+
+        let discovery = send(get("/oauth2/.well-known/openid-configuration")).await;
+        assert_eq!(discovery["issuer"], id_token::ID_TOKEN_ISSUER);
+        assert_eq!(
+            discovery["token_endpoint"],
+            "http://localhost:8080/oauth2/token"
+        );
+        assert_eq!(
+            discovery["revocation_endpoint"],
+            "http://localhost:8080/oauth2/revoke"
+        );
+        let jwks_uri = discovery["jwks_uri"].as_str().unwrap();
+        assert_eq!(
+            jwks_uri,
+            "http://localhost:8080/oauth2/.well-known/jwks.json"
+        );
+
+        let code = issue_auth_code(Some("openid".to_string()), 600);
+        let body = send(
+            Request::builder()
+                .method("POST")
+                .uri("/oauth2/token")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "grant_type=authorization_code&code={}",
+                    encode(&code)
+                )))
+                .expect("Valid request"),
+        )
+        .await;
+        let id_token = body["id_token"].as_str().expect("id_token");
+        let (signing_input, signature) = id_token.rsplit_once('.').unwrap();
+        let token_header: serde_json::Value = serde_json::from_slice(
+            &BASE64URL
+                .decode(signing_input.split('.').next().unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+
+        let jwks = send(get(jwks_uri.trim_start_matches("http://localhost:8080"))).await;
+        let keys = jwks["keys"].as_array().expect("keys");
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0]["kid"], token_header["kid"]);
+        assert_eq!(keys[0]["kty"], "oct");
+        let key = ring::hmac::Key::new(
+            ring::hmac::HMAC_SHA256,
+            &BASE64URL.decode(keys[0]["k"].as_str().unwrap()).unwrap(),
+        );
+        assert!(
+            ring::hmac::verify(
+                &key,
+                signing_input.as_bytes(),
+                &BASE64URL.decode(signature).unwrap()
+            )
+            .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_invalidates_tokens() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let router = create_router(OAuthConfig::default());
+        let revoke = |token: &str| {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/revoke")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(format!("token={}", encode(token))))
+                .expect("Valid request");
+            router.clone().oneshot(request)
+        };
+
+        let refresh_token = issue_refresh_token("scope".to_string());
+        let access_token = format!("ya29.mock_{}", uuid::Uuid::new_v4());
+        TOKEN_STORE.write().unwrap().insert(
+            access_token.clone(),
+            TokenMetadata::new(&*clock::system_clock(), 3600, "scope".to_string()),
+        );
+
+        for token in [&refresh_token, &access_token] {
+            let response = revoke(token).await.expect("Response");
+            assert_eq!(response.status(), StatusCode::OK);
+            let response = revoke(token).await.expect("Response");
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(
+            !REFRESH_TOKEN_STORE
+                .read()
+                .unwrap()
+                .contains_key(&refresh_token)
+        );
+        let strict = IssuedTokenValidator { strict: true };
+        assert_eq!(strict.validate(&access_token), Err(TokenError::Unknown));
+    }
 }
@@ -0,0 +1,144 @@
+//! Persistence of refresh tokens across restarts
+//!
+//! Long-lived clients keep their refresh tokens, so a restarted mock should still honor
+//! them. The refresh token store is loaded from a JSON file at startup and written back
+//! after every change, to a temporary file renamed into place. Access tokens stay in memory:
+//! clients get new ones by refreshing.
+
+use crate::{REFRESH_TOKEN_STORE, RefreshTokenMetadata};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// JSON file holding the issued refresh tokens and their metadata
+#[derive(Debug)]
+pub struct RefreshTokenFile {
+    path: PathBuf,
+    /// Serializes writers so an older store never replaces a newer one
+    lock: Mutex<()>,
+}
+
+impl RefreshTokenFile {
+    /// Load the refresh tokens stored at `path` into the token store
+    ///
+    /// A missing or unreadable file is reported and no tokens are loaded; the file is
+    /// (re)written with the next change.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                serde_json::from_str::<HashMap<String, RefreshTokenMetadata>>(&json)
+                    .map_err(|e| e.to_string())
+            });
+        match loaded {
+            Ok(tokens) => {
+                tracing::info!("Loaded {} refresh tokens from {path:?}", tokens.len());
+                REFRESH_TOKEN_STORE.write().unwrap().extend(tokens);
+            }
+            Err(e) => {
+                tracing::warn!("No refresh tokens loaded from {path:?}: {e}");
+            }
+        }
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the current refresh tokens, reporting failures without failing the request
+    pub(crate) fn save(&self) {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let json = {
+            let store = REFRESH_TOKEN_STORE.read().unwrap();
+            serde_json::to_string_pretty(&*store)
+        };
+        let tmp_path = self.path.with_extension("tmp");
+        let result = json
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(&tmp_path, json).map_err(|e| e.to_string()))
+            .and_then(|()| std::fs::rename(&tmp_path, &self.path).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::error!("Failed to persist refresh tokens to {:?}: {e}", self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OAuthConfig, create_router, issue_refresh_token};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn post(config: &OAuthConfig, uri: &str, body: String) -> StatusCode {
+        let request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .expect("Valid request");
+        create_router(config.clone())
+            .oneshot(request)
+            .await
+            .expect("Response")
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_refresh_tokens_survive_a_restart() {
+        let dir = tempfile::tempdir().expect("Temp dir");
+        let path = dir.path().join("refresh_tokens.json");
+        let config = OAuthConfig {
+            refresh_token_file: Some(Arc::new(RefreshTokenFile::open(&path))),
+            ..Default::default()
+        };
+        let kept = issue_refresh_token("persist.scope".to_string());
+        let revoked = issue_refresh_token("persist.scope".to_string());
+        config.refresh_tokens_changed();
+        let status = post(&config, "/revoke", format!("token={revoked}")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Simulate a restart: the in-memory store forgets the token until the file is loaded
+        REFRESH_TOKEN_STORE.write().unwrap().remove(&kept);
+        let refresh = |token: &str| format!("grant_type=refresh_token&refresh_token={token}");
+        assert_eq!(
+            post(&OAuthConfig::default(), "/token", refresh(&kept)).await,
+            StatusCode::BAD_REQUEST
+        );
+
+        let restarted = OAuthConfig {
+            refresh_token_file: Some(Arc::new(RefreshTokenFile::open(&path))),
+            ..Default::default()
+        };
+        assert_eq!(
+            post(&restarted, "/token", refresh(&kept)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            post(&restarted, "/token", refresh(&revoked)).await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_missing_or_corrupt_file_loads_no_tokens() {
+        let dir = tempfile::tempdir().expect("Temp dir");
+        let path = dir.path().join("refresh_tokens.json");
+        assert_eq!(RefreshTokenFile::open(&path).path(), path);
+
+        std::fs::write(&path, "not json").unwrap();
+        let file = RefreshTokenFile::open(&path);
+        file.save();
+        // The next save replaces the corrupt file
+        let saved = std::fs::read_to_string(&path).unwrap();
+        serde_json::from_str::<HashMap<String, RefreshTokenMetadata>>(&saved).expect("Valid JSON");
+    }
+}
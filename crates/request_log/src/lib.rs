@@ -0,0 +1,290 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub mod redact;
+pub mod responses;
+
+pub use redact::Redactor;
+pub use responses::ResponseRecorder;
+
+/// A single recorded request, written to the log as one JSON line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum RequestLogEntry {
+    /// An incoming REST request
+    Rest {
+        timestamp: DateTime<Utc>,
+        method: String,
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        query: Option<String>,
+        #[serde(default)]
+        body: String,
+    },
+    /// An incoming gRPC call
+    Grpc {
+        timestamp: DateTime<Utc>,
+        method: String,
+        #[serde(default)]
+        metadata: BTreeMap<String, String>,
+        #[serde(default)]
+        arguments: serde_json::Value,
+    },
+}
+
+/// Append-only JSON lines log of incoming requests
+/// Used to capture a client session so it can be replayed later
+/// Credentials are redacted before entries are written, see [`Redactor`]
+pub struct RequestLog {
+    path: PathBuf,
+    file: Mutex<File>,
+    redactor: Redactor,
+}
+
+impl RequestLog {
+    /// Open (or create) the log file at the given path in append mode
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            redactor: Redactor::default(),
+        })
+    }
+
+    /// Redact entries with these rules instead of the defaults
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Path of the underlying log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry to the log
+    /// Write failures are reported but never interrupt request handling
+    pub fn record(&self, entry: &RequestLogEntry) {
+        let entry = self.redact(entry.clone());
+        let mut line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!("Failed to serialize request log entry: {e}");
+                return;
+            }
+        };
+        line.push('\n');
+
+        let mut file = self
+            .file
+            .lock()
+            .expect("Failed to acquire lock on request log");
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            tracing::error!("Failed to write request log entry to {:?}: {e}", self.path);
+        }
+    }
+
+    fn redact(&self, entry: RequestLogEntry) -> RequestLogEntry {
+        let redactor = &self.redactor;
+        match entry {
+            RequestLogEntry::Rest {
+                timestamp,
+                method,
+                path,
+                query,
+                body,
+            } => RequestLogEntry::Rest {
+                timestamp,
+                method,
+                path: redactor.text(&path),
+                query: query.map(|query| redactor.query(&query)),
+                body: redactor.body(&body),
+            },
+            RequestLogEntry::Grpc {
+                timestamp,
+                method,
+                metadata,
+                mut arguments,
+            } => {
+                redactor.json(&mut arguments);
+                RequestLogEntry::Grpc {
+                    timestamp,
+                    method,
+                    metadata: metadata
+                        .into_iter()
+                        .map(|(name, value)| {
+                            let value = redactor.header(&name, &value);
+                            (name, value)
+                        })
+                        .collect(),
+                    arguments,
+                }
+            }
+        }
+    }
+}
+
+/// Read all entries from a request log file
+/// Blank lines are ignored; any malformed line is reported as an error
+pub fn read_entries(path: &Path) -> std::io::Result<Vec<RequestLogEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid request log entry on line {}: {e}", line_number + 1),
+            )
+        })?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_log_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("request-log-{}-{name}.jsonl", std::process::id()))
+    }
+
+    fn fixed_time() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+            .single()
+            .expect("Valid datetime")
+    }
+
+    #[test]
+    fn test_record_and_read_entries_round_trip() {
+        let path = temp_log_path("round-trip");
+        let _ = std::fs::remove_file(&path);
+
+        let rest_entry = RequestLogEntry::Rest {
+            timestamp: fixed_time(),
+            method: "POST".to_string(),
+            path: "/control/videos".to_string(),
+            query: None,
+            body: r#"{"id":"video-1"}"#.to_string(),
+        };
+        let grpc_entry = RequestLogEntry::Grpc {
+            timestamp: fixed_time(),
+            method: "stream_list".to_string(),
+            metadata: BTreeMap::from([("x-request-id".to_string(), "req-1".to_string())]),
+            arguments: serde_json::json!({ "live_chat_id": "chat-1" }),
+        };
+
+        let log = RequestLog::open(&path).expect("Should open request log");
+        log.record(&rest_entry);
+        log.record(&grpc_entry);
+
+        let entries = read_entries(&path).expect("Should read request log");
+        assert_eq!(entries, vec![rest_entry, grpc_entry]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_open_appends_to_existing_log() {
+        let path = temp_log_path("append");
+        let _ = std::fs::remove_file(&path);
+
+        let entry = RequestLogEntry::Rest {
+            timestamp: fixed_time(),
+            method: "GET".to_string(),
+            path: "/youtube/v3/videos".to_string(),
+            query: Some("part=snippet&id=test-video-1".to_string()),
+            body: String::new(),
+        };
+
+        RequestLog::open(&path).expect("Should open").record(&entry);
+        RequestLog::open(&path)
+            .expect("Should reopen")
+            .record(&entry);
+
+        let entries = read_entries(&path).expect("Should read request log");
+        assert_eq!(entries.len(), 2, "Reopening should append, not truncate");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_record_redacts_credentials() {
+        let path = temp_log_path("redacted");
+        let _ = std::fs::remove_file(&path);
+        let token = "ya29.mock_0123456789abcdef";
+
+        let log = RequestLog::open(&path).expect("Should open request log");
+        log.record(&RequestLogEntry::Rest {
+            timestamp: fixed_time(),
+            method: "POST".to_string(),
+            path: "/token".to_string(),
+            query: Some(format!("access_token={token}")),
+            body: format!("grant_type=refresh_token&refresh_token={token}"),
+        });
+        log.record(&RequestLogEntry::Grpc {
+            timestamp: fixed_time(),
+            method: "stream_list".to_string(),
+            metadata: BTreeMap::from([("authorization".to_string(), format!("Bearer {token}"))]),
+            arguments: serde_json::json!({ "live_chat_id": "chat-1" }),
+        });
+
+        let contents = std::fs::read_to_string(&path).expect("Should read request log");
+        assert!(
+            !contents.contains(token),
+            "Token written verbatim: {contents}"
+        );
+        assert!(contents.contains(&redact::marker(token)));
+        assert!(contents.contains(&redact::marker(&format!("Bearer {token}"))));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_record_keeps_credentials_when_redaction_is_disabled() {
+        let path = temp_log_path("unredacted");
+        let _ = std::fs::remove_file(&path);
+
+        let entry = RequestLogEntry::Rest {
+            timestamp: fixed_time(),
+            method: "GET".to_string(),
+            path: "/youtube/v3/videos".to_string(),
+            query: Some("key=my-key".to_string()),
+            body: String::new(),
+        };
+        RequestLog::open(&path)
+            .expect("Should open request log")
+            .with_redactor(Redactor::disabled())
+            .record(&entry);
+
+        let entries = read_entries(&path).expect("Should read request log");
+        assert_eq!(entries, vec![entry]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_read_entries_rejects_malformed_line() {
+        let path = temp_log_path("malformed");
+        std::fs::write(&path, "{\"transport\":\"rest\"}\n").expect("Should write file");
+
+        let result = read_entries(&path);
+        assert!(result.is_err(), "Malformed entry should be an error");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
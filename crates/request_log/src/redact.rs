@@ -0,0 +1,314 @@
+//! Redaction of credentials in logs and recordings
+//!
+//! A [`Redactor`] replaces sensitive values with `[redacted:<hash>]`, where the hash is a
+//! short FNV-1a digest of the value. The same token redacts to the same marker in the
+//! access log and the request log, so requests can still be correlated without exposing
+//! it. Values are sensitive when they sit under a known header, metadata or parameter
+//! name, or when they look like a token on their own.
+
+use regex::Regex;
+use std::collections::BTreeSet;
+
+/// Headers and gRPC metadata whose values are always redacted
+pub const DEFAULT_SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-goog-api-key",
+    "cookie",
+    "set-cookie",
+];
+
+/// Query, form and JSON body parameters whose values are always redacted
+pub const DEFAULT_SENSITIVE_PARAMS: &[&str] = &[
+    "key",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "token",
+    "code",
+    "client_secret",
+    "password",
+];
+
+/// Token-looking strings redacted wherever they appear: bearer credentials, Google access
+/// and refresh tokens, and API keys
+pub const DEFAULT_SENSITIVE_PATTERNS: &[&str] = &[
+    r"(?i)\bbearer\s+[A-Za-z0-9._~+/=-]+",
+    r"\bya29\.[A-Za-z0-9._-]+",
+    r"\b1//[A-Za-z0-9._-]+",
+    r"\bAIza[A-Za-z0-9_-]{20,}",
+];
+
+/// Marker replacing a redacted value
+pub fn marker(value: &str) -> String {
+    // FNV-1a: stable across processes and builds, unlike the std hasher
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in value.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("[redacted:{:08x}]", hash >> 32)
+}
+
+/// Whether `text` contains a redaction marker, i.e. a value that was not recorded verbatim
+pub fn contains_marker(text: &str) -> bool {
+    text.match_indices("[redacted:").any(|(start, prefix)| {
+        let hash = &text.as_bytes()[start + prefix.len()..];
+        hash.len() > 8 && hash[..8].iter().all(u8::is_ascii_hexdigit) && hash[8] == b']'
+    })
+}
+
+/// Rules deciding which values are redacted
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    enabled: bool,
+    headers: BTreeSet<String>,
+    params: BTreeSet<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Default for Redactor {
+    /// Redact the known-sensitive headers, parameters and token patterns
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            headers: DEFAULT_SENSITIVE_HEADERS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            params: DEFAULT_SENSITIVE_PARAMS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            patterns: DEFAULT_SENSITIVE_PATTERNS
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("Default patterns are valid"))
+                .collect(),
+        }
+    }
+}
+
+impl Redactor {
+    /// Redactor leaving every value as it is
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    /// Also redact the values of these headers and metadata keys, matched case-insensitively
+    pub fn with_headers<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.headers.extend(
+            names
+                .into_iter()
+                .map(|name| name.trim().to_ascii_lowercase()),
+        );
+        self
+    }
+
+    /// Also redact the values of these query, form and JSON body parameters
+    pub fn with_params<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.params
+            .extend(names.into_iter().map(|name| name.trim().to_string()));
+        self
+    }
+
+    /// Also redact every match of `pattern`
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, String> {
+        let pattern = Regex::new(pattern).map_err(|e| format!("Invalid redaction pattern: {e}"))?;
+        self.patterns.push(pattern);
+        Ok(self)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Value of a header or metadata entry as it may be logged
+    pub fn header(&self, name: &str, value: &str) -> String {
+        if self.enabled && self.headers.contains(&name.to_ascii_lowercase()) {
+            marker(value)
+        } else {
+            self.text(value)
+        }
+    }
+
+    /// Free text with every token-looking string replaced
+    pub fn text(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        let mut text = text.to_string();
+        for pattern in &self.patterns {
+            text = pattern
+                .replace_all(&text, |captures: &regex::Captures| marker(&captures[0]))
+                .into_owned();
+        }
+        text
+    }
+
+    /// A query string or form body with the values of sensitive parameters replaced
+    pub fn query(&self, query: &str) -> String {
+        if !self.enabled {
+            return query.to_string();
+        }
+        query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) if self.params.contains(name) => {
+                    format!("{name}={}", marker(value))
+                }
+                _ => self.text(pair),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// A request body: JSON has the values of sensitive fields replaced, anything else is
+    /// treated as a form body
+    pub fn body(&self, body: &str) -> String {
+        if !self.enabled || body.is_empty() {
+            return body.to_string();
+        }
+        match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(json) if json.is_object() || json.is_array() => {
+                let mut redacted = json.clone();
+                self.json(&mut redacted);
+                // Leave bodies without credentials byte-for-byte intact
+                if redacted == json {
+                    return body.to_string();
+                }
+                serde_json::to_string(&redacted).unwrap_or_else(|_| self.text(body))
+            }
+            _ => self.query(body),
+        }
+    }
+
+    /// Replace the values of sensitive fields and token-looking strings in a JSON value
+    pub fn json(&self, value: &mut serde_json::Value) {
+        if !self.enabled {
+            return;
+        }
+        match value {
+            serde_json::Value::String(text) => *text = self.text(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|item| self.json(item)),
+            serde_json::Value::Object(fields) => {
+                for (name, field) in fields.iter_mut() {
+                    match field {
+                        serde_json::Value::String(text) if self.params.contains(name) => {
+                            *text = marker(text);
+                        }
+                        _ => self.json(field),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "ya29.mock_0123456789abcdef";
+
+    #[test]
+    fn test_markers_are_stable_and_distinct() {
+        assert_eq!(marker(TOKEN), marker(TOKEN));
+        assert_ne!(marker(TOKEN), marker("ya29.mock_other"));
+        assert!(marker(TOKEN).starts_with("[redacted:"));
+    }
+
+    #[test]
+    fn test_contains_marker_finds_redacted_values() {
+        let redactor = Redactor::default();
+        assert!(contains_marker(&redactor.body(r#"{"password":"hunter2"}"#)));
+        assert!(contains_marker(&format!("code={}", marker("abc"))));
+        assert!(!contains_marker(
+            r#"{"messageText":"[redacted:not a hash]"}"#
+        ));
+        assert!(!contains_marker("[redacted:0123abcd"));
+    }
+
+    #[test]
+    fn test_known_sensitive_values_are_redacted_by_default() {
+        let redactor = Redactor::default();
+        let bearer = format!("Bearer {TOKEN}");
+
+        let header = redactor.header("Authorization", &bearer);
+        assert_eq!(header, marker(&bearer));
+        assert_eq!(
+            redactor.header("x-goog-api-key", "my-key"),
+            marker("my-key")
+        );
+        assert_eq!(redactor.header("user-agent", "client/1.0"), "client/1.0");
+
+        let query = redactor.query(&format!("part=snippet&key=my-key&access_token={TOKEN}"));
+        assert_eq!(
+            query,
+            format!(
+                "part=snippet&key={}&access_token={}",
+                marker("my-key"),
+                marker(TOKEN)
+            )
+        );
+
+        // Token-looking strings are caught under any name
+        let text = redactor.text(&format!("failed to validate {TOKEN}: expired"));
+        assert_eq!(
+            text,
+            format!("failed to validate {}: expired", marker(TOKEN))
+        );
+        assert!(!redactor.text("refresh 1//mock_abc").contains("1//mock_abc"));
+    }
+
+    #[test]
+    fn test_bodies_are_redacted_as_json_or_form() {
+        let redactor = Redactor::default();
+
+        let json = redactor.body(&format!(
+            r#"{{"refresh_token":"opaque","nested":[{{"note":"{TOKEN}"}}],"title":"kept"}}"#
+        ));
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["refresh_token"], marker("opaque"));
+        assert_eq!(json["nested"][0]["note"], marker(TOKEN));
+        assert_eq!(json["title"], "kept");
+
+        let form = redactor.body("grant_type=refresh_token&refresh_token=opaque");
+        assert_eq!(
+            form,
+            format!(
+                "grant_type=refresh_token&refresh_token={}",
+                marker("opaque")
+            )
+        );
+    }
+
+    #[test]
+    fn test_rules_are_configurable() {
+        let redactor = Redactor::default()
+            .with_headers(["X-Session"])
+            .with_params(["session"])
+            .with_pattern(r"sess-[0-9]+")
+            .unwrap();
+        assert_eq!(redactor.header("x-session", "abc"), marker("abc"));
+        assert_eq!(
+            redactor.query("session=abc"),
+            format!("session={}", marker("abc"))
+        );
+        assert_eq!(
+            redactor.text("id sess-42"),
+            format!("id {}", marker("sess-42"))
+        );
+        assert!(Redactor::default().with_pattern("(").is_err());
+
+        let disabled = Redactor::disabled();
+        assert_eq!(
+            disabled.query(&format!("key={TOKEN}")),
+            format!("key={TOKEN}")
+        );
+        assert_eq!(disabled.header("authorization", TOKEN), TOKEN);
+    }
+}
@@ -0,0 +1,103 @@
+//! Recorded responses for fixtures
+//!
+//! A request carrying the `x-mock-record: <name>` header has its response body saved to
+//! `<name>.json` in the configured directory, so responses seen during exploratory runs
+//! can be reused as assertion fixtures.
+
+use std::path::{Path, PathBuf};
+
+/// Request header naming the fixture to record the response as
+pub const RECORD_HEADER: &str = "x-mock-record";
+
+/// Longest accepted fixture name
+pub const MAX_NAME_LEN: usize = 128;
+
+/// Writes recorded response bodies into a directory
+pub struct ResponseRecorder {
+    dir: PathBuf,
+}
+
+impl ResponseRecorder {
+    /// Record into `dir`, which is created on the first recording
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Directory the responses are written to
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File a response recorded as `name` is written to
+    ///
+    /// Names are limited to ASCII letters, digits, `-`, `_` and `.`, do not start with `.`
+    /// and are at most [`MAX_NAME_LEN`] long, so they cannot leave the directory.
+    pub fn path_for(&self, name: &str) -> Result<PathBuf, String> {
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(format!(
+                "{RECORD_HEADER} must be 1 to {MAX_NAME_LEN} characters long"
+            ));
+        }
+        if name.starts_with('.')
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "{RECORD_HEADER} '{name}' may only contain letters, digits, '-', '_' and '.', and must not start with '.'"
+            ));
+        }
+        Ok(self.dir.join(format!("{name}.json")))
+    }
+
+    /// Write `body` as the response recorded as `name`, replacing an earlier recording
+    ///
+    /// The file is renamed into place, so readers never see a partial body.
+    pub fn save(&self, name: &str, body: &[u8]) -> std::io::Result<PathBuf> {
+        let path = self
+            .path_for(name)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        std::fs::create_dir_all(&self.dir)?;
+        let partial = self.dir.join(format!(".{name}.json.partial"));
+        std::fs::write(&partial, body)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_cannot_leave_the_directory() {
+        let recorder = ResponseRecorder::new("/fixtures");
+        assert_eq!(
+            recorder.path_for("videos.list-live_1").unwrap(),
+            Path::new("/fixtures/videos.list-live_1.json")
+        );
+        for name in ["", "../escape", "nested/name", ".hidden", "with space"] {
+            assert!(recorder.path_for(name).is_err(), "{name:?}");
+        }
+        assert!(recorder.path_for(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_save_writes_and_replaces_the_body() {
+        let dir = tempfile::tempdir().expect("Temp dir");
+        let recorder = ResponseRecorder::new(dir.path().join("fixtures"));
+
+        let path = recorder.save("videos", br#"{"items":[]}"#).unwrap();
+        assert_eq!(path, dir.path().join("fixtures/videos.json"));
+        assert_eq!(std::fs::read(&path).unwrap(), br#"{"items":[]}"#);
+
+        recorder.save("videos", br#"{"items":[1]}"#).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), br#"{"items":[1]}"#);
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("fixtures"))
+                .unwrap()
+                .count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! `channels.list`, for clients that resolve a channel before fetching its videos
+//!
+//! Channels are looked up by `id`, a comma-separated list, or with `mine=true`, which
+//! returns the channel of the authenticated user. The mock has a single such channel,
+//! [`MINE_CHANNEL_ID`], whatever credentials are presented.
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::live_chat_rest::bad_request;
+use crate::{PageInfo, VideoState, repository_error_response, representation_etag, unauthorized};
+
+/// Channel returned for `mine=true`, the channel of the dummy data's video
+pub const MINE_CHANNEL_ID: &str = "channel-1";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelsListParams {
+    #[serde(default)]
+    pub part: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub mine: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelListResponse {
+    pub kind: String,
+    pub etag: String,
+    pub page_info: PageInfo,
+    pub items: Vec<Channel>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    pub kind: String,
+    pub etag: String,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<ChannelSnippet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statistics: Option<ChannelStatistics>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelSnippet {
+    pub title: String,
+    pub description: String,
+    pub custom_url: String,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Channel statistics; counts are strings, as in the real API
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelStatistics {
+    pub view_count: String,
+    pub subscriber_count: String,
+    pub hidden_subscriber_count: bool,
+    pub video_count: String,
+}
+
+/// Handler for `channels.list`
+pub(crate) async fn channels_list(
+    State(state): State<VideoState>,
+    headers: HeaderMap,
+    Query(params): Query<ChannelsListParams>,
+) -> Response {
+    if params.part.is_empty() {
+        return bad_request("required", "Required parameter: part".to_string());
+    }
+    let parts: Vec<&str> = params.part.split(',').map(|s| s.trim()).collect();
+    if let Some(part) = parts
+        .iter()
+        .find(|part| !["id", "snippet", "statistics"].contains(part))
+    {
+        return bad_request(
+            "unknownPart",
+            format!(
+                "'{part}' is not a valid part for channels.list. Use id, snippet or statistics"
+            ),
+        );
+    }
+
+    let id = params.id.as_deref().filter(|id| !id.is_empty());
+    let mine = match params.mine.as_deref() {
+        None | Some("") | Some("false") => false,
+        Some("true") => true,
+        Some(value) => {
+            return bad_request(
+                "invalidValue",
+                format!("Invalid value '{value}' for mine. Use true or false"),
+            );
+        }
+    };
+    let ids: Vec<&str> = match (id, mine) {
+        (Some(id), false) => id.split(',').map(str::trim).collect(),
+        (None, true) => {
+            // As in the real API, the authenticated user's channel needs OAuth credentials
+            if !headers.contains_key(header::AUTHORIZATION) {
+                return unauthorized(
+                    "The request uses the mine parameter but is not properly authorized."
+                        .to_string(),
+                    "authorizationRequired",
+                    "Login Required".to_string(),
+                );
+            }
+            vec![MINE_CHANNEL_ID]
+        }
+        (Some(_), true) => {
+            return bad_request(
+                "incompatibleParameters",
+                "Only one of id and mine can be given".to_string(),
+            );
+        }
+        (None, false) => {
+            return bad_request(
+                "missingRequiredParameter",
+                "No filter selected. Expected one of: id, mine".to_string(),
+            );
+        }
+    };
+
+    let include_snippet = parts.contains(&"snippet");
+    let include_statistics = parts.contains(&"statistics");
+    let mut items = Vec::new();
+    for id in ids {
+        // Unknown IDs are left out of the results rather than failing the call
+        let channel = match state.repo.get_channel(id) {
+            Ok(Some(channel)) => channel,
+            Ok(None) => continue,
+            Err(e) => return repository_error_response(&e),
+        };
+        let mut item = Channel {
+            kind: "youtube#channel".to_string(),
+            etag: String::new(),
+            id: channel.id.clone(),
+            snippet: include_snippet.then(|| ChannelSnippet {
+                title: channel.title.clone(),
+                description: channel.description.clone(),
+                custom_url: channel.custom_url.clone(),
+                published_at: channel.published_at,
+            }),
+            statistics: include_statistics.then(|| ChannelStatistics {
+                view_count: channel.view_count.to_string(),
+                subscriber_count: channel.subscriber_count.to_string(),
+                hidden_subscriber_count: false,
+                video_count: channel.video_count.to_string(),
+            }),
+        };
+        item.etag = representation_etag(&item);
+        items.push(item);
+    }
+
+    let response = ChannelListResponse {
+        kind: "youtube#channelListResponse".to_string(),
+        etag: representation_etag(&items),
+        page_info: PageInfo {
+            total_results: items.len() as i32,
+            results_per_page: items.len() as i32,
+        },
+        items,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_router;
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode, header},
+    };
+    use chrono::Utc;
+    use datastore::Repository;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get_json(
+        router: &Router,
+        uri: &str,
+        bearer: Option<&str>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(token) = bearer {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let request = request.body(Body::empty()).expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        (status, serde_json::from_slice(&bytes).expect("JSON body"))
+    }
+
+    fn router() -> Router {
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        repo.add_channel(domain::Channel {
+            id: "channel-2".to_string(),
+            title: "Second Channel".to_string(),
+            description: String::new(),
+            custom_url: "@second".to_string(),
+            published_at: Utc::now(),
+            subscriber_count: 5,
+            video_count: 0,
+            view_count: 7,
+        })
+        .unwrap();
+        create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_channels_are_listed_by_id_with_requested_parts() {
+        let router = router();
+
+        let (status, body) = get_json(
+            &router,
+            "/channels?part=snippet,statistics&id=channel-1,unknown,channel-2",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kind"], "youtube#channelListResponse");
+        assert_eq!(body["pageInfo"]["totalResults"], 2);
+        let channel = &body["items"][0];
+        assert_eq!(channel["kind"], "youtube#channel");
+        assert_eq!(channel["id"], "channel-1");
+        assert_eq!(channel["snippet"]["title"], "Mock Channel");
+        assert_eq!(channel["snippet"]["customUrl"], "@mockchannel");
+        assert_eq!(channel["statistics"]["subscriberCount"], "1200");
+        assert_eq!(channel["statistics"]["videoCount"], "1");
+        assert_eq!(channel["statistics"]["viewCount"], "34000");
+        assert_eq!(body["items"][1]["id"], "channel-2");
+
+        let (_, body) = get_json(&router, "/channels?part=id&id=channel-2", None).await;
+        assert!(body["items"][0].get("snippet").is_none());
+        assert!(body["items"][0].get("statistics").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mine_returns_the_authenticated_channel() {
+        let router = router();
+
+        let (status, body) =
+            get_json(&router, "/channels?part=snippet&mine=true", Some("token")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["items"][0]["id"], super::MINE_CHANNEL_ID);
+
+        let (status, body) = get_json(&router, "/channels?part=snippet&mine=true", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            body["error"]["errors"][0]["reason"],
+            "authorizationRequired"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_channel_requests_are_rejected() {
+        let router = router();
+
+        for (uri, reason) in [
+            ("/channels?id=channel-1", "required"),
+            ("/channels?part=contentDetails&id=channel-1", "unknownPart"),
+            ("/channels?part=snippet", "missingRequiredParameter"),
+            (
+                "/channels?part=snippet&id=channel-1&mine=true",
+                "incompatibleParameters",
+            ),
+            ("/channels?part=snippet&mine=yes", "invalidValue"),
+        ] {
+            let (status, body) = get_json(&router, uri, Some("token")).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(body["error"]["errors"][0]["reason"], reason, "{uri}");
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use axum::{
     Json, Router,
-    extract::{Query, State},
-    http::{Request, StatusCode, header},
+    extract::{FromRef, Query, State},
+    http::{HeaderMap, HeaderValue, Method, Request, StatusCode, header},
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::get,
@@ -10,6 +10,55 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+mod channels;
+mod live_chat_rest;
+mod search;
+
+pub use channels::MINE_CHANNEL_ID;
+pub use live_chat_rest::{POLLING_INTERVAL_MILLIS, SCHEDULED_POLLING_INTERVAL_MILLIS};
+
+/// Shared state for the videos and live chat REST handlers
+#[derive(Clone)]
+pub struct VideoState {
+    pub repo: Arc<dyn datastore::Repository>,
+    pub display_message_policy: domain::DisplayMessagePolicy,
+    /// Quota charged for each call
+    pub quota: Arc<domain::QuotaLedger>,
+    /// Faults injected into the REST API via the control API
+    pub faults: Arc<domain::FaultConfig>,
+    /// Longest message text accepted by `liveChatMessages.insert`, unlimited when `None`
+    pub max_text_len: Option<usize>,
+}
+
+impl VideoState {
+    pub fn new(
+        repo: Arc<dyn datastore::Repository>,
+        display_message_policy: domain::DisplayMessagePolicy,
+        quota: Arc<domain::QuotaLedger>,
+        faults: Arc<domain::FaultConfig>,
+    ) -> Self {
+        Self {
+            repo,
+            display_message_policy,
+            quota,
+            faults,
+            max_text_len: None,
+        }
+    }
+
+    /// Reject inserted chat messages whose text is longer than `max_text_len` characters
+    pub fn with_max_text_len(mut self, max_text_len: Option<usize>) -> Self {
+        self.max_text_len = max_text_len;
+        self
+    }
+}
+
+impl FromRef<VideoState> for Arc<dyn datastore::Repository> {
+    fn from_ref(state: &VideoState) -> Self {
+        Arc::clone(&state.repo)
+    }
+}
+
 // Constant for the default live chat ID - this should match the one used in live_chat_service
 pub const DEFAULT_LIVE_CHAT_ID: &str = "live-chat-id-1";
 
@@ -43,6 +92,44 @@ pub struct ErrorItem {
     pub message: String,
 }
 
+/// Error body of calls rejected for exceeding the daily quota
+/// The standard error, plus the canonical status and the quota that ran out
+#[derive(Debug, Serialize)]
+pub struct QuotaErrorResponse {
+    pub error: QuotaErrorDetail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuotaErrorDetail {
+    #[serde(flatten)]
+    pub base: ErrorDetail,
+    /// Canonical status, e.g. `RESOURCE_EXHAUSTED`
+    pub status: String,
+    pub details: Vec<QuotaErrorInfo>,
+}
+
+/// `google.rpc.ErrorInfo` entry naming the exhausted quota
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaErrorInfo {
+    #[serde(rename = "@type")]
+    pub type_url: String,
+    pub reason: String,
+    pub domain: String,
+    pub metadata: QuotaErrorMetadata,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaErrorMetadata {
+    /// Daily limit in units, as a string like the real metadata values
+    pub quota_limit: String,
+    /// Key the call was charged against
+    pub quota_user: String,
+    pub quota_metric: String,
+    pub service: String,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideosListResponse {
@@ -73,20 +160,54 @@ pub struct Video {
     pub live_streaming_details: Option<LiveStreamingDetails>,
 }
 
+/// Video snippet; only `title` and `channelTitle` are present at the minimal detail level
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoSnippet {
-    pub published_at: DateTime<Utc>,
-    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
     pub title: String,
-    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub channel_title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub live_broadcast_content: Option<domain::LiveBroadcastContent>,
+}
+
+/// Mock-specific header selecting how much of the snippet is returned
+pub const DETAIL_HEADER: &str = "x-mock-detail";
+
+/// Snippet detail level requested through the `x-mock-detail` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailLevel {
+    /// Only `title` and `channelTitle`, to exercise clients' handling of absent optional fields
+    Minimal,
+    /// Every snippet field, as the real API returns
+    Full,
+}
+
+impl std::str::FromStr for DetailLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "minimal" => Ok(Self::Minimal),
+            "full" => Ok(Self::Full),
+            _ => Err(format!(
+                "Invalid {DETAIL_HEADER} header '{s}'. Use 'minimal' or 'full'"
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LiveStreamingDetails {
-    pub active_live_chat_id: String,
+    /// Absent while the chat has not started and `SCHEDULED_CHAT_NOT_STARTED` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_live_chat_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub actual_start_time: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -99,10 +220,58 @@ pub struct LiveStreamingDetails {
     pub concurrent_viewers: Option<u64>,
 }
 
+/// Map a repository failure to the Google error envelope
+/// Backend failures become 500 `backendError` rather than a panic
+pub(crate) fn repository_error_response(error: &datastore::RepositoryError) -> Response {
+    let (status, reason) = match error {
+        datastore::RepositoryError::NotFound => (StatusCode::NOT_FOUND, "notFound"),
+        datastore::RepositoryError::Conflict => (StatusCode::CONFLICT, "conflict"),
+        datastore::RepositoryError::Invalid(_) => (StatusCode::BAD_REQUEST, "invalidParameter"),
+        datastore::RepositoryError::Backend(_) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, "backendError")
+        }
+    };
+    let error = ErrorResponse {
+        error: ErrorDetail {
+            code: status.as_u16(),
+            message: error.to_string(),
+            errors: vec![ErrorItem {
+                domain: "global".to_string(),
+                reason: reason.to_string(),
+                message: error.to_string(),
+            }],
+        },
+    };
+    (status, Json(error)).into_response()
+}
+
 async fn videos_list(
     State(repo): State<Arc<dyn datastore::Repository>>,
+    headers: HeaderMap,
     Query(params): Query<VideosListParams>,
 ) -> impl IntoResponse {
+    // Snippets are full unless the mock-specific detail header asks otherwise
+    let detail = match headers.get(DETAIL_HEADER).map(|value| value.to_str()) {
+        None => DetailLevel::Full,
+        Some(value) => match value.map_err(|e| e.to_string()).and_then(str::parse) {
+            Ok(detail) => detail,
+            Err(message) => {
+                let error = ErrorResponse {
+                    error: ErrorDetail {
+                        code: 400,
+                        message: message.clone(),
+                        errors: vec![ErrorItem {
+                            domain: "global".to_string(),
+                            reason: "invalidParameter".to_string(),
+                            message,
+                        }],
+                    },
+                };
+                return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+            }
+        },
+    };
+
     // Validate required parameters
     // Note: The actual YouTube API behavior for missing required parameters is unconfirmed.
     // This implementation returns 400 Bad Request to enforce proper API usage.
@@ -140,7 +309,24 @@ async fn videos_list(
     let video_id = params.id.split(',').next().unwrap_or("video-1").to_string();
 
     // Fetch video from datastore
-    let video_data = repo.get_video(&video_id);
+    let video_data = match repo.get_video(&video_id) {
+        Ok(video_data) => video_data,
+        Err(e) => return repository_error_response(&e),
+    };
+
+    // Chats that have not started are not advertised when they are closed to clients
+    let chat_open = match &video_data {
+        Some(video_data) if domain::scheduled_chat_not_started() => {
+            match video_data.live_chat_id.as_deref().map(|live_chat_id| {
+                repo.live_chat_not_started(live_chat_id, clock::system_clock().now())
+            }) {
+                Some(Ok(not_started)) => !not_started,
+                Some(Err(e)) => return repository_error_response(&e),
+                None => true,
+            }
+        }
+        _ => true,
+    };
 
     // If video not found, return empty items array
     let items = if let Some(video_data) = video_data {
@@ -149,18 +335,21 @@ async fn videos_list(
         let include_snippet = parts.contains(&"snippet");
         let include_live_streaming = parts.contains(&"liveStreamingDetails");
 
-        // Create the video resource
-        let video = Video {
+        // Create the video resource; the etag is filled in from the rest of it
+        let mut video = Video {
             kind: "youtube#video".to_string(),
-            etag: "etag-video-1".to_string(),
+            etag: String::new(),
             id: video_data.id.clone(),
             snippet: if include_snippet {
+                let full = detail == DetailLevel::Full;
                 Some(VideoSnippet {
-                    published_at: video_data.published_at,
-                    channel_id: video_data.channel_id.clone(),
+                    published_at: full.then_some(video_data.published_at),
+                    channel_id: full.then(|| video_data.channel_id.clone()),
                     title: video_data.title.clone(),
-                    description: video_data.description.clone(),
+                    description: full.then(|| video_data.description.clone()),
                     channel_title: video_data.channel_title.clone(),
+                    live_broadcast_content: full
+                        .then(|| video_data.live_broadcast_content(clock::system_clock().now())),
                 })
             } else {
                 None
@@ -170,7 +359,7 @@ async fn videos_list(
                     .live_chat_id
                     .as_ref()
                     .map(|live_chat_id| LiveStreamingDetails {
-                        active_live_chat_id: live_chat_id.clone(),
+                        active_live_chat_id: chat_open.then(|| live_chat_id.clone()),
                         actual_start_time: video_data.actual_start_time,
                         actual_end_time: video_data.actual_end_time,
                         scheduled_start_time: video_data.scheduled_start_time,
@@ -181,6 +370,7 @@ async fn videos_list(
                 None
             },
         };
+        video.etag = representation_etag(&video);
         vec![video]
     } else {
         vec![]
@@ -188,7 +378,7 @@ async fn videos_list(
 
     let response = VideosListResponse {
         kind: "youtube#videoListResponse".to_string(),
-        etag: "etag-list-1".to_string(),
+        etag: representation_etag(&items),
         page_info: PageInfo {
             total_results: items.len() as i32,
             results_per_page: items.len() as i32,
@@ -200,10 +390,38 @@ async fn videos_list(
     (StatusCode::OK, Json(response)).into_response()
 }
 
+/// Etag derived from the serialized representation, so it changes with any returned field,
+/// including which parts were requested
+fn representation_etag<T: Serialize>(representation: &T) -> String {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(representation)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("etag-{:016x}", hasher.finish())
+}
+
+/// 401 response in the Google error envelope
+fn unauthorized(message: String, reason: &str, item_message: String) -> Response {
+    let error = ErrorResponse {
+        error: ErrorDetail {
+            code: 401,
+            message,
+            errors: vec![ErrorItem {
+                domain: "global".to_string(),
+                reason: reason.to_string(),
+                message: item_message,
+            }],
+        },
+    };
+    (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+}
+
 // Middleware to check authorization for REST API
 // Checks for either:
-// 1. 'key' query parameter (API key)
-// 2. 'Authorization' header (OAuth 2.0)
+// 1. 'key' query parameter or 'x-goog-api-key' header (API key)
+// 2. 'Authorization: Bearer' header (OAuth 2.0), validated against the issued tokens
 async fn check_auth(request: Request<axum::body::Body>, next: Next) -> Response {
     // Check if auth check is enabled via environment variable
     let require_auth = std::env::var("REQUIRE_AUTH")
@@ -215,65 +433,768 @@ async fn check_auth(request: Request<axum::body::Body>, next: Next) -> Response
         return next.run(request).await;
     }
 
-    // Extract query parameters to check for 'key' parameter
+    // Extract query parameters to check for a non-empty 'key' parameter
     let uri = request.uri();
     let query = uri.query().unwrap_or("");
-    let has_key_param = query.split('&').any(|param| param.starts_with("key="));
+    let has_key_param = query.split('&').any(|param| {
+        param
+            .strip_prefix("key=")
+            .is_some_and(|key| !key.is_empty())
+    });
+    let has_key_header = request.headers().contains_key("x-goog-api-key");
 
     // Check for Authorization header and validate token expiry
     let auth_header = request.headers().get(header::AUTHORIZATION);
-    let has_auth_header = auth_header.is_some();
 
-    if !has_key_param && !has_auth_header {
-        let error = ErrorResponse {
-            error: ErrorDetail {
-                code: 401,
-                message: "Request is missing required authentication credential. Expected OAuth 2 access token, login cookie or other valid authentication credential.".to_string(),
-                errors: vec![ErrorItem {
-                    domain: "global".to_string(),
-                    reason: "required".to_string(),
-                    message: "Login Required".to_string(),
-                }],
-            },
-        };
-        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    if !has_key_param && !has_key_header && auth_header.is_none() {
+        return unauthorized(
+            "Request is missing required authentication credential. Expected OAuth 2 access token, login cookie or other valid authentication credential.".to_string(),
+            "required",
+            "Login Required".to_string(),
+        );
     }
 
-    // Validate OAuth token expiry if Authorization header is present
-    #[allow(clippy::collapsible_if)]
+    // Validate the OAuth token if an Authorization header is present
     if let Some(auth_value) = auth_header {
-        if let Ok(auth_str) = auth_value.to_str() {
-            // Extract token from "Bearer <token>" format
-            if let Some(token) = auth_str
+        // Extract token from "Bearer <token>" format
+        let token = auth_value.to_str().ok().and_then(|auth_str| {
+            auth_str
                 .strip_prefix("Bearer ")
                 .or_else(|| auth_str.strip_prefix("bearer "))
-            {
-                // Validate token expiry
-                if let Err(err_msg) = oauth_service::validate_token(token) {
-                    let error = ErrorResponse {
-                        error: ErrorDetail {
-                            code: 401,
-                            message: format!("Invalid Credentials: {err_msg}"),
-                            errors: vec![ErrorItem {
-                                domain: "global".to_string(),
-                                reason: "authError".to_string(),
-                                message: err_msg,
-                            }],
-                        },
-                    };
-                    return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
-                }
+        });
+        let validated = match token {
+            Some(token) => oauth_service::validate_token(token),
+            None => Err("Expected a Bearer token".to_string()),
+        };
+        if let Err(err_msg) = validated {
+            return unauthorized(
+                format!("Invalid Credentials: {err_msg}"),
+                "authError",
+                err_msg,
+            );
+        }
+
+        // Mock extension: suggest a fresh token once the presented one nears expiry
+        let rotated = token
+            .zip(rotation_threshold_secs())
+            .and_then(|(token, threshold)| oauth_service::rotate_if_expiring(token, threshold));
+        if let Some(rotated) = rotated {
+            let mut response = next.run(request).await;
+            if let Ok(value) = HeaderValue::from_str(&rotated) {
+                response.headers_mut().insert(ROTATED_TOKEN_HEADER, value);
             }
+            return response;
         }
     }
 
     next.run(request).await
 }
 
-// Create the router for the video API
-pub fn create_router(repo: Arc<dyn datastore::Repository>) -> Router {
+/// Response header carrying the token a client should adopt in place of the one it sent
+pub const ROTATED_TOKEN_HEADER: &str = "x-mock-rotated-token";
+
+// Parse OAUTH_ROTATION_THRESHOLD_SECS environment variable
+// When set above 0, bearer tokens with at most that many seconds left are rotated
+fn rotation_threshold_secs() -> Option<i64> {
+    std::env::var("OAUTH_ROTATION_THRESHOLD_SECS")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|&threshold| threshold > 0)
+}
+
+// Middleware charging each call to the quota ledger
+// Runs after the auth check, so rejected credentials are not charged
+// Calls the key cannot afford under an enforced daily limit get the real quotaExceeded error,
+// with 403 or, if configured, 429
+async fn charge_quota(
+    State(quota): State<Arc<domain::QuotaLedger>>,
+    request: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let endpoint = match (request.method(), request.uri().path()) {
+        (_, "/videos") => domain::QuotaEndpoint::VideosList,
+        (_, "/search") => domain::QuotaEndpoint::SearchList,
+        (_, "/channels") => domain::QuotaEndpoint::ChannelsList,
+        (&Method::POST, "/liveChat/messages") => domain::QuotaEndpoint::LiveChatMessagesInsert,
+        (_, "/liveChat/messages") => domain::QuotaEndpoint::LiveChatMessagesList,
+        _ => return next.run(request).await,
+    };
+
+    let key_param = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|param| param.strip_prefix("key="))
+    });
+    let key_header = request
+        .headers()
+        .get("x-goog-api-key")
+        .and_then(|value| value.to_str().ok());
+    let key = domain::quota::caller_key(
+        key_param.or(key_header),
+        request.headers().contains_key(header::AUTHORIZATION),
+    );
+    let charge = match quota.charge(endpoint, &key) {
+        Ok(charge) => charge,
+        Err(exceeded) => {
+            let error_status = quota.error_status();
+            let status = StatusCode::from_u16(error_status.code()).unwrap_or(StatusCode::FORBIDDEN);
+            let error = QuotaErrorResponse {
+                error: QuotaErrorDetail {
+                    base: ErrorDetail {
+                        code: status.as_u16(),
+                        message: domain::quota::QUOTA_EXCEEDED_MESSAGE.to_string(),
+                        errors: vec![ErrorItem {
+                            domain: "youtube.quota".to_string(),
+                            reason: domain::quota::QUOTA_EXCEEDED.to_string(),
+                            message: domain::quota::QUOTA_EXCEEDED_MESSAGE.to_string(),
+                        }],
+                    },
+                    status: error_status.canonical_name().to_string(),
+                    details: vec![QuotaErrorInfo {
+                        type_url: "type.googleapis.com/google.rpc.ErrorInfo".to_string(),
+                        reason: "RATE_LIMIT_EXCEEDED".to_string(),
+                        domain: "googleapis.com".to_string(),
+                        metadata: QuotaErrorMetadata {
+                            quota_limit: exceeded.daily_limit.to_string(),
+                            quota_user: key,
+                            quota_metric: "youtube.googleapis.com/default".to_string(),
+                            service: "youtube.googleapis.com".to_string(),
+                        },
+                    }],
+                },
+            };
+            return (status, Json(error)).into_response();
+        }
+    };
+
+    let mut response = next.run(request).await;
+    if quota.cost_headers() {
+        let headers = response.headers_mut();
+        headers.insert(domain::quota::QUOTA_COST_HEADER, charge.cost.into());
+        headers.insert(
+            domain::quota::QUOTA_REMAINING_HEADER,
+            charge.remaining.into(),
+        );
+    }
+    response
+}
+
+// Middleware answering calls with the fault configured for them, before anything else runs
+async fn inject_faults(
+    State(faults): State<Arc<domain::FaultConfig>>,
+    request: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let target = match request.uri().path() {
+        "/videos" => domain::FaultTarget::VideosList,
+        _ => return next.run(request).await,
+    };
+    let Some(fault) = faults.hit(target) else {
+        return next.run(request).await;
+    };
+
+    let status =
+        StatusCode::from_u16(fault.http_status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    // The real API reports quota errors under their own domain
+    let domain = match fault.reason.as_str() {
+        "quotaExceeded" | "rateLimitExceeded" => "youtube.quota",
+        _ => "global",
+    };
+    let error = ErrorResponse {
+        error: ErrorDetail {
+            code: status.as_u16(),
+            message: fault.message.clone(),
+            errors: vec![ErrorItem {
+                domain: domain.to_string(),
+                reason: fault.reason,
+                message: fault.message,
+            }],
+        },
+    };
+    (status, Json(error)).into_response()
+}
+
+// Create the router for the video and live chat APIs
+pub fn create_router(
+    repo: Arc<dyn datastore::Repository>,
+    display_message_policy: domain::DisplayMessagePolicy,
+    quota: Arc<domain::QuotaLedger>,
+    faults: Arc<domain::FaultConfig>,
+) -> Router {
+    router_with_state(VideoState::new(repo, display_message_policy, quota, faults))
+}
+
+/// Create the router for the video and live chat APIs with configured state
+pub fn router_with_state(state: VideoState) -> Router {
     Router::new()
         .route("/videos", get(videos_list))
+        .route("/search", get(search::search_list))
+        .route("/channels", get(channels::channels_list))
+        .route(
+            "/liveChat/messages",
+            get(live_chat_rest::live_chat_messages_list)
+                .post(live_chat_rest::live_chat_messages_insert),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&state.quota),
+            charge_quota,
+        ))
         .route_layer(middleware::from_fn(check_auth))
-        .with_state(repo)
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&state.faults),
+            inject_faults,
+        ))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_backend_errors_return_500_envelope() {
+        let router = create_router(
+            Arc::new(datastore::FailingRepository),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        for uri in [
+            "/videos?part=snippet&id=test-video-1",
+            "/liveChat/messages?liveChatId=test-chat-id&part=snippet",
+        ] {
+            let request = Request::builder()
+                .uri(uri)
+                .body(Body::empty())
+                .expect("Valid request");
+            let response = router.clone().oneshot(request).await.expect("Response");
+            assert_eq!(
+                response.status(),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "{uri}"
+            );
+
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("Readable body");
+            let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+            assert_eq!(body["error"]["code"], 500);
+            assert_eq!(body["error"]["errors"][0]["reason"], "backendError");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snippet_reports_live_broadcast_content() {
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let request = Request::builder()
+            .uri("/videos?part=snippet&id=test-video-1")
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.oneshot(request).await.expect("Response");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+        // The seed video started in the past and has no end time
+        assert_eq!(body["items"][0]["snippet"]["liveBroadcastContent"], "live");
+    }
+
+    #[tokio::test]
+    async fn test_etag_depends_on_requested_parts() {
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let etags = |part: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .uri(format!("/videos?part={part}&id=test-video-1"))
+                    .body(Body::empty())
+                    .expect("Valid request");
+                let response = router.oneshot(request).await.expect("Response");
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+                (
+                    body["etag"].as_str().unwrap().to_string(),
+                    body["items"][0]["etag"].as_str().unwrap().to_string(),
+                )
+            }
+        };
+
+        let snippet = etags("snippet").await;
+        let both = etags("snippet,liveStreamingDetails").await;
+        assert_ne!(snippet.1, both.1);
+        assert_ne!(snippet.0, both.0);
+        assert_ne!(snippet.0, snippet.1);
+        // The same representation keeps its etag
+        assert_eq!(etags("snippet").await, snippet);
+    }
+
+    #[tokio::test]
+    async fn test_detail_header_selects_snippet_fields() {
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let list = |detail: Option<&'static str>| {
+            let router = router.clone();
+            async move {
+                let mut request = Request::builder().uri("/videos?part=snippet&id=test-video-1");
+                if let Some(detail) = detail {
+                    request = request.header(DETAIL_HEADER, detail);
+                }
+                let response = router
+                    .oneshot(request.body(Body::empty()).expect("Valid request"))
+                    .await
+                    .expect("Response");
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Readable body");
+                let body: serde_json::Value = serde_json::from_slice(&bytes).expect("JSON body");
+                (status, body)
+            }
+        };
+        let keys = |body: &serde_json::Value| {
+            let mut keys: Vec<_> = body["items"][0]["snippet"]
+                .as_object()
+                .expect("Snippet")
+                .keys()
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        };
+
+        let (_, minimal) = list(Some("minimal")).await;
+        assert_eq!(keys(&minimal), ["channelTitle", "title"]);
+
+        let (_, default) = list(None).await;
+        let (_, full) = list(Some("full")).await;
+        assert_eq!(keys(&default), keys(&full));
+        assert!(keys(&full).contains(&"description".to_string()));
+
+        let (status, body) = list(Some("verbose")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["errors"][0]["reason"], "invalidParameter");
+    }
+
+    #[tokio::test]
+    async fn test_quota_headers_report_cost_and_remaining() {
+        let quota = Arc::new(domain::QuotaLedger::new(true));
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::clone(&quota),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let call = |uri: &'static str, api_key: Option<&'static str>| {
+            let router = router.clone();
+            async move {
+                let mut request = Request::builder().uri(uri);
+                if let Some(api_key) = api_key {
+                    request = request.header("x-goog-api-key", api_key);
+                }
+                let response = router
+                    .oneshot(request.body(Body::empty()).expect("Valid request"))
+                    .await
+                    .expect("Response");
+                let header = |name: &str| {
+                    response.headers()[name]
+                        .to_str()
+                        .unwrap()
+                        .parse::<u64>()
+                        .unwrap()
+                };
+                (
+                    header(domain::quota::QUOTA_COST_HEADER),
+                    header(domain::quota::QUOTA_REMAINING_HEADER),
+                )
+            }
+        };
+
+        let videos = "/videos?part=snippet&id=test-video-1&key=key-a";
+        let messages = "/liveChat/messages?liveChatId=test-chat-id&part=snippet";
+        assert_eq!(call(videos, None).await, (1, 9_999));
+        assert_eq!(call(videos, None).await, (1, 9_998));
+        assert_eq!(call(messages, Some("key-b")).await, (5, 9_995));
+        // The key parameter wins over the header, as in the auth check
+        let messages_a = "/liveChat/messages?liveChatId=test-chat-id&part=snippet&key=key-a";
+        assert_eq!(call(messages_a, Some("key-b")).await, (5, 9_993));
+
+        let report = quota.report(None);
+        assert_eq!((report.calls, report.units), (4, 12));
+        assert_eq!(report.by_endpoint["videos.list"].units, 2);
+        assert_eq!(report.by_endpoint["liveChatMessages.list"].units, 10);
+        assert_eq!(report.by_key["key-a"].units, 7);
+        assert_eq!(report.by_key["key-b"].remaining, 9_995);
+    }
+
+    #[tokio::test]
+    async fn test_quota_headers_are_opt_in() {
+        let quota = Arc::new(domain::QuotaLedger::default());
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::clone(&quota),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let request = Request::builder()
+            .uri("/videos?part=snippet&id=test-video-1")
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.oneshot(request).await.expect("Response");
+        assert!(
+            !response
+                .headers()
+                .contains_key(domain::quota::QUOTA_COST_HEADER)
+        );
+        // Calls are charged whether or not the headers are enabled
+        assert_eq!(quota.report(None).by_key["anonymous"].units, 1);
+    }
+
+    #[tokio::test]
+    async fn test_enforced_quota_rejects_calls_with_quota_exceeded() {
+        let quota = Arc::new(domain::QuotaLedger::default());
+        quota.set_daily_limit(Some(3));
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::clone(&quota),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let call = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .uri(uri)
+                    .body(Body::empty())
+                    .expect("Valid request");
+                let response = router.oneshot(request).await.expect("Response");
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&bytes).unwrap(),
+                )
+            }
+        };
+
+        let videos = "/videos?part=snippet&id=test-video-1&key=key-a";
+        for _ in 0..3 {
+            assert_eq!(call(videos).await.0, StatusCode::OK);
+        }
+        let (status, body) = call(videos).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"]["code"], 403);
+        assert_eq!(body["error"]["errors"][0]["reason"], "quotaExceeded");
+        assert_eq!(body["error"]["errors"][0]["domain"], "youtube.quota");
+        assert_eq!(
+            body["error"]["message"],
+            domain::quota::QUOTA_EXCEEDED_MESSAGE
+        );
+        assert_eq!(body["error"]["status"], "PERMISSION_DENIED");
+        let metadata = &body["error"]["details"][0]["metadata"];
+        assert_eq!(metadata["quotaLimit"], "3");
+        assert_eq!(metadata["quotaUser"], "key-a");
+        // Other keys keep their own quota; a call costing more than the limit never passes
+        let other = "/videos?part=snippet&id=test-video-1&key=key-b";
+        assert_eq!(call(other).await.0, StatusCode::OK);
+        let messages = "/liveChat/messages?liveChatId=test-chat-id&part=snippet&key=key-c";
+        assert_eq!(call(messages).await.0, StatusCode::FORBIDDEN);
+
+        assert_eq!(quota.report(None).by_key["key-a"].calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_quota_exceeded_can_answer_429() {
+        let quota = Arc::new(
+            domain::QuotaLedger::default()
+                .with_error_status(domain::QuotaErrorStatus::TooManyRequests),
+        );
+        quota.set_daily_limit(Some(0));
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            domain::DisplayMessagePolicy::Raw,
+            quota,
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let request = Request::builder()
+            .uri("/videos?part=snippet&id=test-video-1")
+            .header("authorization", "Bearer token")
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.oneshot(request).await.expect("Response");
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"]["code"], 429);
+        assert_eq!(body["error"]["status"], "RESOURCE_EXHAUSTED");
+        assert_eq!(body["error"]["errors"][0]["reason"], "quotaExceeded");
+        let details = &body["error"]["details"][0];
+        assert_eq!(details["@type"], "type.googleapis.com/google.rpc.ErrorInfo");
+        assert_eq!(details["metadata"]["quotaLimit"], "0");
+        assert_eq!(
+            details["metadata"]["quotaUser"],
+            domain::quota::OAUTH_CALLER
+        );
+    }
+
+    #[tokio::test]
+    async fn test_injected_fault_fails_configured_number_of_calls() {
+        let faults = Arc::new(domain::FaultConfig::default());
+        faults.set(domain::Fault {
+            target: domain::FaultTarget::VideosList,
+            http_status: 403,
+            reason: "quotaExceeded".to_string(),
+            message: "Quota exceeded".to_string(),
+            grpc_code: "UNAVAILABLE".to_string(),
+            probability: None,
+            remaining: Some(2),
+            after_messages: None,
+        });
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            faults,
+        );
+        let call = || {
+            let request = Request::builder()
+                .uri("/videos?part=snippet&id=test-video-1")
+                .body(Body::empty())
+                .expect("Valid request");
+            router.clone().oneshot(request)
+        };
+
+        for _ in 0..2 {
+            let response = call().await.expect("Response");
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("Readable body");
+            let body: serde_json::Value = serde_json::from_slice(&body).expect("JSON body");
+            assert_eq!(body["error"]["code"], 403);
+            assert_eq!(body["error"]["errors"][0]["reason"], "quotaExceeded");
+            assert_eq!(body["error"]["errors"][0]["domain"], "youtube.quota");
+        }
+        assert_eq!(call().await.expect("Response").status(), StatusCode::OK);
+    }
+
+    /// Paths of object keys that are not camelCase
+    fn non_camel_case_keys(value: &serde_json::Value, path: &str, found: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (name, value) in fields {
+                    let child = format!("{path}.{name}");
+                    if name.contains('_') || name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        found.push(child.clone());
+                    }
+                    non_camel_case_keys(value, &child, found);
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    non_camel_case_keys(item, &format!("{path}[]"), found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_responses_are_camel_case() {
+        use crate::channels::{Channel, ChannelListResponse, ChannelSnippet, ChannelStatistics};
+        use crate::live_chat_rest::{
+            LiveChatMessage, LiveChatMessageListResponse, LiveChatMessageSnippet,
+            LiveChatTextMessageDetails, SuperChatDetails,
+        };
+        use crate::search::{
+            SearchListResponse, SearchResult, SearchResultId, SearchResultSnippet,
+        };
+
+        let snippet = |text: Option<&str>, super_chat: Option<SuperChatDetails>| {
+            Some(LiveChatMessageSnippet {
+                message_type: "textMessageEvent".to_string(),
+                live_chat_id: "chat".to_string(),
+                author_channel_id: "channel".to_string(),
+                published_at: Utc::now(),
+                has_display_content: true,
+                display_message: "Hello".to_string(),
+                text_message_details: text.map(|text| LiveChatTextMessageDetails {
+                    message_text: text.to_string(),
+                }),
+                super_chat_details: super_chat,
+                super_sticker_details: None,
+                message_deleted_details: None,
+            })
+        };
+        let author_details = domain::AuthorDetails {
+            channel_id: "channel".to_string(),
+            channel_url: "http://www.youtube.com/channel/channel".to_string(),
+            display_name: "Author".to_string(),
+            profile_image_url: "https://yt3.ggpht.com/avatar".to_string(),
+            is_verified: true,
+            is_chat_owner: false,
+            is_chat_sponsor: false,
+            is_chat_moderator: false,
+        };
+        let super_chat = SuperChatDetails::from(&domain::SuperChatDetails {
+            amount_micros: 5_000_000,
+            currency: "USD".to_string(),
+            tier: 2,
+            user_comment: "Thanks".to_string(),
+        });
+
+        let samples = [
+            serde_json::to_value(VideosListResponse {
+                kind: "youtube#videoListResponse".to_string(),
+                etag: "etag".to_string(),
+                page_info: PageInfo {
+                    total_results: 1,
+                    results_per_page: 1,
+                },
+                next_page_token: Some("token".to_string()),
+                items: vec![Video {
+                    kind: "youtube#video".to_string(),
+                    etag: "etag".to_string(),
+                    id: "video".to_string(),
+                    snippet: Some(VideoSnippet {
+                        published_at: Some(Utc::now()),
+                        channel_id: Some("channel".to_string()),
+                        title: "Title".to_string(),
+                        description: Some(String::new()),
+                        channel_title: "Channel".to_string(),
+                        live_broadcast_content: Some(domain::LiveBroadcastContent::Live),
+                    }),
+                    live_streaming_details: Some(LiveStreamingDetails {
+                        active_live_chat_id: Some("chat".to_string()),
+                        actual_start_time: Some(Utc::now()),
+                        actual_end_time: Some(Utc::now()),
+                        scheduled_start_time: Some(Utc::now()),
+                        scheduled_end_time: Some(Utc::now()),
+                        concurrent_viewers: Some(1),
+                    }),
+                }],
+            }),
+            serde_json::to_value(LiveChatMessageListResponse {
+                kind: "youtube#liveChatMessageListResponse".to_string(),
+                etag: "etag".to_string(),
+                next_page_token: Some("token".to_string()),
+                polling_interval_millis: 1000,
+                offline_at: Some(Utc::now()),
+                page_info: PageInfo {
+                    total_results: 2,
+                    results_per_page: 2,
+                },
+                items: vec![
+                    LiveChatMessage {
+                        kind: "youtube#liveChatMessage".to_string(),
+                        etag: "etag".to_string(),
+                        id: "text".to_string(),
+                        snippet: snippet(Some("Hello"), None),
+                        author_details: Some(author_details.clone()),
+                    },
+                    LiveChatMessage {
+                        kind: "youtube#liveChatMessage".to_string(),
+                        etag: "etag".to_string(),
+                        id: "super-chat".to_string(),
+                        snippet: snippet(None, Some(super_chat)),
+                        author_details: Some(author_details),
+                    },
+                ],
+            }),
+            serde_json::to_value(SearchListResponse {
+                kind: "youtube#searchListResponse".to_string(),
+                etag: "etag".to_string(),
+                next_page_token: Some("token".to_string()),
+                prev_page_token: Some("token".to_string()),
+                page_info: PageInfo {
+                    total_results: 1,
+                    results_per_page: 1,
+                },
+                items: vec![SearchResult {
+                    kind: "youtube#searchResult".to_string(),
+                    etag: "etag".to_string(),
+                    id: SearchResultId {
+                        kind: "youtube#video".to_string(),
+                        video_id: "video".to_string(),
+                    },
+                    snippet: Some(SearchResultSnippet {
+                        published_at: Utc::now(),
+                        channel_id: "channel".to_string(),
+                        title: "Title".to_string(),
+                        description: String::new(),
+                        channel_title: "Channel".to_string(),
+                        live_broadcast_content: domain::LiveBroadcastContent::Live,
+                    }),
+                }],
+            }),
+            serde_json::to_value(ChannelListResponse {
+                kind: "youtube#channelListResponse".to_string(),
+                etag: "etag".to_string(),
+                page_info: PageInfo {
+                    total_results: 1,
+                    results_per_page: 1,
+                },
+                items: vec![Channel {
+                    kind: "youtube#channel".to_string(),
+                    etag: "etag".to_string(),
+                    id: "channel".to_string(),
+                    snippet: Some(ChannelSnippet {
+                        title: "Channel".to_string(),
+                        description: String::new(),
+                        custom_url: "@channel".to_string(),
+                        published_at: Utc::now(),
+                    }),
+                    statistics: Some(ChannelStatistics {
+                        view_count: "1".to_string(),
+                        subscriber_count: "1".to_string(),
+                        hidden_subscriber_count: false,
+                        video_count: "1".to_string(),
+                    }),
+                }],
+            }),
+            serde_json::to_value(ErrorResponse {
+                error: ErrorDetail {
+                    code: 400,
+                    message: "Bad request".to_string(),
+                    errors: vec![ErrorItem {
+                        domain: "global".to_string(),
+                        reason: "badRequest".to_string(),
+                        message: "Bad request".to_string(),
+                    }],
+                },
+            }),
+        ];
+
+        for sample in samples {
+            let sample = sample.expect("Serializable response");
+            let mut found = Vec::new();
+            non_camel_case_keys(&sample, "", &mut found);
+            assert!(found.is_empty(), "{found:?} in {sample}");
+        }
+    }
 }
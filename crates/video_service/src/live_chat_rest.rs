@@ -0,0 +1,982 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use domain::{DEFAULT_MAX_RESULTS, LiveChatState, MAX_MAX_RESULTS};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ErrorDetail, ErrorItem, ErrorResponse, PageInfo, VideoState, repository_error_response,
+};
+
+/// Interval clients are asked to wait between polls, matching the gRPC stream polling interval
+pub const POLLING_INTERVAL_MILLIS: u64 = 1000;
+
+/// Longer interval for chats whose broadcast has not started yet
+pub const SCHEDULED_POLLING_INTERVAL_MILLIS: u64 = 10_000;
+
+/// Channel inserted messages are attributed to, as the mock has no user identities
+/// Its display name comes from the datastore's author registry
+pub const INSERT_AUTHOR_CHANNEL_ID: &str = "mock-user-channel";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatMessagesListParams {
+    #[serde(default)]
+    pub live_chat_id: String,
+    #[serde(default)]
+    pub part: String,
+    #[serde(default)]
+    pub page_token: Option<String>,
+    /// Kept as a string so invalid values produce the standard error envelope
+    #[serde(default)]
+    pub max_results: Option<String>,
+    /// Channel the list is viewed as (mock extension), which sees its own muted messages
+    #[serde(default)]
+    pub viewer_channel_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LiveChatMessagesInsertParams {
+    #[serde(default)]
+    pub part: String,
+}
+
+/// Body of `liveChatMessages.insert`; only text messages can be inserted
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatMessageInsertRequest {
+    /// Generated when absent
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub snippet: Option<LiveChatMessageInsertSnippet>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatMessageInsertSnippet {
+    #[serde(default)]
+    pub live_chat_id: String,
+    /// Defaults to the current time
+    #[serde(default)]
+    pub published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub text_message_details: Option<LiveChatTextMessageInsertDetails>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatTextMessageInsertDetails {
+    #[serde(default)]
+    pub message_text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatMessageListResponse {
+    pub kind: String,
+    pub etag: String,
+    /// Absent once the chat has ended, as no further messages will arrive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_page_token: Option<String>,
+    pub polling_interval_millis: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline_at: Option<DateTime<Utc>>,
+    pub page_info: PageInfo,
+    pub items: Vec<LiveChatMessage>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatMessage {
+    pub kind: String,
+    pub etag: String,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<LiveChatMessageSnippet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_details: Option<domain::AuthorDetails>,
+}
+
+impl LiveChatMessage {
+    /// Resource for a stored message, with the parts requested
+    fn from_domain(
+        msg: &domain::LiveChatMessage,
+        etag: String,
+        display_message_policy: domain::DisplayMessagePolicy,
+        include_snippet: bool,
+        include_author_details: bool,
+    ) -> Self {
+        Self {
+            kind: "youtube#liveChatMessage".to_string(),
+            etag,
+            id: msg.id.clone(),
+            snippet: include_snippet.then(|| LiveChatMessageSnippet {
+                message_type: msg.message_type().to_string(),
+                live_chat_id: msg.live_chat_id.clone(),
+                author_channel_id: msg.author_channel_id.clone(),
+                published_at: msg.published_at,
+                // Deletion events have nothing to display
+                has_display_content: msg.deleted_message_id.is_none(),
+                display_message: display_message_policy.render(&msg.message_text),
+                text_message_details: (msg.message_type() == "textMessageEvent").then(|| {
+                    LiveChatTextMessageDetails {
+                        message_text: msg.message_text.clone(),
+                    }
+                }),
+                super_chat_details: msg.super_chat_details.as_ref().map(SuperChatDetails::from),
+                super_sticker_details: msg
+                    .super_sticker_details
+                    .as_ref()
+                    .map(SuperStickerDetails::from),
+                message_deleted_details: msg.deleted_message_id.as_ref().map(|id| {
+                    MessageDeletedDetails {
+                        deleted_message_id: id.clone(),
+                    }
+                }),
+            }),
+            author_details: include_author_details.then(|| msg.author_details()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatMessageSnippet {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub live_chat_id: String,
+    pub author_channel_id: String,
+    pub published_at: DateTime<Utc>,
+    pub has_display_content: bool,
+    pub display_message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_message_details: Option<LiveChatTextMessageDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub super_chat_details: Option<SuperChatDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub super_sticker_details: Option<SuperStickerDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_deleted_details: Option<MessageDeletedDetails>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageDeletedDetails {
+    pub deleted_message_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperChatDetails {
+    /// A string, as the JSON API encodes 64-bit integers
+    pub amount_micros: String,
+    pub currency: String,
+    pub amount_display_string: String,
+    pub user_comment: String,
+    pub tier: u32,
+}
+
+impl From<&domain::SuperChatDetails> for SuperChatDetails {
+    fn from(details: &domain::SuperChatDetails) -> Self {
+        Self {
+            amount_micros: details.amount_micros.to_string(),
+            currency: details.currency.clone(),
+            amount_display_string: details.amount_display_string(),
+            user_comment: details.user_comment.clone(),
+            tier: details.tier,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperStickerDetails {
+    /// A string, as the JSON API encodes 64-bit integers
+    pub amount_micros: String,
+    pub currency: String,
+    pub amount_display_string: String,
+    pub tier: u32,
+    pub super_sticker_metadata: SuperStickerMetadata,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperStickerMetadata {
+    pub sticker_id: String,
+    pub alt_text: String,
+    pub alt_text_language: String,
+}
+
+impl From<&domain::SuperStickerDetails> for SuperStickerDetails {
+    fn from(details: &domain::SuperStickerDetails) -> Self {
+        Self {
+            amount_micros: details.amount_micros.to_string(),
+            currency: details.currency.clone(),
+            amount_display_string: details.amount_display_string(),
+            tier: details.tier,
+            super_sticker_metadata: SuperStickerMetadata {
+                sticker_id: details.sticker_id.clone(),
+                alt_text: details.alt_text.clone(),
+                alt_text_language: "en".to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChatTextMessageDetails {
+    pub message_text: String,
+}
+
+/// 400 response in the Google error envelope
+pub(crate) fn bad_request(reason: &str, message: String) -> Response {
+    let error = ErrorResponse {
+        error: ErrorDetail {
+            code: 400,
+            message: message.clone(),
+            errors: vec![ErrorItem {
+                domain: "global".to_string(),
+                reason: reason.to_string(),
+                message,
+            }],
+        },
+    };
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+/// Handler for `liveChatMessages.list`
+///
+/// Returns the messages after `pageToken` and a `nextPageToken` that stays valid as new
+/// messages arrive, so polling again with it picks up anything injected in the meantime.
+pub(crate) async fn live_chat_messages_list(
+    State(state): State<VideoState>,
+    Query(params): Query<LiveChatMessagesListParams>,
+) -> Response {
+    if params.part.is_empty() {
+        return bad_request("required", "Required parameter: part".to_string());
+    }
+
+    if params.live_chat_id.is_empty() {
+        return bad_request("required", "Required parameter: liveChatId".to_string());
+    }
+
+    let max_results = match params.max_results.as_deref() {
+        None | Some("") => DEFAULT_MAX_RESULTS,
+        Some(value) => match value.parse::<usize>() {
+            Ok(max) if (1..=MAX_MAX_RESULTS).contains(&max) => max,
+            _ => {
+                return bad_request(
+                    "invalidValue",
+                    format!(
+                        "Invalid value '{value}' for maxResults. Expected 1 to {MAX_MAX_RESULTS}"
+                    ),
+                );
+            }
+        },
+    };
+
+    let start_index = match params.page_token.as_deref() {
+        None | Some("") => 0,
+        Some(token) => match domain::decode_page_token(token) {
+            Ok(index) => index,
+            Err(e) => return bad_request("invalidPageToken", e.to_string()),
+        },
+    };
+
+    // Chats without a stored lifecycle are active until their video ends
+    let chat = match state.repo.get_effective_live_chat(&params.live_chat_id) {
+        Ok(chat) => chat,
+        Err(e) => return repository_error_response(&e),
+    };
+    let chat_state = chat
+        .as_ref()
+        .map_or(LiveChatState::Active, |chat| chat.state);
+
+    let parts: Vec<&str> = params.part.split(',').map(|s| s.trim()).collect();
+    let include_snippet = parts.contains(&"snippet");
+    let include_author_details = parts.contains(&"authorDetails");
+
+    // A scheduled chat has not started, so no messages are delivered yet
+    let mut messages = match chat_state {
+        LiveChatState::Scheduled => Vec::new(),
+        _ => match state.repo.get_chat_messages_from(&params.live_chat_id, 0) {
+            Ok(messages) => messages,
+            Err(e) => return repository_error_response(&e),
+        },
+    };
+    // Messages of muted authors are only listed for the author, and not counted otherwise
+    let viewer_channel_id = params
+        .viewer_channel_id
+        .as_deref()
+        .filter(|id| !id.is_empty());
+    messages.retain(|(_, msg)| msg.visible_to(viewer_channel_id));
+    // Tokens hold stable positions, so deleted messages leave gaps instead of shifting pages
+    // Messages past the chat's history retention are skipped the same way
+    let now = clock::system_clock().now();
+    let page: Vec<_> = messages
+        .iter()
+        .filter(|(position, msg)| {
+            *position >= start_index && chat.as_ref().is_none_or(|chat| chat.serves(msg, now))
+        })
+        .take(max_results)
+        .collect();
+    let items: Vec<LiveChatMessage> = page
+        .iter()
+        .map(|(position, msg)| {
+            LiveChatMessage::from_domain(
+                msg,
+                format!("etag-{position}"),
+                state.display_message_policy,
+                include_snippet,
+                include_author_details,
+            )
+        })
+        .collect();
+
+    // The token points at the next message even if it has not arrived yet
+    let next_index = page
+        .last()
+        .map_or(start_index, |(position, _)| position + 1);
+    let (next_page_token, polling_interval_millis) = match chat_state {
+        LiveChatState::Scheduled => (
+            Some(domain::encode_page_token(next_index)),
+            SCHEDULED_POLLING_INTERVAL_MILLIS,
+        ),
+        LiveChatState::Active => (
+            Some(domain::encode_page_token(next_index)),
+            POLLING_INTERVAL_MILLIS,
+        ),
+        LiveChatState::Ended => (None, POLLING_INTERVAL_MILLIS),
+    };
+
+    let response = LiveChatMessageListResponse {
+        kind: "youtube#liveChatMessageListResponse".to_string(),
+        etag: format!("etag-list-{start_index}"),
+        next_page_token,
+        polling_interval_millis,
+        offline_at: chat.and_then(|chat| chat.offline_at),
+        page_info: PageInfo {
+            total_results: messages.len() as i32,
+            results_per_page: items.len() as i32,
+        },
+        items,
+    };
+
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Handler for `liveChatMessages.insert`
+///
+/// Stores a text message posted by the caller, so it is delivered by both the gRPC stream
+/// and `liveChatMessages.list`, and returns the created resource.
+pub(crate) async fn live_chat_messages_insert(
+    State(state): State<VideoState>,
+    Query(params): Query<LiveChatMessagesInsertParams>,
+    body: axum::body::Bytes,
+) -> Response {
+    if params.part.is_empty() {
+        return bad_request("required", "Required parameter: part".to_string());
+    }
+    let parts: Vec<&str> = params.part.split(',').map(|s| s.trim()).collect();
+    if !parts.contains(&"snippet") {
+        return bad_request(
+            "invalidValue",
+            format!("Invalid value '{}' for part. Expected snippet", params.part),
+        );
+    }
+
+    let request: LiveChatMessageInsertRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return bad_request("parseError", format!("Parse Error: {e}")),
+    };
+    let snippet = request.snippet.unwrap_or_default();
+    if snippet.live_chat_id.is_empty() {
+        return bad_request(
+            "required",
+            "Required parameter: snippet.liveChatId".to_string(),
+        );
+    }
+    let message_text = snippet
+        .text_message_details
+        .map(|details| details.message_text)
+        .unwrap_or_default();
+    if message_text.is_empty() {
+        return bad_request(
+            "required",
+            "Required parameter: snippet.textMessageDetails.messageText".to_string(),
+        );
+    }
+    if let Err(e) = domain::validate_message_text_len(&message_text, state.max_text_len) {
+        return bad_request("invalidValue", e);
+    }
+
+    let message = domain::LiveChatMessage {
+        id: request
+            .id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("msg-{}", uuid::Uuid::new_v4())),
+        live_chat_id: snippet.live_chat_id,
+        author_channel_id: INSERT_AUTHOR_CHANNEL_ID.to_string(),
+        author_display_name: String::new(),
+        message_text,
+        published_at: snippet
+            .published_at
+            .unwrap_or_else(|| clock::system_clock().now()),
+        is_verified: false,
+        super_chat_details: None,
+        super_sticker_details: None,
+        deleted_message_id: None,
+        author_profile: Default::default(),
+        muted: false,
+    };
+    if let Err(e) = state.repo.add_chat_message(message.clone()) {
+        return repository_error_response(&e);
+    }
+    // Read back the stored message, whose author name the datastore filled in
+    let stored = state
+        .repo
+        .get_chat_messages(&message.live_chat_id)
+        .ok()
+        .and_then(|messages| messages.into_iter().rev().find(|m| m.id == message.id))
+        .unwrap_or(message);
+
+    let resource = LiveChatMessage::from_domain(
+        &stored,
+        format!("etag-{}", stored.id),
+        state.display_message_policy,
+        true,
+        parts.contains(&"authorDetails"),
+    );
+    (StatusCode::OK, Json(resource)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_router;
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use chrono::Utc;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get_json(router: &Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        (status, serde_json::from_slice(&bytes).expect("JSON body"))
+    }
+
+    async fn post_json(
+        router: &Router,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        (status, serde_json::from_slice(&bytes).expect("JSON body"))
+    }
+
+    fn text_message(live_chat_id: &str, text: &str) -> serde_json::Value {
+        serde_json::json!({
+            "snippet": {
+                "liveChatId": live_chat_id,
+                "type": "textMessageEvent",
+                "textMessageDetails": {"messageText": text},
+            }
+        })
+    }
+
+    fn add_message(repo: &datastore::InMemoryRepository, id: &str, live_chat_id: &str) {
+        use datastore::Repository;
+        repo.add_chat_message(domain::LiveChatMessage {
+            id: id.to_string(),
+            live_chat_id: live_chat_id.to_string(),
+            author_channel_id: "channel-1".to_string(),
+            author_display_name: "Tester".to_string(),
+            message_text: format!("message {id}"),
+            published_at: Utc::now(),
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        })
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_polling_with_token_sees_new_messages() {
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        add_message(&repo, "poll-1", "poll-chat");
+        let router = create_router(
+            repo.clone(),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let (status, first) = get_json(
+            &router,
+            "/liveChat/messages?liveChatId=poll-chat&part=snippet,authorDetails",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first["items"].as_array().unwrap().len(), 1);
+        assert_eq!(first["pollingIntervalMillis"], 1000);
+        assert_eq!(
+            first["items"][0]["snippet"]["displayMessage"],
+            "message poll-1"
+        );
+        assert_eq!(first["items"][0]["authorDetails"]["displayName"], "Tester");
+        let token = first["nextPageToken"].as_str().unwrap().to_string();
+
+        // Polling again before anything new arrives returns no items and the same token
+        let uri = format!("/liveChat/messages?liveChatId=poll-chat&part=snippet&pageToken={token}");
+        let (_, idle) = get_json(&router, &uri).await;
+        assert!(idle["items"].as_array().unwrap().is_empty());
+        assert_eq!(idle["nextPageToken"], token.as_str());
+
+        add_message(&repo, "poll-2", "poll-chat");
+        let (_, second) = get_json(&router, &uri).await;
+        let items = second["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["id"], "poll-2");
+        assert!(items[0].get("authorDetails").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_inserted_message_is_listed() {
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        let router = create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let (status, created) = post_json(
+            &router,
+            "/liveChat/messages?part=snippet,authorDetails",
+            text_message("insert-chat", "Hello from the client"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(created["kind"], "youtube#liveChatMessage");
+        let id = created["id"].as_str().unwrap().to_string();
+        assert!(id.starts_with("msg-"));
+        assert_eq!(created["snippet"]["type"], "textMessageEvent");
+        assert_eq!(created["snippet"]["liveChatId"], "insert-chat");
+        assert_eq!(
+            created["snippet"]["authorChannelId"],
+            super::INSERT_AUTHOR_CHANNEL_ID
+        );
+        assert_eq!(
+            created["snippet"]["textMessageDetails"]["messageText"],
+            "Hello from the client"
+        );
+        assert!(created["snippet"]["publishedAt"].is_string());
+        assert!(
+            !created["authorDetails"]["displayName"]
+                .as_str()
+                .unwrap()
+                .is_empty()
+        );
+
+        // An explicit id and publishedAt are kept
+        let mut explicit = text_message("insert-chat", "Second");
+        explicit["id"] = serde_json::json!("client-id");
+        explicit["snippet"]["publishedAt"] = serde_json::json!("2024-01-01T00:00:00Z");
+        let (status, created) =
+            post_json(&router, "/liveChat/messages?part=snippet", explicit).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(created["id"], "client-id");
+        assert_eq!(created["snippet"]["publishedAt"], "2024-01-01T00:00:00Z");
+        assert!(created.get("authorDetails").is_none());
+
+        let (_, listed) = get_json(
+            &router,
+            "/liveChat/messages?liveChatId=insert-chat&part=snippet",
+        )
+        .await;
+        let items = listed["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["id"], id.as_str());
+        assert_eq!(items[1]["snippet"]["displayMessage"], "Second");
+    }
+
+    #[tokio::test]
+    async fn test_insert_validates_part_text_and_length() {
+        let router = crate::router_with_state(
+            crate::VideoState::new(
+                Arc::new(datastore::InMemoryRepository::new()),
+                domain::DisplayMessagePolicy::Raw,
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            )
+            .with_max_text_len(Some(5)),
+        );
+
+        for (uri, body, reason, message) in [
+            (
+                "/liveChat/messages",
+                text_message("chat", "hi"),
+                "required",
+                "Required parameter: part",
+            ),
+            (
+                "/liveChat/messages?part=id",
+                text_message("chat", "hi"),
+                "invalidValue",
+                "Invalid value 'id' for part. Expected snippet",
+            ),
+            (
+                "/liveChat/messages?part=snippet",
+                text_message("", "hi"),
+                "required",
+                "Required parameter: snippet.liveChatId",
+            ),
+            (
+                "/liveChat/messages?part=snippet",
+                serde_json::json!({"snippet": {"liveChatId": "chat"}}),
+                "required",
+                "Required parameter: snippet.textMessageDetails.messageText",
+            ),
+            (
+                "/liveChat/messages?part=snippet",
+                text_message("chat", "héllo!"),
+                "invalidValue",
+                "messageText is 6 characters long, the maximum is 5",
+            ),
+        ] {
+            let (status, body) = post_json(&router, uri, body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(body["error"]["code"], 400);
+            assert_eq!(body["error"]["errors"][0]["reason"], reason);
+            assert_eq!(body["error"]["message"], message);
+        }
+
+        // Exactly at the limit
+        let (status, _) = post_json(
+            &router,
+            "/liveChat/messages?part=snippet",
+            text_message("chat", "héllo"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_max_results_pages_through_messages() {
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        for i in 0..3 {
+            add_message(&repo, &format!("page-{i}"), "page-chat");
+        }
+        let router = create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let (_, first) = get_json(
+            &router,
+            "/liveChat/messages?liveChatId=page-chat&part=id&maxResults=2",
+        )
+        .await;
+        assert_eq!(first["items"].as_array().unwrap().len(), 2);
+        assert_eq!(first["pageInfo"]["totalResults"], 3);
+
+        let uri = format!(
+            "/liveChat/messages?liveChatId=page-chat&part=id&maxResults=2&pageToken={}",
+            first["nextPageToken"].as_str().unwrap()
+        );
+        let (_, second) = get_json(&router, &uri).await;
+        assert_eq!(second["items"][0]["id"], "page-2");
+    }
+
+    #[tokio::test]
+    async fn test_missing_parameters_use_error_envelope() {
+        let router = create_router(
+            Arc::new(datastore::InMemoryRepository::new()),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        for (uri, message) in [
+            (
+                "/liveChat/messages?liveChatId=test-chat-id",
+                "Required parameter: part",
+            ),
+            (
+                "/liveChat/messages?part=snippet",
+                "Required parameter: liveChatId",
+            ),
+        ] {
+            let (status, body) = get_json(&router, uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"]["code"], 400);
+            assert_eq!(body["error"]["message"], message);
+            assert_eq!(body["error"]["errors"][0]["reason"], "required");
+        }
+
+        let (status, body) = get_json(
+            &router,
+            "/liveChat/messages?liveChatId=test-chat-id&part=snippet&pageToken=not-base64!",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["errors"][0]["reason"], "invalidPageToken");
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_states_change_list_semantics() {
+        use datastore::Repository;
+
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        add_message(&repo, "life-1", "life-chat");
+        repo.save_live_chat(domain::LiveChat::scheduled("life-chat", None))
+            .unwrap();
+        let router = create_router(
+            repo.clone(),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let uri = "/liveChat/messages?liveChatId=life-chat&part=snippet";
+
+        let (_, scheduled) = get_json(&router, uri).await;
+        assert!(scheduled["items"].as_array().unwrap().is_empty());
+        assert_eq!(scheduled["pollingIntervalMillis"], 10_000);
+        assert!(scheduled["nextPageToken"].is_string());
+
+        let mut chat = repo.get_live_chat("life-chat").unwrap().unwrap();
+        chat.transition(domain::LiveChatState::Active, Utc::now())
+            .unwrap();
+        repo.save_live_chat(chat.clone()).unwrap();
+        let (_, active) = get_json(&router, uri).await;
+        assert_eq!(active["items"].as_array().unwrap().len(), 1);
+        assert_eq!(active["pollingIntervalMillis"], 1000);
+        assert!(active.get("offlineAt").is_none());
+
+        chat.transition(domain::LiveChatState::Ended, Utc::now())
+            .unwrap();
+        repo.save_live_chat(chat).unwrap();
+        let (_, ended) = get_json(&router, uri).await;
+        assert_eq!(ended["items"].as_array().unwrap().len(), 1);
+        assert!(ended["offlineAt"].is_string());
+        assert!(ended.get("nextPageToken").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_super_chat_and_super_sticker_snippet_shapes() {
+        use datastore::Repository;
+
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        repo.add_chat_message(domain::LiveChatMessage {
+            id: "super-1".to_string(),
+            live_chat_id: "super-chat".to_string(),
+            author_channel_id: "channel-1".to_string(),
+            author_display_name: "Patron".to_string(),
+            message_text: "Thanks!".to_string(),
+            published_at: Utc::now(),
+            is_verified: false,
+            super_chat_details: Some(domain::SuperChatDetails {
+                amount_micros: 500_000_000,
+                currency: "JPY".to_string(),
+                tier: 3,
+                user_comment: "Thanks!".to_string(),
+            }),
+            super_sticker_details: None,
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        })
+        .unwrap();
+        repo.add_chat_message(domain::LiveChatMessage {
+            id: "super-2".to_string(),
+            live_chat_id: "super-chat".to_string(),
+            author_channel_id: "channel-2".to_string(),
+            author_display_name: "Sticker Fan".to_string(),
+            message_text: "Party cat".to_string(),
+            published_at: Utc::now(),
+            is_verified: false,
+            super_chat_details: None,
+            super_sticker_details: Some(domain::SuperStickerDetails {
+                amount_micros: 2_000_000,
+                currency: "USD".to_string(),
+                tier: 1,
+                sticker_id: "party-cat".to_string(),
+                alt_text: "Party cat".to_string(),
+            }),
+            deleted_message_id: None,
+            author_profile: Default::default(),
+            muted: false,
+        })
+        .unwrap();
+        let router = create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let (status, body) = get_json(
+            &router,
+            "/liveChat/messages?liveChatId=super-chat&part=snippet",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let snippet = &body["items"][0]["snippet"];
+        assert_eq!(snippet["type"], "superChatEvent");
+        assert!(snippet.get("textMessageDetails").is_none());
+        assert_eq!(
+            snippet["superChatDetails"],
+            serde_json::json!({
+                "amountMicros": "500000000",
+                "currency": "JPY",
+                "amountDisplayString": "¥500",
+                "userComment": "Thanks!",
+                "tier": 3,
+            })
+        );
+
+        let snippet = &body["items"][1]["snippet"];
+        assert_eq!(snippet["type"], "superStickerEvent");
+        assert!(snippet.get("textMessageDetails").is_none());
+        assert!(snippet.get("superChatDetails").is_none());
+        assert_eq!(
+            snippet["superStickerDetails"],
+            serde_json::json!({
+                "amountMicros": "2000000",
+                "currency": "USD",
+                "amountDisplayString": "$2.00",
+                "tier": 1,
+                "superStickerMetadata": {
+                    "stickerId": "party-cat",
+                    "altText": "Party cat",
+                    "altTextLanguage": "en",
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_retention_withholds_old_messages() {
+        use datastore::Repository;
+
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        for (id, age_days) in [("old-1", 10), ("old-2", 5), ("new-1", 0)] {
+            repo.add_chat_message(domain::LiveChatMessage {
+                id: id.to_string(),
+                live_chat_id: "retention-chat".to_string(),
+                author_channel_id: "channel-1".to_string(),
+                author_display_name: "Tester".to_string(),
+                message_text: format!("message {id}"),
+                published_at: Utc::now() - chrono::Duration::days(age_days),
+                is_verified: false,
+                super_chat_details: None,
+                super_sticker_details: None,
+                deleted_message_id: None,
+                author_profile: Default::default(),
+                muted: false,
+            })
+            .unwrap();
+        }
+        let mut chat = domain::LiveChat::active("retention-chat");
+        chat.history_retention_seconds = Some(86_400);
+        repo.save_live_chat(chat).unwrap();
+        let router = create_router(
+            repo.clone(),
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+
+        let (_, fresh) = get_json(
+            &router,
+            "/liveChat/messages?liveChatId=retention-chat&part=id",
+        )
+        .await;
+        let items = fresh["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["id"], "new-1");
+        assert_eq!(fresh["pageInfo"]["totalResults"], 3);
+
+        // A token pointing at a withheld message resumes from the oldest served one
+        let uri = format!(
+            "/liveChat/messages?liveChatId=retention-chat&part=id&pageToken={}",
+            domain::encode_page_token(0)
+        );
+        let (_, resumed) = get_json(&router, &uri).await;
+        assert_eq!(resumed["items"][0]["id"], "new-1");
+
+        // The messages stay stored
+        assert_eq!(repo.count_chat_messages("retention-chat").unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_muted_messages_are_only_listed_for_their_author() {
+        use datastore::Repository;
+
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        add_message(&repo, "before-mute", "mute-chat");
+        let mut chat = domain::LiveChat::active("mute-chat");
+        chat.muted_author_channel_ids
+            .insert("channel-1".to_string());
+        repo.save_live_chat(chat).unwrap();
+        add_message(&repo, "while-muted", "mute-chat");
+        let router = create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        );
+        let ids = |list: &serde_json::Value| -> Vec<String> {
+            list["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let (_, others) =
+            get_json(&router, "/liveChat/messages?liveChatId=mute-chat&part=id").await;
+        assert_eq!(ids(&others), ["before-mute"]);
+        assert_eq!(others["pageInfo"]["totalResults"], 1);
+
+        let (_, author) = get_json(
+            &router,
+            "/liveChat/messages?liveChatId=mute-chat&part=id&viewerChannelId=channel-1",
+        )
+        .await;
+        assert_eq!(ids(&author), ["before-mute", "while-muted"]);
+        assert_eq!(author["pageInfo"]["totalResults"], 2);
+    }
+}
@@ -0,0 +1,384 @@
+//! `search.list`, limited to finding the videos of a channel
+//!
+//! Supports the documented discovery flow: `search.list?channelId=...&eventType=live&type=video`
+//! to find the live video, then `videos.list` for its chat ID. Results are ordered newest
+//! first and paged with `maxResults` and `pageToken`.
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use domain::LiveBroadcastContent;
+use serde::{Deserialize, Serialize};
+
+use crate::live_chat_rest::bad_request;
+use crate::{PageInfo, VideoState, repository_error_response};
+
+/// Results per page when `maxResults` is not given
+pub const DEFAULT_SEARCH_MAX_RESULTS: usize = 5;
+
+/// Largest accepted `maxResults`
+pub const MAX_SEARCH_MAX_RESULTS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchListParams {
+    #[serde(default)]
+    pub part: String,
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default)]
+    pub event_type: Option<String>,
+    #[serde(default, rename = "type")]
+    pub resource_type: Option<String>,
+    /// Kept as a string so invalid values produce the standard error envelope
+    #[serde(default)]
+    pub max_results: Option<String>,
+    #[serde(default)]
+    pub page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchListResponse {
+    pub kind: String,
+    pub etag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_page_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_page_token: Option<String>,
+    pub page_info: PageInfo,
+    pub items: Vec<SearchResult>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub kind: String,
+    pub etag: String,
+    pub id: SearchResultId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<SearchResultSnippet>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultId {
+    pub kind: String,
+    pub video_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultSnippet {
+    pub published_at: DateTime<Utc>,
+    pub channel_id: String,
+    pub title: String,
+    pub description: String,
+    pub channel_title: String,
+    pub live_broadcast_content: LiveBroadcastContent,
+}
+
+/// Whether a video matches `eventType`: live, upcoming or completed broadcasts
+fn matches_event_type(event_type: &str, video: &domain::Video, now: DateTime<Utc>) -> bool {
+    match event_type {
+        "completed" => video.actual_end_time.is_some_and(|end| end <= now),
+        "live" => video.live_broadcast_content(now) == LiveBroadcastContent::Live,
+        "upcoming" => video.live_broadcast_content(now) == LiveBroadcastContent::Upcoming,
+        _ => false,
+    }
+}
+
+/// Handler for `search.list`
+pub(crate) async fn search_list(
+    State(state): State<VideoState>,
+    Query(params): Query<SearchListParams>,
+) -> Response {
+    if params.part.is_empty() {
+        return bad_request("required", "Required parameter: part".to_string());
+    }
+    let parts: Vec<&str> = params.part.split(',').map(|s| s.trim()).collect();
+    if let Some(part) = parts.iter().find(|part| !["id", "snippet"].contains(part)) {
+        return bad_request(
+            "unknownPart",
+            format!("'{part}' is not a valid part for search.list. Use id or snippet"),
+        );
+    }
+
+    let event_type = params.event_type.as_deref().filter(|s| !s.is_empty());
+    if let Some(event_type) = event_type {
+        if !["completed", "live", "upcoming"].contains(&event_type) {
+            return bad_request(
+                "invalidValue",
+                format!(
+                    "Invalid value '{event_type}' for eventType. Use completed, live or upcoming"
+                ),
+            );
+        }
+        // As in the real API, event filters only apply to video searches
+        if params.resource_type.as_deref() != Some("video") {
+            return bad_request(
+                "invalidSearchFilter",
+                "The eventType parameter requires the type parameter to be set to video"
+                    .to_string(),
+            );
+        }
+    }
+
+    let max_results = match params.max_results.as_deref() {
+        None | Some("") => DEFAULT_SEARCH_MAX_RESULTS,
+        Some(value) => match value.parse::<usize>() {
+            Ok(max) if max <= MAX_SEARCH_MAX_RESULTS => max,
+            _ => {
+                return bad_request(
+                    "invalidValue",
+                    format!(
+                        "Invalid value '{value}' for maxResults. Expected 0 to {MAX_SEARCH_MAX_RESULTS}"
+                    ),
+                );
+            }
+        },
+    };
+
+    let start_index = match params.page_token.as_deref() {
+        None | Some("") => 0,
+        Some(token) => match domain::decode_page_token(token) {
+            Ok(index) => index,
+            Err(e) => return bad_request("invalidPageToken", e.to_string()),
+        },
+    };
+
+    let mut videos = match state.repo.get_videos() {
+        Ok(videos) => videos,
+        Err(e) => return repository_error_response(&e),
+    };
+    // Only videos are searchable; a type list without `video` matches nothing
+    let searches_videos = params
+        .resource_type
+        .as_deref()
+        .is_none_or(|types| types.is_empty() || types.split(',').any(|t| t.trim() == "video"));
+    let now = clock::system_clock().now();
+    videos.retain(|video| {
+        searches_videos
+            && params
+                .channel_id
+                .as_deref()
+                .is_none_or(|channel_id| video.channel_id == channel_id)
+            && event_type.is_none_or(|event_type| matches_event_type(event_type, video, now))
+    });
+    // Newest first; the ID keeps pages stable between videos published at the same time
+    videos.sort_by(|a, b| {
+        b.published_at
+            .cmp(&a.published_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    let include_snippet = parts.contains(&"snippet");
+    let items: Vec<SearchResult> = videos
+        .iter()
+        .skip(start_index)
+        .take(max_results)
+        .map(|video| SearchResult {
+            kind: "youtube#searchResult".to_string(),
+            etag: format!("etag-search-{}", video.id),
+            id: SearchResultId {
+                kind: "youtube#video".to_string(),
+                video_id: video.id.clone(),
+            },
+            snippet: include_snippet.then(|| SearchResultSnippet {
+                published_at: video.published_at,
+                channel_id: video.channel_id.clone(),
+                title: video.title.clone(),
+                description: video.description.clone(),
+                channel_title: video.channel_title.clone(),
+                live_broadcast_content: video.live_broadcast_content(now),
+            }),
+        })
+        .collect();
+
+    let next_index = start_index + items.len();
+    let response = SearchListResponse {
+        kind: "youtube#searchListResponse".to_string(),
+        etag: format!("etag-search-{start_index}"),
+        next_page_token: (next_index < videos.len() && !items.is_empty())
+            .then(|| domain::encode_page_token(next_index)),
+        prev_page_token: (start_index > 0)
+            .then(|| domain::encode_page_token(start_index.saturating_sub(max_results))),
+        page_info: PageInfo {
+            total_results: videos.len() as i32,
+            results_per_page: max_results as i32,
+        },
+        items,
+    };
+
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_router;
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use chrono::{Duration, Utc};
+    use datastore::Repository;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get_json(router: &Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("Valid request");
+        let response = router.clone().oneshot(request).await.expect("Response");
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Readable body");
+        (status, serde_json::from_slice(&bytes).expect("JSON body"))
+    }
+
+    fn video_ids(body: &serde_json::Value) -> Vec<&str> {
+        body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"]["videoId"].as_str().unwrap())
+            .collect()
+    }
+
+    /// A repository with one live, one upcoming and one completed video on `search-channel`
+    /// and a live video on another channel
+    fn router() -> Router {
+        let repo = Arc::new(datastore::InMemoryRepository::new());
+        let now = Utc::now();
+        let video = |id: &str, channel_id: &str, minutes_ago: i64| domain::Video {
+            id: id.to_string(),
+            channel_id: channel_id.to_string(),
+            title: format!("Video {id}"),
+            description: String::new(),
+            channel_title: "Search Channel".to_string(),
+            published_at: now - Duration::minutes(minutes_ago),
+            live_chat_id: Some(format!("{id}-chat")),
+            actual_start_time: None,
+            actual_end_time: None,
+            scheduled_start_time: None,
+            scheduled_end_time: None,
+            concurrent_viewers: None,
+        };
+        repo.add_video(domain::Video {
+            actual_start_time: Some(now - Duration::minutes(5)),
+            ..video("live", "search-channel", 10)
+        })
+        .unwrap();
+        repo.add_video(domain::Video {
+            scheduled_start_time: Some(now + Duration::hours(1)),
+            ..video("upcoming", "search-channel", 20)
+        })
+        .unwrap();
+        repo.add_video(domain::Video {
+            actual_start_time: Some(now - Duration::hours(2)),
+            actual_end_time: Some(now - Duration::hours(1)),
+            ..video("completed", "search-channel", 30)
+        })
+        .unwrap();
+        repo.add_video(domain::Video {
+            actual_start_time: Some(now - Duration::minutes(5)),
+            ..video("other-live", "other-channel", 10)
+        })
+        .unwrap();
+        create_router(
+            repo,
+            domain::DisplayMessagePolicy::Raw,
+            Arc::new(domain::QuotaLedger::default()),
+            Arc::new(domain::FaultConfig::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_search_finds_live_video_of_channel() {
+        let router = router();
+        let (status, body) = get_json(
+            &router,
+            "/search?part=snippet&channelId=search-channel&eventType=live&type=video",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["kind"], "youtube#searchListResponse");
+        assert_eq!(video_ids(&body), ["live"]);
+        let item = &body["items"][0];
+        assert_eq!(item["kind"], "youtube#searchResult");
+        assert_eq!(item["id"]["kind"], "youtube#video");
+        assert_eq!(item["snippet"]["channelId"], "search-channel");
+        assert_eq!(item["snippet"]["liveBroadcastContent"], "live");
+
+        let (_, body) = get_json(
+            &router,
+            "/search?part=id&channelId=search-channel&eventType=upcoming&type=video",
+        )
+        .await;
+        assert_eq!(video_ids(&body), ["upcoming"]);
+        assert!(body["items"][0].get("snippet").is_none());
+
+        let (_, body) = get_json(
+            &router,
+            "/search?part=id&channelId=search-channel&eventType=completed&type=video",
+        )
+        .await;
+        assert_eq!(video_ids(&body), ["completed"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_pages_over_results() {
+        let router = router();
+        let (status, first) = get_json(
+            &router,
+            "/search?part=id&channelId=search-channel&maxResults=2",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(video_ids(&first), ["live", "upcoming"]);
+        assert_eq!(first["pageInfo"]["totalResults"], 3);
+        assert!(first.get("prevPageToken").is_none());
+
+        let token = first["nextPageToken"].as_str().unwrap();
+        let (_, second) = get_json(
+            &router,
+            &format!("/search?part=id&channelId=search-channel&maxResults=2&pageToken={token}"),
+        )
+        .await;
+        assert_eq!(video_ids(&second), ["completed"]);
+        assert!(second.get("nextPageToken").is_none());
+        assert!(second["prevPageToken"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_invalid_parameters() {
+        let router = router();
+        for (uri, reason) in [
+            ("/search?channelId=search-channel", "required"),
+            ("/search?part=contentDetails", "unknownPart"),
+            ("/search?part=id&eventType=live", "invalidSearchFilter"),
+            (
+                "/search?part=id&eventType=ongoing&type=video",
+                "invalidValue",
+            ),
+            ("/search?part=id&maxResults=51", "invalidValue"),
+            ("/search?part=id&pageToken=!!", "invalidPageToken"),
+        ] {
+            let (status, body) = get_json(&router, uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(body["error"]["errors"][0]["reason"], reason, "{uri}");
+        }
+
+        // Other resource types have no results
+        let (status, body) = get_json(&router, "/search?part=id&type=channel").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["items"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+//! Opt-in emulation of the Google frontend in front of the real API
+//!
+//! Replicates the frontend's edge behaviors on the REST listener so client error-path
+//! parsing can be tested: HTML error pages for unknown paths and malformed queries,
+//! `alt` handling, and 411/415 for POST bodies. Control endpoints are left untouched.
+//! Response bodies are served from the reference fixtures in `fixtures/gateway`.
+
+use axum::{
+    extract::Request,
+    http::{HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+const NOT_FOUND_PAGE: &str = include_str!("../fixtures/gateway/404.html");
+const LENGTH_REQUIRED_PAGE: &str = include_str!("../fixtures/gateway/411.html");
+const BAD_REQUEST_PAGE: &str = include_str!("../fixtures/gateway/400.html");
+const UNSUPPORTED_MEDIA_TYPE: &str = include_str!("../fixtures/gateway/415.json");
+const INVALID_ALT: &str = include_str!("../fixtures/gateway/invalid_alt.json");
+
+const HTML_CONTENT_TYPE: &str = "text/html; charset=UTF-8";
+const JSON_CONTENT_TYPE: &str = "application/json; charset=UTF-8";
+
+// Body types accepted on POST, PUT and PATCH
+const SUPPORTED_CONTENT_TYPES: &[&str] = &["application/json", "application/x-www-form-urlencoded"];
+
+fn respond(status: StatusCode, content_type: &'static str, body: String) -> Response {
+    (status, [(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// Decode a percent-encoded query component, `None` if it is malformed or not UTF-8
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Middleware replicating the Google frontend's edge behaviors on the REST listener
+pub async fn gateway_parity(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    if path == "/control" || path.starts_with("/control/") {
+        return next.run(request).await;
+    }
+
+    // Malformed percent-encoding is rejected before reaching the API
+    let mut params = Vec::new();
+    for pair in request.uri().query().unwrap_or("").split('&') {
+        if pair.is_empty() {
+            continue;
+        }
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match (percent_decode(key), percent_decode(value)) {
+            (Some(key), Some(value)) => params.push((key, value)),
+            _ => {
+                return respond(
+                    StatusCode::BAD_REQUEST,
+                    HTML_CONTENT_TYPE,
+                    BAD_REQUEST_PAGE.to_string(),
+                );
+            }
+        }
+    }
+
+    // JSON is the default and only supported output format
+    if params
+        .iter()
+        .any(|(key, value)| key == "alt" && value != "json")
+    {
+        return respond(
+            StatusCode::BAD_REQUEST,
+            JSON_CONTENT_TYPE,
+            INVALID_ALT.to_string(),
+        );
+    }
+
+    let method = request.method().clone();
+    if matches!(method, Method::POST | Method::PUT | Method::PATCH) {
+        let headers = request.headers();
+        let content_length = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        let chunked = headers.contains_key(header::TRANSFER_ENCODING);
+
+        if content_length.is_none() && !chunked {
+            return respond(
+                StatusCode::LENGTH_REQUIRED,
+                HTML_CONTENT_TYPE,
+                LENGTH_REQUIRED_PAGE.replace("{method}", method.as_str()),
+            );
+        }
+
+        let has_body = chunked || content_length.is_some_and(|length| length > 0);
+        let supported = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|media_type| {
+                SUPPORTED_CONTENT_TYPES
+                    .iter()
+                    .any(|supported| media_type.trim().eq_ignore_ascii_case(supported))
+            });
+        if has_body && !supported {
+            return respond(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                JSON_CONTENT_TYPE,
+                UNSUPPORTED_MEDIA_TYPE.to_string(),
+            );
+        }
+    }
+
+    let mut response = next.run(request).await;
+
+    // Unmatched routes get the frontend's HTML page instead of an empty body
+    if response.status() == StatusCode::NOT_FOUND
+        && !response.headers().contains_key(header::CONTENT_TYPE)
+    {
+        return respond(
+            StatusCode::NOT_FOUND,
+            HTML_CONTENT_TYPE,
+            NOT_FOUND_PAGE.replace("{path}", &escape_html(&path)),
+        );
+    }
+
+    // JSON responses carry an explicit charset like the frontend's
+    if response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value == "application/json")
+    {
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(JSON_CONTENT_TYPE),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, body::Body, routing::get};
+    use tower::ServiceExt;
+
+    fn router() -> Router {
+        Router::new()
+            .route(
+                "/youtube/v3/videos",
+                get(|| async { Json(serde_json::json!({"items": []})) }),
+            )
+            .route(
+                "/oauth2/token",
+                axum::routing::post(|| async { Json(serde_json::json!({"ok": true})) }),
+            )
+            .route(
+                "/control/reset",
+                axum::routing::post(|| async { Json(serde_json::json!({"ok": true})) }),
+            )
+            .layer(axum::middleware::from_fn(gateway_parity))
+    }
+
+    async fn send(request: http::Request<Body>) -> (StatusCode, String, String) {
+        let response = router().oneshot(request).await.expect("Response");
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            content_type,
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    fn get_request(uri: &str) -> http::Request<Body> {
+        http::Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unknown_path_gets_html_404() {
+        let (status, content_type, body) = send(get_request("/youtube/v3/nope?part=id")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(content_type, HTML_CONTENT_TYPE);
+        assert_eq!(body, NOT_FOUND_PAGE.replace("{path}", "/youtube/v3/nope"));
+        assert!(body.contains("<title>Error 404 (Not Found)!!1</title>"));
+    }
+
+    #[tokio::test]
+    async fn test_alt_json_is_the_default() {
+        for uri in ["/youtube/v3/videos", "/youtube/v3/videos?alt=json"] {
+            let (status, content_type, _) = send(get_request(uri)).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+            assert_eq!(content_type, JSON_CONTENT_TYPE, "{uri}");
+        }
+
+        let (status, content_type, body) = send(get_request("/youtube/v3/videos?alt=xml")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type, JSON_CONTENT_TYPE);
+        assert_eq!(body, INVALID_ALT);
+        let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(error["error"]["errors"][0]["location"], "alt");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_query_encoding_gets_html_400() {
+        for uri in [
+            "/youtube/v3/videos?part=%zz",
+            "/youtube/v3/videos?part=%E",
+            "/youtube/v3/videos?part=%FF%FE",
+        ] {
+            let (status, content_type, body) = send(get_request(uri)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(content_type, HTML_CONTENT_TYPE);
+            assert_eq!(body, BAD_REQUEST_PAGE);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_without_length_gets_411() {
+        let request = http::Request::post("/oauth2/token")
+            .body(Body::empty())
+            .unwrap();
+        let (status, content_type, body) = send(request).await;
+        assert_eq!(status, StatusCode::LENGTH_REQUIRED);
+        assert_eq!(content_type, HTML_CONTENT_TYPE);
+        assert_eq!(body, LENGTH_REQUIRED_PAGE.replace("{method}", "POST"));
+    }
+
+    #[tokio::test]
+    async fn test_post_with_unsupported_content_type_gets_415() {
+        let request = http::Request::post("/oauth2/token")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .header(header::CONTENT_LENGTH, "4")
+            .body(Body::from("code"))
+            .unwrap();
+        let (status, content_type, body) = send(request).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(content_type, JSON_CONTENT_TYPE);
+        assert_eq!(body, UNSUPPORTED_MEDIA_TYPE);
+
+        let request = http::Request::post("/oauth2/token")
+            .header(
+                header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )
+            .header(header::CONTENT_LENGTH, "4")
+            .body(Body::from("code"))
+            .unwrap();
+        let (status, _, _) = send(request).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_control_endpoints_are_untouched() {
+        let request = http::Request::post("/control/reset")
+            .body(Body::empty())
+            .unwrap();
+        let (status, content_type, _) = send(request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/json");
+    }
+}
@@ -0,0 +1,158 @@
+//! gRPC serving that cycles connections like the real server
+//!
+//! After a configured number of streams on a connection, the server sends an HTTP/2
+//! GOAWAY so the client opens a new connection for further calls. Streams already open
+//! keep running, for at most the grace period when one is set; then the connection is
+//! closed.
+
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::service::TowerToHyperService;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::{Notify, watch};
+use tower::ServiceExt;
+
+/// When to cycle a gRPC connection
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionCycling {
+    /// Streams served on a connection before it is sent a GOAWAY
+    pub max_streams: u64,
+    /// How long streams opened before the GOAWAY may keep running, `None` = until they end
+    pub grace: Option<Duration>,
+}
+
+/// Serve `service` over HTTP/2 on `listener` until `shutdown` resolves, cycling
+/// connections as configured
+pub async fn serve<S, B>(
+    listener: TcpListener,
+    tls: Option<tokio_rustls::TlsAcceptor>,
+    service: S,
+    cycling: ConnectionCycling,
+    shutdown: impl Future<Output = ()>,
+) -> std::io::Result<()>
+where
+    S: tower::Service<http::Request<hyper::body::Incoming>, Response = http::Response<B>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    // Each connection holds a receiver; the sender learns when all of them are gone
+    let (shutdown_tx, shutdown_rx) = watch::channel(());
+    let mut shutdown = std::pin::pin!(shutdown);
+
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("Failed to accept gRPC connection: {e}");
+                    continue;
+                }
+            },
+        };
+        let service = service.clone();
+        let shutdown_rx = shutdown_rx.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            match tls {
+                Some(acceptor) => match acceptor.accept(stream).await {
+                    Ok(stream) => {
+                        serve_connection(stream, remote_addr, service, cycling, shutdown_rx).await
+                    }
+                    Err(e) => tracing::warn!("TLS handshake with {remote_addr} failed: {e}"),
+                },
+                None => serve_connection(stream, remote_addr, service, cycling, shutdown_rx).await,
+            }
+        });
+    }
+
+    // Ask open connections to finish their streams, then wait for them to close
+    let _ = shutdown_tx.send(());
+    drop(shutdown_rx);
+    shutdown_tx.closed().await;
+    Ok(())
+}
+
+async fn serve_connection<IO, S, B>(
+    io: IO,
+    remote_addr: SocketAddr,
+    service: S,
+    cycling: ConnectionCycling,
+    mut shutdown: watch::Receiver<()>,
+) where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: tower::Service<http::Request<hyper::body::Incoming>, Response = http::Response<B>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let streams = Arc::new(AtomicU64::new(0));
+    let limit_reached = Arc::new(Notify::new());
+    let counted = {
+        let streams = Arc::clone(&streams);
+        let limit_reached = Arc::clone(&limit_reached);
+        tower::service_fn(move |mut request: http::Request<hyper::body::Incoming>| {
+            if streams.fetch_add(1, Ordering::SeqCst) + 1 == cycling.max_streams {
+                limit_reached.notify_one();
+            }
+            request.extensions_mut().insert(remote_addr);
+            service.clone().oneshot(request)
+        })
+    };
+
+    let connection = hyper::server::conn::http2::Builder::new(TokioExecutor::new())
+        .serve_connection(TokioIo::new(io), TowerToHyperService::new(counted));
+    let mut connection = std::pin::pin!(connection);
+    let mut going_away = false;
+    let mut grace = std::pin::pin!(sleep_or_pending(None));
+
+    loop {
+        tokio::select! {
+            result = &mut connection => {
+                if let Err(e) = result {
+                    tracing::warn!("gRPC connection from {remote_addr} failed: {e}");
+                }
+                break;
+            }
+            _ = limit_reached.notified(), if !going_away => {
+                tracing::info!(
+                    "Sending GOAWAY to {remote_addr} after {} streams",
+                    cycling.max_streams
+                );
+                connection.as_mut().graceful_shutdown();
+                grace.set(sleep_or_pending(cycling.grace));
+                going_away = true;
+            }
+            _ = &mut grace => {
+                tracing::info!("Closing connection from {remote_addr}: GOAWAY grace period elapsed");
+                break;
+            }
+            _ = shutdown.changed(), if !going_away => {
+                connection.as_mut().graceful_shutdown();
+                going_away = true;
+            }
+        }
+    }
+}
+
+async fn sleep_or_pending(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
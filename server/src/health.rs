@@ -0,0 +1,454 @@
+//! Component-level health checks
+//!
+//! `/healthz` probes every subsystem and reports the aggregate; `/healthz?component=<name>`
+//! probes only one. Each probe does a small amount of real work through the same code
+//! paths requests use, cleans up after itself, and is cut off after its own timeout so a
+//! wedged subsystem degrades only its own component and those built on it, like gRPC on the
+//! datastore.
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Query, State},
+    http::{Request, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use live_chat_service::proto::v3_data_live_chat_message_service_server::SERVICE_NAME;
+use live_chat_service::proto::{LiveChatMessageListRequest, LiveChatMessageListResponse};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+
+/// How long a single probe may take before its component is reported as degraded
+pub(crate) const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Subsystems that can be probed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Component {
+    Oauth,
+    Datastore,
+    Grpc,
+    Control,
+}
+
+impl Component {
+    const ALL: [Component; 4] = [
+        Component::Oauth,
+        Component::Datastore,
+        Component::Grpc,
+        Component::Control,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Component::Oauth => "oauth",
+            Component::Datastore => "datastore",
+            Component::Grpc => "grpc",
+            Component::Control => "control",
+        }
+    }
+}
+
+impl std::str::FromStr for Component {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Component::ALL
+            .into_iter()
+            .find(|component| component.name() == s)
+            .ok_or_else(|| {
+                format!("Unknown component '{s}'. Use oauth, datastore, grpc or control")
+            })
+    }
+}
+
+/// The gRPC routes wrapped in the layers the gRPC listener serves them through
+pub(crate) type GrpcService = tower::util::BoxCloneSyncService<
+    http::Request<tonic::body::Body>,
+    http::Response<tonic::body::Body>,
+    std::convert::Infallible,
+>;
+
+/// Handles to the subsystems the probes exercise
+pub(crate) struct Probes {
+    pub repo: Arc<dyn datastore::Repository>,
+    /// The OAuth router, without its path prefix
+    pub oauth: Router,
+    /// The control router, without its `/control` prefix
+    pub control: Router,
+    pub grpc: GrpcService,
+    pub timeout: Duration,
+}
+
+/// Distinguishes the sentinel records of concurrent probes
+static PROBE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Chat the gRPC probe streams; it never exists, so the call ends at the chat lookup
+const PROBE_LIVE_CHAT_ID: &str = "healthz-probe";
+
+/// Issue a token, look it up and revoke it through the OAuth endpoints
+async fn probe_oauth(oauth: Router) -> Result<(), String> {
+    let request = Request::post("/token")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(
+            "grant_type=client_credentials&scope=healthz.probe&expires_in=60",
+        ))
+        .map_err(|e| e.to_string())?;
+    let response = oauth
+        .clone()
+        .oneshot(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status() != StatusCode::OK {
+        return Err(format!("token endpoint returned {}", response.status()));
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|e| e.to_string())?;
+    let token = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|body| body["access_token"].as_str().map(str::to_string))
+        .ok_or("token endpoint returned no access token")?;
+
+    let request = Request::get(format!("/tokeninfo?access_token={token}"))
+        .body(Body::empty())
+        .map_err(|e| e.to_string())?;
+    let response = oauth
+        .clone()
+        .oneshot(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status() != StatusCode::OK {
+        return Err(format!("tokeninfo endpoint returned {}", response.status()));
+    }
+
+    let request = Request::post("/revoke")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(format!("token={token}")))
+        .map_err(|e| e.to_string())?;
+    let response = oauth.oneshot(request).await.map_err(|e| e.to_string())?;
+    if response.status() != StatusCode::OK {
+        return Err(format!("revoke endpoint returned {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Insert, read back and delete a sentinel video
+fn probe_datastore(repo: &dyn datastore::Repository) -> Result<(), String> {
+    let id = format!(
+        "healthz-probe-{}",
+        PROBE_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    );
+    let now = clock::system_clock().now();
+    repo.add_video(domain::Video {
+        id: id.clone(),
+        channel_id: "healthz-probe".to_string(),
+        title: "Health probe".to_string(),
+        description: String::new(),
+        channel_title: "Health probe".to_string(),
+        published_at: now,
+        live_chat_id: None,
+        actual_start_time: None,
+        actual_end_time: None,
+        scheduled_start_time: None,
+        scheduled_end_time: None,
+        concurrent_viewers: None,
+    })
+    .map_err(|e| e.to_string())?;
+    let found = repo.get_video(&id).map(|video| video.is_some());
+    repo.delete_video(&id).map_err(|e| e.to_string())?;
+    match found {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("sentinel video was not readable".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Call StreamList on a sentinel chat through the layered gRPC service
+///
+/// The call passes the access log, rate limiter and concurrency limit on its way to the live
+/// chat service, which looks the chat up in the datastore and answers `NOT_FOUND`.
+async fn probe_grpc(service: GrpcService, deadline: Duration) -> Result<(), String> {
+    let mut grpc = tonic::client::Grpc::new(service);
+    grpc.ready().await.map_err(|e| e.to_string())?;
+    let mut request = tonic::Request::new(LiveChatMessageListRequest {
+        live_chat_id: Some(PROBE_LIVE_CHAT_ID.to_string()),
+        ..Default::default()
+    });
+    request.set_timeout(deadline);
+    request
+        .extensions_mut()
+        .insert(live_chat_service::HealthProbe);
+    // Passes the credential check when authentication is required
+    request.metadata_mut().insert(
+        "x-goog-api-key",
+        tonic::metadata::MetadataValue::from_static(PROBE_LIVE_CHAT_ID),
+    );
+    let path = http::uri::PathAndQuery::try_from(format!("/{SERVICE_NAME}/StreamList"))
+        .map_err(|e| e.to_string())?;
+    let codec =
+        tonic_prost::ProstCodec::<LiveChatMessageListRequest, LiveChatMessageListResponse>::default(
+        );
+    match grpc.server_streaming(request, path, codec).await {
+        Err(status) if status.code() == tonic::Code::NotFound => Ok(()),
+        // A lookup slower than the first-response budget opens the stream instead
+        Ok(_) => Ok(()),
+        Err(status) => Err(format!(
+            "StreamList returned {:?}: {}",
+            status.code(),
+            status.message()
+        )),
+    }
+}
+
+/// Read the server status through the control router
+async fn probe_control(control: Router) -> Result<(), String> {
+    let request = Request::get("/status")
+        .body(Body::empty())
+        .map_err(|e| e.to_string())?;
+    let response = control.oneshot(request).await.map_err(|e| e.to_string())?;
+    if response.status() != StatusCode::OK {
+        return Err(format!("status endpoint returned {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Run the probe of one component, reporting its status and latency
+async fn check(probes: &Probes, component: Component) -> serde_json::Value {
+    let started = Instant::now();
+    // Probes run on their own tasks so one blocked on a lock cannot hold up the timeout
+    let probe = match component {
+        Component::Oauth => tokio::spawn(probe_oauth(probes.oauth.clone())),
+        Component::Datastore => {
+            let repo = Arc::clone(&probes.repo);
+            tokio::task::spawn_blocking(move || probe_datastore(&*repo))
+        }
+        Component::Grpc => tokio::spawn(probe_grpc(probes.grpc.clone(), probes.timeout)),
+        Component::Control => tokio::spawn(probe_control(probes.control.clone())),
+    };
+    let result = match tokio::time::timeout(probes.timeout, probe).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(format!("probe failed: {e}")),
+        Err(_) => Err(format!(
+            "probe timed out after {}ms",
+            probes.timeout.as_millis()
+        )),
+    };
+
+    let mut report = serde_json::json!({
+        "component": component.name(),
+        "status": if result.is_ok() { "ok" } else { "degraded" },
+        "latencyMicros": started.elapsed().as_micros() as u64,
+    });
+    if let Err(error) = result {
+        report["error"] = error.into();
+    }
+    report
+}
+
+fn is_ok(report: &serde_json::Value) -> bool {
+    report["status"] == "ok"
+}
+
+/// Handler for `/healthz`, probing every component or the one named by `component`
+pub(crate) async fn healthz(
+    State(probes): State<Arc<Probes>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let (status, body) = match params.get("component") {
+        Some(name) => match name.parse::<Component>() {
+            Ok(component) => {
+                let report = check(&probes, component).await;
+                (is_ok(&report), report)
+            }
+            Err(error) => {
+                let body = serde_json::json!({ "error": error });
+                return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+            }
+        },
+        None => {
+            let (oauth, datastore, grpc, control) = tokio::join!(
+                check(&probes, Component::Oauth),
+                check(&probes, Component::Datastore),
+                check(&probes, Component::Grpc),
+                check(&probes, Component::Control),
+            );
+            let components = [oauth, datastore, grpc, control];
+            let ok = components.iter().all(is_ok);
+            let body = serde_json::json!({
+                "status": if ok { "ok" } else { "degraded" },
+                "components": components,
+            });
+            (ok, body)
+        }
+    };
+
+    let status = if status {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The live chat service behind the server's rate limiter
+    fn grpc_service(
+        repo: Arc<dyn datastore::Repository>,
+        rate_limiter: Option<Arc<crate::rate_limit::TokenBucket>>,
+    ) -> GrpcService {
+        let live_chat = live_chat_service::LiveChatService::new(
+            repo,
+            None,
+            None,
+            domain::DisplayMessagePolicy::Raw,
+            None,
+            Arc::new(live_chat_service::IssuedTokenValidator::default()),
+            Arc::new(domain::StreamRegistry::default()),
+        );
+        GrpcService::new(
+            tower::ServiceBuilder::new()
+                .map_response(|response: http::Response<_>| response.map(tonic::body::Body::new))
+                .layer(tonic::service::InterceptorLayer::new(
+                    crate::rate_limit::grpc_interceptor(rate_limiter),
+                ))
+                .service(
+                    tonic::service::Routes::new(
+                        live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageServiceServer::new(live_chat),
+                    )
+                    .prepare(),
+                ),
+        )
+    }
+
+    fn probes_with_grpc(repo: Arc<dyn datastore::Repository>, grpc: GrpcService) -> Router {
+        let probes = Probes {
+            repo: Arc::clone(&repo),
+            oauth: oauth_service::create_router(oauth_service::OAuthConfig::default()),
+            control: control_service::create_router(
+                repo,
+                Arc::new(domain::StreamRegistry::default()),
+                Arc::new(domain::QuotaLedger::default()),
+                Arc::new(domain::FaultConfig::default()),
+            ),
+            grpc,
+            timeout: Duration::from_millis(200),
+        };
+        Router::new()
+            .route("/healthz", axum::routing::get(healthz))
+            .with_state(Arc::new(probes))
+    }
+
+    fn probes(repo: Arc<dyn datastore::Repository>) -> Router {
+        let grpc = grpc_service(Arc::clone(&repo), None);
+        probes_with_grpc(repo, grpc)
+    }
+
+    async fn get(router: &Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_every_component_is_healthy() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let router = probes(Arc::clone(&repo));
+
+        let (status, body) = get(&router, "/healthz").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["status"], "ok");
+        let names: Vec<_> = body["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|component| component["component"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["oauth", "datastore", "grpc", "control"]);
+
+        for name in names {
+            let (status, body) = get(&router, &format!("/healthz?component={name}")).await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+            assert_eq!(body["component"], name);
+            assert!(body["latencyMicros"].is_u64());
+        }
+
+        // Probes clean up after themselves
+        assert!(
+            repo.get_videos()
+                .unwrap()
+                .iter()
+                .all(|video| !video.id.starts_with("healthz-probe"))
+        );
+
+        let (status, body) = get(&router, "/healthz?component=cache").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["error"],
+            "Unknown component 'cache'. Use oauth, datastore, grpc or control"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_slow_datastore_degrades_only_the_datastore_and_grpc() {
+        let repo = Arc::new(datastore::SlowRepository {
+            inner: Arc::new(datastore::InMemoryRepository::new()),
+            delay: Duration::from_millis(500),
+        });
+        let router = probes(repo);
+
+        let (status, body) = get(&router, "/healthz?component=datastore").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["error"], "probe timed out after 200ms");
+
+        let (status, body) = get(&router, "/healthz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "degraded");
+        // StreamList looks its chat up in the datastore
+        for component in body["components"].as_array().unwrap() {
+            let expected =
+                if ["datastore", "grpc"].contains(&component["component"].as_str().unwrap()) {
+                    "degraded"
+                } else {
+                    "ok"
+                };
+            assert_eq!(component["status"], expected, "{component}");
+        }
+
+        let (status, _) = get(&router, "/healthz?component=control").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_grpc_probe_goes_through_the_rate_limiter() {
+        let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+        let limiter = Arc::new(crate::rate_limit::TokenBucket::new(
+            1,
+            clock::system_clock(),
+        ));
+        let router = probes_with_grpc(
+            Arc::clone(&repo),
+            grpc_service(repo, Some(Arc::clone(&limiter))),
+        );
+
+        // The first call uses up the bucket, so the second is refused before the service
+        let (status, body) = get(&router, "/healthz?component=grpc").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let (status, body) = get(&router, "/healthz?component=grpc").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            body["error"],
+            "StreamList returned ResourceExhausted: Rate limit exceeded"
+        );
+    }
+}
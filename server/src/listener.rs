@@ -0,0 +1,39 @@
+//! Explicit listener configuration for the REST and gRPC servers
+
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpSocket};
+
+/// Listen backlog used when `LISTEN_BACKLOG` is not set, as `TcpListener::bind` uses
+pub const DEFAULT_LISTEN_BACKLOG: u32 = 1024;
+
+/// Bind `addr` with a listen queue of `backlog` pending connections
+/// Connections beyond the backlog are left to the OS, which refuses or drops them
+pub fn bind(addr: SocketAddr, backlog: u32) -> std::io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    // Match TcpListener::bind, so a restarted server can rebind immediately
+    #[cfg(unix)]
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(backlog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bound_listener_accepts_connections() {
+        let listener = bind("127.0.0.1:0".parse().unwrap(), 1).expect("Bind");
+        let addr = listener.local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+
+        let (client, accepted) =
+            tokio::join!(tokio::net::TcpStream::connect(addr), listener.accept());
+        client.expect("Connect");
+        accepted.expect("Accept");
+    }
+}
@@ -0,0 +1,236 @@
+//! Structured logging with `tracing`
+//!
+//! `RUST_LOG` filters events (`info` by default) and `LOG_FORMAT` picks human-readable
+//! text or one JSON object per line. Every REST request and gRPC call is logged by
+//! `AccessLogLayer` with the peer address of its connection, credentials in the URI redacted.
+
+use request_log::Redactor;
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tracing_subscriber::EnvFilter;
+
+/// Output format of the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown log format '{s}'. Use 'text' or 'json'")),
+        }
+    }
+}
+
+/// Install the global subscriber writing to stdout
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Text => builder.with_ansi(std::io::stdout().is_terminal()).init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
+
+/// Peer address of the connection a request arrived on
+///
+/// Each server stack records it differently: the GOAWAY-cycling gRPC server as a bare
+/// `SocketAddr`, axum as `ConnectInfo`, and tonic as its own connect info, wrapped when TLS is on.
+fn peer_addr(extensions: &http::Extensions) -> Option<SocketAddr> {
+    extensions
+        .get::<SocketAddr>()
+        .copied()
+        .or_else(|| {
+            extensions
+                .get::<axum::extract::ConnectInfo<SocketAddr>>()
+                .map(|info| info.0)
+        })
+        .or_else(|| {
+            extensions
+                .get::<TcpConnectInfo>()
+                .and_then(TcpConnectInfo::remote_addr)
+        })
+        .or_else(|| {
+            extensions
+                .get::<TlsConnectInfo<TcpConnectInfo>>()
+                .and_then(|info| info.get_ref().remote_addr())
+        })
+}
+
+/// Full name of the gRPC method a request calls, `None` for other requests
+///
+/// e.g. `youtube.api.v3.V3DataLiveChatMessageService/StreamList`
+fn grpc_method<B>(request: &http::Request<B>) -> Option<&str> {
+    let is_grpc = request
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/grpc"));
+    is_grpc.then(|| request.uri().path().trim_start_matches('/'))
+}
+
+/// Path and query of a REST request as they are logged
+///
+/// e.g. `/youtube/v3/videos?part=snippet&key=[redacted:1a2b3c4d]`
+fn redacted_uri(redactor: &Redactor, uri: &http::Uri) -> String {
+    let path = redactor.text(uri.path());
+    match uri.query() {
+        Some(query) => format!("{path}?{}", redactor.query(query)),
+        None => path,
+    }
+}
+
+/// Middleware logging every request as it arrives
+#[derive(Clone)]
+pub struct AccessLogLayer {
+    redactor: Arc<Redactor>,
+}
+
+impl AccessLogLayer {
+    pub fn new(redactor: Redactor) -> Self {
+        Self {
+            redactor: Arc::new(redactor),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for AccessLogLayer {
+    type Service = AccessLogService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        AccessLogService {
+            inner: service,
+            redactor: self.redactor.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AccessLogService<S> {
+    inner: S,
+    redactor: Arc<Redactor>,
+}
+
+impl<S, B> tower::Service<http::Request<B>> for AccessLogService<S>
+where
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let peer = peer_addr(req.extensions()).map(tracing::field::display);
+        match grpc_method(&req) {
+            Some(method) => tracing::info!(grpc.method = method, peer, "gRPC call {method}"),
+            None => {
+                let uri = redacted_uri(&self.redactor, req.uri());
+                tracing::info!(
+                    http.method = %req.method(),
+                    uri = %uri,
+                    peer,
+                    "{} {uri}",
+                    req.method()
+                )
+            }
+        }
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_format_parses_case_insensitively() {
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("TEXT".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_peer_addr_reads_every_server_stack() {
+        let addr: SocketAddr = "127.0.0.1:4242".parse().unwrap();
+
+        let mut extensions = http::Extensions::new();
+        assert_eq!(peer_addr(&extensions), None);
+        extensions.insert(addr);
+        assert_eq!(peer_addr(&extensions), Some(addr));
+
+        let mut extensions = http::Extensions::new();
+        extensions.insert(axum::extract::ConnectInfo(addr));
+        assert_eq!(peer_addr(&extensions), Some(addr));
+    }
+
+    #[test]
+    fn test_grpc_method_is_the_full_method_name() {
+        let grpc = http::Request::builder()
+            .uri("http://localhost/youtube.api.v3.V3DataLiveChatMessageService/StreamList")
+            .header(http::header::CONTENT_TYPE, "application/grpc+proto")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            grpc_method(&grpc),
+            Some("youtube.api.v3.V3DataLiveChatMessageService/StreamList")
+        );
+
+        let rest = http::Request::builder()
+            .uri("http://localhost/youtube/v3/videos")
+            .body(())
+            .unwrap();
+        assert_eq!(grpc_method(&rest), None);
+    }
+
+    #[test]
+    fn test_redacted_uri_matches_the_request_log() {
+        let token = "ya29.mock_0123456789abcdef";
+        let uri: http::Uri = format!("/youtube/v3/videos?part=snippet&access_token={token}")
+            .parse()
+            .unwrap();
+
+        let logged = redacted_uri(&Redactor::default(), &uri);
+        assert!(!logged.contains(token), "Token logged verbatim: {logged}");
+
+        // The request log redacts the same token to the same marker
+        let path = std::env::temp_dir().join(format!("access-log-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        request_log::RequestLog::open(&path)
+            .unwrap()
+            .record(&request_log::RequestLogEntry::Rest {
+                timestamp: chrono::Utc::now(),
+                method: "GET".to_string(),
+                path: uri.path().to_string(),
+                query: uri.query().map(str::to_string),
+                body: String::new(),
+            });
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(
+            !recorded.contains(token),
+            "Token recorded verbatim: {recorded}"
+        );
+
+        let marker = request_log::redact::marker(token);
+        assert!(logged.contains(&marker));
+        assert!(recorded.contains(&marker));
+
+        assert_eq!(redacted_uri(&Redactor::disabled(), &uri), uri.to_string());
+    }
+}
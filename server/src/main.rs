@@ -1,64 +1,94 @@
 use axum::Router;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use live_chat_service::proto::v3_data_live_chat_message_service_server::V3DataLiveChatMessageServiceServer;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tonic::transport::Server as GrpcServer;
+use tonic::transport::server::TcpIncoming;
 use tower::ServiceBuilder;
-
-// Middleware to log access requests
-#[derive(Clone)]
-struct LogLayer;
-
-impl<S> tower::Layer<S> for LogLayer {
-    type Service = LogService<S>;
-
-    fn layer(&self, service: S) -> Self::Service {
-        LogService { inner: service }
-    }
-}
-
-#[derive(Clone)]
-struct LogService<S> {
-    inner: S,
+use tower::limit::GlobalConcurrencyLimitLayer;
+
+mod gateway_parity;
+mod goaway;
+mod health;
+mod listener;
+mod logging;
+mod rate_limit;
+
+// Middleware to record REST requests (method, path, query, body) to the request log
+async fn record_rest_request(
+    State(log): State<Arc<request_log::RequestLog>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                http::StatusCode::BAD_REQUEST,
+                format!("Failed to read request body: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    log.record(&request_log::RequestLogEntry::Rest {
+        timestamp: clock::system_clock().now(),
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().map(str::to_string),
+        body: String::from_utf8_lossy(&bytes).into_owned(),
+    });
+
+    next.run(axum::extract::Request::from_parts(
+        parts,
+        axum::body::Body::from(bytes),
+    ))
+    .await
 }
 
-impl<S, B> tower::Service<http::Request<B>> for LogService<S>
-where
-    S: tower::Service<http::Request<B>> + Clone + Send + 'static,
-    S::Future: Send + 'static,
-    B: Send + 'static,
-{
-    type Response = S::Response;
-    type Error = S::Error;
-    type Future = std::pin::Pin<
-        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
-    >;
-
-    fn poll_ready(
-        &mut self,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx)
+// Middleware to save the response body of requests naming a fixture in x-mock-record
+async fn record_rest_response(
+    State(recorder): State<Arc<request_log::ResponseRecorder>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let name = match request
+        .headers()
+        .get(request_log::responses::RECORD_HEADER)
+        .map(|value| value.to_str())
+    {
+        None => return next.run(request).await,
+        Some(Ok(name)) => name.trim().to_string(),
+        Some(Err(_)) => String::new(),
+    };
+    // Rejected before the request runs, so a typo does not go unnoticed
+    if let Err(e) = recorder.path_for(&name) {
+        return (http::StatusCode::BAD_REQUEST, e).into_response();
     }
 
-    fn call(&mut self, req: http::Request<B>) -> Self::Future {
-        let method = req.method().clone();
-        let uri = req.uri().clone();
-        let remote_addr = req.extensions().get::<std::net::SocketAddr>().copied();
-
-        let timestamp = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-
-        if let Some(addr) = remote_addr {
-            println!("[{timestamp}] {method} {uri} from {addr}");
-        } else {
-            println!("[{timestamp}] {method} {uri} from <unknown>");
+    let response = next.run(request).await;
+    let (parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read response body: {e}"),
+            )
+                .into_response();
         }
-
-        Box::pin(self.inner.call(req))
+    };
+    match recorder.save(&name, &bytes) {
+        Ok(path) => tracing::info!(path = %path.display(), "Recorded response as '{name}'"),
+        Err(e) => tracing::warn!("Failed to record response as '{name}': {e}"),
     }
+
+    axum::response::Response::from_parts(parts, axum::body::Body::from(bytes))
 }
 
 // Load TLS configuration from certificate and key files
@@ -83,26 +113,87 @@ async fn load_rustls_config(
     Ok(axum_server::tls_rustls::RustlsConfig::from_pem_file(cert_path, key_path).await?)
 }
 
-// Signal handler for graceful shutdown
+// Readiness endpoint: OK once every listener is bound and serving
+async fn readiness(State(ready): State<Arc<AtomicBool>>) -> impl IntoResponse {
+    if ready.load(Ordering::SeqCst) {
+        (http::StatusCode::OK, "OK")
+    } else {
+        (http::StatusCode::SERVICE_UNAVAILABLE, "NOT READY")
+    }
+}
+
+// Write the bound ports as JSON, renaming into place so readers never see a partial file
+fn write_port_file(
+    path: &std::path::Path,
+    grpc_addr: SocketAddr,
+    rest_addr: SocketAddr,
+    health_addr: SocketAddr,
+) -> std::io::Result<()> {
+    let ports = serde_json::json!({
+        "grpc": grpc_addr.port(),
+        "rest": rest_addr.port(),
+        "health": health_addr.port(),
+    });
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, ports.to_string())?;
+    std::fs::rename(&tmp_path, path)
+}
+
+// Time the servers get to finish in-flight requests after a shutdown signal before the
+// process exits anyway
+const SHUTDOWN_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+// Signal handler for graceful shutdown, on SIGTERM or SIGINT (Ctrl+C)
 async fn shutdown_signal() {
     #[cfg(unix)]
     {
-        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-            .expect("failed to install SIGTERM handler")
-            .recv()
-            .await;
-        println!("Received SIGTERM signal, starting graceful shutdown...");
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("failed to install SIGTERM handler");
+        tokio::select! {
+            _ = terminate.recv() => {
+                tracing::info!("Received SIGTERM signal, starting graceful shutdown...");
+            }
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("Received SIGINT signal, starting graceful shutdown...");
+            }
+        }
     }
 
     #[cfg(not(unix))]
     {
-        // On non-Unix platforms, wait indefinitely (no signal handling)
-        std::future::pending::<()>().await
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+        tracing::info!("Received Ctrl+C, starting graceful shutdown...");
+    }
+}
+
+// Wait for the servers to finish, giving up after SHUTDOWN_DRAIN_TIMEOUT
+async fn drain_servers(servers: impl std::future::Future) {
+    if tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, servers)
+        .await
+        .is_err()
+    {
+        tracing::info!(
+            "Servers did not drain within {}s, exiting",
+            SHUTDOWN_DRAIN_TIMEOUT.as_secs()
+        );
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Parse LOG_FORMAT environment variable ("text" or "json"), before anything is logged
+    // RUST_LOG filters the events, `info` and above by default
+    let log_format = match std::env::var("LOG_FORMAT") {
+        Ok(format) if !format.is_empty() => format
+            .parse::<logging::LogFormat>()
+            .map_err(|e| format!("Failed to parse LOG_FORMAT: {e}"))?,
+        _ => logging::LogFormat::default(),
+    };
+    logging::init(log_format);
+
     // Install the default crypto provider for rustls (required for TLS)
     // This is safe to call even if a provider is already installed
     let _ = rustls::crypto::ring::default_provider().install_default();
@@ -118,8 +209,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let tls_cert_path = std::env::var("TLS_CERT_PATH").ok().map(PathBuf::from);
     let tls_key_path = std::env::var("TLS_KEY_PATH").ok().map(PathBuf::from);
 
-    let use_tls = tls_cert_path.is_some() && tls_key_path.is_some();
-
     // Parse CHAT_STREAM_TIMEOUT environment variable
     // If not set or set to 0, the connection will be kept alive indefinitely
     // Otherwise, it should be a number of seconds
@@ -129,67 +218,599 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .filter(|&timeout| timeout > 0)
         .map(std::time::Duration::from_secs);
 
-    let grpc_addr: std::net::SocketAddr = grpc_bind_address
+    // Parse STRICT_TOKEN_VALIDATION environment variable
+    // When true, live chat streams reject bearer tokens that were not issued by this server
+    let strict_token_validation = std::env::var("STRICT_TOKEN_VALIDATION")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+    let token_validator: Arc<dyn oauth_service::TokenValidator> =
+        Arc::new(oauth_service::IssuedTokenValidator {
+            strict: strict_token_validation,
+        });
+
+    // Faults injected via the control API, consulted by the REST, OAuth and gRPC APIs
+    let faults = Arc::new(domain::FaultConfig::default());
+
+    // Parse OAUTH_CODE_VALIDATION environment variable
+    // auto (default): any code until /authorize issues one, then only issued codes, once each
+    // strict: only issued codes, once each; permissive: any non-empty code
+    let auth_code_validation = match std::env::var("OAUTH_CODE_VALIDATION") {
+        Ok(mode) if !mode.is_empty() => mode
+            .parse::<oauth_service::AuthCodeValidation>()
+            .map_err(|e| format!("Failed to parse OAUTH_CODE_VALIDATION: {e}"))?,
+        _ => oauth_service::AuthCodeValidation::default(),
+    };
+
+    // Parse OAUTH_ROTATE_REFRESH environment variable
+    // When true, refreshing returns a new refresh token and invalidates the presented one
+    // Parse OAUTH_ID_TOKEN_KEY environment variable
+    // When set, ID tokens for the openid scope are signed with HS256 using this key,
+    // otherwise with a key generated at startup
+    // Parse OAUTH_PERSIST_PATH environment variable
+    // When set, refresh tokens are loaded from and saved to this JSON file,
+    // so they stay valid across restarts
+    let oauth_config = oauth_service::OAuthConfig {
+        rotate_refresh_tokens: std::env::var("OAUTH_ROTATE_REFRESH")
+            .ok()
+            .and_then(|s| s.parse::<bool>().ok())
+            .unwrap_or(false),
+        id_token_signing_key: std::env::var("OAUTH_ID_TOKEN_KEY")
+            .ok()
+            .filter(|key| !key.is_empty()),
+        tls: tls_cert_path.is_some() && tls_key_path.is_some(),
+        faults: Arc::clone(&faults),
+        refresh_token_file: std::env::var("OAUTH_PERSIST_PATH")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(|path| Arc::new(oauth_service::RefreshTokenFile::open(path))),
+        auth_code_validation,
+    };
+
+    // Parse CHAT_MAX_TEXT_LEN environment variable
+    // If not set or set to 0, message texts of any length are accepted
+    // Otherwise, creating or inserting a message with a longer text (in characters) is rejected
+    let max_text_len = std::env::var("CHAT_MAX_TEXT_LEN")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|&len| len > 0);
+
+    // Parse DISPLAY_MESSAGE_POLICY environment variable ("raw" or "escaped")
+    // Controls how chat message text is rendered into displayMessage
+    let display_message_policy = match std::env::var("DISPLAY_MESSAGE_POLICY") {
+        Ok(policy) if !policy.is_empty() => policy
+            .parse::<domain::DisplayMessagePolicy>()
+            .map_err(|e| format!("Failed to parse DISPLAY_MESSAGE_POLICY: {e}"))?,
+        _ => domain::DEFAULT_DISPLAY_MESSAGE_POLICY,
+    };
+
+    // Parse STREAM_CURSOR_TTL environment variable
+    // If not set or set to 0, page tokens are stateless encoded message indexes
+    // Otherwise, page tokens are opaque server-tracked cursors that expire after this many seconds
+    let cursor_store = std::env::var("STREAM_CURSOR_TTL")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&ttl| ttl > 0)
+        .map(|ttl| {
+            Arc::new(live_chat_service::CursorStore::new(
+                std::time::Duration::from_secs(ttl),
+                clock::system_clock(),
+            ))
+        });
+
+    // Parse OAUTH_PATH_PREFIX environment variable
+    // Defaults to /oauth2; "/" or an empty value serves /token and /authorize at the root,
+    // matching https://oauth2.googleapis.com/token
+    let oauth_path_prefix = std::env::var("OAUTH_PATH_PREFIX")
+        .map(|prefix| prefix.trim_matches('/').to_string())
+        .unwrap_or_else(|_| "oauth2".to_string());
+
+    // Parse REDACT_SENSITIVE, REDACT_HEADERS, REDACT_QUERY_PARAMS and REDACT_PATTERN
+    // Credentials are replaced with a stable hash in the access log and the request log,
+    // unless REDACT_SENSITIVE is false. The others add comma-separated header/metadata names,
+    // query/body parameter names, and one regex to the built-in rules
+    let redact_sensitive = std::env::var("REDACT_SENSITIVE")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(true);
+    let redactor = if redact_sensitive {
+        let list = |name| std::env::var(name).unwrap_or_default();
+        let headers = list("REDACT_HEADERS");
+        let params = list("REDACT_QUERY_PARAMS");
+        let redactor = request_log::Redactor::default()
+            .with_headers(headers.split(',').filter(|name| !name.trim().is_empty()))
+            .with_params(params.split(',').filter(|name| !name.trim().is_empty()));
+        match std::env::var("REDACT_PATTERN") {
+            Ok(pattern) if !pattern.is_empty() => redactor
+                .with_pattern(&pattern)
+                .map_err(|e| format!("Failed to parse REDACT_PATTERN: {e}"))?,
+            _ => redactor,
+        }
+    } else {
+        tracing::warn!("REDACT_SENSITIVE=false: credentials are logged verbatim");
+        request_log::Redactor::disabled()
+    };
+    let access_log = logging::AccessLogLayer::new(redactor.clone());
+
+    // Optional request log for capture/replay of client sessions
+    let request_log = match std::env::var("REQUEST_LOG_FILE") {
+        Ok(path) if !path.is_empty() => {
+            let log = request_log::RequestLog::open(&path)
+                .map_err(|e| format!("Failed to open REQUEST_LOG_FILE '{path}': {e}"))?
+                .with_redactor(redactor);
+            Some(Arc::new(log))
+        }
+        _ => None,
+    };
+
+    // Parse RECORD_RESPONSES_DIR environment variable
+    // When set, Data API requests with an x-mock-record: <name> header have their response
+    // body saved to <name>.json in this directory; otherwise the header is ignored
+    let response_recorder = std::env::var("RECORD_RESPONSES_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Arc::new(request_log::ResponseRecorder::new(dir)));
+
+    // Parse GLOBAL_RATE_LIMIT_PER_SEC environment variable
+    // If not set or set to 0, requests are not rate limited
+    // Otherwise, a token bucket shared by all REST and gRPC endpoints allows this many requests per second
+    let global_rate_limit = std::env::var("GLOBAL_RATE_LIMIT_PER_SEC")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|&rate| rate > 0);
+    let rate_limiter = global_rate_limit
+        .map(|rate| Arc::new(rate_limit::TokenBucket::new(rate, clock::system_clock())));
+
+    // Parse LISTEN_BACKLOG environment variable
+    // Length of the pending-connection queue of the REST and gRPC listeners
+    let listen_backlog = std::env::var("LISTEN_BACKLOG")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|&backlog| backlog > 0)
+        .unwrap_or(listener::DEFAULT_LISTEN_BACKLOG);
+
+    // Parse MAX_CONCURRENT_REQUESTS environment variable
+    // If not set or set to 0, concurrency is unlimited
+    // Otherwise, each of the REST and gRPC listeners handles at most this many requests at once
+    // and further requests wait for a free slot instead of failing. A gRPC stream frees its
+    // slot once it is established
+    let max_concurrent_requests = std::env::var("MAX_CONCURRENT_REQUESTS")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|&limit| limit > 0);
+
+    // Parse GRPC_MAX_STREAMS_PER_CONNECTION and GRPC_GOAWAY_GRACE environment variables
+    // If set and not 0, the gRPC server sends a GOAWAY after this many streams on a connection,
+    // so clients have to reconnect. Streams already open run on for GRPC_GOAWAY_GRACE seconds
+    // (unset = until they end), then the connection is closed
+    let grpc_connection_cycling = std::env::var("GRPC_MAX_STREAMS_PER_CONNECTION")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&max_streams| max_streams > 0)
+        .map(|max_streams| goaway::ConnectionCycling {
+            max_streams,
+            grace: std::env::var("GRPC_GOAWAY_GRACE")
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .map(std::time::Duration::from_secs),
+        });
+
+    let grpc_addr: SocketAddr = grpc_bind_address
         .parse()
         .map_err(|e| format!("Failed to parse GRPC_BIND_ADDRESS '{grpc_bind_address}': {e}"))?;
-    let rest_addr: std::net::SocketAddr = rest_bind_address
+    let rest_addr: SocketAddr = rest_bind_address
         .parse()
         .map_err(|e| format!("Failed to parse REST_BIND_ADDRESS '{rest_bind_address}': {e}"))?;
-    let health_addr: std::net::SocketAddr = health_bind_address
+    let health_addr: SocketAddr = health_bind_address
         .parse()
         .map_err(|e| format!("Failed to parse HEALTH_BIND_ADDRESS '{health_bind_address}': {e}"))?;
 
+    // Parse GATEWAY_PARITY environment variable
+    // When true, the REST listener replicates edge behaviors of Google's API frontend
+    let gateway_parity = std::env::var("GATEWAY_PARITY")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse QUOTA_COST_HEADERS environment variable
+    // When true, API responses report the quota units charged and the units left
+    let quota_cost_headers = std::env::var("QUOTA_COST_HEADERS")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse QUOTA_ERROR_STATUS environment variable ("403" or "429")
+    // HTTP status of REST calls rejected for exceeding an enforced daily quota
+    let quota_error_status = match std::env::var("QUOTA_ERROR_STATUS") {
+        Ok(status) if !status.is_empty() => status
+            .parse::<domain::QuotaErrorStatus>()
+            .map_err(|e| format!("Failed to parse QUOTA_ERROR_STATUS: {e}"))?,
+        _ => domain::QuotaErrorStatus::default(),
+    };
+
+    // Parse CONTROL_READONLY environment variable
+    // When true, mutating control routes return 403 while the GET routes keep working
+    let control_readonly = std::env::var("CONTROL_READONLY")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse CONTROL_METHOD_OVERRIDE environment variable
+    // When true, a control POST with X-HTTP-Method-Override is routed as the header's method
+    let control_method_override = std::env::var("CONTROL_METHOD_OVERRIDE")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse CONTROL_REPLAY_ANY_PATH environment variable
+    // When true, POST /control/replay reads any file named in the request, not just the
+    // REQUEST_LOG_FILE
+    let control_replay_any_path = std::env::var("CONTROL_REPLAY_ANY_PATH")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse CHAT_UNIQUE_IDS environment variable
+    // When true, adding a chat message whose ID already exists in the same chat is rejected
+    let chat_unique_ids = std::env::var("CHAT_UNIQUE_IDS")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse REALISTIC_CHAT_IDS environment variable
+    // When true, videos created through the control API without a liveChatId get a chat ID
+    // in the opaque base64 format of the real API, derived from the video ID
+    let realistic_chat_ids = std::env::var("REALISTIC_CHAT_IDS")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse CHAT_SINGLE_CONSUMER environment variable
+    // When true, a stream opened with a page token that an open stream of the same chat
+    // presented is rejected with ALREADY_EXISTS
+    let chat_single_consumer = std::env::var("CHAT_SINGLE_CONSUMER")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // Parse CHAT_POLLING_INTERVAL_MS environment variable
+    // Polling interval advertised on StreamList responses in milliseconds, 1000 when unset or 0
+    let chat_polling_interval_millis = std::env::var("CHAT_POLLING_INTERVAL_MS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&interval| interval > 0)
+        .unwrap_or(live_chat_service::DEFAULT_POLLING_INTERVAL_MILLIS);
+
+    // Parse CHAT_KEEPALIVE_EVERY_N_MESSAGES environment variable
+    // When set above 0, StreamList follows every N delivered messages with an empty keep-alive
+    // response carrying the current page token, even while more messages are pending
+    let chat_keepalive_every_n_messages = std::env::var("CHAT_KEEPALIVE_EVERY_N_MESSAGES")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|&n| n > 0);
+
+    // Parse FIRST_RESPONSE_BUDGET_MS environment variable
+    // When set, every StreamList call sends its first response within this many milliseconds,
+    // an empty one if the history is not read by then. Unset or 0 waits for the history
+    let first_response_budget = std::env::var("FIRST_RESPONSE_BUDGET_MS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&budget| budget > 0)
+        .map(std::time::Duration::from_millis);
+
+    // Parse DUMMY_AUTHOR_COUNT environment variable
+    // When set, the generated dummy messages come from this many recurring authors instead of
+    // a different author per message (ignored with SEED_DATA_PATH)
+    let dummy_author_count = std::env::var("DUMMY_AUTHOR_COUNT")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    // Parse SEED_DATA_PATH environment variable
+    // When set, the datastore is loaded from this JSON file instead of the dummy data
+    // Files ending in .yaml or .yml are read as YAML when built with the `yaml` feature
+    let seed = match std::env::var("SEED_DATA_PATH") {
+        Ok(path) if !path.is_empty() => Some(datastore::SeedData::load(&PathBuf::from(path))?),
+        _ => None,
+    };
+
+    // Parse DATASTORE and DATASTORE_PATH environment variables
+    // DATASTORE=file keeps the datastore in the JSON file at DATASTORE_PATH across restarts;
+    // "memory" (the default) starts from the seed or dummy data every time
+    let file_repo = match std::env::var("DATASTORE").as_deref() {
+        Err(_) | Ok("") | Ok("memory") => None,
+        Ok("file") => {
+            let path = std::env::var("DATASTORE_PATH")
+                .ok()
+                .filter(|path| !path.is_empty())
+                .ok_or("DATASTORE=file needs DATASTORE_PATH")?;
+            if seed.is_some() {
+                return Err("SEED_DATA_PATH cannot be combined with DATASTORE=file".into());
+            }
+            Some(Arc::new(
+                datastore::FileRepository::open(path).with_unique_message_ids(chat_unique_ids),
+            ))
+        }
+        Ok(other) => {
+            return Err(format!("Unknown DATASTORE '{other}'. Use 'memory' or 'file'").into());
+        }
+    };
+
     // Create the centralized datastore
-    let repo: Arc<dyn datastore::Repository> = Arc::new(datastore::InMemoryRepository::new());
+    let repo: Arc<dyn datastore::Repository> = match &file_repo {
+        Some(file_repo) => file_repo.clone(),
+        None => {
+            let in_memory_repo = match seed {
+                Some(seed) => {
+                    tracing::info!(
+                        "Seeding datastore with {} videos and {} chat messages",
+                        seed.videos.len(),
+                        seed.chat_messages.len()
+                    );
+                    datastore::InMemoryRepository::from_seed(seed)
+                        .map_err(|e| format!("Failed to seed datastore: {e}"))?
+                }
+                None => datastore::InMemoryRepository::with_dummy_author_pool(dummy_author_count),
+            };
+            Arc::new(in_memory_repo.with_unique_message_ids(chat_unique_ids))
+        }
+    };
+
+    // Open live chat streams, shared with the control API for reporting
+    let stream_registry = Arc::new(domain::StreamRegistry::default());
+    // Cancelled on shutdown so open streams end cleanly instead of holding their connections,
+    // and message generators stop
+    let stream_shutdown = tokio_util::sync::CancellationToken::new();
+
+    // Quota charged by the REST and gRPC APIs, reported by the control API
+    let quota = Arc::new(
+        domain::QuotaLedger::new(quota_cost_headers).with_error_status(quota_error_status),
+    );
 
     // Create gRPC service for live chat with shared datastore
-    let grpc_service = live_chat_service::create_service(Arc::clone(&repo), stream_timeout);
+    let live_chat_core = live_chat_service::LiveChatService::new(
+        Arc::clone(&repo),
+        stream_timeout,
+        request_log.clone(),
+        display_message_policy,
+        cursor_store.clone(),
+        Arc::clone(&token_validator),
+        Arc::clone(&stream_registry),
+    )
+    .with_quota(Arc::clone(&quota))
+    .with_faults(Arc::clone(&faults))
+    .with_polling_interval_millis(chat_polling_interval_millis)
+    .with_first_response_budget(first_response_budget)
+    .with_single_consumer(chat_single_consumer)
+    .with_keepalive_every_n_messages(chat_keepalive_every_n_messages)
+    .with_shutdown(stream_shutdown.clone());
+    let grpc_service = V3DataLiveChatMessageServiceServer::new(live_chat_core.clone());
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(live_chat_service::proto::FILE_DESCRIPTOR_SET)
         .build_v1()?;
 
+    // Collect gRPC services; the reflection descriptor set covers every compiled package
+    #[allow(unused_mut)]
+    let mut grpc_routes = tonic::service::Routes::new(grpc_service).add_service(reflection_service);
+
+    // Serve the experimental vNext package side by side, backed by the same datastore
+    #[cfg(feature = "vnext")]
+    {
+        let vnext_service = live_chat_service::vnext::create_service(live_chat_core);
+        grpc_routes = grpc_routes.add_service(vnext_service);
+        tracing::info!(
+            "Serving live chat packages {} and {}",
+            live_chat_service::proto::PACKAGE,
+            live_chat_service::proto::vnext::PACKAGE
+        );
+    }
+
     // Create REST service for videos API with shared datastore
-    let video_router = video_service::create_router(Arc::clone(&repo));
+    let video_router = video_service::router_with_state(
+        video_service::VideoState::new(
+            Arc::clone(&repo),
+            display_message_policy,
+            Arc::clone(&quota),
+            Arc::clone(&faults),
+        )
+        .with_max_text_len(max_text_len),
+    );
+    let video_router = match &response_recorder {
+        Some(recorder) => video_router.layer(axum::middleware::from_fn_with_state(
+            Arc::clone(recorder),
+            record_rest_response,
+        )),
+        None => video_router,
+    };
 
     // Create control service for managing videos and chat messages
-    let control_router = control_service::create_router(Arc::clone(&repo));
+    let control_router = control_service::router_with_state(
+        control_service::ControlState::new(
+            Arc::clone(&repo),
+            Arc::clone(&stream_registry),
+            Arc::clone(&quota),
+            faults,
+        )
+        .with_max_text_len(max_text_len)
+        .with_shutdown(stream_shutdown.clone())
+        .with_realistic_chat_ids(realistic_chat_ids)
+        .with_request_log_file(request_log.as_ref().map(|log| log.path().to_path_buf()))
+        .with_replay_any_path(control_replay_any_path),
+    );
+    let control_router = if control_readonly {
+        tracing::info!("Control API is read-only");
+        control_service::read_only(control_router)
+    } else {
+        control_router
+    };
+    let control_router = if control_method_override {
+        tracing::info!("Control API honors X-HTTP-Method-Override");
+        control_service::method_override(control_router)
+    } else {
+        control_router
+    };
 
     // Create OAuth service for token generation and refresh
-    let oauth_router = oauth_service::create_router();
+    let oauth_router = oauth_service::create_router(oauth_config);
+
+    // The health probes call the OAuth and control routers directly
+    let oauth_probe_router = oauth_router.clone();
+    let control_probe_router = control_router.clone();
 
     // Nest routers under their respective paths to avoid conflicts
     let rest_app = Router::new()
         .nest("/youtube/v3", video_router)
-        .nest("/control", control_router)
-        .nest("/oauth2", oauth_router);
+        .nest("/control", control_router);
+    let rest_app = if oauth_path_prefix.is_empty() {
+        rest_app.merge(oauth_router)
+    } else {
+        rest_app.nest(&format!("/{oauth_path_prefix}"), oauth_router)
+    };
+
+    // Record all REST requests when a request log is configured
+    let rest_app = match &request_log {
+        Some(log) => rest_app.layer(axum::middleware::from_fn_with_state(
+            Arc::clone(log),
+            record_rest_request,
+        )),
+        None => rest_app,
+    };
+
+    // Apply the global rate limit to all REST endpoints
+    let rest_app = match &rate_limiter {
+        Some(limiter) => rest_app.layer(axum::middleware::from_fn_with_state(
+            Arc::clone(limiter),
+            rate_limit::limit_rest_requests,
+        )),
+        None => rest_app,
+    };
+
+    // Emulate the Google frontend's edge behaviors, outside every other REST layer
+    let rest_app = if gateway_parity {
+        rest_app.layer(axum::middleware::from_fn(gateway_parity::gateway_parity))
+    } else {
+        rest_app
+    };
+
+    // Cap concurrent REST requests, outside every other REST layer
+    let rest_app = match max_concurrent_requests {
+        Some(limit) => rest_app.layer(GlobalConcurrencyLimitLayer::new(limit)),
+        None => rest_app,
+    };
+
+    // Log every REST request as it arrives, including those waiting for the concurrency limit
+    let rest_app = rest_app.layer(access_log.clone());
+
+    let grpc_rate_limit =
+        tonic::service::InterceptorLayer::new(rate_limit::grpc_interceptor(rate_limiter.clone()));
+    let grpc_concurrency_limit = max_concurrent_requests.map(GlobalConcurrencyLimitLayer::new);
+
+    // The gRPC health probe calls the routes in-process, through the listener's layers
+    let grpc_probe_service = health::GrpcService::new(
+        ServiceBuilder::new()
+            .map_response(|response: http::Response<_>| response.map(tonic::body::Body::new))
+            .option_layer(grpc_concurrency_limit.clone())
+            .layer(access_log.clone())
+            .layer(grpc_rate_limit.clone())
+            .service(grpc_routes.clone().prepare()),
+    );
+
+    // Load TLS configuration before binding so a bad certificate fails fast
+    let tls_configs = match (tls_cert_path, tls_key_path) {
+        (Some(cert_path), Some(key_path)) => Some((
+            load_tls_config(cert_path.clone(), key_path.clone())?,
+            load_rustls_config(cert_path, key_path).await?,
+        )),
+        _ => None,
+    };
+
+    // Bind all listeners up front so ephemeral ports (":0") are resolved before serving
+    let grpc_listener = listener::bind(grpc_addr, listen_backlog)
+        .map_err(|e| format!("Failed to bind gRPC server to {grpc_addr}: {e}"))?;
+    let rest_listener = listener::bind(rest_addr, listen_backlog)
+        .map_err(|e| format!("Failed to bind REST server to {rest_addr}: {e}"))?;
+    let health_listener = tokio::net::TcpListener::bind(health_addr)
+        .await
+        .map_err(|e| format!("Failed to bind health check endpoint to {health_addr}: {e}"))?;
+    let grpc_addr = grpc_listener.local_addr()?;
+    let rest_addr = rest_listener.local_addr()?;
+    let health_addr = health_listener.local_addr()?;
+
+    // Create the health and readiness endpoints (always run without TLS)
+    // /healthz probes each subsystem, /readyz reports whether every listener is serving
+    let ready = Arc::new(AtomicBool::new(false));
+    let probes = health::Probes {
+        repo: Arc::clone(&repo),
+        oauth: oauth_probe_router,
+        control: control_probe_router,
+        grpc: grpc_probe_service,
+        timeout: health::PROBE_TIMEOUT,
+    };
+    let health_app = Router::new()
+        .route("/healthz", axum::routing::get(health::healthz))
+        .with_state(Arc::new(probes))
+        .merge(
+            Router::new()
+                .route("/readyz", axum::routing::get(readiness))
+                .with_state(Arc::clone(&ready)),
+        );
+
+    // Report the bound ports to callers that started the server on ephemeral ports
+    match std::env::var("PORT_FILE") {
+        Ok(path) if !path.is_empty() => {
+            write_port_file(&PathBuf::from(&path), grpc_addr, rest_addr, health_addr)
+                .map_err(|e| format!("Failed to write PORT_FILE '{path}': {e}"))?;
+        }
+        _ => {}
+    }
 
-    // Create a simple health check endpoint (always runs without TLS)
-    let health_app = Router::new().route("/healthz", axum::routing::get(|| async { "OK" }));
+    if let Some(rate) = global_rate_limit {
+        tracing::info!("Global rate limit: {rate} requests/sec");
+    }
 
-    if use_tls {
-        println!("TLS enabled");
-        println!("gRPC server (live chat) listening on {grpc_addr} with TLS");
-        println!("REST server (videos API) listening on {rest_addr} with TLS");
-        println!("Health check endpoint listening on {health_addr} (no TLS)");
-    } else {
-        println!("TLS disabled");
-        println!("gRPC server (live chat) listening on {grpc_addr}");
-        println!("REST server (videos API) listening on {rest_addr}");
-        println!("Health check endpoint listening on {health_addr}");
+    if let Some(limit) = max_concurrent_requests {
+        tracing::info!("Concurrent request limit: {limit} per listener (backlog {listen_backlog})");
     }
 
-    // Run all servers concurrently with graceful shutdown
-    if use_tls {
-        let cert_path =
-            tls_cert_path.expect("TLS cert path should be present when use_tls is true");
-        let key_path = tls_key_path.expect("TLS key path should be present when use_tls is true");
+    if let Some(cycling) = grpc_connection_cycling {
+        match cycling.grace {
+            Some(grace) => tracing::info!(
+                "gRPC connections are sent a GOAWAY after {} streams ({}s grace)",
+                cycling.max_streams,
+                grace.as_secs()
+            ),
+            None => tracing::info!(
+                "gRPC connections are sent a GOAWAY after {} streams",
+                cycling.max_streams
+            ),
+        }
+    }
 
-        // Load TLS config for gRPC
-        let grpc_tls_config = load_tls_config(cert_path.clone(), key_path.clone())?;
+    if let Some(cursors) = &cursor_store {
+        tracing::info!(
+            "Stream page tokens are server-tracked cursors (TTL {}s)",
+            cursors.ttl().as_secs()
+        );
+    }
 
-        // Load TLS config for REST
-        let rest_tls_config = load_rustls_config(cert_path, key_path).await?;
+    if let Some(log) = &request_log {
+        tracing::info!("Recording requests to {:?}", log.path());
+    }
 
+    if tls_configs.is_some() {
+        tracing::info!("TLS enabled");
+        tracing::info!("gRPC server (live chat) listening on {grpc_addr} with TLS");
+        tracing::info!("REST server (videos API) listening on {rest_addr} with TLS");
+        tracing::info!("Health check endpoint listening on {health_addr} (no TLS)");
+    } else {
+        tracing::info!("TLS disabled");
+        tracing::info!("gRPC server (live chat) listening on {grpc_addr}");
+        tracing::info!("REST server (videos API) listening on {rest_addr}");
+        tracing::info!("Health check endpoint listening on {health_addr}");
+    }
+
+    // Run all servers concurrently with graceful shutdown
+    if let Some((grpc_tls_config, rest_tls_config)) = tls_configs {
         // Create a broadcast channel for shutdown signal
         let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(1);
 
@@ -199,19 +820,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let health_shutdown_rx = shutdown_tx.subscribe();
 
         // Spawn gRPC server
-        let grpc_handle = tokio::spawn(async move {
-            let mut rx = grpc_shutdown_rx;
-            GrpcServer::builder()
-                .tls_config(grpc_tls_config)
-                .expect("Failed to configure TLS for gRPC server")
-                .layer(ServiceBuilder::new().layer(LogLayer))
-                .add_service(grpc_service)
-                .add_service(reflection_service)
-                .serve_with_shutdown(grpc_addr, async move {
-                    let _ = rx.recv().await;
+        let grpc_handle = match grpc_connection_cycling {
+            Some(cycling) => {
+                let mut grpc_rustls_config = (*rest_tls_config.get_inner()).clone();
+                grpc_rustls_config.alpn_protocols = vec![b"h2".to_vec()];
+                let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(grpc_rustls_config));
+                let service = ServiceBuilder::new()
+                    .option_layer(grpc_concurrency_limit)
+                    .layer(access_log.clone())
+                    .layer(grpc_rate_limit)
+                    .service(grpc_routes.prepare());
+                tokio::spawn(async move {
+                    let mut rx = grpc_shutdown_rx;
+                    let shutdown = async move {
+                        let _ = rx.recv().await;
+                    };
+                    if let Err(e) =
+                        goaway::serve(grpc_listener, Some(acceptor), service, cycling, shutdown)
+                            .await
+                    {
+                        tracing::error!("gRPC server error: {e}");
+                    }
                 })
-                .await
-        });
+            }
+            None => tokio::spawn(async move {
+                let mut rx = grpc_shutdown_rx;
+                let result = GrpcServer::builder()
+                    .tls_config(grpc_tls_config)
+                    .expect("Failed to configure TLS for gRPC server")
+                    .layer(
+                        ServiceBuilder::new()
+                            .option_layer(grpc_concurrency_limit)
+                            .layer(access_log.clone())
+                            .layer(grpc_rate_limit),
+                    )
+                    .add_routes(grpc_routes)
+                    .serve_with_incoming_shutdown(TcpIncoming::from(grpc_listener), async move {
+                        let _ = rx.recv().await;
+                    })
+                    .await;
+                if let Err(e) = result {
+                    tracing::error!("gRPC server error: {e}");
+                }
+            }),
+        };
 
         // Spawn REST server with axum-server handle for graceful shutdown
         let rest_handle = tokio::spawn(async move {
@@ -225,31 +877,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 shutdown_handle.graceful_shutdown(None);
             });
 
-            axum_server::bind_rustls(rest_addr, rest_tls_config)
+            axum_server::from_tcp_rustls(rest_listener.into_std()?, rest_tls_config)
                 .handle(handle)
-                .serve(rest_app.into_make_service())
+                .serve(rest_app.into_make_service_with_connect_info::<SocketAddr>())
                 .await
         });
 
         // Spawn health check server
         let health_handle = tokio::spawn(async move {
             let mut rx = health_shutdown_rx;
-            let listener = tokio::net::TcpListener::bind(health_addr).await?;
-            axum::serve(listener, health_app)
+            axum::serve(health_listener, health_app)
                 .with_graceful_shutdown(async move {
                     let _ = rx.recv().await;
                 })
                 .await
         });
 
+        // All listeners are bound and serving
+        ready.store(true, Ordering::SeqCst);
+
         // Wait for shutdown signal
         shutdown_signal().await;
+        ready.store(false, Ordering::SeqCst);
 
-        // Broadcast shutdown to all servers
+        // End open streams, then broadcast shutdown to all servers
+        stream_shutdown.cancel();
         let _ = shutdown_tx.send(());
 
         // Wait for all servers to shut down gracefully
-        let _ = tokio::join!(grpc_handle, rest_handle, health_handle);
+        drain_servers(async { tokio::join!(grpc_handle, rest_handle, health_handle) }).await;
     } else {
         // Create a broadcast channel for shutdown signal
         let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(1);
@@ -260,48 +916,86 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let health_shutdown_rx = shutdown_tx.subscribe();
 
         // Spawn gRPC server
-        let grpc_handle = tokio::spawn(async move {
-            let mut rx = grpc_shutdown_rx;
-            GrpcServer::builder()
-                .layer(ServiceBuilder::new().layer(LogLayer))
-                .add_service(grpc_service)
-                .add_service(reflection_service)
-                .serve_with_shutdown(grpc_addr, async move {
-                    let _ = rx.recv().await;
+        let grpc_handle = match grpc_connection_cycling {
+            Some(cycling) => {
+                let service = ServiceBuilder::new()
+                    .option_layer(grpc_concurrency_limit)
+                    .layer(access_log.clone())
+                    .layer(grpc_rate_limit)
+                    .service(grpc_routes.prepare());
+                tokio::spawn(async move {
+                    let mut rx = grpc_shutdown_rx;
+                    let shutdown = async move {
+                        let _ = rx.recv().await;
+                    };
+                    if let Err(e) =
+                        goaway::serve(grpc_listener, None, service, cycling, shutdown).await
+                    {
+                        tracing::error!("gRPC server error: {e}");
+                    }
                 })
-                .await
-        });
+            }
+            None => tokio::spawn(async move {
+                let mut rx = grpc_shutdown_rx;
+                let result = GrpcServer::builder()
+                    .layer(
+                        ServiceBuilder::new()
+                            .option_layer(grpc_concurrency_limit)
+                            .layer(access_log.clone())
+                            .layer(grpc_rate_limit),
+                    )
+                    .add_routes(grpc_routes)
+                    .serve_with_incoming_shutdown(TcpIncoming::from(grpc_listener), async move {
+                        let _ = rx.recv().await;
+                    })
+                    .await;
+                if let Err(e) = result {
+                    tracing::error!("gRPC server error: {e}");
+                }
+            }),
+        };
 
         // Spawn REST server
         let rest_handle = tokio::spawn(async move {
             let mut rx = rest_shutdown_rx;
-            let listener = tokio::net::TcpListener::bind(rest_addr).await?;
-            axum::serve(listener, rest_app)
-                .with_graceful_shutdown(async move {
-                    let _ = rx.recv().await;
-                })
-                .await
+            axum::serve(
+                rest_listener,
+                rest_app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(async move {
+                let _ = rx.recv().await;
+            })
+            .await
         });
 
         // Spawn health check server
         let health_handle = tokio::spawn(async move {
             let mut rx = health_shutdown_rx;
-            let listener = tokio::net::TcpListener::bind(health_addr).await?;
-            axum::serve(listener, health_app)
+            axum::serve(health_listener, health_app)
                 .with_graceful_shutdown(async move {
                     let _ = rx.recv().await;
                 })
                 .await
         });
 
+        // All listeners are bound and serving
+        ready.store(true, Ordering::SeqCst);
+
         // Wait for shutdown signal
         shutdown_signal().await;
+        ready.store(false, Ordering::SeqCst);
 
-        // Broadcast shutdown to all servers
+        // End open streams, then broadcast shutdown to all servers
+        stream_shutdown.cancel();
         let _ = shutdown_tx.send(());
 
         // Wait for all servers to shut down gracefully
-        let _ = tokio::join!(grpc_handle, rest_handle, health_handle);
+        drain_servers(async { tokio::join!(grpc_handle, rest_handle, health_handle) }).await;
+    }
+
+    // Write changes still waiting for the debounce before the process exits
+    if let Some(file_repo) = &file_repo {
+        file_repo.flush();
     }
 
     Ok(())
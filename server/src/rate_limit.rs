@@ -0,0 +1,164 @@
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use clock::Clock;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Token bucket shared by all REST and gRPC endpoints
+/// Holds up to one second worth of tokens and refills continuously at the configured rate
+pub struct TokenBucket {
+    rate_per_sec: f64,
+    clock: Arc<dyn Clock>,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    last_refill: Duration,
+}
+
+impl TokenBucket {
+    pub fn new(rate_per_sec: u32, clock: Arc<dyn Clock>) -> Self {
+        let rate_per_sec = f64::from(rate_per_sec);
+        let last_refill = clock.monotonic();
+        Self {
+            rate_per_sec,
+            clock,
+            state: Mutex::new(BucketState {
+                tokens: rate_per_sec,
+                last_refill,
+            }),
+        }
+    }
+
+    /// Take one token, returning false when the bucket is empty
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self
+            .state
+            .lock()
+            .expect("Failed to acquire lock on rate limiter");
+
+        let now = self.clock.monotonic();
+        let elapsed = now.saturating_sub(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate_per_sec).min(self.rate_per_sec);
+        state.last_refill = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Middleware to reject REST requests with 429 when the global rate limit is exceeded
+pub async fn limit_rest_requests(
+    State(limiter): State<Arc<TokenBucket>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limiter.try_acquire() {
+        return next.run(request).await;
+    }
+
+    let error = video_service::ErrorResponse {
+        error: video_service::ErrorDetail {
+            code: 429,
+            message: "Rate limit exceeded".to_string(),
+            errors: vec![video_service::ErrorItem {
+                domain: "global".to_string(),
+                reason: "rateLimitExceeded".to_string(),
+                message: "Rate limit exceeded".to_string(),
+            }],
+        },
+    };
+    (StatusCode::TOO_MANY_REQUESTS, Json(error)).into_response()
+}
+
+// Interceptor to reject gRPC calls with RESOURCE_EXHAUSTED when the global rate limit is exceeded
+pub fn grpc_interceptor(
+    limiter: Option<Arc<TokenBucket>>,
+) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> + Clone {
+    move |request| match &limiter {
+        Some(limiter) if !limiter.try_acquire() => {
+            Err(tonic::Status::resource_exhausted("Rate limit exceeded"))
+        }
+        _ => Ok(request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use clock::MockClock;
+
+    fn mock_clock() -> Arc<MockClock> {
+        Arc::new(MockClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+                .single()
+                .expect("Valid datetime"),
+        ))
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_up_to_rate() {
+        let clock = mock_clock();
+        let bucket = TokenBucket::new(3, clock);
+
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let clock = mock_clock();
+        let bucket = TokenBucket::new(2, clock.clone());
+
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+
+        clock.advance(Duration::from_millis(500));
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_bucket_does_not_exceed_capacity_after_idle() {
+        let clock = mock_clock();
+        let bucket = TokenBucket::new(2, clock.clone());
+
+        clock.advance(Duration::from_secs(60));
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_grpc_interceptor_rejects_when_exhausted() {
+        let bucket = Arc::new(TokenBucket::new(1, mock_clock()));
+        let mut interceptor = grpc_interceptor(Some(bucket));
+
+        assert!(interceptor(tonic::Request::new(())).is_ok());
+        let status = interceptor(tonic::Request::new(())).expect_err("Should be limited");
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+
+    #[test]
+    fn test_grpc_interceptor_without_limit_allows_all() {
+        let mut interceptor = grpc_interceptor(None);
+
+        for _ in 0..100 {
+            assert!(interceptor(tonic::Request::new(())).is_ok());
+        }
+    }
+}